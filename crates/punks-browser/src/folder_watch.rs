@@ -0,0 +1,73 @@
+//! Notices when the folder being browsed changes on disk (a sample exported
+//! into it from a DAW, a file renamed or deleted elsewhere) so the listing
+//! can be refreshed without navigating away and back. A background thread
+//! polls the folder's modification time, which adding, removing or renaming
+//! an entry bumps; like the watch folders, polling rather than filesystem
+//! events keeps network shares working.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::listing_cache;
+
+/// Time between checks.
+const INTERVAL: Duration = Duration::from_millis(750);
+
+/// Watches one folder; stopped when dropped.
+pub(crate) struct FolderWatcher {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    rx: Receiver<()>,
+}
+
+impl FolderWatcher {
+    pub(crate) fn start(path: &Path) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let thread_stop = Arc::clone(&stop);
+        let dir = path.to_path_buf();
+        // Read here rather than on the thread, so a change made straight
+        // after starting isn't taken for the starting point.
+        let mut last = listing_cache::modified(&dir);
+        let spawned = std::thread::Builder::new()
+            .name("punks-folder-watch".into())
+            .spawn(move || {
+                while !thread_stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(INTERVAL);
+                    let now = listing_cache::modified(&dir);
+                    if now != last {
+                        last = now;
+                        if tx.send(()).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("folder watch: {e}");
+        }
+        FolderWatcher {
+            path: path.to_path_buf(),
+            stop,
+            rx,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the folder changed since the last call.
+    pub(crate) fn changed(&self) -> bool {
+        self.rx.try_iter().count() > 0
+    }
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
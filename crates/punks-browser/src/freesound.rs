@@ -0,0 +1,393 @@
+//! Freesound.org as a sample source: text search, preview streaming through
+//! the playback engine, and downloads into a library folder with a license
+//! sidecar next to each file.
+//!
+//! All HTTP runs on short-lived worker threads; results come back over a
+//! channel drained by `SampleBrowser::poll`, like local search.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use serde::{Deserialize, Serialize};
+
+const API: &str = "https://freesound.org/apiv2";
+const FIELDS: &str = "id,name,username,license,duration,type,url,previews";
+const PAGE_SIZE: &str = "50";
+
+/// One search hit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sound {
+    pub id: u64,
+    pub name: String,
+    pub username: String,
+    /// License deed URL, e.g. a Creative Commons page.
+    pub license: String,
+    /// Seconds.
+    pub duration: f64,
+    /// Original file type (`wav`, `aiff`, `mp3`, ...).
+    #[serde(rename = "type")]
+    pub file_type: String,
+    /// The sound's page on freesound.org.
+    pub url: String,
+    previews: Previews,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Previews {
+    #[serde(rename = "preview-hq-mp3")]
+    hq_mp3: String,
+}
+
+#[derive(Deserialize)]
+struct SearchPage {
+    results: Vec<Sound>,
+}
+
+/// Completed background work, sent back to the UI thread.
+enum Event {
+    Results(Result<Vec<Sound>, String>),
+    Preview(u64, Result<PathBuf, String>),
+    Downloaded(Result<PathBuf, String>),
+}
+
+/// What [`FreesoundClient::poll`] wants the browser to act on.
+#[derive(Default)]
+pub(crate) struct PollOutcome {
+    /// A preview finished downloading and is still the one last requested.
+    pub play: Option<PathBuf>,
+    pub downloaded: Vec<PathBuf>,
+}
+
+pub struct FreesoundClient {
+    tx: mpsc::Sender<Event>,
+    rx: mpsc::Receiver<Event>,
+    results: Vec<Sound>,
+    searching: bool,
+    /// Only the most recently requested preview plays; earlier ones that
+    /// finish late are dropped.
+    pending_preview: Option<u64>,
+    downloads_in_flight: usize,
+    status: Option<String>,
+}
+
+impl Default for FreesoundClient {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        FreesoundClient {
+            tx,
+            rx,
+            results: Vec::new(),
+            searching: false,
+            pending_preview: None,
+            downloads_in_flight: 0,
+            status: None,
+        }
+    }
+}
+
+impl FreesoundClient {
+    pub fn search(&mut self, api_key: &str, query: &str) {
+        let (tx, key, query) = (self.tx.clone(), api_key.to_string(), query.to_string());
+        self.searching = true;
+        self.status = None;
+        std::thread::spawn(move || {
+            let _ = tx.send(Event::Results(search(&key, &query)));
+        });
+    }
+
+    pub fn results(&self) -> &[Sound] {
+        &self.results
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Fetch result `index`'s HQ preview; it plays once it arrives.
+    pub fn preview(&mut self, index: usize) {
+        let Some(sound) = self.results.get(index).cloned() else {
+            return;
+        };
+        self.pending_preview = Some(sound.id);
+        let tx = self.tx.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(Event::Preview(sound.id, fetch_preview(&sound)));
+        });
+    }
+
+    /// Save result `index` into `dir`, numbered rather than replacing a file
+    /// of the same name. The original file needs an OAuth2 token; without one
+    /// the HQ preview is saved instead.
+    pub fn download(&mut self, index: usize, oauth_token: Option<&str>, dir: &Path) {
+        let Some(sound) = self.results.get(index).cloned() else {
+            return;
+        };
+        let (tx, token, dir) = (
+            self.tx.clone(),
+            oauth_token.map(str::to_string),
+            dir.to_path_buf(),
+        );
+        self.downloads_in_flight += 1;
+        std::thread::spawn(move || {
+            let _ = tx.send(Event::Downloaded(download(&sound, token.as_deref(), &dir)));
+        });
+    }
+
+    pub fn downloads_in_flight(&self) -> usize {
+        self.downloads_in_flight
+    }
+
+    /// Outcome of the last search or download, for display.
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub(crate) fn poll(&mut self) -> PollOutcome {
+        let mut out = PollOutcome::default();
+        while let Ok(event) = self.rx.try_recv() {
+            match event {
+                Event::Results(Ok(results)) => {
+                    self.searching = false;
+                    self.status = Some(format!("{} results", results.len()));
+                    self.results = results;
+                }
+                Event::Results(Err(e)) => {
+                    self.searching = false;
+                    self.status = Some(format!("search failed: {e}"));
+                }
+                Event::Preview(id, Ok(path)) if self.pending_preview == Some(id) => {
+                    self.pending_preview = None;
+                    out.play = Some(path);
+                }
+                Event::Preview(_, Ok(_)) => {}
+                Event::Preview(_, Err(e)) => {
+                    tracing::warn!("freesound preview: {e}");
+                    self.status = Some(format!("preview failed: {e}"));
+                }
+                Event::Downloaded(result) => {
+                    self.downloads_in_flight -= 1;
+                    match result {
+                        Ok(path) => {
+                            self.status = Some(format!("saved {}", path.display()));
+                            out.downloaded.push(path);
+                        }
+                        Err(e) => {
+                            tracing::warn!("freesound download: {e}");
+                            self.status = Some(format!("download failed: {e}"));
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn search(api_key: &str, query: &str) -> Result<Vec<Sound>, String> {
+    let body = ureq::get(&format!("{API}/search/text/"))
+        .query("query", query)
+        .query("fields", FIELDS)
+        .query("page_size", PAGE_SIZE)
+        .query("token", api_key)
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    parse_search(&body)
+}
+
+fn parse_search(body: &str) -> Result<Vec<Sound>, String> {
+    serde_json::from_str::<SearchPage>(body)
+        .map(|page| page.results)
+        .map_err(|e| format!("unexpected response: {e}"))
+}
+
+/// Previews are cached by sound id in the temp dir, so re-auditioning a
+/// result doesn't hit the network again.
+fn fetch_preview(sound: &Sound) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join("punks2-freesound");
+    let path = dir.join(format!("{}.mp3", sound.id));
+    if !path.exists() {
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        fetch_to(ureq::get(&sound.previews.hq_mp3), &path)?;
+    }
+    Ok(path)
+}
+
+fn download(sound: &Sound, oauth_token: Option<&str>, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = claim(&dir.join(file_name(sound, oauth_token.is_some())))?;
+    let fetched = match oauth_token {
+        Some(token) => fetch_to(
+            ureq::get(&format!("{API}/sounds/{}/download/", sound.id))
+                .set("Authorization", &format!("Bearer {token}")),
+            &path,
+        ),
+        None => fetch_to(ureq::get(&sound.previews.hq_mp3), &path),
+    };
+    if let Err(e) = fetched {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+    write_license(sound, &path)?;
+    Ok(path)
+}
+
+/// Create an empty placeholder at `path`, or at the first `stem (2).ext`,
+/// `stem (3).ext`, … where neither the file nor its license sidecar exists,
+/// so a download never replaces a sample (or its attribution) already in the
+/// library, nor one another download is saving under the same name.
+fn claim(path: &Path) -> Result<PathBuf, String> {
+    for candidate in punks_core::numbered_paths(path) {
+        if license_path(&candidate).exists() {
+            continue;
+        }
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Err(format!("no free name for {}", path.display()))
+}
+
+/// `<name>.<ext>` for the saved file: the original's type, or `mp3` for a
+/// preview. The name is the uploader's title, so it's made path-safe; the
+/// type comes from the server too, and is dropped unless it's a plain word.
+fn file_name(sound: &Sound, original: bool) -> String {
+    let safe: String = sound
+        .name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let ext = if original {
+        let ext = sound.file_type.to_ascii_lowercase();
+        let plain = (1..=8).contains(&ext.len()) && ext.bytes().all(|b| b.is_ascii_alphanumeric());
+        plain.then_some(ext)
+    } else {
+        Some("mp3".to_string())
+    };
+    let stem = Path::new(&safe)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| sound.id.to_string());
+    match ext {
+        Some(ext) => format!("{stem}.{ext}"),
+        None => stem,
+    }
+}
+
+/// Stream a response body to `dest` via a `.part` temp file, so a dropped
+/// connection never leaves a truncated sample in the library.
+fn fetch_to(request: ureq::Request, dest: &Path) -> Result<(), String> {
+    let response = request.call().map_err(|e| e.to_string())?;
+    let mut tmp_name = dest.as_os_str().to_os_string();
+    tmp_name.push(".part");
+    let tmp = PathBuf::from(tmp_name);
+
+    let copy = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&tmp)?;
+        std::io::copy(&mut response.into_reader(), &mut file)?;
+        Ok(())
+    };
+    if let Err(e) = copy() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.to_string());
+    }
+    std::fs::rename(&tmp, dest).map_err(|e| e.to_string())
+}
+
+/// Attribution travels with the file: `<file>.license.json` holds the
+/// author, license and source page that CC-BY and friends require.
+fn write_license(sound: &Sound, file: &Path) -> Result<(), String> {
+    let sidecar = serde_json::json!({
+        "source": "freesound.org",
+        "id": sound.id,
+        "name": sound.name,
+        "author": sound.username,
+        "license": sound.license,
+        "url": sound.url,
+    });
+    let text = serde_json::to_string_pretty(&sidecar).map_err(|e| e.to_string())?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(license_path(file))
+        .and_then(|mut f| std::io::Write::write_all(&mut f, text.as_bytes()))
+        .map_err(|e| e.to_string())
+}
+
+fn license_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(".license.json");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"{
+        "count": 1, "next": null, "previous": null,
+        "results": [{
+            "id": 42, "name": "Kick: deep/808.wav", "username": "someone",
+            "license": "http://creativecommons.org/licenses/by/4.0/",
+            "duration": 0.75, "type": "wav",
+            "url": "https://freesound.org/people/someone/sounds/42/",
+            "previews": {
+                "preview-hq-mp3": "https://cdn.freesound.org/previews/0/42-hq.mp3",
+                "preview-lq-mp3": "https://cdn.freesound.org/previews/0/42-lq.mp3"
+            }
+        }]
+    }"#;
+
+    #[test]
+    fn parses_search_page() {
+        let results = parse_search(PAGE).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, 42);
+        assert_eq!(results[0].file_type, "wav");
+        assert!(results[0].previews.hq_mp3.ends_with("42-hq.mp3"));
+        assert!(parse_search("{\"detail\": \"Invalid token\"}").is_err());
+    }
+
+    #[test]
+    fn file_name_is_path_safe_and_typed() {
+        let sound = &parse_search(PAGE).unwrap()[0];
+        assert_eq!(file_name(sound, true), "Kick_ deep_808.wav");
+        assert_eq!(file_name(sound, false), "Kick_ deep_808.mp3");
+
+        // The type can't reach outside the folder or hide the file.
+        let mut odd = sound.clone();
+        for bad in ["../../x", "wav/..", "", "w a v", "wav\0", "exe.wav"] {
+            odd.file_type = bad.into();
+            assert_eq!(file_name(&odd, true), "Kick_ deep_808", "{bad:?}");
+        }
+        odd.file_type = "AIFF".into();
+        assert_eq!(file_name(&odd, true), "Kick_ deep_808.aiff");
+    }
+
+    #[test]
+    fn claims_a_name_nothing_else_is_using() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        std::fs::write(&kick, b"mine").unwrap();
+        // A sidecar left without its file still holds the name.
+        std::fs::write(dir.path().join("kick (2).wav.license.json"), b"{}").unwrap();
+
+        let claimed = claim(&kick).unwrap();
+        assert_eq!(claimed, dir.path().join("kick (3).wav"));
+        assert!(claimed.exists());
+        assert_eq!(claim(&kick).unwrap(), dir.path().join("kick (4).wav"));
+        assert_eq!(std::fs::read(&kick).unwrap(), b"mine");
+    }
+}
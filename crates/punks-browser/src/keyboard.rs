@@ -0,0 +1,98 @@
+//! The computer keyboard as a piano for the selected sample, laid out like
+//! Ableton's: the home row plays the white keys from C, the row above it
+//! the black keys, and Z / X move down or up an octave. C is the sample at
+//! its own pitch; other notes are varispeed (see
+//! [`SampleBrowser::play_note`](crate::SampleBrowser::play_note)).
+
+/// Keys that play notes, by the letter on them, and how many semitones
+/// above C each one is.
+pub const NOTE_KEYS: &[(char, i32)] = &[
+    ('A', 0),
+    ('W', 1),
+    ('S', 2),
+    ('E', 3),
+    ('D', 4),
+    ('F', 5),
+    ('T', 6),
+    ('G', 7),
+    ('Y', 8),
+    ('H', 9),
+    ('U', 10),
+    ('J', 11),
+    ('K', 12),
+    ('O', 13),
+    ('L', 14),
+    ('P', 15),
+];
+
+pub const OCTAVE_DOWN: char = 'Z';
+pub const OCTAVE_UP: char = 'X';
+
+const NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// The octave the keys play in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoteKeyboard {
+    octave: i32,
+}
+
+impl NoteKeyboard {
+    /// Octaves the keys shift down or up by at most; the top key of the
+    /// highest one is still within [`MAX_PITCH`](punks_playback::MAX_PITCH).
+    pub const OCTAVES: i32 = 2;
+
+    /// Octaves above (or below) the sample's own pitch.
+    pub fn octave(&self) -> i32 {
+        self.octave
+    }
+
+    /// `key` pressed: the semitones to play for a note key, `None` for the
+    /// octave keys (which move the octave) and anything else.
+    pub fn press(&mut self, key: char) -> Option<i32> {
+        match key.to_ascii_uppercase() {
+            OCTAVE_DOWN => self.octave = (self.octave - 1).max(-Self::OCTAVES),
+            OCTAVE_UP => self.octave = (self.octave + 1).min(Self::OCTAVES - 1),
+            key => {
+                let &(_, note) = NOTE_KEYS.iter().find(|(k, _)| *k == key)?;
+                return Some(self.octave * 12 + note);
+            }
+        }
+        None
+    }
+}
+
+/// `semitones` from the sample's pitch as a note name, taking the sample as
+/// C: `G`, `D#+1` an octave up, `A-2` two down.
+pub fn note_name(semitones: i32) -> String {
+    let name = NAMES[semitones.rem_euclid(12) as usize];
+    match semitones.div_euclid(12) {
+        0 => name.to_string(),
+        octave => format!("{name}{octave:+}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_play_semitones_in_the_current_octave() {
+        let mut keys = NoteKeyboard::default();
+        assert_eq!(keys.press('a'), Some(0));
+        assert_eq!(keys.press('G'), Some(7));
+        assert_eq!(keys.press('Q'), None);
+        assert_eq!(keys.press('X'), None);
+        assert_eq!(keys.press('X'), None);
+        assert_eq!(keys.octave(), 1);
+        assert_eq!(keys.press('P'), Some(27));
+        for _ in 0..4 {
+            keys.press('Z');
+        }
+        assert_eq!(keys.press('W'), Some(-23));
+        assert_eq!(note_name(-23), "C#-2");
+        assert_eq!(note_name(15), "D#+1");
+        assert_eq!(note_name(7), "G");
+    }
+}
@@ -1,85 +1,849 @@
-use std::fmt;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime};
 
-pub use punks_core::config::PunksConfig;
-pub use punks_core::{DirListing, FileEntry, ScanError, SUPPORTED_EXTENSIONS};
-pub use punks_playback::{AudioMetadata, PlaybackError, PlaybackStatus, TrackInfo, WaveformPeaks};
+mod folder_watch;
+#[cfg(feature = "freesound")]
+pub mod freesound;
+pub mod keyboard;
+mod listing_cache;
+pub mod midi;
+mod observe;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod watch;
 
-use punks_playback::PlaybackEngine;
+pub use punks_core::collections::Collection;
+use punks_core::collections::Collections;
+pub use punks_core::config::{LoopSync, MidiTrigger, PadSource, PunksConfig, WatchFolder};
+pub use punks_core::dedupe::DuplicateGroup;
+pub use punks_core::favorites::Favorites;
+pub use punks_core::history::{Audition, AuditionHistory};
+pub use punks_core::index::{SampleIndex, SampleRecord};
+pub use punks_core::integrity::VerifyReport;
+pub use punks_core::library::{Library, RefreshStats};
+pub use punks_core::size::FolderSize;
+pub use punks_core::sort::SortMode;
+pub use punks_core::trash::{Deleted, TrashError};
+pub use punks_core::{
+    DirListing, ErrorKind, ExtensionRegistry, FileEntry, ListOptions, ScanError, ScanProgress,
+    MODULE_EXTENSIONS, SUPPORTED_EXTENSIONS,
+};
+pub use punks_playback::analysis::analyzer::{
+    AnalysisInput, AnalysisResults, AnalysisValue, Analyzer, AnalyzerRegistry,
+};
+pub use punks_playback::analysis::classify::Category;
+pub use punks_playback::analysis::tempo::LoopTempo;
+pub use punks_playback::export::{BitDepth, ExportSpec, ExportedFile};
+pub use punks_playback::process::{Normalize, OutputMode, ProcessOptions, ProcessedFile};
+pub use punks_playback::{
+    AudioMetadata, FileInfo, PeakLevels, PlaybackError, PlaybackStatus, Region, TempoSync,
+    TrackInfo, VoiceId, VoiceInfo, WaveformPeaks, WaveformView, MAX_BPM, MAX_LAYERS, MAX_VOICES,
+    MIN_BPM,
+};
 
-#[derive(Debug)]
+use folder_watch::FolderWatcher;
+use listing_cache::ListingCache;
+use midi::{MidiAction, MidiInput, MidiMap};
+pub use observe::{BrowserEvent, ObserverId};
+use observe::{Observers, Snapshot};
+use punks_core::network;
+use punks_core::{ScanEvent, ScanOptions, TrackRegion};
+use punks_playback::analysis::pool::{Priority, WorkerPool};
+pub use punks_playback::{NullPlayback, Playback, PlaybackEngine, PlaybackEngineBuilder};
+use watch::Watcher;
+
+/// Peaks per list-row overview; rows are far narrower than the main waveform.
+const OVERVIEW_BUCKETS: usize = 128;
+
+/// Overviews waiting to start. Rows scrolled away are dropped from the queue
+/// anyway, so this only bites on very tall windows.
+const OVERVIEW_QUEUE: usize = 256;
+
+/// Header probes waiting to start; see [`OVERVIEW_QUEUE`].
+const METADATA_QUEUE: usize = 512;
+
+/// How long the selection has to rest before auto-preview plays it while
+/// it's moving quickly; a single step plays straight away.
+const AUTO_PREVIEW_DELAY: Duration = Duration::from_millis(120);
+
+/// Directory listings kept for instant back/up/breadcrumb navigation.
+const LISTING_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(64).unwrap();
+
+/// Folders each tab remembers to go back to.
+const MAX_VISITS: usize = 100;
+
+/// Samples [`SampleBrowser::recent`] lists.
+pub const RECENT_SAMPLES: usize = 50;
+
+#[derive(Debug, thiserror::Error)]
 pub enum BrowserError {
-    Scan(ScanError),
-    Playback(PlaybackError),
+    #[error("scan error: {0}")]
+    Scan(#[from] ScanError),
+    #[error("playback error: {0}")]
+    Playback(#[from] PlaybackError),
+    #[error("MIDI error on {port}: {message}")]
+    Midi { port: String, message: String },
+    #[error("remote API error on {bind}: {message}")]
+    Remote { bind: String, message: String },
+    #[error("delete failed: {0}")]
+    Trash(#[from] TrashError),
+    #[error("no file selected")]
     NoSelection,
 }
 
-impl fmt::Display for BrowserError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl BrowserError {
+    /// What went wrong, whichever layer it came from.
+    pub fn kind(&self) -> ErrorKind {
         match self {
-            BrowserError::Scan(e) => write!(f, "scan error: {e}"),
-            BrowserError::Playback(e) => write!(f, "playback error: {e}"),
-            BrowserError::NoSelection => write!(f, "no file selected"),
+            BrowserError::Scan(e) => e.kind(),
+            BrowserError::Playback(e) => e.kind(),
+            BrowserError::Midi { .. } => ErrorKind::Midi,
+            BrowserError::Remote { .. } => ErrorKind::Remote,
+            BrowserError::Trash(e) => e.kind(),
+            BrowserError::NoSelection => ErrorKind::NoSelection,
         }
     }
 }
 
-impl std::error::Error for BrowserError {}
-
-impl From<ScanError> for BrowserError {
-    fn from(e: ScanError) -> Self {
-        BrowserError::Scan(e)
-    }
-}
-
-impl From<PlaybackError> for BrowserError {
-    fn from(e: PlaybackError) -> Self {
-        BrowserError::Playback(e)
-    }
-}
-
 /// One tab's navigation context: its own directory history, selection, and
 /// search. Playback is global and lives on `SampleBrowser`, not here.
 #[derive(Default)]
 struct TabState {
     history: Vec<PathBuf>,
-    listing: Option<DirListing>,
+    /// Folders left, latest last, for [`SampleBrowser::navigate_back`].
+    back: Vec<Visit>,
+    /// Folders gone back from, for [`SampleBrowser::navigate_forward`].
+    forward: Vec<Visit>,
+    /// Shared with the listing cache, so revisiting a folder is just a clone.
+    listing: Option<Arc<DirListing>>,
+    /// Narrows the listing's files by name; empty shows them all.
+    filter: String,
+    /// The listing's entries that pass `filter`, in the browser's order;
+    /// `None` when that's the listing as it is.
+    filtered: Option<Arc<Vec<FileEntry>>>,
     selected: Option<usize>,
     /// Committed search text, so a tab restores its query when reactivated.
     search_query: String,
-    search_results: Option<Vec<FileEntry>>,
-    search_rx: Option<mpsc::Receiver<Vec<FileEntry>>>,
+    /// What's matched so far, while `search_job` is still walking.
+    search_results: Option<Arc<Vec<FileEntry>>>,
+    search_job: Option<SearchJob>,
     search_selected: Option<usize>,
 }
 
+/// A folder as a tab left it: the trail of folders down to it, and what was
+/// selected there.
+#[derive(Clone)]
+struct Visit {
+    history: Vec<PathBuf>,
+    selected: Option<(PathBuf, Option<TrackRegion>)>,
+}
+
+/// A search walking a folder outside the library, its matches added to the
+/// tab's results as the walk finds files.
+struct SearchJob {
+    events: mpsc::Receiver<ScanEvent>,
+    root: PathBuf,
+    /// The query's words to match, without its `#tag` and `*3` filters.
+    text: String,
+    /// What the filters keep; see [`SampleBrowser::search_filter`].
+    kept: Option<HashMap<PathBuf, u8>>,
+    progress: ScanProgress,
+}
+
+impl SearchJob {
+    /// `results` with the matches among `found` added, all in rank order.
+    fn merge(&self, results: &[FileEntry], found: Vec<FileEntry>) -> Vec<FileEntry> {
+        let mut matched = punks_core::search::rank(found, &self.root, &self.text);
+        apply_search_filter(&mut matched, self.kept.as_ref());
+        if matched.is_empty() {
+            return results.to_vec();
+        }
+        matched.extend_from_slice(results);
+        let mut merged = punks_core::search::rank(matched, &self.root, &self.text);
+        apply_search_filter(&mut merged, self.kept.as_ref());
+        merged
+    }
+}
+
+impl TabState {
+    /// Filter and sort the listing into what's shown, taking lengths for
+    /// [`SortMode::Duration`] from `duration`.
+    fn arrange(
+        &mut self,
+        (mode, descending): (SortMode, bool),
+        duration: impl Fn(&FileEntry) -> Option<Duration>,
+    ) {
+        let needle = self.filter.to_lowercase();
+        let as_listed = mode == SortMode::Name && !descending;
+        self.filtered = match &self.listing {
+            Some(listing) if !needle.is_empty() || !as_listed => {
+                let mut entries: Vec<FileEntry> = listing
+                    .entries
+                    .iter()
+                    .filter(|e| e.is_directory || e.name.to_lowercase().contains(&needle))
+                    .cloned()
+                    .collect();
+                if !as_listed {
+                    punks_core::sort::sort_entries(&mut entries, mode, descending, duration);
+                }
+                Some(Arc::new(entries))
+            }
+            _ => None,
+        };
+    }
+
+    /// The selected entry, to find again with [`reselect`](Self::reselect)
+    /// once the entries have moved.
+    fn selected_entry(&self) -> Option<(PathBuf, Option<TrackRegion>)> {
+        self.selected
+            .and_then(|i| self.visible().get(i))
+            .map(|e| (e.path.clone(), e.region))
+    }
+
+    /// Remember where the tab is before it moves on, to go back to; moving
+    /// on drops the way forward.
+    fn leave(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let visit = self.visit();
+        if self.back.len() == MAX_VISITS {
+            self.back.remove(0);
+        }
+        self.back.push(visit);
+        self.forward.clear();
+    }
+
+    fn visit(&self) -> Visit {
+        Visit {
+            history: self.history.clone(),
+            selected: self.selected_entry(),
+        }
+    }
+
+    fn reselect(&mut self, entry: Option<(PathBuf, Option<TrackRegion>)>) {
+        self.selected = entry.and_then(|(path, region)| {
+            self.visible()
+                .iter()
+                .position(|e| e.path == path && e.region == region)
+        });
+    }
+
+    /// Add the files the search's walk found since the last call to its
+    /// results, keeping the selection on the same file.
+    fn poll_search(&mut self) {
+        let Some(job) = &mut self.search_job else {
+            return;
+        };
+        let mut found = Vec::new();
+        let mut done = false;
+        loop {
+            match job.events.try_recv() {
+                Ok(ScanEvent::Found(files, progress)) => {
+                    found.extend(files);
+                    job.progress = progress;
+                }
+                Ok(ScanEvent::Done(result)) => {
+                    if let Err(e) = result {
+                        tracing::warn!("search in {}: {e}", job.root.display());
+                    }
+                    done = true;
+                    break;
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    done = true;
+                    break;
+                }
+            }
+        }
+        if !found.is_empty() {
+            let results = self
+                .search_results
+                .as_deref()
+                .map_or(&[][..], Vec::as_slice);
+            let selected = self
+                .search_selected
+                .and_then(|i| results.get(i))
+                .map(|e| (e.path.clone(), e.region));
+            let merged = job.merge(results, found);
+            self.search_selected = selected.and_then(|(path, region)| {
+                merged
+                    .iter()
+                    .position(|e| e.path == path && e.region == region)
+            });
+            self.search_results = Some(Arc::new(merged));
+        }
+        if done {
+            self.search_job = None;
+        }
+    }
+
+    /// The entries shown: the listing's, filtered.
+    fn visible(&self) -> &[FileEntry] {
+        match (&self.filtered, &self.listing) {
+            (Some(filtered), _) => filtered,
+            (None, Some(listing)) => &listing.entries,
+            (None, None) => &[],
+        }
+    }
+}
+
+/// Outcome of one file in a batch process run.
+pub struct BatchResult {
+    pub source: PathBuf,
+    pub result: Result<ProcessedFile, PlaybackError>,
+}
+
+/// A batch process run on its background thread. Results arrive one file at a
+/// time so the UI can show progress.
+struct BatchJob {
+    total: usize,
+    results: Vec<BatchResult>,
+    rx: Option<mpsc::Receiver<BatchResult>>,
+}
+
+/// A file and what it was classified as; `None` if it's silent.
+type Guess = (PathBuf, Result<Option<Category>, PlaybackError>);
+
+/// An auto-tag run on its background thread: each file's guess arrives as
+/// it's classified.
+struct AutoTagJob {
+    total: usize,
+    done: usize,
+    rx: Option<mpsc::Receiver<Guess>>,
+}
+
+/// What a library integrity run found.
+pub enum IntegrityOutcome {
+    /// No manifest existed (or a rebuild was asked for), so one was written
+    /// covering this many files.
+    ManifestWritten(usize),
+    Verified(VerifyReport),
+    Failed(String),
+}
+
+enum IntegrityMsg {
+    Hashing,
+    Done(IntegrityOutcome),
+}
+
+/// A manifest build or verify on its background thread.
+struct IntegrityJob {
+    root: PathBuf,
+    files_hashed: usize,
+    outcome: Option<IntegrityOutcome>,
+    rx: Option<mpsc::Receiver<IntegrityMsg>>,
+}
+
+enum DuplicateMsg {
+    Hashing,
+    Done(Result<Vec<DuplicateGroup>, String>),
+}
+
+/// A duplicate scan on its background thread. Stopped when dropped.
+struct DuplicateJob {
+    files_hashed: usize,
+    groups: Option<Result<Vec<DuplicateGroup>, String>>,
+    stop: Arc<AtomicBool>,
+    rx: Option<mpsc::Receiver<DuplicateMsg>>,
+}
+
+impl Drop for DuplicateJob {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// What a script run sends back from its thread.
+#[cfg(feature = "scripting")]
+enum ScriptMsg {
+    Print(String),
+    /// A file the script wrote, moved or trashed.
+    Changed(PathBuf),
+    /// A tag or rating the script changed in its copy of the sample index.
+    Edit(punks_script::IndexEdit),
+    Done(Result<(), String>),
+}
+
+/// A rhai script running on its background thread. Stopped when dropped.
+#[cfg(feature = "scripting")]
+struct ScriptJob {
+    file: PathBuf,
+    output: Vec<String>,
+    outcome: Option<Result<(), String>>,
+    stop: Arc<AtomicBool>,
+    rx: Option<mpsc::Receiver<ScriptMsg>>,
+}
+
+#[cfg(feature = "scripting")]
+impl Drop for ScriptJob {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A recursive size walk on its background thread. Each message is the
+/// running total; the channel closing means it's final.
+struct SizeJob {
+    size: FolderSize,
+    rx: Option<mpsc::Receiver<FolderSize>>,
+}
+
+/// A library index refresh on its background thread, which sends the
+/// refreshed index once done. Stopped when dropped.
+struct IndexJob {
+    stop: Arc<AtomicBool>,
+    rx: mpsc::Receiver<Library>,
+}
+
+impl Drop for IndexJob {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
 pub struct SampleBrowser {
     tabs: Vec<TabState>,
     active_tab: usize,
-    playback: PlaybackEngine,
+    playback: Box<dyn Playback>,
     last_error: Option<String>,
+    batch: Option<BatchJob>,
+    auto_tag: Option<AutoTagJob>,
+    integrity: Option<IntegrityJob>,
+    duplicates: Option<DuplicateJob>,
+    #[cfg(feature = "scripting")]
+    script: Option<ScriptJob>,
+    folder_sizes: HashMap<PathBuf, SizeJob>,
+    overviews: WorkerPool<PathBuf, Result<WaveformPeaks, PlaybackError>>,
+    /// Whether overviews use `.reapeaks` files; shared with the pool's workers.
+    reapeaks: Arc<AtomicBool>,
+    /// Finished overviews; `None` for files that failed to decode, so they
+    /// aren't retried every frame.
+    overview_cache: HashMap<PathBuf, Option<WaveformPeaks>>,
+    metadata: WorkerPool<PathBuf, Result<FileInfo, PlaybackError>>,
+    /// Probed file info; `None` for files that couldn't be probed.
+    metadata_cache: HashMap<PathBuf, Option<FileInfo>>,
+    list_options: ListOptions,
+    /// How every tab's listing is ordered; see [`SampleBrowser::set_sort_mode`].
+    sort_mode: SortMode,
+    sort_descending: bool,
+    listings: ListingCache,
+    /// Watches the active tab's folder while `auto_refresh` is on.
+    folder_watcher: Option<FolderWatcher>,
+    auto_refresh: bool,
+    /// The folder last re-read because it changed on disk, until taken.
+    folder_changed: Option<PathBuf>,
+    /// Play files as they're selected.
+    auto_preview: bool,
+    /// The selected file auto-preview plays next, and when.
+    pending_preview: Option<(FileEntry, Instant)>,
+    /// When the selection last moved, to tell a single step from scrolling.
+    last_selected: Option<Instant>,
+    /// How long a listing on a network share gets before it's given up on.
+    network_timeout: Duration,
+    analyzers: AnalyzerRegistry,
+    /// Per-sample preview trims, saved to `index_file` when there is one.
+    index: SampleIndex,
+    index_file: Option<PathBuf>,
+    index_dirty: bool,
+    /// Samples heard, appended to `history_file` when there is one.
+    history: AuditionHistory,
+    history_file: Option<PathBuf>,
+    listening: Option<Listening>,
+    /// Starred files and folders, saved to `favorites_file` on each change
+    /// when there is one.
+    favorites: Favorites,
+    favorites_file: Option<PathBuf>,
+    /// Named lists of samples, saved to `collections_file` on each change
+    /// when there is one.
+    collections: Collections,
+    collections_file: Option<PathBuf>,
+    /// The collection being played through, and the sample of it playing.
+    play_through: Option<(String, PathBuf)>,
+    /// Files under the library folders, saved to `library_file` when there
+    /// is one; searches there are answered from it.
+    library: Library,
+    library_file: Option<PathBuf>,
+    library_folders: Vec<PathBuf>,
+    indexer: Option<IndexJob>,
+    watch_folders: Vec<WatchFolder>,
+    watcher: Option<Watcher>,
+    /// Samples stacked for a layered preview; shared by all tabs.
+    layers: Vec<Layer>,
+    /// The metronome's tempo, kept while it's off.
+    metronome_bpm: f32,
+    loop_sync: LoopSync,
+    allow_permanent_delete: bool,
+    midi_input: Option<MidiInput>,
+    midi_map: MidiMap,
+    pad_source: PadSource,
+    first_pad_note: u8,
+    /// The samples on the pads, taken when the source was set or last
+    /// refreshed so they don't move while being played.
+    pads: Vec<PathBuf>,
+    observers: Observers,
+    #[cfg(feature = "remote")]
+    remote: Option<remote::RemoteServer>,
+    #[cfg(feature = "freesound")]
+    freesound: freesound::FreesoundClient,
 }
 
-impl SampleBrowser {
-    /// `cfg` is read once by the caller (see `BrowserPanel::prefs`) rather than
-    /// loaded again here, so a single app startup only touches disk once for
-    /// config instead of once per component that needs it.
-    pub fn new(cfg: &PunksConfig) -> Result<Self, BrowserError> {
-        let playback = PlaybackEngine::new()?;
+/// Sets up a [`SampleBrowser`] for embedding: everything starts from a
+/// [`PunksConfig`] (the defaults unless given one), and the application
+/// overrides what it wants fixed.
+///
+/// ```no_run
+/// # use punks_browser::{PlaybackEngine, SampleBrowserBuilder};
+/// let playback = PlaybackEngine::builder().buffer_size(256).build()?;
+/// let browser = SampleBrowserBuilder::new()
+///     .show_hidden(true)
+///     .start_directory("/path/to/samples")
+///     .playback(playback)
+///     .build()?;
+/// # Ok::<(), punks_browser::BrowserError>(())
+/// ```
+#[must_use]
+pub struct SampleBrowserBuilder {
+    config: PunksConfig,
+    extensions: Option<ExtensionRegistry>,
+    show_hidden: bool,
+    playback: Option<Box<dyn Playback>>,
+    analyzers: AnalyzerRegistry,
+    index_file: Option<PathBuf>,
+    history_file: Option<PathBuf>,
+    library_file: Option<PathBuf>,
+    favorites_file: Option<PathBuf>,
+    collections_file: Option<PathBuf>,
+}
+
+/// A sample in the layer stack (see [`SampleBrowser::play_layers`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub path: PathBuf,
+    pub gain_db: f32,
+}
+
+/// The sample playing now, until it stops and goes into the history.
+struct Listening {
+    path: PathBuf,
+    started: SystemTime,
+    since: Instant,
+}
+
+impl Default for SampleBrowserBuilder {
+    fn default() -> Self {
+        Self::from_config(&PunksConfig::default())
+    }
+}
+
+impl SampleBrowserBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from `cfg`: volume, extensions, MIDI, remote API and the rest.
+    pub fn from_config(cfg: &PunksConfig) -> Self {
+        SampleBrowserBuilder {
+            config: cfg.clone(),
+            extensions: None,
+            show_hidden: false,
+            playback: None,
+            analyzers: AnalyzerRegistry::with_builtins(),
+            index_file: None,
+            history_file: None,
+            library_file: None,
+            favorites_file: None,
+            collections_file: None,
+        }
+    }
+
+    /// List these file types instead of the built-ins plus the config's
+    /// custom ones.
+    pub fn extensions(mut self, extensions: ExtensionRegistry) -> Self {
+        self.extensions = Some(extensions);
+        self
+    }
+
+    /// List dot-files and dot-folders, which are skipped by default.
+    pub fn show_hidden(mut self, show: bool) -> Self {
+        self.show_hidden = show;
+        self
+    }
+
+    /// Open this folder on startup instead of the config's last directory.
+    pub fn start_directory(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.last_directory = Some(dir.into());
+        self
+    }
+
+    /// See [`SampleBrowser::set_allow_permanent_delete`].
+    pub fn allow_permanent_delete(mut self, allow: bool) -> Self {
+        self.config.allow_permanent_delete = allow;
+        self
+    }
+
+    /// Play through this instead of an engine on the default output: one
+    /// from [`PlaybackEngineBuilder`], or [`NullPlayback`] where there's no
+    /// audio device. The config's playback settings are still applied to it.
+    pub fn playback(mut self, playback: impl Playback + 'static) -> Self {
+        self.playback = Some(Box::new(playback));
+        self
+    }
+
+    /// Run `analyzer` on every analyzed file alongside the built-ins. One
+    /// with a built-in's name replaces it.
+    pub fn analyzer(mut self, analyzer: impl Analyzer + 'static) -> Self {
+        self.analyzers.register(analyzer);
+        self
+    }
+
+    /// Remember per-sample settings (preview trims) in the index at `file`,
+    /// read now and written by [`SampleBrowser::save_sample_index`]. Without
+    /// one they last only as long as the browser.
+    pub fn sample_index(mut self, file: impl Into<PathBuf>) -> Self {
+        self.index_file = Some(file.into());
+        self
+    }
+
+    /// Log every sample auditioned to the history at `file`, read now and
+    /// appended to as each one stops. Without one the history lasts only as
+    /// long as the browser.
+    pub fn audition_history(mut self, file: impl Into<PathBuf>) -> Self {
+        self.history_file = Some(file.into());
+        self
+    }
+
+    /// Keep the favorites in `file`, read now and written on each change.
+    /// Without one they last only as long as the browser.
+    pub fn favorites(mut self, file: impl Into<PathBuf>) -> Self {
+        self.favorites_file = Some(file.into());
+        self
+    }
+
+    /// Keep the collections in `file`, read now and written on each change.
+    /// Without one they last only as long as the browser.
+    pub fn collections(mut self, file: impl Into<PathBuf>) -> Self {
+        self.collections_file = Some(file.into());
+        self
+    }
+
+    /// Keep the library index (see [`SampleBrowser::set_library_folders`])
+    /// in `file`, read now and written after each refresh, so a launch only
+    /// re-reads folders that changed. Without one every launch indexes the
+    /// library from scratch.
+    pub fn library_index(mut self, file: impl Into<PathBuf>) -> Self {
+        self.library_file = Some(file.into());
+        self
+    }
+
+    pub fn build(self) -> Result<SampleBrowser, BrowserError> {
+        let cfg = &self.config;
+        let playback = match self.playback {
+            Some(playback) => playback,
+            None => Box::new(open_engine(cfg.output_device.as_deref())?),
+        };
+        let extensions = self
+            .extensions
+            .unwrap_or_else(|| ExtensionRegistry::with_custom(&cfg.extensions));
+        let reapeaks = Arc::new(AtomicBool::new(cfg.reapeaks));
+        let index = match self.index_file.as_deref().map(SampleIndex::load) {
+            Some(Ok(index)) => index,
+            Some(Err(e)) => {
+                tracing::warn!("sample index: {e}");
+                SampleIndex::default()
+            }
+            None => SampleIndex::default(),
+        };
+        let history = match self.history_file.as_deref().map(AuditionHistory::load) {
+            Some(Ok(history)) => history,
+            Some(Err(e)) => {
+                tracing::warn!("audition history: {e}");
+                AuditionHistory::default()
+            }
+            None => AuditionHistory::default(),
+        };
+        let favorites = match self.favorites_file.as_deref().map(Favorites::load) {
+            Some(Ok(favorites)) => favorites,
+            Some(Err(e)) => {
+                tracing::warn!("favorites: {e}");
+                Favorites::default()
+            }
+            None => Favorites::default(),
+        };
+        let collections = match self.collections_file.as_deref().map(Collections::load) {
+            Some(Ok(collections)) => collections,
+            Some(Err(e)) => {
+                tracing::warn!("collections: {e}");
+                Collections::default()
+            }
+            None => Collections::default(),
+        };
+        let library = match self.library_file.as_deref().map(Library::load) {
+            Some(Ok(library)) => library,
+            Some(Err(e)) => {
+                tracing::warn!("library index: {e}");
+                Library::default()
+            }
+            None => Library::default(),
+        };
         let mut browser = SampleBrowser {
             tabs: vec![TabState::default()],
             active_tab: 0,
             playback,
             last_error: None,
+            batch: None,
+            auto_tag: None,
+            integrity: None,
+            duplicates: None,
+            #[cfg(feature = "scripting")]
+            script: None,
+            folder_sizes: HashMap::new(),
+            overviews: overview_pool(Arc::clone(&reapeaks)),
+            reapeaks,
+            overview_cache: HashMap::new(),
+            // Probes only read headers; two threads keep a slow share busy
+            // without flooding it.
+            metadata: WorkerPool::new(2, METADATA_QUEUE, |path: &PathBuf| {
+                punks_playback::probe(path)
+            }),
+            metadata_cache: HashMap::new(),
+            list_options: ListOptions {
+                count_audio: true,
+                extensions,
+                show_hidden: self.show_hidden,
+            },
+            sort_mode: cfg.sort_mode,
+            sort_descending: cfg.sort_descending,
+            listings: ListingCache::new(LISTING_CACHE_CAPACITY),
+            folder_watcher: None,
+            auto_refresh: true,
+            folder_changed: None,
+            auto_preview: cfg.auto_preview,
+            pending_preview: None,
+            last_selected: None,
+            network_timeout: Duration::from_secs_f32(cfg.network_timeout_secs.max(0.1)),
+            analyzers: self.analyzers,
+            index,
+            index_file: self.index_file,
+            index_dirty: false,
+            history,
+            history_file: self.history_file,
+            listening: None,
+            favorites,
+            favorites_file: self.favorites_file,
+            collections,
+            collections_file: self.collections_file,
+            play_through: None,
+            library,
+            library_file: self.library_file,
+            library_folders: Vec::new(),
+            indexer: None,
+            watch_folders: Vec::new(),
+            watcher: None,
+            layers: Vec::new(),
+            metronome_bpm: cfg.metronome.bpm,
+            loop_sync: cfg.metronome.sync,
+            allow_permanent_delete: cfg.allow_permanent_delete,
+            midi_input: None,
+            midi_map: MidiMap::from_bindings(&cfg.midi.bindings),
+            pad_source: PadSource::Off,
+            first_pad_note: cfg.midi.first_pad_note,
+            pads: Vec::new(),
+            observers: Observers::default(),
+            #[cfg(feature = "remote")]
+            remote: None,
+            #[cfg(feature = "freesound")]
+            freesound: freesound::FreesoundClient::default(),
         };
 
         browser.playback.set_volume(cfg.volume);
-        if let Some(dir) = cfg.last_directory.as_deref().filter(|p| p.is_dir()) {
+        browser.playback.set_loudness_target(cfg.loudness_target);
+        browser.playback.set_fade_frames(cfg.fade_frames);
+        browser.playback.set_polyphonic(cfg.polyphonic);
+        browser.playback.set_reapeaks(cfg.reapeaks);
+        browser.set_quick_audition(cfg.quick_audition_secs);
+        browser.set_memory_budget(cfg.memory_budget_mb);
+        browser.set_watch_folders(cfg.watch_folders.clone());
+        browser.set_library_folders(cfg.library_folders.clone());
+        browser.set_metronome_bpm(cfg.metronome.bpm);
+        browser.set_metronome_on(cfg.metronome.enabled);
+        browser.set_pad_source(cfg.midi.pads);
+        if let Some(port) = cfg.midi.input_port.as_deref() {
+            if let Err(e) = browser.connect_midi(port) {
+                tracing::warn!("{e}");
+            }
+        }
+        #[cfg(feature = "remote")]
+        if cfg.remote.enabled && cfg.remote.root.is_some() {
+            if let Err(e) = browser.start_remote(&cfg.remote) {
+                tracing::warn!("{e}");
+            }
+        }
+        // Not checked with `is_dir` first: on a share that's gone, that would
+        // hang startup where the listing gives up.
+        if let Some(dir) = cfg.last_directory.as_deref() {
             let _ = browser.open_directory(dir);
         }
+        // Selected without playing, unlike `select`.
+        if let Some(path) = cfg.last_selected.as_deref() {
+            browser.active_mut().selected = browser.entries().iter().position(|e| e.path == path);
+        }
 
         Ok(browser)
     }
+}
+
+impl SampleBrowser {
+    /// `cfg` is read once by the caller (see `BrowserPanel::prefs`) rather than
+    /// loaded again here, so a single app startup only touches disk once for
+    /// config instead of once per component that needs it.
+    pub fn new(cfg: &PunksConfig) -> Result<Self, BrowserError> {
+        let mut builder = SampleBrowserBuilder::from_config(cfg);
+        if let Some(file) = SampleIndex::default_path() {
+            builder = builder.sample_index(file);
+        }
+        if let Some(file) = AuditionHistory::default_path() {
+            builder = builder.audition_history(file);
+        }
+        if let Some(file) = Library::default_path() {
+            builder = builder.library_index(file);
+        }
+        if let Some(file) = Favorites::default_path() {
+            builder = builder.favorites(file);
+        }
+        if let Some(file) = Collections::default_path() {
+            builder = builder.collections(file);
+        }
+        builder.build()
+    }
+
+    /// Take up the settings a [`Profile`](punks_core::config::Profile)
+    /// carries from `cfg`, e.g. after importing one: volume, loudness
+    /// matching, fades, audition and memory limits, file types, deletion,
+    /// `.reapeaks` and MIDI bindings. Keybinds are the UI's to apply.
+    pub fn apply_settings(&mut self, cfg: &PunksConfig) {
+        self.set_volume(cfg.volume);
+        self.set_loudness_target(cfg.loudness_target);
+        self.set_fade_frames(cfg.fade_frames);
+        self.set_sort_mode(cfg.sort_mode, cfg.sort_descending);
+        self.set_quick_audition(cfg.quick_audition_secs);
+        self.set_memory_budget(cfg.memory_budget_mb);
+        self.set_reapeaks(cfg.reapeaks);
+        self.set_allow_permanent_delete(cfg.allow_permanent_delete);
+        self.midi_map = MidiMap::from_bindings(&cfg.midi.bindings);
+        self.first_pad_note = cfg.midi.first_pad_note;
+        self.set_pad_source(cfg.midi.pads);
+        let extensions = ExtensionRegistry::with_custom(&cfg.extensions);
+        if extensions != *self.extensions() {
+            self.set_extensions(extensions);
+        }
+    }
 
     fn active(&self) -> &TabState {
         &self.tabs[self.active_tab]
@@ -94,241 +858,2738 @@ impl SampleBrowser {
             self.last_error = Some(err.to_string());
         }
 
-        // Drain every tab's search channel, not just the active one, so a
-        // search started in a tab still resolves while another tab is focused.
+        // Drain every tab's search, not just the active one's, so a search
+        // started in a tab still resolves while another tab is focused.
         for tab in &mut self.tabs {
-            if let Some(rx) = &tab.search_rx {
-                match rx.try_recv() {
-                    Ok(results) => {
-                        tab.search_results = Some(results);
-                        tab.search_rx = None;
-                    }
-                    Err(mpsc::TryRecvError::Disconnected) => {
-                        tab.search_results = Some(Vec::new());
-                        tab.search_rx = None;
-                    }
-                    Err(mpsc::TryRecvError::Empty) => {}
-                }
-            }
+            tab.poll_search();
+        }
+
+        self.poll_batch();
+        self.poll_auto_tag();
+        self.poll_watch();
+        self.poll_library();
+        self.poll_integrity();
+        self.poll_duplicates();
+        #[cfg(feature = "scripting")]
+        self.poll_script();
+        self.poll_folder_sizes();
+        self.poll_folder_watch();
+        self.poll_preview();
+        self.poll_overviews();
+        self.poll_metadata();
+
+        let triggers = self
+            .midi_input
+            .as_ref()
+            .map(MidiInput::drain)
+            .unwrap_or_default();
+        for trigger in triggers {
+            self.handle_midi(trigger);
+        }
+
+        #[cfg(feature = "remote")]
+        self.poll_remote();
+
+        #[cfg(feature = "freesound")]
+        self.poll_freesound();
+
+        let status = self.playback.status();
+        self.track_audition(&status);
+        self.poll_play_through(&status);
+        if !self.observers.is_empty() {
+            self.observers.update(self.snapshot(&status), status);
         }
     }
 
-    pub fn open_directory(&mut self, path: &Path) -> Result<(), BrowserError> {
-        let listing = punks_core::list_directory(path)?;
-        {
-            let tab = self.active_mut();
-            tab.history = vec![path.to_path_buf()];
-            tab.listing = Some(listing);
-            tab.selected = None;
+    /// Start timing a sample when it starts playing, and log it to the
+    /// history when it stops, ends or another one starts.
+    fn track_audition(&mut self, status: &PlaybackStatus) {
+        let current = self.listening.as_ref().map(|l| l.path.as_path());
+        let playing = match status {
+            PlaybackStatus::Playing { file, .. } => Some(file.as_path()),
+            // Seeking past a long file's preview reloads it; still the same
+            // audition.
+            PlaybackStatus::Loading { file, .. } if current == Some(file.as_path()) => return,
+            // Nor is a pause.
+            PlaybackStatus::Paused { file, .. } if current == Some(file.as_path()) => return,
+            PlaybackStatus::Loading { .. }
+            | PlaybackStatus::Paused { .. }
+            | PlaybackStatus::Idle
+            | PlaybackStatus::Finished { .. }
+            | PlaybackStatus::Reconnecting { .. } => None,
+        };
+        if playing == current {
+            return;
         }
-        self.last_error = None;
-        self.clear_search();
-        Ok(())
+        let next = playing.map(|path| Listening {
+            path: path.to_path_buf(),
+            started: SystemTime::now(),
+            since: Instant::now(),
+        });
+        self.finish_audition();
+        self.listening = next;
     }
 
-    pub fn navigate_into(&mut self, index: usize) -> Result<(), BrowserError> {
-        let path = {
-            let entry = self.entries().get(index).ok_or(BrowserError::NoSelection)?;
-            if !entry.is_directory {
-                return Err(BrowserError::NoSelection);
-            }
-            entry.path.clone()
+    fn finish_audition(&mut self) {
+        let Some(listening) = self.listening.take() else {
+            return;
         };
+        let audition = Audition {
+            path: listening.path,
+            started: listening.started,
+            listened: listening.since.elapsed(),
+        };
+        match self.history_file.as_deref() {
+            Some(file) => {
+                if let Err(e) = self.history.record(file, audition) {
+                    tracing::warn!("failed to write {}: {e}", file.display());
+                }
+            }
+            None => self.history.push(audition),
+        }
+    }
 
-        let listing = punks_core::list_directory(&path)?;
-        let tab = self.active_mut();
-        tab.history.push(path);
-        tab.listing = Some(listing);
-        tab.selected = None;
-        Ok(())
+    /// Every sample auditioned, with when and for how long; see
+    /// [`SampleBrowserBuilder::audition_history`]. One still playing is
+    /// added once it stops.
+    pub fn audition_history(&self) -> &AuditionHistory {
+        &self.history
     }
 
-    pub fn navigate_up(&mut self) -> Result<(), BrowserError> {
-        if self.active().history.len() <= 1 {
-            return Ok(());
+    /// The last [`RECENT_SAMPLES`] samples auditioned, each once and latest
+    /// first: the history without the repeats, for getting back to one
+    /// heard a while ago. Kept between sessions with the history.
+    pub fn recent(&self) -> Vec<&Path> {
+        let mut recent: Vec<&Path> = Vec::with_capacity(RECENT_SAMPLES);
+        for audition in self.history.recent() {
+            if recent.len() == RECENT_SAMPLES {
+                break;
+            }
+            if !recent.contains(&audition.path.as_path()) {
+                recent.push(&audition.path);
+            }
         }
-        let path = {
-            let tab = self.active_mut();
-            tab.history.pop();
-            tab.history.last().unwrap().clone()
-        };
-        let listing = punks_core::list_directory(&path)?;
-        let tab = self.active_mut();
-        tab.listing = Some(listing);
-        tab.selected = None;
-        Ok(())
+        recent
     }
 
-    pub fn navigate_to_breadcrumb(&mut self, level: usize) -> Result<(), BrowserError> {
-        if level >= self.active().history.len() {
-            return Ok(());
+    /// Star `path`, a file or a folder; see [`favorites`](Self::favorites).
+    pub fn add_favorite(&mut self, path: &Path) {
+        if self.favorites.add(path) {
+            self.favorites_changed();
         }
-        let path = {
-            let tab = self.active_mut();
-            tab.history.truncate(level + 1);
-            tab.history.last().unwrap().clone()
-        };
-        let listing = punks_core::list_directory(&path)?;
-        let tab = self.active_mut();
-        tab.listing = Some(listing);
-        tab.selected = None;
-        Ok(())
     }
 
-    pub fn entries(&self) -> &[FileEntry] {
-        self.active()
-            .listing
-            .as_ref()
-            .map(|l| l.entries.as_slice())
-            .unwrap_or(&[])
+    pub fn remove_favorite(&mut self, path: &Path) {
+        if self.favorites.remove(path) {
+            self.favorites_changed();
+        }
     }
 
-    pub fn current_directory(&self) -> Option<&Path> {
-        self.active().history.last().map(PathBuf::as_path)
+    pub fn is_favorite(&self, path: &Path) -> bool {
+        self.favorites.contains(path)
     }
 
-    pub fn breadcrumbs(&self) -> Vec<String> {
-        self.active()
-            .history
-            .iter()
-            .map(|p| {
-                p.file_name()
-                    .map(|n| n.to_string_lossy().into_owned())
-                    .unwrap_or_else(|| p.to_string_lossy().into_owned())
-            })
-            .collect()
+    /// Starred files and folders, oldest first; see
+    /// [`SampleBrowserBuilder::favorites`].
+    pub fn favorites(&self) -> &[PathBuf] {
+        self.favorites.paths()
     }
-    pub fn can_navigate_up(&self) -> bool {
-        self.active().history.len() > 1
+
+    /// Go to a favorite: open it if it's a folder, otherwise open its folder
+    /// with it selected.
+    pub fn open_favorite(&mut self, path: &Path) -> Result<(), BrowserError> {
+        if path.is_dir() {
+            self.open_directory(path)
+        } else {
+            self.reveal(path)
+        }
     }
 
-    pub fn select(&mut self, index: usize) {
-        if index < self.entries().len() {
-            self.active_mut().selected = Some(index);
+    fn favorites_changed(&mut self) {
+        if let Some(file) = self.favorites_file.as_deref() {
+            if let Err(e) = self.favorites.save(file) {
+                tracing::warn!("failed to write {}: {e}", file.display());
+            }
+        }
+        if self.pad_source == PadSource::Favorites {
+            self.refresh_pads();
         }
     }
 
-    pub fn selected(&self) -> Option<usize> {
-        self.active().selected
+    /// Collections of samples from anywhere, oldest first; see
+    /// [`SampleBrowserBuilder::collections`].
+    pub fn collections(&self) -> &[Collection] {
+        self.collections.all()
     }
 
-    pub fn play_selected(&mut self) {
-        let index = match self.active().selected {
-            Some(i) => i,
-            None => return,
+    pub fn collection(&self, name: &str) -> Option<&Collection> {
+        self.collections.get(name)
+    }
+
+    /// Start an empty collection called `name`; false if that's blank or
+    /// taken.
+    pub fn create_collection(&mut self, name: &str) -> bool {
+        let created = self.collections.create(name);
+        if created {
+            self.collections_changed();
+        }
+        created
+    }
+
+    /// Call collection `from` `to` instead; false if there's no `from`, or
+    /// `to` is blank or taken.
+    pub fn rename_collection(&mut self, from: &str, to: &str) -> bool {
+        if !self.collections.rename(from, to) {
+            return false;
+        }
+        if let Some((name, _)) = self.play_through.as_mut().filter(|(n, _)| n == from) {
+            *name = to.trim().to_string();
+        }
+        self.collections_changed();
+        true
+    }
+
+    pub fn delete_collection(&mut self, name: &str) -> bool {
+        let deleted = self.collections.delete(name);
+        if deleted {
+            self.collections_changed();
+        }
+        deleted
+    }
+
+    /// Add `path` to the end of collection `name`; false if there's no such
+    /// collection or it's in already.
+    pub fn add_to_collection(&mut self, name: &str, path: &Path) -> bool {
+        let added = self.collections.get_mut(name).is_some_and(|c| c.add(path));
+        if added {
+            self.collections_changed();
+        }
+        added
+    }
+
+    /// Take the sample at `index` out of collection `name`.
+    pub fn remove_from_collection(&mut self, name: &str, index: usize) -> bool {
+        let removed = self
+            .collections
+            .get_mut(name)
+            .is_some_and(|c| c.remove(index));
+        if removed {
+            self.collections_changed();
+        }
+        removed
+    }
+
+    /// Move the sample at `from` in collection `name` to `to`.
+    pub fn reorder_collection(&mut self, name: &str, from: usize, to: usize) -> bool {
+        let moved = self
+            .collections
+            .get_mut(name)
+            .is_some_and(|c| c.reorder(from, to));
+        if moved {
+            self.collections_changed();
+        }
+        moved
+    }
+
+    /// Play collection `name` from its sample at `index`, each one after
+    /// the last has ended, until the end of the collection or until
+    /// something else plays or playback stops.
+    pub fn play_collection(&mut self, name: &str, index: usize) {
+        let Some(path) = self
+            .collections
+            .get(name)
+            .and_then(|c| c.samples().get(index))
+            .cloned()
+        else {
+            return;
+        };
+        self.play_file(&path);
+        self.play_through = Some((name.to_string(), path));
+    }
+
+    /// Skip to the next sample of the collection playing through; false at
+    /// its end, which stops playing through, or when none is.
+    pub fn play_collection_next(&mut self) -> bool {
+        let Some((name, path)) = self.play_through.take() else {
+            return false;
+        };
+        let next = self.collections.get(&name).and_then(|c| {
+            let at = c.samples().iter().position(|p| *p == path)?;
+            Some(at + 1).filter(|&next| next < c.samples().len())
+        });
+        match next {
+            Some(next) => {
+                self.play_collection(&name, next);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The collection being played through and where in it, if one is.
+    pub fn collection_playing(&self) -> Option<(&str, usize)> {
+        let (name, path) = self.play_through.as_ref()?;
+        let at = self
+            .collections
+            .get(name)?
+            .samples()
+            .iter()
+            .position(|p| p == path)?;
+        Some((name, at))
+    }
+
+    /// Move on through the collection when its sample ends; stop playing
+    /// through once anything else plays or playback stops.
+    fn poll_play_through(&mut self, status: &PlaybackStatus) {
+        let Some((_, path)) = &self.play_through else {
+            return;
+        };
+        match status {
+            PlaybackStatus::Finished { file } if file == path => {
+                self.play_collection_next();
+            }
+            PlaybackStatus::Loading { file, .. }
+            | PlaybackStatus::Playing { file, .. }
+            | PlaybackStatus::Paused { file, .. }
+                if file == path => {}
+            PlaybackStatus::Reconnecting { .. } => {}
+            _ => self.play_through = None,
+        }
+    }
+
+    fn collections_changed(&self) {
+        if let Some(file) = self.collections_file.as_deref() {
+            if let Err(e) = self.collections.save(file) {
+                tracing::warn!("failed to write {}: {e}", file.display());
+            }
+        }
+    }
+
+    /// Call `callback` from [`poll`](Self::poll) with each change since the
+    /// previous poll, on the thread that polls. Changes made before it was
+    /// registered aren't reported.
+    pub fn observe(&mut self, callback: impl FnMut(&BrowserEvent) + 'static) -> ObserverId {
+        let now = self.snapshot(&self.playback.status());
+        self.observers.add(Box::new(callback), now)
+    }
+
+    pub fn unobserve(&mut self, id: ObserverId) {
+        self.observers.remove(id);
+    }
+
+    fn snapshot(&self, status: &PlaybackStatus) -> Snapshot {
+        let tab = self.active();
+        Snapshot {
+            tab: self.active_tab,
+            listing: tab.listing.clone(),
+            filtered: tab.filtered.clone(),
+            search: tab.search_results.clone(),
+            selected: (tab.selected, tab.search_selected),
+            playback: Some(observe::playback_key(status)),
+            error: self.last_error.clone(),
+        }
+    }
+
+    #[cfg(feature = "freesound")]
+    fn poll_freesound(&mut self) {
+        let outcome = self.freesound.poll();
+        if let Some(preview) = outcome.play {
+            self.last_error = None;
+            self.apply_sample_gain(&preview);
+            self.playback.play(&preview);
+        }
+        let dir = self.current_directory();
+        if outcome
+            .downloaded
+            .iter()
+            .any(|p| p.parent().is_some() && p.parent() == dir)
+        {
+            if let Err(e) = self.refresh() {
+                tracing::warn!("refresh after download: {e}");
+            }
+        }
+    }
+
+    #[cfg(feature = "remote")]
+    fn poll_remote(&mut self) {
+        let Some(server) = &self.remote else {
+            return;
+        };
+        for cmd in server.drain() {
+            match cmd {
+                // The server only queues playable files.
+                remote::RemoteCommand::Play(path) => {
+                    self.last_error = None;
+                    self.apply_sample_gain(&path);
+                    self.playback.play(&path);
+                }
+                remote::RemoteCommand::Stop => self.playback.stop_all(),
+            }
+        }
+        server.publish(&self.playback.status());
+    }
+
+    fn poll_batch(&mut self) {
+        let Some(job) = &mut self.batch else {
+            return;
+        };
+        let Some(rx) = &job.rx else {
+            return;
+        };
+        let mut finished = false;
+        loop {
+            match rx.try_recv() {
+                Ok(r) => {
+                    // Rewritten files must not replay a stale cached decode.
+                    if let Ok(done) = &r.result {
+                        self.playback.invalidate(&done.output);
+                        self.overview_cache.remove(&done.output);
+                        self.metadata_cache.remove(&done.output);
+                        self.listings.invalidate(&done.output);
+                    }
+                    job.results.push(r);
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            job.rx = None;
+            // New or replaced files may live in the folder being browsed.
+            if let Err(e) = self.refresh() {
+                tracing::warn!("refresh after batch: {e}");
+            }
+        }
+    }
+
+    fn poll_integrity(&mut self) {
+        let Some(job) = &mut self.integrity else {
+            return;
+        };
+        let Some(rx) = &job.rx else {
+            return;
+        };
+        let mut finished = false;
+        loop {
+            match rx.try_recv() {
+                Ok(IntegrityMsg::Hashing) => job.files_hashed += 1,
+                Ok(IntegrityMsg::Done(outcome)) => job.outcome = Some(outcome),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            job.rx = None;
+        }
+    }
+
+    fn poll_duplicates(&mut self) {
+        let Some(job) = &mut self.duplicates else {
+            return;
+        };
+        let Some(rx) = &job.rx else {
+            return;
+        };
+        let mut finished = false;
+        loop {
+            match rx.try_recv() {
+                Ok(DuplicateMsg::Hashing) => job.files_hashed += 1,
+                Ok(DuplicateMsg::Done(groups)) => job.groups = Some(groups),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            job.rx = None;
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    fn poll_script(&mut self) {
+        let Some(job) = &mut self.script else {
+            return;
+        };
+        let Some(rx) = &job.rx else {
+            return;
+        };
+        let mut finished = false;
+        loop {
+            match rx.try_recv() {
+                Ok(ScriptMsg::Print(line)) => job.output.push(line),
+                Ok(ScriptMsg::Changed(path)) => {
+                    self.playback.invalidate(&path);
+                    self.overview_cache.remove(&path);
+                    self.metadata_cache.remove(&path);
+                    self.listings.invalidate(&path);
+                }
+                Ok(ScriptMsg::Edit(edit)) => self.index_dirty |= edit.apply(&mut self.index),
+                Ok(ScriptMsg::Done(outcome)) => job.outcome = Some(outcome),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            job.rx = None;
+            self.save_sample_index();
+            if let Err(e) = self.refresh() {
+                tracing::warn!("refresh after script: {e}");
+            }
+        }
+    }
+
+    fn poll_folder_sizes(&mut self) {
+        for job in self.folder_sizes.values_mut() {
+            let Some(rx) = &job.rx else {
+                continue;
+            };
+            loop {
+                match rx.try_recv() {
+                    Ok(size) => job.size = size,
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        job.rx = None;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Follow the active tab's folder, and re-read it when it changed on
+    /// disk, keeping the selected entry selected.
+    fn poll_folder_watch(&mut self) {
+        let current = self
+            .current_directory()
+            .filter(|_| self.auto_refresh)
+            .map(Path::to_path_buf);
+        if self.folder_watcher.as_ref().map(FolderWatcher::path) != current.as_deref() {
+            self.folder_watcher = current.as_deref().map(FolderWatcher::start);
+            return;
+        }
+        let Some(path) = self
+            .folder_watcher
+            .as_ref()
+            .filter(|w| w.changed())
+            .map(|w| w.path().to_path_buf())
+        else {
+            return;
+        };
+        tracing::debug!("{} changed on disk", path.display());
+        let selected = self
+            .selected()
+            .and_then(|i| self.entries().get(i))
+            .map(|e| (e.path.clone(), e.region));
+        if let Err(e) = self.refresh() {
+            self.last_error = Some(e.to_string());
+            return;
+        }
+        if let Some((path, region)) = selected {
+            let tab = self.active_mut();
+            tab.selected = tab
+                .visible()
+                .iter()
+                .position(|e| e.path == path && e.region == region);
+        }
+        self.folder_changed = Some(path);
+    }
+
+    /// Re-read the current folder by itself when files are added to it,
+    /// removed or renamed, e.g. a sample exported from a DAW. On by default.
+    pub fn set_auto_refresh(&mut self, on: bool) {
+        self.auto_refresh = on;
+        if !on {
+            self.folder_watcher = None;
+        }
+    }
+
+    pub fn auto_refresh(&self) -> bool {
+        self.auto_refresh
+    }
+
+    /// The folder [`poll`](Self::poll) last re-read because it changed on
+    /// disk, if it did since the last call. Observers hear of it as
+    /// [`BrowserEvent::ListingChanged`].
+    pub fn take_folder_change(&mut self) -> Option<PathBuf> {
+        self.folder_changed.take()
+    }
+
+    /// List `path` for display: directories carry their audio file count so
+    /// empty or near-empty folders stand out before they're entered. Served
+    /// from the listing cache while the folder is unchanged. On a network
+    /// share, a listing that takes longer than the
+    /// [network timeout](Self::set_network_timeout) fails and is reported
+    /// in [`last_error`](Self::last_error) as unreachable.
+    fn list(&mut self, path: &Path) -> Result<Arc<DirListing>, ScanError> {
+        #[cfg(feature = "tracker")]
+        if punks_playback::tracker::is_module(path) && path.is_file() {
+            return self
+                .listings
+                .get_or_list(path, listing_cache::modified(path), |p| {
+                    punks_playback::tracker::list_samples(p)
+                        .map_err(|e| ScanError::io(p, std::io::Error::other(e)))
+                });
+        }
+        let opts = &self.list_options;
+        let listed = if network::is_network_path(path) {
+            let timeout = self.network_timeout;
+            let dir = path.to_path_buf();
+            network::with_timeout(path, timeout, move |_| Ok(listing_cache::modified(&dir)))
+                .and_then(|modified| {
+                    self.listings.get_or_list(path, modified, |p| {
+                        let (dir, opts) = (p.to_path_buf(), opts.clone());
+                        network::with_timeout(p, timeout, move |cancel| {
+                            punks_core::list_directory_cancellable(&dir, &opts, cancel)
+                        })
+                    })
+                })
+        } else {
+            self.listings
+                .get_or_list(path, listing_cache::modified(path), |p| {
+                    punks_core::list_directory_with(p, opts)
+                })
         };
+        if let Err(e @ ScanError::Unreachable { .. }) = &listed {
+            self.last_error = Some(e.to_string());
+        }
+        listed
+    }
+
+    /// How long a listing on a network share may take before the share is
+    /// reported unreachable.
+    pub fn network_timeout(&self) -> Duration {
+        self.network_timeout
+    }
+
+    pub fn set_network_timeout(&mut self, timeout: Duration) {
+        self.network_timeout = timeout;
+    }
+
+    /// Drop cached listings for `path`, anything under it and its parent,
+    /// e.g. when a filesystem watcher reports it changed. Doesn't re-read the
+    /// current directory; see [`refresh`](Self::refresh).
+    pub fn invalidate_listing(&mut self, path: &Path) {
+        self.listings.invalidate(path);
+    }
+
+    /// The built-in analyzers plus those registered with
+    /// [`SampleBrowserBuilder::analyzer`].
+    pub fn analyzers(&self) -> &AnalyzerRegistry {
+        &self.analyzers
+    }
+
+    /// File types listed, and which of them play.
+    pub fn extensions(&self) -> &ExtensionRegistry {
+        &self.list_options.extensions
+    }
+
+    /// Swap the file types listed and re-read the current directory. Searches
+    /// and jobs already running keep the types they started with, as does a
+    /// running remote API until restarted.
+    pub fn set_extensions(&mut self, extensions: ExtensionRegistry) {
+        self.list_options.extensions = extensions;
+        self.listings.clear();
+        if let Err(e) = self.refresh() {
+            self.last_error = Some(e.to_string());
+        }
+    }
+
+    /// Move the entry at `index` (a sample or a whole folder) to the trash and
+    /// re-read the listing. Where there's no trash it's deleted for good only
+    /// if [`set_allow_permanent_delete`](Self::set_allow_permanent_delete) was
+    /// opted into. CUE tracks are parts of a shared file and can't be deleted
+    /// one by one.
+    pub fn delete_entry(&mut self, index: usize) -> Result<Deleted, BrowserError> {
         let path = match self.entries().get(index) {
-            Some(entry) if !entry.is_directory => entry.path.clone(),
-            _ => return,
+            Some(entry) if entry.region.is_none() => entry.path.clone(),
+            _ => return Err(BrowserError::NoSelection),
         };
+        self.delete_path(&path)
+    }
 
-        self.last_error = None;
-        self.playback.play(&path);
+    fn delete_path(&mut self, path: &Path) -> Result<Deleted, BrowserError> {
+        // Release the file before it goes (Windows won't delete open files).
+        let playing = match self.playback.status() {
+            PlaybackStatus::Loading { file, .. }
+            | PlaybackStatus::Playing { file, .. }
+            | PlaybackStatus::Paused { file, .. } => file.starts_with(path),
+            PlaybackStatus::Idle
+            | PlaybackStatus::Finished { .. }
+            | PlaybackStatus::Reconnecting { .. } => false,
+        };
+        if playing {
+            self.playback.stop();
+        }
+
+        let deleted = punks_core::trash::delete(path, self.allow_permanent_delete)?;
+        self.folder_sizes.remove(path);
+        self.overview_cache.remove(path);
+        self.metadata_cache.remove(path);
+        self.listings.invalidate(path);
+        self.refresh()?;
+        Ok(deleted)
     }
 
-    pub fn play_file(&mut self, path: &Path) {
+    pub fn allow_permanent_delete(&self) -> bool {
+        self.allow_permanent_delete
+    }
+
+    pub fn set_allow_permanent_delete(&mut self, allow: bool) {
+        self.allow_permanent_delete = allow;
+    }
+
+    /// Whether `entry` is a file that can be previewed, as opposed to a
+    /// folder or a list-only type.
+    pub fn is_playable(&self, entry: &FileEntry) -> bool {
+        !entry.is_directory && self.extensions().is_playable(&entry.extension)
+    }
+
+    pub fn open_directory(&mut self, path: &Path) -> Result<(), BrowserError> {
+        // Canonicalized or dropped paths can arrive as `\\?\C:\…` on Windows.
+        let path = punks_core::paths::simplified(path);
+        let listing = self.list(&path)?;
+        {
+            let tab = self.active_mut();
+            if tab.history != [path.as_ref()] {
+                tab.leave();
+            }
+            tab.history = vec![path.into_owned()];
+            tab.filter.clear();
+            tab.selected = None;
+        }
+        self.show(listing);
         self.last_error = None;
-        self.playback.play(path);
+        self.clear_search();
+        Ok(())
     }
 
-    pub fn stop(&mut self) {
-        self.playback.stop();
+    /// Open the folder `path` is in and select it there, e.g. to go to a
+    /// sample picked from the audition history.
+    pub fn reveal(&mut self, path: &Path) -> Result<(), BrowserError> {
+        self.open_directory(path.parent().unwrap_or(path))?;
+        if let Some(i) = self.entries().iter().position(|e| e.path == path) {
+            self.active_mut().selected = Some(i);
+        }
+        Ok(())
     }
 
-    pub fn playback_status(&self) -> PlaybackStatus {
-        self.playback.status()
+    /// Whether `entry` is a tracker module, whose samples
+    /// [`navigate_into`](Self::navigate_into) lists like a folder's.
+    pub fn has_samples(&self, entry: &FileEntry) -> bool {
+        cfg!(feature = "tracker")
+            && !entry.is_directory
+            && entry.region.is_none()
+            && MODULE_EXTENSIONS.contains(&entry.extension.as_str())
     }
 
-    pub fn last_error(&self) -> Option<&str> {
-        self.last_error.as_deref()
+    /// Write the samples inside the tracker module `module` into `dir` as
+    /// WAVs, returning the files written.
+    #[cfg(feature = "tracker")]
+    pub fn extract_samples(&self, module: &Path, dir: &Path) -> Result<Vec<PathBuf>, BrowserError> {
+        Ok(punks_playback::tracker::extract_samples(module, dir)?)
+    }
+
+    /// The file to hand another program when `entry` is dragged out of the
+    /// browser. CUE tracks and samples inside tracker modules aren't files
+    /// of their own, so those are written as a WAV to a scratch folder first.
+    pub fn drag_file(&self, entry: &FileEntry) -> Result<PathBuf, BrowserError> {
+        #[cfg(feature = "tracker")]
+        let embedded = punks_playback::tracker::embedded(&entry.path).is_some();
+        #[cfg(not(feature = "tracker"))]
+        let embedded = false;
+        if entry.region.is_none() && !embedded {
+            return Ok(entry.path.clone());
+        }
+
+        let file = std::env::temp_dir()
+            .join("punks-drag")
+            .join(export_name(entry));
+        punks_playback::process::export_clip(&entry.path, entry.region, &file)?;
+        Ok(file)
+    }
+
+    /// Write the selected sample (or CUE track) to `dest` as a WAV in
+    /// `spec`'s rate and bit depth; see [`export_name`] for a name to offer.
+    pub fn export_selected(
+        &self,
+        dest: &Path,
+        spec: &ExportSpec,
+    ) -> Result<ExportedFile, BrowserError> {
+        let entry = if self.is_in_search_mode() {
+            self.search_selected()
+                .and_then(|i| self.search_results()?.get(i))
+        } else {
+            self.selected().and_then(|i| self.entries().get(i))
+        };
+        let entry = entry.ok_or(BrowserError::NoSelection)?;
+        self.export_entry(entry, dest, spec)
+    }
+
+    /// [`export_selected`](Self::export_selected) for any entry.
+    pub fn export_entry(
+        &self,
+        entry: &FileEntry,
+        dest: &Path,
+        spec: &ExportSpec,
+    ) -> Result<ExportedFile, BrowserError> {
+        if !self.is_playable(entry) {
+            return Err(BrowserError::NoSelection);
+        }
+        Ok(punks_playback::export::export(
+            &entry.path,
+            entry.region,
+            dest,
+            spec,
+        )?)
+    }
+
+    /// `spec` with what previewing `path` (or `region` of it) does baked in:
+    /// its trim, the rate or pitch, a tempo-sync stretch, and the
+    /// [loudness target](Self::set_loudness_target) unless `spec`
+    /// normalizes already. The volume is left out, being the listener's.
+    pub fn baked_spec(&self, path: &Path, region: Option<Region>, spec: &ExportSpec) -> ExportSpec {
+        let looped = region.is_none().then_some(path);
+        let stretch = self.tempo_sync_for(looped).map_or(1.0, |s| s.stretch);
+        let normalize = match (spec.normalize, self.loudness_target()) {
+            (Normalize::Off, Some(target_lufs)) => Normalize::Loudness { target_lufs },
+            (normalize, _) => normalize,
+        };
+        ExportSpec {
+            normalize,
+            gain_db: spec.gain_db + self.sample_gain_db(path),
+            speed: spec.speed * self.rate() * stretch,
+            ..spec.clone()
+        }
+    }
+
+    /// Where to save `start..end` of the loaded clip by default: next to
+    /// its file, named for the span (see [`slice_name`]). `None` when
+    /// nothing is loaded.
+    pub fn slice_file(&self, start: Duration, end: Duration) -> Option<PathBuf> {
+        let (path, region) = self.loaded_clip()?;
+        let offset = region.map_or(Duration::ZERO, |r| r.start);
+        let name = slice_name(path, offset + start, offset + end);
+        Some(path.with_file_name(name))
+    }
+
+    /// Write `start..end` of the loaded clip, measured from its start like
+    /// [`loop_region`](Self::loop_region), to `dest` as a WAV in `spec`'s
+    /// format: a chop saved as a sample of its own.
+    pub fn save_slice(
+        &self,
+        start: Duration,
+        end: Duration,
+        dest: &Path,
+        spec: &ExportSpec,
+    ) -> Result<ExportedFile, BrowserError> {
+        let (path, region) = self.loaded_clip().ok_or(BrowserError::NoSelection)?;
+        if end <= start {
+            return Err(BrowserError::NoSelection);
+        }
+        let offset = region.map_or(Duration::ZERO, |r| r.start);
+        let span = Region {
+            start: offset + start,
+            end: Some(offset + end),
+        };
+        Ok(punks_playback::export::export(
+            path,
+            Some(span),
+            dest,
+            spec,
+        )?)
+    }
+
+    /// [`save_slice`](Self::save_slice) of the A/B loop, into `dir` or
+    /// else next to the loaded file.
+    pub fn save_loop(
+        &self,
+        dir: Option<&Path>,
+        spec: &ExportSpec,
+    ) -> Result<ExportedFile, BrowserError> {
+        let (start, end) = self.loop_region().ok_or(BrowserError::NoSelection)?;
+        let mut dest = self
+            .slice_file(start, end)
+            .ok_or(BrowserError::NoSelection)?;
+        if let (Some(dir), Some(name)) = (dir, dest.file_name()) {
+            dest = dir.join(name);
+        }
+        self.save_slice(start, end, &dest, spec)
+    }
+
+    pub fn navigate_into(&mut self, index: usize) -> Result<(), BrowserError> {
+        let path = {
+            let entry = self.entries().get(index).ok_or(BrowserError::NoSelection)?;
+            if !entry.is_directory && !self.has_samples(entry) {
+                return Err(BrowserError::NoSelection);
+            }
+            entry.path.clone()
+        };
+
+        let listing = self.list(&path)?;
+        let tab = self.active_mut();
+        tab.leave();
+        tab.history.push(path);
+        tab.filter.clear();
+        tab.selected = None;
+        self.show(listing);
+        Ok(())
+    }
+
+    pub fn navigate_up(&mut self) -> Result<(), BrowserError> {
+        if self.active().history.len() <= 1 {
+            return Ok(());
+        }
+        let path = {
+            let tab = self.active_mut();
+            tab.leave();
+            tab.history.pop();
+            tab.history.last().unwrap().clone()
+        };
+        let listing = self.list(&path)?;
+        let tab = self.active_mut();
+        tab.filter.clear();
+        tab.selected = None;
+        self.show(listing);
+        Ok(())
+    }
+
+    pub fn navigate_to_breadcrumb(&mut self, level: usize) -> Result<(), BrowserError> {
+        if level >= self.active().history.len() {
+            return Ok(());
+        }
+        let path = {
+            let tab = self.active_mut();
+            if level + 1 < tab.history.len() {
+                tab.leave();
+            }
+            tab.history.truncate(level + 1);
+            tab.history.last().unwrap().clone()
+        };
+        let listing = self.list(&path)?;
+        let tab = self.active_mut();
+        tab.filter.clear();
+        tab.selected = None;
+        self.show(listing);
+        Ok(())
+    }
+
+    /// Go back to the folder the tab was in before the last move, with the
+    /// entry that was selected there selected again, like a file manager's
+    /// Back. A folder that can no longer be read is dropped from the way
+    /// back.
+    pub fn navigate_back(&mut self) -> Result<(), BrowserError> {
+        self.revisit(false)
+    }
+
+    /// Undo [`navigate_back`](Self::navigate_back), until the tab moves on
+    /// somewhere new.
+    pub fn navigate_forward(&mut self) -> Result<(), BrowserError> {
+        self.revisit(true)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.active().back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.active().forward.is_empty()
+    }
+
+    fn revisit(&mut self, forward: bool) -> Result<(), BrowserError> {
+        let tab = self.active_mut();
+        let visit = if forward {
+            tab.forward.pop()
+        } else {
+            tab.back.pop()
+        };
+        let Some(visit) = visit else {
+            return Ok(());
+        };
+        let Some(path) = visit.history.last() else {
+            return Ok(());
+        };
+        let listing = self.list(path)?;
+        let tab = self.active_mut();
+        let here = tab.visit();
+        if forward {
+            tab.back.push(here);
+        } else {
+            tab.forward.push(here);
+        }
+        tab.history = visit.history;
+        tab.filter.clear();
+        tab.selected = None;
+        self.show(listing);
+        self.active_mut().reselect(visit.selected);
+        Ok(())
+    }
+
+    /// Re-read the active tab's current directory, keeping the selection when
+    /// it's still in range.
+    pub fn refresh(&mut self) -> Result<(), BrowserError> {
+        let Some(path) = self.current_directory().map(Path::to_path_buf) else {
+            return Ok(());
+        };
+        self.listings.invalidate(&path);
+        let listing = self.list(&path)?;
+        self.show(listing);
+        let tab = self.active_mut();
+        let shown = tab.visible().len();
+        tab.selected = tab.selected.filter(|&i| i < shown);
+        Ok(())
+    }
+
+    /// The current folder's entries, as narrowed by the
+    /// [filter](Self::set_filter).
+    pub fn entries(&self) -> &[FileEntry] {
+        self.active().visible()
+    }
+
+    /// Show only the folders, and the files whose name contains `filter`
+    /// (case-insensitive), of the current folder; empty shows everything.
+    /// The selection stays on its entry while that's still shown. Moving to
+    /// another folder clears it.
+    pub fn set_filter(&mut self, filter: &str) {
+        if self.active().filter == filter {
+            return;
+        }
+        self.active_mut().filter = filter.to_string();
+        self.arrange(self.active_tab);
+    }
+
+    /// Show `listing` in the active tab, filtered and sorted.
+    fn show(&mut self, listing: Arc<DirListing>) {
+        self.active_mut().listing = Some(listing);
+        let order = (self.sort_mode, self.sort_descending);
+        let info = &self.metadata_cache;
+        self.tabs[self.active_tab].arrange(order, |e| info.get(&e.path)?.as_ref()?.duration);
+    }
+
+    /// Re-filter and re-sort tab `index`'s listing, keeping its selection on
+    /// the same entry while that's still shown.
+    fn arrange(&mut self, index: usize) {
+        let order = (self.sort_mode, self.sort_descending);
+        let info = &self.metadata_cache;
+        let tab = &mut self.tabs[index];
+        let selected = tab.selected_entry();
+        tab.arrange(order, |e| info.get(&e.path)?.as_ref()?.duration);
+        tab.reselect(selected);
+    }
+
+    /// Order the listings by `mode`, largest or latest first when
+    /// `descending`; folders stay above files either way.
+    pub fn set_sort_mode(&mut self, mode: SortMode, descending: bool) {
+        if (self.sort_mode, self.sort_descending) == (mode, descending) {
+            return;
+        }
+        self.sort_mode = mode;
+        self.sort_descending = descending;
+        for index in 0..self.tabs.len() {
+            self.arrange(index);
+        }
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort_mode
+    }
+
+    pub fn sort_descending(&self) -> bool {
+        self.sort_descending
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.active().filter
+    }
+
+    /// Entries of the current directory that couldn't be read, with why.
+    pub fn skipped_entries(&self) -> &[(PathBuf, std::io::Error)] {
+        self.active()
+            .listing
+            .as_ref()
+            .map(|l| l.skipped.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn current_directory(&self) -> Option<&Path> {
+        self.active().history.last().map(PathBuf::as_path)
+    }
+
+    pub fn breadcrumbs(&self) -> Vec<String> {
+        self.active()
+            .history
+            .iter()
+            .map(|p| punks_core::paths::display_name(p))
+            .collect()
+    }
+    pub fn can_navigate_up(&self) -> bool {
+        self.active().history.len() > 1
+    }
+
+    /// Select entry `index`; with auto-preview on, a file selected plays
+    /// shortly after.
+    pub fn select(&mut self, index: usize) {
+        if index < self.entries().len() {
+            self.active_mut().selected = Some(index);
+            self.schedule_preview(self.entries()[index].clone());
+        }
+    }
+
+    /// Play files as they're selected with [`select`](Self::select) and
+    /// [`select_search_result`](Self::select_search_result). Moving the
+    /// selection quickly plays only where it comes to rest.
+    pub fn set_auto_preview(&mut self, on: bool) {
+        self.auto_preview = on;
+        if !on {
+            self.pending_preview = None;
+        }
+    }
+
+    pub fn auto_preview(&self) -> bool {
+        self.auto_preview
+    }
+
+    /// Queue `entry`, just selected, for auto-preview: at once after a
+    /// pause, else once the selection stops moving.
+    fn schedule_preview(&mut self, entry: FileEntry) {
+        let now = Instant::now();
+        let scrolling = self
+            .last_selected
+            .is_some_and(|t| now.duration_since(t) < AUTO_PREVIEW_DELAY);
+        self.last_selected = Some(now);
+        let at = if scrolling {
+            now + AUTO_PREVIEW_DELAY
+        } else {
+            now
+        };
+        self.pending_preview =
+            (self.auto_preview && self.is_playable(&entry)).then_some((entry, at));
+    }
+
+    /// Play the queued auto-preview once it's due, if it's still selected.
+    fn poll_preview(&mut self) {
+        let now = Instant::now();
+        let Some((entry, _)) = self.pending_preview.take_if(|(_, at)| now >= *at) else {
+            return;
+        };
+        let selected = if self.is_in_search_mode() {
+            self.search_selected()
+                .and_then(|i| self.search_results()?.get(i))
+        } else {
+            self.selected().and_then(|i| self.entries().get(i))
+        };
+        if selected.is_some_and(|e| e.path == entry.path && e.region == entry.region) {
+            self.play_entry(&entry);
+        }
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.active().selected
+    }
+
+    pub fn play_selected(&mut self) {
+        let index = match self.active().selected {
+            Some(i) => i,
+            None => return,
+        };
+        let entry = match self.entries().get(index) {
+            Some(entry) if !entry.is_directory => entry.clone(),
+            _ => return,
+        };
+        self.play_entry(&entry);
+    }
+
+    /// Play `path`, unless its type is list-only.
+    pub fn play_file(&mut self, path: &Path) {
+        self.play_file_with_gain(path, self.sample_gain_db(path));
+    }
+
+    /// Like [`play_file`](Self::play_file), but trimmed by `gain_db` this
+    /// once instead of by the [trim remembered](Self::sample_gain_db) for
+    /// `path`.
+    pub fn play_file_with_gain(&mut self, path: &Path, gain_db: f32) {
+        self.pending_preview = None;
+        if !self.extensions().plays_path(path) {
+            self.last_error = Some(format!("{} can't be previewed", path.display()));
+            return;
+        }
+        self.last_error = None;
+        self.apply_tempo_sync(Some(path));
+        self.playback
+            .play_with_gain(path, punks_playback::analysis::db_to_gain(gain_db));
+    }
+
+    /// Play a listing entry, honouring its region (e.g. a CUE sheet track).
+    pub fn play_entry(&mut self, entry: &FileEntry) {
+        self.pending_preview = None;
+        if !self.is_playable(entry) {
+            self.last_error = Some(format!("{} can't be previewed", entry.name));
+            return;
+        }
+        match entry.region {
+            Some(region) => {
+                self.last_error = None;
+                self.apply_tempo_sync(None);
+                self.playback.play_region(&entry.path, region);
+                self.apply_sample_gain(&entry.path);
+            }
+            None => self.play_file(&entry.path),
+        }
+    }
+
+    /// Stop the sample, and any voices left ringing out under
+    /// [polyphonic preview](Self::set_polyphonic).
+    pub fn stop(&mut self) {
+        self.pending_preview = None;
+        self.play_through = None;
+        self.playback.stop_all();
+    }
+
+    /// Start each sample over the one before, which rings out to its end,
+    /// rather than stopping it; for hearing a kick and a snare together.
+    pub fn set_polyphonic(&self, enabled: bool) {
+        self.playback.set_polyphonic(enabled);
+    }
+
+    pub fn polyphonic(&self) -> bool {
+        self.playback.polyphonic()
+    }
+
+    /// Samples still ringing out under polyphonic preview, oldest first.
+    pub fn voices(&self) -> Vec<VoiceInfo> {
+        self.playback.voices()
+    }
+
+    pub fn stop_voice(&self, id: VoiceId) {
+        self.playback.stop_voice(id);
+    }
+
+    /// Hold the playing sample (and any layers) where they are.
+    pub fn pause(&mut self) {
+        self.playback.pause();
+    }
+
+    /// Carry on from where [`pause`](Self::pause) left off.
+    pub fn resume(&mut self) {
+        self.playback.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.playback.is_paused()
+    }
+
+    pub fn playback_status(&self) -> PlaybackStatus {
+        self.playback.status()
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn waveform_peaks(&self) -> Option<&WaveformPeaks> {
+        self.playback.waveform_peaks()
+    }
+
+    /// Peaks at several resolutions, for drawing a zoomed-in waveform.
+    pub fn waveform_levels(&self) -> Option<&PeakLevels> {
+        self.playback.waveform_levels()
+    }
+
+    /// The file and region loaded, whether playing or not.
+    pub fn loaded_clip(&self) -> Option<(&Path, Option<Region>)> {
+        self.playback.loaded_clip()
+    }
+
+    /// Container metadata + preview info for the current track (global, like
+    /// playback). `None` when nothing is loaded.
+    pub fn current_track_info(&self) -> Option<&TrackInfo> {
+        self.playback.current_info()
+    }
+
+    /// Playable duration of the loaded clip, or `None` when nothing is loaded.
+    pub fn loaded_duration(&self) -> Option<std::time::Duration> {
+        self.playback.loaded_duration()
+    }
+
+    /// Seek to `fraction` (0..1) of the loaded clip and play from there.
+    pub fn seek_fraction(&self, fraction: f32) {
+        self.playback.seek_fraction(fraction);
+    }
+
+    /// Play the selected sample (or search result) `semitones` from its own
+    /// pitch, e.g. from a [`keyboard::NoteKeyboard`]. The pitch stays for
+    /// whatever plays next until [`set_pitch`](Self::set_pitch) resets it.
+    pub fn play_note(&mut self, semitones: i32) {
+        self.playback.set_pitch(semitones as f32);
+        self.play_current_selection();
+    }
+
+    /// The samples stacked for [`play_layers`](Self::play_layers), in the
+    /// order they were added.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Add `path` to the layer stack at its preview trim, or take it off if
+    /// it's there. Returns whether it's in the stack now; a full stack
+    /// ([`MAX_LAYERS`]) or an unplayable file isn't added.
+    pub fn toggle_layer(&mut self, path: &Path) -> bool {
+        if let Some(i) = self.layers.iter().position(|l| l.path == path) {
+            self.layers.remove(i);
+            return false;
+        }
+        if !self.extensions().plays_path(path) {
+            self.last_error = Some(format!("{} can't be previewed", path.display()));
+            return false;
+        }
+        if self.layers.len() >= MAX_LAYERS {
+            self.last_error = Some(format!("At most {MAX_LAYERS} samples can be layered"));
+            return false;
+        }
+        let gain_db = self.sample_gain_db(path);
+        self.layers.push(Layer {
+            path: path.to_path_buf(),
+            gain_db,
+        });
+        true
+    }
+
+    pub fn remove_layer(&mut self, index: usize) {
+        if index < self.layers.len() {
+            self.layers.remove(index);
+        }
+    }
+
+    pub fn clear_layers(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Set one layer's gain; it applies straight away if the stack is
+    /// playing.
+    pub fn set_layer_gain_db(&mut self, index: usize, gain_db: f32) {
+        if let Some(layer) = self.layers.get_mut(index) {
+            layer.gain_db = gain_db;
+            self.playback
+                .set_layer_gain(index, punks_playback::analysis::db_to_gain(gain_db));
+        }
+    }
+
+    /// Play the layer stack: every sample in it started together, each at
+    /// its own gain, until it ends or something else plays.
+    pub fn play_layers(&mut self) {
+        if self.layers.is_empty() {
+            return;
+        }
+        self.last_error = None;
+        let layers: Vec<(PathBuf, f32)> = self
+            .layers
+            .iter()
+            .map(|l| {
+                (
+                    l.path.clone(),
+                    punks_playback::analysis::db_to_gain(l.gain_db),
+                )
+            })
+            .collect();
+        self.playback.play_layers(&layers);
+    }
+
+    /// Whether the layer stack is loading or playing.
+    pub fn layers_playing(&self) -> bool {
+        self.playback.layers_playing()
+    }
+
+    /// Click along with previews (see [`set_metronome_bpm`](Self::set_metronome_bpm)).
+    pub fn set_metronome_on(&mut self, on: bool) {
+        self.playback
+            .set_metronome(on.then_some(self.metronome_bpm));
+    }
+
+    pub fn metronome_on(&self) -> bool {
+        self.playback.metronome().is_some()
+    }
+
+    /// The metronome's tempo, also what [`LoopSync::Stretch`] plays loops
+    /// at; it applies to a loop already playing.
+    pub fn set_metronome_bpm(&mut self, bpm: f32) {
+        self.metronome_bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        if self.metronome_on() {
+            self.playback.set_metronome(Some(self.metronome_bpm));
+        }
+        self.resync_loaded_clip();
+    }
+
+    pub fn metronome_bpm(&self) -> f32 {
+        self.metronome_bpm
+    }
+
+    /// How loops follow the metronome from the next one played; the stretch
+    /// and the looping also change for one already playing.
+    pub fn set_loop_sync(&mut self, sync: LoopSync) {
+        self.loop_sync = sync;
+        self.resync_loaded_clip();
+    }
+
+    pub fn loop_sync(&self) -> LoopSync {
+        self.loop_sync
+    }
+
+    /// `path`'s tempo if it's a loop, from its name or, for files named or
+    /// filed as loops, its length.
+    pub fn loop_tempo(&self, path: &Path) -> Option<LoopTempo> {
+        let length = match self.file_info(path).and_then(|info| info.duration) {
+            Some(length) => length,
+            None => punks_playback::probe(path).ok()?.duration?,
+        };
+        punks_playback::analysis::tempo::loop_tempo(path, length)
+    }
+
+    /// Set up tempo sync for playing `path` (`None` for a region, which
+    /// isn't looped).
+    fn apply_tempo_sync(&self, path: Option<&Path>) {
+        self.playback.set_tempo_sync(self.tempo_sync_for(path));
+    }
+
+    /// The tempo sync [`apply_tempo_sync`](Self::apply_tempo_sync) sets for
+    /// `path`.
+    fn tempo_sync_for(&self, path: Option<&Path>) -> Option<TempoSync> {
+        match (self.loop_sync, path) {
+            (LoopSync::Off, _) | (_, None) => None,
+            (mode, Some(path)) => self.loop_tempo(path).map(|tempo| TempoSync {
+                beats: tempo.beats as f32,
+                stretch: if mode == LoopSync::Stretch {
+                    self.metronome_bpm / tempo.bpm
+                } else {
+                    1.0
+                },
+            }),
+        }
+    }
+
+    fn resync_loaded_clip(&self) {
+        let path = match self.playback.loaded_clip() {
+            Some((path, None)) => Some(path.to_path_buf()),
+            _ => None,
+        };
+        self.apply_tempo_sync(path.as_deref());
+    }
+
+    /// Transpose playback by `semitones` (varispeed, up to
+    /// [`MAX_PITCH`](punks_playback::MAX_PITCH)); 0 plays samples as they are.
+    pub fn set_pitch(&self, semitones: f32) {
+        self.playback.set_pitch(semitones);
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.playback.pitch()
+    }
+
+    /// Play at `rate` times the normal speed (varispeed, within
+    /// [`MIN_RATE`](punks_playback::MIN_RATE) and
+    /// [`MAX_RATE`](punks_playback::MAX_RATE)), pitch moving with it, to hear
+    /// a sample slowed down or sped up.
+    pub fn set_rate(&self, rate: f32) {
+        self.playback.set_rate(rate);
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.playback.rate()
+    }
+
+    /// Play `span` of the loaded clip (e.g. a selection on its waveform),
+    /// measured from the clip's start, and stop at its end.
+    pub fn play_span(&self, span: Region) {
+        self.playback.play_span(span);
+    }
+
+    /// Repeat `start..end` of the loaded clip, measured from its start, for
+    /// auditioning a chop; see [`PlaybackEngine::set_loop_region`].
+    pub fn set_loop_region(&self, start: Duration, end: Duration) {
+        self.playback.set_loop_region(start, end);
+    }
+
+    pub fn clear_loop_region(&self) {
+        self.playback.clear_loop_region();
+    }
+
+    /// The loop [`set_loop_region`](Self::set_loop_region) set, until it's
+    /// dropped.
+    pub fn loop_region(&self) -> Option<(Duration, Duration)> {
+        self.playback.loop_region()
+    }
+
+    /// Jump to `position` in the playing sample, decoding from there if it's
+    /// past the end of a long file's preview.
+    pub fn seek(&mut self, position: Duration) {
+        self.playback.seek(position);
+    }
+
+    pub fn set_volume(&self, v: f32) {
+        self.playback.set_volume(v);
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.playback.volume()
+    }
+
+    /// Level previews to `target` integrated loudness in LUFS (e.g.
+    /// [`punks_playback::DEFAULT_LOUDNESS_TARGET`]), on top of each sample's
+    /// trim; `None` plays them as they are.
+    pub fn set_loudness_target(&self, target: Option<f32>) {
+        self.playback.set_loudness_target(target);
+    }
+
+    pub fn loudness_target(&self) -> Option<f32> {
+        self.playback.loudness_target()
+    }
+
+    /// Fade previews in and out over `frames` when they start, stop or are
+    /// retriggered, so cutting one off doesn't click; 0 cuts hard.
+    pub fn set_fade_frames(&self, frames: usize) {
+        self.playback.set_fade_frames(frames);
+    }
+
+    pub fn fade_frames(&self) -> usize {
+        self.playback.fade_frames()
+    }
+
+    /// Record everything the output plays to a new timestamped WAV in `dir`
+    /// until [`stop_recording`](Self::stop_recording).
+    pub fn start_recording(&mut self, dir: &Path) -> Result<PathBuf, BrowserError> {
+        Ok(self.playback.start_recording(dir)?)
+    }
+
+    /// Names of the output devices [`switch_output_device`] can move to.
+    ///
+    /// [`switch_output_device`]: Self::switch_output_device
+    pub fn output_devices(&self) -> Result<Vec<String>, BrowserError> {
+        Ok(PlaybackEngine::list_output_devices()?)
+    }
+
+    /// The output device chosen by name; `None` for the system default.
+    pub fn output_device(&self) -> Option<&str> {
+        self.playback.output_device()
+    }
+
+    /// Play through the device named `name` from now on, or the system
+    /// default with `None`. Whatever is loaded, the tabs and the selection
+    /// all stay as they are.
+    pub fn switch_output_device(&mut self, name: Option<&str>) -> Result<(), BrowserError> {
+        Ok(self.playback.switch_device(name)?)
+    }
+
+    /// Finish the session recording, returning its file; `None` if there
+    /// wasn't one running.
+    pub fn stop_recording(&mut self) -> Option<Result<PathBuf, BrowserError>> {
+        let finished = self.playback.stop_recording()?;
+        if let Ok(file) = &finished {
+            if file.parent().is_some() && file.parent() == self.current_directory() {
+                if let Err(e) = self.refresh() {
+                    tracing::warn!("refresh after recording: {e}");
+                }
+            }
+        }
+        Some(finished.map_err(BrowserError::from))
+    }
+
+    /// The file the session is being recorded to.
+    pub fn recording(&self) -> Option<&Path> {
+        self.playback.recording()
+    }
+
+    /// The preview trim remembered for `path`, in dB; 0 when there's none.
+    pub fn sample_gain_db(&self, path: &Path) -> f32 {
+        self.index.gain_db(path).unwrap_or(0.0)
+    }
+
+    /// Remember `gain_db` as `path`'s preview trim, from now on and, once
+    /// saved, in later sessions; it applies straight away if `path` is
+    /// what's playing. 0 dB forgets it.
+    pub fn set_sample_gain_db(&mut self, path: &Path, gain_db: f32) {
+        self.index.set_gain_db(path, Some(gain_db));
+        self.index_dirty = true;
+        let current = match self.playback.status() {
+            PlaybackStatus::Loading { file, .. }
+            | PlaybackStatus::Playing { file, .. }
+            | PlaybackStatus::Paused { file, .. } => Some(file),
+            PlaybackStatus::Idle
+            | PlaybackStatus::Finished { .. }
+            | PlaybackStatus::Reconnecting { .. } => None,
+        };
+        if current.as_deref() == Some(path) {
+            self.apply_sample_gain(path);
+        }
+    }
+
+    /// Write the sample index if anything changed since it was read; see
+    /// [`SampleBrowserBuilder::sample_index`].
+    pub fn save_sample_index(&mut self) {
+        let Some(file) = self.index_file.as_deref().filter(|_| self.index_dirty) else {
+            return;
+        };
+        match self.index.save(file) {
+            Ok(()) => self.index_dirty = false,
+            Err(e) => tracing::warn!("failed to write {}: {e}", file.display()),
+        }
+    }
+
+    pub fn sample_index(&self) -> &SampleIndex {
+        &self.index
+    }
+
+    fn apply_sample_gain(&self, path: &Path) {
+        let db = self.sample_gain_db(path);
+        self.playback
+            .set_gain(punks_playback::analysis::db_to_gain(db));
+    }
+
+    /// Search the current folder, recursively, for `query`. Within the
+    /// [library folders](Self::set_library_folders) the results come from
+    /// the index straight away; elsewhere the folder is walked on background
+    /// threads, and matches are added to the
+    /// [results](Self::search_results) as they're found, with
+    /// [progress](Self::search_progress) reported as it goes. `#tag` words keep to samples with those
+    /// [tags](Self::add_sample_tag) and `*3` to those
+    /// [rated](Self::set_rating) three stars or more, so `#dark` alone lists
+    /// every dark sample under the folder; with either, the best rated come
+    /// first.
+    pub fn search(&mut self, query: &str) {
+        let root = match self.current_directory() {
+            Some(p) => p.to_path_buf(),
+            None => return,
+        };
+        let query = query.to_string();
+        let (text, filters) = punks_core::search::split_filters(&query);
+        let kept = self.search_filter(&filters);
+        // A playlist or CUE sheet is one small file; searching it filters
+        // its entries.
+        let indexed = self.library.covers(&root) && !root.is_file();
+        let (results, job) = if indexed || root.is_file() {
+            let mut results = if indexed {
+                self.library.search(&root, &text, self.extensions())
+            } else {
+                punks_core::search_directory(&root, &text, self.extensions()).unwrap_or_else(|e| {
+                    tracing::warn!("search in {}: {e}", root.display());
+                    Vec::new()
+                })
+            };
+            apply_search_filter(&mut results, kept.as_ref());
+            (results, None)
+        } else {
+            let options = ScanOptions {
+                extensions: self.extensions().clone(),
+                threads: Some(punks_core::scan::default_threads()),
+                ..ScanOptions::default()
+            };
+            let job = SearchJob {
+                events: punks_core::scan_in_background(&root, options),
+                root,
+                text,
+                kept,
+                progress: ScanProgress::default(),
+            };
+            (Vec::new(), Some(job))
+        };
+        let tab = self.active_mut();
+        tab.search_job = job;
+        tab.search_results = Some(Arc::new(results));
+        tab.search_selected = None;
+        tab.search_query = query;
+    }
+
+    /// The samples a search's `#tag` and `*3` words keep, with their ratings
+    /// to order by; `None` when it has neither.
+    fn search_filter(&self, filters: &punks_core::search::Filters) -> Option<HashMap<PathBuf, u8>> {
+        if filters.is_empty() {
+            return None;
+        }
+        let tags: Vec<&str> = filters.tags.iter().map(String::as_str).collect();
+        let min_rating = filters.min_rating.unwrap_or(0);
+        let kept = self
+            .index
+            .tagged(&tags)
+            .into_iter()
+            .map(|path| (path, self.index.rating(path).unwrap_or(0)))
+            .filter(|&(_, rating)| rating >= min_rating)
+            .map(|(path, rating)| (path.to_path_buf(), rating))
+            .collect();
+        Some(kept)
+    }
+
+    pub fn clear_search(&mut self) {
+        let tab = self.active_mut();
+        tab.search_results = None;
+        tab.search_job = None;
+        tab.search_selected = None;
+        tab.search_query = String::new();
+    }
+
+    /// Whether the search is still walking its folder.
+    pub fn is_searching(&self) -> bool {
+        self.active().search_job.is_some()
+    }
+
+    /// How far the search's walk has got, while it's going.
+    pub fn search_progress(&self) -> Option<ScanProgress> {
+        self.active().search_job.as_ref().map(|job| job.progress)
+    }
+
+    pub fn is_in_search_mode(&self) -> bool {
+        self.active().search_results.is_some()
+    }
+
+    pub fn search_results(&self) -> Option<&[FileEntry]> {
+        self.active().search_results.as_deref().map(Vec::as_slice)
+    }
+
+    pub fn search_selected(&self) -> Option<usize> {
+        self.active().search_selected
+    }
+
+    pub fn select_search_result(&mut self, index: usize) {
+        let valid = self
+            .active()
+            .search_results
+            .as_ref()
+            .is_some_and(|r| index < r.len());
+        if valid {
+            self.active_mut().search_selected = Some(index);
+            if let Some(entry) = self.search_results().and_then(|r| r.get(index)).cloned() {
+                self.schedule_preview(entry);
+            }
+        }
+    }
+
+    // --- Freesound -------------------------------------------------------------
+
+    #[cfg(feature = "freesound")]
+    pub fn freesound(&self) -> &freesound::FreesoundClient {
+        &self.freesound
+    }
+
+    #[cfg(feature = "freesound")]
+    pub fn freesound_mut(&mut self) -> &mut freesound::FreesoundClient {
+        &mut self.freesound
+    }
+
+    // --- Remote API ------------------------------------------------------------
+
+    /// Serve `config`'s root over HTTP on its bind address, replacing any
+    /// running server.
+    #[cfg(feature = "remote")]
+    pub fn start_remote(
+        &mut self,
+        config: &punks_core::config::RemoteConfig,
+    ) -> Result<(), BrowserError> {
+        self.remote = None;
+        let extensions = self.extensions().clone();
+        self.remote = Some(
+            remote::RemoteServer::start(config, extensions).map_err(|message| {
+                BrowserError::Remote {
+                    bind: config.bind.clone(),
+                    message,
+                }
+            })?,
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "remote")]
+    pub fn stop_remote(&mut self) {
+        self.remote = None;
+    }
+
+    /// Address the remote API is listening on, if running.
+    #[cfg(feature = "remote")]
+    pub fn remote_addr(&self) -> Option<&str> {
+        self.remote.as_ref().map(remote::RemoteServer::addr)
+    }
+
+    // --- MIDI ----------------------------------------------------------------
+
+    pub fn connect_midi(&mut self, port: &str) -> Result<(), BrowserError> {
+        self.midi_input = None;
+        let input = MidiInput::connect(port).map_err(|message| BrowserError::Midi {
+            port: port.to_string(),
+            message,
+        })?;
+        self.midi_input = Some(input);
+        Ok(())
+    }
+
+    pub fn disconnect_midi(&mut self) {
+        self.midi_input = None;
+    }
+
+    /// Name of the connected MIDI input port, if any.
+    pub fn midi_port(&self) -> Option<&str> {
+        self.midi_input.as_ref().map(MidiInput::port_name)
+    }
+
+    pub fn midi_map(&self) -> &MidiMap {
+        &self.midi_map
+    }
+
+    pub fn midi_map_mut(&mut self) -> &mut MidiMap {
+        &mut self.midi_map
+    }
+
+    /// Act on `trigger` as if the connected controller sent it: learn it,
+    /// perform the action bound to it, or else play the pad it hits.
+    pub fn handle_midi(&mut self, trigger: MidiTrigger) {
+        let learning = self.midi_map.learning().is_some();
+        if let Some(action) = self.midi_map.handle(trigger) {
+            self.apply_midi_action(action);
+        } else if !learning {
+            if let Some(pad) = midi::pad_index(&trigger, self.first_pad_note) {
+                self.play_pad(pad);
+            }
+        }
+    }
+
+    /// Put samples from `source` on the pads, one per note from the
+    /// [first pad note](Self::set_first_pad_note) up; see
+    /// [`pads`](Self::pads).
+    pub fn set_pad_source(&mut self, source: PadSource) {
+        self.pad_source = source;
+        self.refresh_pads();
+    }
+
+    pub fn pad_source(&self) -> PadSource {
+        self.pad_source
+    }
+
+    /// Take the pads' samples from their source again: recently played
+    /// samples are taken once and stay put, so that playing them from the
+    /// pads doesn't shuffle them.
+    pub fn refresh_pads(&mut self) {
+        let candidates: Vec<PathBuf> = match self.pad_source {
+            PadSource::Off => Vec::new(),
+            PadSource::Recent => self.recent().into_iter().map(Path::to_path_buf).collect(),
+            PadSource::Favorites => self
+                .favorites
+                .paths()
+                .iter()
+                .filter(|path| !path.is_dir())
+                .cloned()
+                .collect(),
+        };
+        let mut pads: Vec<PathBuf> = Vec::with_capacity(midi::PAD_COUNT);
+        for path in candidates {
+            if pads.len() == midi::PAD_COUNT {
+                break;
+            }
+            if !pads.contains(&path) && self.extensions().plays_path(&path) {
+                pads.push(path);
+            }
+        }
+        self.pads = pads;
+    }
+
+    /// The samples on the pads, first pad first; fewer than
+    /// [`PAD_COUNT`](midi::PAD_COUNT) when the source runs short.
+    pub fn pads(&self) -> &[PathBuf] {
+        &self.pads
+    }
+
+    /// Which note the first pad is on, on any channel.
+    pub fn set_first_pad_note(&mut self, note: u8) {
+        self.first_pad_note = note;
+    }
+
+    pub fn first_pad_note(&self) -> u8 {
+        self.first_pad_note
+    }
+
+    /// Play the sample on `pad`; nothing if it has none.
+    pub fn play_pad(&mut self, pad: usize) {
+        if let Some(path) = self.pads.get(pad).cloned() {
+            self.play_file(&path);
+        }
+    }
+
+    /// Perform a MIDI-mapped action. Works on search results while searching,
+    /// mirroring the keyboard navigation.
+    pub fn apply_midi_action(&mut self, action: MidiAction) {
+        let in_search = self.is_in_search_mode();
+        let result = match action {
+            MidiAction::SelectNext => {
+                self.step_selection(1);
+                Ok(())
+            }
+            MidiAction::SelectPrev => {
+                self.step_selection(-1);
+                Ok(())
+            }
+            MidiAction::Enter if !in_search => match self.selected() {
+                Some(i) if self.entries().get(i).is_some_and(|e| e.is_directory) => {
+                    self.navigate_into(i)
+                }
+                _ => {
+                    self.play_selected();
+                    Ok(())
+                }
+            },
+            MidiAction::Enter | MidiAction::Play => {
+                self.play_current_selection();
+                Ok(())
+            }
+            MidiAction::Back if in_search => {
+                self.clear_search();
+                Ok(())
+            }
+            MidiAction::Back => self.navigate_up(),
+            MidiAction::Stop => {
+                self.stop();
+                Ok(())
+            }
+            MidiAction::NextTab => {
+                self.switch_tab((self.active_tab + 1) % self.tabs.len());
+                Ok(())
+            }
+            MidiAction::PrevTab => {
+                let count = self.tabs.len();
+                self.switch_tab((self.active_tab + count - 1) % count);
+                Ok(())
+            }
+            MidiAction::Favorite => {
+                if let Some(path) = self.current_selection_path() {
+                    if self.is_favorite(&path) {
+                        self.remove_favorite(&path);
+                    } else {
+                        self.add_favorite(&path);
+                    }
+                }
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            self.last_error = Some(e.to_string());
+        }
+    }
+
+    /// Move the selection (search results while searching, else the listing)
+    /// by `delta`, clamped; auto-preview plays the newly selected file.
+    fn step_selection(&mut self, delta: isize) {
+        let (current, len) = if self.is_in_search_mode() {
+            let len = self.search_results().map_or(0, <[FileEntry]>::len);
+            (self.search_selected(), len)
+        } else {
+            (self.selected(), self.entries().len())
+        };
+        if len == 0 {
+            return;
+        }
+        let index = match current {
+            Some(i) => (i as isize + delta).clamp(0, len as isize - 1) as usize,
+            None => 0,
+        };
+        if self.is_in_search_mode() {
+            self.select_search_result(index);
+        } else {
+            self.select(index);
+        }
+    }
+
+    /// Play the selected search result while searching, else the selected file.
+    /// The selected search result while searching, else the selected entry.
+    fn current_selection_path(&self) -> Option<PathBuf> {
+        let entry = if self.is_in_search_mode() {
+            self.search_selected()
+                .and_then(|i| self.search_results()?.get(i))
+        } else {
+            self.selected().and_then(|i| self.entries().get(i))
+        };
+        entry.map(|e| e.path.clone())
+    }
+
+    fn play_current_selection(&mut self) {
+        if !self.is_in_search_mode() {
+            self.play_selected();
+            return;
+        }
+        let entry = self
+            .search_selected()
+            .and_then(|i| self.search_results()?.get(i))
+            .cloned();
+        if let Some(entry) = entry {
+            self.play_entry(&entry);
+        }
+    }
+
+    // --- Batch processing -------------------------------------------------
+
+    /// Trim/normalize `sources` on a background thread. Replaces the results
+    /// of any previous run; ignored while a run is still in progress.
+    pub fn start_batch(&mut self, sources: Vec<PathBuf>, opts: ProcessOptions) {
+        if self.is_batch_running() || sources.is_empty() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let total = sources.len();
+        std::thread::spawn(move || {
+            punks_playback::process::process_batch(&sources, &opts, |source, result| {
+                if let Err(e) = &result {
+                    tracing::warn!("batch process {}: {e}", source.display());
+                }
+                let _ = tx.send(BatchResult {
+                    source: source.to_path_buf(),
+                    result,
+                });
+            });
+        });
+        self.batch = Some(BatchJob {
+            total,
+            results: Vec::new(),
+            rx: Some(rx),
+        });
+    }
+
+    pub fn is_batch_running(&self) -> bool {
+        self.batch.as_ref().is_some_and(|b| b.rx.is_some())
+    }
+
+    /// `(files done, files total)` for the current or last batch run.
+    pub fn batch_progress(&self) -> Option<(usize, usize)> {
+        self.batch.as_ref().map(|b| (b.results.len(), b.total))
+    }
+
+    /// Per-file outcomes of the current or last batch run, in completion order.
+    pub fn batch_results(&self) -> &[BatchResult] {
+        self.batch
+            .as_ref()
+            .map(|b| b.results.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Forget a finished run's results. No-op while a run is in progress.
+    pub fn clear_batch(&mut self) {
+        if !self.is_batch_running() {
+            self.batch = None;
+        }
+    }
+
+    // --- Auto-tagging -----------------------------------------------------------
+
+    /// Guess a [`Category`] for each of `sources` on a background thread and
+    /// suggest it as a tag in the sample index, for the user to confirm or
+    /// reject. Samples that already have tags or suggestions are skipped.
+    /// Ignored while a run is in progress.
+    pub fn start_auto_tag(&mut self, mut sources: Vec<PathBuf>) {
+        if self.is_auto_tag_running() {
+            return;
+        }
+        sources.retain(|path| {
+            self.index
+                .get(path)
+                .is_none_or(|r| r.tags.is_empty() && r.suggested_tags.is_empty())
+        });
+        let (tx, rx) = mpsc::channel();
+        let total = sources.len();
+        std::thread::spawn(move || {
+            for source in sources {
+                let guess = punks_playback::analysis::classify::classify_file(&source);
+                if tx.send((source, guess)).is_err() {
+                    break;
+                }
+            }
+        });
+        self.auto_tag = Some(AutoTagJob {
+            total,
+            done: 0,
+            rx: Some(rx),
+        });
+    }
+
+    pub fn is_auto_tag_running(&self) -> bool {
+        self.auto_tag.as_ref().is_some_and(|j| j.rx.is_some())
+    }
+
+    /// `(files classified, files total)` for the current or last run.
+    pub fn auto_tag_progress(&self) -> Option<(usize, usize)> {
+        self.auto_tag.as_ref().map(|j| (j.done, j.total))
+    }
+
+    fn poll_auto_tag(&mut self) {
+        let Some(job) = &mut self.auto_tag else {
+            return;
+        };
+        let Some(rx) = &job.rx else {
+            return;
+        };
+        let mut finished = false;
+        loop {
+            match rx.try_recv() {
+                Ok((path, guess)) => {
+                    job.done += 1;
+                    match guess {
+                        Ok(Some(category)) => {
+                            self.index_dirty |= self.index.suggest_tags(&path, &[category.tag()]);
+                        }
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!("auto-tag: {e}"),
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            job.rx = None;
+            self.save_sample_index();
+        }
+    }
+
+    /// Samples with suggested tags waiting, sorted by path.
+    pub fn tag_suggestions(&self) -> Vec<(&Path, &[String])> {
+        let mut suggestions: Vec<_> = self.index.suggestions().collect();
+        suggestions.sort_by(|a, b| a.0.cmp(b.0));
+        suggestions
+    }
+
+    /// `path`'s stars, from 1 to [`MAX_RATING`](punks_core::index::MAX_RATING).
+    pub fn rating(&self, path: &Path) -> Option<u8> {
+        self.index.rating(path)
+    }
+
+    /// Rate `path` from 1 to [`MAX_RATING`](punks_core::index::MAX_RATING)
+    /// stars, and save; `None` or 0 clears it. Search for `*4` to find the
+    /// samples rated four or more.
+    pub fn set_rating(&mut self, path: &Path, rating: Option<u8>) {
+        self.index.set_rating(path, rating);
+        self.index_dirty = true;
+        self.save_sample_index();
+    }
+
+    /// Rate the selected file, in the listing or the search results.
+    pub fn rate_selected(&mut self, rating: Option<u8>) {
+        let entry = if self.is_in_search_mode() {
+            self.search_selected()
+                .and_then(|i| self.search_results()?.get(i))
+        } else {
+            self.selected().and_then(|i| self.entries().get(i))
+        };
+        if let Some(path) = entry.filter(|e| !e.is_directory).map(|e| e.path.clone()) {
+            self.set_rating(&path, rating);
+        }
+    }
+
+    /// Tags confirmed for `path`.
+    pub fn sample_tags(&self, path: &Path) -> &[String] {
+        self.index.tags(path)
+    }
+
+    /// Tag `path` with `tag` (see [`punks_core::index::normalize_tag`]), and
+    /// save. Returns whether it's new to `path`.
+    pub fn add_sample_tag(&mut self, path: &Path, tag: &str) -> bool {
+        let added = self.index.add_tag(path, tag);
+        self.index_dirty |= added;
+        self.save_sample_index();
+        added
+    }
+
+    /// Take `tag` off `path`, and save. Returns whether it had it.
+    pub fn remove_sample_tag(&mut self, path: &Path, tag: &str) -> bool {
+        let removed = self.index.remove_tag(path, tag);
+        self.index_dirty |= removed;
+        self.save_sample_index();
+        removed
+    }
+
+    /// Every tag in use with how many samples carry it, by name; search
+    /// for `#tag` to browse one.
+    pub fn all_tags(&self) -> Vec<(&str, usize)> {
+        self.index.all_tags()
+    }
+
+    /// Accept the suggested tags of every sample in `paths`, and save.
+    pub fn confirm_tag_suggestions(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.index.confirm_suggestions(path);
+        }
+        self.index_dirty = true;
+        self.save_sample_index();
+    }
+
+    /// Turn down the suggested tags of every sample in `paths`, and save.
+    pub fn reject_tag_suggestions(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.index.reject_suggestions(path);
+        }
+        self.index_dirty = true;
+        self.save_sample_index();
+    }
+
+    // --- Watch folders ----------------------------------------------------------
+
+    /// Watch `folders` in the background: files that turn up in them are
+    /// classified, suggested a tag, and listed in
+    /// [`recently_added`](Self::recently_added). Replaces any folders
+    /// watched before.
+    pub fn set_watch_folders(&mut self, folders: Vec<WatchFolder>) {
+        self.watcher = None;
+        if !folders.is_empty() {
+            let known = self
+                .index
+                .recently_added()
+                .into_iter()
+                .map(|(path, _)| path.to_path_buf())
+                .collect();
+            let extensions = self.extensions().clone();
+            self.watcher = Some(Watcher::start(folders.clone(), known, extensions));
+        }
+        self.watch_folders = folders;
+    }
+
+    pub fn watch_folders(&self) -> &[WatchFolder] {
+        &self.watch_folders
+    }
+
+    /// Samples the watch folders turned up, newest first.
+    pub fn recently_added(&self) -> Vec<(&Path, SystemTime)> {
+        self.index.recently_added()
+    }
+
+    fn poll_watch(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        let arrivals = watcher.drain();
+        if arrivals.is_empty() {
+            return;
+        }
+        for arrival in arrivals {
+            tracing::debug!("watch folder: {} added", arrival.path.display());
+            self.index.mark_added(&arrival.path, arrival.at);
+            if let Some(category) = arrival.category {
+                self.index.suggest_tags(&arrival.path, &[category.tag()]);
+            }
+        }
+        self.index_dirty = true;
+        self.save_sample_index();
+    }
+
+    // --- Library index ------------------------------------------------------
+
+    /// Index `folders` in the background (see [`Library`]), replacing any
+    /// indexed before, and answer searches within them from the index.
+    pub fn set_library_folders(&mut self, folders: Vec<PathBuf>) {
+        self.library_folders = folders;
+        self.reindex_library();
+    }
+
+    pub fn library_folders(&self) -> &[PathBuf] {
+        &self.library_folders
+    }
+
+    /// Bring the library index up to date with the disk on a background
+    /// thread, re-reading only folders that changed. Restarts a refresh
+    /// already running.
+    pub fn reindex_library(&mut self) {
+        self.indexer = None;
+        if self.library_folders.is_empty() && self.library.roots().is_empty() {
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let mut library = self.library.clone();
+        let folders = self.library_folders.clone();
+        let file = self.library_file.clone();
+        let thread_stop = Arc::clone(&stop);
+        let spawned = std::thread::Builder::new()
+            .name("punks-index".into())
+            .spawn(move || {
+                for root in library.roots().to_vec() {
+                    if !folders.contains(&root) {
+                        library.forget(&root);
+                    }
+                }
+                for folder in &folders {
+                    match library.refresh(folder, &thread_stop) {
+                        Ok(stats) => tracing::debug!(
+                            "library: {} indexed, {} of {} folders read",
+                            folder.display(),
+                            stats.folders_read,
+                            stats.folders_read + stats.folders_unchanged,
+                        ),
+                        Err(_) if thread_stop.load(Ordering::Relaxed) => return,
+                        Err(e) => tracing::warn!("library: {e}"),
+                    }
+                }
+                if let Some(file) = &file {
+                    if let Err(e) = library.save(file) {
+                        tracing::warn!("failed to write {}: {e}", file.display());
+                    }
+                }
+                let _ = tx.send(library);
+            });
+        match spawned {
+            Ok(_) => self.indexer = Some(IndexJob { stop, rx }),
+            Err(e) => tracing::warn!("library index: {e}"),
+        }
+    }
+
+    /// Whether the library index is being refreshed.
+    pub fn is_indexing(&self) -> bool {
+        self.indexer.is_some()
+    }
+
+    /// The library index as of the last refresh.
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    fn poll_library(&mut self) {
+        let Some(job) = &self.indexer else {
+            return;
+        };
+        match job.rx.try_recv() {
+            Ok(library) => {
+                self.library = library;
+                self.indexer = None;
+            }
+            Err(mpsc::TryRecvError::Disconnected) => self.indexer = None,
+            Err(mpsc::TryRecvError::Empty) => {}
+        }
+    }
+
+    // --- Library integrity ----------------------------------------------------
+
+    /// Check `root` against its checksum manifest on a background thread. If
+    /// it has none yet, or `rebuild` is set, a fresh manifest is written
+    /// instead (accepting the current contents as good).
+    pub fn start_integrity_check(&mut self, root: &Path, rebuild: bool) {
+        if self.is_integrity_running() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let dir = root.to_path_buf();
+        let extensions = self.extensions().clone();
+        std::thread::spawn(move || {
+            use punks_core::integrity::{self, Manifest};
+            let on_file = |_: &Path| {
+                let _ = tx.send(IntegrityMsg::Hashing);
+            };
+            let existing = if rebuild {
+                Ok(None)
+            } else {
+                Manifest::load(&dir)
+            };
+            let outcome = match existing {
+                Ok(Some(manifest)) => {
+                    match integrity::verify(&dir, &manifest, &extensions, on_file) {
+                        Ok(report) => IntegrityOutcome::Verified(report),
+                        Err(e) => IntegrityOutcome::Failed(e.to_string()),
+                    }
+                }
+                Ok(None) => match integrity::build_manifest(&dir, &extensions, on_file)
+                    .and_then(|m| m.save(&dir).map(|()| m.files.len()))
+                {
+                    Ok(count) => IntegrityOutcome::ManifestWritten(count),
+                    Err(e) => IntegrityOutcome::Failed(e.to_string()),
+                },
+                Err(e) => IntegrityOutcome::Failed(e.to_string()),
+            };
+            if let IntegrityOutcome::Verified(report) = &outcome {
+                for path in &report.mismatched {
+                    tracing::warn!("checksum mismatch: {}", path.display());
+                }
+            }
+            let _ = tx.send(IntegrityMsg::Done(outcome));
+        });
+        self.integrity = Some(IntegrityJob {
+            root: root.to_path_buf(),
+            files_hashed: 0,
+            outcome: None,
+            rx: Some(rx),
+        });
+    }
+
+    pub fn is_integrity_running(&self) -> bool {
+        self.integrity.as_ref().is_some_and(|j| j.rx.is_some())
+    }
+
+    /// Root being (or last) checked and how many files have been hashed.
+    pub fn integrity_progress(&self) -> Option<(&Path, usize)> {
+        self.integrity
+            .as_ref()
+            .map(|j| (j.root.as_path(), j.files_hashed))
+    }
+
+    pub fn integrity_outcome(&self) -> Option<&IntegrityOutcome> {
+        self.integrity.as_ref().and_then(|j| j.outcome.as_ref())
+    }
+
+    // --- Duplicates -----------------------------------------------------------
+
+    /// Look for samples with the same contents on a background thread (see
+    /// [`punks_core::dedupe`]): across the library index, or everything under
+    /// the open folder when there are no library folders. Ignored while a
+    /// scan is still running.
+    pub fn start_duplicate_scan(&mut self) {
+        if self.is_duplicate_scan_running() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let extensions = self.extensions().clone();
+        let indexed: Vec<(PathBuf, u64)> = self
+            .library
+            .roots()
+            .iter()
+            .flat_map(|root| self.library.files_under(root, &extensions))
+            .filter(|e| !e.is_directory && e.region.is_none())
+            .map(|e| (e.path, e.size_bytes))
+            .collect();
+        let folder = self.current_directory().map(Path::to_path_buf);
+        let thread_stop = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            let files = if !indexed.is_empty() {
+                Ok(indexed)
+            } else if let Some(folder) = folder {
+                let options = ScanOptions {
+                    extensions,
+                    threads: Some(punks_core::scan::default_threads()),
+                    ..ScanOptions::default()
+                };
+                punks_core::scan_directory_iter(&folder, options)
+                    .map(|files| {
+                        files
+                            .flatten()
+                            .filter(|e| e.region.is_none())
+                            .map(|e| (e.path, e.size_bytes))
+                            .collect()
+                    })
+                    .map_err(|e| e.to_string())
+            } else {
+                Err("no library folders or open folder to look in".to_string())
+            };
+            let groups = files.and_then(|files| {
+                punks_core::dedupe::find_duplicates(files, &thread_stop, |_| {
+                    let _ = tx.send(DuplicateMsg::Hashing);
+                })
+                .map_err(|e| e.to_string())
+            });
+            if !thread_stop.load(Ordering::Relaxed) {
+                let _ = tx.send(DuplicateMsg::Done(groups));
+            }
+        });
+        self.duplicates = Some(DuplicateJob {
+            files_hashed: 0,
+            groups: None,
+            stop,
+            rx: Some(rx),
+        });
+    }
+
+    pub fn is_duplicate_scan_running(&self) -> bool {
+        self.duplicates.as_ref().is_some_and(|j| j.rx.is_some())
+    }
+
+    /// How many files the running (or last) scan has hashed.
+    pub fn duplicate_scan_progress(&self) -> Option<usize> {
+        self.duplicates.as_ref().map(|j| j.files_hashed)
+    }
+
+    /// The groups the last scan found, biggest waste first, or why it failed.
+    pub fn duplicates(&self) -> Option<Result<&[DuplicateGroup], &str>> {
+        self.duplicates
+            .as_ref()
+            .and_then(|j| j.groups.as_ref())
+            .map(|groups| groups.as_deref().map_err(String::as_str))
+    }
+
+    /// Delete the copy at `path` from a duplicate group like
+    /// [`delete_entry`](Self::delete_entry) does, and take it out of the
+    /// group; a group left with one copy is dropped.
+    pub fn trash_duplicate(&mut self, path: &Path) -> Result<Deleted, BrowserError> {
+        let deleted = self.delete_path(path)?;
+        if let Some(Ok(groups)) = self.duplicates.as_mut().and_then(|j| j.groups.as_mut()) {
+            for group in groups.iter_mut() {
+                group.paths.retain(|p| p != path);
+            }
+            groups.retain(|group| group.paths.len() > 1);
+        }
+        Ok(deleted)
+    }
+
+    // --- Scripts --------------------------------------------------------------
+
+    /// Run the rhai script at `file` on a background thread, with the
+    /// bindings from `punks-script`. The script also sees `folder`, the
+    /// folder being browsed, and `selected`, the selected entry's path (each
+    /// `()` when there's none). It tags, rates and searches a copy of the
+    /// sample index whose changes are applied here as they're made. Files it
+    /// changes are dropped from the caches, and the listing is refreshed and
+    /// the index saved when it ends. Ignored while a script is still running;
+    /// see [`Self::cancel_script`].
+    #[cfg(feature = "scripting")]
+    pub fn start_script(&mut self, file: &Path) {
+        if self.is_script_running() {
+            return;
+        }
+        let folder = self.current_directory().map(Path::to_path_buf);
+        let selected = self
+            .selected()
+            .and_then(|i| self.entries().get(i))
+            .map(|e| e.path.clone());
+        let extensions = self.extensions().clone();
+        let index = self.index.clone();
+        let script = file.to_path_buf();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let changed = tx.clone();
+            let mut engine = punks_script::engine(extensions, move |path| {
+                let _ = changed.send(ScriptMsg::Changed(path.to_path_buf()));
+            });
+            let printed = tx.clone();
+            engine.on_print(move |line| {
+                let _ = printed.send(ScriptMsg::Print(line.to_string()));
+            });
+            let edited = tx.clone();
+            punks_script::register_index(&mut engine, index, move |edit| {
+                let _ = edited.send(ScriptMsg::Edit(edit.clone()));
+            });
+            engine.on_progress(move |_| {
+                stopped
+                    .load(Ordering::Relaxed)
+                    .then_some(punks_script::rhai::Dynamic::UNIT)
+            });
+            let path_value = |p: Option<PathBuf>| {
+                p.map_or(punks_script::rhai::Dynamic::UNIT, |p| {
+                    p.to_string_lossy().into_owned().into()
+                })
+            };
+            let mut scope = punks_script::rhai::Scope::new();
+            scope.push_dynamic("folder", path_value(folder));
+            scope.push_dynamic("selected", path_value(selected));
+            let outcome = engine
+                .run_file_with_scope(&mut scope, script.clone())
+                .map_err(|e| match *e {
+                    punks_script::rhai::EvalAltResult::ErrorTerminated(..) => {
+                        "Cancelled.".to_string()
+                    }
+                    e => e.to_string(),
+                });
+            if let Err(e) = &outcome {
+                tracing::warn!("script {}: {e}", script.display());
+            }
+            let _ = tx.send(ScriptMsg::Done(outcome));
+        });
+        self.script = Some(ScriptJob {
+            file: file.to_path_buf(),
+            output: Vec::new(),
+            outcome: None,
+            stop,
+            rx: Some(rx),
+        });
+    }
+
+    #[cfg(feature = "scripting")]
+    pub fn is_script_running(&self) -> bool {
+        self.script.as_ref().is_some_and(|j| j.rx.is_some())
+    }
+
+    /// Stop the running script at its next statement. What it changed so far
+    /// stays changed; it ends with "Cancelled." as its error.
+    #[cfg(feature = "scripting")]
+    pub fn cancel_script(&self) {
+        if let Some(job) = &self.script {
+            job.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// The script running (or last run) and what it has printed so far.
+    #[cfg(feature = "scripting")]
+    pub fn script_output(&self) -> Option<(&Path, &[String])> {
+        self.script
+            .as_ref()
+            .map(|j| (j.file.as_path(), j.output.as_slice()))
     }
 
-    pub fn waveform_peaks(&self) -> Option<&WaveformPeaks> {
-        self.playback.waveform_peaks()
+    /// How the last script ended: `Err` holds the error, with its line.
+    #[cfg(feature = "scripting")]
+    pub fn script_outcome(&self) -> Option<&Result<(), String>> {
+        self.script.as_ref().and_then(|j| j.outcome.as_ref())
     }
 
-    /// Container metadata + preview info for the current track (global, like
-    /// playback). `None` when nothing is loaded.
-    pub fn current_track_info(&self) -> Option<&TrackInfo> {
-        self.playback.current_info()
+    // --- Folder sizes ---------------------------------------------------------
+
+    /// Total up `dir` (bytes and audio files, recursively) on a background
+    /// thread. Re-running a finished folder starts over; a running one is left
+    /// alone.
+    pub fn compute_folder_size(&mut self, dir: &Path) {
+        if self
+            .folder_sizes
+            .get(dir)
+            .is_some_and(|job| job.rx.is_some())
+        {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let root = dir.to_path_buf();
+        let extensions = self.extensions().clone();
+        std::thread::spawn(move || {
+            let result = punks_core::size::folder_size(&root, &extensions, |partial| {
+                let _ = tx.send(*partial);
+            });
+            match result {
+                Ok(size) => {
+                    let _ = tx.send(size);
+                }
+                Err(e) => tracing::warn!("folder size of {}: {e}", root.display()),
+            }
+        });
+        self.folder_sizes.insert(
+            dir.to_path_buf(),
+            SizeJob {
+                size: FolderSize::default(),
+                rx: Some(rx),
+            },
+        );
     }
 
-    /// Playable duration of the loaded clip, or `None` when nothing is loaded.
-    pub fn loaded_duration(&self) -> Option<std::time::Duration> {
-        self.playback.loaded_duration()
+    /// Totals for `dir` so far and whether they're final, if a size was ever
+    /// requested for it.
+    pub fn folder_size(&self, dir: &Path) -> Option<(FolderSize, bool)> {
+        self.folder_sizes
+            .get(dir)
+            .map(|job| (job.size, job.rx.is_none()))
     }
 
-    /// Seek to `fraction` (0..1) of the loaded clip and play from there.
-    pub fn seek_fraction(&self, fraction: f32) {
-        self.playback.seek_fraction(fraction);
+    // --- Overviews -------------------------------------------------------------
+
+    /// Queue waveform overviews for the rows in `visible` (indices into
+    /// [`entries`](Self::entries)) and the selection, which goes first. Work
+    /// for rows outside them is dropped, so call this each frame with what's
+    /// on screen.
+    pub fn request_overviews(&mut self, visible: Range<usize>) {
+        let wanted = self.row_requests(&visible, 0, |p| self.overview_cache.contains_key(p));
+        schedule(&self.overviews, wanted);
     }
 
-    pub fn set_volume(&self, v: f32) {
-        self.playback.set_volume(v);
+    pub fn reapeaks(&self) -> bool {
+        self.reapeaks.load(Ordering::Relaxed)
     }
 
-    pub fn volume(&self) -> f32 {
-        self.playback.volume()
+    /// Read and write REAPER `.reapeaks` files for waveforms and overviews.
+    pub fn set_reapeaks(&mut self, enabled: bool) {
+        self.reapeaks.store(enabled, Ordering::Relaxed);
+        self.playback.set_reapeaks(enabled);
     }
 
-    pub fn search(&mut self, query: &str) {
-        let root = match self.current_directory() {
-            Some(p) => p.to_path_buf(),
-            None => return,
-        };
-        let query = query.to_string();
-        let (tx, rx) = mpsc::channel();
-        let thread_query = query.clone();
-        std::thread::spawn(move || {
-            let results = punks_core::search_directory(&root, &thread_query, SUPPORTED_EXTENSIONS)
-                .unwrap_or_else(|e| {
-                    log::warn!("search in {}: {e}", root.display());
-                    Vec::new()
-                });
-            let _ = tx.send(results);
-        });
-        let tab = self.active_mut();
-        tab.search_rx = Some(rx);
-        tab.search_results = None;
-        tab.search_selected = None;
-        tab.search_query = query;
+    /// Audition long files from their first `secs` seconds while the rest
+    /// decodes; `None` (or a non-positive value) waits for the full decode.
+    pub fn set_quick_audition(&mut self, secs: Option<f32>) {
+        let window = secs
+            .filter(|s| s.is_finite() && *s > 0.0)
+            .map(std::time::Duration::from_secs_f32);
+        self.playback.set_quick_audition(window);
     }
 
-    pub fn clear_search(&mut self) {
-        let tab = self.active_mut();
-        tab.search_results = None;
-        tab.search_rx = None;
-        tab.search_selected = None;
-        tab.search_query = String::new();
+    /// Refuse to load samples whose decoded audio would take more than `mb`
+    /// MiB; `None` for no cap.
+    pub fn set_memory_budget(&mut self, mb: Option<u32>) {
+        self.playback
+            .set_memory_budget(mb.map(|mb| u64::from(mb) << 20));
     }
 
-    pub fn is_searching(&self) -> bool {
-        self.active().search_rx.is_some()
+    /// `path`'s overview, once [`request_overviews`](Self::request_overviews)
+    /// has produced it.
+    pub fn overview(&self, path: &Path) -> Option<&WaveformPeaks> {
+        self.overview_cache.get(path).and_then(Option::as_ref)
     }
 
-    pub fn is_in_search_mode(&self) -> bool {
-        self.active().search_results.is_some() || self.active().search_rx.is_some()
+    fn poll_overviews(&mut self) {
+        while let Some((path, result)) = self.overviews.try_recv() {
+            let peaks = result
+                .map_err(|e| tracing::debug!("overview of {}: {e}", path.display()))
+                .ok();
+            self.overview_cache.insert(path, peaks);
+        }
     }
 
-    pub fn search_results(&self) -> Option<&[FileEntry]> {
-        self.active().search_results.as_deref()
+    // --- File info ------------------------------------------------------------
+
+    /// Queue header probes (length, format, metadata) for the selection, then
+    /// the rows in `visible`, then a screenful either side of them. Rows
+    /// outside that are dropped, and rows leaving the screen are demoted, so
+    /// info fills in where the user is looking. Call each frame.
+    /// Sorted by length, every row is probed so the order settles.
+    pub fn request_metadata(&mut self, visible: Range<usize>) {
+        let margin = if self.sort_mode == SortMode::Duration {
+            usize::MAX
+        } else {
+            visible.len()
+        };
+        let wanted = self.row_requests(&visible, margin, |p| self.metadata_cache.contains_key(p));
+        schedule(&self.metadata, wanted);
     }
 
-    pub fn search_selected(&self) -> Option<usize> {
-        self.active().search_selected
+    /// `path`'s probed info, once [`request_metadata`](Self::request_metadata)
+    /// has produced it.
+    pub fn file_info(&self, path: &Path) -> Option<&FileInfo> {
+        self.metadata_cache.get(path).and_then(Option::as_ref)
     }
 
-    pub fn select_search_result(&mut self, index: usize) {
-        let valid = self
-            .active()
-            .search_results
-            .as_ref()
-            .is_some_and(|r| index < r.len());
-        if valid {
-            self.active_mut().search_selected = Some(index);
+    fn poll_metadata(&mut self) {
+        let mut probed = false;
+        while let Some((path, result)) = self.metadata.try_recv() {
+            let info = result
+                .map_err(|e| tracing::debug!("probing {}: {e}", path.display()))
+                .ok();
+            self.metadata_cache.insert(path, info);
+            probed = true;
+        }
+        if probed && self.sort_mode == SortMode::Duration {
+            self.arrange(self.active_tab);
         }
     }
 
+    /// Playable rows worth background work, and how urgently: the selection,
+    /// then `visible`, then `prefetch` rows either side. Rows `done` says
+    /// already have a result are left out.
+    fn row_requests(
+        &self,
+        visible: &Range<usize>,
+        prefetch: usize,
+        done: impl Fn(&Path) -> bool,
+    ) -> Vec<(PathBuf, Priority)> {
+        let selected = self.selected();
+        let nearby = visible.start.saturating_sub(prefetch)..visible.end.saturating_add(prefetch);
+        self.entries()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| {
+                let priority = if selected == Some(i) {
+                    Priority::Selected
+                } else if visible.contains(&i) {
+                    Priority::Visible
+                } else if nearby.contains(&i) {
+                    Priority::Background
+                } else {
+                    return None;
+                };
+                (self.is_playable(e) && !done(&e.path)).then(|| (e.path.clone(), priority))
+            })
+            .collect()
+    }
+
     // --- Tab management ---------------------------------------------------
 
     /// Create a new tab and make it active. `start` selects its initial
@@ -379,13 +3640,11 @@ impl SampleBrowser {
     }
 
     /// Title for the tab at `index`: its current directory's name, falling
-    /// back to the full path, or "New Tab" when no folder is open.
+    /// back to the root (`C:\`, `\\server\share`), or "New Tab" when no
+    /// folder is open.
     pub fn tab_title(&self, index: usize) -> String {
         match self.tabs.get(index).and_then(|t| t.history.last()) {
-            Some(p) => p
-                .file_name()
-                .map(|n| n.to_string_lossy().into_owned())
-                .unwrap_or_else(|| p.to_string_lossy().into_owned()),
+            Some(p) => punks_core::paths::display_name(p),
             None => "New Tab".to_string(),
         }
     }
@@ -397,6 +3656,81 @@ impl SampleBrowser {
     }
 }
 
+/// A file name for `entry` written out as a WAV: its name, made safe for
+/// any file system, ending in `.wav`.
+pub fn export_name(entry: &FileEntry) -> String {
+    let mut name: String = entry
+        .name
+        .chars()
+        .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    if !name.to_lowercase().ends_with(".wav") {
+        name.push_str(".wav");
+    }
+    name
+}
+
+/// A file name for `start..end` of `path`, saved as a WAV of its own: the
+/// file's stem and the span in seconds, e.g. `Break 1.250-2.500.wav`.
+pub fn slice_name(path: &Path, start: Duration, end: Duration) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!(
+        "{stem} {:.3}-{:.3}.wav",
+        start.as_secs_f64(),
+        end.as_secs_f64()
+    )
+}
+
+/// The engine on the configured output device, falling back to the system
+/// default when that device is gone.
+fn open_engine(device: Option<&str>) -> Result<PlaybackEngine, PlaybackError> {
+    if let Some(name) = device {
+        match PlaybackEngine::builder().device(name).build() {
+            Ok(engine) => return Ok(engine),
+            Err(e) => tracing::warn!("{e}; using the default output"),
+        }
+    }
+    PlaybackEngine::new()
+}
+
+/// Keep the `results` in `kept` (see [`SampleBrowser::search_filter`]),
+/// best rated first and otherwise in the order they came.
+fn apply_search_filter(results: &mut Vec<FileEntry>, kept: Option<&HashMap<PathBuf, u8>>) {
+    if let Some(kept) = kept {
+        results.retain(|entry| kept.contains_key(&entry.path));
+        results.sort_by_key(|entry| std::cmp::Reverse(kept[&entry.path]));
+    }
+}
+
+/// Hand `wanted` to `pool`, dropping whatever it had queued that isn't wanted
+/// any more.
+fn schedule<T: Send + 'static>(pool: &WorkerPool<PathBuf, T>, wanted: Vec<(PathBuf, Priority)>) {
+    let keep: HashSet<&PathBuf> = wanted.iter().map(|(p, _)| p).collect();
+    pool.retain(|p| keep.contains(p));
+    for (path, priority) in wanted {
+        pool.request(path, priority);
+    }
+}
+
+/// Decodes for list overviews run on a few threads, leaving cores free for
+/// playback decodes and the UI.
+fn overview_pool(
+    reapeaks: Arc<AtomicBool>,
+) -> WorkerPool<PathBuf, Result<WaveformPeaks, PlaybackError>> {
+    let threads = std::thread::available_parallelism().map_or(2, |n| n.get() / 2);
+    WorkerPool::new(
+        threads.clamp(1, 4),
+        OVERVIEW_QUEUE,
+        move |path: &PathBuf| {
+            let reapeaks = reapeaks.load(Ordering::Relaxed);
+            punks_playback::peaks::file_peaks(path, OVERVIEW_BUCKETS, reapeaks)
+        },
+    )
+}
+
 /// Active-tab index after removing the tab at `removed`. `new_len` is the tab
 /// count *after* removal (>= 1). Closing a tab left of the active one shifts it
 /// down; closing the active tab focuses the tab that slid into its slot,
@@ -428,9 +3762,926 @@ fn adjust_active_after_reorder(active: usize, from: usize, to: usize) -> usize {
     }
 }
 
+impl Drop for SampleBrowser {
+    /// Log the sample still playing, so quitting doesn't lose it.
+    fn drop(&mut self) {
+        self.finish_audition();
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{adjust_active_after_close, adjust_active_after_reorder};
+    use super::{
+        adjust_active_after_close, adjust_active_after_reorder, BrowserEvent, ExportSpec, LoopSync,
+        MidiTrigger, Normalize, NullPlayback, PadSource, PlaybackStatus, SampleBrowserBuilder,
+        SortMode, TempoSync, WatchFolder, MAX_LAYERS,
+    };
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::Path;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[test]
+    fn browses_and_plays_without_an_audio_device() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kick.wav"), b"data").unwrap();
+        fs::write(dir.path().join(".snare.wav"), b"data").unwrap();
+
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        assert_eq!(browser.entries().len(), 1);
+        browser.select(0);
+        browser.play_selected();
+        assert!(matches!(
+            browser.playback_status(),
+            PlaybackStatus::Playing { file, .. } if file == dir.path().join("kick.wav")
+        ));
+        browser.stop();
+        assert!(matches!(browser.playback_status(), PlaybackStatus::Idle));
+
+        let hidden = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .show_hidden(true)
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        assert_eq!(hidden.entries().len(), 2);
+    }
+
+    #[test]
+    fn switching_output_device_keeps_playing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kick.wav"), b"data").unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.select(0);
+        browser.play_selected();
+
+        browser.switch_output_device(Some("Interface 3-4")).unwrap();
+        assert_eq!(browser.output_device(), Some("Interface 3-4"));
+        assert_eq!(browser.selected(), Some(0));
+        assert!(matches!(
+            browser.playback_status(),
+            PlaybackStatus::Playing { .. }
+        ));
+        browser.switch_output_device(None).unwrap();
+        assert_eq!(browser.output_device(), None);
+    }
+
+    #[test]
+    fn restores_the_last_selection_without_playing_it() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.wav", "b.wav"] {
+            fs::write(dir.path().join(name), b"data").unwrap();
+        }
+        let cfg = super::PunksConfig {
+            last_directory: Some(dir.path().to_path_buf()),
+            last_selected: Some(dir.path().join("b.wav")),
+            ..Default::default()
+        };
+        let mut browser = SampleBrowserBuilder::from_config(&cfg)
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.poll();
+        assert_eq!(browser.selected(), Some(1));
+        assert!(matches!(browser.playback_status(), PlaybackStatus::Idle));
+    }
+
+    #[test]
+    fn rate_is_the_pitch_as_a_speed() {
+        let browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.set_rate(0.5);
+        assert!((browser.pitch() + 12.0).abs() < 1e-4);
+        browser.set_pitch(7.0);
+        assert!((browser.rate() - 1.4983).abs() < 1e-4);
+        browser.set_rate(10.0);
+        assert!((browser.rate() - punks_playback::MAX_RATE).abs() < 1e-4);
+    }
+
+    #[test]
+    fn auto_preview_plays_where_the_selection_rests() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.wav", "b.wav", "c.wav"] {
+            fs::write(dir.path().join(name), b"data").unwrap();
+        }
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let playing = |browser: &crate::SampleBrowser| match browser.playback_status() {
+            PlaybackStatus::Playing { file, .. } => file.file_name().map(|n| n.to_owned()),
+            _ => None,
+        };
+
+        browser.select(0);
+        browser.poll();
+        assert_eq!(playing(&browser).unwrap(), "a.wav");
+
+        // Stepping on quickly waits for the selection to settle.
+        browser.select(1);
+        browser.select(2);
+        browser.poll();
+        assert_eq!(playing(&browser).unwrap(), "a.wav");
+        std::thread::sleep(super::AUTO_PREVIEW_DELAY);
+        browser.poll();
+        assert_eq!(playing(&browser).unwrap(), "c.wav");
+
+        browser.stop();
+        browser.set_auto_preview(false);
+        std::thread::sleep(super::AUTO_PREVIEW_DELAY);
+        browser.select(0);
+        browser.poll();
+        assert_eq!(playing(&browser), None);
+    }
+
+    #[test]
+    fn remembers_sample_gain_between_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        fs::write(&kick, b"data").unwrap();
+        let index = dir.path().join("index.json");
+        let open = || {
+            SampleBrowserBuilder::new()
+                .start_directory(dir.path())
+                .sample_index(&index)
+                .playback(NullPlayback::new())
+                .build()
+                .unwrap()
+        };
+
+        let mut browser = open();
+        browser.play_file(&kick);
+        browser.set_sample_gain_db(&kick, 4.5);
+        browser.save_sample_index();
+        assert_eq!(open().sample_gain_db(&kick), 4.5);
+    }
+
+    #[test]
+    fn favorites_persist_and_open_where_they_are() {
+        let dir = tempfile::tempdir().unwrap();
+        let drums = dir.path().join("Drums");
+        let kick = drums.join("kick.wav");
+        fs::create_dir(&drums).unwrap();
+        fs::write(drums.join("clap.wav"), b"data").unwrap();
+        fs::write(&kick, b"data").unwrap();
+        let file = dir.path().join("favorites.json");
+        let open = || {
+            SampleBrowserBuilder::new()
+                .start_directory(dir.path())
+                .favorites(&file)
+                .playback(NullPlayback::new())
+                .build()
+                .unwrap()
+        };
+
+        let mut browser = open();
+        browser.add_favorite(&drums);
+        browser.add_favorite(&kick);
+        browser.add_favorite(&drums);
+        let mut browser = open();
+        assert_eq!(browser.favorites(), [drums.clone(), kick.clone()]);
+
+        browser.open_favorite(&kick).unwrap();
+        assert_eq!(browser.current_directory(), Some(drums.as_path()));
+        assert_eq!(browser.selected(), Some(1));
+        browser.remove_favorite(&drums);
+        assert!(!open().is_favorite(&drums));
+    }
+
+    #[test]
+    fn midi_favorite_toggles_the_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        fs::write(&kick, b"data").unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.select(0);
+
+        browser.apply_midi_action(crate::midi::MidiAction::Favorite);
+        assert!(browser.is_favorite(&kick));
+        browser.apply_midi_action(crate::midi::MidiAction::Favorite);
+        assert!(!browser.is_favorite(&kick));
+    }
+
+    #[test]
+    fn collections_persist_and_play_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("collections.json");
+        let [kick, snare, hat] =
+            ["a/kick.wav", "b/snare.wav", "c/hat.wav"].map(|p| dir.path().join(p));
+        let open = || {
+            SampleBrowserBuilder::new()
+                .collections(&file)
+                .playback(NullPlayback::new())
+                .build()
+                .unwrap()
+        };
+
+        let mut browser = open();
+        assert!(browser.create_collection("Track 12 drums"));
+        assert!(!browser.add_to_collection("Vox", &kick));
+        for path in [&kick, &snare, &hat] {
+            assert!(browser.add_to_collection("Track 12 drums", path));
+        }
+        assert!(browser.reorder_collection("Track 12 drums", 2, 1));
+        drop(browser);
+
+        let mut browser = open();
+        assert!(browser.rename_collection("Track 12 drums", "Drums"));
+        let drums = browser.collection("Drums").unwrap();
+        assert_eq!(drums.samples(), [kick.clone(), hat.clone(), snare.clone()]);
+
+        browser.play_collection("Drums", 1);
+        browser.poll();
+        assert_eq!(browser.collection_playing(), Some(("Drums", 1)));
+        assert!(browser.play_collection_next());
+        assert_eq!(browser.collection_playing(), Some(("Drums", 2)));
+        assert!(matches!(
+            browser.playback_status(),
+            PlaybackStatus::Playing { file, .. } if file == snare
+        ));
+        assert!(!browser.play_collection_next());
+        assert_eq!(browser.collection_playing(), None);
+
+        // Playing anything else stops playing through.
+        browser.play_collection("Drums", 0);
+        browser.play_file(&hat);
+        browser.poll();
+        assert_eq!(browser.collection_playing(), None);
+
+        assert!(browser.remove_from_collection("Drums", 0));
+        assert!(browser.delete_collection("Drums"));
+        assert!(open().collections().is_empty());
+    }
+
+    #[test]
+    fn pads_play_favorites_and_recent_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        let snare = dir.path().join("snare.wav");
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let pad = |note| MidiTrigger::Note { channel: 9, note };
+        browser.add_favorite(dir.path());
+        browser.add_favorite(&kick);
+        browser.set_pad_source(PadSource::Favorites);
+        browser.add_favorite(&snare);
+        assert_eq!(browser.pads(), [kick.clone(), snare.clone()]);
+
+        browser.handle_midi(pad(37));
+        browser.poll();
+        assert_eq!(browser.loaded_clip(), Some((snare.as_path(), None)));
+        // Past the last sample, and below the first pad.
+        browser.handle_midi(pad(38));
+        browser.handle_midi(pad(35));
+        assert_eq!(browser.loaded_clip(), Some((snare.as_path(), None)));
+
+        // Recent samples stay put until refreshed.
+        browser.play_file(&kick);
+        browser.poll();
+        browser.set_pad_source(PadSource::Recent);
+        assert_eq!(browser.pads(), [snare.as_path()]);
+        browser.handle_midi(pad(36));
+        browser.poll();
+        assert_eq!(browser.pads(), [snare.as_path()]);
+        browser.refresh_pads();
+        assert_eq!(browser.pads(), [kick, snare]);
+    }
+
+    #[test]
+    fn logs_each_audition_once_it_stops() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        let snare = dir.path().join("snare.wav");
+        let history = dir.path().join("history.jsonl");
+        let open = || {
+            SampleBrowserBuilder::new()
+                .audition_history(&history)
+                .playback(NullPlayback::new())
+                .build()
+                .unwrap()
+        };
+
+        let mut browser = open();
+        browser.play_file(&kick);
+        browser.poll();
+        browser.poll();
+        assert!(browser.audition_history().is_empty());
+        browser.play_file(&snare);
+        browser.poll();
+        let heard: Vec<_> = browser.audition_history().recent().collect();
+        assert_eq!(heard.len(), 1);
+        assert_eq!(heard[0].path, kick);
+        drop(browser);
+
+        let mut browser = open();
+        let paths: Vec<_> = browser
+            .audition_history()
+            .recent()
+            .map(|a| a.path.clone())
+            .collect();
+        assert_eq!(paths, [snare.clone(), kick.clone()]);
+
+        // Recent samples leave out the repeats.
+        browser.play_file(&kick);
+        browser.poll();
+        browser.stop();
+        browser.poll();
+        assert_eq!(browser.audition_history().len(), 3);
+        assert_eq!(browser.recent(), [kick, snare]);
+    }
+
+    #[test]
+    fn stacks_samples_for_layered_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        let hat = dir.path().join("hat.wav");
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.set_sample_gain_db(&hat, -6.0);
+
+        assert!(browser.toggle_layer(&kick));
+        assert!(browser.toggle_layer(&hat));
+        assert!(!browser.toggle_layer(&dir.path().join("notes.txt")));
+        assert_eq!(browser.layers()[1].gain_db, -6.0);
+        browser.set_layer_gain_db(0, 3.0);
+        assert_eq!(browser.layers()[0].gain_db, 3.0);
+
+        browser.play_layers();
+        assert!(browser.layers_playing());
+        browser.play_file(&kick);
+        assert!(!browser.layers_playing());
+
+        assert!(!browser.toggle_layer(&kick));
+        assert_eq!(browser.layers().len(), 1);
+        for i in 0..MAX_LAYERS {
+            browser.toggle_layer(&dir.path().join(format!("{i}.wav")));
+        }
+        assert_eq!(browser.layers().len(), MAX_LAYERS);
+    }
+
+    #[test]
+    fn polyphonic_preview_leaves_samples_ringing() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        let snare = dir.path().join("snare.wav");
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.play_file(&kick);
+        browser.play_file(&snare);
+        assert!(browser.voices().is_empty());
+
+        browser.set_polyphonic(true);
+        browser.play_file(&kick);
+        browser.play_file(&snare);
+        let voices = browser.voices();
+        let paths: Vec<_> = voices.iter().map(|v| v.path.as_path()).collect();
+        assert_eq!(paths, [snare.as_path(), kick.as_path()]);
+        browser.stop_voice(voices[0].id);
+        assert_eq!(browser.voices().len(), 1);
+
+        browser.stop();
+        assert!(browser.voices().is_empty());
+    }
+
+    #[test]
+    fn the_ab_loop_saves_as_a_wav_of_its_own() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("break.wav");
+        write_silence(&source, 2.0);
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let spec = ExportSpec::default();
+        assert!(browser.save_loop(None, &spec).is_err());
+
+        browser.play_file(&source);
+        browser.set_loop_region(Duration::from_millis(500), Duration::from_secs(1));
+        let done = browser.save_loop(None, &spec).unwrap();
+        assert_eq!(done.output, dir.path().join("break 0.500-1.000.wav"));
+        let info = punks_playback::probe(&done.output).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_millis(500)));
+
+        let chops = dir.path().join("chops");
+        let done = browser.save_loop(Some(&chops), &spec).unwrap();
+        assert_eq!(done.output, chops.join("break 0.500-1.000.wav"));
+    }
+
+    #[test]
+    fn plays_at_a_one_off_gain_or_the_remembered_trim() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        write_silence(&kick, 0.1);
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.set_sample_gain_db(&kick, -6.0);
+
+        browser.play_file_with_gain(&kick, -20.0);
+        assert!((browser.playback.gain() - 0.1).abs() < 1e-4);
+        browser.play_file(&kick);
+        assert!((browser.playback.gain() - 0.501).abs() < 1e-3);
+    }
+
+    #[test]
+    fn exports_bake_in_the_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let spec = ExportSpec::default();
+        assert_eq!(browser.baked_spec(&kick, None, &spec), spec);
+
+        browser.set_sample_gain_db(&kick, -3.0);
+        browser.set_rate(2.0);
+        browser.set_loudness_target(Some(-18.0));
+        let baked = browser.baked_spec(&kick, None, &spec);
+        assert_eq!((baked.gain_db, baked.speed), (-3.0, 2.0));
+        assert_eq!(baked.normalize, Normalize::Loudness { target_lufs: -18.0 });
+
+        // Normalizing asked for wins over the loudness target.
+        let peak = ExportSpec {
+            normalize: Normalize::Peak { target_dbfs: -1.0 },
+            ..spec
+        };
+        let baked = browser.baked_spec(&kick, None, &peak);
+        assert_eq!(baked.normalize, peak.normalize);
+    }
+
+    /// `secs` of silence as an 8 kHz mono 16-bit WAV.
+    fn write_silence(path: &Path, secs: f32) {
+        let data_len = (secs * 8000.0) as u32 * 2;
+        let mut wav = Vec::new();
+        wav.extend(b"RIFF");
+        wav.extend((36 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(16000u32.to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        fs::write(path, wav).unwrap();
+    }
+
+    #[test]
+    fn syncs_loops_to_the_metronome() {
+        let dir = tempfile::tempdir().unwrap();
+        let loops = dir.path().join("Loops");
+        fs::create_dir(&loops).unwrap();
+        // Two bars at 100 bpm.
+        let groove = loops.join("groove_100bpm.wav");
+        write_silence(&groove, 4.8);
+        let kick = dir.path().join("kick.wav");
+        write_silence(&kick, 0.5);
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        assert_eq!(browser.loop_tempo(&kick), None);
+
+        browser.set_metronome_bpm(120.0);
+        browser.set_metronome_on(true);
+        assert_eq!(browser.playback.metronome(), Some(120.0));
+        browser.set_loop_sync(LoopSync::Stretch);
+        browser.play_file(&groove);
+        let stretched = TempoSync {
+            beats: 8.0,
+            stretch: 1.2,
+        };
+        assert_eq!(browser.playback.tempo_sync(), Some(stretched));
+
+        browser.set_loop_sync(LoopSync::Align);
+        let aligned = TempoSync {
+            beats: 8.0,
+            stretch: 1.0,
+        };
+        assert_eq!(browser.playback.tempo_sync(), Some(aligned));
+
+        // One-shots play as they are.
+        browser.play_file(&kick);
+        assert_eq!(browser.playback.tempo_sync(), None);
+    }
+
+    #[test]
+    fn lists_files_that_land_in_watch_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let downloads = dir.path().join("Downloads");
+        fs::create_dir(&downloads).unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.set_watch_folders(vec![WatchFolder {
+            path: downloads.clone(),
+            since: 0,
+        }]);
+
+        // Settled already, so it's picked up on the first pass.
+        let kick = downloads.join("kick.wav");
+        fs::write(&kick, b"data").unwrap();
+        let settled = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&kick)
+            .unwrap()
+            .set_modified(settled)
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while browser.recently_added().is_empty() && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let added: Vec<_> = browser
+            .recently_added()
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect();
+        assert_eq!(added, [kick.as_path()]);
+    }
+
+    #[test]
+    fn searches_library_folders_from_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let samples = dir.path().join("Samples");
+        fs::create_dir_all(samples.join("Drums")).unwrap();
+        fs::write(samples.join("Drums/kick.wav"), b"data").unwrap();
+        let file = dir.path().join("library.json");
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(&samples)
+            .playback(NullPlayback::new())
+            .library_index(&file)
+            .build()
+            .unwrap();
+        browser.set_library_folders(vec![samples.clone()]);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while browser.is_indexing() && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(browser.library().len(), 1);
+        assert!(file.exists());
+
+        // Not on disk yet as far as the index knows, so not found.
+        fs::write(samples.join("kick_2.wav"), b"data").unwrap();
+        browser.search("KICK");
+        assert!(!browser.is_searching());
+        let found: Vec<_> = browser
+            .search_results()
+            .unwrap()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(found, ["kick.wav"]);
+    }
+
+    #[test]
+    fn finds_duplicates_across_the_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let samples = dir.path().join("Samples");
+        fs::create_dir_all(samples.join("Pack A")).unwrap();
+        fs::create_dir_all(samples.join("Pack B")).unwrap();
+        let [clap, copy, snap] =
+            ["Pack A/909 clap.wav", "Pack B/CLAP_01.wav", "snap.wav"].map(|p| samples.join(p));
+        fs::write(&clap, b"clap").unwrap();
+        fs::write(&copy, b"clap").unwrap();
+        fs::write(&snap, b"snap").unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(&samples)
+            .playback(NullPlayback::new())
+            .allow_permanent_delete(true)
+            .build()
+            .unwrap();
+        browser.set_library_folders(vec![samples.clone()]);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while browser.is_indexing() && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        browser.start_duplicate_scan();
+        while browser.is_duplicate_scan_running() && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(browser.duplicate_scan_progress(), Some(3));
+        let groups = browser.duplicates().unwrap().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].paths, [clap.clone(), copy.clone()]);
+
+        // Down to one copy, it's no longer a duplicate.
+        browser.trash_duplicate(&copy).unwrap();
+        assert!(!copy.exists());
+        assert!(browser.duplicates().unwrap().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn scripts_tag_the_library_until_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("808 kick.wav");
+        fs::write(&kick, b"data").unwrap();
+        let script = dir.path().join("forever.rhai");
+        fs::write(
+            &script,
+            "tag(selected, \"808\"); print(\"tagged\"); loop {}",
+        )
+        .unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.select(0);
+
+        browser.start_script(&script);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while browser.script_output().unwrap().1.is_empty() && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(browser.is_script_running());
+        browser.cancel_script();
+        while browser.is_script_running() && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(
+            browser.script_outcome(),
+            Some(&Err("Cancelled.".to_string()))
+        );
+        assert_eq!(browser.sample_index().tags(&kick), ["808"]);
+    }
+
+    #[test]
+    fn hash_and_star_words_search_by_tag_and_rating() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["kick dark.wav", "kick bright.wav", "pad.wav"] {
+            fs::write(dir.path().join(name), b"data").unwrap();
+        }
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let path = |b: &crate::SampleBrowser, name: &str| {
+            b.entries()
+                .iter()
+                .find(|e| e.name == name)
+                .unwrap()
+                .path
+                .clone()
+        };
+        let (dark_kick, pad) = (path(&browser, "kick dark.wav"), path(&browser, "pad.wav"));
+        assert!(browser.add_sample_tag(&dark_kick, "Dark"));
+        assert!(browser.add_sample_tag(&pad, "dark"));
+        assert!(!browser.add_sample_tag(&pad, "dark "));
+        assert_eq!(browser.all_tags(), [("dark", 2)]);
+
+        let found = |browser: &mut crate::SampleBrowser, query: &str| -> Vec<String> {
+            browser.search(query);
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while browser.is_searching() && Instant::now() < deadline {
+                browser.poll();
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let results = browser.search_results().unwrap();
+            results.iter().map(|e| e.name.clone()).collect()
+        };
+        assert_eq!(found(&mut browser, "#dark"), ["kick dark.wav", "pad.wav"]);
+        assert_eq!(found(&mut browser, "kick #dark"), ["kick dark.wav"]);
+        browser.set_rating(&pad, Some(4));
+        browser.clear_search();
+        browser.select(0);
+        browser.rate_selected(Some(2));
+        assert_eq!(browser.rating(&path(&browser, "kick bright.wav")), Some(2));
+        assert_eq!(
+            found(&mut browser, "#dark"),
+            ["pad.wav", "kick dark.wav"],
+            "best rated first"
+        );
+        assert_eq!(found(&mut browser, "*2"), ["pad.wav", "kick bright.wav"]);
+        assert_eq!(found(&mut browser, "*3 pad"), ["pad.wav"]);
+        assert!(browser.remove_sample_tag(&pad, "dark"));
+        assert_eq!(browser.all_tags(), [("dark", 1)]);
+    }
+
+    #[test]
+    fn filter_narrows_files_but_keeps_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Loops")).unwrap();
+        for name in ["Kick 01.wav", "kick 02.wav", "snare.wav"] {
+            fs::write(dir.path().join(name), b"data").unwrap();
+        }
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let names = |b: &crate::SampleBrowser| -> Vec<String> {
+            b.entries().iter().map(|e| e.name.clone()).collect()
+        };
+        browser.select(2);
+
+        browser.set_filter("KICK");
+        assert_eq!(names(&browser), ["Loops", "Kick 01.wav", "kick 02.wav"]);
+        assert_eq!(browser.selected(), Some(2), "kick 02.wav stays selected");
+        browser.set_filter("snare");
+        assert_eq!(names(&browser), ["Loops", "snare.wav"]);
+        assert_eq!(browser.selected(), None);
+
+        browser.navigate_into(0).unwrap();
+        assert_eq!(browser.filter(), "");
+        browser.navigate_up().unwrap();
+        assert_eq!(browser.entries().len(), 4);
+    }
+
+    #[test]
+    fn sorting_reorders_files_and_follows_the_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Loops")).unwrap();
+        for (name, size) in [("a.wav", 30), ("b.aif", 10), ("c.wav", 20)] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let names = |b: &crate::SampleBrowser| -> Vec<String> {
+            b.entries().iter().map(|e| e.name.clone()).collect()
+        };
+        browser.select(1);
+
+        browser.set_sort_mode(SortMode::Size, true);
+        assert_eq!(names(&browser), ["Loops", "a.wav", "c.wav", "b.aif"]);
+        assert_eq!(browser.selected(), Some(1), "a.wav stays selected");
+        browser.set_sort_mode(SortMode::Extension, false);
+        assert_eq!(names(&browser), ["Loops", "b.aif", "a.wav", "c.wav"]);
+        browser.set_filter("wav");
+        assert_eq!(names(&browser), ["Loops", "a.wav", "c.wav"]);
+
+        browser.navigate_into(0).unwrap();
+        browser.navigate_up().unwrap();
+        assert_eq!(names(&browser), ["Loops", "b.aif", "a.wav", "c.wav"]);
+        browser.set_sort_mode(SortMode::Name, true);
+        assert_eq!(names(&browser), ["Loops", "c.wav", "b.aif", "a.wav"]);
+    }
+
+    #[test]
+    fn goes_back_and_forward_through_the_folders_visited() {
+        let dir = tempfile::tempdir().unwrap();
+        let drums = dir.path().join("Drums");
+        let kicks = drums.join("Kicks");
+        fs::create_dir_all(&kicks).unwrap();
+        fs::write(dir.path().join("a.wav"), b"data").unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        assert!(!browser.can_go_back());
+        browser.select(1);
+        browser.navigate_into(0).unwrap();
+        browser.navigate_into(0).unwrap();
+        assert_eq!(browser.current_directory(), Some(kicks.as_path()));
+
+        browser.navigate_back().unwrap();
+        assert_eq!(browser.current_directory(), Some(drums.as_path()));
+        browser.navigate_back().unwrap();
+        assert_eq!(browser.current_directory(), Some(dir.path()));
+        assert_eq!(browser.selected(), Some(1), "a.wav is selected again");
+        assert!(!browser.can_go_back());
+        browser.navigate_forward().unwrap();
+        assert_eq!(browser.current_directory(), Some(drums.as_path()));
+        assert!(browser.can_go_forward());
+
+        // Going somewhere new drops the way forward; going up counts.
+        browser.navigate_up().unwrap();
+        assert!(!browser.can_go_forward());
+        browser.navigate_back().unwrap();
+        assert_eq!(browser.current_directory(), Some(drums.as_path()));
+
+        // Each tab has its own way back.
+        browser.new_tab(Some(dir.path()));
+        assert!(!browser.can_go_back());
+    }
+
+    // Directories can't be opened as files to set their mtime on Windows.
+    #[cfg(unix)]
+    #[test]
+    fn refreshes_the_folder_when_it_changes_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kick.wav"), b"data").unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        browser.select(0);
+        browser.poll();
+
+        // Sorts before the selection, which should follow kick.wav.
+        fs::write(dir.path().join("clap.wav"), b"data").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        fs::File::open(dir.path())
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while browser.entries().len() < 2 && Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(browser.entries().len(), 2);
+        assert_eq!(browser.selected(), Some(1));
+        assert_eq!(browser.take_folder_change().as_deref(), Some(dir.path()));
+        assert_eq!(browser.take_folder_change(), None);
+    }
+
+    #[test]
+    fn observers_hear_each_change_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kick.wav"), b"data").unwrap();
+        let mut browser = SampleBrowserBuilder::new()
+            .start_directory(dir.path())
+            .playback(NullPlayback::new())
+            .build()
+            .unwrap();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let id = browser.observe({
+            let events = Rc::clone(&events);
+            move |e: &BrowserEvent| events.borrow_mut().push(e.clone())
+        });
+        let take = || events.borrow_mut().drain(..).collect::<Vec<_>>();
+
+        browser.poll();
+        assert!(take().is_empty());
+
+        browser.select(0);
+        browser.play_selected();
+        browser.poll();
+        let heard = take();
+        assert!(matches!(
+            heard.as_slice(),
+            [
+                BrowserEvent::SelectionChanged,
+                BrowserEvent::PlaybackChanged(PlaybackStatus::Playing { .. })
+            ]
+        ));
+        browser.poll();
+        assert!(take().is_empty());
+
+        browser.play_file(&dir.path().join("notes.txt"));
+        browser.refresh().unwrap();
+        browser.poll();
+        let heard = take();
+        assert!(matches!(
+            heard.as_slice(),
+            [
+                BrowserEvent::ListingChanged,
+                BrowserEvent::SelectionChanged,
+                BrowserEvent::Error(_)
+            ]
+        ));
+
+        browser.unobserve(id);
+        browser.stop();
+        browser.poll();
+        assert!(take().is_empty());
+    }
 
     #[test]
     fn close_left_of_active_shifts_down() {
@@ -0,0 +1,153 @@
+//! Recently read directory listings, so going back up a level or jumping to a
+//! breadcrumb doesn't re-read a folder that was just listed. A listing is
+//! reused only while the folder's modification time is unchanged; whatever
+//! knows better (a delete from the browser, a filesystem watcher) drops
+//! entries with [`ListingCache::invalidate`].
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use lru::LruCache;
+use punks_core::{DirListing, ScanError};
+
+struct Cached {
+    modified: SystemTime,
+    listing: Arc<DirListing>,
+}
+
+pub(crate) struct ListingCache {
+    entries: LruCache<PathBuf, Cached>,
+}
+
+impl ListingCache {
+    pub(crate) fn new(capacity: NonZeroUsize) -> Self {
+        ListingCache {
+            entries: LruCache::new(capacity),
+        }
+    }
+
+    /// The cached listing of `path` if the folder hasn't changed since it
+    /// was last `modified` (see [`modified`]), else a fresh one from `list`.
+    pub(crate) fn get_or_list(
+        &mut self,
+        path: &Path,
+        modified: Option<SystemTime>,
+        list: impl FnOnce(&Path) -> Result<DirListing, ScanError>,
+    ) -> Result<Arc<DirListing>, ScanError> {
+        if let (Some(modified), Some(cached)) = (modified, self.entries.get(path)) {
+            if cached.modified == modified {
+                tracing::trace!(dir = %path.display(), "listing cache hit");
+                return Ok(Arc::clone(&cached.listing));
+            }
+        }
+
+        let listing = Arc::new(list(path)?);
+        match modified {
+            Some(modified) => {
+                let listing = Arc::clone(&listing);
+                self.entries
+                    .put(path.to_path_buf(), Cached { modified, listing });
+            }
+            // Without an mtime there's no telling when it goes stale.
+            None => {
+                self.entries.pop(path);
+            }
+        }
+        Ok(listing)
+    }
+
+    /// Forget `path`, everything under it, and its parent (whose listing
+    /// shows it), after it was changed, added or removed.
+    pub(crate) fn invalidate(&mut self, path: &Path) {
+        let parent = path.parent();
+        let stale: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .map(|(k, _)| k)
+            .filter(|k| k.starts_with(path) || Some(k.as_path()) == parent)
+            .cloned()
+            .collect();
+        for key in stale {
+            self.entries.pop(&key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// When the folder at `path` last changed, if that can be told.
+pub(crate) fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(punks_core::paths::extended(path))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::fs;
+    use std::time::Duration;
+
+    /// Lists through `cache`, counting real reads in `reads`.
+    fn list(cache: &mut ListingCache, reads: &Cell<usize>, path: &Path) -> Arc<DirListing> {
+        cache
+            .get_or_list(path, modified(path), |p| {
+                reads.set(reads.get() + 1);
+                punks_core::list_directory(p)
+            })
+            .unwrap()
+    }
+
+    // Directories can't be opened as files to set their mtime on Windows.
+    #[cfg(unix)]
+    #[test]
+    fn reuses_listing_until_folder_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = ListingCache::new(NonZeroUsize::new(4).unwrap());
+        let reads = Cell::new(0);
+
+        let first = list(&mut cache, &reads, dir.path());
+        let again = list(&mut cache, &reads, dir.path());
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(reads.get(), 1);
+
+        // A new file bumps the folder's mtime; force it past coarse timestamps.
+        fs::write(dir.path().join("808.wav"), b"").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        fs::File::open(dir.path())
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(list(&mut cache, &reads, dir.path()).entries.len(), 1);
+        assert_eq!(reads.get(), 2);
+    }
+
+    #[test]
+    fn invalidate_drops_folder_subtree_and_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let kicks = dir.path().join("Kicks");
+        let acoustic = kicks.join("Acoustic");
+        fs::create_dir_all(&acoustic).unwrap();
+        let mut cache = ListingCache::new(NonZeroUsize::new(4).unwrap());
+        let reads = Cell::new(0);
+
+        for path in [dir.path(), &kicks, &acoustic] {
+            list(&mut cache, &reads, path);
+        }
+        cache.invalidate(&kicks);
+        for path in [dir.path(), &kicks, &acoustic] {
+            list(&mut cache, &reads, path);
+        }
+        assert_eq!(reads.get(), 6);
+
+        cache.invalidate(&acoustic.join("kick.wav"));
+        list(&mut cache, &reads, dir.path());
+        list(&mut cache, &reads, &acoustic);
+        assert_eq!(reads.get(), 7, "only the file's folder is re-read");
+    }
+}
@@ -0,0 +1,338 @@
+//! MIDI-learn: map notes/CCs from a pad controller to browser actions so the
+//! audition workflow can be driven from hardware. Notes not bound to an
+//! action can also play samples straight from the pads, one per pad (see
+//! [`PadSource`]).
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+pub use punks_core::config::PadSource;
+use punks_core::config::{MidiBinding, MidiTrigger};
+
+/// How many pads play samples: 16, a bank on most pad controllers.
+pub const PAD_COUNT: usize = 16;
+
+/// Browser actions a MIDI message can trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiAction {
+    SelectNext,
+    SelectPrev,
+    /// Enter the selected folder, or play the selected file.
+    Enter,
+    Back,
+    Play,
+    Stop,
+    NextTab,
+    PrevTab,
+    /// Star the selected file or folder, or unstar it if it already is.
+    Favorite,
+}
+
+impl MidiAction {
+    pub const ALL: &'static [MidiAction] = &[
+        MidiAction::SelectNext,
+        MidiAction::SelectPrev,
+        MidiAction::Enter,
+        MidiAction::Back,
+        MidiAction::Play,
+        MidiAction::Stop,
+        MidiAction::NextTab,
+        MidiAction::PrevTab,
+        MidiAction::Favorite,
+    ];
+
+    /// Stable name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            MidiAction::SelectNext => "select_next",
+            MidiAction::SelectPrev => "select_prev",
+            MidiAction::Enter => "enter",
+            MidiAction::Back => "back",
+            MidiAction::Play => "play",
+            MidiAction::Stop => "stop",
+            MidiAction::NextTab => "next_tab",
+            MidiAction::PrevTab => "prev_tab",
+            MidiAction::Favorite => "favorite",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MidiAction> {
+        Self::ALL.iter().copied().find(|a| a.name() == name)
+    }
+
+    /// Human-readable label for settings UIs.
+    pub fn label(self) -> &'static str {
+        match self {
+            MidiAction::SelectNext => "Next sample",
+            MidiAction::SelectPrev => "Previous sample",
+            MidiAction::Enter => "Enter folder / Play",
+            MidiAction::Back => "Back",
+            MidiAction::Play => "Play",
+            MidiAction::Stop => "Stop",
+            MidiAction::NextTab => "Next tab",
+            MidiAction::PrevTab => "Previous tab",
+            MidiAction::Favorite => "Favorite / Unfavorite",
+        }
+    }
+}
+
+/// Short display form of a trigger, e.g. `Note 36 ch1` / `CC 20 ch10`.
+pub fn trigger_label(trigger: &MidiTrigger) -> String {
+    match *trigger {
+        MidiTrigger::Note { channel, note } => format!("Note {note} ch{}", channel + 1),
+        MidiTrigger::Cc {
+            channel,
+            controller,
+        } => format!("CC {controller} ch{}", channel + 1),
+    }
+}
+
+/// Which pad `trigger` hits, counting up from the first pad on
+/// `first_note`: a note on any channel, within [`PAD_COUNT`] of it.
+pub fn pad_index(trigger: &MidiTrigger, first_note: u8) -> Option<usize> {
+    match *trigger {
+        MidiTrigger::Note { note, .. } => {
+            let pad = usize::from(note.checked_sub(first_note)?);
+            (pad < PAD_COUNT).then_some(pad)
+        }
+        MidiTrigger::Cc { .. } => None,
+    }
+}
+
+/// Turns raw MIDI bytes into button-like presses. Note-ons with velocity > 0
+/// press; CCs press when they rise through 64, so a momentary pad sending
+/// 127/0 fires once per hit and a knob fires once per pass over the midpoint.
+#[derive(Default)]
+pub struct TriggerDecoder {
+    cc_high: HashMap<(u8, u8), bool>,
+}
+
+impl TriggerDecoder {
+    pub fn decode(&mut self, msg: &[u8]) -> Option<MidiTrigger> {
+        let (&status, data) = msg.split_first()?;
+        let channel = status & 0x0f;
+        match (status & 0xf0, data) {
+            (0x90, &[note, velocity, ..]) if velocity > 0 => {
+                Some(MidiTrigger::Note { channel, note })
+            }
+            (0xb0, &[controller, value, ..]) => {
+                let high = value >= 64;
+                let was_high = self.cc_high.insert((channel, controller), high);
+                (high && was_high != Some(true)).then_some(MidiTrigger::Cc {
+                    channel,
+                    controller,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The learned trigger → action table plus the action currently waiting for a
+/// trigger to be learned, if any.
+#[derive(Default)]
+pub struct MidiMap {
+    bindings: Vec<(MidiTrigger, MidiAction)>,
+    learning: Option<MidiAction>,
+}
+
+impl MidiMap {
+    pub fn from_bindings(bindings: &[MidiBinding]) -> Self {
+        MidiMap {
+            bindings: bindings
+                .iter()
+                .filter_map(|b| Some((b.trigger, MidiAction::from_name(&b.action)?)))
+                .collect(),
+            learning: None,
+        }
+    }
+
+    pub fn to_bindings(&self) -> Vec<MidiBinding> {
+        self.bindings
+            .iter()
+            .map(|&(trigger, action)| MidiBinding {
+                action: action.name().to_string(),
+                trigger,
+            })
+            .collect()
+    }
+
+    /// Bind the next incoming trigger to `action`.
+    pub fn learn(&mut self, action: MidiAction) {
+        self.learning = Some(action);
+    }
+
+    pub fn cancel_learn(&mut self) {
+        self.learning = None;
+    }
+
+    pub fn learning(&self) -> Option<MidiAction> {
+        self.learning
+    }
+
+    /// Feed a trigger. While learning it becomes the action's only binding
+    /// (and is taken from any other action) and `None` is returned; otherwise
+    /// returns the bound action, if any.
+    pub fn handle(&mut self, trigger: MidiTrigger) -> Option<MidiAction> {
+        if let Some(action) = self.learning.take() {
+            self.bindings.retain(|&(t, a)| a != action && t != trigger);
+            self.bindings.push((trigger, action));
+            return None;
+        }
+        self.bindings
+            .iter()
+            .find(|&&(t, _)| t == trigger)
+            .map(|&(_, a)| a)
+    }
+
+    pub fn binding_for(&self, action: MidiAction) -> Option<MidiTrigger> {
+        self.bindings
+            .iter()
+            .find(|&&(_, a)| a == action)
+            .map(|&(t, _)| t)
+    }
+
+    pub fn clear(&mut self, action: MidiAction) {
+        self.bindings.retain(|&(_, a)| a != action);
+    }
+}
+
+/// Names of the MIDI input ports currently available.
+pub fn input_ports() -> Vec<String> {
+    let Ok(input) = midir::MidiInput::new("punks2") else {
+        return Vec::new();
+    };
+    input
+        .ports()
+        .iter()
+        .filter_map(|p| input.port_name(p).ok())
+        .collect()
+}
+
+/// An open input port. Decoded triggers are queued by midir's callback thread
+/// and drained on the UI thread with [`MidiInput::drain`].
+pub struct MidiInput {
+    port_name: String,
+    _conn: midir::MidiInputConnection<()>,
+    rx: mpsc::Receiver<MidiTrigger>,
+}
+
+impl MidiInput {
+    pub fn connect(port_name: &str) -> Result<Self, String> {
+        let mut input = midir::MidiInput::new("punks2").map_err(|e| e.to_string())?;
+        input.ignore(midir::Ignore::All);
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|p| input.port_name(p).ok().as_deref() == Some(port_name))
+            .ok_or_else(|| format!("MIDI port not found: {port_name}"))?;
+
+        let (tx, rx) = mpsc::channel();
+        let mut decoder = TriggerDecoder::default();
+        let conn = input
+            .connect(
+                &port,
+                "punks2-in",
+                move |_, msg, _| {
+                    if let Some(trigger) = decoder.decode(msg) {
+                        let _ = tx.send(trigger);
+                    }
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(MidiInput {
+            port_name: port_name.to_string(),
+            _conn: conn,
+            rx,
+        })
+    }
+
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+
+    pub fn drain(&self) -> Vec<MidiTrigger> {
+        self.rx.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAD: MidiTrigger = MidiTrigger::Note {
+        channel: 9,
+        note: 36,
+    };
+
+    #[test]
+    fn decodes_note_on_and_ignores_note_off() {
+        let mut d = TriggerDecoder::default();
+        assert_eq!(d.decode(&[0x99, 36, 100]), Some(PAD));
+        assert_eq!(d.decode(&[0x99, 36, 0]), None); // note-on velocity 0 == off
+        assert_eq!(d.decode(&[0x89, 36, 64]), None);
+    }
+
+    #[test]
+    fn cc_fires_on_rising_edge_only() {
+        let mut d = TriggerDecoder::default();
+        let cc = MidiTrigger::Cc {
+            channel: 0,
+            controller: 20,
+        };
+        assert_eq!(d.decode(&[0xb0, 20, 127]), Some(cc));
+        assert_eq!(d.decode(&[0xb0, 20, 127]), None);
+        assert_eq!(d.decode(&[0xb0, 20, 0]), None);
+        assert_eq!(d.decode(&[0xb0, 20, 100]), Some(cc));
+    }
+
+    #[test]
+    fn learn_binds_next_trigger_and_replaces_old() {
+        let mut map = MidiMap::default();
+        map.learn(MidiAction::Play);
+        assert_eq!(map.handle(PAD), None); // consumed by learn
+        assert_eq!(map.handle(PAD), Some(MidiAction::Play));
+
+        // Re-learning the same pad for another action moves it.
+        map.learn(MidiAction::Stop);
+        map.handle(PAD);
+        assert_eq!(map.handle(PAD), Some(MidiAction::Stop));
+        assert_eq!(map.binding_for(MidiAction::Play), None);
+    }
+
+    #[test]
+    fn pads_count_up_from_the_first_note_on_any_channel() {
+        assert_eq!(pad_index(&PAD, 36), Some(0));
+        let last = MidiTrigger::Note {
+            channel: 0,
+            note: 51,
+        };
+        assert_eq!(pad_index(&last, 36), Some(15));
+        assert_eq!(pad_index(&last, 35), None);
+        assert_eq!(pad_index(&PAD, 40), None);
+        let cc = MidiTrigger::Cc {
+            channel: 9,
+            controller: 36,
+        };
+        assert_eq!(pad_index(&cc, 36), None);
+    }
+
+    #[test]
+    fn bindings_round_trip_and_skip_unknown_actions() {
+        let mut bindings = vec![MidiBinding {
+            action: "play".into(),
+            trigger: PAD,
+        }];
+        bindings.push(MidiBinding {
+            action: "from_the_future".into(),
+            trigger: MidiTrigger::Cc {
+                channel: 0,
+                controller: 1,
+            },
+        });
+        let map = MidiMap::from_bindings(&bindings);
+        assert_eq!(map.to_bindings(), bindings[..1]);
+    }
+}
@@ -0,0 +1,133 @@
+//! Change notifications for hosts that don't redraw every frame (plugin
+//! GUIs, terminal UIs): callbacks registered with
+//! [`SampleBrowser::observe`](crate::SampleBrowser::observe) hear about what
+//! changed instead of the host diffing the browser's state itself.
+
+use std::mem::{self, Discriminant};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use punks_core::{DirListing, FileEntry};
+use punks_playback::PlaybackStatus;
+
+/// Something that changed since the last [`poll`](crate::SampleBrowser::poll).
+#[derive(Debug, Clone)]
+pub enum BrowserEvent {
+    /// The active tab shows different entries: another folder, a refresh
+    /// (including one after the folder changed on disk), a new filter,
+    /// search results arriving or a tab switch. Read them back with
+    /// `entries()` / `search_results()`.
+    ListingChanged,
+    /// The active tab's selection or search selection moved, or the listing
+    /// it indexes into changed.
+    SelectionChanged,
+    /// Playback started, paused, finished, stopped or started reconnecting;
+    /// not sent for the position moving while a file plays.
+    PlaybackChanged(PlaybackStatus),
+    /// A new error to show; see [`last_error`](crate::SampleBrowser::last_error).
+    Error(String),
+}
+
+/// Returned by [`SampleBrowser::observe`](crate::SampleBrowser::observe), to
+/// remove the observer again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+/// What observers were last told about. Listings are held rather than
+/// compared by address, so a freed one can't be mistaken for a new one.
+#[derive(Default)]
+pub(crate) struct Snapshot {
+    pub(crate) tab: usize,
+    pub(crate) listing: Option<Arc<DirListing>>,
+    pub(crate) filtered: Option<Arc<Vec<FileEntry>>>,
+    pub(crate) search: Option<Arc<Vec<FileEntry>>>,
+    pub(crate) selected: (Option<usize>, Option<usize>),
+    pub(crate) playback: Option<(Discriminant<PlaybackStatus>, Option<PathBuf>)>,
+    pub(crate) error: Option<String>,
+}
+
+/// The parts of `status` an observer cares about: which state, for which
+/// file.
+pub(crate) fn playback_key(
+    status: &PlaybackStatus,
+) -> (Discriminant<PlaybackStatus>, Option<PathBuf>) {
+    let file = match status {
+        PlaybackStatus::Loading { file, .. }
+        | PlaybackStatus::Playing { file, .. }
+        | PlaybackStatus::Paused { file, .. }
+        | PlaybackStatus::Finished { file } => Some(file.clone()),
+        PlaybackStatus::Idle | PlaybackStatus::Reconnecting { .. } => None,
+    };
+    (mem::discriminant(status), file)
+}
+
+type Callback = Box<dyn FnMut(&BrowserEvent)>;
+
+#[derive(Default)]
+pub(crate) struct Observers {
+    next_id: u64,
+    callbacks: Vec<(ObserverId, Callback)>,
+    seen: Snapshot,
+}
+
+impl Observers {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    /// Register `callback`; `now` is the state it starts out knowing.
+    pub(crate) fn add(&mut self, callback: Callback, now: Snapshot) -> ObserverId {
+        if self.callbacks.is_empty() {
+            self.seen = now;
+        }
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+        self.callbacks.push((id, callback));
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: ObserverId) {
+        self.callbacks.retain(|(i, _)| *i != id);
+    }
+
+    /// Tell every observer how `now` differs from what they last heard.
+    pub(crate) fn update(&mut self, now: Snapshot, status: PlaybackStatus) {
+        let seen = &self.seen;
+        let mut events = Vec::new();
+        let same_listing = now.tab == seen.tab
+            && opt_ptr_eq(&now.listing, &seen.listing)
+            && opt_ptr_eq(&now.filtered, &seen.filtered)
+            && opt_ptr_eq(&now.search, &seen.search);
+        if !same_listing {
+            events.push(BrowserEvent::ListingChanged);
+        }
+        if !same_listing || now.selected != seen.selected {
+            events.push(BrowserEvent::SelectionChanged);
+        }
+        if now.playback != seen.playback {
+            events.push(BrowserEvent::PlaybackChanged(status));
+        }
+        if let Some(error) = now
+            .error
+            .as_ref()
+            .filter(|&e| seen.error.as_ref() != Some(e))
+        {
+            events.push(BrowserEvent::Error(error.clone()));
+        }
+        self.seen = now;
+
+        for event in &events {
+            for (_, callback) in &mut self.callbacks {
+                callback(event);
+            }
+        }
+    }
+}
+
+fn opt_ptr_eq<T>(a: &Option<Arc<T>>, b: &Option<Arc<T>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
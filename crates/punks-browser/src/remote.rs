@@ -0,0 +1,705 @@
+//! Optional embedded HTTP/JSON API, so another device on the network (a tablet
+//! on the couch) can browse the library and trigger previews on this machine.
+//!
+//! The server runs on its own thread. Quick requests (list, metadata,
+//! status) are answered there straight from disk. Searches and file
+//! downloads, which can take a while, go to a few worker threads so they
+//! don't hold up the rest, and are turned away with a 503 when those are all
+//! busy and the queue behind them is full; play/stop are queued as [`RemoteCommand`]s for `SampleBrowser::poll` to
+//! apply, since the playback engine lives on the UI thread. Every path is
+//! resolved relative to a single root and may not escape it.
+//!
+//! The server listens on this machine only unless the config opts in to
+//! other devices, which also takes an access token: with one set, every
+//! request has to send it as `Authorization: Bearer …` or `?token=…`. On
+//! this machine alone it answers only requests addressed to `localhost` or
+//! `127.0.0.1` at its own port, so a web page can't reach it by rebinding a
+//! name of its own to the loopback address, and without a token it turns
+//! down `POST`s another site's page sends.
+//!
+//! Endpoints (all paths are `/`-separated and relative to the root):
+//!
+//! - `GET  /api/list?path=…` — directory listing
+//! - `GET  /api/search?q=…&path=…` — recursive filename search
+//! - `GET  /api/metadata?path=…` — file size + container metadata
+//! - `GET  /api/status` — current playback state
+//! - `GET  /api/file?path=…` — raw bytes of a file the listing would show
+//! - `POST /api/play?path=…`, `POST /api/stop`
+
+use std::net::ToSocketAddrs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use punks_core::config::RemoteConfig;
+use punks_core::playlist::percent_decode;
+
+use crate::{ExtensionRegistry, FileEntry, ListOptions, PlaybackStatus};
+
+/// Threads serving searches and downloads.
+const WORKERS: usize = 4;
+/// Searches and downloads that may wait for a worker before more get a 503.
+const QUEUED: usize = 16;
+
+/// A request from a remote client that needs the playback engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteCommand {
+    Play(PathBuf),
+    Stop,
+}
+
+pub struct RemoteServer {
+    addr: String,
+    root: PathBuf,
+    server: Arc<Server>,
+    commands: mpsc::Receiver<RemoteCommand>,
+    status: Arc<Mutex<Value>>,
+}
+
+impl RemoteServer {
+    /// Bind `config.bind` (e.g. `127.0.0.1:7879`) and start serving its
+    /// root, listing the file types in `extensions`. An address other
+    /// devices can reach is refused unless `config` allows the network and
+    /// has a token.
+    pub fn start(config: &RemoteConfig, extensions: ExtensionRegistry) -> Result<Self, String> {
+        let bind = config.bind.as_str();
+        let root = config.root.as_deref().ok_or("no root folder")?;
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("remote root {}: {e}", root.display()))?;
+        let token = Some(config.token.trim())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+        if !is_loopback(bind) {
+            if !config.allow_network {
+                return Err(format!(
+                    "{bind} is reachable from other devices; allow the network to serve them"
+                ));
+            }
+            if token.is_none() {
+                return Err("serving other devices needs an access token".into());
+            }
+        }
+        let server = Arc::new(Server::http(bind).map_err(|e| format!("bind {bind}: {e}"))?);
+        let addr = server.server_addr().to_ip();
+        let guard = Guard {
+            token,
+            local_port: addr.filter(|_| is_loopback(bind)).map(|a| a.port()),
+        };
+        let (tx, commands) = mpsc::channel();
+        let status = Arc::new(Mutex::new(json!({ "state": "idle" })));
+
+        {
+            let server = Arc::clone(&server);
+            let status = Arc::clone(&status);
+            let root = root.clone();
+            let opts = ListOptions {
+                extensions,
+                ..ListOptions::default()
+            };
+            let jobs = start_workers(&root, &opts);
+            std::thread::spawn(move || {
+                // Ends when `Drop` unblocks the server, and with it the
+                // workers once they run out of jobs.
+                for request in server.incoming_requests() {
+                    handle(request, &root, &opts, &guard, &tx, &jobs, &status);
+                }
+            });
+        }
+
+        Ok(RemoteServer {
+            addr: addr.map_or_else(|| bind.to_string(), |a| a.to_string()),
+            root,
+            server,
+            commands,
+            status,
+        })
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn drain(&self) -> Vec<RemoteCommand> {
+        self.commands.try_iter().collect()
+    }
+
+    /// Snapshot the playback state for `GET /api/status`.
+    pub fn publish(&self, status: &PlaybackStatus) {
+        let value = match status {
+            PlaybackStatus::Idle => json!({ "state": "idle" }),
+            PlaybackStatus::Loading { file, duration } => json!({
+                "state": "loading",
+                "file": relative(&self.root, file),
+                "duration": duration.map(|d| d.as_secs_f64()),
+            }),
+            PlaybackStatus::Playing {
+                file,
+                position,
+                duration,
+            } => json!({
+                "state": "playing",
+                "file": relative(&self.root, file),
+                "position": position.as_secs_f64(),
+                "duration": duration.as_secs_f64(),
+            }),
+            PlaybackStatus::Paused {
+                file,
+                position,
+                duration,
+            } => json!({
+                "state": "paused",
+                "file": relative(&self.root, file),
+                "position": position.as_secs_f64(),
+                "duration": duration.as_secs_f64(),
+            }),
+            PlaybackStatus::Finished { file } => json!({
+                "state": "finished",
+                "file": relative(&self.root, file),
+            }),
+            PlaybackStatus::Reconnecting { error } => json!({
+                "state": "reconnecting",
+                "error": error,
+            }),
+        };
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = value;
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+type Reply = Response<std::io::Cursor<Vec<u8>>>;
+
+fn json_reply(code: u16, body: Value) -> Reply {
+    let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_string(body.to_string())
+        .with_status_code(code)
+        .with_header(header)
+}
+
+fn error_reply(code: u16, msg: &str) -> Reply {
+    json_reply(code, json!({ "error": msg }))
+}
+
+/// Status code and message for a failed scan. The message leaves out the
+/// path, which is absolute and says more about this machine than clients need.
+fn scan_error_reply(e: &punks_core::ScanError) -> Reply {
+    use punks_core::ScanError;
+    match e {
+        ScanError::NotFound(_) => error_reply(404, "not found"),
+        ScanError::PermissionDenied(_) => error_reply(403, "permission denied"),
+        ScanError::NotADirectory(_) => error_reply(400, "not a directory"),
+        ScanError::Io { .. } => error_reply(500, "I/O error"),
+        ScanError::Unreachable { .. } => error_reply(504, "share unreachable"),
+    }
+}
+
+/// Whether everything `bind` resolves to is on this machine. An address
+/// that doesn't resolve counts as reachable; binding it fails anyway.
+fn is_loopback(bind: &str) -> bool {
+    bind.to_socket_addrs()
+        .is_ok_and(|mut addrs| addrs.all(|a| a.ip().is_loopback()))
+}
+
+/// Who the server answers.
+struct Guard {
+    /// Required of every request when set.
+    token: Option<String>,
+    /// The port of a server on this machine alone, whose requests have to
+    /// be addressed to it by a loopback name; `None` when serving others.
+    local_port: Option<u16>,
+}
+
+impl Guard {
+    /// The status and reason `request` is turned away with, if it is.
+    fn refuses(
+        &self,
+        request: &Request,
+        query: &[(String, String)],
+    ) -> Option<(u16, &'static str)> {
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str())
+        };
+        let host = header("Host").unwrap_or("");
+        if let Some(port) = self.local_port {
+            let local = ["localhost", "127.0.0.1", "[::1]"]
+                .iter()
+                .any(|name| host == format!("{name}:{port}"));
+            if !local {
+                return Some((403, "unexpected host"));
+            }
+        }
+        let Some(token) = &self.token else {
+            // Without a token to prove who sent it, a browser's own word
+            // that another site's page did is all there is to go on.
+            let foreign = header("Origin").is_some_and(|o| o != format!("http://{host}"));
+            return (*request.method() != Method::Get && foreign)
+                .then_some((403, "cross-origin request"));
+        };
+        let bearer = header("Authorization").and_then(|v| v.strip_prefix("Bearer "));
+        let given = bearer.or_else(|| {
+            query
+                .iter()
+                .find(|(k, _)| k == "token")
+                .map(|(_, v)| v.as_str())
+        });
+        (!given.is_some_and(|given| same_secret(given, token)))
+            .then_some((401, "missing or wrong token"))
+    }
+}
+
+/// `a == b` in a time that doesn't depend on where they first differ, so a
+/// token can't be guessed a character at a time.
+fn same_secret(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let differ = (0..a.len().max(b.len())).fold(a.len() ^ b.len(), |acc, i| {
+        let (x, y) = (a.get(i).copied(), b.get(i).copied());
+        acc | usize::from(x.unwrap_or(0) ^ y.unwrap_or(0))
+    });
+    std::hint::black_box(differ) == 0
+}
+
+/// A slow request for a worker.
+struct Job {
+    request: Request,
+    work: Work,
+}
+
+enum Work {
+    /// Stream this file back.
+    File(std::fs::File),
+    /// Search this folder for this query.
+    Search(PathBuf, String),
+}
+
+/// Start the [`WORKERS`], serving `root` as `opts` lists it, and return
+/// where to send them jobs.
+fn start_workers(root: &Path, opts: &ListOptions) -> mpsc::SyncSender<Job> {
+    let (tx, rx) = mpsc::sync_channel::<Job>(QUEUED);
+    let rx = Arc::new(Mutex::new(rx));
+    for i in 0..WORKERS {
+        let (rx, root, opts) = (Arc::clone(&rx), root.to_path_buf(), opts.clone());
+        let spawned = std::thread::Builder::new()
+            .name(format!("punks-remote-{i}"))
+            .spawn(move || loop {
+                // The lock is let go as soon as a job comes in.
+                let Ok(Job { request, work }) = rx.lock().unwrap_or_else(|e| e.into_inner()).recv()
+                else {
+                    return;
+                };
+                let _ = match work {
+                    Work::File(file) => request.respond(Response::from_file(file)),
+                    Work::Search(dir, q) => request.respond(search_reply(&root, &opts, &dir, &q)),
+                };
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("remote worker {i}: {e}");
+        }
+    }
+    tx
+}
+
+fn handle(
+    request: Request,
+    root: &Path,
+    opts: &ListOptions,
+    guard: &Guard,
+    commands: &mpsc::Sender<RemoteCommand>,
+    jobs: &mpsc::SyncSender<Job>,
+    status: &Mutex<Value>,
+) {
+    let (route, query) = split_url(request.url());
+    if let Some((code, why)) = guard.refuses(&request, &query) {
+        let _ = request.respond(error_reply(code, why));
+        return;
+    }
+    let param = |name: &str| {
+        query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let path = resolve(root, param("path").unwrap_or(""));
+
+    // Downloads and searches go to the workers: a slow client or a big
+    // folder would otherwise stall every request behind it.
+    let work = match (request.method(), route.as_str()) {
+        (Method::Get, "/api/file") => Some(
+            path.clone()
+                .and_then(|p| listed(root, opts, &p).then_some(p).ok_or(404))
+                .and_then(|p| std::fs::File::open(p).map_err(|_| 404))
+                .map(Work::File)
+                .map_err(|code| error_reply(code, "not found")),
+        ),
+        (Method::Get, "/api/search") => Some(match (&path, param("q")) {
+            (Ok(dir), Some(q)) => Ok(Work::Search(dir.clone(), q.to_string())),
+            (Err(code), _) => Err(error_reply(*code, "bad path")),
+            (_, None) => Err(error_reply(400, "missing q")),
+        }),
+        _ => None,
+    };
+    match work {
+        Some(Ok(work)) => {
+            if let Err(mpsc::TrySendError::Full(job) | mpsc::TrySendError::Disconnected(job)) =
+                jobs.try_send(Job { request, work })
+            {
+                let _ = job
+                    .request
+                    .respond(error_reply(503, "busy; try again shortly"));
+            }
+            return;
+        }
+        Some(Err(reply)) => {
+            let _ = request.respond(reply);
+            return;
+        }
+        None => {}
+    }
+
+    let reply = match (request.method(), route.as_str()) {
+        (Method::Get, "/api/list") => match path {
+            Ok(dir) => match punks_core::list_directory_with(&dir, opts) {
+                Ok(listing) => json_reply(200, entries_json(root, &listing.entries)),
+                Err(e) => scan_error_reply(&e),
+            },
+            Err(code) => error_reply(code, "bad path"),
+        },
+        (Method::Get, "/api/metadata") => match path {
+            Ok(file) if listed(root, opts, &file) => metadata_reply(root, &file),
+            Ok(_) => error_reply(404, "not a file"),
+            Err(code) => error_reply(code, "bad path"),
+        },
+        (Method::Get, "/api/status") => json_reply(
+            200,
+            status.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        ),
+        (Method::Post, "/api/play") => match path {
+            Ok(file) if listed(root, opts, &file) && opts.extensions.plays_path(&file) => {
+                let _ = commands.send(RemoteCommand::Play(file));
+                json_reply(202, json!({ "ok": true }))
+            }
+            Ok(_) => error_reply(400, "not a playable file"),
+            Err(code) => error_reply(code, "bad path"),
+        },
+        (Method::Post, "/api/stop") => {
+            let _ = commands.send(RemoteCommand::Stop);
+            json_reply(202, json!({ "ok": true }))
+        }
+        _ => error_reply(404, "no such endpoint"),
+    };
+    let _ = request.respond(reply);
+}
+
+fn search_reply(root: &Path, opts: &ListOptions, dir: &Path, q: &str) -> Reply {
+    match punks_core::search_directory(dir, q, &opts.extensions) {
+        Ok(results) => json_reply(200, entries_json(root, &results)),
+        Err(e) => scan_error_reply(&e),
+    }
+}
+
+/// Whether `file` is one the listing would show: a file of a listed type,
+/// not hidden and not inside a hidden folder unless `opts` shows those. The
+/// root's dot-files (the manifest, say) are no business of clients.
+fn listed(root: &Path, opts: &ListOptions, file: &Path) -> bool {
+    let hidden = || {
+        file.strip_prefix(root)
+            .unwrap_or(file)
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    };
+    file.is_file() && opts.extensions.lists_path(file) && (opts.show_hidden || !hidden())
+}
+
+fn metadata_reply(root: &Path, file: &Path) -> Reply {
+    let Some(fs_meta) = std::fs::metadata(file).ok().filter(|m| m.is_file()) else {
+        return error_reply(404, "not a file");
+    };
+    let meta = punks_playback::read_metadata(file).unwrap_or_default();
+    json_reply(
+        200,
+        json!({
+            "path": relative(root, file),
+            "size_bytes": fs_meta.len(),
+            "description": meta.description,
+            "originator": meta.originator,
+            "origination_date": meta.origination_date,
+            "origination_time": meta.origination_time,
+            "time_reference": meta.time_reference,
+        }),
+    )
+}
+
+fn entries_json(root: &Path, entries: &[FileEntry]) -> Value {
+    let entries: Vec<Value> = entries
+        .iter()
+        .map(|e| {
+            json!({
+                "name": e.name,
+                "path": relative(root, &e.path),
+                "is_directory": e.is_directory,
+                "extension": e.extension,
+                "size_bytes": e.size_bytes,
+            })
+        })
+        .collect();
+    json!({ "entries": entries })
+}
+
+/// `path` relative to `root`, `/`-separated, as clients see it.
+fn relative(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Map a client path onto the filesystem under `root`. Rejects absolute paths,
+/// `..`, and symlinks leading outside the root (403); missing paths are 404.
+fn resolve(root: &Path, rel: &str) -> Result<PathBuf, u16> {
+    let rel = Path::new(rel);
+    if rel
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(403);
+    }
+    let full = root.join(rel).canonicalize().map_err(|_| 404u16)?;
+    if full.starts_with(root) {
+        Ok(full)
+    } else {
+        Err(403)
+    }
+}
+
+/// Split a request URL into its path and decoded query pairs. In the query,
+/// `+` stands for a space, as forms send it.
+fn split_url(url: &str) -> (String, Vec<(String, String)>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let decode = |s: &str| percent_decode(&s.replace('+', " "));
+    let pairs = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (decode(k), decode(v))
+        })
+        .collect();
+    (percent_decode(path), pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_and_decodes_query() {
+        let (path, q) = split_url("/api/search?q=kick%20808&path=Drums%2FKicks+x%2B");
+        assert_eq!(path, "/api/search");
+        assert_eq!(
+            q,
+            vec![
+                ("q".to_string(), "kick 808".to_string()),
+                ("path".to_string(), "Drums/Kicks x+".to_string()),
+            ]
+        );
+    }
+
+    /// The status code `GET url` gets from the server at `addr`.
+    fn status_of(addr: &str, url: &str, headers: &str) -> u16 {
+        request(addr, "GET", url, &format!("Host: {addr}\r\n{headers}"))
+    }
+
+    /// The status code `method url` with just `headers` gets.
+    fn request(addr: &str, method: &str, url: &str, headers: &str) -> u16 {
+        use std::io::{Read, Write};
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+            .unwrap();
+        write!(
+            stream,
+            "{method} {url} HTTP/1.1\r\nConnection: close\r\n{headers}\r\n"
+        )
+        .unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        reply.split(' ').nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn other_devices_need_an_opt_in_and_a_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = RemoteConfig {
+            bind: "0.0.0.0:0".into(),
+            root: Some(dir.path().to_path_buf()),
+            ..RemoteConfig::default()
+        };
+        let start =
+            |config: &RemoteConfig| RemoteServer::start(config, ExtensionRegistry::default());
+        assert!(start(&config).is_err());
+        config.allow_network = true;
+        assert!(start(&config).is_err());
+
+        config.bind = "127.0.0.1:0".into();
+        config.token = "s3cret".into();
+        let server = start(&config).unwrap();
+        let addr = server.addr();
+        assert_eq!(status_of(addr, "/api/status", ""), 401);
+        assert_eq!(status_of(addr, "/api/status?token=wrong", ""), 401);
+        assert_eq!(status_of(addr, "/api/status?token=s3cret", ""), 200);
+        let bearer = "Authorization: Bearer s3cret\r\n";
+        assert_eq!(status_of(addr, "/api/status", bearer), 200);
+    }
+
+    #[test]
+    fn answers_only_this_machine_by_name_and_its_own_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RemoteConfig {
+            root: Some(dir.path().to_path_buf()),
+            bind: "127.0.0.1:0".into(),
+            ..RemoteConfig::default()
+        };
+        let server = RemoteServer::start(&config, ExtensionRegistry::default()).unwrap();
+        let addr = server.addr();
+        let port = addr.rsplit(':').next().unwrap();
+
+        let localhost = format!("Host: localhost:{port}\r\n");
+        assert_eq!(request(addr, "GET", "/api/status", &localhost), 200);
+        // A rebound name, or the right name at another port.
+        let rebound = format!("Host: evil.example:{port}\r\n");
+        assert_eq!(request(addr, "GET", "/api/status", &rebound), 403);
+        assert_eq!(
+            request(addr, "GET", "/api/status", "Host: localhost\r\n"),
+            403
+        );
+        assert_eq!(request(addr, "GET", "/api/status", ""), 403);
+
+        let from =
+            |origin: &str| format!("Host: {addr}\r\nOrigin: {origin}\r\nContent-Length: 0\r\n");
+        let own = from(&format!("http://{addr}"));
+        assert_eq!(request(addr, "POST", "/api/stop", &own), 202);
+        let other = from("https://evil.example");
+        assert_eq!(request(addr, "POST", "/api/stop", &other), 403);
+        assert_eq!(server.drain(), [RemoteCommand::Stop]);
+    }
+
+    #[test]
+    fn secrets_compare_whole() {
+        assert!(same_secret("s3cret", "s3cret"));
+        assert!(!same_secret("s3cret", "s3creT"));
+        assert!(!same_secret("s3cret", "s3cre"));
+        assert!(!same_secret("", "s3cret"));
+    }
+
+    #[test]
+    fn slow_requests_go_to_a_bounded_set_of_workers() {
+        use std::io::Write;
+        let dir = tempfile::tempdir().unwrap();
+        // Far more than the socket buffers hold, so sending it blocks until
+        // the client reads.
+        std::fs::write(dir.path().join("long.wav"), vec![0u8; 32 << 20]).unwrap();
+        let config = RemoteConfig {
+            root: Some(dir.path().to_path_buf()),
+            bind: "127.0.0.1:0".into(),
+            ..RemoteConfig::default()
+        };
+        let server = RemoteServer::start(&config, ExtensionRegistry::default()).unwrap();
+
+        let mut download = std::net::TcpStream::connect(server.addr()).unwrap();
+        write!(
+            download,
+            "GET /api/file?path=long.wav HTTP/1.1\r\nHost: {}\r\n\r\n",
+            server.addr()
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(status_of(server.addr(), "/api/status", ""), 200);
+        assert_eq!(status_of(server.addr(), "/api/search?q=long", ""), 200);
+
+        // Every worker stuck on a client that doesn't read, and the queue
+        // full behind them.
+        let stuck: Vec<_> = (1..WORKERS + QUEUED)
+            .map(|_| {
+                let mut stream = std::net::TcpStream::connect(server.addr()).unwrap();
+                write!(
+                    stream,
+                    "GET /api/file?path=long.wav HTTP/1.1\r\nHost: {}\r\n\r\n",
+                    server.addr()
+                )
+                .unwrap();
+                // tiny_http takes on connections arriving all at once only
+                // slowly; give each time to reach the server.
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                stream
+            })
+            .collect();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert_eq!(status_of(server.addr(), "/api/file?path=long.wav", ""), 503);
+        assert_eq!(status_of(server.addr(), "/api/status", ""), 200);
+        drop((download, stuck));
+    }
+
+    #[test]
+    fn only_serves_files_the_listing_shows() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join(".stash")).unwrap();
+        for name in ["kick.wav", ".snare.wav", ".stash/hat.wav", "notes.txt"] {
+            std::fs::write(root.join(name), b"data").unwrap();
+        }
+        std::fs::write(root.join(punks_core::integrity::MANIFEST_NAME), b"{}").unwrap();
+        let opts = ListOptions::default();
+
+        assert!(listed(&root, &opts, &root.join("kick.wav")));
+        for name in [
+            ".snare.wav",
+            ".stash/hat.wav",
+            "notes.txt",
+            punks_core::integrity::MANIFEST_NAME,
+        ] {
+            assert!(!listed(&root, &opts, &root.join(name)), "{name}");
+        }
+
+        let config = RemoteConfig {
+            root: Some(root.clone()),
+            bind: "127.0.0.1:0".into(),
+            ..RemoteConfig::default()
+        };
+        let server = RemoteServer::start(&config, ExtensionRegistry::default()).unwrap();
+        assert_eq!(status_of(server.addr(), "/api/file?path=kick.wav", ""), 200);
+        assert_eq!(
+            status_of(server.addr(), "/api/file?path=notes.txt", ""),
+            404
+        );
+        assert_eq!(
+            status_of(server.addr(), "/api/file?path=.stash%2Fhat.wav", ""),
+            404
+        );
+    }
+
+    #[test]
+    fn resolve_stays_inside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("Drums")).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+
+        assert_eq!(resolve(&root, "Drums"), Ok(root.join("Drums")));
+        assert_eq!(resolve(&root, ""), Ok(root.clone()));
+        assert_eq!(resolve(&root, "../etc"), Err(403));
+        assert_eq!(resolve(&root, "/etc"), Err(403));
+        assert_eq!(resolve(&root, "Missing"), Err(404));
+        assert_eq!(relative(&root, &root.join("Drums")), "Drums");
+    }
+}
@@ -0,0 +1,142 @@
+//! Watch folders. A background thread rescans each registered folder every
+//! few seconds (polling rather than filesystem events, so network shares
+//! work too) and reports the files that arrived since the folder was
+//! registered, each classified on the way so it comes with a suggested tag.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use punks_core::config::WatchFolder;
+use punks_core::{ExtensionRegistry, ScanOptions};
+use punks_playback::analysis::classify::{self, Category};
+
+/// Time between rescans.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a file must go unmodified before it's picked up, so a download
+/// still being written isn't analyzed half done.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// A file that turned up in a watch folder.
+pub(crate) struct Arrival {
+    pub(crate) path: PathBuf,
+    pub(crate) at: SystemTime,
+    /// `None` if it couldn't be decoded or is silent.
+    pub(crate) category: Option<Category>,
+}
+
+/// The watching thread; stopped when dropped.
+pub(crate) struct Watcher {
+    stop: Arc<AtomicBool>,
+    rx: Receiver<Arrival>,
+}
+
+impl Watcher {
+    /// Watch `folders` for files of the listed types, skipping the `known`
+    /// ones reported in earlier sessions.
+    pub(crate) fn start(
+        folders: Vec<WatchFolder>,
+        known: HashSet<PathBuf>,
+        extensions: ExtensionRegistry,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let thread_stop = Arc::clone(&stop);
+        let spawned = std::thread::Builder::new()
+            .name("punks-watch".into())
+            .spawn(move || watch(&folders, known, &extensions, &thread_stop, &tx));
+        if let Err(e) = spawned {
+            tracing::warn!("watch folders: {e}");
+        }
+        Watcher { stop, rx }
+    }
+
+    /// Files that arrived since the last call.
+    pub(crate) fn drain(&self) -> Vec<Arrival> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn watch(
+    folders: &[WatchFolder],
+    mut known: HashSet<PathBuf>,
+    extensions: &ExtensionRegistry,
+    stop: &AtomicBool,
+    tx: &Sender<Arrival>,
+) {
+    let options = ScanOptions {
+        extensions: extensions.clone(),
+        ..ScanOptions::default()
+    };
+    while !stop.load(Ordering::Relaxed) {
+        for folder in folders {
+            let since = UNIX_EPOCH + Duration::from_secs(folder.since);
+            let files = match punks_core::scan_directory_iter(&folder.path, options.clone()) {
+                Ok(files) => files,
+                Err(e) => {
+                    tracing::debug!("watch folder: {e}");
+                    continue;
+                }
+            };
+            for file in files.flatten() {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                if known.contains(&file.path) {
+                    continue;
+                }
+                let Some((at, settled)) = arrival(&file.path) else {
+                    continue;
+                };
+                if at < since {
+                    known.insert(file.path);
+                    continue;
+                }
+                if !settled {
+                    continue;
+                }
+                let category = classify::classify_file(&file.path)
+                    .inspect_err(|e| tracing::warn!("watch folder: {e}"))
+                    .ok()
+                    .flatten();
+                known.insert(file.path.clone());
+                let arrived = Arrival {
+                    path: file.path,
+                    at,
+                    category,
+                };
+                if tx.send(arrived).is_err() {
+                    return;
+                }
+            }
+        }
+        let mut waited = Duration::ZERO;
+        while waited < INTERVAL && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(200));
+            waited += Duration::from_millis(200);
+        }
+    }
+}
+
+/// When `path` arrived (the later of its creation and modification, since
+/// copies can keep an old modification time), and whether it has stopped
+/// changing.
+fn arrival(path: &std::path::Path) -> Option<(SystemTime, bool)> {
+    let meta = std::fs::metadata(punks_core::paths::extended(path)).ok()?;
+    let modified = meta.modified().ok()?;
+    let at = meta
+        .created()
+        .map_or(modified, |created| created.max(modified));
+    let settled = modified.elapsed().map_or(true, |age| age >= SETTLE);
+    Some((at, settled))
+}
@@ -0,0 +1,78 @@
+//! Walking a large tree on one thread and on several. Run with
+//! `cargo bench -p punks-core --bench scan`; `PUNKS_BENCH_ROOT=<dir>` walks
+//! a real library instead of a generated one (best with a cold cache, e.g.
+//! a freshly mounted drive, where the threads gain the most).
+
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use punks_core::{scan_directory_iter, ScanOptions};
+
+/// Folders per level and levels of the generated tree, and files in each
+/// folder: 8 + 64 + 512 folders, about 12,000 files.
+const FANOUT: usize = 8;
+const LEVELS: usize = 3;
+const FILES_PER_FOLDER: usize = 20;
+
+/// Walks timed per thread count; the fastest is reported.
+const RUNS: usize = 5;
+
+fn main() {
+    let generated;
+    let root = match std::env::var_os("PUNKS_BENCH_ROOT") {
+        Some(root) => root.into(),
+        None => {
+            generated = tempfile::tempdir().expect("temp dir");
+            grow(generated.path(), LEVELS);
+            generated.path().to_path_buf()
+        }
+    };
+
+    let mut serial = None;
+    for threads in [
+        None,
+        NonZeroUsize::new(2),
+        NonZeroUsize::new(4),
+        NonZeroUsize::new(8),
+    ] {
+        let (files, time) = (0..RUNS)
+            .map(|_| walk(&root, threads))
+            .min_by_key(|(_, time)| *time)
+            .unwrap();
+        let baseline = *serial.get_or_insert(time);
+        println!(
+            "{:>9}: {files} files in {:>8.2?} ({:.2}x)",
+            threads.map_or("serial".to_string(), |t| format!("{t} threads")),
+            time,
+            baseline.as_secs_f64() / time.as_secs_f64()
+        );
+    }
+}
+
+fn walk(root: &Path, threads: Option<NonZeroUsize>) -> (usize, Duration) {
+    let options = ScanOptions {
+        threads,
+        ..ScanOptions::default()
+    };
+    let start = Instant::now();
+    let files = scan_directory_iter(root, options)
+        .expect("readable root")
+        .flatten()
+        .count();
+    (files, start.elapsed())
+}
+
+fn grow(dir: &Path, levels: usize) {
+    for i in 0..FILES_PER_FOLDER {
+        std::fs::write(dir.join(format!("sample {i}.wav")), b"").expect("write file");
+    }
+    if levels == 0 {
+        return;
+    }
+    for i in 0..FANOUT {
+        let sub = dir.join(format!("folder {i}"));
+        std::fs::create_dir(&sub).expect("create folder");
+        grow(&sub, levels - 1);
+    }
+}
@@ -0,0 +1,230 @@
+//! Named collections of samples gathered from any number of folders, e.g.
+//! "Track 12 drums", kept between sessions in a JSON file next to the
+//! config. A collection keeps its samples in the order the user puts them
+//! in, which is the order it plays through in.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::raw_path;
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    collections: Vec<StoredCollection>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCollection {
+    name: String,
+    #[serde(with = "raw_path::list")]
+    samples: Vec<PathBuf>,
+}
+
+/// A named list of samples, in the user's order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Collection {
+    name: String,
+    samples: Vec<PathBuf>,
+}
+
+impl Collection {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn samples(&self) -> &[PathBuf] {
+        &self.samples
+    }
+
+    /// Add `path` at the end; false if it's already in.
+    pub fn add(&mut self, path: &Path) -> bool {
+        if self.contains(path) {
+            return false;
+        }
+        self.samples.push(path.to_path_buf());
+        true
+    }
+
+    /// Take out the sample at `index`; false if there's none.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index >= self.samples.len() {
+            return false;
+        }
+        self.samples.remove(index);
+        true
+    }
+
+    /// Move the sample at `from` to `to`, shifting the ones between; false
+    /// if either is out of range.
+    pub fn reorder(&mut self, from: usize, to: usize) -> bool {
+        let len = self.samples.len();
+        if from >= len || to >= len {
+            return false;
+        }
+        let path = self.samples.remove(from);
+        self.samples.insert(to, path);
+        true
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.samples.iter().any(|p| p == path)
+    }
+}
+
+/// Every collection, oldest first, each with a name of its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Collections {
+    collections: Vec<Collection>,
+}
+
+impl Collections {
+    /// Where the app keeps its collections; `None` without a config
+    /// directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("punks").join("collections.json"))
+    }
+
+    /// Read the collections at `path`; a missing file is none.
+    pub fn load(path: &Path) -> io::Result<Collections> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Collections::default()),
+            Err(e) => return Err(e),
+        };
+        let stored: Stored = serde_json::from_str(&contents)?;
+        Ok(Collections {
+            collections: stored
+                .collections
+                .into_iter()
+                .map(|c| Collection {
+                    name: c.name,
+                    samples: c.samples,
+                })
+                .collect(),
+        })
+    }
+
+    /// Write the collections to `path`, creating its folder.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let stored = Stored {
+            collections: self
+                .collections
+                .iter()
+                .map(|c| StoredCollection {
+                    name: c.name.clone(),
+                    samples: c.samples.clone(),
+                })
+                .collect(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&stored)?)
+    }
+
+    /// Start an empty collection called `name`, trimmed; false if that's
+    /// blank or taken.
+    pub fn create(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.get(name).is_some() {
+            return false;
+        }
+        self.collections.push(Collection {
+            name: name.to_string(),
+            samples: Vec::new(),
+        });
+        true
+    }
+
+    /// Call collection `from` `to` instead, trimmed; false if there's no
+    /// `from`, or `to` is blank or another's name.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        let to = to.trim();
+        if to.is_empty() || (to != from && self.get(to).is_some()) {
+            return false;
+        }
+        match self.get_mut(from) {
+            Some(collection) => {
+                collection.name = to.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the collection called `name`; false if there's none.
+    pub fn delete(&mut self, name: &str) -> bool {
+        let before = self.collections.len();
+        self.collections.retain(|c| c.name != name);
+        self.collections.len() != before
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Collection> {
+        self.collections.iter().find(|c| c.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Collection> {
+        self.collections.iter_mut().find(|c| c.name == name)
+    }
+
+    /// Oldest first.
+    pub fn all(&self) -> &[Collection] {
+        &self.collections
+    }
+
+    pub fn len(&self) -> usize {
+        self.collections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_collections_and_their_order_between_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("punks/collections.json");
+        assert!(Collections::load(&file).unwrap().is_empty());
+
+        let mut collections = Collections::default();
+        assert!(collections.create(" Track 12 drums "));
+        assert!(!collections.create("Track 12 drums"));
+        assert!(!collections.create("  "));
+        let drums = collections.get_mut("Track 12 drums").unwrap();
+        assert!(drums.add(Path::new("/a/kick.wav")));
+        assert!(drums.add(Path::new("/b/snare.wav")));
+        assert!(drums.add(Path::new("/c/hat.wav")));
+        assert!(!drums.add(Path::new("/a/kick.wav")));
+        assert!(drums.reorder(2, 0));
+        assert!(!drums.reorder(0, 3));
+        collections.save(&file).unwrap();
+
+        let mut loaded = Collections::load(&file).unwrap();
+        assert_eq!(loaded, collections);
+        let samples = loaded.get("Track 12 drums").unwrap().samples();
+        assert_eq!(
+            samples,
+            [
+                PathBuf::from("/c/hat.wav"),
+                PathBuf::from("/a/kick.wav"),
+                PathBuf::from("/b/snare.wav"),
+            ]
+        );
+
+        assert!(loaded.create("Vox"));
+        assert!(!loaded.rename("Vox", "Track 12 drums"));
+        assert!(loaded.rename("Track 12 drums", "Drums"));
+        assert!(loaded.get_mut("Drums").unwrap().remove(0));
+        assert!(loaded.delete("Vox"));
+        assert!(!loaded.delete("Vox"));
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.all()[0].samples().len(), 2);
+    }
+}
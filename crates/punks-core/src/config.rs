@@ -1,8 +1,13 @@
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::raw_path;
+use crate::sort::SortMode;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Keybinds {
     #[serde(default = "default_navigate_up")]
     pub navigate_up: String,
@@ -20,6 +25,14 @@ pub struct Keybinds {
     pub prev_tab: String,
     #[serde(default = "default_next_tab")]
     pub next_tab: String,
+    /// Pauses and resumes, or plays the selection when nothing is playing.
+    /// With Shift it stops.
+    #[serde(default = "default_play_pause")]
+    pub play_pause: String,
+    #[serde(default = "default_stop")]
+    pub stop: String,
+    #[serde(default = "default_focus_search")]
+    pub focus_search: String,
 }
 
 fn default_navigate_up() -> String {
@@ -46,10 +59,35 @@ fn default_prev_tab() -> String {
 fn default_next_tab() -> String {
     "RightArrow".into()
 }
+fn default_play_pause() -> String {
+    "Space".into()
+}
+fn default_stop() -> String {
+    "Q".into()
+}
+fn default_focus_search() -> String {
+    "Slash".into()
+}
 fn default_volume() -> f32 {
     1.0
 }
 
+fn default_fade_frames() -> usize {
+    128
+}
+
+fn default_auto_preview() -> bool {
+    true
+}
+
+fn default_memory_budget_mb() -> Option<u32> {
+    Some(1024)
+}
+
+fn default_network_timeout_secs() -> f32 {
+    crate::network::DEFAULT_TIMEOUT.as_secs_f32()
+}
+
 impl Default for Keybinds {
     fn default() -> Self {
         Keybinds {
@@ -61,26 +99,436 @@ impl Default for Keybinds {
             close_tab: default_close_tab(),
             prev_tab: default_prev_tab(),
             next_tab: default_next_tab(),
+            play_pause: default_play_pause(),
+            stop: default_stop(),
+            focus_search: default_focus_search(),
         }
     }
 }
 
+/// A MIDI message that can be bound to a browser action. Channels are 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MidiTrigger {
+    Note { channel: u8, note: u8 },
+    Cc { channel: u8, controller: u8 },
+}
+
+/// One learned MIDI mapping. `action` is the action's name, like the strings
+/// in `Keybinds`, so unknown names from a newer build are simply ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidiBinding {
+    pub action: String,
+    pub trigger: MidiTrigger,
+}
+
+/// Which samples a controller's pads play, one per note.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PadSource {
+    /// Pads only do what they're bound to.
+    #[default]
+    Off,
+    /// The samples auditioned most recently, newest on the first pad.
+    Recent,
+    /// Starred samples, in the order they were starred.
+    Favorites,
+}
+
+impl PadSource {
+    pub const ALL: [PadSource; 3] = [PadSource::Off, PadSource::Recent, PadSource::Favorites];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PadSource::Off => "Off",
+            PadSource::Recent => "Recently played",
+            PadSource::Favorites => "Favorites",
+        }
+    }
+}
+
+fn default_first_pad_note() -> u8 {
+    36
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PunksConfig {
+pub struct MidiConfig {
+    /// Input port to reconnect to on launch.
+    #[serde(default)]
+    pub input_port: Option<String>,
+    #[serde(default)]
+    pub bindings: Vec<MidiBinding>,
+    #[serde(default)]
+    pub pads: PadSource,
+    /// Note of the first pad, on any channel; the rest follow up from it.
+    /// C1 (36) is where most pad controllers start.
+    #[serde(default = "default_first_pad_note")]
+    pub first_pad_note: u8,
+}
+
+impl Default for MidiConfig {
+    fn default() -> Self {
+        MidiConfig {
+            input_port: None,
+            bindings: Vec::new(),
+            pads: PadSource::Off,
+            first_pad_note: default_first_pad_note(),
+        }
+    }
+}
+
+fn default_remote_bind() -> String {
+    "127.0.0.1:7879".into()
+}
+
+/// Embedded HTTP API settings. Only `root` and below is ever served, and by
+/// default only to this machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_remote_bind")]
+    pub bind: String,
+    #[serde(default, with = "raw_path")]
+    pub root: Option<PathBuf>,
+    /// Opt in to a `bind` address other devices can reach. Refused without
+    /// a `token`.
+    #[serde(default)]
+    pub allow_network: bool,
+    /// Secret every request has to carry, as `Authorization: Bearer …` or
+    /// `?token=…`; none when blank.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub token: String,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        RemoteConfig {
+            enabled: false,
+            bind: default_remote_bind(),
+            root: None,
+            allow_network: false,
+            token: String::new(),
+        }
+    }
+}
+
+impl RemoteConfig {
+    /// A fresh random token, 32 hex digits.
+    pub fn new_token() -> String {
+        use std::hash::{BuildHasher, RandomState};
+        let mut hasher = blake3::Hasher::new();
+        for _ in 0..4 {
+            hasher.update(&RandomState::new().hash_one(0u8).to_le_bytes());
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        hasher.update(&now.as_nanos().to_le_bytes());
+        hasher.finalize().to_hex()[..32].to_string()
+    }
+}
+
+/// Freesound.org credentials and where downloaded sounds land. An API key is
+/// enough to search and preview; downloading originals needs an OAuth2 access
+/// token, without which the HQ preview is saved instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FreesoundConfig {
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub oauth_token: Option<String>,
+    #[serde(default, with = "raw_path")]
+    pub download_dir: Option<PathBuf>,
+}
+
+/// A file type listed on top of the built-in audio formats, e.g. `rex` or
+/// `8svx`. Unless `playable`, files show up in listings and search but
+/// aren't previewed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomExtension {
+    pub extension: String,
+    #[serde(default)]
+    pub playable: bool,
+}
+
+/// A folder new samples land in, like the Splice download folder. Files that
+/// turn up in it are indexed and analyzed as they arrive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchFolder {
+    #[serde(with = "raw_path::required")]
+    pub path: PathBuf,
+    /// When watching started, in seconds since the Unix epoch; files already
+    /// there by then don't count as added.
+    pub since: u64,
+}
+
+impl WatchFolder {
+    /// Watch `path` from now on.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        WatchFolder {
+            path: path.into(),
+            since: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+fn default_bpm() -> f32 {
+    120.0
+}
+
+/// Whether and how loops follow the metronome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoopSync {
+    /// Loops play once, at their own tempo.
+    #[default]
+    Off,
+    /// Loops repeat, started in step with the metronome.
+    Align,
+    /// As `Align`, and sped up or slowed to the metronome's tempo.
+    Stretch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetronomeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_bpm")]
+    pub bpm: f32,
     #[serde(default)]
+    pub sync: LoopSync,
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        MetronomeConfig {
+            enabled: false,
+            bpm: default_bpm(),
+            sync: LoopSync::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PunksConfig {
+    #[serde(default, with = "raw_path")]
     pub last_directory: Option<PathBuf>,
+    /// The entry selected in `last_directory` when the app last closed.
+    #[serde(default, with = "raw_path")]
+    pub last_selected: Option<PathBuf>,
+    /// The window's size when the app last closed, in logical pixels.
+    #[serde(default)]
+    pub window_size: Option<[f64; 2]>,
     #[serde(default)]
     pub keybinds: Keybinds,
     #[serde(default = "default_volume")]
     pub volume: f32,
+    /// Play each file as it's selected, e.g. stepping through with the
+    /// arrow keys.
+    #[serde(default = "default_auto_preview")]
+    pub auto_preview: bool,
+    /// Level previews to this integrated loudness, in LUFS, so louder and
+    /// quieter packs audition alike. `None` plays them as they are.
+    #[serde(default)]
+    pub loudness_target: Option<f32>,
+    /// Frames a preview fades in and out over when it starts, stops or is
+    /// retriggered, so cutting it off doesn't click; 0 cuts hard.
+    #[serde(default = "default_fade_frames")]
+    pub fade_frames: usize,
+    /// Start each preview over the one before, which rings out, rather than
+    /// stopping it.
+    #[serde(default)]
+    pub polyphonic: bool,
+    /// How folder listings are ordered; folders always come first.
+    #[serde(default)]
+    pub sort_mode: SortMode,
+    #[serde(default)]
+    pub sort_descending: bool,
+    #[serde(default)]
+    pub midi: MidiConfig,
+    #[serde(default)]
+    pub remote: RemoteConfig,
+    #[serde(default)]
+    pub freesound: FreesoundConfig,
+    #[serde(default)]
+    pub extensions: Vec<CustomExtension>,
+    /// Delete files for good when they can't be moved to the trash (e.g. on
+    /// a network share). Off unless the user opts in.
+    #[serde(default)]
+    pub allow_permanent_delete: bool,
+    /// Share waveform peaks with REAPER through `.reapeaks` files next to
+    /// samples: reuse current ones and write new ones.
+    #[serde(default)]
+    pub reapeaks: bool,
+    /// Start long files after decoding only this many seconds, finishing the
+    /// decode in the background. `None` waits for the full decode.
+    #[serde(default)]
+    pub quick_audition_secs: Option<f32>,
+    /// Largest decoded sample buffer to hold, in MiB; files that need more
+    /// are refused with an error. `None` for no cap.
+    #[serde(default = "default_memory_budget_mb")]
+    pub memory_budget_mb: Option<u32>,
+    /// Output device to play through, by name; the system default when
+    /// unset or no longer there.
+    #[serde(default)]
+    pub output_device: Option<String>,
+    /// Where session recordings go; see [`PunksConfig::recording_folder`].
+    #[serde(default, with = "raw_path")]
+    pub recordings_dir: Option<PathBuf>,
+    /// Seconds a folder listing on a network share may take before the
+    /// share is reported unreachable.
+    #[serde(default = "default_network_timeout_secs")]
+    pub network_timeout_secs: f32,
+    #[serde(default)]
+    pub watch_folders: Vec<WatchFolder>,
+    /// Folders kept in the library index (see [`crate::library`]), so
+    /// searching them doesn't walk the disk.
+    #[serde(default, with = "raw_path::list")]
+    pub library_folders: Vec<PathBuf>,
+    #[serde(default)]
+    pub metronome: MetronomeConfig,
 }
 
 impl Default for PunksConfig {
     fn default() -> Self {
         PunksConfig {
             last_directory: None,
+            last_selected: None,
+            window_size: None,
             keybinds: Keybinds::default(),
             volume: default_volume(),
+            auto_preview: default_auto_preview(),
+            loudness_target: None,
+            fade_frames: default_fade_frames(),
+            polyphonic: false,
+            sort_mode: SortMode::default(),
+            sort_descending: false,
+            midi: MidiConfig::default(),
+            remote: RemoteConfig::default(),
+            freesound: FreesoundConfig::default(),
+            extensions: Vec::new(),
+            allow_permanent_delete: false,
+            reapeaks: false,
+            quick_audition_secs: None,
+            memory_budget_mb: default_memory_budget_mb(),
+            output_device: None,
+            recordings_dir: None,
+            network_timeout_secs: default_network_timeout_secs(),
+            watch_folders: Vec::new(),
+            library_folders: Vec::new(),
+            metronome: MetronomeConfig::default(),
+        }
+    }
+}
+
+/// Playback settings in a [`Profile`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioProfile {
+    pub volume: f32,
+    #[serde(default)]
+    pub loudness_target: Option<f32>,
+    #[serde(default = "default_fade_frames")]
+    pub fade_frames: usize,
+    #[serde(default)]
+    pub quick_audition_secs: Option<f32>,
+    #[serde(default)]
+    pub memory_budget_mb: Option<u32>,
+}
+
+/// What gets listed and how files are handled, in a [`Profile`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanProfile {
+    #[serde(default)]
+    pub extensions: Vec<CustomExtension>,
+    #[serde(default)]
+    pub reapeaks: bool,
+    #[serde(default)]
+    pub allow_permanent_delete: bool,
+}
+
+/// A named set of settings to share between machines, e.g. so every room in
+/// a studio maps the same keys. Each section is optional: importing a
+/// profile changes only the sections it has. Per-machine settings (the last
+/// folder, MIDI and remote ports, Freesound credentials) are never included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keybinds: Option<Keybinds>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub midi_bindings: Option<Vec<MidiBinding>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<ScanProfile>,
+}
+
+impl Profile {
+    pub fn load(path: &Path) -> io::Result<Profile> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+}
+
+impl PunksConfig {
+    /// Where to record sessions: `recordings_dir`, else a "punks recordings"
+    /// folder in the user's Music (or home) folder.
+    pub fn recording_folder(&self) -> Option<PathBuf> {
+        self.recordings_dir.clone().or_else(|| {
+            dirs::audio_dir()
+                .or_else(dirs::home_dir)
+                .map(|d| d.join("punks recordings"))
+        })
+    }
+
+    /// Every shareable setting, as a profile called `name`.
+    pub fn to_profile(&self, name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            audio: Some(AudioProfile {
+                volume: self.volume,
+                loudness_target: self.loudness_target,
+                fade_frames: self.fade_frames,
+                quick_audition_secs: self.quick_audition_secs,
+                memory_budget_mb: self.memory_budget_mb,
+            }),
+            keybinds: Some(self.keybinds.clone()),
+            midi_bindings: Some(self.midi.bindings.clone()),
+            scan: Some(ScanProfile {
+                extensions: self.extensions.clone(),
+                reapeaks: self.reapeaks,
+                allow_permanent_delete: self.allow_permanent_delete,
+            }),
+        }
+    }
+
+    /// Take the sections `profile` has, leaving the rest as they are.
+    pub fn apply_profile(&mut self, profile: &Profile) {
+        if let Some(audio) = &profile.audio {
+            self.volume = audio.volume;
+            self.loudness_target = audio.loudness_target;
+            self.fade_frames = audio.fade_frames;
+            self.quick_audition_secs = audio.quick_audition_secs;
+            self.memory_budget_mb = audio.memory_budget_mb;
+        }
+        if let Some(keybinds) = &profile.keybinds {
+            self.keybinds = keybinds.clone();
+        }
+        if let Some(bindings) = &profile.midi_bindings {
+            self.midi.bindings = bindings.clone();
+        }
+        if let Some(scan) = &profile.scan {
+            self.extensions = scan.extensions.clone();
+            self.reapeaks = scan.reapeaks;
+            self.allow_permanent_delete = scan.allow_permanent_delete;
         }
     }
 }
@@ -96,12 +544,12 @@ pub fn load() -> PunksConfig {
 
     match std::fs::read_to_string(&path) {
         Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
-            log::warn!("failed to parse {}: {e}", path.display());
+            tracing::warn!("failed to parse {}: {e}", path.display());
             PunksConfig::default()
         }),
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => PunksConfig::default(),
         Err(e) => {
-            log::warn!("failed to read {}: {e}", path.display());
+            tracing::warn!("failed to read {}: {e}", path.display());
             PunksConfig::default()
         }
     }
@@ -114,7 +562,7 @@ pub fn save(config: &PunksConfig) {
 
     if let Some(parent) = path.parent() {
         if let Err(e) = std::fs::create_dir_all(parent) {
-            log::warn!("failed to create {}: {e}", parent.display());
+            tracing::warn!("failed to create {}: {e}", parent.display());
             return;
         }
     }
@@ -122,12 +570,81 @@ pub fn save(config: &PunksConfig) {
     let json = match serde_json::to_string_pretty(config) {
         Ok(j) => j,
         Err(e) => {
-            log::warn!("failed to serialize config: {e}");
+            tracing::warn!("failed to serialize config: {e}");
             return;
         }
     };
 
     if let Err(e) = std::fs::write(&path, json) {
-        log::warn!("failed to write {}: {e}", path.display());
+        tracing::warn!("failed to write {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(path: PathBuf) -> (String, Option<PathBuf>) {
+        let cfg = PunksConfig {
+            last_directory: Some(path),
+            ..PunksConfig::default()
+        };
+        let json = serde_json::to_string(&cfg).unwrap();
+        let back: PunksConfig = serde_json::from_str(&json).unwrap();
+        (json, back.last_directory)
+    }
+
+    #[test]
+    fn unicode_paths_stay_plain_strings() {
+        let (json, back) = round_trip(PathBuf::from("/Volumes/Samples"));
+        assert!(json.contains(r#""last_directory":"/Volumes/Samples""#));
+        assert_eq!(back, Some(PathBuf::from("/Volumes/Samples")));
+    }
+
+    #[test]
+    fn profiles_carry_only_shareable_settings() {
+        let studio = PunksConfig {
+            last_directory: Some(PathBuf::from("/Volumes/Studio A")),
+            volume: 0.5,
+            keybinds: Keybinds {
+                confirm: "Enter".into(),
+                ..Keybinds::default()
+            },
+            ..PunksConfig::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("studio.json");
+        studio.to_profile("Studio").save(&file).unwrap();
+
+        let mut laptop = PunksConfig {
+            last_directory: Some(PathBuf::from("/Users/me/Samples")),
+            ..PunksConfig::default()
+        };
+        let profile = Profile::load(&file).unwrap();
+        assert_eq!(profile.name, "Studio");
+        laptop.apply_profile(&profile);
+        assert_eq!(laptop.keybinds.confirm, "Enter");
+        assert_eq!(laptop.volume, 0.5);
+        assert_eq!(
+            laptop.last_directory,
+            Some(PathBuf::from("/Users/me/Samples"))
+        );
+
+        // Sections a profile leaves out stay as they were.
+        let keys_only: Profile =
+            serde_json::from_str(r#"{"name": "Keys", "keybinds": {"confirm": "Space"}}"#).unwrap();
+        laptop.apply_profile(&keys_only);
+        assert_eq!(laptop.keybinds.confirm, "Space");
+        assert_eq!(laptop.keybinds.new_tab, "T");
+        assert_eq!(laptop.volume, 0.5);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_paths_round_trip() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = PathBuf::from(std::ffi::OsString::from_vec(b"/samples/caf\xe9".to_vec()));
+        assert_eq!(round_trip(path.clone()).1, Some(path));
     }
 }
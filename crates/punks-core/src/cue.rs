@@ -0,0 +1,260 @@
+//! CUE sheets as virtual folders: each track becomes an entry pointing at its
+//! region of the referenced audio file, so a long mix or a sampled record can
+//! be auditioned track by track.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{DirListing, ExtensionRegistry, FileEntry, ScanError, TrackRegion};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// The audio file this track lives in, resolved against the sheet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::raw_path::required"))]
+    pub file: PathBuf,
+    pub region: TrackRegion,
+}
+
+pub fn is_cue(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| e.eq_ignore_ascii_case("cue"))
+}
+
+/// Parse a CUE sheet. A track starts at its `INDEX 01` and ends where the next
+/// track in the same `FILE` starts (so pregaps stay with the previous track);
+/// the last track of each file runs to its end. Tracks without an `INDEX 01`
+/// are dropped.
+pub fn parse_cue(text: &str, base: &Path) -> Vec<CueTrack> {
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    let mut file: Option<PathBuf> = None;
+    let mut current: Option<PendingTrack> = None;
+
+    let mut finish = |current: &mut Option<PendingTrack>, file: &Option<PathBuf>| {
+        if let (Some(track), Some(file)) = (current.take(), file) {
+            if let Some(start) = track.start {
+                tracks.push(CueTrack {
+                    number: track.number,
+                    title: track.title,
+                    performer: track.performer,
+                    file: file.clone(),
+                    region: TrackRegion { start, end: None },
+                });
+            }
+        }
+    };
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let (command, args) = split_command(line.trim());
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                finish(&mut current, &file);
+                file = args.first().map(|name| base.join(name.replace('\\', "/")));
+            }
+            "TRACK" => {
+                finish(&mut current, &file);
+                current = Some(PendingTrack {
+                    number: args.first().and_then(|n| n.parse().ok()).unwrap_or(0),
+                    ..PendingTrack::default()
+                });
+            }
+            "TITLE" => {
+                if let Some(track) = &mut current {
+                    track.title = args.first().cloned();
+                }
+            }
+            "PERFORMER" => {
+                if let Some(track) = &mut current {
+                    track.performer = args.first().cloned();
+                }
+            }
+            "INDEX" => {
+                if let (Some(track), [index, time, ..]) = (&mut current, args.as_slice()) {
+                    if index == "01" {
+                        track.start = parse_msf(time);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    finish(&mut current, &file);
+
+    // Close each track at the next one's start within the same file.
+    for i in 1..tracks.len() {
+        if tracks[i].file == tracks[i - 1].file {
+            tracks[i - 1].region.end = Some(tracks[i].region.start);
+        }
+    }
+    tracks
+}
+
+/// A `TRACK` whose lines are still being read.
+#[derive(Default)]
+struct PendingTrack {
+    number: u32,
+    title: Option<String>,
+    performer: Option<String>,
+    start: Option<Duration>,
+}
+
+/// `mm:ss:ff` with 75 frames per second (CD sectors). Minutes may exceed 99.
+fn parse_msf(time: &str) -> Option<Duration> {
+    let mut parts = time.split(':').map(|p| p.parse::<u64>().ok());
+    let (m, s, f) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || s >= 60 || f >= 75 {
+        return None;
+    }
+    Some(Duration::from_secs(m * 60 + s) + Duration::from_nanos(f * 1_000_000_000 / 75))
+}
+
+/// Split a CUE line into its command and arguments, honouring double quotes.
+fn split_command(line: &str) -> (&str, Vec<String>) {
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let mut args = Vec::new();
+    let mut chars = rest.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            args.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut arg = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                arg.push(c);
+                chars.next();
+            }
+            args.push(arg);
+        }
+    }
+    (command, args)
+}
+
+/// List a CUE sheet's tracks as entries with regions. Tracks whose audio file
+/// is missing or not playable are reported once per file in `skipped`;
+/// a region of a list-only file would be no use.
+pub(crate) fn list_cue(
+    path: &Path,
+    extensions: &ExtensionRegistry,
+) -> Result<DirListing, ScanError> {
+    let text = crate::read_text_lossy(path).map_err(|e| ScanError::io(path, e))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut entries = Vec::new();
+    let mut skipped: Vec<(PathBuf, io::Error)> = Vec::new();
+    for track in parse_cue(&text, base) {
+        if skipped.iter().any(|(p, _)| *p == track.file) {
+            continue;
+        }
+        let ext = track
+            .file
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(|s| s.to_ascii_lowercase())
+            .unwrap_or_default();
+        if !extensions.is_playable(&ext) {
+            let err = io::Error::new(io::ErrorKind::Unsupported, "not a supported audio format");
+            skipped.push((track.file, err));
+            continue;
+        }
+        let size_bytes = match std::fs::metadata(&track.file) {
+            Ok(meta) => meta.len(),
+            Err(e) => {
+                skipped.push((track.file, e));
+                continue;
+            }
+        };
+        let title = track
+            .title
+            .unwrap_or_else(|| format!("Track {:02}", track.number));
+        let name = match track.performer {
+            Some(performer) => format!("{:02} {performer} - {title}", track.number),
+            None => format!("{:02} {title}", track.number),
+        };
+        entries.push(FileEntry {
+            file_name: track.file.file_name().unwrap_or_default().to_os_string(),
+            path: track.file,
+            name,
+            extension: ext,
+            size_bytes,
+            is_directory: false,
+            region: Some(track.region),
+            audio_count: None,
+            modified: None,
+        });
+    }
+
+    Ok(DirListing {
+        root: path.to_path_buf(),
+        entries,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const SHEET: &str = r#"REM GENRE Electronic
+PERFORMER "Various"
+TITLE "Late Night Mix"
+FILE "mix.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Deep Cut"
+    PERFORMER "Someone"
+    INDEX 00 03:58:00
+    INDEX 01 04:00:37
+  TRACK 03 AUDIO
+    INDEX 01 125:00:00
+"#;
+
+    #[test]
+    fn parses_tracks_and_regions() {
+        let tracks = parse_cue(SHEET, Path::new("/rips"));
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].file, Path::new("/rips/mix.flac"));
+        assert_eq!(tracks[0].title.as_deref(), Some("Intro"));
+        assert_eq!(tracks[0].performer, None); // disc-level PERFORMER isn't a track's
+        let t2_start = Duration::from_secs(240) + Duration::from_nanos(37 * 1_000_000_000 / 75);
+        assert_eq!(tracks[0].region.end, Some(t2_start));
+        assert_eq!(tracks[1].region.start, t2_start);
+        assert_eq!(tracks[1].performer.as_deref(), Some("Someone"));
+        assert_eq!(tracks[2].region.start, Duration::from_secs(125 * 60));
+        assert_eq!(tracks[2].region.end, None);
+    }
+
+    #[test]
+    fn rejects_malformed_times() {
+        assert_eq!(parse_msf("01:60:00"), None);
+        assert_eq!(parse_msf("01:00:75"), None);
+        assert_eq!(parse_msf("1:2"), None);
+        assert_eq!(parse_msf("00:01:00"), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn lists_tracks_with_regions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("mix.flac"), b"flac").unwrap();
+        let sheet = dir.path().join("mix.cue");
+        fs::write(&sheet, SHEET).unwrap();
+
+        let listing = crate::list_directory(&sheet).unwrap();
+        let names: Vec<&str> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["01 Intro", "02 Someone - Deep Cut", "03 Track 03"]
+        );
+        assert!(listing.entries.iter().all(|e| e.region.is_some()));
+    }
+}
@@ -0,0 +1,145 @@
+//! Finding duplicate samples: the same file saved under several names or in
+//! several folders, like one 909 clap that came with five packs. Files are
+//! grouped by size first, which the index already knows, and only sizes
+//! shared by more than one file are read and hashed (BLAKE3, as for
+//! [`crate::integrity`]), so a library of mostly unique samples costs little
+//! more than a listing. Only byte-identical files match; the same sound
+//! saved in another format or bit depth doesn't.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::integrity::hash_file;
+
+/// Files with the same contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Size of each copy, in bytes.
+    pub size: u64,
+    /// Hex BLAKE3 digest of each copy.
+    pub blake3: String,
+    /// Two or more, sorted.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Space taken by all the copies but one.
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64).saturating_sub(1)
+    }
+}
+
+/// Group the files among `files`, each a path and its size, whose contents
+/// are the same, biggest waste first. Paths listed twice count once, and
+/// empty files are left out. `on_file` is called before each file is
+/// hashed, for progress reporting. Files that can't be read are left out
+/// (and logged). Stops with an [`Interrupted`](io::ErrorKind::Interrupted)
+/// error once `cancel` is set.
+#[tracing::instrument(skip_all)]
+pub fn find_duplicates(
+    files: impl IntoIterator<Item = (PathBuf, u64)>,
+    cancel: &AtomicBool,
+    mut on_file: impl FnMut(&Path),
+) -> io::Result<Vec<DuplicateGroup>> {
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for (path, size) in files {
+        if size > 0 {
+            by_size.entry(size).or_default().push(path);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (size, mut paths) in by_size {
+        paths.sort();
+        paths.dedup();
+        if paths.len() < 2 {
+            continue;
+        }
+        let mut by_hash: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for path in paths {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            on_file(&path);
+            match hash_file(&path) {
+                // Changed since it was indexed: it can't match the others.
+                Ok(entry) if entry.size != size => {}
+                Ok(entry) => by_hash.entry(entry.blake3).or_default().push(path),
+                Err(e) => tracing::warn!(path = %path.display(), "dedupe: skipping: {e}"),
+            }
+        }
+        groups.extend(
+            by_hash
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(|(blake3, paths)| DuplicateGroup {
+                    size,
+                    blake3,
+                    paths,
+                }),
+        );
+    }
+    groups.sort_by(|a, b| {
+        b.wasted_bytes()
+            .cmp(&a.wasted_bytes())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn groups_files_with_the_same_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let files: Vec<(PathBuf, u64)> = [
+            ("Pack A/909 clap.wav", &b"clap"[..]),
+            ("Pack B/CLAP_01.wav", b"clap"),
+            ("clap copy.wav", b"clap"),
+            ("snap.wav", b"snap"), // same size, different contents
+            ("kick.wav", b"kick!"),
+            ("kick2.wav", b"kick!"),
+            ("empty.wav", b""),
+            ("empty2.wav", b""),
+        ]
+        .into_iter()
+        .map(|(name, contents)| {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            (path, contents.len() as u64)
+        })
+        .collect();
+
+        let mut hashed = 0;
+        let cancel = AtomicBool::new(false);
+        let mut listed_twice = files.clone();
+        listed_twice.push(files[4].clone());
+        let groups = find_duplicates(listed_twice, &cancel, |_| hashed += 1).unwrap();
+        assert_eq!(hashed, 6);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].paths,
+            [
+                root.join("Pack A/909 clap.wav"),
+                root.join("Pack B/CLAP_01.wav"),
+                root.join("clap copy.wav"),
+            ]
+        );
+        assert_eq!((groups[0].size, groups[0].wasted_bytes()), (4, 8));
+        assert_eq!(
+            groups[1].paths,
+            [root.join("kick.wav"), root.join("kick2.wav")]
+        );
+
+        cancel.store(true, Ordering::Relaxed);
+        let stopped = find_duplicates(files, &cancel, |_| {}).unwrap_err();
+        assert_eq!(stopped.kind(), io::ErrorKind::Interrupted);
+    }
+}
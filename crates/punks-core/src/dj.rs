@@ -0,0 +1,275 @@
+//! Sample sets for DJ software: a folder or playlist written as a rekordbox
+//! XML playlist or a Serato crate that references the files where they are,
+//! so a curated set can be loaded onto decks without copying anything.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::{ExtensionRegistry, ScanError, ScanOptions};
+
+/// The audio files in `source`, in order: a playlist's or CUE sheet's
+/// entries (each file once), or everything under a folder, recursively and
+/// sorted by path.
+pub fn collect_tracks(
+    source: &Path,
+    extensions: &ExtensionRegistry,
+) -> Result<Vec<PathBuf>, ScanError> {
+    if let Some(listing) = crate::list_virtual(source, extensions) {
+        let mut seen = HashSet::new();
+        return Ok(listing?
+            .entries
+            .into_iter()
+            .filter(|e| !e.is_directory && seen.insert(e.path.clone()))
+            .map(|e| e.path)
+            .collect());
+    }
+    let options = ScanOptions {
+        extensions: extensions.clone(),
+        ..ScanOptions::default()
+    };
+    let mut tracks: Vec<PathBuf> = crate::scan_directory_iter(source, options)?
+        .flatten()
+        .map(|e| e.path)
+        .collect();
+    tracks.sort();
+    Ok(tracks)
+}
+
+/// The name a set exported from `source` gets: the folder's name or the
+/// playlist's file stem.
+pub fn set_name(source: &Path) -> String {
+    let name = if source.is_file() {
+        source.file_stem()
+    } else {
+        source.file_name()
+    };
+    name.map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "punks".into())
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `path` as the `file://localhost/...` URL rekordbox expects, with
+/// everything but unreserved characters and separators percent-encoded.
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://localhost");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                url.push(b as char)
+            }
+            _ => url.push_str(&format!("%{b:02X}")),
+        }
+    }
+    url
+}
+
+/// Write `tracks` as a rekordbox collection holding one playlist called
+/// `name`, for File → Import → rekordbox xml.
+pub fn write_rekordbox_xml(name: &str, tracks: &[PathBuf], mut out: impl Write) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<DJ_PLAYLISTS Version="1.0.0">"#)?;
+    writeln!(
+        out,
+        r#"  <PRODUCT Name="punks" Version="{}" Company=""/>"#,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out, r#"  <COLLECTION Entries="{}">"#, tracks.len())?;
+    for (i, track) in tracks.iter().enumerate() {
+        let title = track.file_stem().unwrap_or_default().to_string_lossy();
+        let kind = track
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(|e| format!(" Kind=\"{} File\"", e.to_ascii_uppercase()))
+            .unwrap_or_default();
+        let size = std::fs::metadata(track)
+            .map(|m| format!(" Size=\"{}\"", m.len()))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            r#"    <TRACK TrackID="{}" Name="{}"{kind}{size} Location="{}"/>"#,
+            i + 1,
+            xml_escape(&title),
+            xml_escape(&file_url(track)),
+        )?;
+    }
+    writeln!(out, "  </COLLECTION>")?;
+    writeln!(out, "  <PLAYLISTS>")?;
+    writeln!(out, r#"    <NODE Type="0" Name="ROOT" Count="1">"#)?;
+    writeln!(
+        out,
+        r#"      <NODE Name="{}" Type="1" KeyType="0" Entries="{}">"#,
+        xml_escape(name),
+        tracks.len()
+    )?;
+    for i in 0..tracks.len() {
+        writeln!(out, r#"        <TRACK Key="{}"/>"#, i + 1)?;
+    }
+    writeln!(out, "      </NODE>")?;
+    writeln!(out, "    </NODE>")?;
+    writeln!(out, "  </PLAYLISTS>")?;
+    writeln!(out, "</DJ_PLAYLISTS>")
+}
+
+/// The `_Serato_` folder that should hold crates for `track`: the one at the
+/// root of the external drive it's on, or the one in the user's Music
+/// folder for the system drive.
+pub fn serato_dir(track: &Path) -> Option<PathBuf> {
+    if let Some(volume) = external_volume(track) {
+        return Some(volume.join("_Serato_"));
+    }
+    dirs::audio_dir().map(|music| music.join("_Serato_"))
+}
+
+/// `/Volumes/<name>` on macOS, `/media/<user>/<name>` or `/run/media/...` on
+/// Linux; `None` for the system drive.
+fn external_volume(path: &Path) -> Option<PathBuf> {
+    let parts: Vec<_> = path.components().take(5).collect();
+    let depth = match parts.as_slice() {
+        [Component::RootDir, Component::Normal(v), Component::Normal(_), ..] if *v == "Volumes" => {
+            3
+        }
+        [Component::RootDir, Component::Normal(m), _, Component::Normal(_), ..]
+            if *m == "media" =>
+        {
+            4
+        }
+        [Component::RootDir, Component::Normal(r), Component::Normal(m), _, Component::Normal(_), ..]
+            if *r == "run" && *m == "media" =>
+        {
+            5
+        }
+        _ => return None,
+    };
+    Some(parts[..depth].iter().collect())
+}
+
+/// Where Serato keeps a crate called `name` under `serato_dir`.
+pub fn serato_crate_path(serato_dir: &Path, name: &str) -> PathBuf {
+    // Serato nests crates with `%%` in the file name; keep a literal one out.
+    let file = name.replace("%%", "%").replace(['/', '\\'], "-");
+    serato_dir.join("Subcrates").join(format!("{file}.crate"))
+}
+
+/// `track` as Serato stores it: relative to the root of its drive, with `/`
+/// separators.
+fn serato_track_path(track: &Path) -> String {
+    let relative = match external_volume(track) {
+        Some(volume) => track.strip_prefix(volume).unwrap_or(track),
+        None => track,
+    };
+    relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn serato_field(out: &mut impl Write, tag: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "crate field too long"))?;
+    out.write_all(tag)?;
+    out.write_all(&len.to_be_bytes())?;
+    out.write_all(data)
+}
+
+fn utf16_be(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+/// Write `tracks` as a Serato `.crate` file: a version header, then one
+/// track record per file. See [`serato_crate_path`] for where it goes.
+pub fn write_serato_crate(tracks: &[PathBuf], mut out: impl Write) -> io::Result<()> {
+    serato_field(&mut out, b"vrsn", &utf16_be("1.0/Serato ScratchLive Crate"))?;
+    for track in tracks {
+        let mut record = Vec::new();
+        serato_field(&mut record, b"ptrk", &utf16_be(&serato_track_path(track)))?;
+        serato_field(&mut out, b"otrk", &record)?;
+    }
+    out.flush()
+}
+
+/// Write `tracks` as a crate called `name` in the `_Serato_` folder for the
+/// drive they're on (see [`serato_dir`]), returning the crate's path.
+pub fn save_serato_crate(name: &str, tracks: &[PathBuf]) -> io::Result<PathBuf> {
+    let dir = tracks
+        .first()
+        .and_then(|t| serato_dir(t))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no samples to export"))?;
+    let file = serato_crate_path(&dir, name);
+    std::fs::create_dir_all(dir.join("Subcrates"))?;
+    let out = io::BufWriter::new(std::fs::File::create(&file)?);
+    write_serato_crate(tracks, out)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_rekordbox_playlists() {
+        let tracks = [
+            PathBuf::from("/Users/dj/Samples/Kick & Snare.wav"),
+            PathBuf::from("/Users/dj/Samples/hat.flac"),
+        ];
+        let mut out = Vec::new();
+        write_rekordbox_xml("Drums <live>", &tracks, &mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+        assert!(xml.contains(
+            r#"<TRACK TrackID="1" Name="Kick &amp; Snare" Kind="WAV File" Location="file://localhost/Users/dj/Samples/Kick%20%26%20Snare.wav"/>"#
+        ));
+        assert!(
+            xml.contains(r#"<NODE Name="Drums &lt;live&gt;" Type="1" KeyType="0" Entries="2">"#)
+        );
+        assert!(xml.contains(r#"<TRACK Key="2"/>"#));
+    }
+
+    #[test]
+    fn writes_serato_crates_relative_to_the_drive() {
+        let tracks = [PathBuf::from("/Volumes/Gig/Samples/kick.wav")];
+        assert_eq!(
+            serato_dir(&tracks[0]),
+            Some(PathBuf::from("/Volumes/Gig/_Serato_"))
+        );
+        let mut out = Vec::new();
+        write_serato_crate(&tracks, &mut out).unwrap();
+
+        let header = utf16_be("1.0/Serato ScratchLive Crate");
+        assert_eq!(&out[..4], b"vrsn");
+        assert_eq!(out[4..8], (header.len() as u32).to_be_bytes());
+        let rest = &out[8 + header.len()..];
+        let path = utf16_be("Samples/kick.wav");
+        assert_eq!(&rest[..4], b"otrk");
+        assert_eq!(rest[4..8], (path.len() as u32 + 8).to_be_bytes());
+        assert_eq!(&rest[8..12], b"ptrk");
+        assert_eq!(&rest[16..], &path[..]);
+
+        assert_eq!(
+            serato_track_path(Path::new("/home/dj/kick.wav")),
+            "home/dj/kick.wav"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Error kinds shared by every punks crate. Each error type (this crate's
+//! [`ScanError`], `PlaybackError`, `BrowserError`, ...) says which
+//! [`ErrorKind`] it is, so a front end can decide what to do about a failure
+//! (retry, ask for access, tell the user) without matching on messages.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    NotFound,
+    PermissionDenied,
+    NotADirectory,
+    /// Any other filesystem or network failure.
+    Io,
+    /// A network share didn't answer in time.
+    Unreachable,
+    /// Not audio the decoder understands.
+    UnsupportedFormat,
+    /// Audio the decoder understands but couldn't read through.
+    Decode,
+    /// Writing audio out failed.
+    Encode,
+    /// Loading would exceed the memory budget.
+    TooLarge,
+    /// The audio output couldn't be opened or failed while playing.
+    Device,
+    /// The platform trash refused a file.
+    Trash,
+    Midi,
+    Remote,
+    /// An operation needed a selected entry and there wasn't one.
+    NoSelection,
+}
+
+impl ErrorKind {
+    /// A stable identifier, for logs, bug reports and bindings that can't
+    /// carry the enum.
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::PermissionDenied => "permission_denied",
+            ErrorKind::NotADirectory => "not_a_directory",
+            ErrorKind::Io => "io",
+            ErrorKind::Unreachable => "unreachable",
+            ErrorKind::UnsupportedFormat => "unsupported_format",
+            ErrorKind::Decode => "decode",
+            ErrorKind::Encode => "encode",
+            ErrorKind::TooLarge => "too_large",
+            ErrorKind::Device => "device",
+            ErrorKind::Trash => "trash",
+            ErrorKind::Midi => "midi",
+            ErrorKind::Remote => "remote",
+            ErrorKind::NoSelection => "no_selection",
+        }
+    }
+
+    /// Whether trying again later may succeed with nothing changed: devices
+    /// come back, network shares reconnect. Everything else needs the user.
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Io | ErrorKind::Unreachable | ErrorKind::Device | ErrorKind::Remote
+        )
+    }
+
+    /// The kind of an I/O error.
+    pub fn of_io(e: &io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            io::ErrorKind::NotADirectory => ErrorKind::NotADirectory,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+/// Why a path couldn't be scanned, naming the path that failed so the UI can
+/// say "permission denied for /Volumes/Samples" rather than just "I/O error".
+#[derive(Debug, thiserror::Error)]
+pub enum ScanError {
+    #[error("{} not found", .0.display())]
+    NotFound(PathBuf),
+    #[error("permission denied for {}", .0.display())]
+    PermissionDenied(PathBuf),
+    #[error("{} is not a directory", .0.display())]
+    NotADirectory(PathBuf),
+    #[error("I/O error on {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    /// A network share gave no answer within `timeout`; see
+    /// [`network`](crate::network).
+    #[error("{} is unreachable (no response in {timeout:?})", path.display())]
+    Unreachable { path: PathBuf, timeout: Duration },
+}
+
+impl ScanError {
+    /// Classify an I/O error hit while accessing `path`.
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        let path = path.to_path_buf();
+        match source.kind() {
+            io::ErrorKind::NotFound => ScanError::NotFound(path),
+            io::ErrorKind::PermissionDenied => ScanError::PermissionDenied(path),
+            _ => ScanError::Io { path, source },
+        }
+    }
+
+    /// The path the error is about.
+    pub fn path(&self) -> &Path {
+        match self {
+            ScanError::NotFound(path)
+            | ScanError::PermissionDenied(path)
+            | ScanError::NotADirectory(path)
+            | ScanError::Io { path, .. }
+            | ScanError::Unreachable { path, .. } => path,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ScanError::NotFound(_) => ErrorKind::NotFound,
+            ScanError::PermissionDenied(_) => ErrorKind::PermissionDenied,
+            ScanError::NotADirectory(_) => ErrorKind::NotADirectory,
+            ScanError::Io { source, .. } => ErrorKind::of_io(source),
+            ScanError::Unreachable { .. } => ErrorKind::Unreachable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_io_errors() {
+        let path = Path::new("/samples");
+        let denied = ScanError::io(path, io::ErrorKind::PermissionDenied.into());
+        assert!(matches!(denied, ScanError::PermissionDenied(_)));
+        assert_eq!(denied.kind(), ErrorKind::PermissionDenied);
+        assert!(!denied.kind().is_transient());
+        assert_eq!(denied.to_string(), "permission denied for /samples");
+
+        let timed_out = ScanError::io(path, io::ErrorKind::TimedOut.into());
+        assert_eq!(timed_out.kind(), ErrorKind::Io);
+        assert!(timed_out.kind().is_transient());
+        assert_eq!(timed_out.kind().code(), "io");
+    }
+}
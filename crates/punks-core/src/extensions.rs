@@ -0,0 +1,141 @@
+//! Which file types the browser lists and which of those it can preview.
+//! Starts from the formats the decoder handles and can be extended from
+//! config or at runtime, e.g. with `rex`/`rx2` slices or Amiga `8svx` that
+//! should show up in listings even though they can't be auditioned.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::config::CustomExtension;
+use crate::{MODULE_EXTENSIONS, OPUS_EXTENSIONS, SUPPORTED_EXTENSIONS};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionRegistry {
+    /// Lowercase extension without the dot, and whether it's playable.
+    entries: Vec<(String, bool)>,
+}
+
+impl Default for ExtensionRegistry {
+    /// The built-in, playable formats.
+    fn default() -> Self {
+        let modules = if cfg!(feature = "tracker") {
+            MODULE_EXTENSIONS
+        } else {
+            &[]
+        };
+        let opus = if cfg!(feature = "opus") {
+            OPUS_EXTENSIONS
+        } else {
+            &[]
+        };
+        ExtensionRegistry {
+            entries: SUPPORTED_EXTENSIONS
+                .iter()
+                .chain(modules)
+                .chain(opus)
+                .map(|e| (e.to_string(), true))
+                .collect(),
+        }
+    }
+}
+
+impl ExtensionRegistry {
+    /// The built-ins plus `custom`, which may also override a built-in.
+    pub fn with_custom(custom: &[CustomExtension]) -> Self {
+        let mut registry = Self::default();
+        for c in custom {
+            registry.register(&c.extension, c.playable);
+        }
+        registry
+    }
+
+    /// Add `extension` (case-insensitive, leading dot optional), replacing any
+    /// existing entry. Empty names are ignored.
+    pub fn register(&mut self, extension: &str, playable: bool) {
+        let extension = extension
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase();
+        if extension.is_empty() {
+            return;
+        }
+        match self.entries.iter_mut().find(|(e, _)| *e == extension) {
+            Some(entry) => entry.1 = playable,
+            None => self.entries.push((extension, playable)),
+        }
+    }
+
+    /// Whether files with this extension appear in listings and search.
+    pub fn is_listed(&self, extension: &str) -> bool {
+        self.find(extension).is_some()
+    }
+
+    /// Whether files with this extension can be previewed.
+    pub fn is_playable(&self, extension: &str) -> bool {
+        self.find(extension) == Some(true)
+    }
+
+    /// [`is_listed`](Self::is_listed) for `path`'s extension.
+    pub fn lists_path(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|e| self.is_listed(e))
+    }
+
+    /// [`is_playable`](Self::is_playable) for `path`'s extension.
+    pub fn plays_path(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|e| self.is_playable(e))
+    }
+
+    /// Every registered extension and whether it's playable, built-ins first.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.entries.iter().map(|(e, p)| (e.as_str(), *p))
+    }
+
+    /// The entries that differ from the built-ins, for writing back to config.
+    pub fn custom(&self) -> Vec<CustomExtension> {
+        let builtin = Self::default();
+        self.iter()
+            .filter(|&(e, p)| builtin.find(e) != Some(p))
+            .map(|(e, p)| CustomExtension {
+                extension: e.to_string(),
+                playable: p,
+            })
+            .collect()
+    }
+
+    fn find(&self, extension: &str) -> Option<bool> {
+        self.entries
+            .iter()
+            .find(|(e, _)| e.eq_ignore_ascii_case(extension))
+            .map(|&(_, p)| p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_extensions_extend_and_override_builtins() {
+        let registry = ExtensionRegistry::with_custom(&[
+            CustomExtension {
+                extension: ".REX".into(),
+                playable: false,
+            },
+            CustomExtension {
+                extension: "mp3".into(),
+                playable: false,
+            },
+        ]);
+        assert!(registry.is_playable("WAV"));
+        assert!(registry.is_listed("rex") && !registry.is_playable("rex"));
+        assert!(registry.is_listed("mp3") && !registry.is_playable("mp3"));
+        assert!(!registry.is_listed("txt"));
+        assert!(registry.lists_path(Path::new("/loops/break.Rex")));
+        assert_eq!(registry.custom().len(), 2);
+        assert_eq!(ExtensionRegistry::with_custom(&registry.custom()), registry);
+    }
+}
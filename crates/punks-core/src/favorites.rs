@@ -0,0 +1,109 @@
+//! Samples and folders the user starred to get back to quickly, kept between
+//! sessions in a JSON file next to the config, in the order they were added.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::raw_path;
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(with = "raw_path::list")]
+    favorites: Vec<PathBuf>,
+}
+
+/// Starred files and folders, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Favorites {
+    paths: Vec<PathBuf>,
+}
+
+impl Favorites {
+    /// Where the app keeps its favorites; `None` without a config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("punks").join("favorites.json"))
+    }
+
+    /// Read the favorites at `path`; a missing file is none.
+    pub fn load(path: &Path) -> io::Result<Favorites> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Favorites::default()),
+            Err(e) => return Err(e),
+        };
+        let stored: Stored = serde_json::from_str(&contents)?;
+        Ok(Favorites {
+            paths: stored.favorites,
+        })
+    }
+
+    /// Write the favorites to `path`, creating its folder.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let stored = Stored {
+            favorites: self.paths.clone(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&stored)?)
+    }
+
+    /// Star `path`; false if it already was.
+    pub fn add(&mut self, path: &Path) -> bool {
+        if self.contains(path) {
+            return false;
+        }
+        self.paths.push(path.to_path_buf());
+        true
+    }
+
+    /// Unstar `path`; false if it wasn't starred.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        let before = self.paths.len();
+        self.paths.retain(|p| p != path);
+        self.paths.len() != before
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.iter().any(|p| p == path)
+    }
+
+    /// Oldest first.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_favorites_between_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("punks/favorites.json");
+        assert!(Favorites::load(&file).unwrap().is_empty());
+
+        let mut favorites = Favorites::default();
+        assert!(favorites.add(Path::new("/samples/Drums")));
+        assert!(favorites.add(Path::new("/samples/kick.wav")));
+        assert!(!favorites.add(Path::new("/samples/Drums")));
+        favorites.save(&file).unwrap();
+
+        let mut loaded = Favorites::load(&file).unwrap();
+        assert_eq!(loaded, favorites);
+        assert!(loaded.remove(Path::new("/samples/Drums")));
+        assert!(!loaded.remove(Path::new("/samples/Drums")));
+        assert_eq!(loaded.paths(), [PathBuf::from("/samples/kick.wav")]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Every sample auditioned, when and for how long, kept between sessions so
+//! "that snare from yesterday evening" can be found again. The log is a
+//! JSON Lines file that each audition is appended to.
+
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::raw_path;
+
+/// One sample heard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audition {
+    pub path: PathBuf,
+    /// When it started playing.
+    pub started: SystemTime,
+    /// How long it played for.
+    pub listened: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(with = "raw_path::required")]
+    path: PathBuf,
+    /// Seconds since the Unix epoch.
+    at: u64,
+    listened_ms: u64,
+}
+
+impl From<&Audition> for Stored {
+    fn from(a: &Audition) -> Self {
+        Stored {
+            path: a.path.clone(),
+            at: a
+                .started
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            listened_ms: a.listened.as_millis().try_into().unwrap_or(u64::MAX),
+        }
+    }
+}
+
+impl From<Stored> for Audition {
+    fn from(s: Stored) -> Self {
+        Audition {
+            path: s.path,
+            started: UNIX_EPOCH + Duration::from_secs(s.at),
+            listened: Duration::from_millis(s.listened_ms),
+        }
+    }
+}
+
+/// The newest [`MAX_ENTRIES`](Self::MAX_ENTRIES) auditions, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditionHistory {
+    entries: VecDeque<Audition>,
+    /// Lines in the file, including ones already dropped from `entries`.
+    lines: usize,
+}
+
+impl AuditionHistory {
+    /// Auditions kept. The file is cut back to these once it holds twice as
+    /// many.
+    pub const MAX_ENTRIES: usize = 10_000;
+
+    /// Where the app keeps its history; `None` without a config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("punks").join("history.jsonl"))
+    }
+
+    /// Read the history at `path`; a missing file is an empty history, and
+    /// lines that can't be read (a write cut short) are skipped.
+    pub fn load(path: &Path) -> io::Result<AuditionHistory> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(AuditionHistory::default()),
+            Err(e) => return Err(e),
+        };
+        let mut history = AuditionHistory::default();
+        for line in BufReader::new(file).lines() {
+            history.lines += 1;
+            match serde_json::from_str::<Stored>(&line?) {
+                Ok(stored) => history.push(stored.into()),
+                Err(e) => tracing::warn!("{}:{}: {e}", path.display(), history.lines),
+            }
+        }
+        Ok(history)
+    }
+
+    /// Add `audition` without writing it anywhere.
+    pub fn push(&mut self, audition: Audition) {
+        if self.entries.len() == Self::MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(audition);
+    }
+
+    /// Add `audition` and append it to the file at `path`, creating its
+    /// folder.
+    pub fn record(&mut self, path: &Path, audition: Audition) -> io::Result<()> {
+        let line = serde_json::to_string(&Stored::from(&audition))?;
+        self.push(audition);
+        if self.lines >= 2 * Self::MAX_ENTRIES {
+            return self.save(path);
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{line}")?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Rewrite the file at `path` with just the auditions kept.
+    fn save(&mut self, path: &Path) -> io::Result<()> {
+        let mut out = String::new();
+        for audition in &self.entries {
+            out.push_str(&serde_json::to_string(&Stored::from(audition))?);
+            out.push('\n');
+        }
+        std::fs::write(path, out)?;
+        self.lines = self.entries.len();
+        Ok(())
+    }
+
+    /// Newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Audition> {
+        self.entries.iter().rev()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// `time` in local time, as briefly as it can be told apart from `now`:
+/// `23:04` today, `Mon 23:04` this past week, `2026-10-09 23:04` before.
+pub fn when(time: SystemTime, now: SystemTime) -> String {
+    let time: DateTime<Local> = time.into();
+    let now: DateTime<Local> = now.into();
+    let days = (now.date_naive() - time.date_naive()).num_days();
+    match days {
+        0 => time.format("%H:%M").to_string(),
+        1..=6 => time.format("%a %H:%M").to_string(),
+        _ => time.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audition(name: &str, at: u64) -> Audition {
+        Audition {
+            path: PathBuf::from("/samples").join(name),
+            started: UNIX_EPOCH + Duration::from_secs(at),
+            listened: Duration::from_millis(1500),
+        }
+    }
+
+    #[test]
+    fn appends_and_reads_back_auditions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("punks").join("history.jsonl");
+        assert!(AuditionHistory::load(&file).unwrap().is_empty());
+
+        let mut history = AuditionHistory::default();
+        history.record(&file, audition("snare.wav", 100)).unwrap();
+        history.record(&file, audition("kick.wav", 200)).unwrap();
+        // A torn last line is skipped, not fatal.
+        let mut f = OpenOptions::new().append(true).open(&file).unwrap();
+        write!(f, "{{\"path\":").unwrap();
+
+        let back = AuditionHistory::load(&file).unwrap();
+        let names: Vec<_> = back.recent().map(|a| a.path.file_name().unwrap()).collect();
+        assert_eq!(names, ["kick.wav", "snare.wav"]);
+        assert_eq!(back.recent().next(), Some(&audition("kick.wav", 200)));
+    }
+
+    #[test]
+    fn keeps_the_newest_auditions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("history.jsonl");
+        let mut history = AuditionHistory::default();
+        for i in 0..2 * AuditionHistory::MAX_ENTRIES as u64 + 1 {
+            history.record(&file, audition("hat.wav", i)).unwrap();
+        }
+        assert_eq!(history.len(), AuditionHistory::MAX_ENTRIES);
+
+        let back = AuditionHistory::load(&file).unwrap();
+        assert_eq!(back.lines, AuditionHistory::MAX_ENTRIES);
+        assert_eq!(
+            back.recent().next().unwrap().started,
+            UNIX_EPOCH + Duration::from_secs(2 * AuditionHistory::MAX_ENTRIES as u64)
+        );
+    }
+}
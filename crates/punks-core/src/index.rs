@@ -0,0 +1,350 @@
+//! What the user has told punks about individual samples, kept between
+//! sessions in one file next to the config: the preview gain a sample was
+//! trimmed to, its star rating and the tags it's filed under, including ones
+//! the auto-tagger suggested that are waiting to be confirmed or rejected,
+//! and when a watch folder turned it up. Tags are kept lowercase with their words joined by
+//! hyphens, so "Dark Pad" and "dark-pad" are one tag, searchable as
+//! `#dark-pad`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::raw_path;
+
+/// One sample's remembered settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SampleRecord {
+    /// Preview trim in dB, on top of the master volume.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain_db: Option<f32>,
+    /// Stars from 1 to [`MAX_RATING`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Tags the user gave or confirmed, like `kick`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Tags the auto-tagger guessed, not yet confirmed or rejected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggested_tags: Vec<String>,
+    /// Suggestions the user turned down, so they aren't offered again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejected_tags: Vec<String>,
+    /// When a watch folder turned it up, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<u64>,
+}
+
+impl SampleRecord {
+    fn is_empty(&self) -> bool {
+        self.gain_db.is_none()
+            && self.rating.is_none()
+            && self.tags.is_empty()
+            && self.suggested_tags.is_empty()
+            && self.rejected_tags.is_empty()
+            && self.added.is_none()
+    }
+}
+
+/// Most stars a sample can be rated.
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(with = "raw_path::required")]
+    path: PathBuf,
+    #[serde(flatten)]
+    record: SampleRecord,
+}
+
+/// Per-sample records keyed by path. Samples with nothing remembered aren't
+/// stored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SampleIndex {
+    records: HashMap<PathBuf, SampleRecord>,
+}
+
+impl SampleIndex {
+    /// Where the app keeps its index; `None` without a config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("punks").join("index.json"))
+    }
+
+    /// Read the index at `path`; a missing file is an empty index.
+    pub fn load(path: &Path) -> io::Result<SampleIndex> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SampleIndex::default()),
+            Err(e) => return Err(e),
+        };
+        let stored: Vec<Stored> = serde_json::from_str(&contents)?;
+        Ok(SampleIndex {
+            records: stored.into_iter().map(|s| (s.path, s.record)).collect(),
+        })
+    }
+
+    /// Write the index to `path`, sorted by path, creating its folder.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut stored: Vec<Stored> = self
+            .records
+            .iter()
+            .map(|(path, record)| Stored {
+                path: path.clone(),
+                record: record.clone(),
+            })
+            .collect();
+        stored.sort_by(|a, b| a.path.cmp(&b.path));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&stored)?)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&SampleRecord> {
+        self.records.get(path)
+    }
+
+    /// The preview trim remembered for `path`, in dB.
+    pub fn gain_db(&self, path: &Path) -> Option<f32> {
+        self.get(path).and_then(|r| r.gain_db)
+    }
+
+    /// Remember `gain_db` for `path`; `None` or 0 dB forgets it.
+    pub fn set_gain_db(&mut self, path: &Path, gain_db: Option<f32>) {
+        let gain_db = gain_db.filter(|&db| db != 0.0);
+        self.update(path, |r| r.gain_db = gain_db);
+    }
+
+    /// `path`'s stars, from 1 to [`MAX_RATING`].
+    pub fn rating(&self, path: &Path) -> Option<u8> {
+        self.get(path).and_then(|r| r.rating)
+    }
+
+    /// Rate `path` (capped at [`MAX_RATING`]); `None` or 0 clears it.
+    pub fn set_rating(&mut self, path: &Path, rating: Option<u8>) {
+        let rating = rating.filter(|&r| r > 0).map(|r| r.min(MAX_RATING));
+        self.update(path, |r| r.rating = rating);
+    }
+
+    /// `path`'s confirmed tags.
+    pub fn tags(&self, path: &Path) -> &[String] {
+        self.get(path).map_or(&[], |r| &r.tags)
+    }
+
+    /// Tag `path` with `tag`, confirming it if it was suggested and taking
+    /// back an earlier rejection. Returns whether it's new to `path`.
+    pub fn add_tag(&mut self, path: &Path, tag: &str) -> bool {
+        let Some(tag) = normalize_tag(tag) else {
+            return false;
+        };
+        if self.tags(path).contains(&tag) {
+            return false;
+        }
+        self.update(path, |r| {
+            r.suggested_tags.retain(|t| *t != tag);
+            r.rejected_tags.retain(|t| *t != tag);
+            r.tags.push(tag);
+        });
+        true
+    }
+
+    /// Take `tag` off `path`; false if it didn't have it.
+    pub fn remove_tag(&mut self, path: &Path, tag: &str) -> bool {
+        let Some(tag) = normalize_tag(tag) else {
+            return false;
+        };
+        if !self.tags(path).contains(&tag) {
+            return false;
+        }
+        self.update(path, |r| r.tags.retain(|t| *t != tag));
+        true
+    }
+
+    /// Every confirmed tag with how many samples carry it, by name.
+    pub fn all_tags(&self) -> Vec<(&str, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for tag in self.records.values().flat_map(|r| &r.tags) {
+            *counts.entry(tag).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Samples carrying every one of `tags`, sorted by path.
+    pub fn tagged(&self, tags: &[&str]) -> Vec<&Path> {
+        let tags: Vec<String> = tags.iter().filter_map(|t| normalize_tag(t)).collect();
+        let mut paths: Vec<&Path> = self
+            .records
+            .iter()
+            .filter(|(_, r)| tags.iter().all(|t| r.tags.contains(t)))
+            .map(|(path, _)| path.as_path())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Suggest `tags` for `path`, except ones it already has or that were
+    /// rejected for it before. Returns whether anything new was suggested.
+    pub fn suggest_tags(&mut self, path: &Path, tags: &[&str]) -> bool {
+        let mut added = false;
+        self.update(path, |r| {
+            for &tag in tags {
+                let known = [&r.tags, &r.suggested_tags, &r.rejected_tags]
+                    .iter()
+                    .any(|list| list.iter().any(|t| t == tag));
+                if !known {
+                    r.suggested_tags.push(tag.to_string());
+                    added = true;
+                }
+            }
+        });
+        added
+    }
+
+    /// Make `path`'s suggested tags its own.
+    pub fn confirm_suggestions(&mut self, path: &Path) {
+        self.update(path, |r| r.tags.append(&mut r.suggested_tags));
+    }
+
+    /// Drop `path`'s suggested tags and don't suggest them again.
+    pub fn reject_suggestions(&mut self, path: &Path) {
+        self.update(path, |r| r.rejected_tags.append(&mut r.suggested_tags));
+    }
+
+    /// Samples with suggestions waiting, and what's suggested.
+    pub fn suggestions(&self) -> impl Iterator<Item = (&Path, &[String])> {
+        self.records
+            .iter()
+            .filter(|(_, r)| !r.suggested_tags.is_empty())
+            .map(|(path, r)| (path.as_path(), r.suggested_tags.as_slice()))
+    }
+
+    /// Note that a watch folder turned `path` up `at`.
+    pub fn mark_added(&mut self, path: &Path, at: SystemTime) {
+        let secs = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.update(path, |r| r.added = Some(secs));
+    }
+
+    /// Samples watch folders turned up, newest first.
+    pub fn recently_added(&self) -> Vec<(&Path, SystemTime)> {
+        let mut added: Vec<_> = self
+            .records
+            .iter()
+            .filter_map(|(path, r)| {
+                let at = UNIX_EPOCH + Duration::from_secs(r.added?);
+                Some((path.as_path(), at))
+            })
+            .collect();
+        added.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        added
+    }
+
+    fn update(&mut self, path: &Path, f: impl FnOnce(&mut SampleRecord)) {
+        let record = self.records.entry(path.to_path_buf()).or_default();
+        f(record);
+        if record.is_empty() {
+            self.records.remove(path);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// `tag` as stored: lowercase, words joined by hyphens; `None` if there are
+/// no words.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join("-");
+    (!tag.is_empty()).then(|| tag.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembers_gain_across_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("punks").join("index.json");
+        assert!(SampleIndex::load(&file).unwrap().is_empty());
+
+        let mut index = SampleIndex::default();
+        index.set_gain_db(Path::new("/samples/quiet.wav"), Some(6.0));
+        index.set_gain_db(Path::new("/samples/loud.wav"), Some(-3.5));
+        index.set_gain_db(Path::new("/samples/loud.wav"), Some(0.0));
+        assert_eq!(index.len(), 1);
+        index.save(&file).unwrap();
+
+        let back = SampleIndex::load(&file).unwrap();
+        assert_eq!(back, index);
+        assert_eq!(back.gain_db(Path::new("/samples/quiet.wav")), Some(6.0));
+        assert_eq!(back.gain_db(Path::new("/samples/loud.wav")), None);
+    }
+
+    #[test]
+    fn ratings_are_capped_and_cleared() {
+        let kick = Path::new("/samples/kick.wav");
+        let mut index = SampleIndex::default();
+        index.set_rating(kick, Some(9));
+        assert_eq!(index.rating(kick), Some(MAX_RATING));
+        index.set_rating(kick, Some(3));
+        assert_eq!(index.rating(kick), Some(3));
+        index.set_rating(kick, Some(0));
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn suggested_tags_wait_to_be_confirmed_or_rejected() {
+        let (kick, pad) = (
+            Path::new("/samples/kick.wav"),
+            Path::new("/samples/pad.wav"),
+        );
+        let mut index = SampleIndex::default();
+        assert!(index.suggest_tags(kick, &["kick"]));
+        assert!(index.suggest_tags(pad, &["pad"]));
+        assert!(!index.suggest_tags(kick, &["kick"]), "already suggested");
+        assert_eq!(index.suggestions().count(), 2);
+
+        index.confirm_suggestions(kick);
+        index.reject_suggestions(pad);
+        assert_eq!(index.tags(kick), ["kick"]);
+        assert!(index.tags(pad).is_empty());
+        assert_eq!(index.suggestions().count(), 0);
+        assert!(!index.suggest_tags(kick, &["kick"]), "already tagged");
+        assert!(!index.suggest_tags(pad, &["pad"]), "rejected before");
+        assert!(index.suggest_tags(pad, &["fx"]));
+    }
+
+    #[test]
+    fn tags_are_added_removed_and_browsed() {
+        let (kick, snare) = (
+            Path::new("/samples/kick.wav"),
+            Path::new("/samples/snare.wav"),
+        );
+        let mut index = SampleIndex::default();
+        index.suggest_tags(kick, &["kick"]);
+        assert!(index.add_tag(kick, " Kick"));
+        assert!(index.add_tag(kick, "dark"));
+        assert!(!index.add_tag(kick, "DARK"));
+        assert!(!index.add_tag(kick, "  "));
+        assert!(index.add_tag(kick, "Low  End"));
+        assert!(index.remove_tag(kick, "low-end"));
+        assert!(index.add_tag(snare, "dark"));
+        assert_eq!(index.suggestions().count(), 0, "confirmed by hand");
+
+        assert_eq!(index.all_tags(), [("dark", 2), ("kick", 1)]);
+        assert_eq!(index.tagged(&["Dark"]), [kick, snare]);
+        assert_eq!(index.tagged(&["dark", "kick"]), [kick]);
+
+        assert!(index.remove_tag(snare, "dark"));
+        assert!(!index.remove_tag(snare, "dark"));
+        assert_eq!(index.get(snare), None, "nothing left to remember");
+    }
+}
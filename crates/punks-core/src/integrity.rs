@@ -0,0 +1,219 @@
+//! Checksum manifests for bit-rot detection. Each library root gets a
+//! `.punks-manifest.json` (hidden, so listings skip it) recording the size and
+//! BLAKE3 hash of every audio file beneath it; `verify` re-hashes and reports
+//! what changed. Aging external drives corrupt files silently, and a sample
+//! that decodes to garbage is otherwise only noticed mid-session.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{scan_directory_iter, ExtensionRegistry, ScanError, ScanOptions};
+
+pub const MANIFEST_NAME: &str = ".punks-manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    /// Hex BLAKE3 digest.
+    pub blake3: String,
+}
+
+/// Hashes keyed by `/`-separated path relative to the root, so a manifest
+/// survives the drive being mounted somewhere else.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// Result of checking a root against its manifest.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub ok: usize,
+    /// Same path, different contents: corruption (or an edit made outside
+    /// punks — rebuild the manifest after intentional changes).
+    pub mismatched: Vec<PathBuf>,
+    /// In the manifest but gone from disk.
+    pub missing: Vec<PathBuf>,
+    /// On disk but couldn't be read back.
+    pub unreadable: Vec<(PathBuf, io::Error)>,
+    /// On disk but not in the manifest yet.
+    pub untracked: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty() && self.unreadable.is_empty()
+    }
+}
+
+impl Manifest {
+    /// The manifest stored under `root`, or `None` if there isn't one yet.
+    pub fn load(root: &Path) -> Result<Option<Manifest>, ScanError> {
+        let path = root.join(MANIFEST_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map(Some)
+                .map_err(|e| ScanError::io(&path, io::Error::new(io::ErrorKind::InvalidData, e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ScanError::io(&path, e)),
+        }
+    }
+
+    /// Store the manifest under `root`. It's written beside the old one and
+    /// renamed over it, so a crash or full disk mid-write leaves the previous
+    /// manifest whole rather than a truncated one that fails to load.
+    pub fn save(&self, root: &Path) -> Result<(), ScanError> {
+        let path = root.join(MANIFEST_NAME);
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| ScanError::io(&path, io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        let tmp = root.join(format!("{MANIFEST_NAME}.part"));
+        let written = std::fs::write(&tmp, text).and_then(|()| std::fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(ScanError::io(&path, e));
+        }
+        Ok(())
+    }
+}
+
+/// Hash every audio file under `root` of a type `extensions` lists.
+/// `on_file` is called before each file is hashed, for progress reporting.
+/// Unreadable files are left out (and logged) rather than failing the whole
+/// build.
+#[tracing::instrument(skip_all, fields(root = %root.display()))]
+pub fn build_manifest(
+    root: &Path,
+    extensions: &ExtensionRegistry,
+    mut on_file: impl FnMut(&Path),
+) -> Result<Manifest, ScanError> {
+    let mut manifest = Manifest::default();
+    for path in audio_files(root, extensions)? {
+        on_file(&path);
+        match hash_file(&path) {
+            Ok(entry) => {
+                manifest.files.insert(relative_key(root, &path), entry);
+            }
+            Err(e) => tracing::warn!(path = %path.display(), "manifest: skipping: {e}"),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Re-hash the files under `root` and compare against `manifest`.
+#[tracing::instrument(skip_all, fields(root = %root.display()))]
+pub fn verify(
+    root: &Path,
+    manifest: &Manifest,
+    extensions: &ExtensionRegistry,
+    mut on_file: impl FnMut(&Path),
+) -> Result<VerifyReport, ScanError> {
+    let mut report = VerifyReport::default();
+    let mut seen = std::collections::HashSet::new();
+
+    for path in audio_files(root, extensions)? {
+        let key = relative_key(root, &path);
+        let Some(expected) = manifest.files.get(&key) else {
+            report.untracked.push(path);
+            continue;
+        };
+        seen.insert(key);
+        on_file(&path);
+        match hash_file(&path) {
+            Ok(actual) if actual == *expected => report.ok += 1,
+            Ok(_) => report.mismatched.push(path),
+            Err(e) => report.unreadable.push((path, e)),
+        }
+    }
+
+    report.missing = manifest
+        .files
+        .keys()
+        .filter(|k| !seen.contains(*k))
+        .map(|k| root.join(k))
+        .collect();
+    Ok(report)
+}
+
+pub(crate) fn hash_file(path: &Path) -> io::Result<ManifestEntry> {
+    let mut file = std::fs::File::open(crate::paths::extended(path))?;
+    let mut hasher = blake3::Hasher::new();
+    let size = io::copy(&mut file, &mut hasher)?;
+    Ok(ManifestEntry {
+        size,
+        blake3: hasher.finalize().to_hex().to_string(),
+    })
+}
+
+fn relative_key(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Audio files under `root`, recursively, skipping hidden entries like
+/// `search_directory` does. Sorted for stable progress and reports.
+fn audio_files(root: &Path, extensions: &ExtensionRegistry) -> Result<Vec<PathBuf>, ScanError> {
+    let options = ScanOptions {
+        extensions: extensions.clone(),
+        threads: Some(crate::scan::default_threads()),
+        ..ScanOptions::default()
+    };
+    let mut files: Vec<PathBuf> = scan_directory_iter(root, options)?
+        .flatten()
+        .map(|e| e.path)
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detects_corruption_missing_and_untracked() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("Kicks")).unwrap();
+        fs::write(root.join("Kicks/kick.wav"), b"kick").unwrap();
+        fs::write(root.join("snare.flac"), b"snare").unwrap();
+        fs::write(root.join("hat.mp3"), b"hat").unwrap();
+        fs::write(root.join("notes.txt"), b"ignored").unwrap();
+
+        let manifest = build_manifest(root, &ExtensionRegistry::default(), |_| {}).unwrap();
+        assert_eq!(manifest.files.len(), 3);
+        assert!(manifest.files.contains_key("Kicks/kick.wav"));
+        manifest.save(root).unwrap();
+        manifest.save(root).unwrap();
+        assert_eq!(Manifest::load(root).unwrap(), Some(manifest.clone()));
+        assert!(!root.join(format!("{MANIFEST_NAME}.part")).exists());
+
+        let clean = verify(root, &manifest, &ExtensionRegistry::default(), |_| {}).unwrap();
+        assert!(clean.is_clean());
+        assert_eq!(clean.ok, 3);
+
+        fs::write(root.join("Kicks/kick.wav"), b"kicK").unwrap(); // one flipped bit
+        fs::remove_file(root.join("hat.mp3")).unwrap();
+        fs::write(root.join("new.ogg"), b"new").unwrap();
+
+        let report = verify(root, &manifest, &ExtensionRegistry::default(), |_| {}).unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.ok, 1);
+        assert_eq!(report.mismatched, vec![root.join("Kicks/kick.wav")]);
+        assert_eq!(report.missing, vec![root.join("hat.mp3")]);
+        assert_eq!(report.untracked, vec![root.join("new.ogg")]);
+    }
+
+    #[test]
+    fn no_manifest_loads_as_none() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Manifest::load(dir.path()).unwrap(), None);
+    }
+}
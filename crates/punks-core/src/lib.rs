@@ -1,91 +1,208 @@
+pub mod collections;
 pub mod config;
-
-use std::ffi::OsStr;
-use std::fmt;
+pub mod cue;
+pub mod dedupe;
+pub mod dj;
+mod error;
+pub mod extensions;
+pub mod favorites;
+pub mod history;
+pub mod index;
+pub mod integrity;
+pub mod library;
+#[cfg(feature = "logging")]
+pub mod logging;
+pub mod network;
+pub mod paths;
+pub mod playlist;
+mod raw_path;
+pub mod scan;
+pub mod search;
+pub mod size;
+pub mod sort;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod trash;
+pub mod vfs;
+
+use std::ffi::{OsStr, OsString};
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+pub use error::{ErrorKind, ScanError};
+pub use extensions::ExtensionRegistry;
+pub use scan::{
+    scan_directory_iter, scan_in_background, ScanEvent, ScanIter, ScanOptions, ScanProgress,
+};
+
+/// Formats the decoder handles; the defaults of [`ExtensionRegistry`].
+pub const SUPPORTED_EXTENSIONS: &[&str] =
+    &["wav", "flac", "mp3", "ogg", "aif", "aiff", "aifc", "m4a"];
 
-pub const SUPPORTED_EXTENSIONS: &[&str] = &["wav", "flac", "mp3", "ogg"];
+/// Tracker modules. Listed and playable when built with the `tracker`
+/// feature, which punks-playback's feature of the same name turns on along
+/// with the renderer.
+pub const MODULE_EXTENSIONS: &[&str] = &["mod", "s3m", "xm", "it"];
+
+/// Opus, listed and playable when built with the `opus` feature, which
+/// punks-playback's feature of the same name turns on with the decoder.
+pub const OPUS_EXTENSIONS: &[&str] = &["opus"];
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileEntry {
+    #[cfg_attr(feature = "serde", serde(with = "raw_path::required"))]
     pub path: PathBuf,
+    /// `file_name` for display; names that aren't valid Unicode have their
+    /// bad bytes replaced, so never rebuild a path from this.
     pub name: String,
+    /// `path`'s final component exactly as on disk.
+    #[cfg_attr(feature = "serde", serde(with = "raw_path::os_string"))]
+    pub file_name: OsString,
     pub extension: String,
     pub size_bytes: u64,
     pub is_directory: bool,
+    /// Set for virtual entries that are only part of `path`, like the tracks
+    /// of a CUE sheet; play just this span.
+    pub region: Option<TrackRegion>,
+    /// For directories listed with [`ListOptions::count_audio`]: how many
+    /// listed audio files sit directly inside (not recursive).
+    pub audio_count: Option<usize>,
+    /// When it was last modified on disk, where that's known (not inside
+    /// archives, say).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub modified: Option<SystemTime>,
 }
 
-#[derive(Debug, Clone)]
-pub struct DirListing {
-    pub root: PathBuf,
-    pub entries: Vec<FileEntry>,
+/// What [`list_directory_with`] lists and any optional extra work.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListOptions {
+    /// Count the audio files directly inside each subdirectory. Costs one
+    /// extra `read_dir` per subdirectory.
+    pub count_audio: bool,
+    /// File types to list; the built-in formats by default.
+    pub extensions: ExtensionRegistry,
+    /// List dot-files and dot-folders too, and count them in
+    /// [`count_audio`](Self::count_audio).
+    pub show_hidden: bool,
+}
+
+/// A `[start, end)` span within an audio file. `end` of `None` runs to the
+/// end of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackRegion {
+    pub start: Duration,
+    pub end: Option<Duration>,
 }
 
 #[derive(Debug)]
-pub enum ScanError {
-    Io(io::Error),
-    NotADirectory,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirListing {
+    #[cfg_attr(feature = "serde", serde(with = "raw_path::required"))]
+    pub root: PathBuf,
+    pub entries: Vec<FileEntry>,
+    /// Entries that couldn't be read (permissions, broken symlinks, flaky
+    /// network shares) and so are absent from `entries`, with the reason.
+    /// Serialized, only the error message is kept.
+    #[cfg_attr(feature = "serde", serde(default, with = "raw_path::failures"))]
+    pub skipped: Vec<(PathBuf, io::Error)>,
 }
 
-impl fmt::Display for ScanError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ScanError::Io(e) => write!(f, "I/O error: {e}"),
-            ScanError::NotADirectory => write!(f, "path is not a directory"),
-        }
+/// Succeed if `path` is an accessible directory, else say why not.
+pub(crate) fn require_dir(path: &Path) -> Result<(), ScanError> {
+    let meta = std::fs::metadata(paths::extended(path)).map_err(|e| ScanError::io(path, e))?;
+    if meta.is_dir() {
+        Ok(())
+    } else {
+        Err(ScanError::NotADirectory(path.to_path_buf()))
     }
 }
 
-impl std::error::Error for ScanError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            ScanError::Io(e) => Some(e),
-            ScanError::NotADirectory => None,
-        }
-    }
+/// Extensions of files that are browsed into like folders: playlists and
+/// CUE sheets.
+pub const VIRTUAL_FOLDER_EXTENSIONS: &[&str] = &["m3u", "m3u8", "cue"];
+
+/// List a directory: subdirectories first, then audio files, each
+/// sorted case-insensitively. Playlists and CUE sheets appear among the
+/// subdirectories, and passing one as `dir` lists the files or tracks it
+/// references (see [`playlist`], [`cue`]).
+pub fn list_directory(dir: &Path) -> Result<DirListing, ScanError> {
+    list_directory_with(dir, &ListOptions::default())
 }
 
-impl From<io::Error> for ScanError {
-    fn from(e: io::Error) -> Self {
-        ScanError::Io(e)
-    }
+/// [`list_directory`] with extras from `opts`.
+pub fn list_directory_with(dir: &Path, opts: &ListOptions) -> Result<DirListing, ScanError> {
+    list_directory_cancellable(dir, opts, &AtomicBool::new(false))
 }
 
-pub fn list_directory(dir: &Path) -> Result<DirListing, ScanError> {
-    if !dir.is_dir() {
-        return Err(ScanError::NotADirectory);
+/// [`list_directory_with`] that stops with an [`Interrupted`] error once
+/// `cancel` is set, checked between entries; for listings given up on, like a
+/// slow share's (see [`network::with_timeout`]).
+///
+/// [`Interrupted`]: io::ErrorKind::Interrupted
+#[tracing::instrument(level = "debug", skip_all, fields(dir = %dir.display()))]
+pub fn list_directory_cancellable(
+    dir: &Path,
+    opts: &ListOptions,
+    cancel: &AtomicBool,
+) -> Result<DirListing, ScanError> {
+    if let Some(listing) = list_virtual(dir, &opts.extensions) {
+        return listing;
     }
+    require_dir(dir)?;
 
     let mut dirs: Vec<FileEntry> = Vec::new();
     let mut files: Vec<FileEntry> = Vec::new();
+    let mut skipped = Vec::new();
 
-    for entry in std::fs::read_dir(dir)? {
+    for entry in std::fs::read_dir(paths::extended(dir)).map_err(|e| ScanError::io(dir, e))? {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(ScanError::io(dir, io::ErrorKind::Interrupted.into()));
+        }
         let entry = match entry {
             Ok(e) => e,
-            Err(_) => continue,
+            // No name to report when the directory stream itself fails.
+            Err(e) => {
+                skipped.push((dir.to_path_buf(), e));
+                continue;
+            }
         };
 
-        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_name = entry.file_name();
+        let name = file_name.to_string_lossy().into_owned();
 
-        if name.starts_with('.') {
+        if name.starts_with('.') && !opts.show_hidden {
             continue;
         }
 
-        let metadata = match entry.metadata() {
+        // Joined onto `dir` rather than `entry.path()`, which would carry the
+        // extended-length prefix.
+        let path = dir.join(&file_name);
+
+        // Follows symlinks (unlike `DirEntry::metadata` on Unix), so linked
+        // samples list normally and dangling links show up in `skipped`.
+        let metadata = match std::fs::metadata(paths::extended(&path)) {
             Ok(m) => m,
-            Err(_) => continue,
+            Err(e) => {
+                skipped.push((path, e));
+                continue;
+            }
         };
 
-        let path = entry.path();
-
         if metadata.is_dir() {
+            let audio_count = opts.count_audio.then(|| count_audio_files(&path, opts));
             dirs.push(FileEntry {
                 path,
                 name,
+                file_name,
                 extension: String::new(),
                 size_bytes: 0,
                 is_directory: true,
+                region: None,
+                audio_count,
+                modified: metadata.modified().ok(),
             });
         } else if metadata.is_file() {
             let ext = path
@@ -94,18 +211,45 @@ pub fn list_directory(dir: &Path) -> Result<DirListing, ScanError> {
                 .map(|s| s.to_ascii_lowercase())
                 .unwrap_or_default();
 
-            if SUPPORTED_EXTENSIONS.contains(&ext.as_str()) {
+            if opts.extensions.is_listed(&ext) {
                 files.push(FileEntry {
                     name,
+                    file_name,
                     extension: ext,
                     size_bytes: metadata.len(),
                     path,
                     is_directory: false,
+                    region: None,
+                    audio_count: None,
+                    modified: metadata.modified().ok(),
+                });
+            } else if VIRTUAL_FOLDER_EXTENSIONS.contains(&ext.as_str()) {
+                // Browsed into like a folder.
+                dirs.push(FileEntry {
+                    name,
+                    file_name,
+                    extension: ext,
+                    size_bytes: metadata.len(),
+                    path,
+                    is_directory: true,
+                    region: None,
+                    audio_count: None,
+                    modified: metadata.modified().ok(),
                 });
             }
         }
     }
 
+    Ok(sorted_listing(dir, dirs, files, skipped))
+}
+
+/// Folders first, then files, each sorted case-insensitively.
+pub(crate) fn sorted_listing(
+    dir: &Path,
+    mut dirs: Vec<FileEntry>,
+    mut files: Vec<FileEntry>,
+    skipped: Vec<(PathBuf, io::Error)>,
+) -> DirListing {
     let ci_sort = |a: &FileEntry, b: &FileEntry| {
         a.name
             .to_ascii_lowercase()
@@ -117,89 +261,103 @@ pub fn list_directory(dir: &Path) -> Result<DirListing, ScanError> {
     let mut entries = dirs;
     entries.extend(files);
 
-    Ok(DirListing {
+    DirListing {
         root: dir.to_path_buf(),
         entries,
-    })
-}
-
-pub fn search_directory(
-    root: &Path,
-    query: &str,
-    extensions: &[&str],
-) -> Result<Vec<FileEntry>, ScanError> {
-    if !root.is_dir() {
-        return Err(ScanError::NotADirectory);
+        skipped,
     }
+}
 
-    let query_lower = query.to_ascii_lowercase();
-    let ext_lower: Vec<String> = extensions.iter().map(|e| e.to_ascii_lowercase()).collect();
-
-    let mut results = Vec::new();
-    let mut queue = std::collections::VecDeque::new();
-    queue.push_back(root.to_path_buf());
-
-    while let Some(current) = queue.pop_front() {
-        let entries = match std::fs::read_dir(&current) {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().into_owned();
-
-            if name.starts_with('.') {
-                continue;
-            }
-
-            let file_type = match entry.file_type() {
-                Ok(ft) => ft,
-                Err(_) => continue,
-            };
-
-            let path = entry.path();
-
-            if file_type.is_dir() {
-                queue.push_back(path);
-                continue;
-            }
-
-            if !name.to_ascii_lowercase().contains(&query_lower) {
-                continue;
-            }
+/// Listed audio files directly inside `dir`, skipping hidden ones unless
+/// `opts` shows them. An unreadable directory counts as empty; it'll report
+/// properly when entered.
+fn count_audio_files(dir: &Path, opts: &ListOptions) -> usize {
+    let Ok(entries) = std::fs::read_dir(paths::extended(dir)) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            (opts.show_hidden || !name.to_string_lossy().starts_with('.'))
+                && opts.extensions.lists_path(Path::new(&name))
+        })
+        .count()
+}
 
-            let ext = path
-                .extension()
-                .and_then(OsStr::to_str)
-                .map(|s| s.to_ascii_lowercase())
-                .unwrap_or_default();
+/// The listing of a virtual folder, or `None` if `path` isn't one.
+fn list_virtual(
+    path: &Path,
+    extensions: &ExtensionRegistry,
+) -> Option<Result<DirListing, ScanError>> {
+    if !path.is_file() {
+        return None;
+    }
+    if playlist::is_playlist(path) {
+        Some(playlist::list_playlist(path, extensions))
+    } else if cue::is_cue(path) {
+        Some(cue::list_cue(path, extensions))
+    } else {
+        None
+    }
+}
 
-            if !ext_lower.is_empty() && !ext_lower.contains(&ext) {
-                continue;
-            }
+/// Read a text file that may not be UTF-8. Playlists and CUE sheets from
+/// older tools are commonly Latin-1, which maps byte-for-byte onto `char`.
+pub(crate) fn read_text_lossy(path: &Path) -> io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    })
+}
 
-            let metadata = match entry.metadata() {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
+/// `path` if nothing is there yet, else the first of `stem (2).ext`,
+/// `stem (3).ext`, … that's free, for writing a file without replacing one.
+pub fn unused_path(path: &Path) -> PathBuf {
+    numbered_paths(path)
+        .find(|p| std::fs::symlink_metadata(paths::extended(p)).is_err())
+        .unwrap_or_else(|| path.to_path_buf())
+}
 
-            results.push(FileEntry {
-                name,
-                extension: ext,
-                size_bytes: metadata.len(),
-                path,
-                is_directory: false,
-            });
+/// `path`, then `stem (2).ext`, `stem (3).ext`, … for callers that need to
+/// claim a name themselves; see [`unused_path`].
+pub fn numbered_paths(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    let stem = path.file_stem().unwrap_or_default();
+    std::iter::once(path.to_path_buf()).chain((2..).map(move |n| {
+        let mut name = stem.to_os_string();
+        name.push(format!(" ({n})"));
+        if let Some(ext) = path.extension() {
+            name.push(".");
+            name.push(ext);
         }
-    }
+        path.with_file_name(name)
+    }))
+}
 
-    results.sort_by(|a, b| {
-        a.name
-            .to_ascii_lowercase()
-            .cmp(&b.name.to_ascii_lowercase())
-    });
+/// Files under `root`, recursively, whose type `extensions` lists and that
+/// match every word of `query` by name or folder, best first; see
+/// [`search`].
+#[tracing::instrument(level = "debug", skip(extensions), fields(root = %root.display()))]
+pub fn search_directory(
+    root: &Path,
+    query: &str,
+    extensions: &ExtensionRegistry,
+) -> Result<Vec<FileEntry>, ScanError> {
+    // Searching "inside" a playlist or CUE sheet filters its entries.
+    if let Some(listing) = list_virtual(root, extensions) {
+        return Ok(search::rank(listing?.entries, root, query));
+    }
 
-    Ok(results)
+    let options = ScanOptions {
+        extensions: extensions.clone(),
+        threads: Some(scan::default_threads()),
+        ..ScanOptions::default()
+    };
+    let files = scan::scan_directory_iter(root, options)?
+        .flatten()
+        .collect();
+    Ok(search::rank(files, root, query))
 }
 
 #[cfg(test)]
@@ -220,6 +378,21 @@ mod tests {
         dir
     }
 
+    #[test]
+    fn unused_path_numbers_names_already_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        let kick = dir.path().join("kick.wav");
+        assert_eq!(unused_path(&kick), kick);
+        fs::write(&kick, b"data").unwrap();
+        fs::write(dir.path().join("kick (2).wav"), b"data").unwrap();
+        assert_eq!(unused_path(&kick), dir.path().join("kick (3).wav"));
+        fs::write(dir.path().join("README"), b"data").unwrap();
+        assert_eq!(
+            unused_path(&dir.path().join("README")),
+            dir.path().join("README (2)")
+        );
+    }
+
     #[test]
     fn list_includes_subdirs_and_audio_files() {
         let dir = make_audio_dir();
@@ -285,6 +458,38 @@ mod tests {
         let names: Vec<&str> = result.entries.iter().map(|e| e.name.as_str()).collect();
         assert!(!names.iter().any(|n| n.starts_with('.')));
         assert_eq!(result.entries.len(), 1);
+
+        let opts = ListOptions {
+            show_hidden: true,
+            ..ListOptions::default()
+        };
+        let result = list_directory_with(dir.path(), &opts).unwrap();
+        let names: Vec<&str> = result.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec![".hiddendir", ".hidden.wav", "kick.wav"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn listing_round_trips_through_json() {
+        let dir = make_audio_dir();
+        let mut listing = list_directory(dir.path()).unwrap();
+        listing.skipped.push((
+            dir.path().join("locked.wav"),
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        ));
+
+        let json = serde_json::to_string(&listing).unwrap();
+        let back: DirListing = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.root, listing.root);
+        let paths =
+            |l: &DirListing| -> Vec<PathBuf> { l.entries.iter().map(|e| e.path.clone()).collect() };
+        assert_eq!(paths(&back), paths(&listing));
+        assert_eq!(back.entries[0].file_name, listing.entries[0].file_name);
+        assert_eq!(back.skipped[0].0, dir.path().join("locked.wav"));
+        assert_eq!(
+            back.skipped[0].1.to_string(),
+            listing.skipped[0].1.to_string()
+        );
     }
 
     #[test]
@@ -301,13 +506,103 @@ mod tests {
         fs::write(&file, b"data").unwrap();
         assert!(matches!(
             list_directory(&file),
-            Err(ScanError::NotADirectory)
+            Err(ScanError::NotADirectory(p)) if p == file
         ));
     }
 
     #[test]
     fn list_nonexistent_path() {
-        assert!(list_directory(Path::new("/nonexistent/path/xyz")).is_err());
+        let err = list_directory(Path::new("/nonexistent/path/xyz")).unwrap_err();
+        assert!(matches!(err, ScanError::NotFound(_)));
+        assert_eq!(err.path(), Path::new("/nonexistent/path/xyz"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_unreadable_directory_is_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let result = list_directory(&locked);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // Root ignores permission bits, so there's nothing to assert there.
+        if let Err(e) = result {
+            assert!(matches!(e, ScanError::PermissionDenied(ref p) if *p == locked));
+            assert_eq!(
+                e.to_string(),
+                format!("permission denied for {}", locked.display())
+            );
+        }
+    }
+
+    #[test]
+    fn list_treats_playlists_as_folders() {
+        let dir = make_audio_dir();
+        fs::write(dir.path().join("Set.m3u8"), "kick.wav\npad.ogg\n").unwrap();
+
+        let result = list_directory(dir.path()).unwrap();
+        let playlist = result
+            .entries
+            .iter()
+            .find(|e| e.name == "Set.m3u8")
+            .unwrap();
+        assert!(playlist.is_directory);
+
+        let inside = list_directory(&playlist.path).unwrap();
+        let names: Vec<&str> = inside.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["kick.wav", "pad.ogg"]);
+
+        let hits = search_directory(&playlist.path, "PAD", &ExtensionRegistry::default()).unwrap();
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[test]
+    fn list_counts_audio_in_subdirs_when_asked() {
+        let dir = make_audio_dir();
+        fs::write(dir.path().join("Loops/loop1.wav"), b"data").unwrap();
+        fs::write(dir.path().join("Loops/loop2.OGG"), b"data").unwrap();
+        fs::write(dir.path().join("Loops/.hidden.wav"), b"data").unwrap();
+        fs::write(dir.path().join("Loops/notes.txt"), b"data").unwrap();
+        fs::create_dir(dir.path().join("Loops/Nested")).unwrap();
+
+        let opts = ListOptions {
+            count_audio: true,
+            ..ListOptions::default()
+        };
+        let result = list_directory_with(dir.path(), &opts).unwrap();
+        let counts: Vec<(&str, Option<usize>)> = result
+            .entries
+            .iter()
+            .filter(|e| e.is_directory)
+            .map(|e| (e.name.as_str(), e.audio_count))
+            .collect();
+        assert_eq!(counts, vec![("Loops", Some(2)), ("One-Shots", Some(0))]);
+
+        let plain = list_directory(dir.path()).unwrap();
+        assert!(plain.entries.iter().all(|e| e.audio_count.is_none()));
+    }
+
+    #[test]
+    fn list_includes_custom_extensions() {
+        let dir = make_audio_dir();
+        fs::write(dir.path().join("break.rx2"), b"data").unwrap();
+
+        let mut opts = ListOptions::default();
+        opts.extensions.register("rx2", false);
+        let result = list_directory_with(dir.path(), &opts).unwrap();
+        assert!(result.entries.iter().any(|e| e.name == "break.rx2"));
+        assert!(!list_directory(dir.path())
+            .unwrap()
+            .entries
+            .iter()
+            .any(|e| e.name == "break.rx2"));
+
+        let hits = search_directory(dir.path(), "break", &opts.extensions).unwrap();
+        assert_eq!(hits.len(), 1);
     }
 
     #[test]
@@ -334,6 +629,41 @@ mod tests {
         assert_eq!(entry.size_bytes, 5);
     }
 
+    // macOS filesystems refuse names that aren't UTF-8.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn list_keeps_non_utf8_names_exact() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let raw = OsStr::from_bytes(b"caf\xe9 kick.wav");
+        fs::write(dir.path().join(raw), b"data").unwrap();
+
+        let result = list_directory(dir.path()).unwrap();
+        let entry = &result.entries[0];
+        assert_eq!(entry.file_name, raw);
+        assert_eq!(entry.name, "caf\u{fffd} kick.wav");
+        assert_eq!(fs::read(&entry.path).unwrap(), b"data");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_reports_unreadable_entries_as_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kick.wav"), b"data").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("kick.wav"), dir.path().join("link.wav"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.path().join("gone.wav"), dir.path().join("dangling.wav"))
+            .unwrap();
+
+        let result = list_directory(dir.path()).unwrap();
+        let names: Vec<&str> = result.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["kick.wav", "link.wav"]);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].0, dir.path().join("dangling.wav"));
+        assert_eq!(result.skipped[0].1.kind(), io::ErrorKind::NotFound);
+    }
+
     #[test]
     fn list_case_insensitive_extension() {
         let dir = tempfile::tempdir().unwrap();
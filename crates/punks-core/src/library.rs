@@ -0,0 +1,381 @@
+//! A persistent index of every file under the library's folders, so search
+//! doesn't walk a big sample drive on every launch. Each folder is stored
+//! with its modification time; a [`Library::refresh`] still visits every
+//! folder, but only re-reads the ones whose time changed (a file added,
+//! removed or renamed in them) and reuses the rest from the index. A file
+//! rewritten in place keeps its old size until its folder changes.
+//!
+//! Dot-files and dot-folders aren't indexed, and symlinked folders aren't
+//! followed, as in [`scan_directory_iter`](crate::scan_directory_iter).
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{paths, raw_path, ExtensionRegistry, FileEntry, ScanError};
+
+/// A file as of its folder's last read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexedFile {
+    #[serde(with = "raw_path::os_string")]
+    name: OsString,
+    size: u64,
+    /// Seconds since the Unix epoch.
+    modified: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Name(#[serde(with = "raw_path::os_string")] OsString);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Folder {
+    /// Nanoseconds since the Unix epoch; `None` if the platform can't tell,
+    /// in which case it's re-read every time.
+    modified: Option<u64>,
+    folders: Vec<Name>,
+    files: Vec<IndexedFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredFolder {
+    #[serde(with = "raw_path::required")]
+    path: PathBuf,
+    #[serde(flatten)]
+    folder: Folder,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(with = "raw_path::list")]
+    roots: Vec<PathBuf>,
+    folders: Vec<StoredFolder>,
+}
+
+/// What a [`Library::refresh`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// Folders read from disk because they were new or had changed.
+    pub folders_read: usize,
+    /// Folders reused from the index.
+    pub folders_unchanged: usize,
+    /// Files now indexed under the refreshed root.
+    pub files: usize,
+}
+
+/// The indexed folders, keyed by path.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Library {
+    roots: Vec<PathBuf>,
+    folders: HashMap<PathBuf, Folder>,
+}
+
+impl Library {
+    /// Where the app keeps its library index; `None` without a config
+    /// directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("punks").join("library.json"))
+    }
+
+    /// Read the index at `path`; a missing file is an empty library.
+    pub fn load(path: &Path) -> io::Result<Library> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Library::default()),
+            Err(e) => return Err(e),
+        };
+        let stored: Stored = serde_json::from_str(&contents)?;
+        Ok(Library {
+            roots: stored.roots,
+            folders: stored
+                .folders
+                .into_iter()
+                .map(|s| (s.path, s.folder))
+                .collect(),
+        })
+    }
+
+    /// Write the index to `path`, creating its folder. Not pretty-printed:
+    /// a big library runs to megabytes.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut folders: Vec<StoredFolder> = self
+            .folders
+            .iter()
+            .map(|(path, folder)| StoredFolder {
+                path: path.clone(),
+                folder: folder.clone(),
+            })
+            .collect();
+        folders.sort_by(|a, b| a.path.cmp(&b.path));
+        let stored = Stored {
+            roots: self.roots.clone(),
+            folders,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(&stored)?)
+    }
+
+    /// The folders indexed, as passed to [`refresh`](Self::refresh).
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Whether everything under `path` is indexed.
+    pub fn covers(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /// Bring the index of `root` up to date, re-reading only folders that
+    /// changed since the last refresh and dropping ones that are gone.
+    /// Subfolders that can't be read are left out. Stops with an
+    /// [`Interrupted`](io::ErrorKind::Interrupted) error once `cancel` is
+    /// set, keeping what was refreshed so far.
+    #[tracing::instrument(level = "debug", skip_all, fields(root = %root.display()))]
+    pub fn refresh(&mut self, root: &Path, cancel: &AtomicBool) -> Result<RefreshStats, ScanError> {
+        crate::require_dir(root)?;
+        let mut stats = RefreshStats::default();
+        let mut reached = HashSet::new();
+        let mut stack = vec![root.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(ScanError::io(&dir, io::ErrorKind::Interrupted.into()));
+            }
+            let modified = std::fs::metadata(paths::extended(&dir))
+                .and_then(|m| m.modified())
+                .ok()
+                .map(|t| {
+                    t.duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_nanos() as u64)
+                });
+            let unchanged = modified.is_some()
+                && self
+                    .folders
+                    .get(&dir)
+                    .is_some_and(|f| f.modified == modified);
+            if unchanged {
+                stats.folders_unchanged += 1;
+            } else {
+                match read_folder(&dir, modified) {
+                    Ok(folder) => {
+                        stats.folders_read += 1;
+                        self.folders.insert(dir.clone(), folder);
+                    }
+                    Err(e) => {
+                        tracing::debug!("library: {}: {e}", dir.display());
+                        continue;
+                    }
+                }
+            }
+            let folder = &self.folders[&dir];
+            stats.files += folder.files.len();
+            stack.extend(folder.folders.iter().map(|name| dir.join(&name.0)));
+            reached.insert(dir);
+        }
+        self.folders
+            .retain(|path, _| !path.starts_with(root) || reached.contains(path));
+        if !self.covers(root) {
+            self.roots.retain(|r| !r.starts_with(root));
+            self.roots.push(root.to_path_buf());
+        }
+        Ok(stats)
+    }
+
+    /// Drop `root` and everything indexed under it, unless another root
+    /// still covers it.
+    pub fn forget(&mut self, root: &Path) {
+        self.roots.retain(|r| r != root);
+        if !self.covers(root) {
+            self.folders.retain(|path, _| !path.starts_with(root));
+        }
+    }
+
+    /// Files under `dir`, recursively, whose type `extensions` lists, in no
+    /// particular order.
+    pub fn files_under<'a>(
+        &'a self,
+        dir: &'a Path,
+        extensions: &'a ExtensionRegistry,
+    ) -> impl Iterator<Item = FileEntry> + 'a {
+        self.folders
+            .iter()
+            .filter(move |(path, _)| path.starts_with(dir))
+            .flat_map(move |(path, folder)| {
+                folder
+                    .files
+                    .iter()
+                    .filter_map(move |file| entry(path, file, extensions))
+            })
+    }
+
+    /// Indexed files under `root` matching `query` whose type `extensions`
+    /// lists; the index's answer to
+    /// [`search_directory`](crate::search_directory), ranked the same way.
+    pub fn search(
+        &self,
+        root: &Path,
+        query: &str,
+        extensions: &ExtensionRegistry,
+    ) -> Vec<FileEntry> {
+        crate::search::rank(self.files_under(root, extensions).collect(), root, query)
+    }
+
+    /// When `path` was last modified, as of the index.
+    pub fn modified(&self, path: &Path) -> Option<SystemTime> {
+        let folder = self.folders.get(path.parent()?)?;
+        let name = path.file_name()?;
+        let file = folder.files.iter().find(|f| f.name == name)?;
+        Some(UNIX_EPOCH + std::time::Duration::from_secs(file.modified))
+    }
+
+    /// Files indexed, of any type.
+    pub fn len(&self) -> usize {
+        self.folders.values().map(|f| f.files.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folders.values().all(|f| f.files.is_empty())
+    }
+}
+
+fn read_folder(dir: &Path, modified: Option<u64>) -> io::Result<Folder> {
+    let mut folder = Folder {
+        modified,
+        folders: Vec::new(),
+        files: Vec::new(),
+    };
+    for entry in std::fs::read_dir(paths::extended(dir))? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            folder.folders.push(Name(name));
+            continue;
+        }
+        // Follows symlinks, so linked samples are indexed like the rest.
+        let Ok(meta) = std::fs::metadata(paths::extended(&dir.join(&name))) else {
+            continue;
+        };
+        if meta.is_file() {
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            folder.files.push(IndexedFile {
+                name,
+                size: meta.len(),
+                modified,
+            });
+        }
+    }
+    Ok(folder)
+}
+
+fn entry(dir: &Path, file: &IndexedFile, extensions: &ExtensionRegistry) -> Option<FileEntry> {
+    let extension = Path::new(&file.name)
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_default();
+    extensions.is_listed(&extension).then(|| FileEntry {
+        path: dir.join(&file.name),
+        name: file.name.to_string_lossy().into_owned(),
+        file_name: file.name.clone(),
+        extension,
+        size_bytes: file.size,
+        is_directory: false,
+        region: None,
+        audio_count: None,
+        modified: Some(UNIX_EPOCH + std::time::Duration::from_secs(file.modified)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn names(mut entries: Vec<FileEntry>) -> Vec<String> {
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries.into_iter().map(|e| e.name).collect()
+    }
+
+    // Directories can't be opened as files to set their mtime on Windows.
+    #[cfg(unix)]
+    #[test]
+    fn rereads_only_changed_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("Drums/Kicks")).unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join("pad.wav"), b"pad").unwrap();
+        fs::write(root.join("notes.txt"), b"").unwrap();
+        fs::write(root.join("Drums/Kicks/808.wav"), b"").unwrap();
+        fs::write(root.join(".git/hidden.wav"), b"").unwrap();
+        let extensions = ExtensionRegistry::default();
+        let cancel = AtomicBool::new(false);
+
+        let mut library = Library::default();
+        let stats = library.refresh(root, &cancel).unwrap();
+        assert_eq!(stats.folders_read, 3);
+        assert_eq!(stats.files, 3);
+        assert!(library.covers(&root.join("Drums")));
+        let all: Vec<_> = library.files_under(root, &extensions).collect();
+        assert_eq!(names(all), ["808.wav", "pad.wav"]);
+
+        let again = library.refresh(root, &cancel).unwrap();
+        assert_eq!((again.folders_read, again.folders_unchanged), (0, 3));
+
+        // A new file bumps its folder's mtime; force it past coarse timestamps.
+        let kicks = root.join("Drums/Kicks");
+        fs::write(kicks.join("909.wav"), b"909").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::open(&kicks).unwrap().set_modified(later).unwrap();
+        let changed = library.refresh(root, &cancel).unwrap();
+        assert_eq!((changed.folders_read, changed.folders_unchanged), (1, 2));
+        let found = library.search(root, "90", &extensions);
+        assert_eq!(names(found.clone()), ["909.wav"]);
+        assert_eq!(found[0].size_bytes, 3);
+        assert_eq!(found[0].path, kicks.join("909.wav"));
+
+        fs::remove_dir_all(root.join("Drums")).unwrap();
+        library.refresh(root, &cancel).unwrap();
+        assert_eq!(library.len(), 2, "pad.wav and notes.txt");
+    }
+
+    #[test]
+    fn survives_a_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let samples = dir.path().join("Samples");
+        fs::create_dir(&samples).unwrap();
+        fs::write(samples.join("snare.flac"), b"").unwrap();
+        let file = dir.path().join("punks").join("library.json");
+        assert!(Library::load(&file).unwrap().is_empty());
+
+        let mut library = Library::default();
+        library.refresh(&samples, &AtomicBool::new(false)).unwrap();
+        library.save(&file).unwrap();
+        let back = Library::load(&file).unwrap();
+        assert_eq!(back, library);
+        assert_eq!(back.roots(), [samples.as_path()]);
+
+        let mut library = back;
+        library.forget(&samples);
+        assert!(library.is_empty());
+        assert!(!library.covers(&samples));
+    }
+}
@@ -0,0 +1,34 @@
+//! Logging setup shared by the punks binaries, so the CLI and the standalone
+//! app read `RUST_LOG` and `PUNKS_TRACE` the same way.
+
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+/// Log to stderr, filtered by `RUST_LOG` (errors only by default). With
+/// `PUNKS_TRACE=<file>`, every span and event from the punks crates also goes
+/// to that file, each span with its duration when it closes.
+pub fn init() {
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(EnvFilter::from_default_env());
+    let trace_file =
+        std::env::var_os("PUNKS_TRACE").and_then(|path| match std::fs::File::create(&path) {
+            Ok(file) => Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(std::sync::Mutex::new(file))
+                    .with_ansi(false)
+                    .with_thread_names(true)
+                    .with_span_events(FmtSpan::CLOSE)
+                    .with_filter(EnvFilter::new("info,punks=trace")),
+            ),
+            Err(e) => {
+                eprintln!("PUNKS_TRACE: {}: {e}", path.to_string_lossy());
+                None
+            }
+        });
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(trace_file)
+        .init();
+}
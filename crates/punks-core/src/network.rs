@@ -0,0 +1,198 @@
+//! Network shares (SMB, NFS and the like). A share that's slow or gone can
+//! block a filesystem call for a minute or more, and nothing can interrupt
+//! one in progress; so on paths that may be remote, listings run on their own
+//! thread and are given up on after a timeout, reported as
+//! [`ScanError::Unreachable`]. The thread finishes (or stays stuck) on its
+//! own, told to stop at its next chance.
+
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use crate::ScanError;
+
+/// How long a listing on a share gets by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Threads still stuck on shares past their timeout after which calls fail
+/// straight away rather than leave more behind.
+const MAX_STUCK: usize = 8;
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Filesystem types that live on another machine, as `/proc/self/mounts`
+/// names them.
+#[cfg(any(target_os = "linux", target_os = "android", test))]
+const NETWORK_FS: &[&str] = &[
+    "cifs",
+    "smb3",
+    "smbfs",
+    "nfs",
+    "nfs4",
+    "afs",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "davfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+];
+
+/// Whether `path` may be on a network share, so is worth a timeout: UNC paths
+/// (`\\server\share`) on Windows, network filesystem mounts on Linux, and on
+/// macOS anything under `/Volumes`, `/Network` or `/net` (external disks
+/// too, which can take as long to wake). Mapped drive letters on Windows
+/// aren't recognised. Relative paths never are.
+pub fn is_network_path(path: &Path) -> bool {
+    if cfg!(windows) {
+        let path = crate::paths::simplified(path);
+        return path.to_str().is_some_and(|s| {
+            (s.starts_with(r"\\") || s.starts_with("//")) && !s.starts_with(r"\\.\")
+        });
+    }
+    if !path.is_absolute() {
+        return false;
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        match std::fs::read_to_string("/proc/self/mounts") {
+            Ok(mounts) => mount_type(&mounts, path).is_some_and(|fs| NETWORK_FS.contains(&fs)),
+            Err(_) => false,
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        ["/Volumes", "/Network", "/net"]
+            .iter()
+            .any(|root| path.starts_with(root))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+    {
+        false
+    }
+}
+
+/// The filesystem type of the mount `path` is on, from a mount table in the
+/// `/proc/self/mounts` format.
+#[cfg(any(target_os = "linux", target_os = "android", test))]
+fn mount_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_device, point, fs) = (fields.next()?, fields.next()?, fields.next()?);
+            let point = unescape_mount(point);
+            path.starts_with(&point).then_some((point.len(), fs))
+        })
+        .max_by_key(|&(len, _)| len)
+        .map(|(_, fs)| fs)
+}
+
+/// Undo the octal escapes (`\040` for a space) the mount table uses.
+#[cfg(any(target_os = "linux", target_os = "android", test))]
+fn unescape_mount(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 4);
+        match code.and_then(|c| u8::from_str_radix(c, 8).ok()) {
+            Some(byte) => {
+                out.push(char::from(byte));
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Run `f` for `path` on its own thread and wait at most `timeout` for it.
+/// `f` is handed a flag that's set once it has been given up on; long jobs
+/// should check it between calls and bail out.
+pub fn with_timeout<T: Send + 'static>(
+    path: &Path,
+    timeout: Duration,
+    f: impl FnOnce(&AtomicBool) -> Result<T, ScanError> + Send + 'static,
+) -> Result<T, ScanError> {
+    let unreachable = || ScanError::Unreachable {
+        path: path.to_path_buf(),
+        timeout,
+    };
+    if IN_FLIGHT.load(Ordering::Relaxed) >= MAX_STUCK {
+        return Err(unreachable());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let worker_cancel = Arc::clone(&cancel);
+    IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+    let spawned = std::thread::Builder::new()
+        .name("punks-share".into())
+        .spawn(move || {
+            let _ = tx.send(f(&worker_cancel));
+            IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+        });
+    if let Err(e) = spawned {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+        return Err(ScanError::io(path, e));
+    }
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            cancel.store(true, Ordering::Relaxed);
+            tracing::warn!("{}: no response in {timeout:?}", path.display());
+            Err(unreachable())
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(ScanError::io(
+            path,
+            io::Error::other("share access thread panicked"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_the_mount_a_path_is_on() {
+        let mounts = "\
+/dev/sda1 / ext4 rw 0 0
+//nas/samples /mnt/nas cifs rw 0 0
+/dev/sdb1 /mnt/nas/local\\040disk ext4 rw 0 0
+";
+        let on = |p: &str| mount_type(mounts, Path::new(p));
+        assert_eq!(on("/home/me/Samples"), Some("ext4"));
+        assert_eq!(on("/mnt/nas/Drums/kick.wav"), Some("cifs"));
+        assert_eq!(on("/mnt/nas/local disk/kick.wav"), Some("ext4"));
+        // Components, not string prefixes.
+        assert_eq!(on("/mnt/nasty"), Some("ext4"));
+    }
+
+    #[test]
+    fn gives_up_on_a_share_that_does_not_answer() {
+        let path = PathBuf::from("/mnt/nas");
+        let (tx, rx) = mpsc::channel();
+        let err = with_timeout(&path, Duration::from_millis(20), move |cancel| {
+            std::thread::sleep(Duration::from_millis(100));
+            let _ = tx.send(cancel.load(Ordering::Relaxed));
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(matches!(err, ScanError::Unreachable { .. }));
+        assert_eq!(err.kind(), crate::ErrorKind::Unreachable);
+        assert!(rx.recv().unwrap(), "the worker is told to stop");
+
+        let answered = with_timeout(&path, Duration::from_secs(5), |_| Ok(7));
+        assert_eq!(answered.unwrap(), 7);
+    }
+}
@@ -0,0 +1,152 @@
+//! Windows path quirks. Deeply nested packs run past `MAX_PATH` (260
+//! characters), which only the extended-length `\\?\` form avoids, and
+//! network shares (`\\server\share`) have roots that don't look like drive
+//! letters. Paths are kept in their familiar form for display and history;
+//! [`extended`] is applied right where the filesystem is touched.
+//!
+//! Everywhere but Windows these are no-ops.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+const VERBATIM: &str = r"\\?\";
+const VERBATIM_UNC: &str = r"\\?\UNC\";
+
+/// `path` in extended-length form for filesystem calls: `C:\a` becomes
+/// `\\?\C:\a` and `\\server\share\a` becomes `\\?\UNC\server\share\a`.
+/// Relative and already-verbatim paths are returned unchanged.
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) {
+        return Cow::Borrowed(path);
+    }
+    match path.to_str().and_then(extend_str) {
+        Some(s) => Cow::Owned(PathBuf::from(s)),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// `path` with any `\\?\` prefix removed where that's lossless, e.g. from
+/// `canonicalize`, so it reads and compares like a path the user typed.
+pub fn simplified(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) {
+        return Cow::Borrowed(path);
+    }
+    match path.to_str().and_then(simplify_str) {
+        Some(s) => Cow::Owned(PathBuf::from(s)),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// Short label for a breadcrumb or tab: the last component, or for a root
+/// the whole thing, e.g. `C:\` or `\\server\share`.
+pub fn display_name(path: &Path) -> String {
+    if let Some(name) = path.file_name() {
+        return name.to_string_lossy().into_owned();
+    }
+    let full = simplified(path).to_string_lossy().into_owned();
+    match full.strip_suffix('\\') {
+        Some(trimmed) if trimmed.starts_with(r"\\") => trimmed.to_string(),
+        _ => full,
+    }
+}
+
+fn extend_str(path: &str) -> Option<String> {
+    if path.starts_with(VERBATIM) || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        let mut parts = unc.split('\\');
+        let server = parts.next().filter(|s| !s.is_empty())?;
+        let share = parts.next().filter(|s| !s.is_empty())?;
+        let rest = normalize(parts);
+        Some(format!("{VERBATIM_UNC}{server}\\{share}\\{rest}"))
+    } else {
+        let (drive, rest) = split_drive(&path)?;
+        let rest = rest.strip_prefix('\\')?;
+        Some(format!(
+            "{VERBATIM}{drive}\\{}",
+            normalize(rest.split('\\'))
+        ))
+    }
+}
+
+fn simplify_str(path: &str) -> Option<String> {
+    if let Some(unc) = path.strip_prefix(VERBATIM_UNC) {
+        return Some(format!(r"\\{unc}"));
+    }
+    let rest = path.strip_prefix(VERBATIM)?;
+    // Only drive paths have a plain equivalent; `\\?\Volume{…}` doesn't.
+    split_drive(rest)?;
+    Some(rest.to_string())
+}
+
+/// Split `C:…` into `C:` and the remainder.
+fn split_drive(path: &str) -> Option<(&str, &str)> {
+    let bytes = path.as_bytes();
+    (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+        .then(|| path.split_at(2))
+}
+
+/// Resolve `.` and `..` and drop empty components. Verbatim paths are passed
+/// to the filesystem as-is, so nothing else will do this for them.
+fn normalize<'a>(parts: impl Iterator<Item = &'a str>) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    for part in parts {
+        match part {
+            "" | "." => {}
+            ".." => {
+                out.pop();
+            }
+            _ => out.push(part),
+        }
+    }
+    out.join("\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extends_drive_and_unc_paths() {
+        assert_eq!(
+            extend_str(r"C:\Samples\Drums").as_deref(),
+            Some(r"\\?\C:\Samples\Drums")
+        );
+        assert_eq!(
+            extend_str("D:/Packs/./Kits/../Loops/").as_deref(),
+            Some(r"\\?\D:\Packs\Loops")
+        );
+        assert_eq!(extend_str(r"C:\").as_deref(), Some(r"\\?\C:\"));
+        assert_eq!(
+            extend_str(r"\\nas\samples\Drums").as_deref(),
+            Some(r"\\?\UNC\nas\samples\Drums")
+        );
+        assert_eq!(extend_str(r"\\?\C:\x"), None);
+        assert_eq!(extend_str(r"relative\path"), None);
+        assert_eq!(extend_str(r"C:drive-relative"), None);
+        assert_eq!(extend_str(r"\\nas"), None);
+    }
+
+    #[test]
+    fn simplifies_verbatim_paths() {
+        assert_eq!(
+            simplify_str(r"\\?\C:\Samples").as_deref(),
+            Some(r"C:\Samples")
+        );
+        assert_eq!(
+            simplify_str(r"\\?\UNC\nas\samples\Drums").as_deref(),
+            Some(r"\\nas\samples\Drums")
+        );
+        assert_eq!(simplify_str(r"\\?\Volume{1234}\x"), None);
+        assert_eq!(simplify_str(r"C:\Samples"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn display_name_falls_back_to_root() {
+        assert_eq!(display_name(Path::new("/Volumes/Samples")), "Samples");
+        assert_eq!(display_name(Path::new("/")), "/");
+    }
+}
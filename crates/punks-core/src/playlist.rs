@@ -0,0 +1,166 @@
+//! M3U/M3U8 playlists presented as virtual folders. Plenty of curated sample
+//! sets ship a playlist rather than a folder layout, so `list_directory` lists
+//! a playlist's referenced audio files as if they lived in one directory.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{DirListing, ExtensionRegistry, FileEntry, ScanError};
+
+pub const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8"];
+
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| PLAYLIST_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Paths referenced by a playlist, in order. `#` lines (comments and
+/// `#EXTINF`-style directives) and blanks are skipped, relative entries
+/// resolve against `base`, and `file://` URLs are accepted; other URLs are
+/// ignored since there's nothing local to preview.
+pub fn parse_m3u(text: &str, base: &Path) -> Vec<PathBuf> {
+    text.trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let line = match line.strip_prefix("file://") {
+                Some(rest) => percent_decode(rest),
+                None if line.contains("://") => return None,
+                None => line.to_string(),
+            };
+            // Playlists made on Windows use backslashes even for relative
+            // entries; treat them as separators everywhere.
+            let line = if cfg!(windows) {
+                line
+            } else {
+                line.replace('\\', "/")
+            };
+            Some(base.join(line))
+        })
+        .collect()
+}
+
+/// Read `path` as a playlist and list the audio files it references, in
+/// playlist order. Entries that are missing or not a listed format go to
+/// `skipped`.
+pub(crate) fn list_playlist(
+    path: &Path,
+    extensions: &ExtensionRegistry,
+) -> Result<DirListing, ScanError> {
+    // `.m3u8` is UTF-8 by definition; plain `.m3u` is often Latin-1.
+    let text = crate::read_text_lossy(path).map_err(|e| ScanError::io(path, e))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for file in parse_m3u(&text, base) {
+        let ext = file
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(|s| s.to_ascii_lowercase())
+            .unwrap_or_default();
+        if !extensions.is_listed(&ext) {
+            let err = io::Error::new(io::ErrorKind::Unsupported, "not a supported audio format");
+            skipped.push((file, err));
+            continue;
+        }
+        match std::fs::metadata(&file) {
+            Ok(meta) if meta.is_file() => entries.push(FileEntry {
+                name: file
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                file_name: file.file_name().unwrap_or_default().to_os_string(),
+                extension: ext,
+                size_bytes: meta.len(),
+                path: file,
+                is_directory: false,
+                region: None,
+                audio_count: None,
+                modified: meta.modified().ok(),
+            }),
+            Ok(_) => skipped.push((file, io::Error::other("not a file"))),
+            Err(e) => skipped.push((file, e)),
+        }
+    }
+
+    Ok(DirListing {
+        root: path.to_path_buf(),
+        entries,
+        skipped,
+    })
+}
+
+/// `s` with each `%XX` escape turned back into its byte; a `%` not followed
+/// by two hex digits is kept as is.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn percent_decoding_keeps_stray_percent_signs() {
+        assert_eq!(percent_decode("hat%20open%2B"), "hat open+");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn parses_extended_m3u() {
+        let text = "\u{feff}#EXTM3U\n#EXTINF:1,Kick\nKicks/kick.wav\n\n\
+                    /abs/snare.wav\nfile:///abs/hat%20open.wav\nhttp://example.com/x.mp3\n";
+        let paths = parse_m3u(text, Path::new("/pack"));
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/pack/Kicks/kick.wav"),
+                PathBuf::from("/abs/snare.wav"),
+                PathBuf::from("/abs/hat open.wav"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_playlist_in_order_and_reports_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Kicks")).unwrap();
+        fs::write(dir.path().join("Kicks/kick.wav"), b"kick").unwrap();
+        fs::write(dir.path().join("snare.flac"), b"snare").unwrap();
+        let list = dir.path().join("set.m3u");
+        fs::write(&list, "snare.flac\nKicks\\kick.wav\ngone.wav\nnotes.txt\n").unwrap();
+
+        assert!(is_playlist(&list));
+        let listing = list_playlist(&list, &ExtensionRegistry::default()).unwrap();
+        let names: Vec<&str> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["snare.flac", "kick.wav"]);
+        assert_eq!(listing.skipped.len(), 2);
+        assert_eq!(listing.skipped[0].1.kind(), io::ErrorKind::NotFound);
+    }
+}
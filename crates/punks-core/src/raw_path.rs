@@ -0,0 +1,161 @@
+//! Serde for paths that may not be valid Unicode, which plain `PathBuf`
+//! serialization refuses (failing the whole save). Unicode paths are written
+//! as plain strings; others as their raw bytes (Unix) or UTF-16 units
+//! (Windows). The module itself handles `Option<PathBuf>`; the submodules
+//! other shapes.
+
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Text(String),
+    #[cfg(unix)]
+    Bytes {
+        bytes: Vec<u8>,
+    },
+    #[cfg(windows)]
+    Wide {
+        wide: Vec<u16>,
+    },
+}
+
+fn to_repr(name: &OsStr) -> Repr {
+    if let Some(s) = name.to_str() {
+        return Repr::Text(s.to_string());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Repr::Bytes {
+            bytes: name.as_bytes().to_vec(),
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        Repr::Wide {
+            wide: name.encode_wide().collect(),
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    Repr::Text(name.to_string_lossy().into_owned())
+}
+
+fn from_repr(repr: Repr) -> OsString {
+    match repr {
+        Repr::Text(s) => OsString::from(s),
+        #[cfg(unix)]
+        Repr::Bytes { bytes } => {
+            use std::os::unix::ffi::OsStringExt;
+            OsString::from_vec(bytes)
+        }
+        #[cfg(windows)]
+        Repr::Wide { wide } => {
+            use std::os::windows::ffi::OsStringExt;
+            OsString::from_wide(&wide)
+        }
+    }
+}
+
+pub fn serialize<S: Serializer>(path: &Option<PathBuf>, s: S) -> Result<S::Ok, S::Error> {
+    path.as_deref().map(|p| to_repr(p.as_os_str())).serialize(s)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<PathBuf>, D::Error> {
+    Ok(Option::<Repr>::deserialize(d)?.map(|r| PathBuf::from(from_repr(r))))
+}
+
+/// A plain `PathBuf`.
+pub mod required {
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{from_repr, to_repr, Repr};
+
+    pub fn serialize<S: Serializer>(path: &Path, s: S) -> Result<S::Ok, S::Error> {
+        to_repr(path.as_os_str()).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<PathBuf, D::Error> {
+        Ok(PathBuf::from(from_repr(Repr::deserialize(d)?)))
+    }
+}
+
+/// A list of `PathBuf`s.
+pub mod list {
+    use std::path::PathBuf;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{from_repr, to_repr, Repr};
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(paths.iter().map(|p| to_repr(p.as_os_str())))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<PathBuf>, D::Error> {
+        Ok(Vec::<Repr>::deserialize(d)?
+            .into_iter()
+            .map(|r| PathBuf::from(from_repr(r)))
+            .collect())
+    }
+}
+
+/// A bare file name.
+pub mod os_string {
+    use std::ffi::{OsStr, OsString};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{from_repr, to_repr, Repr};
+
+    pub fn serialize<S: Serializer>(name: &OsStr, s: S) -> Result<S::Ok, S::Error> {
+        to_repr(name).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<OsString, D::Error> {
+        Ok(from_repr(Repr::deserialize(d)?))
+    }
+}
+
+/// Paths that failed with an I/O error. Only the message survives the trip;
+/// it comes back as an [`io::ErrorKind::Other`](std::io::ErrorKind::Other).
+#[cfg(feature = "serde")]
+pub mod failures {
+    use std::io;
+    use std::path::PathBuf;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{from_repr, to_repr, Repr};
+
+    #[derive(Serialize, Deserialize)]
+    struct Failure {
+        path: Repr,
+        error: String,
+    }
+
+    pub fn serialize<S: Serializer>(
+        failures: &[(PathBuf, io::Error)],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.collect_seq(failures.iter().map(|(path, e)| Failure {
+            path: to_repr(path.as_os_str()),
+            error: e.to_string(),
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Vec<(PathBuf, io::Error)>, D::Error> {
+        Ok(Vec::<Failure>::deserialize(d)?
+            .into_iter()
+            .map(|f| (PathBuf::from(from_repr(f.path)), io::Error::other(f.error)))
+            .collect())
+    }
+}
@@ -0,0 +1,575 @@
+//! Walking a whole library one file at a time. [`scan_directory_iter`]
+//! yields entries as it reads them instead of collecting the tree first, so
+//! indexing or checking millions of files keeps only the open folders on the
+//! current path in memory.
+//!
+//! Reading a folder is mostly waiting on the disk, and disks (SSDs and
+//! network shares especially) answer several requests at once faster than
+//! one after another, so with [`ScanOptions::threads`] set the walk reads
+//! that many folders at a time. Each thread takes the next folder from a
+//! shared queue and adds the subfolders it finds; the files come back
+//! through a bounded channel, so a slow consumer holds the walk up rather
+//! than letting it fill memory.
+//!
+//! [`scan_in_background`] runs a walk on a thread of its own and sends what
+//! it finds in batches, with running totals, for a UI to show as it goes.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{DirEntry, ReadDir};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{paths, ExtensionRegistry, FileEntry, ScanError};
+
+/// Most threads [`default_threads`] picks; past this a disk answers no
+/// faster.
+const MAX_DEFAULT_THREADS: usize = 8;
+
+/// Entries a parallel walk gets ahead of its consumer by.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Longest a background scan holds on to files it found before sending
+/// them, and most it sends at once.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+const BATCH_FILES: usize = 1000;
+
+/// What [`scan_directory_iter`] walks and yields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// File types to yield; the built-in formats by default.
+    pub extensions: ExtensionRegistry,
+    /// Descend into dot-folders and yield dot-files too.
+    pub show_hidden: bool,
+    /// How many folder levels below the root to descend; `Some(0)` yields
+    /// only the root's own files. Unlimited by default.
+    pub max_depth: Option<usize>,
+    /// Folders to read at once, each on a thread of its own. `None` (the
+    /// default) walks on the calling thread, a folder at a time.
+    pub threads: Option<NonZeroUsize>,
+}
+
+/// Threads worth walking a big tree with: one per core, up to eight.
+pub fn default_threads() -> NonZeroUsize {
+    let cores = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    NonZeroUsize::new(cores.min(MAX_DEFAULT_THREADS)).unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Audio files under `dir`, recursively, as the walk finds them: depth
+/// first, in the order the filesystem returns entries (so unsorted), or in
+/// no particular order at all with [`ScanOptions::threads`] set. Symlinked
+/// folders aren't followed. A subfolder that can't be read comes out as an
+/// `Err` item and the walk carries on; use `.flatten()` to skip those.
+/// Fails up front only if `dir` itself isn't a readable directory.
+pub fn scan_directory_iter(dir: &Path, options: ScanOptions) -> Result<ScanIter, ScanError> {
+    crate::require_dir(dir)?;
+    let entries = std::fs::read_dir(paths::extended(dir)).map_err(|e| ScanError::io(dir, e))?;
+    let walk = match options.threads {
+        None => Walk::Serial {
+            stack: vec![(dir.to_path_buf(), entries)],
+            folders: 1,
+            options,
+        },
+        Some(threads) => {
+            // The threads open folders themselves, the root included.
+            drop(entries);
+            Walk::Parallel(
+                Parallel::start(dir, options, threads).map_err(|e| ScanError::io(dir, e))?,
+            )
+        }
+    };
+    Ok(ScanIter { walk })
+}
+
+/// The iterator from [`scan_directory_iter`].
+pub struct ScanIter {
+    walk: Walk,
+}
+
+impl ScanIter {
+    /// Folders opened so far, the root included.
+    pub fn folders_read(&self) -> usize {
+        match &self.walk {
+            Walk::Serial { folders, .. } => *folders,
+            Walk::Parallel(walk) => walk.shared.folders.load(Ordering::Relaxed),
+        }
+    }
+}
+
+enum Walk {
+    Serial {
+        /// The folders being read, from the root down to the current one.
+        stack: Vec<(PathBuf, ReadDir)>,
+        folders: usize,
+        options: ScanOptions,
+    },
+    Parallel(Parallel),
+}
+
+impl Iterator for ScanIter {
+    type Item = Result<FileEntry, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (stack, folders, options) = match &mut self.walk {
+            Walk::Serial {
+                stack,
+                folders,
+                options,
+            } => (stack, folders, options),
+            Walk::Parallel(walk) => return walk.found.recv().ok(),
+        };
+        loop {
+            let depth = stack.len().checked_sub(1)?;
+            let (dir, entries) = stack.last_mut()?;
+            let Some(entry) = entries.next() else {
+                stack.pop();
+                continue;
+            };
+            match examine(dir, entry, options) {
+                None => {}
+                Some(Found::Folder(path)) => {
+                    if options.max_depth.is_some_and(|max| depth >= max) {
+                        continue;
+                    }
+                    match std::fs::read_dir(paths::extended(&path)) {
+                        Ok(entries) => {
+                            stack.push((path, entries));
+                            *folders += 1;
+                        }
+                        Err(e) => return Some(Err(ScanError::io(&path, e))),
+                    }
+                }
+                Some(Found::File(file)) => return Some(file),
+            }
+        }
+    }
+}
+
+enum Found {
+    Folder(PathBuf),
+    File(Result<FileEntry, ScanError>),
+}
+
+/// What `entry` of `dir` is to the walk: a folder to descend into, a file
+/// to yield, or (`None`) something to pass over.
+fn examine(dir: &Path, entry: io::Result<DirEntry>, options: &ScanOptions) -> Option<Found> {
+    let entry = entry.ok()?;
+    let file_name = entry.file_name();
+    if !options.show_hidden && file_name.to_string_lossy().starts_with('.') {
+        return None;
+    }
+    let file_type = entry.file_type().ok()?;
+    let path = dir.join(&file_name);
+
+    if file_type.is_dir() {
+        return Some(Found::Folder(path));
+    }
+
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_default();
+    if !options.extensions.is_listed(&extension) {
+        return None;
+    }
+    let (size_bytes, modified) = match entry.metadata() {
+        Ok(m) => (m.len(), m.modified().ok()),
+        Err(e) => return Some(Found::File(Err(ScanError::io(&path, e)))),
+    };
+    Some(Found::File(Ok(FileEntry {
+        name: file_name.to_string_lossy().into_owned(),
+        file_name,
+        extension,
+        size_bytes,
+        path,
+        is_directory: false,
+        region: None,
+        audio_count: None,
+        modified,
+    })))
+}
+
+/// A walk on its own threads; stopped when dropped.
+struct Parallel {
+    found: Receiver<Result<FileEntry, ScanError>>,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Signalled when folders are queued or the walk ends.
+    changed: Condvar,
+    options: ScanOptions,
+    /// Folders opened so far.
+    folders: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Queue {
+    /// Folders still to read, with how far below the root they are.
+    folders: Vec<(PathBuf, usize)>,
+    /// Folders queued or being read; the walk is over when there are none.
+    pending: usize,
+    stopped: bool,
+}
+
+impl Parallel {
+    fn start(root: &Path, options: ScanOptions, threads: NonZeroUsize) -> io::Result<Parallel> {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                folders: vec![(root.to_path_buf(), 0)],
+                pending: 1,
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+            options,
+            folders: AtomicUsize::new(0),
+        });
+        let (tx, found) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        for i in 0..threads.get() {
+            let (shared, tx) = (Arc::clone(&shared), tx.clone());
+            let spawned = std::thread::Builder::new()
+                .name("punks-scan".into())
+                .spawn(move || shared.work(&tx));
+            match spawned {
+                Ok(_) => {}
+                // With one thread running the walk still finishes.
+                Err(e) if i > 0 => tracing::warn!("scan: {e}"),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Parallel { found, shared })
+    }
+}
+
+impl Drop for Parallel {
+    fn drop(&mut self) {
+        // Threads waiting for a folder stop here; ones waiting to send stop
+        // when the receiver goes.
+        self.shared.lock().stopped = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Read folders off the queue until there are none left.
+    fn work(&self, tx: &SyncSender<Result<FileEntry, ScanError>>) {
+        while let Some((dir, depth)) = self.next_folder() {
+            let sent = self.read(&dir, depth, tx);
+            let mut queue = self.lock();
+            queue.pending -= 1;
+            if !sent {
+                queue.stopped = true;
+            }
+            if queue.pending == 0 || queue.stopped {
+                self.changed.notify_all();
+            }
+        }
+    }
+
+    fn next_folder(&self) -> Option<(PathBuf, usize)> {
+        let mut queue = self.lock();
+        loop {
+            if queue.stopped {
+                return None;
+            }
+            if let Some(folder) = queue.folders.pop() {
+                return Some(folder);
+            }
+            if queue.pending == 0 {
+                return None;
+            }
+            queue = self.changed.wait(queue).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Send `dir`'s files and queue its folders; false once nobody's
+    /// listening.
+    fn read(
+        &self,
+        dir: &Path,
+        depth: usize,
+        tx: &SyncSender<Result<FileEntry, ScanError>>,
+    ) -> bool {
+        let entries = match std::fs::read_dir(paths::extended(dir)) {
+            Ok(entries) => entries,
+            Err(e) => return tx.send(Err(ScanError::io(dir, e))).is_ok(),
+        };
+        self.folders.fetch_add(1, Ordering::Relaxed);
+        let descend = self.options.max_depth.is_none_or(|max| depth < max);
+        for entry in entries {
+            match examine(dir, entry, &self.options) {
+                Some(Found::Folder(path)) if descend => {
+                    let mut queue = self.lock();
+                    queue.folders.push((path, depth + 1));
+                    queue.pending += 1;
+                    drop(queue);
+                    self.changed.notify_one();
+                }
+                Some(Found::File(file)) => {
+                    if tx.send(file).is_err() {
+                        return false;
+                    }
+                }
+                Some(Found::Folder(_)) | None => {}
+            }
+        }
+        true
+    }
+}
+
+/// How far a [background scan](scan_in_background) has got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// Folders opened, the root included.
+    pub folders: usize,
+    /// Files found that the scan's options asked for.
+    pub files: usize,
+}
+
+/// Reads like `12,340 files in 512 folders`.
+impl fmt::Display for ScanProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files in {} folders",
+            with_commas(self.files),
+            with_commas(self.folders)
+        )
+    }
+}
+
+/// `n` with a comma between each group of three digits.
+fn with_commas(n: usize) -> String {
+    let digits = n.to_string();
+    let mut text = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
+
+/// What a [background scan](scan_in_background) sends.
+#[derive(Debug)]
+pub enum ScanEvent {
+    /// Files found since the last event, and the totals so far.
+    Found(Vec<FileEntry>, ScanProgress),
+    /// The walk is over, with its totals; an error if the root couldn't be
+    /// read. Nothing comes after it.
+    Done(Result<ScanProgress, ScanError>),
+}
+
+/// Walk `dir` as [`scan_directory_iter`] does, but on a background thread,
+/// sending what it finds in batches at least every tenth of a second.
+/// Dropping the receiver stops the walk. Subfolders that can't be read are
+/// skipped.
+pub fn scan_in_background(dir: &Path, options: ScanOptions) -> Receiver<ScanEvent> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("punks-scan".into())
+        .spawn({
+            let (dir, tx) = (dir.to_path_buf(), tx.clone());
+            move || send_scan(&dir, options, &tx)
+        });
+    if let Err(e) = spawned {
+        let _ = tx.send(ScanEvent::Done(Err(ScanError::io(dir, e))));
+    }
+    rx
+}
+
+fn send_scan(dir: &Path, options: ScanOptions, tx: &mpsc::Sender<ScanEvent>) {
+    let mut walk = match scan_directory_iter(dir, options) {
+        Ok(walk) => walk,
+        Err(e) => {
+            let _ = tx.send(ScanEvent::Done(Err(e)));
+            return;
+        }
+    };
+    let mut progress = ScanProgress::default();
+    let mut batch = Vec::new();
+    let mut sent = Instant::now();
+    while let Some(found) = walk.next() {
+        match found {
+            Ok(file) => batch.push(file),
+            Err(e) => tracing::debug!("scan: {e}"),
+        }
+        if batch.len() >= BATCH_FILES || sent.elapsed() >= BATCH_INTERVAL {
+            progress.files += batch.len();
+            progress.folders = walk.folders_read();
+            if tx
+                .send(ScanEvent::Found(std::mem::take(&mut batch), progress))
+                .is_err()
+            {
+                return;
+            }
+            sent = Instant::now();
+        }
+    }
+    progress.files += batch.len();
+    progress.folders = walk.folders_read();
+    if !batch.is_empty() {
+        let _ = tx.send(ScanEvent::Found(batch, progress));
+    }
+    let _ = tx.send(ScanEvent::Done(Ok(progress)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn names(iter: ScanIter) -> Vec<String> {
+        let mut names: Vec<String> = iter.flatten().map(|e| e.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn walks_the_tree_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("Drums/Kicks")).unwrap();
+        fs::create_dir(root.join(".cache")).unwrap();
+        fs::write(root.join("pad.wav"), b"pad").unwrap();
+        fs::write(root.join("notes.txt"), b"").unwrap();
+        fs::write(root.join("Drums/snare.flac"), b"").unwrap();
+        fs::write(root.join("Drums/Kicks/808.WAV"), b"").unwrap();
+        fs::write(root.join(".cache/old.wav"), b"").unwrap();
+
+        let all = scan_directory_iter(root, ScanOptions::default()).unwrap();
+        assert_eq!(names(all), ["808.WAV", "pad.wav", "snare.flac"]);
+
+        let pad = scan_directory_iter(root, ScanOptions::default())
+            .unwrap()
+            .flatten()
+            .find(|e| e.name == "pad.wav")
+            .unwrap();
+        assert_eq!(pad.size_bytes, 3);
+        assert_eq!(pad.path, root.join("pad.wav"));
+
+        let shallow = ScanOptions {
+            max_depth: Some(1),
+            show_hidden: true,
+            ..ScanOptions::default()
+        };
+        let shallow = scan_directory_iter(root, shallow).unwrap();
+        assert_eq!(names(shallow), ["old.wav", "pad.wav", "snare.flac"]);
+    }
+
+    #[test]
+    fn threads_find_the_same_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let mut expected = Vec::new();
+        for folder in ["A", "A/1", "A/2", "B", "B/1/deep", ".hidden"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+            for i in 0..5 {
+                let name = format!("{}-{i}.wav", folder.replace('/', "_"));
+                fs::write(root.join(folder).join(&name), b"").unwrap();
+                if !folder.starts_with('.') {
+                    expected.push(name);
+                }
+            }
+        }
+        expected.sort();
+        let threads = ScanOptions {
+            threads: NonZeroUsize::new(3),
+            ..ScanOptions::default()
+        };
+
+        let all = scan_directory_iter(root, threads.clone()).unwrap();
+        assert_eq!(names(all), expected);
+        let serial = scan_directory_iter(root, ScanOptions::default()).unwrap();
+        assert_eq!(names(serial), expected);
+
+        let shallow = ScanOptions {
+            max_depth: Some(1),
+            ..threads.clone()
+        };
+        let shallow = scan_directory_iter(root, shallow).unwrap();
+        let top: Vec<&String> = expected
+            .iter()
+            .filter(|n| n.starts_with("A-") || n.starts_with("B-"))
+            .collect();
+        assert_eq!(names(shallow).iter().collect::<Vec<_>>(), top);
+
+        // Dropping the walk part way through stops its threads.
+        let mut partial = scan_directory_iter(root, threads).unwrap();
+        assert!(partial.next().is_some());
+        drop(partial);
+    }
+
+    #[test]
+    fn background_scans_send_batches_then_the_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Drums")).unwrap();
+        for name in ["pad.wav", "Drums/kick.wav", "Drums/snare.wav", "notes.txt"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let options = ScanOptions {
+            threads: NonZeroUsize::new(2),
+            ..ScanOptions::default()
+        };
+        let mut found = Vec::new();
+        let mut last = ScanProgress::default();
+        let events = scan_in_background(dir.path(), options.clone());
+        let done = loop {
+            match events.recv().unwrap() {
+                ScanEvent::Found(files, progress) => {
+                    assert_eq!(progress.files, last.files + files.len());
+                    found.extend(files.into_iter().map(|f| f.name));
+                    last = progress;
+                }
+                ScanEvent::Done(totals) => break totals.unwrap(),
+            }
+        };
+        found.sort();
+        assert_eq!(found, ["kick.wav", "pad.wav", "snare.wav"]);
+        assert_eq!(
+            done,
+            ScanProgress {
+                folders: 2,
+                files: 3
+            }
+        );
+        let big = ScanProgress {
+            folders: 512,
+            files: 1_234_567,
+        };
+        assert_eq!(big.to_string(), "1,234,567 files in 512 folders");
+
+        let missing = scan_in_background(&dir.path().join("nope"), options).recv();
+        assert!(matches!(
+            missing,
+            Ok(ScanEvent::Done(Err(ScanError::NotFound(_))))
+        ));
+    }
+
+    #[test]
+    fn fails_only_for_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("kick.wav");
+        fs::write(&file, b"").unwrap();
+        assert!(matches!(
+            scan_directory_iter(&file, ScanOptions::default()),
+            Err(ScanError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            scan_directory_iter(&dir.path().join("nope"), ScanOptions::default()),
+            Err(ScanError::NotFound(_))
+        ));
+    }
+}
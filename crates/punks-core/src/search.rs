@@ -0,0 +1,181 @@
+//! Matching and ranking search results. A query is split into words, each of
+//! which has to turn up in the file's name or in a folder between the search
+//! root and the file, so "kick 808" finds `808/Kick Hard.wav`. Files rank
+//! higher the more of the query their own name carries, and a word found at
+//! the start of a word in the name ranks above one found inside it. Words of
+//! three letters or more that don't appear whole also match a name holding
+//! their letters in order ("snre" finds "snare"), ranked below the rest.
+//! Words starting with `#` name tags (see [`crate::index`]) and `*3` asks for
+//! three stars or more, rather than text to match; [`split_filters`] splits
+//! them off for the caller to filter by.
+
+use std::path::Path;
+
+use crate::FileEntry;
+
+/// Shortest word matched by its letters in order rather than whole; shorter
+/// ones would match nearly everything.
+const FUZZY_MIN_LEN: usize = 3;
+
+/// A search split into the words every result has to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<String>,
+}
+
+impl Query {
+    pub fn new(query: &str) -> Self {
+        Query {
+            terms: query.split_whitespace().map(str::to_lowercase).collect(),
+        }
+    }
+
+    /// No words: everything matches.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// How well a file called `name`, in `folders` under the search root,
+    /// matches, higher being better; `None` if any word is missing.
+    pub fn score(&self, name: &str, folders: &str) -> Option<u32> {
+        let name = name.to_lowercase();
+        let folders = folders.to_lowercase();
+        self.terms.iter().try_fold(0, |total, term| {
+            let score = match (find(&name, term), find(&folders, term)) {
+                (Some(Found::WordStart), _) => 4,
+                (Some(Found::Inside), _) => 3,
+                (None, Some(Found::WordStart)) => 2,
+                (None, Some(Found::Inside)) => 1,
+                (None, None) if term.chars().count() >= FUZZY_MIN_LEN && in_order(&name, term) => 0,
+                (None, None) => return None,
+            };
+            Some(total + score)
+        })
+    }
+}
+
+/// What a search asks of the sample index besides its text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filters {
+    /// Tags every result has to carry, from `#tag` words.
+    pub tags: Vec<String>,
+    /// Fewest stars a result can have, from a `*3` word.
+    pub min_rating: Option<u8>,
+}
+
+impl Filters {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.min_rating.is_none()
+    }
+}
+
+/// `query`'s text to match, and the `#tag` and `*3` words taken out of it.
+pub fn split_filters(query: &str) -> (String, Filters) {
+    let mut filters = Filters::default();
+    let mut words = Vec::new();
+    for word in query.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#') {
+            if !tag.is_empty() {
+                filters.tags.push(tag.to_string());
+            }
+        } else if let Some(stars) = word.strip_prefix('*').and_then(|s| s.parse().ok()) {
+            filters.min_rating = Some(stars);
+        } else {
+            words.push(word);
+        }
+    }
+    (words.join(" "), filters)
+}
+
+/// The entries of `results` that match `query`, best first and by name
+/// among equals. Folders are taken from each path below `root`.
+pub fn rank(results: Vec<FileEntry>, root: &Path, query: &str) -> Vec<FileEntry> {
+    let query = Query::new(query);
+    let mut scored: Vec<(u32, String, FileEntry)> = results
+        .into_iter()
+        .filter_map(|entry| {
+            let folders = entry
+                .path
+                .parent()
+                .and_then(|dir| dir.strip_prefix(root).ok())
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let score = query.score(&entry.name, &folders)?;
+            Some((score, entry.name.to_lowercase(), entry))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, _, entry)| entry).collect()
+}
+
+enum Found {
+    /// At the start of `text` or of a word in it.
+    WordStart,
+    Inside,
+}
+
+/// Where `term` best appears in `text`, if anywhere.
+fn find(text: &str, term: &str) -> Option<Found> {
+    let mut found = None;
+    for (at, _) in text.match_indices(term) {
+        if word_starts_at(text, at) {
+            return Some(Found::WordStart);
+        }
+        found = Some(Found::Inside);
+    }
+    found
+}
+
+/// Whether a word starts at byte `at` of `text`: after a separator, or
+/// where letters turn to digits or back ("kick808").
+fn word_starts_at(text: &str, at: usize) -> bool {
+    let (Some(prev), Some(next)) = (text[..at].chars().next_back(), text[at..].chars().next())
+    else {
+        return true;
+    };
+    !prev.is_alphanumeric() || prev.is_alphabetic() != next.is_alphabetic()
+}
+
+/// Whether the letters of `term` appear in `text` in order.
+fn in_order(text: &str, term: &str) -> bool {
+    let mut chars = text.chars();
+    term.chars().all(|c| chars.any(|t| t == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_word_has_to_match_the_name_or_a_folder() {
+        let query = Query::new("Kick 808");
+        assert!(query.score("Kick Hard.wav", "Drums/808").is_some());
+        assert!(query.score("808 kick.wav", "").is_some());
+        assert_eq!(query.score("Kick Hard.wav", "Drums"), None);
+        assert_eq!(Query::new("").score("pad.wav", ""), Some(0));
+    }
+
+    #[test]
+    fn names_rank_above_folders_and_word_starts_above_the_middle() {
+        let query = Query::new("kick");
+        let start = query.score("kick_01.wav", "");
+        let inside = query.score("bigkick.wav", "");
+        let folder = query.score("01.wav", "Kicks");
+        let fuzzy = query.score("k_i_c_k.wav", "");
+        assert!(start > inside && inside > folder && folder > fuzzy);
+        assert!(fuzzy.is_some());
+        assert_eq!(Query::new("kc").score("k_c.wav", ""), None);
+        assert!(Query::new("808").score("Kick808.wav", "") > inside);
+    }
+
+    #[test]
+    fn tag_and_rating_words_are_split_off() {
+        let (text, filters) = split_filters("kick #dark 808 #Punchy # *4 *x");
+        assert_eq!(text, "kick 808 *x");
+        assert_eq!(filters.tags, ["dark", "Punchy"]);
+        assert_eq!(filters.min_rating, Some(4));
+        let (text, filters) = split_filters("#vinyl");
+        assert_eq!((text.as_str(), filters.min_rating), ("", None));
+        assert!(split_filters("snare").1.is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+//! Recursive folder totals: bytes on disk and audio file count, for finding
+//! which pack is eating the drive. Walks can take a while on big libraries,
+//! so progress is reported as the totals grow.
+
+use std::path::Path;
+
+use crate::{ExtensionRegistry, ScanError};
+
+/// Totals for everything beneath a folder. `bytes` counts every file, audio
+/// or not, since it's all taking up space; `audio_files` counts the types an
+/// [`ExtensionRegistry`] lists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FolderSize {
+    pub bytes: u64,
+    pub audio_files: usize,
+    pub files: usize,
+}
+
+/// How many files to count between progress callbacks.
+const PROGRESS_EVERY: usize = 256;
+
+/// Total up `root` recursively. `on_progress` receives the running totals
+/// every few hundred files and is not called for the final result, which is
+/// returned. Hidden entries are included (they use space too); symlinks are
+/// not followed, so linked folders aren't counted twice. Unreadable
+/// subdirectories are skipped.
+#[tracing::instrument(level = "debug", skip_all, fields(root = %root.display()))]
+pub fn folder_size(
+    root: &Path,
+    extensions: &ExtensionRegistry,
+    mut on_progress: impl FnMut(&FolderSize),
+) -> Result<FolderSize, ScanError> {
+    crate::require_dir(root)?;
+    let mut total = FolderSize::default();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(crate::paths::extended(&dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                stack.push(entry.path());
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            total.files += 1;
+            total.bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if extensions.lists_path(&entry.path()) {
+                total.audio_files += 1;
+            }
+            if total.files % PROGRESS_EVERY == 0 {
+                on_progress(&total);
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Byte count as a short human-readable size, e.g. `1.4 GB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn totals_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("Kicks/Sub")).unwrap();
+        fs::write(dir.path().join("Kicks/kick.wav"), [0u8; 100]).unwrap();
+        fs::write(dir.path().join("Kicks/Sub/deep.FLAC"), [0u8; 50]).unwrap();
+        fs::write(dir.path().join("readme.txt"), [0u8; 7]).unwrap();
+
+        let size = folder_size(dir.path(), &ExtensionRegistry::default(), |_| {}).unwrap();
+        assert_eq!(
+            size,
+            FolderSize {
+                bytes: 157,
+                audio_files: 2,
+                files: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn formats_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
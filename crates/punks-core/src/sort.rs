@@ -0,0 +1,163 @@
+//! Orders a folder listing can be shown in. Folders always come before
+//! files; entries that tie, and folders under orders only files have a value
+//! for (size, length), go by name. Entries without a value for the order,
+//! like a file whose length isn't known yet, go last either way round.
+
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::FileEntry;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortMode {
+    /// Case-insensitively, as listed.
+    #[default]
+    Name,
+    Size,
+    /// Length, which the caller supplies (it takes a decoder to know).
+    Duration,
+    /// Last modified on disk.
+    Modified,
+    /// File type, by extension.
+    Extension,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 5] = [
+        SortMode::Name,
+        SortMode::Size,
+        SortMode::Duration,
+        SortMode::Modified,
+        SortMode::Extension,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "Name",
+            SortMode::Size => "Size",
+            SortMode::Duration => "Length",
+            SortMode::Modified => "Modified",
+            SortMode::Extension => "Type",
+        }
+    }
+}
+
+/// Sort `entries` by `mode`, largest (or latest, or last) first when
+/// `descending`, taking lengths from `duration`.
+pub fn sort_entries(
+    entries: &mut [FileEntry],
+    mode: SortMode,
+    descending: bool,
+    duration: impl Fn(&FileEntry) -> Option<Duration>,
+) {
+    let name = |e: &FileEntry| e.name.to_ascii_lowercase();
+    entries.sort_by(|a, b| {
+        let by = match mode {
+            SortMode::Name => Ordering::Equal,
+            SortMode::Size if a.is_directory => Ordering::Equal,
+            SortMode::Size => by_value(Some(a.size_bytes), Some(b.size_bytes), descending),
+            SortMode::Duration => by_value(duration(a), duration(b), descending),
+            SortMode::Modified => by_value(a.modified, b.modified, descending),
+            SortMode::Extension => by_value(Some(&a.extension), Some(&b.extension), descending),
+        };
+        let by_name = || {
+            let order = name(a).cmp(&name(b));
+            if descending && mode == SortMode::Name {
+                order.reverse()
+            } else {
+                order
+            }
+        };
+        b.is_directory
+            .cmp(&a.is_directory)
+            .then(by)
+            .then_with(by_name)
+    });
+}
+
+/// `a` against `b`, flipped when `descending`; a missing value goes last
+/// either way.
+fn by_value<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::UNIX_EPOCH;
+
+    fn entry(name: &str, size: u64, modified: Option<u64>) -> FileEntry {
+        let extension = name.rsplit_once('.').map_or("", |(_, e)| e).to_string();
+        FileEntry {
+            path: PathBuf::from(name),
+            name: name.to_string(),
+            file_name: name.into(),
+            extension,
+            size_bytes: size,
+            is_directory: !name.contains('.'),
+            region: None,
+            audio_count: None,
+            modified: modified.map(|s| UNIX_EPOCH + Duration::from_secs(s)),
+        }
+    }
+
+    fn sorted(mode: SortMode, descending: bool) -> Vec<String> {
+        let mut entries = vec![
+            entry("b.wav", 300, Some(20)),
+            entry("Loops", 0, Some(5)),
+            entry("a.flac", 100, None),
+            entry("c.wav", 200, Some(10)),
+            entry("Drums", 0, Some(1)),
+        ];
+        let length = |e: &FileEntry| match e.name.as_str() {
+            "a.flac" => Some(Duration::from_secs(3)),
+            "c.wav" => Some(Duration::from_secs(1)),
+            _ => None,
+        };
+        sort_entries(&mut entries, mode, descending, length);
+        entries.into_iter().map(|e| e.name).collect()
+    }
+
+    #[test]
+    fn folders_first_then_by_the_mode() {
+        assert_eq!(
+            sorted(SortMode::Name, false),
+            ["Drums", "Loops", "a.flac", "b.wav", "c.wav"]
+        );
+        assert_eq!(
+            sorted(SortMode::Name, true),
+            ["Loops", "Drums", "c.wav", "b.wav", "a.flac"]
+        );
+        assert_eq!(
+            sorted(SortMode::Size, true),
+            ["Drums", "Loops", "b.wav", "c.wav", "a.flac"]
+        );
+        assert_eq!(
+            sorted(SortMode::Extension, false),
+            ["Drums", "Loops", "a.flac", "b.wav", "c.wav"]
+        );
+    }
+
+    #[test]
+    fn unknown_values_go_last_either_way() {
+        assert_eq!(
+            sorted(SortMode::Duration, false),
+            ["Drums", "Loops", "c.wav", "a.flac", "b.wav"]
+        );
+        assert_eq!(
+            sorted(SortMode::Duration, true),
+            ["Drums", "Loops", "a.flac", "c.wav", "b.wav"]
+        );
+        assert_eq!(
+            sorted(SortMode::Modified, true),
+            ["Loops", "Drums", "b.wav", "c.wav", "a.flac"]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Deleting samples without losing them: files go to the platform's trash
+//! (Recycle Bin, macOS Trash, or the XDG trash on Linux) so a slip of the
+//! Delete key can be undone. Some locations have no trash, like many network
+//! shares or a headless Linux box; removing files for good there is only done
+//! when the caller explicitly allows it.
+
+use std::path::{Path, PathBuf};
+
+use crate::{ErrorKind, ScanError};
+
+/// How a [`delete`] went through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deleted {
+    Trashed,
+    /// The trash was unavailable and permanent deletion was allowed.
+    Permanently,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrashError {
+    /// The platform trash refused the file; nothing was deleted.
+    #[error("couldn't move {} to the trash: {reason}", path.display())]
+    Unavailable { path: PathBuf, reason: String },
+    /// Permanent deletion was attempted and failed.
+    #[error("couldn't delete: {0}")]
+    Delete(#[source] ScanError),
+}
+
+impl TrashError {
+    pub fn path(&self) -> &Path {
+        match self {
+            TrashError::Unavailable { path, .. } => path,
+            TrashError::Delete(e) => e.path(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            TrashError::Unavailable { .. } => ErrorKind::Trash,
+            TrashError::Delete(e) => e.kind(),
+        }
+    }
+}
+
+/// Move `path` (a file or a whole folder) to the platform trash.
+pub fn move_to_trash(path: &Path) -> Result<(), TrashError> {
+    ::trash::delete(path).map_err(|e| TrashError::Unavailable {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })
+}
+
+/// Trash `path`, falling back to deleting it for good only when
+/// `allow_permanent` is set.
+pub fn delete(path: &Path, allow_permanent: bool) -> Result<Deleted, TrashError> {
+    match move_to_trash(path) {
+        Ok(()) => Ok(Deleted::Trashed),
+        Err(e) if !allow_permanent => Err(e),
+        Err(e) => {
+            tracing::warn!("{e}; deleting permanently");
+            remove_permanently(path).map(|()| Deleted::Permanently)
+        }
+    }
+}
+
+fn remove_permanently(path: &Path) -> Result<(), TrashError> {
+    let fs_path = crate::paths::extended(path);
+    // `symlink_metadata` so a link to a folder removes the link, not the
+    // folder's contents.
+    let result = match std::fs::symlink_metadata(&fs_path) {
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(&fs_path),
+        Ok(_) => std::fs::remove_file(&fs_path),
+        Err(e) => Err(e),
+    };
+    result.map_err(|e| TrashError::Delete(ScanError::io(path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn permanent_removal_handles_files_and_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("kick.wav");
+        let folder = dir.path().join("Loops");
+        fs::write(&file, b"data").unwrap();
+        fs::create_dir_all(folder.join("Nested")).unwrap();
+        fs::write(folder.join("Nested/loop.wav"), b"data").unwrap();
+
+        remove_permanently(&file).unwrap();
+        remove_permanently(&folder).unwrap();
+        assert!(!file.exists() && !folder.exists());
+
+        assert!(matches!(
+            remove_permanently(&file),
+            Err(TrashError::Delete(ScanError::NotFound(_)))
+        ));
+    }
+}
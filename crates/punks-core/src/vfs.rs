@@ -0,0 +1,236 @@
+//! Listing a library that isn't on a local disk. A web build has no
+//! `std::fs`, so it lists from a tree of paths and sizes fetched from
+//! wherever the library lives, typically the checksum manifest a shared
+//! library already carries (see [`integrity`](crate::integrity)). Listings
+//! come out as [`list_directory_with`](crate::list_directory_with) would
+//! produce them, except that playlists and CUE sheets aren't browsable:
+//! that needs their contents.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+use crate::integrity::Manifest;
+use crate::{DirListing, FileEntry, ListOptions, ScanError};
+
+/// Files by path, with their sizes. Folders exist implicitly, as the parents
+/// of files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryTree {
+    files: BTreeMap<PathBuf, u64>,
+}
+
+impl MemoryTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The files a manifest records, placed under `root`.
+    pub fn from_manifest(root: &Path, manifest: &Manifest) -> Self {
+        let mut tree = Self::new();
+        for (relative, entry) in &manifest.files {
+            tree.insert(root.join(relative), entry.size);
+        }
+        tree
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, size_bytes: u64) {
+        self.files.insert(path.into(), size_bytes);
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// List `dir` like [`list_directory_with`](crate::list_directory_with).
+    pub fn list(&self, dir: &Path, opts: &ListOptions) -> Result<DirListing, ScanError> {
+        if self.files.contains_key(dir) {
+            return Err(ScanError::NotADirectory(dir.to_path_buf()));
+        }
+
+        // Immediate children: files directly inside, and the first component
+        // of anything deeper, with its audio count.
+        let mut files = Vec::new();
+        let mut dirs: BTreeMap<PathBuf, usize> = BTreeMap::new();
+        let mut found = false;
+        for (path, &size) in self.descendants(dir) {
+            found = true;
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let mut parts = relative.components().filter_map(|c| match c {
+                Component::Normal(name) => Some(name),
+                _ => None,
+            });
+            let (Some(first), rest) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            if !opts.show_hidden && is_hidden(first) {
+                continue;
+            }
+            match rest {
+                None => {
+                    if let Some(entry) = file_entry(path, size, opts) {
+                        files.push(entry);
+                    }
+                }
+                Some(second) => {
+                    let count = dirs.entry(dir.join(first)).or_default();
+                    let direct = parts.next().is_none();
+                    if direct
+                        && (opts.show_hidden || !is_hidden(second))
+                        && opts.extensions.lists_path(Path::new(second))
+                    {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        if !found {
+            return Err(ScanError::NotFound(dir.to_path_buf()));
+        }
+
+        let dirs = dirs
+            .into_iter()
+            .map(|(path, count)| {
+                let file_name = path.file_name().unwrap_or_default().to_os_string();
+                FileEntry {
+                    name: file_name.to_string_lossy().into_owned(),
+                    file_name,
+                    extension: String::new(),
+                    size_bytes: 0,
+                    is_directory: true,
+                    region: None,
+                    audio_count: opts.count_audio.then_some(count),
+                    modified: None,
+                    path,
+                }
+            })
+            .collect();
+        Ok(crate::sorted_listing(dir, dirs, files, Vec::new()))
+    }
+
+    /// Files anywhere under `dir`, in path order.
+    fn descendants<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = (&'a PathBuf, &'a u64)> {
+        self.files
+            .range(dir.to_path_buf()..)
+            .take_while(move |(path, _)| path.starts_with(dir))
+    }
+}
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+fn file_entry(path: &Path, size: u64, opts: &ListOptions) -> Option<FileEntry> {
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_default();
+    if !opts.extensions.is_listed(&extension) {
+        return None;
+    }
+    let file_name = path.file_name()?.to_os_string();
+    Some(FileEntry {
+        path: path.to_path_buf(),
+        name: file_name.to_string_lossy().into_owned(),
+        file_name,
+        extension,
+        size_bytes: size,
+        is_directory: false,
+        region: None,
+        audio_count: None,
+        modified: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrity::ManifestEntry;
+
+    fn tree() -> MemoryTree {
+        let mut tree = MemoryTree::new();
+        for (path, size) in [
+            ("/lib/Kick.wav", 10),
+            ("/lib/snare.WAV", 20),
+            ("/lib/notes.txt", 1),
+            ("/lib/.hidden.wav", 1),
+            ("/lib/Loops/a.wav", 5),
+            ("/lib/Loops/b.flac", 5),
+            ("/lib/Loops/Deep/c.wav", 5),
+            ("/lib/.cache/d.wav", 5),
+            ("/library/other.wav", 5),
+        ] {
+            tree.insert(path, size);
+        }
+        tree
+    }
+
+    #[test]
+    fn lists_like_the_filesystem() {
+        let opts = ListOptions {
+            count_audio: true,
+            ..ListOptions::default()
+        };
+        let listing = tree().list(Path::new("/lib"), &opts).unwrap();
+        let entries: Vec<(&str, bool, Option<usize>)> = listing
+            .entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.is_directory, e.audio_count))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("Loops", true, Some(2)),
+                ("Kick.wav", false, None),
+                ("snare.WAV", false, None),
+            ]
+        );
+        assert_eq!(listing.entries[2].extension, "wav");
+        assert_eq!(listing.entries[2].size_bytes, 20);
+
+        let hidden = ListOptions {
+            show_hidden: true,
+            ..ListOptions::default()
+        };
+        let listing = tree().list(Path::new("/lib"), &hidden).unwrap();
+        assert_eq!(listing.entries.len(), 5);
+    }
+
+    #[test]
+    fn reports_missing_folders_and_files() {
+        let opts = ListOptions::default();
+        assert!(matches!(
+            tree().list(Path::new("/nope"), &opts),
+            Err(ScanError::NotFound(_))
+        ));
+        assert!(matches!(
+            tree().list(Path::new("/lib/Kick.wav"), &opts),
+            Err(ScanError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn builds_from_a_manifest() {
+        let mut manifest = Manifest::default();
+        manifest.files.insert(
+            "Drums/kick.wav".into(),
+            ManifestEntry {
+                size: 42,
+                blake3: String::new(),
+            },
+        );
+        let tree = MemoryTree::from_manifest(Path::new("/shared"), &manifest);
+        let listing = tree
+            .list(Path::new("/shared/Drums"), &ListOptions::default())
+            .unwrap();
+        assert_eq!(listing.entries[0].path, Path::new("/shared/Drums/kick.wav"));
+        assert_eq!(listing.entries[0].size_bytes, 42);
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * C API for punks: directory listing, file info and preview playback.
+ *
+ * Fallible calls return a PunksStatus and hand results back through out
+ * pointers; after a failure punks_last_error() says what went wrong.
+ * Handles come from a *_new / *_open call and are released with the matching
+ * *_free (freeing NULL does nothing). Strings are NUL-terminated UTF-8; on
+ * Unix, paths may be any bytes. Returned strings belong to the handle they
+ * came from. Handles aren't thread-safe: use each from one thread at a time.
+ */
+
+#ifndef PUNKS_H
+#define PUNKS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum PunksStatus {
+    PUNKS_OK = 0,
+    PUNKS_INVALID_ARGUMENT = 1,
+    PUNKS_NOT_FOUND = 2,
+    PUNKS_PERMISSION_DENIED = 3,
+    PUNKS_NOT_A_DIRECTORY = 4,
+    PUNKS_IO = 5,
+    PUNKS_DECODE = 6,
+    PUNKS_UNSUPPORTED_FORMAT = 7,
+    PUNKS_DEVICE = 8,
+    PUNKS_TOO_LARGE = 9,
+} PunksStatus;
+
+/* Message for the last failed call on this thread, or NULL. Valid until the
+ * next failing call on the same thread. */
+const char *punks_last_error(void);
+
+/* ---- Listings ---- */
+
+typedef struct PunksListing PunksListing;
+
+/* List a folder, playlist or CUE sheet: folders first, then audio files. */
+PunksStatus punks_listing_open(const char *dir, PunksListing **out);
+void punks_listing_free(PunksListing *listing);
+size_t punks_listing_count(const PunksListing *listing);
+/* NULL past the end. */
+const char *punks_listing_path(const PunksListing *listing, size_t index);
+const char *punks_listing_name(const PunksListing *listing, size_t index);
+bool punks_listing_is_directory(const PunksListing *listing, size_t index);
+uint64_t punks_listing_size(const PunksListing *listing, size_t index);
+/* For CUE tracks: the span in seconds (*end < 0 runs to the end of the
+ * file). False for whole files. */
+bool punks_listing_region(const PunksListing *listing, size_t index, double *start,
+                          double *end);
+
+/* ---- File info ---- */
+
+typedef struct PunksFileInfo PunksFileInfo;
+
+/* Read format and Broadcast Wave metadata without decoding audio. */
+PunksStatus punks_probe(const char *path, PunksFileInfo **out);
+void punks_file_info_free(PunksFileInfo *info);
+uint32_t punks_file_info_sample_rate(const PunksFileInfo *info);
+uint16_t punks_file_info_channels(const PunksFileInfo *info);
+/* 0 when the format has no fixed depth. */
+uint16_t punks_file_info_bit_depth(const PunksFileInfo *info);
+/* Seconds, or negative when the file doesn't state its length. */
+double punks_file_info_duration(const PunksFileInfo *info);
+/* NULL when absent. */
+const char *punks_file_info_description(const PunksFileInfo *info);
+const char *punks_file_info_originator(const PunksFileInfo *info);
+
+/* ---- Playback ---- */
+
+typedef struct PunksPlayer PunksPlayer;
+
+typedef enum PunksPlayerState {
+    PUNKS_IDLE = 0,
+    PUNKS_LOADING = 1,
+    PUNKS_PLAYING = 2,
+    PUNKS_FINISHED = 3,
+    PUNKS_RECONNECTING = 4,
+    PUNKS_PAUSED = 5,
+} PunksPlayerState;
+
+/* Seconds; duration is negative while unknown. */
+typedef struct PunksPlayerStatus {
+    PunksPlayerState state;
+    double position;
+    double duration;
+} PunksPlayerStatus;
+
+/* A player on the default audio output. */
+PunksStatus punks_player_new(PunksPlayer **out);
+/* A player with no device that reports files as playing; for tests. */
+PunksStatus punks_player_new_silent(PunksPlayer **out);
+void punks_player_free(PunksPlayer *player);
+PunksStatus punks_player_play(PunksPlayer *player, const char *path);
+/* end < 0 plays to the end of the file. */
+PunksStatus punks_player_play_region(PunksPlayer *player, const char *path, double start,
+                                     double end);
+PunksStatus punks_player_stop(PunksPlayer *player);
+PunksStatus punks_player_pause(PunksPlayer *player);
+PunksStatus punks_player_resume(PunksPlayer *player);
+/* Call regularly (e.g. once per frame). Returns a load or device error that
+ * happened since the last call. */
+PunksStatus punks_player_poll(PunksPlayer *player);
+PunksStatus punks_player_status(const PunksPlayer *player, PunksPlayerStatus *out);
+/* fraction is 0 to 1 of the loaded sample. */
+PunksStatus punks_player_seek(PunksPlayer *player, float fraction);
+/* seconds from the start of the playing sample; past a long file's preview it
+ * decodes from there. */
+PunksStatus punks_player_seek_to(PunksPlayer *player, double seconds);
+/* 0 to 1. */
+PunksStatus punks_player_set_volume(PunksPlayer *player, float volume);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PUNKS_H */
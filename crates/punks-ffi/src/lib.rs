@@ -0,0 +1,754 @@
+//! C ABI over scanning, file info and preview playback, for embedding punks
+//! in C/C++ hosts and other UI toolkits. The matching header is
+//! `include/punks.h`.
+//!
+//! Conventions:
+//! - Fallible calls return a [`PunksStatus`] and hand results back through
+//!   out pointers; after a failure [`punks_last_error`] says what went wrong.
+//! - Objects are opaque handles from a `*_new`/`*_open` call, released with
+//!   the matching `*_free`. Freeing NULL does nothing.
+//! - Strings are NUL-terminated UTF-8; on Unix, paths may be any bytes.
+//!   Strings the library returns belong to the handle they came from and
+//!   stay valid until it's freed.
+//! - Handles aren't thread-safe: use each from one thread at a time.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::Duration;
+
+use punks_core::{DirListing, ErrorKind, FileEntry, ScanError};
+use punks_playback::{
+    FileInfo, NullPlayback, Playback, PlaybackEngine, PlaybackError, PlaybackStatus, Region,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunksStatus {
+    Ok = 0,
+    /// A required pointer was NULL or a number was out of range.
+    InvalidArgument = 1,
+    NotFound = 2,
+    PermissionDenied = 3,
+    NotADirectory = 4,
+    Io = 5,
+    Decode = 6,
+    UnsupportedFormat = 7,
+    /// The audio output couldn't be opened or failed.
+    Device = 8,
+    /// The audio would take more memory than the player allows.
+    TooLarge = 9,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record `message` for [`punks_last_error`] and return `status`.
+fn fail(status: PunksStatus, message: impl Display) -> PunksStatus {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+impl From<ErrorKind> for PunksStatus {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::NotFound => PunksStatus::NotFound,
+            ErrorKind::PermissionDenied => PunksStatus::PermissionDenied,
+            ErrorKind::NotADirectory => PunksStatus::NotADirectory,
+            ErrorKind::Decode | ErrorKind::Encode => PunksStatus::Decode,
+            ErrorKind::UnsupportedFormat => PunksStatus::UnsupportedFormat,
+            ErrorKind::Device => PunksStatus::Device,
+            ErrorKind::TooLarge => PunksStatus::TooLarge,
+            ErrorKind::NoSelection => PunksStatus::InvalidArgument,
+            ErrorKind::Io
+            | ErrorKind::Unreachable
+            | ErrorKind::Trash
+            | ErrorKind::Midi
+            | ErrorKind::Remote => PunksStatus::Io,
+        }
+    }
+}
+
+fn scan_failed(e: ScanError) -> PunksStatus {
+    fail(e.kind().into(), e)
+}
+
+fn playback_failed(e: PlaybackError) -> PunksStatus {
+    fail(e.kind().into(), e)
+}
+
+fn null_argument(name: &str) -> PunksStatus {
+    fail(PunksStatus::InvalidArgument, format!("{name} is NULL"))
+}
+
+/// The path `path` names, or `None` for NULL (or, off Unix, non-UTF-8).
+///
+/// # Safety
+/// `path` is NULL or a valid NUL-terminated string.
+unsafe fn path_arg(path: *const c_char) -> Option<PathBuf> {
+    if path.is_null() {
+        return None;
+    }
+    let bytes = CStr::from_ptr(path).to_bytes();
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        std::str::from_utf8(bytes).ok().map(PathBuf::from)
+    }
+}
+
+/// `path` for handing back to C. Paths can't contain NUL, so this only
+/// loses anything for non-Unicode paths off Unix.
+fn c_path(path: &Path) -> CString {
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+    CString::new(bytes).unwrap_or_default()
+}
+
+fn c_text(text: &str) -> CString {
+    CString::new(text.replace('\0', " ")).unwrap_or_default()
+}
+
+fn c_str_or_null(s: Option<&CString>) -> *const c_char {
+    s.map_or(ptr::null(), |s| s.as_ptr())
+}
+
+/// The message for the last call on this thread that failed, or NULL. Valid
+/// until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn punks_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| c_str_or_null(last.borrow().as_ref()))
+}
+
+// ---- Listings ----
+
+/// One directory's listing: subdirectories (and playlists/CUE sheets, which
+/// browse like folders) first, then audio files.
+pub struct PunksListing {
+    entries: Vec<FileEntry>,
+    paths: Vec<CString>,
+    names: Vec<CString>,
+}
+
+impl From<DirListing> for PunksListing {
+    fn from(listing: DirListing) -> Self {
+        let entries = listing.entries;
+        PunksListing {
+            paths: entries.iter().map(|e| c_path(&e.path)).collect(),
+            names: entries.iter().map(|e| c_text(&e.name)).collect(),
+            entries,
+        }
+    }
+}
+
+/// List `dir` (a folder, playlist or CUE sheet) into `*out`.
+///
+/// # Safety
+/// `dir` is a NUL-terminated string and `out` points to writable storage for
+/// a handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_open(
+    dir: *const c_char,
+    out: *mut *mut PunksListing,
+) -> PunksStatus {
+    let Some(dir) = path_arg(dir) else {
+        return null_argument("dir");
+    };
+    if out.is_null() {
+        return null_argument("out");
+    }
+    match punks_core::list_directory(&dir) {
+        Ok(listing) => {
+            *out = Box::into_raw(Box::new(PunksListing::from(listing)));
+            PunksStatus::Ok
+        }
+        Err(e) => scan_failed(e),
+    }
+}
+
+/// # Safety
+/// `listing` is NULL or a handle from [`punks_listing_open`] not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_free(listing: *mut PunksListing) {
+    if !listing.is_null() {
+        drop(Box::from_raw(listing));
+    }
+}
+
+/// # Safety
+/// `listing` is NULL or a live listing handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_count(listing: *const PunksListing) -> usize {
+    listing.as_ref().map_or(0, |l| l.entries.len())
+}
+
+/// Entry `index`'s full path, or NULL past the end.
+///
+/// # Safety
+/// `listing` is NULL or a live listing handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_path(
+    listing: *const PunksListing,
+    index: usize,
+) -> *const c_char {
+    c_str_or_null(listing.as_ref().and_then(|l| l.paths.get(index)))
+}
+
+/// Entry `index`'s display name, or NULL past the end.
+///
+/// # Safety
+/// `listing` is NULL or a live listing handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_name(
+    listing: *const PunksListing,
+    index: usize,
+) -> *const c_char {
+    c_str_or_null(listing.as_ref().and_then(|l| l.names.get(index)))
+}
+
+/// Whether entry `index` opens like a folder.
+///
+/// # Safety
+/// `listing` is NULL or a live listing handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_is_directory(
+    listing: *const PunksListing,
+    index: usize,
+) -> bool {
+    listing
+        .as_ref()
+        .and_then(|l| l.entries.get(index))
+        .is_some_and(|e| e.is_directory)
+}
+
+/// Entry `index`'s file size in bytes; 0 for folders.
+///
+/// # Safety
+/// `listing` is NULL or a live listing handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_size(listing: *const PunksListing, index: usize) -> u64 {
+    listing
+        .as_ref()
+        .and_then(|l| l.entries.get(index))
+        .map_or(0, |e| e.size_bytes)
+}
+
+/// For entries that are only a span of their file (CUE tracks): store the
+/// span in seconds and return true. `*end` is negative when it runs to the
+/// end of the file. Returns false for whole files.
+///
+/// # Safety
+/// `listing` is NULL or a live listing handle; `start` and `end` point to
+/// writable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn punks_listing_region(
+    listing: *const PunksListing,
+    index: usize,
+    start: *mut f64,
+    end: *mut f64,
+) -> bool {
+    let region = listing
+        .as_ref()
+        .and_then(|l| l.entries.get(index))
+        .and_then(|e| e.region);
+    match (region, start.as_mut(), end.as_mut()) {
+        (Some(region), Some(start), Some(end)) => {
+            *start = region.start.as_secs_f64();
+            *end = region.end.map_or(-1.0, |e| e.as_secs_f64());
+            true
+        }
+        _ => false,
+    }
+}
+
+// ---- File info ----
+
+/// What a file is, from its headers.
+pub struct PunksFileInfo {
+    info: FileInfo,
+    description: Option<CString>,
+    originator: Option<CString>,
+}
+
+/// Read `path`'s format and Broadcast Wave metadata into `*out` without
+/// decoding any audio.
+///
+/// # Safety
+/// `path` is a NUL-terminated string and `out` points to writable storage
+/// for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_probe(
+    path: *const c_char,
+    out: *mut *mut PunksFileInfo,
+) -> PunksStatus {
+    let Some(path) = path_arg(path) else {
+        return null_argument("path");
+    };
+    if out.is_null() {
+        return null_argument("out");
+    }
+    match punks_playback::probe(&path) {
+        Ok(info) => {
+            let handle = PunksFileInfo {
+                description: info.metadata.description.as_deref().map(c_text),
+                originator: info.metadata.originator.as_deref().map(c_text),
+                info,
+            };
+            *out = Box::into_raw(Box::new(handle));
+            PunksStatus::Ok
+        }
+        Err(e) => playback_failed(e),
+    }
+}
+
+/// # Safety
+/// `info` is NULL or a handle from [`punks_probe`] not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_free(info: *mut PunksFileInfo) {
+    if !info.is_null() {
+        drop(Box::from_raw(info));
+    }
+}
+
+/// # Safety
+/// `info` is NULL or a live file info handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_sample_rate(info: *const PunksFileInfo) -> u32 {
+    info.as_ref().map_or(0, |i| i.info.sample_rate)
+}
+
+/// # Safety
+/// `info` is NULL or a live file info handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_channels(info: *const PunksFileInfo) -> u16 {
+    info.as_ref().map_or(0, |i| i.info.channels)
+}
+
+/// Bits per stored sample, or 0 when the format doesn't have a fixed depth
+/// (MP3, Vorbis, ...).
+///
+/// # Safety
+/// `info` is NULL or a live file info handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_bit_depth(info: *const PunksFileInfo) -> u16 {
+    info.as_ref().and_then(|i| i.info.bit_depth).unwrap_or(0)
+}
+
+/// Length in seconds, or negative when the file doesn't state it.
+///
+/// # Safety
+/// `info` is NULL or a live file info handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_duration(info: *const PunksFileInfo) -> f64 {
+    info.as_ref()
+        .and_then(|i| i.info.duration)
+        .map_or(-1.0, |d| d.as_secs_f64())
+}
+
+/// The Broadcast Wave description, or NULL.
+///
+/// # Safety
+/// `info` is NULL or a live file info handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_description(info: *const PunksFileInfo) -> *const c_char {
+    c_str_or_null(info.as_ref().and_then(|i| i.description.as_ref()))
+}
+
+/// The Broadcast Wave originator (recorder or tool), or NULL.
+///
+/// # Safety
+/// `info` is NULL or a live file info handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_file_info_originator(info: *const PunksFileInfo) -> *const c_char {
+    c_str_or_null(info.as_ref().and_then(|i| i.originator.as_ref()))
+}
+
+// ---- Playback ----
+
+/// A preview player. Call [`punks_player_poll`] regularly (e.g. once per UI
+/// frame): finished decodes are picked up there.
+pub struct PunksPlayer {
+    playback: Box<dyn Playback>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunksPlayerState {
+    Idle = 0,
+    Loading = 1,
+    Playing = 2,
+    /// The sample played through to its end.
+    Finished = 3,
+    /// The output device failed and is being reopened.
+    Reconnecting = 4,
+    /// Paused part way through; resumed with [`punks_player_resume`].
+    Paused = 5,
+}
+
+/// Where the player is. Times are in seconds; `duration` is negative when
+/// not yet known.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PunksPlayerStatus {
+    pub state: PunksPlayerState,
+    pub position: f64,
+    pub duration: f64,
+}
+
+fn new_player(playback: Box<dyn Playback>, out: *mut *mut PunksPlayer) -> PunksStatus {
+    // SAFETY: callers checked `out` is non-NULL; it's writable per their
+    // contracts.
+    unsafe { *out = Box::into_raw(Box::new(PunksPlayer { playback })) };
+    PunksStatus::Ok
+}
+
+/// Open a player on the default audio output into `*out`.
+///
+/// # Safety
+/// `out` points to writable storage for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_new(out: *mut *mut PunksPlayer) -> PunksStatus {
+    if out.is_null() {
+        return null_argument("out");
+    }
+    match PlaybackEngine::new() {
+        Ok(engine) => new_player(Box::new(engine), out),
+        Err(e) => playback_failed(e),
+    }
+}
+
+/// A player that opens no device and plays nothing, reporting each file as
+/// playing straight away; for tests and headless hosts.
+///
+/// # Safety
+/// `out` points to writable storage for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_new_silent(out: *mut *mut PunksPlayer) -> PunksStatus {
+    if out.is_null() {
+        return null_argument("out");
+    }
+    new_player(Box::new(NullPlayback::new()), out)
+}
+
+/// # Safety
+/// `player` is NULL or a handle from `punks_player_new*` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_free(player: *mut PunksPlayer) {
+    if !player.is_null() {
+        drop(Box::from_raw(player));
+    }
+}
+
+/// Start loading and playing `path`. Decode errors arrive later, from
+/// [`punks_player_poll`].
+///
+/// # Safety
+/// `player` is a live player handle and `path` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_play(
+    player: *mut PunksPlayer,
+    path: *const c_char,
+) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    let Some(path) = path_arg(path) else {
+        return null_argument("path");
+    };
+    player.playback.play(&path);
+    PunksStatus::Ok
+}
+
+/// Play `start` to `end` seconds of `path`; a negative `end` runs to the end
+/// of the file.
+///
+/// # Safety
+/// `player` is a live player handle and `path` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_play_region(
+    player: *mut PunksPlayer,
+    path: *const c_char,
+    start: f64,
+    end: f64,
+) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    let Some(path) = path_arg(path) else {
+        return null_argument("path");
+    };
+    let Ok(start) = Duration::try_from_secs_f64(start) else {
+        return fail(PunksStatus::InvalidArgument, format!("bad start {start}"));
+    };
+    let end = (end >= 0.0).then(|| Duration::try_from_secs_f64(end));
+    let Ok(end) = end.transpose() else {
+        return fail(PunksStatus::InvalidArgument, "bad end");
+    };
+    player.playback.play_region(&path, Region { start, end });
+    PunksStatus::Ok
+}
+
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_stop(player: *mut PunksPlayer) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    player.playback.stop();
+    PunksStatus::Ok
+}
+
+/// Hold playback where it is; a no-op unless a sample is playing.
+///
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_pause(player: *mut PunksPlayer) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    player.playback.pause();
+    PunksStatus::Ok
+}
+
+/// Carry on from where [`punks_player_pause`] stopped.
+///
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_resume(player: *mut PunksPlayer) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    player.playback.resume();
+    PunksStatus::Ok
+}
+
+/// Pick up finished decodes and device changes. Returns the error from a
+/// failed load or a lost device, if one happened since the last call.
+///
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_poll(player: *mut PunksPlayer) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    match player.playback.poll() {
+        Some(e) => playback_failed(e),
+        None => PunksStatus::Ok,
+    }
+}
+
+/// # Safety
+/// `player` is a live player handle and `out` points to a writable
+/// [`PunksPlayerStatus`].
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_status(
+    player: *const PunksPlayer,
+    out: *mut PunksPlayerStatus,
+) -> PunksStatus {
+    let Some(player) = player.as_ref() else {
+        return null_argument("player");
+    };
+    let Some(out) = out.as_mut() else {
+        return null_argument("out");
+    };
+    let secs = |d: Option<Duration>| d.map_or(-1.0, |d| d.as_secs_f64());
+    *out = match player.playback.status() {
+        PlaybackStatus::Idle => PunksPlayerStatus {
+            state: PunksPlayerState::Idle,
+            position: 0.0,
+            duration: -1.0,
+        },
+        PlaybackStatus::Loading { duration, .. } => PunksPlayerStatus {
+            state: PunksPlayerState::Loading,
+            position: 0.0,
+            duration: secs(duration),
+        },
+        PlaybackStatus::Playing {
+            position, duration, ..
+        } => PunksPlayerStatus {
+            state: PunksPlayerState::Playing,
+            position: position.as_secs_f64(),
+            duration: duration.as_secs_f64(),
+        },
+        PlaybackStatus::Paused {
+            position, duration, ..
+        } => PunksPlayerStatus {
+            state: PunksPlayerState::Paused,
+            position: position.as_secs_f64(),
+            duration: duration.as_secs_f64(),
+        },
+        PlaybackStatus::Finished { .. } => PunksPlayerStatus {
+            state: PunksPlayerState::Finished,
+            position: 0.0,
+            duration: secs(player.playback.loaded_duration()),
+        },
+        PlaybackStatus::Reconnecting { .. } => PunksPlayerStatus {
+            state: PunksPlayerState::Reconnecting,
+            position: 0.0,
+            duration: -1.0,
+        },
+    };
+    PunksStatus::Ok
+}
+
+/// Jump to `fraction` (0 to 1) of the loaded sample and play from there.
+///
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_seek(player: *mut PunksPlayer, fraction: f32) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    if !fraction.is_finite() {
+        return fail(PunksStatus::InvalidArgument, "fraction isn't a number");
+    }
+    player.playback.seek_fraction(fraction.clamp(0.0, 1.0));
+    PunksStatus::Ok
+}
+
+/// Jump to `seconds` into the playing sample and play from there, decoding
+/// from that point if it's past the end of a long file's preview.
+///
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_seek_to(
+    player: *mut PunksPlayer,
+    seconds: f64,
+) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    let Ok(position) = Duration::try_from_secs_f64(seconds) else {
+        return fail(
+            PunksStatus::InvalidArgument,
+            format!("bad position {seconds}"),
+        );
+    };
+    player.playback.seek(position);
+    PunksStatus::Ok
+}
+
+/// Preview volume, 0 to 1.
+///
+/// # Safety
+/// `player` is a live player handle.
+#[no_mangle]
+pub unsafe extern "C" fn punks_player_set_volume(
+    player: *mut PunksPlayer,
+    volume: f32,
+) -> PunksStatus {
+    let Some(player) = player.as_mut() else {
+        return null_argument("player");
+    };
+    if !volume.is_finite() {
+        return fail(PunksStatus::InvalidArgument, "volume isn't a number");
+    }
+    player.playback.set_volume(volume);
+    PunksStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn c(path: &Path) -> CString {
+        c_path(path)
+    }
+
+    #[test]
+    fn lists_a_folder_through_handles() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Loops")).unwrap();
+        fs::write(dir.path().join("kick.wav"), b"data").unwrap();
+        let path = c(dir.path());
+
+        unsafe {
+            let mut listing = ptr::null_mut();
+            assert_eq!(
+                punks_listing_open(path.as_ptr(), &mut listing),
+                PunksStatus::Ok
+            );
+            assert_eq!(punks_listing_count(listing), 2);
+            assert!(punks_listing_is_directory(listing, 0));
+            let name = CStr::from_ptr(punks_listing_name(listing, 1));
+            assert_eq!(name.to_str().unwrap(), "kick.wav");
+            assert_eq!(punks_listing_size(listing, 1), 4);
+            assert!(punks_listing_path(listing, 2).is_null());
+            punks_listing_free(listing);
+
+            let missing = c(&dir.path().join("nope"));
+            assert_eq!(
+                punks_listing_open(missing.as_ptr(), &mut listing),
+                PunksStatus::NotFound
+            );
+            assert!(!punks_last_error().is_null());
+            assert_eq!(
+                punks_listing_open(ptr::null(), &mut listing),
+                PunksStatus::InvalidArgument
+            );
+        }
+    }
+
+    #[test]
+    fn silent_player_reports_what_it_plays() {
+        let file = c(Path::new("/samples/kick.wav"));
+        unsafe {
+            let mut player = ptr::null_mut();
+            assert_eq!(punks_player_new_silent(&mut player), PunksStatus::Ok);
+            let mut status = PunksPlayerStatus {
+                state: PunksPlayerState::Idle,
+                position: 0.0,
+                duration: 0.0,
+            };
+
+            assert_eq!(
+                punks_player_play_region(player, file.as_ptr(), 1.0, 3.5),
+                PunksStatus::Ok
+            );
+            assert_eq!(punks_player_poll(player), PunksStatus::Ok);
+            punks_player_status(player, &mut status);
+            assert_eq!(status.state, PunksPlayerState::Playing);
+            assert_eq!(status.duration, 2.5);
+
+            assert_eq!(
+                punks_player_play_region(player, file.as_ptr(), -1.0, 2.0),
+                PunksStatus::InvalidArgument
+            );
+            assert_eq!(punks_player_seek_to(player, 1.5), PunksStatus::Ok);
+            assert_eq!(
+                punks_player_seek_to(player, f64::NAN),
+                PunksStatus::InvalidArgument
+            );
+            assert_eq!(punks_player_pause(player), PunksStatus::Ok);
+            punks_player_status(player, &mut status);
+            assert_eq!(status.state, PunksPlayerState::Paused);
+            assert_eq!(punks_player_resume(player), PunksStatus::Ok);
+            punks_player_status(player, &mut status);
+            assert_eq!(status.state, PunksPlayerState::Playing);
+            punks_player_stop(player);
+            punks_player_status(player, &mut status);
+            assert_eq!(status.state, PunksPlayerState::Idle);
+            punks_player_free(player);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Offline measurements over decoded, interleaved PCM: sample and true peak,
+//! clipping, audible bounds (for silence trimming) and BS.1770 integrated
+//! loudness. Nothing here runs on the audio thread.
+
+pub mod analyzer;
+pub mod classify;
+pub mod pool;
+pub mod tempo;
+
+use std::ops::Range;
+
+/// Linear gain for a level in dB.
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Level in dB for a linear gain (`-inf` for 0).
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.log10()
+}
+
+/// Largest absolute sample value across all channels.
+pub fn sample_peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, &s| peak.max(s.abs()))
+}
+
+/// Level a sample must reach to count towards clipping: -0.01 dBFS, so
+/// integer formats' largest positive value (just under 1.0) qualifies.
+const CLIP_LEVEL: f32 = 0.9989;
+
+/// Samples flat-topped at full scale: at [`CLIP_LEVEL`] or beyond with the
+/// same channel's previous or next sample there too. A lone full-scale sample
+/// is a hot peak, not a clip.
+pub fn clipped_samples(samples: &[f32], channels: usize) -> usize {
+    let channels = channels.max(1);
+    let hot = |i: usize| samples.get(i).is_some_and(|s| s.abs() >= CLIP_LEVEL);
+    (0..samples.len())
+        .filter(|&i| hot(i) && ((i >= channels && hot(i - channels)) || hot(i + channels)))
+        .count()
+}
+
+/// Taps in each phase of the true-peak oversampling filter.
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Windowed-sinc interpolators for the three in-between points of 4x
+/// oversampling (the fourth is the sample itself), over samples
+/// `n - 5 ..= n + 6`.
+fn oversampling_phases() -> [[f32; TRUE_PEAK_TAPS]; 3] {
+    let half = (TRUE_PEAK_TAPS / 2) as f64;
+    std::array::from_fn(|p| {
+        let frac = (p + 1) as f64 / 4.0;
+        std::array::from_fn(|k| {
+            let x = frac - (k as f64 - (half - 1.0));
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x)
+            };
+            let window = 0.5 + 0.5 * (std::f64::consts::PI * x / half).cos();
+            (sinc * window) as f32
+        })
+    })
+}
+
+/// Peak level between samples as well as on them (BS.1770-style 4x
+/// oversampling), linear. Stretches too quiet for the interpolation to reach
+/// full scale are skipped, so below 0 dBFS the result can read low; above it
+/// it's exact.
+pub fn true_peak(samples: &[f32], channels: usize) -> f32 {
+    let channels = channels.max(1);
+    let num_frames = samples.len() / channels;
+    let phases = oversampling_phases();
+    // Largest gain an interpolated point can have over its neighbourhood.
+    let gain = phases
+        .iter()
+        .map(|p| p.iter().map(|h| h.abs()).sum::<f32>())
+        .fold(0.0, f32::max);
+    let before = TRUE_PEAK_TAPS / 2 - 1;
+    let after = TRUE_PEAK_TAPS / 2;
+
+    let mut peak = sample_peak(samples);
+    const BLOCK: usize = 64;
+    for ch in 0..channels {
+        let at = |n: usize| samples[n * channels + ch];
+        for block in (0..num_frames).step_by(BLOCK) {
+            let lo = block.saturating_sub(before);
+            let hi = (block + BLOCK + after).min(num_frames);
+            let local = (lo..hi).map(|n| at(n).abs()).fold(0.0f32, f32::max);
+            if local * gain <= peak.max(1.0) {
+                continue;
+            }
+            for n in block..(block + BLOCK).min(num_frames) {
+                for phase in &phases {
+                    let mut y = 0.0;
+                    for (k, h) in phase.iter().enumerate() {
+                        let i = (n + k).checked_sub(before).filter(|&i| i < num_frames);
+                        if let Some(i) = i {
+                            y += h * at(i);
+                        }
+                    }
+                    peak = peak.max(y.abs());
+                }
+            }
+        }
+    }
+    peak
+}
+
+/// Frame range between the first and last frame where any channel reaches
+/// `threshold_db` (dBFS), or `None` if the whole buffer is below it.
+pub fn audible_frames(samples: &[f32], channels: usize, threshold_db: f32) -> Option<Range<usize>> {
+    let channels = channels.max(1);
+    let threshold = db_to_gain(threshold_db);
+    let loud = |frame: &[f32]| frame.iter().any(|s| s.abs() >= threshold);
+
+    let mut frames = samples.chunks_exact(channels);
+    let start = frames.position(loud)?;
+    let end = samples.len() / channels
+        - samples
+            .chunks_exact(channels)
+            .rev()
+            .position(loud)
+            .unwrap_or(0);
+    Some(start..end)
+}
+
+/// One direct-form-I biquad section, run per channel.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
+
+/// The two-stage K-weighting filter from ITU-R BS.1770, with coefficients
+/// derived for `rate` rather than the spec's 48 kHz table.
+fn k_weighting(rate: f64) -> [Biquad; 2] {
+    // Stage 1: high shelf modelling the head's acoustic effect.
+    let (f0, gain_db, q) = (
+        1_681.974_450_955_533,
+        3.999_843_853_973_347,
+        0.707_175_236_955_419_6,
+    );
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // Stage 2: RLB high-pass.
+    let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Integrated loudness in LUFS (ITU-R BS.1770-4: 400 ms blocks, 75 % overlap,
+/// -70 LUFS absolute gate, -10 LU relative gate). Every channel is weighted
+/// 1.0, which is exact for mono/stereo. Clips shorter than one block are
+/// measured as a single block so one-shots still get a value. `None` for
+/// silence.
+pub fn integrated_loudness(samples: &[f32], channels: usize, sample_rate: u32) -> Option<f32> {
+    let channels = channels.max(1);
+    let num_frames = samples.len() / channels;
+    if num_frames == 0 || sample_rate == 0 {
+        return None;
+    }
+
+    // K-weighted energy per 100 ms step; blocks are sums of four steps.
+    let step = (sample_rate as usize / 10).max(1);
+    let mut filters = vec![k_weighting(sample_rate as f64); channels];
+    let mut steps: Vec<f64> = Vec::with_capacity(num_frames / step + 1);
+    let mut acc = 0.0f64;
+    for (i, frame) in samples.chunks_exact(channels).enumerate() {
+        for (s, [shelf, hp]) in frame.iter().zip(filters.iter_mut()) {
+            let y = hp.process(shelf.process(*s as f64));
+            acc += y * y;
+        }
+        if (i + 1) % step == 0 {
+            steps.push(acc);
+            acc = 0.0;
+        }
+    }
+
+    let block_steps = 4;
+    let blocks: Vec<f64> = if steps.len() < block_steps {
+        let total: f64 = steps.iter().sum::<f64>() + acc;
+        vec![total / num_frames as f64]
+    } else {
+        steps
+            .windows(block_steps)
+            .map(|w| w.iter().sum::<f64>() / (step * block_steps) as f64)
+            .collect()
+    };
+
+    let loudness = |mean_square: f64| -0.691 + 10.0 * mean_square.log10();
+    let gated_mean = |threshold: f64| -> Option<f64> {
+        let above: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&z| z > 0.0 && loudness(z) > threshold)
+            .collect();
+        (!above.is_empty()).then(|| above.iter().sum::<f64>() / above.len() as f64)
+    };
+
+    let ungated = gated_mean(-70.0)?;
+    let integrated = gated_mean(loudness(ungated) - 10.0)?;
+    Some(loudness(integrated) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, amplitude: f32, rate: u32, secs: f32) -> Vec<f32> {
+        let n = (rate as f32 * secs) as usize;
+        (0..n)
+            .map(|i| amplitude * (i as f32 / rate as f32 * freq * std::f32::consts::TAU).sin())
+            .collect()
+    }
+
+    #[test]
+    fn peak_is_absolute() {
+        assert_eq!(sample_peak(&[0.1, -0.8, 0.5]), 0.8);
+        assert_eq!(sample_peak(&[]), 0.0);
+    }
+
+    #[test]
+    fn clipping_needs_consecutive_full_scale_samples() {
+        // Stereo: left clips for two frames, right has one lone peak.
+        let samples = [0.2, 0.0, 1.0, 0.1, -1.0, 0.99995, 0.3, 0.0];
+        assert_eq!(clipped_samples(&samples, 2), 2);
+        assert_eq!(clipped_samples(&[0.5, 1.0, 0.5], 1), 0);
+    }
+
+    #[test]
+    fn true_peak_finds_inter_sample_overs() {
+        // A quarter-rate sine sampled 45 degrees off its crests: every sample
+        // reads 0.707 of the real peak, so a +1.6 dB over hides under -1.4 dBFS.
+        let samples: Vec<f32> = (0..4_000)
+            .map(|i| {
+                1.2 * (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin()
+            })
+            .collect();
+        assert!(sample_peak(&samples) < 0.85);
+        let tp = true_peak(&samples, 1);
+        assert!((tp - 1.2).abs() < 0.05, "true peak {tp}");
+    }
+
+    #[test]
+    fn audible_frames_skips_leading_and_trailing_silence() {
+        // Stereo: 2 silent frames, 2 loud frames, 1 silent frame.
+        let samples = [0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, -0.5, 0.0, 0.0];
+        assert_eq!(audible_frames(&samples, 2, -20.0), Some(2..4));
+        assert_eq!(audible_frames(&[0.0; 8], 2, -60.0), None);
+    }
+
+    #[test]
+    fn full_scale_sine_measures_about_minus_three_lufs() {
+        let samples = sine(997.0, 1.0, 48_000, 2.0);
+        let lufs = integrated_loudness(&samples, 1, 48_000).unwrap();
+        assert!((lufs + 3.01).abs() < 0.2, "lufs = {lufs}");
+    }
+
+    #[test]
+    fn loudness_tracks_gain_and_rate() {
+        let loud = integrated_loudness(&sine(1000.0, 0.5, 44_100, 1.0), 1, 44_100).unwrap();
+        let quiet = integrated_loudness(&sine(1000.0, 0.05, 44_100, 1.0), 1, 44_100).unwrap();
+        assert!(((loud - quiet) - 20.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn silence_has_no_loudness() {
+        assert_eq!(integrated_loudness(&[0.0; 4800], 1, 48_000), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Pluggable per-file analysis. An [`Analyzer`] gets a file's decoded PCM
+//! and metadata and returns named values; an [`AnalyzerRegistry`] built at
+//! startup decodes each file once and runs every registered analyzer over
+//! it, so a genre classifier or BPM detector can ship as its own crate.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{analysis, decode, AudioMetadata, PlaybackError};
+
+/// One analysis result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisValue {
+    Number(f64),
+    Text(String),
+    Flag(bool),
+}
+
+impl From<f64> for AnalysisValue {
+    fn from(n: f64) -> Self {
+        AnalysisValue::Number(n)
+    }
+}
+
+impl From<f32> for AnalysisValue {
+    fn from(n: f32) -> Self {
+        AnalysisValue::Number(n.into())
+    }
+}
+
+impl From<String> for AnalysisValue {
+    fn from(s: String) -> Self {
+        AnalysisValue::Text(s)
+    }
+}
+
+impl From<&str> for AnalysisValue {
+    fn from(s: &str) -> Self {
+        AnalysisValue::Text(s.to_string())
+    }
+}
+
+impl From<bool> for AnalysisValue {
+    fn from(b: bool) -> Self {
+        AnalysisValue::Flag(b)
+    }
+}
+
+/// Results for one file, keyed `"<analyzer>.<key>"` (`"levels.peak_db"`).
+pub type AnalysisResults = BTreeMap<String, AnalysisValue>;
+
+/// A decoded file, as an analyzer sees it.
+pub struct AnalysisInput<'a> {
+    pub path: &'a Path,
+    /// Interleaved, at the file's own rate.
+    pub samples: &'a [f32],
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub metadata: &'a AudioMetadata,
+}
+
+impl AnalysisInput<'_> {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+pub trait Analyzer: Send + Sync {
+    /// Stable and unique among registered analyzers; prefixes every key it
+    /// returns.
+    fn name(&self) -> &str;
+
+    /// Bump when the output changes, so stored results from an older version
+    /// can be recomputed.
+    fn version(&self) -> u32 {
+        1
+    }
+
+    /// Values for one file, keyed without the analyzer's name. An error
+    /// drops this analyzer's results for the file; the others still run.
+    fn analyze(
+        &self,
+        input: &AnalysisInput<'_>,
+    ) -> Result<Vec<(String, AnalysisValue)>, Box<dyn Error + Send + Sync>>;
+}
+
+/// The built-in `levels` analyzer: `peak_db`, `true_peak_db`,
+/// `loudness_lufs` (absent for silent files), `clipped` and `duration`.
+pub struct LevelsAnalyzer;
+
+impl Analyzer for LevelsAnalyzer {
+    fn name(&self) -> &str {
+        "levels"
+    }
+
+    fn analyze(
+        &self,
+        input: &AnalysisInput<'_>,
+    ) -> Result<Vec<(String, AnalysisValue)>, Box<dyn Error + Send + Sync>> {
+        let (samples, channels) = (input.samples, input.channels.max(1));
+        let mut values = vec![
+            (
+                "peak_db".to_string(),
+                analysis::gain_to_db(analysis::sample_peak(samples)).into(),
+            ),
+            (
+                "true_peak_db".to_string(),
+                analysis::gain_to_db(analysis::true_peak(samples, channels)).into(),
+            ),
+            (
+                "clipped".to_string(),
+                (analysis::clipped_samples(samples, channels) as f64).into(),
+            ),
+            (
+                "duration".to_string(),
+                input.duration().as_secs_f64().into(),
+            ),
+        ];
+        if let Some(lufs) = analysis::integrated_loudness(samples, channels, input.sample_rate) {
+            values.push(("loudness_lufs".to_string(), lufs.into()));
+        }
+        Ok(values)
+    }
+}
+
+/// The analyzers to run on every file. Cheap to clone; analyzers are shared.
+#[derive(Clone, Default)]
+pub struct AnalyzerRegistry {
+    analyzers: Vec<Arc<dyn Analyzer>>,
+}
+
+impl AnalyzerRegistry {
+    /// The built-in analyzers.
+    pub fn with_builtins() -> Self {
+        let mut registry = AnalyzerRegistry::default();
+        registry.register(LevelsAnalyzer);
+        registry
+    }
+
+    /// Add `analyzer`, replacing any registered under the same name.
+    pub fn register(&mut self, analyzer: impl Analyzer + 'static) {
+        self.analyzers.retain(|a| a.name() != analyzer.name());
+        self.analyzers.push(Arc::new(analyzer));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Analyzer> {
+        self.analyzers.iter().map(|a| a.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.analyzers.is_empty()
+    }
+
+    /// Run every analyzer over `input`. Failures are logged and leave that
+    /// analyzer's keys out.
+    pub fn analyze(&self, input: &AnalysisInput<'_>) -> AnalysisResults {
+        let mut results = AnalysisResults::new();
+        for analyzer in &self.analyzers {
+            let name = analyzer.name();
+            match analyzer.analyze(input) {
+                Ok(values) => results.extend(
+                    values
+                        .into_iter()
+                        .map(|(key, value)| (format!("{name}.{key}"), value)),
+                ),
+                Err(e) => tracing::warn!("analyzer {name} on {}: {e}", input.path.display()),
+            }
+        }
+        results
+    }
+
+    /// Decode all of `path` once and run every analyzer over it.
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %path.display()))]
+    pub fn analyze_file(&self, path: &Path) -> Result<AnalysisResults, PlaybackError> {
+        if self.is_empty() {
+            return Ok(AnalysisResults::new());
+        }
+        let decoded = decode::decode_file_full(path)?;
+        Ok(self.analyze(&AnalysisInput {
+            path,
+            samples: &decoded.interleaved,
+            channels: decoded.channels.max(1) as usize,
+            sample_rate: decoded.sample_rate,
+            metadata: &decoded.metadata,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Brightness;
+
+    impl Analyzer for Brightness {
+        fn name(&self) -> &str {
+            "brightness"
+        }
+
+        fn analyze(
+            &self,
+            input: &AnalysisInput<'_>,
+        ) -> Result<Vec<(String, AnalysisValue)>, Box<dyn Error + Send + Sync>> {
+            if input.samples.is_empty() {
+                return Err("nothing to analyze".into());
+            }
+            let bright = input.sample_rate > 44_100;
+            Ok(vec![("bright".into(), bright.into())])
+        }
+    }
+
+    #[test]
+    fn runs_registered_analyzers_under_their_names() {
+        let mut registry = AnalyzerRegistry::with_builtins();
+        registry.register(Brightness);
+        let metadata = AudioMetadata::default();
+        let samples = [0.5, -0.5, 0.25, -0.25];
+        let input = AnalysisInput {
+            path: Path::new("hat.wav"),
+            samples: &samples,
+            channels: 2,
+            sample_rate: 48_000,
+            metadata: &metadata,
+        };
+
+        let results = registry.analyze(&input);
+        assert_eq!(results["brightness.bright"], AnalysisValue::Flag(true));
+        let AnalysisValue::Number(peak) = results["levels.peak_db"] else {
+            panic!("peak should be a number");
+        };
+        assert!((peak - analysis::gain_to_db(0.5) as f64).abs() < 1e-6);
+
+        let silent = AnalysisInput {
+            samples: &[],
+            ..input
+        };
+        let results = registry.analyze(&silent);
+        assert!(!results.contains_key("brightness.bright"));
+        assert!(results.contains_key("levels.peak_db"));
+
+        // Registering a name again replaces it.
+        registry.register(Brightness);
+        assert_eq!(registry.iter().count(), 2);
+    }
+}
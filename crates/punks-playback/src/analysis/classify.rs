@@ -0,0 +1,309 @@
+//! A rough guess at what kind of sound a sample is, from a few cheap
+//! features of its waveform: how long it sounds, how fast it hits, where its
+//! energy sits, how pitched it is and how much its level moves. Good enough
+//! to sort a pack into kicks, snares and pads for the user to check, not to
+//! be trusted blind.
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::decode::{self, DecodeLimits};
+use crate::PlaybackError;
+
+/// How much of a file is looked at; whatever a sample is, it's told by then.
+const WINDOW: Duration = Duration::from_secs(30);
+
+/// Envelope resolution, in seconds.
+const STEP: f32 = 0.01;
+
+/// Envelope steps per step of the coarser envelope that level movement is
+/// measured on; finer, and chords beating read as movement.
+const FLUCTUATION_STEPS: usize = 5;
+
+/// Level below the peak, as a gain, under which the sound counts as over.
+const AUDIBLE: f32 = 0.01;
+
+/// Below this and above that, the low and high bands.
+const LOW_HZ: f32 = 150.0;
+const HIGH_HZ: f32 = 5000.0;
+
+/// Pitches the periodicity search covers.
+const LOWEST_PITCH_HZ: f32 = 50.0;
+const HIGHEST_PITCH_HZ: f32 = 1000.0;
+const PERIODICITY_FRAMES: usize = 2048;
+
+/// The coarse categories samples are sorted into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Kick,
+    Snare,
+    Hat,
+    Bass,
+    Pad,
+    Vocal,
+    Fx,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::Kick,
+        Category::Snare,
+        Category::Hat,
+        Category::Bass,
+        Category::Pad,
+        Category::Vocal,
+        Category::Fx,
+    ];
+
+    /// The tag it's suggested as.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Category::Kick => "kick",
+            Category::Snare => "snare",
+            Category::Hat => "hat",
+            Category::Bass => "bass",
+            Category::Pad => "pad",
+            Category::Vocal => "vocal",
+            Category::Fx => "fx",
+        }
+    }
+}
+
+/// What the guess is made from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Features {
+    /// Seconds from the first audible moment to the last.
+    pub length: f32,
+    /// Seconds from the first audible moment to the loudest.
+    pub attack: f32,
+    /// Share of the energy below about 150 Hz.
+    pub low: f32,
+    /// Share of the energy above about 5 kHz.
+    pub high: f32,
+    /// How periodic the sound is just after its peak, 0 (noise) to 1 (a
+    /// steady tone).
+    pub periodicity: f32,
+    /// dB the level climbs per second while it sounds: re-attacks, like a
+    /// voice's syllables. Steady or decaying sounds stay low, and a slow
+    /// swell only counts once.
+    pub fluctuation: f32,
+}
+
+/// [`Features`] of interleaved audio, or `None` if it's silent.
+pub fn features(samples: &[f32], channels: usize, sample_rate: u32) -> Option<Features> {
+    let channels = channels.max(1);
+    let rate = sample_rate.max(1) as f32;
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+
+    let step = ((rate * STEP) as usize).max(1);
+    let levels: Vec<f32> = mono
+        .chunks(step)
+        .map(|chunk| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect();
+    let (loudest, &peak) = levels
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    if peak <= 0.0 {
+        return None;
+    }
+    let audible = |level: &&f32| **level > peak * AUDIBLE;
+    let first = levels.iter().position(|l| audible(&l))?;
+    let last = levels.iter().rposition(|l| audible(&l))?;
+
+    let sounding = &mono[first * step..((last + 1) * step).min(mono.len())];
+    let (low, high) = band_shares(sounding, rate);
+
+    let db: Vec<f32> = levels[first..=last]
+        .chunks(FLUCTUATION_STEPS)
+        .map(|chunk| {
+            let power = chunk.iter().map(|l| l * l).sum::<f32>() / chunk.len() as f32;
+            super::gain_to_db(power.sqrt().max(peak * AUDIBLE))
+        })
+        .collect();
+    let climbed: f32 = db.windows(2).map(|w| (w[1] - w[0]).max(0.0)).sum();
+    let length = (last - first + 1) as f32 * STEP;
+
+    Some(Features {
+        length,
+        attack: (loudest - first) as f32 * STEP,
+        low,
+        high,
+        periodicity: periodicity(&mono[loudest * step..], rate),
+        fluctuation: climbed / length,
+    })
+}
+
+/// Shares of `mono`'s energy below [`LOW_HZ`] and above [`HIGH_HZ`], split
+/// with two-pole (12 dB/octave) filters.
+fn band_shares(mono: &[f32], rate: f32) -> (f32, f32) {
+    let coefficient = |hz: f32| (-2.0 * std::f32::consts::PI * hz / rate).exp();
+    let (a_low, a_high) = (coefficient(LOW_HZ), coefficient(HIGH_HZ));
+    let (mut low1, mut low2, mut mid1, mut mid2) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+    let (mut total, mut low, mut high) = (0.0f32, 0.0f32, 0.0f32);
+    for &x in mono {
+        low1 = (1.0 - a_low) * x + a_low * low1;
+        low2 = (1.0 - a_low) * low1 + a_low * low2;
+        mid1 = (1.0 - a_high) * x + a_high * mid1;
+        mid2 = (1.0 - a_high) * mid1 + a_high * mid2;
+        let above = x - mid2;
+        total += x * x;
+        low += low2 * low2;
+        high += above * above;
+    }
+    if total <= 0.0 {
+        return (0.0, 0.0);
+    }
+    ((low / total).min(1.0), (high / total).min(1.0))
+}
+
+/// The strongest normalized autocorrelation of the start of `mono` at a lag
+/// between [`HIGHEST_PITCH_HZ`] and [`LOWEST_PITCH_HZ`].
+fn periodicity(mono: &[f32], rate: f32) -> f32 {
+    let shortest = (rate / HIGHEST_PITCH_HZ) as usize;
+    let longest = (rate / LOWEST_PITCH_HZ) as usize;
+    let frame = PERIODICITY_FRAMES.min(mono.len().saturating_sub(longest));
+    if frame == 0 {
+        return 0.0;
+    }
+    let head = &mono[..frame];
+    let energy = head.iter().map(|s| s * s).sum::<f32>();
+    (shortest.max(1)..=longest)
+        .map(|lag| {
+            let shifted = &mono[lag..lag + frame];
+            let dot: f32 = head.iter().zip(shifted).map(|(a, b)| a * b).sum();
+            let shifted_energy: f32 = shifted.iter().map(|s| s * s).sum();
+            let norm = (energy * shifted_energy).sqrt();
+            if norm > 0.0 {
+                dot / norm
+            } else {
+                0.0
+            }
+        })
+        .fold(0.0, f32::max)
+}
+
+/// The likeliest [`Category`] for a sound with `features`.
+pub fn classify(f: &Features) -> Category {
+    let pitched = f.periodicity >= 0.6;
+    if f.length < 1.5 && f.attack < 0.03 {
+        if f.low > 0.5 {
+            return Category::Kick;
+        }
+        if f.high > 0.7 && f.low < 0.1 {
+            return Category::Hat;
+        }
+        if !pitched {
+            return Category::Snare;
+        }
+    }
+    if pitched {
+        if f.low > 0.5 {
+            return Category::Bass;
+        }
+        if f.fluctuation > 40.0 {
+            return Category::Vocal;
+        }
+        if f.length >= 1.5 {
+            return Category::Pad;
+        }
+    }
+    Category::Fx
+}
+
+/// Decode the start of `path` and guess its [`Category`]; `None` for a
+/// silent file.
+pub fn classify_file(path: &Path) -> Result<Option<Category>, PlaybackError> {
+    let decoded = decode::decode(path, None, DecodeLimits::head(WINDOW))?;
+    let features = features(
+        &decoded.interleaved,
+        decoded.channels.max(1) as usize,
+        decoded.sample_rate,
+    );
+    Ok(features.as_ref().map(classify))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const RATE: u32 = 48_000;
+
+    /// `secs` of `voice(t)`, mono.
+    fn render(secs: f32, mut voice: impl FnMut(f32) -> f32) -> Vec<f32> {
+        (0..(secs * RATE as f32) as usize)
+            .map(|i| voice(i as f32 / RATE as f32))
+            .collect()
+    }
+
+    /// Repeatable white noise in -1..1.
+    fn noise() -> impl FnMut() -> f32 {
+        let mut state = 0x2545_f491_u32;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 * 2.0 - 1.0
+        }
+    }
+
+    fn guess(samples: &[f32]) -> Category {
+        classify(&features(samples, 1, RATE).unwrap())
+    }
+
+    #[test]
+    fn sorts_synthetic_sounds() {
+        let kick = render(0.4, |t| (TAU * 55.0 * t).sin() * (-t * 12.0).exp());
+        assert_eq!(guess(&kick), Category::Kick);
+
+        let mut white = noise();
+        let samples: Vec<f32> = (0..RATE as usize / 5).map(|_| white()).collect();
+        // Differencing tips white noise towards the top, like a hat.
+        let hat: Vec<f32> = samples
+            .windows(2)
+            .enumerate()
+            .map(|(i, w)| (w[1] - w[0]) * (-(i as f32 / RATE as f32) * 30.0).exp())
+            .collect();
+        assert_eq!(guess(&hat), Category::Hat);
+
+        let mut white = noise();
+        let snare = render(0.3, |t| {
+            ((TAU * 190.0 * t).sin() * 0.3 + white()) * (-t * 15.0).exp()
+        });
+        assert_eq!(guess(&snare), Category::Snare);
+
+        let bass = render(2.0, |t| (TAU * 55.0 * t).sin() * 0.8);
+        assert_eq!(guess(&bass), Category::Bass);
+
+        let pad = render(3.0, |t| {
+            let swell = (t / 0.5).min(1.0);
+            [262.0, 330.0, 392.0]
+                .iter()
+                .map(|hz| (TAU * hz * t).sin() * 0.3)
+                .sum::<f32>()
+                * swell
+        });
+        assert_eq!(guess(&pad), Category::Pad);
+
+        // Syllables four times a second on a 220 Hz buzz.
+        let vocal = render(2.0, |t| {
+            let syllable = (TAU * 4.0 * t).sin().max(0.0);
+            (1..6)
+                .map(|h| (TAU * 220.0 * h as f32 * t).sin() / h as f32)
+                .sum::<f32>()
+                * syllable
+                * 0.3
+        });
+        assert_eq!(guess(&vocal), Category::Vocal);
+
+        let mut white = noise();
+        let riser = render(3.0, |t| white() * t / 3.0);
+        assert_eq!(guess(&riser), Category::Fx);
+
+        assert_eq!(features(&[0.0; 4800], 1, RATE), None);
+    }
+}
@@ -0,0 +1,322 @@
+//! A bounded pool of worker threads for per-file analysis such as waveform
+//! overviews. Opening a folder can ask for hundreds of them at once; the pool
+//! runs the most wanted first (the selected file, then whatever is on screen,
+//! newest request first) and lets the caller drop work for rows that have
+//! scrolled away, so the queue tracks what the user is actually looking at.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+/// How urgently a result is wanted. Higher runs first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Nice to have, e.g. prefetching rows just off screen.
+    Background,
+    Visible,
+    Selected,
+}
+
+struct Job<K> {
+    key: K,
+    priority: Priority,
+    /// Request order; among equal priorities the newest runs first and the
+    /// oldest is evicted first.
+    seq: u64,
+}
+
+impl<K> Job<K> {
+    fn rank(&self) -> (Priority, u64) {
+        (self.priority, self.seq)
+    }
+}
+
+struct State<K> {
+    queue: Vec<Job<K>>,
+    /// Keys a worker is busy with, and whether they've since been cancelled
+    /// (their result is then dropped instead of delivered).
+    running: HashMap<K, bool>,
+    next_seq: u64,
+    shutdown: bool,
+}
+
+struct Shared<K> {
+    state: Mutex<State<K>>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+pub struct WorkerPool<K, T> {
+    shared: Arc<Shared<K>>,
+    results: mpsc::Receiver<(K, T)>,
+}
+
+impl<K, T> WorkerPool<K, T>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    T: Send + 'static,
+{
+    /// Start `threads` workers running `work`, with room for `capacity`
+    /// queued (not yet started) jobs.
+    pub fn new<F>(threads: usize, capacity: usize, work: F) -> Self
+    where
+        F: Fn(&K) -> T + Send + Sync + 'static,
+    {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: Vec::new(),
+                running: HashMap::new(),
+                next_seq: 0,
+                shutdown: false,
+            }),
+            ready: Condvar::new(),
+            capacity: capacity.max(1),
+        });
+        let (tx, results) = mpsc::channel();
+        let work = Arc::new(work);
+        for i in 0..threads.max(1) {
+            let shared = Arc::clone(&shared);
+            let tx = tx.clone();
+            let work = Arc::clone(&work);
+            let spawned = std::thread::Builder::new()
+                .name(format!("punks-analysis-{i}"))
+                .spawn(move || worker(&shared, &tx, &*work));
+            if let Err(e) = spawned {
+                tracing::warn!("analysis worker {i}: {e}");
+            }
+        }
+        WorkerPool { shared, results }
+    }
+
+    /// Ask for `key` to be worked on. A key already queued or running isn't
+    /// duplicated, but a queued one takes on `priority`, up or down, so a row
+    /// that scrolls from view to the prefetch margin is demoted. When the
+    /// queue is full the least wanted job is dropped, which may be this one;
+    /// returns whether `key` is queued or running afterwards.
+    pub fn request(&self, key: K, priority: Priority) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+
+        if let Some(cancelled) = state.running.get_mut(&key) {
+            *cancelled = false;
+            return true;
+        }
+        if let Some(job) = state.queue.iter_mut().find(|j| j.key == key) {
+            job.priority = priority;
+            job.seq = seq;
+            return true;
+        }
+        state.queue.push(Job { key, priority, seq });
+        if state.queue.len() > self.shared.capacity {
+            let (lowest, _) = state
+                .queue
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, j)| j.rank())
+                .expect("queue is non-empty");
+            state.queue.swap_remove(lowest);
+            if lowest == state.queue.len() {
+                // The new job itself was the one dropped.
+                return false;
+            }
+        }
+        self.shared.ready.notify_one();
+        true
+    }
+
+    /// Drop every queued job whose key fails `keep`, and discard the result
+    /// of any such job already running.
+    pub fn retain(&self, mut keep: impl FnMut(&K) -> bool) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.queue.retain(|j| keep(&j.key));
+        for (key, cancelled) in state.running.iter_mut() {
+            if !keep(key) {
+                *cancelled = true;
+            }
+        }
+    }
+
+    /// Forget all outstanding work.
+    pub fn clear(&self) {
+        self.retain(|_| false);
+    }
+
+    /// Jobs queued or running.
+    pub fn pending(&self) -> usize {
+        let state = self.shared.state.lock().unwrap();
+        state.queue.len() + state.running.len()
+    }
+
+    /// A finished result, if any, in completion order.
+    pub fn try_recv(&self) -> Option<(K, T)> {
+        self.results.try_recv().ok()
+    }
+}
+
+impl<K, T> Drop for WorkerPool<K, T> {
+    /// Workers finish their current job and exit; nothing waits for them.
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.shutdown = true;
+            state.queue.clear();
+        }
+        self.shared.ready.notify_all();
+    }
+}
+
+fn worker<K, T>(shared: &Shared<K>, tx: &mpsc::Sender<(K, T)>, work: &dyn Fn(&K) -> T)
+where
+    K: Clone + Eq + Hash,
+{
+    loop {
+        let key = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if state.shutdown {
+                    return;
+                }
+                if !state.queue.is_empty() {
+                    break;
+                }
+                state = shared.ready.wait(state).unwrap();
+            }
+            let (next, _) = state
+                .queue
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, j)| j.rank())
+                .expect("queue is non-empty");
+            let job = state.queue.swap_remove(next);
+            state.running.insert(job.key.clone(), false);
+            job.key
+        };
+
+        let result = work(&key);
+
+        let cancelled = shared
+            .state
+            .lock()
+            .unwrap()
+            .running
+            .remove(&key)
+            .unwrap_or(true);
+        if !cancelled && tx.send((key, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A one-thread pool whose jobs report when they start and then block
+    /// until released, so tests control exactly what's running.
+    fn gated_pool(
+        capacity: usize,
+    ) -> (
+        WorkerPool<&'static str, &'static str>,
+        mpsc::Receiver<&'static str>,
+        mpsc::Sender<()>,
+    ) {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (gate_tx, gate_rx) = mpsc::channel::<()>();
+        let started_tx = Mutex::new(started_tx);
+        let gate_rx = Mutex::new(gate_rx);
+        let pool = WorkerPool::new(1, capacity, move |key: &&'static str| {
+            started_tx.lock().unwrap().send(*key).unwrap();
+            gate_rx.lock().unwrap().recv().unwrap();
+            *key
+        });
+        (pool, started_rx, gate_tx)
+    }
+
+    fn recv(pool: &WorkerPool<&'static str, &'static str>) -> &'static str {
+        for _ in 0..500 {
+            if let Some((key, _)) = pool.try_recv() {
+                return key;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        panic!("no result");
+    }
+
+    #[test]
+    fn runs_most_wanted_first() {
+        let (pool, started, gate) = gated_pool(16);
+        pool.request("busy", Priority::Background);
+        assert_eq!(started.recv().unwrap(), "busy");
+
+        pool.request("old-visible", Priority::Visible);
+        pool.request("prefetch", Priority::Background);
+        pool.request("new-visible", Priority::Visible);
+        pool.request("selected", Priority::Selected);
+
+        let mut order = Vec::new();
+        for _ in 0..5 {
+            gate.send(()).unwrap();
+            order.push(recv(&pool));
+        }
+        assert_eq!(
+            order,
+            ["busy", "selected", "new-visible", "old-visible", "prefetch"]
+        );
+        assert_eq!(pool.pending(), 0);
+    }
+
+    #[test]
+    fn requesting_again_demotes() {
+        let (pool, started, gate) = gated_pool(16);
+        pool.request("busy", Priority::Visible);
+        started.recv().unwrap();
+
+        pool.request("scrolling-away", Priority::Visible);
+        pool.request("on-screen", Priority::Visible);
+        pool.request("scrolling-away", Priority::Background);
+
+        for _ in 0..3 {
+            gate.send(()).unwrap();
+        }
+        let order = [recv(&pool), recv(&pool), recv(&pool)];
+        assert_eq!(order, ["busy", "on-screen", "scrolling-away"]);
+    }
+
+    #[test]
+    fn full_queue_drops_least_wanted() {
+        let (pool, started, gate) = gated_pool(2);
+        pool.request("busy", Priority::Visible);
+        started.recv().unwrap();
+
+        assert!(pool.request("a", Priority::Visible));
+        assert!(pool.request("b", Priority::Visible));
+        assert!(!pool.request("c", Priority::Background));
+        // Evicts the oldest visible job, "a".
+        assert!(pool.request("d", Priority::Selected));
+        assert_eq!(pool.pending(), 3);
+
+        for _ in 0..3 {
+            gate.send(()).unwrap();
+        }
+        let mut done = vec![recv(&pool), recv(&pool), recv(&pool)];
+        done.sort_unstable();
+        assert_eq!(done, ["b", "busy", "d"]);
+    }
+
+    #[test]
+    fn retain_cancels_queued_and_running_work() {
+        let (pool, started, gate) = gated_pool(16);
+        pool.request("scrolled-away", Priority::Visible);
+        started.recv().unwrap();
+        pool.request("also-gone", Priority::Visible);
+        pool.request("still-here", Priority::Visible);
+
+        pool.retain(|k| *k == "still-here");
+        gate.send(()).unwrap();
+        gate.send(()).unwrap();
+        assert_eq!(recv(&pool), "still-here");
+        assert!(pool.try_recv().is_none());
+        assert_eq!(pool.pending(), 0);
+    }
+}
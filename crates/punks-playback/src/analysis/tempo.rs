@@ -0,0 +1,125 @@
+//! Tempo of loops, for playing them in time with the metronome. Packs almost
+//! always put it in the name (`Funk_Drums_96bpm.wav`, `Loop 120 Am.wav`);
+//! failing that, a loop is a whole number of bars, so its length gives it
+//! away once a plausible tempo is picked.
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Tempos a bare number in a loop's name, or a guess from its length, is
+/// taken as.
+const PLAUSIBLE_BPM: std::ops::RangeInclusive<f32> = 60.0..=200.0;
+
+/// Tempos a guess from length is steered into; half or double is as likely
+/// otherwise.
+const GUESS_BPM: std::ops::Range<f32> = 80.0..160.0;
+
+/// How fast a loop goes and how long it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopTempo {
+    pub bpm: f32,
+    /// Beats it lasts, at `bpm`.
+    pub beats: u32,
+}
+
+/// A tempo written in `name` as `120bpm`, `120 BPM`, `bpm120` or
+/// `120.5bpm`.
+pub fn bpm_from_name(name: &str) -> Option<f32> {
+    let lower = name.to_lowercase();
+    let mut rest = lower.as_str();
+    while let Some(i) = rest.find("bpm") {
+        let before = rest[..i].trim_end_matches([' ', '_', '-']);
+        let digits = before.len()
+            - before
+                .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+                .len();
+        if let Ok(bpm) = before[before.len() - digits..].parse::<f32>() {
+            return Some(bpm).filter(|b| *b > 0.0);
+        }
+        let after = rest[i + 3..].trim_start_matches([' ', '_', '-']);
+        let digits = after
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(after.len());
+        if let Ok(bpm) = after[..digits].parse::<f32>() {
+            return Some(bpm).filter(|b| *b > 0.0);
+        }
+        rest = &rest[i + 3..];
+    }
+    None
+}
+
+/// The tempo of `path` if it's a loop: named with one, or under a name or
+/// folder saying "loop" (then a bare number that could be a tempo, or a
+/// guess from `length`). One-shots get `None`.
+pub fn loop_tempo(path: &Path, length: Duration) -> Option<LoopTempo> {
+    let secs = length.as_secs_f32();
+    if secs <= 0.0 {
+        return None;
+    }
+    let stem = path.file_stem()?.to_string_lossy();
+    let bpm = match bpm_from_name(&stem) {
+        Some(bpm) => bpm,
+        None if path.to_string_lossy().to_lowercase().contains("loop") => {
+            bare_tempo(&stem).or_else(|| guess_from_length(secs))?
+        }
+        None => return None,
+    };
+    let beats = (secs * bpm / 60.0).round().max(1.0) as u32;
+    Some(LoopTempo { bpm, beats })
+}
+
+/// A number standing alone in `stem` that could be a tempo, e.g. the 128 of
+/// `House_Loop_128_Am`.
+fn bare_tempo(stem: &str) -> Option<f32> {
+    stem.split(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+        .filter_map(|word| word.parse::<f32>().ok())
+        .find(|bpm| PLAUSIBLE_BPM.contains(bpm))
+}
+
+/// The tempo at which `secs` is one, two, four, eight or sixteen bars of
+/// 4/4, taking the first that lands in [`GUESS_BPM`].
+fn guess_from_length(secs: f32) -> Option<f32> {
+    [4.0, 8.0, 16.0, 32.0, 64.0]
+        .iter()
+        .map(|beats| 60.0 * beats / secs)
+        .find(|bpm| GUESS_BPM.contains(bpm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tempo_from_names_and_lengths() {
+        assert_eq!(bpm_from_name("Funk_Drums_96bpm"), Some(96.0));
+        assert_eq!(bpm_from_name("Pad 120 BPM Am"), Some(120.0));
+        assert_eq!(bpm_from_name("BPM_87.5_break"), Some(87.5));
+        assert_eq!(bpm_from_name("kick_hard"), None);
+
+        let secs = Duration::from_secs_f32;
+        let tempo = |p: &str, s: f32| loop_tempo(Path::new(p), secs(s));
+        assert_eq!(
+            tempo("Drums/break_90bpm.wav", 5.333),
+            Some(LoopTempo {
+                bpm: 90.0,
+                beats: 8
+            })
+        );
+        assert_eq!(
+            tempo("Loops/House_128_Am.wav", 7.5),
+            Some(LoopTempo {
+                bpm: 128.0,
+                beats: 16
+            })
+        );
+        // Two bars at 120, or one at 60: steered to 120.
+        assert_eq!(
+            tempo("Loops/groove.wav", 4.0),
+            Some(LoopTempo {
+                bpm: 120.0,
+                beats: 8
+            })
+        );
+        assert_eq!(tempo("Drums/kick.wav", 0.5), None);
+    }
+}
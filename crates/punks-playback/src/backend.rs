@@ -0,0 +1,595 @@
+//! What the browser needs from playback, as a trait, so it can run without an
+//! audio device: [`PlaybackEngine`] is the real thing, and [`NullPlayback`] a
+//! silent stand-in for tests, CI and headless tools.
+
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{
+    PeakLevels, PlaybackEngine, PlaybackError, PlaybackStatus, Region, TempoSync, TrackInfo,
+    VoiceId, VoiceInfo, WaveformPeaks,
+};
+
+/// A preview player. Mirrors [`PlaybackEngine`]'s methods; see there for what
+/// each one does.
+pub trait Playback {
+    fn play(&mut self, path: &Path);
+    fn play_with_gain(&mut self, path: &Path, gain: f32);
+    fn play_region(&mut self, path: &Path, region: Region);
+    /// Called once per frame; returns any error since the last call.
+    fn poll(&mut self) -> Option<PlaybackError>;
+    fn stop(&mut self);
+    fn stop_all(&mut self);
+    fn set_polyphonic(&self, enabled: bool);
+    fn polyphonic(&self) -> bool;
+    fn voices(&self) -> Vec<VoiceInfo>;
+    fn stop_voice(&self, id: VoiceId) -> bool;
+    fn pause(&self);
+    fn resume(&self);
+    fn is_paused(&self) -> bool;
+    fn status(&self) -> PlaybackStatus;
+    fn invalidate(&mut self, path: &Path);
+    fn waveform_peaks(&self) -> Option<&WaveformPeaks>;
+    fn waveform_levels(&self) -> Option<&PeakLevels>;
+    fn loaded_clip(&self) -> Option<(&Path, Option<Region>)>;
+    fn current_info(&self) -> Option<&TrackInfo>;
+    fn loaded_duration(&self) -> Option<Duration>;
+    fn seek_fraction(&self, fraction: f32);
+    fn play_span(&self, span: Region);
+    fn set_loop_region(&self, start: Duration, end: Duration);
+    fn clear_loop_region(&self);
+    fn loop_region(&self) -> Option<(Duration, Duration)>;
+    fn seek(&mut self, position: Duration);
+    fn set_volume(&self, v: f32);
+    fn volume(&self) -> f32;
+    fn set_gain(&self, gain: f32);
+    fn gain(&self) -> f32;
+    fn set_fade_frames(&self, frames: usize);
+    fn fade_frames(&self) -> usize;
+    fn set_loudness_target(&self, target: Option<f32>);
+    fn loudness_target(&self) -> Option<f32>;
+    fn set_pitch(&self, semitones: f32);
+    fn pitch(&self) -> f32;
+    fn set_rate(&self, rate: f32);
+    fn rate(&self) -> f32;
+    fn play_layers(&mut self, layers: &[(PathBuf, f32)]);
+    fn set_layer_gain(&self, layer: usize, gain: f32);
+    fn layers_playing(&self) -> bool;
+    fn set_metronome(&self, bpm: Option<f32>);
+    fn metronome(&self) -> Option<f32>;
+    fn set_tempo_sync(&self, sync: Option<TempoSync>);
+    fn tempo_sync(&self) -> Option<TempoSync>;
+    fn start_recording(&mut self, dir: &Path) -> Result<PathBuf, PlaybackError>;
+    fn stop_recording(&mut self) -> Option<Result<PathBuf, PlaybackError>>;
+    fn recording(&self) -> Option<&Path>;
+    fn set_quick_audition(&self, window: Option<Duration>);
+    fn set_memory_budget(&self, bytes: Option<u64>);
+    fn set_reapeaks(&self, enabled: bool);
+    fn output_device(&self) -> Option<&str>;
+    fn switch_device(&mut self, name: Option<&str>) -> Result<(), PlaybackError>;
+}
+
+impl Playback for PlaybackEngine {
+    fn play(&mut self, path: &Path) {
+        PlaybackEngine::play(self, path);
+    }
+
+    fn play_with_gain(&mut self, path: &Path, gain: f32) {
+        PlaybackEngine::play_with_gain(self, path, gain);
+    }
+
+    fn play_region(&mut self, path: &Path, region: Region) {
+        PlaybackEngine::play_region(self, path, region);
+    }
+
+    fn poll(&mut self) -> Option<PlaybackError> {
+        PlaybackEngine::poll(self)
+    }
+
+    fn stop(&mut self) {
+        PlaybackEngine::stop(self);
+    }
+
+    fn stop_all(&mut self) {
+        PlaybackEngine::stop_all(self);
+    }
+
+    fn set_polyphonic(&self, enabled: bool) {
+        PlaybackEngine::set_polyphonic(self, enabled);
+    }
+
+    fn polyphonic(&self) -> bool {
+        PlaybackEngine::polyphonic(self)
+    }
+
+    fn voices(&self) -> Vec<VoiceInfo> {
+        PlaybackEngine::voices(self)
+    }
+
+    fn stop_voice(&self, id: VoiceId) -> bool {
+        PlaybackEngine::stop_voice(self, id)
+    }
+
+    fn pause(&self) {
+        PlaybackEngine::pause(self);
+    }
+
+    fn resume(&self) {
+        PlaybackEngine::resume(self);
+    }
+
+    fn is_paused(&self) -> bool {
+        PlaybackEngine::is_paused(self)
+    }
+
+    fn status(&self) -> PlaybackStatus {
+        PlaybackEngine::status(self)
+    }
+
+    fn invalidate(&mut self, path: &Path) {
+        PlaybackEngine::invalidate(self, path);
+    }
+
+    fn waveform_peaks(&self) -> Option<&WaveformPeaks> {
+        PlaybackEngine::waveform_peaks(self)
+    }
+
+    fn waveform_levels(&self) -> Option<&PeakLevels> {
+        PlaybackEngine::waveform_levels(self)
+    }
+
+    fn loaded_clip(&self) -> Option<(&Path, Option<Region>)> {
+        PlaybackEngine::loaded_clip(self)
+    }
+
+    fn current_info(&self) -> Option<&TrackInfo> {
+        PlaybackEngine::current_info(self)
+    }
+
+    fn loaded_duration(&self) -> Option<Duration> {
+        PlaybackEngine::loaded_duration(self)
+    }
+
+    fn seek_fraction(&self, fraction: f32) {
+        PlaybackEngine::seek_fraction(self, fraction);
+    }
+
+    fn play_span(&self, span: Region) {
+        PlaybackEngine::play_span(self, span);
+    }
+
+    fn set_loop_region(&self, start: Duration, end: Duration) {
+        PlaybackEngine::set_loop_region(self, start, end);
+    }
+
+    fn clear_loop_region(&self) {
+        PlaybackEngine::clear_loop_region(self);
+    }
+
+    fn loop_region(&self) -> Option<(Duration, Duration)> {
+        PlaybackEngine::loop_region(self)
+    }
+
+    fn seek(&mut self, position: Duration) {
+        PlaybackEngine::seek(self, position);
+    }
+
+    fn set_volume(&self, v: f32) {
+        PlaybackEngine::set_volume(self, v);
+    }
+
+    fn volume(&self) -> f32 {
+        PlaybackEngine::volume(self)
+    }
+
+    fn set_gain(&self, gain: f32) {
+        PlaybackEngine::set_gain(self, gain);
+    }
+
+    fn gain(&self) -> f32 {
+        PlaybackEngine::gain(self)
+    }
+
+    fn set_fade_frames(&self, frames: usize) {
+        PlaybackEngine::set_fade_frames(self, frames);
+    }
+
+    fn fade_frames(&self) -> usize {
+        PlaybackEngine::fade_frames(self)
+    }
+
+    fn set_loudness_target(&self, target: Option<f32>) {
+        PlaybackEngine::set_loudness_target(self, target);
+    }
+
+    fn loudness_target(&self) -> Option<f32> {
+        PlaybackEngine::loudness_target(self)
+    }
+
+    fn set_pitch(&self, semitones: f32) {
+        PlaybackEngine::set_pitch(self, semitones);
+    }
+
+    fn pitch(&self) -> f32 {
+        PlaybackEngine::pitch(self)
+    }
+
+    fn set_rate(&self, rate: f32) {
+        PlaybackEngine::set_rate(self, rate);
+    }
+
+    fn rate(&self) -> f32 {
+        PlaybackEngine::rate(self)
+    }
+
+    fn play_layers(&mut self, layers: &[(PathBuf, f32)]) {
+        PlaybackEngine::play_layers(self, layers);
+    }
+
+    fn set_layer_gain(&self, layer: usize, gain: f32) {
+        PlaybackEngine::set_layer_gain(self, layer, gain);
+    }
+
+    fn layers_playing(&self) -> bool {
+        PlaybackEngine::layers_playing(self)
+    }
+
+    fn set_metronome(&self, bpm: Option<f32>) {
+        PlaybackEngine::set_metronome(self, bpm);
+    }
+
+    fn metronome(&self) -> Option<f32> {
+        PlaybackEngine::metronome(self)
+    }
+
+    fn set_tempo_sync(&self, sync: Option<TempoSync>) {
+        PlaybackEngine::set_tempo_sync(self, sync);
+    }
+
+    fn tempo_sync(&self) -> Option<TempoSync> {
+        PlaybackEngine::tempo_sync(self)
+    }
+
+    fn start_recording(&mut self, dir: &Path) -> Result<PathBuf, PlaybackError> {
+        PlaybackEngine::start_recording(self, dir)
+    }
+
+    fn stop_recording(&mut self) -> Option<Result<PathBuf, PlaybackError>> {
+        PlaybackEngine::stop_recording(self)
+    }
+
+    fn recording(&self) -> Option<&Path> {
+        PlaybackEngine::recording(self)
+    }
+
+    fn set_quick_audition(&self, window: Option<Duration>) {
+        PlaybackEngine::set_quick_audition(self, window);
+    }
+
+    fn set_memory_budget(&self, bytes: Option<u64>) {
+        PlaybackEngine::set_memory_budget(self, bytes);
+    }
+
+    fn set_reapeaks(&self, enabled: bool) {
+        PlaybackEngine::set_reapeaks(self, enabled);
+    }
+
+    fn output_device(&self) -> Option<&str> {
+        PlaybackEngine::output_device(self)
+    }
+
+    fn switch_device(&mut self, name: Option<&str>) -> Result<(), PlaybackError> {
+        PlaybackEngine::switch_device(self, name)
+    }
+}
+
+/// Plays nothing, instantly: `play` reports the file as playing straight
+/// away, nothing is decoded, and no device is opened. Regions with an end
+/// report their span as the duration; anything else is zero-length. Stacks
+/// of layers, and voices left by polyphonic preview, play until stopped. An
+/// A/B loop is kept as set until another clip plays.
+#[derive(Debug)]
+pub struct NullPlayback {
+    current: Option<(PathBuf, Option<Region>, Duration)>,
+    loop_region: Cell<Option<(Duration, Duration)>>,
+    layers: usize,
+    polyphonic: Cell<bool>,
+    voices: RefCell<Vec<VoiceInfo>>,
+    next_voice: u64,
+    paused: Cell<bool>,
+    volume: Cell<f32>,
+    gain: Cell<f32>,
+    fade_frames: Cell<usize>,
+    loudness_target: Cell<Option<f32>>,
+    pitch: Cell<f32>,
+    metronome: Cell<Option<f32>>,
+    tempo_sync: Cell<Option<TempoSync>>,
+    device: Option<String>,
+}
+
+impl Default for NullPlayback {
+    fn default() -> Self {
+        NullPlayback {
+            current: None,
+            loop_region: Cell::new(None),
+            layers: 0,
+            polyphonic: Cell::new(false),
+            voices: RefCell::new(Vec::new()),
+            next_voice: 0,
+            paused: Cell::new(false),
+            volume: Cell::new(1.0),
+            gain: Cell::new(1.0),
+            fade_frames: Cell::new(crate::DEFAULT_FADE_FRAMES),
+            loudness_target: Cell::new(None),
+            pitch: Cell::new(0.0),
+            metronome: Cell::new(None),
+            tempo_sync: Cell::new(None),
+            device: None,
+        }
+    }
+}
+
+impl NullPlayback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave what's playing as a voice, under polyphonic preview.
+    fn release_clip(&mut self) {
+        let playing = self.current.as_ref().filter(|_| !self.paused.get());
+        if let (true, Some((path, _, _))) = (self.polyphonic.get(), playing) {
+            self.next_voice += 1;
+            let mut voices = self.voices.borrow_mut();
+            if voices.len() == crate::MAX_VOICES {
+                voices.remove(0);
+            }
+            voices.push(VoiceInfo {
+                id: VoiceId(self.next_voice),
+                path: path.clone(),
+            });
+        }
+    }
+}
+
+impl Playback for NullPlayback {
+    fn play(&mut self, path: &Path) {
+        self.release_clip();
+        self.layers = 0;
+        self.paused.set(false);
+        self.loop_region.set(None);
+        self.current = Some((path.to_path_buf(), None, Duration::ZERO));
+    }
+
+    fn play_with_gain(&mut self, path: &Path, gain: f32) {
+        self.play(path);
+        self.set_gain(gain);
+    }
+
+    fn play_region(&mut self, path: &Path, region: Region) {
+        self.release_clip();
+        self.layers = 0;
+        self.paused.set(false);
+        self.loop_region.set(None);
+        let span = region
+            .end
+            .map_or(Duration::ZERO, |end| end.saturating_sub(region.start));
+        self.current = Some((path.to_path_buf(), Some(region), span));
+    }
+
+    fn poll(&mut self) -> Option<PlaybackError> {
+        None
+    }
+
+    fn stop(&mut self) {
+        self.layers = 0;
+        self.paused.set(false);
+        self.loop_region.set(None);
+        self.current = None;
+    }
+
+    fn stop_all(&mut self) {
+        self.stop();
+        self.voices.get_mut().clear();
+    }
+
+    fn set_polyphonic(&self, enabled: bool) {
+        self.polyphonic.set(enabled);
+    }
+
+    fn polyphonic(&self) -> bool {
+        self.polyphonic.get()
+    }
+
+    fn voices(&self) -> Vec<VoiceInfo> {
+        self.voices.borrow().clone()
+    }
+
+    fn stop_voice(&self, id: VoiceId) -> bool {
+        let mut voices = self.voices.borrow_mut();
+        let before = voices.len();
+        voices.retain(|v| v.id != id);
+        voices.len() != before
+    }
+
+    fn pause(&self) {
+        if self.current.is_some() || self.layers > 0 {
+            self.paused.set(true);
+        }
+    }
+
+    fn resume(&self) {
+        self.paused.set(false);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    fn status(&self) -> PlaybackStatus {
+        match &self.current {
+            Some((file, _, duration)) if self.paused.get() => PlaybackStatus::Paused {
+                file: file.clone(),
+                position: Duration::ZERO,
+                duration: *duration,
+            },
+            Some((file, _, duration)) => PlaybackStatus::Playing {
+                file: file.clone(),
+                position: Duration::ZERO,
+                duration: *duration,
+            },
+            None => PlaybackStatus::Idle,
+        }
+    }
+
+    fn invalidate(&mut self, _path: &Path) {}
+
+    fn waveform_peaks(&self) -> Option<&WaveformPeaks> {
+        None
+    }
+
+    fn waveform_levels(&self) -> Option<&PeakLevels> {
+        None
+    }
+
+    fn loaded_clip(&self) -> Option<(&Path, Option<Region>)> {
+        self.current
+            .as_ref()
+            .map(|(path, region, _)| (path.as_path(), *region))
+    }
+
+    fn current_info(&self) -> Option<&TrackInfo> {
+        None
+    }
+
+    fn loaded_duration(&self) -> Option<Duration> {
+        self.current.as_ref().map(|(_, _, duration)| *duration)
+    }
+
+    fn seek_fraction(&self, _fraction: f32) {}
+
+    fn play_span(&self, _span: Region) {}
+
+    fn set_loop_region(&self, start: Duration, end: Duration) {
+        if self.current.is_some() && end > start {
+            self.loop_region.set(Some((start, end)));
+        }
+    }
+
+    fn clear_loop_region(&self) {
+        self.loop_region.set(None);
+    }
+
+    fn loop_region(&self) -> Option<(Duration, Duration)> {
+        self.loop_region.get()
+    }
+
+    fn seek(&mut self, _position: Duration) {}
+
+    fn set_volume(&self, v: f32) {
+        self.volume.set(v.clamp(0.0, 1.0));
+    }
+
+    fn volume(&self) -> f32 {
+        self.volume.get()
+    }
+
+    fn set_gain(&self, gain: f32) {
+        self.gain.set(gain.clamp(0.0, crate::MAX_GAIN));
+    }
+
+    fn gain(&self) -> f32 {
+        self.gain.get()
+    }
+
+    fn set_fade_frames(&self, frames: usize) {
+        self.fade_frames.set(frames.min(crate::MAX_FADE_FRAMES));
+    }
+
+    fn fade_frames(&self) -> usize {
+        self.fade_frames.get()
+    }
+
+    fn set_loudness_target(&self, target: Option<f32>) {
+        self.loudness_target.set(target);
+    }
+
+    fn loudness_target(&self) -> Option<f32> {
+        self.loudness_target.get()
+    }
+
+    fn set_pitch(&self, semitones: f32) {
+        self.pitch
+            .set(semitones.clamp(-crate::MAX_PITCH, crate::MAX_PITCH));
+    }
+
+    fn pitch(&self) -> f32 {
+        self.pitch.get()
+    }
+
+    fn set_rate(&self, rate: f32) {
+        let rate = rate.clamp(crate::MIN_RATE, crate::MAX_RATE);
+        self.pitch.set(12.0 * rate.log2());
+    }
+
+    fn rate(&self) -> f32 {
+        2f32.powf(self.pitch.get() / 12.0)
+    }
+
+    fn play_layers(&mut self, layers: &[(PathBuf, f32)]) {
+        self.current = None;
+        self.paused.set(false);
+        self.layers = layers.len().min(crate::MAX_LAYERS);
+    }
+
+    fn set_layer_gain(&self, _layer: usize, _gain: f32) {}
+
+    fn layers_playing(&self) -> bool {
+        self.layers > 0 && !self.paused.get()
+    }
+
+    fn set_metronome(&self, bpm: Option<f32>) {
+        self.metronome
+            .set(bpm.map(|b| b.clamp(crate::MIN_BPM, crate::MAX_BPM)));
+    }
+
+    fn metronome(&self) -> Option<f32> {
+        self.metronome.get()
+    }
+
+    fn set_tempo_sync(&self, sync: Option<TempoSync>) {
+        self.tempo_sync.set(sync);
+    }
+
+    fn tempo_sync(&self) -> Option<TempoSync> {
+        self.tempo_sync.get()
+    }
+
+    /// There's no output to record.
+    fn start_recording(&mut self, _dir: &Path) -> Result<PathBuf, PlaybackError> {
+        Err(PlaybackError::device(None, "recording", "no audio output"))
+    }
+
+    fn stop_recording(&mut self) -> Option<Result<PathBuf, PlaybackError>> {
+        None
+    }
+
+    fn recording(&self) -> Option<&Path> {
+        None
+    }
+
+    fn set_quick_audition(&self, _window: Option<Duration>) {}
+
+    fn set_memory_budget(&self, _bytes: Option<u64>) {}
+
+    fn set_reapeaks(&self, _enabled: bool) {}
+
+    fn output_device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    fn switch_device(&mut self, name: Option<&str>) -> Result<(), PlaybackError> {
+        self.device = name.map(str::to_string);
+        Ok(())
+    }
+}
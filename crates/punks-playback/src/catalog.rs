@@ -0,0 +1,268 @@
+//! A snapshot of a library for other tools: every audio file under a root
+//! with its format, Broadcast Wave metadata, tags, rating and analysis
+//! results, written as JSON or CSV for spreadsheets and databases.
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use punks_core::index::SampleIndex;
+use punks_core::{ExtensionRegistry, ScanError, ScanOptions};
+use serde_json::{json, Map, Value};
+
+use crate::analysis::analyzer::{AnalysisResults, AnalysisValue, AnalyzerRegistry};
+use crate::FileInfo;
+
+/// One file in a catalog.
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// `None` when the headers couldn't be read.
+    pub info: Option<FileInfo>,
+    /// From the sample index.
+    pub tags: Vec<String>,
+    pub rating: Option<u8>,
+    pub analysis: AnalysisResults,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// An array of objects, one per file, with analysis results nested
+    /// under `analysis`.
+    Json,
+    /// A header row, then one row per file; tags go in one column separated
+    /// by spaces, and analysis results get a column per key, empty where a
+    /// file has none.
+    Csv,
+}
+
+/// Every audio file under `root`, sorted by path, probed, looked up in
+/// `index` and run through `analyzers` (pass an empty registry to skip
+/// decoding). `on_file` is called
+/// before each file so long runs can show progress. Files that fail to probe
+/// or decode are kept with what could be read; unreadable subfolders are
+/// skipped.
+#[tracing::instrument(level = "debug", skip_all, fields(root = %root.display()))]
+pub fn build_catalog(
+    root: &Path,
+    extensions: &ExtensionRegistry,
+    index: &SampleIndex,
+    analyzers: &AnalyzerRegistry,
+    mut on_file: impl FnMut(&Path),
+) -> Result<Vec<CatalogEntry>, ScanError> {
+    let options = ScanOptions {
+        extensions: extensions.clone(),
+        threads: Some(punks_core::scan::default_threads()),
+        ..ScanOptions::default()
+    };
+    let mut files: Vec<_> = punks_core::scan_directory_iter(root, options)?
+        .flatten()
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(files
+        .into_iter()
+        .map(|file| {
+            on_file(&file.path);
+            let info = crate::probe(&file.path)
+                .inspect_err(|e| tracing::warn!("catalog: {e}"))
+                .ok();
+            let analysis = analyzers
+                .analyze_file(&file.path)
+                .inspect_err(|e| tracing::warn!("catalog: {e}"))
+                .unwrap_or_default();
+            // The index is keyed by absolute path; `root` may be relative.
+            let key = std::path::absolute(&file.path).unwrap_or_else(|_| file.path.clone());
+            CatalogEntry {
+                tags: index.tags(&key).to_vec(),
+                rating: index.rating(&key),
+                path: file.path,
+                size_bytes: file.size_bytes,
+                info,
+                analysis,
+            }
+        })
+        .collect())
+}
+
+/// Write `entries` to `out` as `format`.
+pub fn export(entries: &[CatalogEntry], format: ExportFormat, out: impl Write) -> io::Result<()> {
+    match format {
+        ExportFormat::Json => write_json(entries, out),
+        ExportFormat::Csv => write_csv(entries, out),
+    }
+}
+
+fn analysis_json(value: &AnalysisValue) -> Value {
+    match value {
+        // Non-finite numbers (a silent file's -inf dB peak) become null.
+        AnalysisValue::Number(n) => json!(n),
+        AnalysisValue::Text(s) => json!(s),
+        AnalysisValue::Flag(b) => json!(b),
+    }
+}
+
+fn write_json(entries: &[CatalogEntry], mut out: impl Write) -> io::Result<()> {
+    let rows: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            let info = entry.info.as_ref();
+            let meta = info.map(|i| &i.metadata);
+            let analysis: Map<String, Value> = entry
+                .analysis
+                .iter()
+                .map(|(key, value)| (key.clone(), analysis_json(value)))
+                .collect();
+            json!({
+                "path": entry.path.to_string_lossy(),
+                "size_bytes": entry.size_bytes,
+                "sample_rate": info.map(|i| i.sample_rate),
+                "channels": info.map(|i| i.channels),
+                "duration": info.and_then(|i| i.duration).map(|d| d.as_secs_f64()),
+                "description": meta.and_then(|m| m.description.as_deref()),
+                "originator": meta.and_then(|m| m.originator.as_deref()),
+                "origination_date": meta.and_then(|m| m.origination_date.as_deref()),
+                "origination_time": meta.and_then(|m| m.origination_time.as_deref()),
+                "time_reference": meta.and_then(|m| m.time_reference),
+                "tags": entry.tags,
+                "rating": entry.rating,
+                "analysis": analysis,
+            })
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut out, &rows)?;
+    writeln!(out)
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "path",
+    "size_bytes",
+    "sample_rate",
+    "channels",
+    "duration",
+    "description",
+    "originator",
+    "origination_date",
+    "origination_time",
+    "time_reference",
+    "tags",
+    "rating",
+];
+
+fn write_csv(entries: &[CatalogEntry], out: impl Write) -> io::Result<()> {
+    let keys: BTreeSet<&str> = entries
+        .iter()
+        .flat_map(|e| e.analysis.keys().map(String::as_str))
+        .collect();
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(CSV_COLUMNS.iter().copied().chain(keys.iter().copied()))?;
+
+    let text = |s: Option<&String>| s.cloned().unwrap_or_default();
+    let number = |n: Option<String>| n.unwrap_or_default();
+    for entry in entries {
+        let info = entry.info.as_ref();
+        let meta = info.map(|i| &i.metadata);
+        let mut row = vec![
+            entry.path.to_string_lossy().into_owned(),
+            entry.size_bytes.to_string(),
+            number(info.map(|i| i.sample_rate.to_string())),
+            number(info.map(|i| i.channels.to_string())),
+            number(
+                info.and_then(|i| i.duration)
+                    .map(|d| d.as_secs_f64().to_string()),
+            ),
+            text(meta.and_then(|m| m.description.as_ref())),
+            text(meta.and_then(|m| m.originator.as_ref())),
+            text(meta.and_then(|m| m.origination_date.as_ref())),
+            text(meta.and_then(|m| m.origination_time.as_ref())),
+            number(meta.and_then(|m| m.time_reference).map(|t| t.to_string())),
+            entry.tags.join(" "),
+            number(entry.rating.map(|r| r.to_string())),
+        ];
+        row.extend(keys.iter().map(|key| match entry.analysis.get(*key) {
+            Some(AnalysisValue::Number(n)) if n.is_finite() => n.to_string(),
+            Some(AnalysisValue::Number(_)) | None => String::new(),
+            Some(AnalysisValue::Text(s)) => s.clone(),
+            Some(AnalysisValue::Flag(b)) => b.to_string(),
+        }));
+        writer.write_record(&row)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AudioMetadata;
+    use std::time::Duration;
+
+    fn entries() -> Vec<CatalogEntry> {
+        let mut analysis = AnalysisResults::new();
+        analysis.insert("levels.peak_db".into(), AnalysisValue::Number(-1.5));
+        analysis.insert("genre.label".into(), "techno, minimal".into());
+        vec![
+            CatalogEntry {
+                path: PathBuf::from("/samples/kick.wav"),
+                size_bytes: 1024,
+                info: Some(FileInfo {
+                    sample_rate: 48_000,
+                    channels: 2,
+                    bit_depth: Some(24),
+                    duration: Some(Duration::from_millis(500)),
+                    metadata: AudioMetadata {
+                        description: Some("Kick \"hard\"".into()),
+                        ..AudioMetadata::default()
+                    },
+                }),
+                tags: vec!["kick".into(), "808".into()],
+                rating: Some(4),
+                analysis,
+            },
+            CatalogEntry {
+                path: PathBuf::from("/samples/broken.wav"),
+                size_bytes: 3,
+                info: None,
+                tags: Vec::new(),
+                rating: None,
+                analysis: AnalysisResults::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn exports_json() {
+        let mut out = Vec::new();
+        export(&entries(), ExportFormat::Json, &mut out).unwrap();
+        let rows: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(rows[0]["path"], "/samples/kick.wav");
+        assert_eq!(rows[0]["sample_rate"], 48_000);
+        assert_eq!(rows[0]["description"], "Kick \"hard\"");
+        assert_eq!(rows[0]["tags"], json!(["kick", "808"]));
+        assert_eq!(rows[0]["rating"], 4);
+        assert_eq!(rows[0]["analysis"]["levels.peak_db"], -1.5);
+        assert_eq!(rows[1]["duration"], Value::Null);
+        assert_eq!(rows[1]["tags"], json!([]));
+        assert_eq!(rows[1]["rating"], Value::Null);
+    }
+
+    #[test]
+    fn exports_csv_with_a_column_per_analysis_key() {
+        let mut out = Vec::new();
+        export(&entries(), ExportFormat::Csv, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "path,size_bytes,sample_rate,channels,duration,description,originator,\
+             origination_date,origination_time,time_reference,tags,rating,genre.label,\
+             levels.peak_db"
+        );
+        assert_eq!(
+            lines[1],
+            "/samples/kick.wav,1024,48000,2,0.5,\"Kick \"\"hard\"\"\",,,,,kick 808,4,\
+             \"techno, minimal\",-1.5"
+        );
+        assert_eq!(lines[2], "/samples/broken.wav,3,,,,,,,,,,,,");
+    }
+}
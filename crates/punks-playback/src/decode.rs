@@ -4,11 +4,12 @@ use std::path::Path;
 use std::time::Duration;
 
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{CodecRegistry, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
 use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
 
 use crate::PlaybackError;
 
@@ -26,6 +27,24 @@ pub struct AudioMetadata {
     pub time_reference: Option<u64>,
 }
 
+/// A `[start, end)` span of a file, e.g. one CUE track of a long mix: the
+/// listing's own [`TrackRegion`](punks_core::TrackRegion), so an entry's
+/// region plays as it is.
+pub use punks_core::TrackRegion as Region;
+
+/// What a file is, read from its headers without decoding any audio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Bits per stored sample; `None` for formats without a fixed depth
+    /// (MP3, Vorbis, ...) or that don't state one.
+    pub bit_depth: Option<u16>,
+    /// `None` when the container doesn't state its length (some MP3s).
+    pub duration: Option<Duration>,
+    pub metadata: AudioMetadata,
+}
+
 pub struct DecodedAudio {
     pub interleaved: Vec<f32>,
     pub channels: u16,
@@ -51,19 +70,171 @@ const WAVE_FORMAT_OGG_VORBIS: u16 = 0x674f;
 const PREVIEW_THRESHOLD: Duration = Duration::from_secs(120);
 const PREVIEW_WINDOW: Duration = Duration::from_secs(120);
 
+/// How much audio a decode may keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Sources (or regions) longer than this decode only a preview.
+    pub threshold: Duration,
+    /// How much of the start a preview keeps.
+    pub window: Duration,
+    /// Refuse to decode more than this many bytes of samples; `None` for no
+    /// cap.
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for DecodeLimits {
+    /// The preview bounds, no memory cap.
+    fn default() -> Self {
+        DecodeLimits {
+            threshold: PREVIEW_THRESHOLD,
+            window: PREVIEW_WINDOW,
+            max_bytes: None,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Everything, however long.
+    pub const FULL: DecodeLimits = DecodeLimits {
+        threshold: Duration::MAX,
+        window: Duration::MAX,
+        max_bytes: None,
+    };
+
+    /// Just the first `window`, flagging `truncated` if there's more. For
+    /// starting playback before a full decode.
+    pub fn head(window: Duration) -> Self {
+        DecodeLimits {
+            threshold: window,
+            window,
+            max_bytes: None,
+        }
+    }
+
+    /// How much of a `source` this long a decode keeps.
+    pub(crate) fn kept(&self, source: Duration) -> Duration {
+        if source > self.threshold {
+            self.window.min(source)
+        } else {
+            source
+        }
+    }
+
+    /// Whether `samples` f32 samples fit under [`max_bytes`](Self::max_bytes).
+    pub(crate) fn fits(&self, samples: u64) -> Result<(), PlaybackError> {
+        match self.max_bytes {
+            Some(limit) if samples.saturating_mul(4) > limit => Err(PlaybackError::TooLarge {
+                needed: samples.saturating_mul(4),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// How many header bytes to read for classification + metadata. All chunks
 /// before `data` (fmt, ds64, bext, …) live comfortably within this.
 const HEADER_PREFIX_MAX: usize = 1 << 20; // 1 MiB
 
 pub fn decode_file(path: &Path) -> Result<DecodedAudio, PlaybackError> {
-    decode_inner(path, PREVIEW_THRESHOLD, PREVIEW_WINDOW)
+    decode(path, None, DecodeLimits::default())
+}
+
+/// Container metadata only (no audio decode): reads the header prefix and
+/// parses any `bext` chunk. Cheap enough to call per request.
+pub fn read_metadata(path: &Path) -> Result<AudioMetadata, PlaybackError> {
+    let prefix = read_header_prefix(path, HEADER_PREFIX_MAX)?;
+    Ok(parse_riff_metadata(&prefix))
+}
+
+/// Format, length and metadata from `path`'s headers. Cheap enough to run
+/// for every row of a listing.
+#[tracing::instrument(level = "trace", skip_all, fields(path = %path.display()))]
+pub fn probe(path: &Path) -> Result<FileInfo, PlaybackError> {
+    probe_headers(path).map_err(|e| e.at(path))
+}
+
+fn probe_headers(path: &Path) -> Result<FileInfo, PlaybackError> {
+    #[cfg(feature = "tracker")]
+    if let Some(info) = crate::tracker::probe(path) {
+        return info;
+    }
+    let prefix = read_header_prefix(path, HEADER_PREFIX_MAX)?;
+    let metadata = parse_riff_metadata(&prefix);
+    let to_duration = |frames: u64, rate: u32| Duration::from_secs_f64(frames as f64 / rate as f64);
+
+    if let Some(layout) = crate::pcm::PcmLayout::parse(&prefix) {
+        let file_len = std::fs::metadata(path)
+            .map_err(|e| PlaybackError::io(path, e))?
+            .len();
+        return Ok(FileInfo {
+            sample_rate: layout.sample_rate(),
+            channels: layout.channels(),
+            bit_depth: Some(layout.bits()),
+            duration: Some(to_duration(layout.frames(file_len), layout.sample_rate())),
+            metadata,
+        });
+    }
+
+    let file = File::open(path).map_err(|e| PlaybackError::io(path, e))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(PlaybackError::unsupported)?;
+    let params = &probed
+        .format
+        .default_track()
+        .ok_or_else(|| PlaybackError::decode("no audio track found"))?
+        .codec_params;
+    let sample_rate = params
+        .sample_rate
+        .ok_or_else(|| PlaybackError::decode("unknown sample rate"))?;
+    Ok(FileInfo {
+        sample_rate,
+        channels: params.channels.map_or(2, |c| c.count() as u16),
+        bit_depth: params.bits_per_sample.map(|bits| bits as u16),
+        duration: params.n_frames.map(|n| to_duration(n, sample_rate)),
+        metadata,
+    })
+}
+
+/// Decode the whole file regardless of length — for offline processing, where
+/// a preview window would silently drop the rest of the audio.
+pub fn decode_file_full(path: &Path) -> Result<DecodedAudio, PlaybackError> {
+    decode(path, None, DecodeLimits::FULL)
+}
+
+/// Decode `path` (or just `region` of it) within `limits`. The preview bound
+/// applies to the region's length, so a five-minute track inside a two-hour
+/// mix is still capped.
+#[tracing::instrument(level = "debug", skip(limits), fields(path = %path.display()))]
+pub fn decode(
+    path: &Path,
+    region: Option<Region>,
+    limits: DecodeLimits,
+) -> Result<DecodedAudio, PlaybackError> {
+    decode_path(path, region, limits).map_err(|e| e.at(path))
 }
 
-fn decode_inner(
+fn decode_path(
     path: &Path,
-    threshold: Duration,
-    window: Duration,
+    region: Option<Region>,
+    limits: DecodeLimits,
 ) -> Result<DecodedAudio, PlaybackError> {
+    #[cfg(feature = "tracker")]
+    if let Some(decoded) = crate::tracker::decode(path, region, limits) {
+        return decoded;
+    }
+
     // Read a bounded header prefix for classification + metadata rather than the
     // whole file — long production-sound files must not be slurped into memory.
     let prefix = read_header_prefix(path, HEADER_PREFIX_MAX)?;
@@ -71,37 +242,39 @@ fn decode_inner(
 
     // RF64: the >4 GB WAV variant. symphonia only knows `RIFF`, so fix it up.
     if prefix.len() >= 12 && &prefix[0..4] == b"RF64" && &prefix[8..12] == b"WAVE" {
-        return decode_rf64(path, &prefix, metadata, threshold, window);
+        return decode_rf64(path, &prefix, metadata, region, limits);
+    }
+
+    // Plain PCM/float WAV, the common one-shot: skip symphonia entirely.
+    if let Some(layout) = crate::pcm::PcmLayout::parse(&prefix) {
+        return crate::pcm::decode(path, &layout, metadata, region, limits);
     }
 
     // Ogg Vorbis in a WAV container: read fully and hand the inner Ogg stream to
     // symphonia. These are small sample files, so the full read is fine.
     if riff_fmt_tag(&prefix) == Some(WAVE_FORMAT_OGG_VORBIS) {
-        let raw = std::fs::read(path)
-            .map_err(|e| PlaybackError::DecodeError(format!("{path:?}: {e}")))?;
+        let raw = std::fs::read(path).map_err(|e| PlaybackError::io(path, e))?;
         if let Some(ogg) = extract_ogg_in_wav(&raw) {
             let mss = MediaSourceStream::new(Box::new(Cursor::new(ogg)), Default::default());
             let mut hint = Hint::new();
             hint.with_extension("ogg");
-            return decode_from_stream(mss, &hint, metadata, None, threshold, window);
+            return decode_from_stream(mss, &hint, metadata, None, region, limits);
         }
     }
 
     // Normal path: stream from the file so long files aren't fully buffered.
-    let file =
-        File::open(path).map_err(|e| PlaybackError::DecodeError(format!("{path:?}: {e}")))?;
+    let file = File::open(path).map_err(|e| PlaybackError::io(path, e))?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
     let mut hint = Hint::new();
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
         hint.with_extension(ext);
     }
-    decode_from_stream(mss, &hint, metadata, None, threshold, window)
+    decode_from_stream(mss, &hint, metadata, None, region, limits)
 }
 
 /// Read up to `max` bytes from the start of `path`.
 fn read_header_prefix(path: &Path, max: usize) -> Result<Vec<u8>, PlaybackError> {
-    let mut file =
-        File::open(path).map_err(|e| PlaybackError::DecodeError(format!("{path:?}: {e}")))?;
+    let mut file = File::open(path).map_err(|e| PlaybackError::io(path, e))?;
     let mut buf = vec![0u8; max];
     let mut filled = 0;
     while filled < max {
@@ -109,7 +282,7 @@ fn read_header_prefix(path: &Path, max: usize) -> Result<Vec<u8>, PlaybackError>
             Ok(0) => break,
             Ok(n) => filled += n,
             Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
-            Err(e) => return Err(PlaybackError::DecodeError(format!("{path:?}: {e}"))),
+            Err(e) => return Err(PlaybackError::io(path, e)),
         }
     }
     buf.truncate(filled);
@@ -248,8 +421,8 @@ fn decode_rf64(
     path: &Path,
     prefix: &[u8],
     metadata: AudioMetadata,
-    threshold: Duration,
-    window: Duration,
+    region: Option<Region>,
+    limits: DecodeLimits,
 ) -> Result<DecodedAudio, PlaybackError> {
     let mut data_size: Option<u64> = None; // real data-chunk size from ds64
     let mut fmt_range: Option<(usize, usize)> = None; // (start, total len incl header + pad)
@@ -284,27 +457,42 @@ fn decode_rf64(
         pos = next;
     }
 
-    let data_size =
-        data_size.ok_or_else(|| PlaybackError::DecodeError("rf64: missing ds64 chunk".into()))?;
+    let data_size = data_size.ok_or_else(|| PlaybackError::decode("rf64: missing ds64 chunk"))?;
     let (fmt_start, fmt_total) =
-        fmt_range.ok_or_else(|| PlaybackError::DecodeError("rf64: missing fmt chunk".into()))?;
+        fmt_range.ok_or_else(|| PlaybackError::decode("rf64: missing fmt chunk"))?;
     let data_offset =
-        data_offset.ok_or_else(|| PlaybackError::DecodeError("rf64: missing data chunk".into()))?;
+        data_offset.ok_or_else(|| PlaybackError::decode("rf64: missing data chunk"))?;
 
     let fb = fmt_start + 8;
     if fb + 14 > prefix.len() {
-        return Err(PlaybackError::DecodeError(
-            "rf64: truncated fmt chunk".into(),
-        ));
+        return Err(PlaybackError::decode("rf64: truncated fmt chunk"));
     }
     let sample_rate = u32::from_le_bytes(prefix[fb + 4..fb + 8].try_into().unwrap());
     let block_align =
         u16::from_le_bytes(prefix[fb + 12..fb + 14].try_into().unwrap()).max(1) as u64;
 
     let source_frames = data_size / block_align;
-    let budget = preview_budget_frames(source_frames, sample_rate, threshold, window);
+    let rate = sample_rate.max(1) as f64;
+    // The synthetic stream can't seek, so start it at the region rather than
+    // decoding everything before it only to throw it away. The region is then
+    // relative to that start.
+    let start_frame = region
+        .map_or(0, |r| (r.start.as_secs_f64() * rate) as u64)
+        .min(source_frames);
+    let start_bytes = start_frame * block_align;
+    let region = region.map(|r| Region {
+        start: Duration::ZERO,
+        end: Some(r.end.map_or(
+            Duration::from_secs_f64((source_frames - start_frame) as f64 / rate),
+            |end| end.saturating_sub(r.start),
+        )),
+    });
+    let budget = match region {
+        Some(r) => r.end.map(|end| (end.as_secs_f64() * rate) as u64),
+        None => preview_budget_frames(source_frames, sample_rate, limits),
+    };
     let data_bytes = match budget {
-        Some(b) => (b * block_align).min(data_size),
+        Some(b) => (b * block_align).min(data_size - start_bytes),
         None => data_size,
     };
     // Fits u32: an untruncated file is < 4 GB; a truncated window is far smaller.
@@ -322,48 +510,85 @@ fn decode_rf64(
     header.extend_from_slice(b"data");
     header.extend_from_slice(&data_size_u32.to_le_bytes());
 
-    let mut file =
-        File::open(path).map_err(|e| PlaybackError::DecodeError(format!("{path:?}: {e}")))?;
-    file.seek(SeekFrom::Start(data_offset as u64))
-        .map_err(|e| PlaybackError::DecodeError(format!("{path:?}: {e}")))?;
+    let mut file = File::open(path).map_err(|e| PlaybackError::io(path, e))?;
+    file.seek(SeekFrom::Start(data_offset as u64 + start_bytes))
+        .map_err(|e| PlaybackError::io(path, e))?;
     let reader = Cursor::new(header).chain(file.take(data_bytes));
     let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(reader)), Default::default());
 
     let mut hint = Hint::new();
     hint.with_extension("wav");
-    decode_from_stream(mss, &hint, metadata, Some(source_frames), threshold, window)
+    decode_from_stream(mss, &hint, metadata, Some(source_frames), region, limits)
 }
 
 /// The preview frame budget for a source of `source_frames` at `sample_rate`,
 /// or `None` if the source is short enough to decode whole.
-fn preview_budget_frames(
+pub(crate) fn preview_budget_frames(
     source_frames: u64,
     sample_rate: u32,
-    threshold: Duration,
-    window: Duration,
+    limits: DecodeLimits,
 ) -> Option<u64> {
     if sample_rate == 0 || source_frames == 0 {
         return None;
     }
     let dur_secs = source_frames as f64 / sample_rate as f64;
-    if dur_secs > threshold.as_secs_f64() {
-        Some((window.as_secs_f64() * sample_rate as f64) as u64)
+    if dur_secs > limits.threshold.as_secs_f64() {
+        Some((limits.window.as_secs_f64() * sample_rate as f64) as u64)
     } else {
         None
     }
 }
 
+/// A run of packets whose sample rate or channel count differs from the
+/// stream's first: chained Ogg links, some broadcast captures.
+struct Segment {
+    channels: usize,
+    rate: u32,
+    samples: Vec<f32>,
+}
+
+impl Segment {
+    /// The samples in the stream's format, so the result plays as one piece.
+    fn convert(self, channels: u16, rate: u32) -> Result<Vec<f32>, PlaybackError> {
+        let samples = crate::mix::adapt_channels(self.samples, self.channels, channels as usize);
+        if self.rate == rate {
+            return Ok(samples);
+        }
+        crate::resample::resample(&samples, channels as usize, self.rate, rate)
+    }
+}
+
 /// Probe and decode an already-built stream into interleaved f32 samples. For
-/// sources longer than `threshold`, only the first `window` is decoded and
-/// `truncated` is set. `source_frames_override` supplies the true length when
-/// the stream's own header was rewritten (RF64 preview).
-fn decode_from_stream(
+/// sources longer than `limits.threshold`, only the first `limits.window` is
+/// decoded and `truncated` is set; more than `limits.max_bytes` of samples is
+/// an error, caught up front when the length is known. `source_frames_override` supplies the true length when
+/// the stream's own header was rewritten (RF64 preview). With a `region`,
+/// decoding seeks to its start (or decodes and discards up to it when the
+/// stream can't seek) and the threshold applies to the region's length.
+/// symphonia's decoders, and ours for Opus.
+#[cfg(feature = "opus")]
+fn codecs() -> &'static CodecRegistry {
+    static CODECS: std::sync::OnceLock<CodecRegistry> = std::sync::OnceLock::new();
+    CODECS.get_or_init(|| {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<crate::opus::OpusDecoder>();
+        registry
+    })
+}
+
+#[cfg(not(feature = "opus"))]
+fn codecs() -> &'static CodecRegistry {
+    symphonia::default::get_codecs()
+}
+
+pub(crate) fn decode_from_stream(
     mss: MediaSourceStream,
     hint: &Hint,
     metadata: AudioMetadata,
     source_frames_override: Option<u64>,
-    threshold: Duration,
-    window: Duration,
+    region: Option<Region>,
+    limits: DecodeLimits,
 ) -> Result<DecodedAudio, PlaybackError> {
     let probed = symphonia::default::get_probe()
         .format(
@@ -372,34 +597,81 @@ fn decode_from_stream(
             &FormatOptions::default(),
             &MetadataOptions::default(),
         )
-        .map_err(|e| PlaybackError::DecodeError(format!("unsupported audio format: {e}")))?;
+        .map_err(PlaybackError::unsupported)?;
 
     let mut format = probed.format;
 
     let track = format
         .default_track()
-        .ok_or_else(|| PlaybackError::DecodeError("no audio track found".into()))?;
+        .ok_or_else(|| PlaybackError::decode("no audio track found"))?;
 
-    let track_id = track.id;
+    let mut track_id = track.id;
     let codec_params = track.codec_params.clone();
 
     let sample_rate = codec_params
         .sample_rate
-        .ok_or_else(|| PlaybackError::DecodeError("unknown sample rate".into()))?;
+        .ok_or_else(|| PlaybackError::decode("unknown sample rate"))?;
 
-    let channels = codec_params.channels.map(|c| c.count() as u16).unwrap_or(2);
+    let mut channels = codec_params.channels.map(|c| c.count() as u16).unwrap_or(2);
 
     let source_frames_hint = source_frames_override
         .or(codec_params.n_frames)
         .unwrap_or(0);
-    let budget = preview_budget_frames(source_frames_hint, sample_rate, threshold, window);
+    let to_frames = |d: Duration| (d.as_secs_f64() * sample_rate as f64) as u64;
+    let start_frame = region.map_or(0, |r| to_frames(r.start));
+    let end_frame = region.and_then(|r| r.end).map(to_frames);
+    let span_frames = end_frame
+        .unwrap_or(source_frames_hint)
+        .saturating_sub(start_frame);
+    let budget = preview_budget_frames(span_frames, sample_rate, limits);
+    // Hard stop: the preview window, or the region's end, whichever is first.
+    let limit = match (budget, end_frame) {
+        (Some(b), _) => Some(b),
+        (None, Some(_)) => Some(span_frames),
+        (None, None) => None,
+    };
 
-    let mut decoder = symphonia::default::get_codecs()
+    let mut decoder = codecs()
         .make(&codec_params, &DecoderOptions::default())
-        .map_err(|e| PlaybackError::DecodeError(format!("codec init failed: {e}")))?;
+        .map_err(|e| PlaybackError::decode(format!("codec init failed: {e}")))?;
+
+    // Frames still to discard before the region starts. An accurate seek
+    // lands on a packet boundary at or before the target; the difference is
+    // trimmed off the first decoded packets.
+    let mut skip_frames = 0u64;
+    if let Some(r) = region.filter(|r| !r.start.is_zero()) {
+        let target = SeekTo::Time {
+            time: Time::from(r.start.as_secs_f64()),
+            track_id: Some(track_id),
+        };
+        skip_frames = match format.seek(SeekMode::Accurate, target) {
+            Ok(seeked) => {
+                decoder.reset();
+                let delta = seeked.required_ts.saturating_sub(seeked.actual_ts);
+                match codec_params.time_base {
+                    Some(tb) => {
+                        (delta as f64 * tb.numer as f64 / tb.denom as f64 * sample_rate as f64)
+                            as u64
+                    }
+                    None => delta,
+                }
+            }
+            Err(_) => start_frame,
+        };
+    }
 
-    let mut all_samples: Vec<f32> = Vec::new();
+    // Sized up front where the length is known, so the buffer isn't regrown
+    // (briefly holding two copies) as a long file streams in.
+    let expected_frames = limit.unwrap_or(source_frames_hint.saturating_sub(start_frame));
+    limits.fits(expected_frames.saturating_mul(channels as u64))?;
+    let mut all_samples: Vec<f32> =
+        Vec::with_capacity((expected_frames as usize).saturating_mul(channels as usize));
     let mut decoded_frames: u64 = 0;
+    // Reused across packets; only replaced if a packet outgrows it.
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+    // Audio in a different format than the stream started with, held until
+    // the format changes again.
+    let mut foreign: Option<Segment> = None;
 
     loop {
         let packet = match format.next_packet() {
@@ -409,10 +681,19 @@ fn decode_from_stream(
             {
                 break;
             }
+            // A chained Ogg link (or the like) starts a new logical stream:
+            // pick up its track with a fresh decoder and carry on.
             Err(symphonia::core::errors::Error::ResetRequired) => {
-                break;
+                let Some(track) = format.default_track() else {
+                    break;
+                };
+                track_id = track.id;
+                decoder = codecs()
+                    .make(&track.codec_params, &DecoderOptions::default())
+                    .map_err(|e| PlaybackError::decode(format!("codec init failed: {e}")))?;
+                continue;
             }
-            Err(e) => return Err(PlaybackError::DecodeError(format!("packet read: {e}"))),
+            Err(e) => return Err(PlaybackError::decode(format!("packet read: {e}"))),
         };
 
         if packet.track_id() != track_id {
@@ -422,10 +703,10 @@ fn decode_from_stream(
         let decoded = match decoder.decode(&packet) {
             Ok(d) => d,
             Err(symphonia::core::errors::Error::DecodeError(e)) => {
-                log::warn!("decode error (skipping packet): {e}");
+                tracing::warn!("decode error (skipping packet): {e}");
                 continue;
             }
-            Err(e) => return Err(PlaybackError::DecodeError(format!("decode: {e}"))),
+            Err(e) => return Err(PlaybackError::decode(format!("decode: {e}"))),
         };
 
         let spec = *decoded.spec();
@@ -435,23 +716,75 @@ fn decode_from_stream(
             continue;
         }
 
-        let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
-        sample_buf.copy_interleaved_ref(decoded);
-
-        all_samples.extend_from_slice(sample_buf.samples());
-        decoded_frames += num_frames as u64;
-
-        // Stop once the preview window is filled (long files).
-        if let Some(b) = budget {
-            if decoded_frames >= b {
-                break;
+        let packet_channels = spec.channels.count().max(1);
+        // Nothing kept yet: trust the stream over a guessed channel count.
+        if all_samples.is_empty() && foreign.is_none() {
+            channels = packet_channels as u16;
+        }
+        let native = packet_channels == channels as usize && spec.rate == sample_rate;
+        let needed = num_frames * packet_channels;
+        let buf = match &mut sample_buf {
+            Some(buf) if buf.capacity() >= needed => buf,
+            slot => slot.insert(SampleBuffer::new(
+                decoded.capacity().max(num_frames) as u64,
+                spec,
+            )),
+        };
+        buf.copy_interleaved_ref(decoded);
+
+        let skip = skip_frames.min(num_frames as u64);
+        skip_frames -= skip;
+        let mut kept = &buf.samples()[skip as usize * packet_channels..];
+        // The preview window and regions end exactly where asked, not on a
+        // packet boundary.
+        if let Some(limit) = limit {
+            let room = ((limit - decoded_frames) * spec.rate as u64).div_ceil(sample_rate as u64);
+            kept = &kept[..kept.len().min(room as usize * packet_channels)];
+        }
+        // Streams that don't state their length only show it as they go.
+        let held = foreign.as_ref().map_or(0, |s| s.samples.len());
+        limits.fits((all_samples.len() + held + kept.len()) as u64)?;
+
+        let kept_frames = (kept.len() / packet_channels) as u64;
+        decoded_frames += (kept_frames * sample_rate as u64).div_ceil(spec.rate.max(1) as u64);
+        if native {
+            if let Some(segment) = foreign.take() {
+                all_samples.extend(segment.convert(channels, sample_rate)?);
+            }
+            all_samples.extend_from_slice(kept);
+        } else {
+            match &mut foreign {
+                Some(s) if s.channels == packet_channels && s.rate == spec.rate => {
+                    s.samples.extend_from_slice(kept);
+                }
+                _ => {
+                    let previous = foreign.replace(Segment {
+                        channels: packet_channels,
+                        rate: spec.rate,
+                        samples: kept.to_vec(),
+                    });
+                    if let Some(segment) = previous {
+                        all_samples.extend(segment.convert(channels, sample_rate)?);
+                    }
+                }
             }
         }
+
+        if limit.is_some_and(|limit| decoded_frames >= limit) {
+            break;
+        }
     }
 
+    if let Some(segment) = foreign.take() {
+        all_samples.extend(segment.convert(channels, sample_rate)?);
+    }
+    if let Some(limit) = limit {
+        all_samples.truncate(limit as usize * channels as usize);
+    }
     if all_samples.is_empty() {
-        return Err(PlaybackError::DecodeError("no audio data decoded".into()));
+        return Err(PlaybackError::decode("no audio data decoded"));
     }
+    let decoded_frames = (all_samples.len() / channels.max(1) as usize) as u64;
 
     let source_frames = if source_frames_hint > 0 {
         source_frames_hint
@@ -601,18 +934,62 @@ mod tests {
 
     #[test]
     fn preview_budget_thresholds() {
-        let thr = Duration::from_secs(120);
-        let win = Duration::from_secs(120);
+        let limits = DecodeLimits::default();
         // 130 s at 48k -> preview of 120 s.
         assert_eq!(
-            preview_budget_frames(48_000 * 130, 48_000, thr, win),
+            preview_budget_frames(48_000 * 130, 48_000, limits),
             Some(48_000 * 120)
         );
         // 60 s -> decode whole.
-        assert_eq!(preview_budget_frames(48_000 * 60, 48_000, thr, win), None);
+        assert_eq!(preview_budget_frames(48_000 * 60, 48_000, limits), None);
         // Unknown length / rate -> no budget.
-        assert_eq!(preview_budget_frames(0, 48_000, thr, win), None);
-        assert_eq!(preview_budget_frames(48_000, 0, thr, win), None);
+        assert_eq!(preview_budget_frames(0, 48_000, limits), None);
+        assert_eq!(preview_budget_frames(48_000, 0, limits), None);
+    }
+
+    #[test]
+    fn foreign_segments_convert_to_the_stream_format() {
+        // A mono 22.05 kHz link inside a stereo 44.1 kHz stream.
+        let segment = Segment {
+            channels: 1,
+            rate: 22_050,
+            samples: vec![0.5; 2_205],
+        };
+        let out = segment.convert(2, 44_100).unwrap();
+        assert_eq!(out.len(), 4_410 * 2);
+        assert!((out[2_000] - 0.5).abs() < 0.01 && out[2_000] == out[2_001]);
+
+        let same = Segment {
+            channels: 2,
+            rate: 44_100,
+            samples: vec![0.25, -0.25],
+        };
+        assert_eq!(same.convert(2, 44_100).unwrap(), [0.25, -0.25]);
+    }
+
+    #[test]
+    fn decode_refuses_over_memory_budget() {
+        // 1 s mono @ 8 kHz = 32 kB of f32 samples.
+        let decode_with = |max_bytes| {
+            let bytes = pcm_wav(8_000, 8_000);
+            let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+            let mut hint = Hint::new();
+            hint.with_extension("wav");
+            let limits = DecodeLimits {
+                max_bytes: Some(max_bytes),
+                ..DecodeLimits::default()
+            };
+            decode_from_stream(mss, &hint, AudioMetadata::default(), None, None, limits)
+        };
+
+        assert!(decode_with(32_000).is_ok());
+        assert!(matches!(
+            decode_with(16_000),
+            Err(PlaybackError::TooLarge {
+                needed: 32_000,
+                limit: 16_000
+            })
+        ));
     }
 
     #[test]
@@ -627,8 +1004,12 @@ mod tests {
             &hint,
             AudioMetadata::default(),
             None,
-            Duration::from_millis(100),
-            Duration::from_millis(200),
+            None,
+            DecodeLimits {
+                threshold: Duration::from_millis(100),
+                window: Duration::from_millis(200),
+                max_bytes: None,
+            },
         )
         .expect("decode");
 
@@ -640,6 +1021,57 @@ mod tests {
         assert!((out.source_duration.as_secs_f64() - 1.0).abs() < 0.05);
     }
 
+    #[test]
+    fn decode_region_is_sample_exact() {
+        // 1 s mono @ 8 kHz ramp: sample i has value i, so the decoded start
+        // identifies exactly where the region began.
+        let mut bytes = pcm_wav(8_000, 8_000);
+        let data_start = bytes.len() - 16_000;
+        for i in 0..8_000usize {
+            bytes[data_start + 2 * i..data_start + 2 * i + 2]
+                .copy_from_slice(&(i as i16).to_le_bytes());
+        }
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("wav");
+        let region = Region {
+            start: Duration::from_millis(250),
+            end: Some(Duration::from_millis(500)),
+        };
+        let out = decode_from_stream(
+            mss,
+            &hint,
+            AudioMetadata::default(),
+            None,
+            Some(region),
+            DecodeLimits::default(),
+        )
+        .expect("decode");
+
+        assert_eq!(out.interleaved.len(), 2_000);
+        assert_eq!((out.interleaved[0] * 32768.0).round(), 2_000.0);
+        assert_eq!((out.interleaved[1_999] * 32768.0).round(), 3_999.0);
+        assert!(!out.truncated);
+    }
+
+    #[test]
+    fn errors_name_the_file_and_their_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.wav");
+        let Err(e) = decode_file(&missing) else {
+            panic!("decoded a missing file");
+        };
+        assert_eq!(e.kind(), punks_core::ErrorKind::NotFound);
+        assert_eq!(e.path(), Some(missing.as_path()));
+
+        let garbage = dir.path().join("notes.mp3");
+        std::fs::write(&garbage, b"not audio at all").unwrap();
+        let e = probe(&garbage).unwrap_err();
+        assert_eq!(e.kind(), punks_core::ErrorKind::UnsupportedFormat);
+        assert_eq!(e.path(), Some(garbage.as_path()));
+        assert!(e.to_string().contains("notes.mp3"));
+    }
+
     #[test]
     fn decode_rf64_end_to_end() {
         // Build a tiny RF64: RF64 + ds64 + fmt (PCM mono 8k) + data (4 frames).
@@ -674,6 +1106,12 @@ mod tests {
         let path = dir.join(format!("punks2_rf64_{}.wav", std::process::id()));
         std::fs::write(&path, &v).expect("write temp rf64");
         let out = decode_file(&path);
+        // From frame 2: the stream starts there instead of skipping to it.
+        let region = Region {
+            start: Duration::from_micros(250),
+            end: None,
+        };
+        let tail = decode(&path, Some(region), DecodeLimits::default());
         let _ = std::fs::remove_file(&path);
         let out = out.expect("decode rf64");
 
@@ -681,5 +1119,197 @@ mod tests {
         assert_eq!(out.sample_rate, 8_000);
         assert_eq!(out.interleaved.len(), frames.len());
         assert!(!out.truncated);
+
+        let tail = tail.expect("decode rf64 region").interleaved;
+        let tail: Vec<i16> = tail.iter().map(|s| (s * 32768.0).round() as i16).collect();
+        assert_eq!(tail, [-1000, 500]);
+    }
+
+    /// An MP4 box of `kind` around `body`; `full` prefixes the version and
+    /// flags word.
+    fn mp4_box(kind: &[u8; 4], full: bool, body: &[u8]) -> Vec<u8> {
+        let extra = if full { 4 } else { 0 };
+        let mut v = Vec::new();
+        v.extend_from_slice(&((8 + extra + body.len()) as u32).to_be_bytes());
+        v.extend_from_slice(kind);
+        v.extend_from_slice(&vec![0; extra]);
+        v.extend_from_slice(body);
+        v
+    }
+
+    fn be32(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    /// A one-track M4A: `entry` is the sample entry's codec box and
+    /// `packets` hold `frames_per_packet` frames each, all in one chunk.
+    fn m4a(
+        codec: &[u8; 4],
+        entry: &[u8],
+        channels: u16,
+        sample_rate: u32,
+        frames_per_packet: u32,
+        packets: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let count = packets.len() as u32;
+        let frames = count * frames_per_packet;
+        let ftyp = mp4_box(b"ftyp", false, b"M4A \0\0\0\0M4A isom");
+        let moov = |data_offset: u32| {
+            let mut sample_entry = vec![0; 6];
+            sample_entry.extend_from_slice(&1u16.to_be_bytes()); // data reference
+            sample_entry.extend_from_slice(&[0; 8]); // version, revision, vendor
+            sample_entry.extend_from_slice(&channels.to_be_bytes());
+            sample_entry.extend_from_slice(&16u16.to_be_bytes());
+            sample_entry.extend_from_slice(&[0; 4]);
+            sample_entry.extend_from_slice(&(sample_rate << 16).to_be_bytes());
+            sample_entry.extend_from_slice(entry);
+            let mut stsd = be32(&[1]);
+            stsd.extend_from_slice(&mp4_box(codec, false, &sample_entry));
+            let mut stsz = be32(&[0, count]);
+            stsz.extend(packets.iter().flat_map(|p| (p.len() as u32).to_be_bytes()));
+            let stbl = [
+                mp4_box(b"stsd", true, &stsd),
+                mp4_box(b"stts", true, &be32(&[1, count, frames_per_packet])),
+                mp4_box(b"stsc", true, &be32(&[1, 1, count, 1])),
+                mp4_box(b"stsz", true, &stsz),
+                mp4_box(b"stco", true, &be32(&[1, data_offset])),
+            ]
+            .concat();
+            let mut hdlr = be32(&[0]);
+            hdlr.extend_from_slice(b"soun");
+            hdlr.extend_from_slice(&[0; 13]);
+            let mdia = [
+                mp4_box(
+                    b"mdhd",
+                    true,
+                    &be32(&[0, 0, sample_rate, frames, 0x55c4_0000]),
+                ),
+                mp4_box(b"hdlr", true, &hdlr),
+                mp4_box(
+                    b"minf",
+                    false,
+                    &[
+                        mp4_box(b"smhd", true, &[0; 4]),
+                        mp4_box(b"stbl", false, &stbl),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat();
+            let mut tkhd = be32(&[0, 0, 1, 0, frames, 0, 0, 0, 0x0100_0000]);
+            tkhd.extend_from_slice(&[0; 44]);
+            let trak = [
+                mp4_box(b"tkhd", true, &tkhd),
+                mp4_box(b"mdia", false, &mdia),
+            ]
+            .concat();
+            let mut mvhd = be32(&[0, 0, sample_rate, frames, 0x0001_0000, 0x0100_0000]);
+            mvhd.extend_from_slice(&[0; 72]);
+            let moov = [
+                mp4_box(b"mvhd", true, &mvhd),
+                mp4_box(b"trak", false, &trak),
+            ]
+            .concat();
+            mp4_box(b"moov", false, &moov)
+        };
+        let data_offset = (ftyp.len() + moov(0).len() + 8) as u32;
+        [
+            ftyp,
+            moov(data_offset),
+            mp4_box(b"mdat", false, &packets.concat()),
+        ]
+        .concat()
+    }
+
+    /// Bits written most significant first, padded to a whole byte.
+    fn pack_bits(fields: &[(u32, u32)]) -> Vec<u8> {
+        let bits: Vec<bool> = fields
+            .iter()
+            .flat_map(|&(value, width)| (0..width).rev().map(move |i| value >> i & 1 == 1))
+            .collect();
+        bits.chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, &bit)| acc | (u8::from(bit) << (7 - i)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_alac_in_m4a() {
+        // Stereo 16-bit, 4 frames a packet, each packet an uncompressed
+        // channel pair element.
+        let frames: Vec<[i16; 2]> = (0..8).map(|i| [i * 1000, -i * 500]).collect();
+        let packets: Vec<Vec<u8>> = frames
+            .chunks(4)
+            .map(|packet| {
+                // Pair element, tag 0, 12 unused bits, whole frame, no
+                // shift, uncompressed; then the samples and the end tag.
+                let mut fields = vec![(1, 3), (0, 4), (0, 12), (0, 1), (0, 2), (1, 1)];
+                for frame in packet {
+                    fields.extend(frame.iter().map(|&s| (s as u16 as u32, 16)));
+                }
+                fields.push((7, 3));
+                pack_bits(&fields)
+            })
+            .collect();
+        let mut cookie = be32(&[4]);
+        cookie.extend_from_slice(&[0, 16, 40, 10, 14, 2, 0, 255]);
+        cookie.extend_from_slice(&be32(&[0, 0, 44_100]));
+        let entry = mp4_box(b"alac", true, &cookie);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loop.m4a");
+        std::fs::write(&path, m4a(b"alac", &entry, 2, 44_100, 4, &packets)).unwrap();
+        let info = probe(&path).unwrap();
+        assert_eq!((info.sample_rate, info.channels), (44_100, 2));
+        let out = decode_file(&path).unwrap();
+        let expected: Vec<f32> = frames
+            .iter()
+            .flatten()
+            .map(|&s| s as f32 / 32_768.0)
+            .collect();
+        assert_eq!(out.interleaved, expected);
+    }
+
+    #[test]
+    fn decodes_aac_in_m4a() {
+        // Mono AAC-LC at 44.1 kHz: AudioSpecificConfig, then the
+        // descriptors wrapping it.
+        let esds = [
+            &[0x03, 25, 0, 1, 0][..],
+            &[0x04, 17, 0x40, 0x15, 0, 0, 0][..],
+            &be32(&[0, 0]),
+            &[0x05, 2, 0x12, 0x08, 0x06, 1, 0x02][..],
+        ]
+        .concat();
+        let entry = mp4_box(b"esds", true, &esds);
+        // Silent frames: a single channel element with no scale factor
+        // bands, then the end tag.
+        let silence = pack_bits(&[
+            (0, 3),
+            (0, 4),
+            (100, 8),
+            (0, 1),
+            (0, 2),
+            (0, 1),
+            (0, 6),
+            (0, 1),
+            (0, 3),
+            (7, 3),
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pad.m4a");
+        std::fs::write(
+            &path,
+            m4a(b"mp4a", &entry, 1, 44_100, 1024, &vec![silence; 4]),
+        )
+        .unwrap();
+        let out = decode_file(&path).unwrap();
+        assert_eq!((out.sample_rate, out.channels), (44_100, 1));
+        assert!(!out.interleaved.is_empty());
+        assert!(out.interleaved.iter().all(|&s| s == 0.0));
     }
 }
@@ -0,0 +1,115 @@
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use punks_core::ErrorKind;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlaybackError {
+    /// The file couldn't be opened or read.
+    #[error("couldn't read {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    /// Not audio the decoder understands.
+    #[error("{}unsupported audio format: {reason}", named(path))]
+    UnsupportedFormat {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+    /// Audio the decoder understands but couldn't read through.
+    #[error("{}decode error: {message}", named(path))]
+    Decode {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    #[error("couldn't write {}: {message}", path.display())]
+    Encode { path: PathBuf, message: String },
+    /// The output device failed while `operation` ("opening", "starting",
+    /// ...) was under way. `device` is `None` for the default output.
+    #[error(
+        "audio device error {operation} {}: {message}",
+        device.as_deref().unwrap_or("the default output")
+    )]
+    Device {
+        device: Option<String>,
+        operation: &'static str,
+        message: String,
+    },
+    /// Decoding would take more memory than the engine's budget allows.
+    /// `needed` is a lower bound for streams that don't state their length.
+    #[error(
+        "too large to load: needs {} MB of memory, over the {} MB limit",
+        needed.div_ceil(1 << 20),
+        limit >> 20
+    )]
+    TooLarge { needed: u64, limit: u64 },
+}
+
+/// `"<path>: "`, to prefix a message with the file it's about, if known.
+fn named(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|p| format!("{}: ", p.display()))
+        .unwrap_or_default()
+}
+
+impl PlaybackError {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        PlaybackError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub(crate) fn unsupported(reason: impl Display) -> Self {
+        PlaybackError::UnsupportedFormat {
+            path: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub(crate) fn decode(message: impl Display) -> Self {
+        PlaybackError::Decode {
+            path: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn device(device: Option<&str>, operation: &'static str, e: impl Display) -> Self {
+        PlaybackError::Device {
+            device: device.map(str::to_string),
+            operation,
+            message: e.to_string(),
+        }
+    }
+
+    /// Name `path` in a decode error raised where the file wasn't known.
+    pub(crate) fn at(mut self, file: &Path) -> Self {
+        if let PlaybackError::UnsupportedFormat { path, .. } | PlaybackError::Decode { path, .. } =
+            &mut self
+        {
+            path.get_or_insert_with(|| file.to_path_buf());
+        }
+        self
+    }
+
+    /// The file the error is about, when it's about one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PlaybackError::Io { path, .. } | PlaybackError::Encode { path, .. } => Some(path),
+            PlaybackError::UnsupportedFormat { path, .. } | PlaybackError::Decode { path, .. } => {
+                path.as_deref()
+            }
+            PlaybackError::Device { .. } | PlaybackError::TooLarge { .. } => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PlaybackError::Io { source, .. } => ErrorKind::of_io(source),
+            PlaybackError::UnsupportedFormat { .. } => ErrorKind::UnsupportedFormat,
+            PlaybackError::Decode { .. } => ErrorKind::Decode,
+            PlaybackError::Encode { .. } => ErrorKind::Encode,
+            PlaybackError::Device { .. } => ErrorKind::Device,
+            PlaybackError::TooLarge { .. } => ErrorKind::TooLarge,
+        }
+    }
+}
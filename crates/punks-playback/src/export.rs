@@ -0,0 +1,235 @@
+//! Exporting a sample as a WAV in the format another tool wants, e.g. a
+//! 44.1 kHz / 16-bit copy of an MP3 one-shot for a hardware sampler: decoded,
+//! resampled to the chosen rate, and written as dithered 16- or 24-bit
+//! integers or as 32-bit floats. On the way it can be normalized, and have
+//! a preview's speed and trim baked in so it sounds as it was auditioned.
+
+use std::path::{Path, PathBuf};
+
+use crate::decode::{self, DecodeLimits, Region};
+use crate::process::Normalize;
+use crate::{analysis, resample, wav, PlaybackError};
+
+/// How each sample is stored in an exported WAV.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    Int16,
+    #[default]
+    Int24,
+    /// Keeps anything above full scale rather than clipping it.
+    Float32,
+}
+
+impl BitDepth {
+    pub const ALL: [BitDepth; 3] = [BitDepth::Int16, BitDepth::Int24, BitDepth::Float32];
+
+    pub fn bits(self) -> u16 {
+        match self {
+            BitDepth::Int16 => 16,
+            BitDepth::Int24 => 24,
+            BitDepth::Float32 => 32,
+        }
+    }
+
+    fn wav_spec(self, channels: u16, sample_rate: u32) -> hound::WavSpec {
+        hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: self.bits(),
+            sample_format: match self {
+                BitDepth::Float32 => hound::SampleFormat::Float,
+                BitDepth::Int16 | BitDepth::Int24 => hound::SampleFormat::Int,
+            },
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BitDepth::Int16 => "16-bit",
+            BitDepth::Int24 => "24-bit",
+            BitDepth::Float32 => "32-bit float",
+        }
+    }
+}
+
+/// Rates offered for export, besides keeping the source's.
+pub const SAMPLE_RATES: [u32; 5] = [22_050, 44_100, 48_000, 88_200, 96_000];
+
+/// The format to export in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSpec {
+    /// `None` keeps the source's rate.
+    pub sample_rate: Option<u32>,
+    pub bit_depth: BitDepth,
+    /// Add triangular noise of one step before rounding to integers, so
+    /// quiet tails fade into noise rather than distorting. Ignored for
+    /// float output.
+    pub dither: bool,
+    /// Level the sample to a peak or loudness before `gain_db`. Unlike a
+    /// preview's loudness target, quiet samples are brought all the way up.
+    pub normalize: Normalize,
+    /// Gain on top of `normalize`, e.g. a preview's trim.
+    pub gain_db: f32,
+    /// Varispeed, as a preview's rate or pitch: 2.0 is twice as fast and
+    /// an octave up, so half as long.
+    pub speed: f32,
+}
+
+impl Default for ExportSpec {
+    fn default() -> Self {
+        ExportSpec {
+            sample_rate: None,
+            bit_depth: BitDepth::default(),
+            dither: true,
+            normalize: Normalize::Off,
+            gain_db: 0.0,
+            speed: 1.0,
+        }
+    }
+}
+
+/// What an export wrote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedFile {
+    pub output: PathBuf,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Gain applied by normalizing and `gain_db` together.
+    pub gain_db: f32,
+    /// Samples past full scale, clipped to fit integer output.
+    pub clipped: usize,
+}
+
+/// Write `source`, or just `region` of it, to `output` as a WAV in `spec`'s
+/// format, with the source's channels.
+#[tracing::instrument(level = "debug", skip_all, fields(path = %source.display()))]
+pub fn export(
+    source: &Path,
+    region: Option<Region>,
+    output: &Path,
+    spec: &ExportSpec,
+) -> Result<ExportedFile, PlaybackError> {
+    let decoded = decode::decode(source, region, DecodeLimits::FULL)?;
+    let channels = decoded.channels.max(1);
+    let rate = spec.sample_rate.unwrap_or(decoded.sample_rate);
+    // Played `speed` times faster, the source's samples go by as if
+    // recorded at that much higher a rate.
+    let speed = f64::from(spec.speed.clamp(crate::MIN_RATE, crate::MAX_RATE));
+    let source_rate = (f64::from(decoded.sample_rate) * speed).round() as u32;
+    let mut samples = if rate == source_rate {
+        decoded.interleaved
+    } else {
+        resample::resample(&decoded.interleaved, channels as usize, source_rate, rate)?
+    };
+
+    let level = match spec.normalize {
+        Normalize::Off => None,
+        Normalize::Peak { target_dbfs } => {
+            let peak = analysis::sample_peak(&samples);
+            (peak > 0.0).then(|| analysis::db_to_gain(target_dbfs) / peak)
+        }
+        Normalize::Loudness { target_lufs } => {
+            analysis::integrated_loudness(&samples, channels as usize, rate)
+                .map(|lufs| analysis::db_to_gain(target_lufs - lufs))
+        }
+    };
+    let gain = level.unwrap_or(1.0) * analysis::db_to_gain(spec.gain_db);
+    if gain != 1.0 {
+        samples.iter_mut().for_each(|s| *s *= gain);
+    }
+
+    let clipped = wav::write_wav(
+        output,
+        &samples,
+        spec.bit_depth.wav_spec(channels, rate),
+        spec.dither,
+    )?;
+    Ok(ExportedFile {
+        output: output.to_path_buf(),
+        sample_rate: rate,
+        channels,
+        gain_db: analysis::gain_to_db(gain),
+        clipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pcm16(path: &Path, rate: u32, samples: &[i16]) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut w = hound::WavWriter::create(path, spec).unwrap();
+        for &s in samples {
+            w.write_sample(s).unwrap();
+        }
+        w.finalize().unwrap();
+    }
+
+    #[test]
+    fn converts_rate_and_bit_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("hit.wav");
+        let pcm: Vec<i16> = (0..8_000)
+            .map(|i| ((i as f32 * 0.05).sin() * 8_000.0) as i16)
+            .collect();
+        write_pcm16(&src, 8_000, &pcm);
+
+        let out = dir.path().join("out/hit.wav");
+        let spec = ExportSpec {
+            sample_rate: Some(16_000),
+            bit_depth: BitDepth::Int24,
+            ..ExportSpec::default()
+        };
+        let done = export(&src, None, &out, &spec).unwrap();
+        assert_eq!(
+            (done.sample_rate, done.channels, done.clipped),
+            (16_000, 1, 0)
+        );
+
+        let reader = hound::WavReader::open(&out).unwrap();
+        assert_eq!(reader.spec().bits_per_sample, 24);
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Int);
+        assert_eq!(reader.len(), 16_000);
+    }
+
+    #[test]
+    fn bakes_in_speed_normalizing_and_gain() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("hit.wav");
+        let pcm: Vec<i16> = (0..8_000)
+            .map(|i| ((i as f32 * 0.05).sin() * 8_192.0) as i16)
+            .collect();
+        write_pcm16(&src, 8_000, &pcm);
+
+        let out = dir.path().join("fast.wav");
+        let spec = ExportSpec {
+            bit_depth: BitDepth::Float32,
+            normalize: Normalize::Peak { target_dbfs: 0.0 },
+            gain_db: -6.0,
+            speed: 2.0,
+            ..ExportSpec::default()
+        };
+        let done = export(&src, None, &out, &spec).unwrap();
+        assert_eq!(done.sample_rate, 8_000);
+        assert!((done.gain_db - 6.0).abs() < 0.1, "gain = {}", done.gain_db);
+
+        // Twice as fast is half as long, and peaks at -6 dB after the
+        // normalize to 0 dB.
+        let mut reader = hound::WavReader::open(&out).unwrap();
+        assert_eq!(reader.len(), 4_000);
+        let peak = reader
+            .samples::<f32>()
+            .map(|s| s.unwrap().abs())
+            .fold(0.0, f32::max);
+        assert!(
+            (analysis::gain_to_db(peak) + 6.0).abs() < 0.1,
+            "peak = {peak}"
+        );
+    }
+}
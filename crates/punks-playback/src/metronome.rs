@@ -0,0 +1,134 @@
+//! The metronome: a click on every beat, higher on the downbeat of each 4/4
+//! bar, mixed into the output by the audio callback. It counts output frames
+//! while on, so a loop can be started where the click is in its bar (see
+//! [`PlaybackEngine::set_tempo_sync`](crate::PlaybackEngine::set_tempo_sync)).
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Slowest and fastest click.
+pub const MIN_BPM: f32 = 20.0;
+pub const MAX_BPM: f32 = 300.0;
+
+const BEATS_PER_BAR: u64 = 4;
+
+/// Click pitches, and how fast each click dies away.
+const DOWNBEAT_HZ: f32 = 1500.0;
+const BEAT_HZ: f32 = 1000.0;
+const DECAY_SECS: f32 = 0.008;
+/// Long enough for the decay to reach silence.
+const CLICK_SECS: f32 = 0.04;
+const LEVEL: f32 = 0.5;
+
+pub(crate) struct Metronome {
+    /// Tempo; 0 when off.
+    bpm: AtomicU32,
+    /// Output frames since it was switched on.
+    frame: AtomicU64,
+    sample_rate: AtomicU32,
+}
+
+impl Metronome {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        Metronome {
+            bpm: AtomicU32::new(0),
+            frame: AtomicU64::new(0),
+            sample_rate: AtomicU32::new(sample_rate),
+        }
+    }
+
+    /// Click at `bpm`, or stop; switching it on starts on a downbeat.
+    pub(crate) fn set_bpm(&self, bpm: Option<f32>) {
+        let bits = bpm.map_or(0, |b| b.clamp(MIN_BPM, MAX_BPM).to_bits());
+        if self.bpm.swap(bits, Ordering::Relaxed) == 0 {
+            self.frame.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn bpm(&self) -> Option<f32> {
+        let bits = self.bpm.load(Ordering::Relaxed);
+        (bits != 0).then(|| f32::from_bits(bits))
+    }
+
+    /// The output's rate changed (the stream was reopened).
+    pub(crate) fn set_sample_rate(&self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn beat_frames(&self, bpm: f32) -> f64 {
+        f64::from(self.sample_rate.load(Ordering::Relaxed)) * 60.0 / f64::from(bpm)
+    }
+
+    /// How far through a span of `beats` beats the click is, 0..1, counting
+    /// spans from when it was switched on; `None` when it's off.
+    pub(crate) fn phase(&self, beats: f32) -> Option<f64> {
+        let bpm = self.bpm()?;
+        let span = self.beat_frames(bpm) * f64::from(beats.max(1.0));
+        let frame = self.frame.load(Ordering::Relaxed) as f64;
+        Some((frame / span).fract())
+    }
+
+    /// Add the clicks due in `data` (interleaved, `channels` wide), scaled
+    /// by `volume`.
+    pub(crate) fn mix(&self, data: &mut [f32], channels: usize, volume: f32) {
+        let Some(bpm) = self.bpm() else {
+            return;
+        };
+        let rate = self.sample_rate.load(Ordering::Relaxed).max(1) as f32;
+        let beat_frames = self.beat_frames(bpm);
+        let click_frames = (CLICK_SECS * rate) as f64;
+        let start = self.frame.load(Ordering::Relaxed);
+        let frames = data.len() / channels.max(1);
+        for (i, out) in data.chunks_exact_mut(channels.max(1)).enumerate() {
+            let frame = (start + i as u64) as f64;
+            let beat = (frame / beat_frames).floor();
+            let into = frame - beat * beat_frames;
+            if into >= click_frames {
+                continue;
+            }
+            let hz = if (beat as u64).is_multiple_of(BEATS_PER_BAR) {
+                DOWNBEAT_HZ
+            } else {
+                BEAT_HZ
+            };
+            let t = into as f32 / rate;
+            let click = (std::f32::consts::TAU * hz * t).sin() * (-t / DECAY_SECS).exp();
+            for s in out {
+                *s += click * LEVEL * volume;
+            }
+        }
+        self.frame.store(start + frames as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_on_the_beat_and_tracks_its_phase() {
+        let metronome = Metronome::new(48_000);
+        let mut out = vec![0.0; 96_000];
+        metronome.mix(&mut out, 1, 1.0);
+        assert!(out.iter().all(|&s| s == 0.0), "off until given a tempo");
+        assert_eq!(metronome.phase(4.0), None);
+
+        // 120 bpm: a beat every 24000 frames, each click 1920 long.
+        metronome.set_bpm(Some(120.0));
+        metronome.mix(&mut out, 1, 1.0);
+        for beat in 0..4 {
+            let click = &out[beat * 24_000..][..1920];
+            assert!(click.iter().any(|s| s.abs() > 0.1), "beat {beat}");
+            let gap = &out[beat * 24_000 + 1920..(beat + 1) * 24_000];
+            assert!(gap.iter().all(|&s| s == 0.0), "after beat {beat}");
+        }
+        // One bar of four in; halfway through a two-bar span.
+        assert_eq!(metronome.phase(4.0), Some(0.0));
+        assert_eq!(metronome.phase(8.0), Some(0.5));
+
+        metronome.set_bpm(Some(90.0));
+        assert_eq!(metronome.phase(8.0), Some(0.375));
+        metronome.set_bpm(None);
+        metronome.set_bpm(Some(120.0));
+        assert_eq!(metronome.phase(4.0), Some(0.0), "restarts on a downbeat");
+    }
+}
@@ -0,0 +1,171 @@
+//! Mapping decoded audio onto the output device's channel count. Extra
+//! channels are mixed down to an equal-weight average on every output
+//! channel; missing ones repeat the last source channel.
+//!
+//! The loops run over whole frames, with the common channel counts fixed at
+//! compile time, so they vectorize instead of indexing sample by sample. That
+//! matters for long multichannel files, where this used to dominate load time.
+//!
+//! Also home to [`sanitize`], the pass that keeps corrupt decodes from
+//! reaching the device.
+
+/// `samples` (interleaved, `from` channels) re-laid out for `to` channels.
+/// A trailing partial frame is dropped. Matching counts hand `samples` back
+/// untouched.
+pub(crate) fn adapt_channels(samples: Vec<f32>, from: usize, to: usize) -> Vec<f32> {
+    if from == to || from == 0 || to == 0 {
+        return samples;
+    }
+    let samples = samples.as_slice();
+
+    let mut out = vec![0.0; samples.len() / from * to];
+    if from > to {
+        match from {
+            2 => downmix::<2>(samples, &mut out, to),
+            4 => downmix::<4>(samples, &mut out, to),
+            6 => downmix::<6>(samples, &mut out, to),
+            8 => downmix::<8>(samples, &mut out, to),
+            _ => downmix_any(samples, from, &mut out, to),
+        }
+    } else {
+        match (from, to) {
+            (1, 2) => spread::<2>(samples, &mut out),
+            (1, _) => {
+                for (o, &s) in out.chunks_exact_mut(to).zip(samples) {
+                    o.fill(s);
+                }
+            }
+            _ => upmix(samples, from, &mut out, to),
+        }
+    }
+    out
+}
+
+/// Average of each `FROM`-channel frame, written to all `to` channels.
+fn downmix<const FROM: usize>(samples: &[f32], out: &mut [f32], to: usize) {
+    let inv = 1.0 / FROM as f32;
+    let frames = samples
+        .chunks_exact(FROM)
+        .map(|f| <&[f32; FROM]>::try_from(f).unwrap());
+    let mono = |f: &[f32; FROM]| f.iter().sum::<f32>() * inv;
+    match to {
+        1 => {
+            for (o, f) in out.iter_mut().zip(frames) {
+                *o = mono(f);
+            }
+        }
+        2 => {
+            for (o, f) in out.chunks_exact_mut(2).zip(frames) {
+                let m = mono(f);
+                o.copy_from_slice(&[m, m]);
+            }
+        }
+        _ => {
+            for (o, f) in out.chunks_exact_mut(to).zip(frames) {
+                o.fill(mono(f));
+            }
+        }
+    }
+}
+
+/// [`downmix`] for channel counts without a fixed-size kernel.
+fn downmix_any(samples: &[f32], from: usize, out: &mut [f32], to: usize) {
+    let inv = 1.0 / from as f32;
+    for (o, f) in out.chunks_exact_mut(to).zip(samples.chunks_exact(from)) {
+        o.fill(f.iter().sum::<f32>() * inv);
+    }
+}
+
+/// Mono to `TO` identical channels.
+fn spread<const TO: usize>(samples: &[f32], out: &mut [f32]) {
+    for (o, &s) in out.chunks_exact_mut(TO).zip(samples) {
+        *<&mut [f32; TO]>::try_from(o).unwrap() = [s; TO];
+    }
+}
+
+/// Copy the `from` channels, then repeat the last one up to `to`.
+fn upmix(samples: &[f32], from: usize, out: &mut [f32], to: usize) {
+    for (o, f) in out.chunks_exact_mut(to).zip(samples.chunks_exact(from)) {
+        let (head, tail) = o.split_at_mut(from);
+        head.copy_from_slice(f);
+        tail.fill(f[from - 1]);
+    }
+}
+
+/// Loudest sample let through. Anything beyond is a decode glitch rather than
+/// audio; at about +12 dBFS, hot float files still pass untouched.
+const SAMPLE_LIMIT: f32 = 4.0;
+
+/// Zero NaN/infinite samples and clamp the rest to ±[`SAMPLE_LIMIT`], so a
+/// corrupt file can't latch a driver into full-scale noise. Returns how many
+/// samples were changed.
+pub(crate) fn sanitize(samples: &mut [f32]) -> usize {
+    let mut fixed = 0;
+    for s in samples {
+        let clean = if s.is_finite() {
+            s.clamp(-SAMPLE_LIMIT, SAMPLE_LIMIT)
+        } else {
+            0.0
+        };
+        // Compare bits so NaN (never equal to itself) counts as changed.
+        if clean.to_bits() != s.to_bits() {
+            *s = clean;
+            fixed += 1;
+        }
+    }
+    fixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_replaces_non_finite_and_clamps() {
+        let mut samples = [
+            0.5,
+            f32::NAN,
+            -1.5,
+            f32::INFINITY,
+            1e9,
+            f32::NEG_INFINITY,
+            -0.0,
+        ];
+        assert_eq!(sanitize(&mut samples), 4);
+        assert_eq!(samples, [0.5, 0.0, -1.5, 0.0, SAMPLE_LIMIT, 0.0, -0.0]);
+    }
+
+    /// The original per-sample loop, as the reference for the kernels.
+    fn scalar(samples: &[f32], from: usize, to: usize) -> Vec<f32> {
+        let num_frames = samples.len() / from;
+        let mut out = Vec::with_capacity(num_frames * to);
+        let inv_from = 1.0 / from as f32;
+        for frame in 0..num_frames {
+            let base = frame * from;
+            if from > to {
+                let mono: f32 = (0..from).map(|ch| samples[base + ch]).sum::<f32>() * inv_from;
+                out.extend(std::iter::repeat_n(mono, to));
+            } else {
+                out.extend((0..to).map(|ch| samples[base + ch.min(from - 1)]));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn matches_scalar_reference_for_all_layouts() {
+        // 101 frames of 8 channels plus a partial frame, values all distinct.
+        let samples: Vec<f32> = (0..8 * 101 + 3).map(|i| (i as f32 * 0.37).sin()).collect();
+        for from in 1..=8 {
+            for to in 1..=8 {
+                if from != to {
+                    assert_eq!(
+                        adapt_channels(samples.clone(), from, to),
+                        scalar(&samples, from, to),
+                        "{from} -> {to}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Opus (`.opus`, Ogg-wrapped): symphonia finds the stream and its packets
+//! but has no decoder of its own, so packets go to libopus through this
+//! one, registered next to symphonia's codecs. Opus always decodes at
+//! 48 kHz whatever rate the file was made from; playback resamples to the
+//! device like any other file's rate.
+
+use std::sync::Mutex;
+
+use audiopus::coder::Decoder as Libopus;
+use audiopus::packet::Packet as OpusPacket;
+use audiopus::{Channels, MutSignals, SampleRate};
+use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia::core::codecs::{
+    CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult, CODEC_TYPE_OPUS,
+};
+use symphonia::core::errors::{unsupported_error, Error, Result};
+use symphonia::core::formats::Packet;
+use symphonia::core::support_codec;
+
+/// Rate every Opus stream decodes at.
+const RATE: u32 = 48_000;
+
+/// Longest packet Opus allows, 120 ms, in frames.
+const MAX_PACKET_FRAMES: usize = RATE as usize * 120 / 1000;
+
+pub(crate) struct OpusDecoder {
+    params: CodecParameters,
+    // libopus's decoder may move between threads but not be shared.
+    decoder: Mutex<Libopus>,
+    channels: usize,
+    /// Encoder lookahead still to drop from the start of the stream.
+    pre_skip: usize,
+    scratch: Vec<f32>,
+    buf: AudioBuffer<f32>,
+}
+
+fn libopus(channels: usize) -> Result<Libopus> {
+    let channels = if channels == 1 {
+        Channels::Mono
+    } else {
+        Channels::Stereo
+    };
+    Libopus::new(SampleRate::Hz48000, channels)
+        .map_err(|_| Error::DecodeError("opus: decoder setup failed"))
+}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _: &DecoderOptions) -> Result<Self> {
+        let Some(layout) = params.channels else {
+            return unsupported_error("opus: unknown channel layout");
+        };
+        let channels = layout.count();
+        // Surround streams need libopus's multistream decoder.
+        if !(1..=2).contains(&channels) {
+            return unsupported_error("opus: more than two channels");
+        }
+        Ok(OpusDecoder {
+            params: params.clone(),
+            decoder: Mutex::new(libopus(channels)?),
+            channels,
+            pre_skip: params.delay.unwrap_or(0) as usize,
+            scratch: vec![0.0; MAX_PACKET_FRAMES * channels],
+            buf: AudioBuffer::new(MAX_PACKET_FRAMES as u64, SignalSpec::new(RATE, layout)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_OPUS, "opus", "Opus")]
+    }
+
+    fn reset(&mut self) {
+        // After a seek: start over, but the lookahead is long gone.
+        if let Ok(decoder) = libopus(self.channels) {
+            self.decoder = Mutex::new(decoder);
+        }
+        self.pre_skip = 0;
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let input = OpusPacket::try_from(packet.buf())
+            .map_err(|_| Error::DecodeError("opus: empty packet"))?;
+        let output = MutSignals::try_from(&mut self.scratch[..])
+            .map_err(|_| Error::DecodeError("opus: no room to decode"))?;
+        let frames = self
+            .decoder
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .decode_float(Some(input), output, false)
+            .map_err(|_| Error::DecodeError("opus: invalid packet"))?;
+
+        let skip = self.pre_skip.min(frames);
+        self.pre_skip -= skip;
+        self.buf.clear();
+        self.buf.render_reserved(Some(frames - skip));
+        for ch in 0..self.channels {
+            let plane = self.buf.chan_mut(ch);
+            for (i, sample) in plane.iter_mut().enumerate() {
+                *sample = self.scratch[(skip + i) * self.channels + ch];
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symphonia::core::audio::Channels as Layout;
+
+    #[test]
+    fn drops_the_pre_skip_then_decodes_whole_packets() {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_OPUS)
+            .with_sample_rate(RATE)
+            .with_channels(Layout::FRONT_LEFT | Layout::FRONT_RIGHT)
+            .with_delay(312);
+        let mut decoder = OpusDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        // A 20 ms fullband CELT frame with no data, which decodes as silence.
+        let packet = |ts| Packet::new_from_slice(0, ts, 960, &[0xf8]);
+
+        let first = decoder.decode(&packet(0)).unwrap();
+        assert_eq!(
+            (first.frames(), first.spec().channels.count()),
+            (960 - 312, 2)
+        );
+        assert_eq!(decoder.decode(&packet(960)).unwrap().frames(), 960);
+        decoder.reset();
+        assert_eq!(decoder.decode(&packet(1920)).unwrap().frames(), 960);
+    }
+}
@@ -0,0 +1,585 @@
+//! Fast path for plain PCM and float WAVs and AIFFs, which is what most
+//! one-shots are. The samples are read in one go and converted
+//! straight to f32, skipping symphonia's probe and packet pipeline. Anything
+//! unusual (compressed codecs, odd bit depths, RF64, a `data` or `SSND` chunk
+//! past the header prefix) isn't recognised by [`PcmLayout::parse`] and takes
+//! the normal path.
+//!
+//! AIFF stores its samples big-endian; AIFC adds a compression type, of
+//! which the uncompressed ones are read here too: `NONE`/`twos`, `sowt`
+//! (little-endian, as Logic and Ableton write) and `fl32`/`fl64`. symphonia
+//! only reads 16-bit `sowt` and turns down an `SSND` offset, so those files
+//! rely on this path.
+//!
+//! Conversions match symphonia's so a file sounds (and caches) the same
+//! whichever path decoded it.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::decode::{preview_budget_frames, AudioMetadata, DecodeLimits, DecodedAudio, Region};
+use crate::PlaybackError;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Tail of the KSDATAFORMAT_SUBTYPE GUIDs; the first two bytes are the
+/// plain format tag.
+const SUBTYPE_GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SampleFormat {
+    U8,
+    /// AIFF's 8-bit samples, signed unlike WAV's.
+    I8,
+    I16,
+    I24,
+    I32,
+    F32,
+    F64,
+}
+
+impl SampleFormat {
+    fn width(self) -> usize {
+        match self {
+            SampleFormat::U8 | SampleFormat::I8 => 1,
+            SampleFormat::I16 => 2,
+            SampleFormat::I24 => 3,
+            SampleFormat::I32 | SampleFormat::F32 => 4,
+            SampleFormat::F64 => 8,
+        }
+    }
+}
+
+/// Where the samples of a canonical PCM/float WAV or AIFF live and how to
+/// read them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PcmLayout {
+    format: SampleFormat,
+    big_endian: bool,
+    channels: u16,
+    sample_rate: u32,
+    data_offset: u64,
+    data_len: u64,
+}
+
+impl PcmLayout {
+    /// Recognise a RIFF/WAVE or FORM/AIFF header prefix the fast path can
+    /// handle.
+    pub(crate) fn parse(prefix: &[u8]) -> Option<PcmLayout> {
+        if prefix.len() < 12 {
+            return None;
+        }
+        match (&prefix[0..4], &prefix[8..12]) {
+            (b"RIFF", b"WAVE") => Self::parse_wave(prefix),
+            (b"FORM", b"AIFF") => Self::parse_aiff(prefix, false),
+            (b"FORM", b"AIFC") => Self::parse_aiff(prefix, true),
+            _ => None,
+        }
+    }
+
+    fn parse_wave(prefix: &[u8]) -> Option<PcmLayout> {
+        let mut fmt: Option<(SampleFormat, u16, u32)> = None;
+        let mut pos = 12;
+        while pos + 8 <= prefix.len() {
+            let id = &prefix[pos..pos + 4];
+            let size = u32::from_le_bytes(prefix[pos + 4..pos + 8].try_into().unwrap());
+            let body = pos + 8;
+            match id {
+                b"fmt " => fmt = Some(parse_fmt(prefix.get(body..body + size as usize)?)?),
+                b"data" => {
+                    // Size 0 / all-ones come from writers that never patched
+                    // the header; let symphonia work those out.
+                    if size == 0 || size == u32::MAX {
+                        return None;
+                    }
+                    let (format, channels, sample_rate) = fmt?;
+                    return Some(PcmLayout {
+                        format,
+                        big_endian: false,
+                        channels,
+                        sample_rate,
+                        data_offset: body as u64,
+                        data_len: size as u64,
+                    });
+                }
+                _ => {}
+            }
+            pos = body + size as usize + (size as usize & 1);
+        }
+        None
+    }
+
+    /// Chunks as in WAV but with big-endian sizes: `COMM` gives the format,
+    /// `SSND` the samples after an offset of its own. Unlike `data`, `SSND`
+    /// may come first, so the layout is settled once both are seen.
+    fn parse_aiff(prefix: &[u8], compressed: bool) -> Option<PcmLayout> {
+        let mut comm: Option<(SampleFormat, bool, u16, u32, u64)> = None;
+        let mut ssnd: Option<(u64, u64)> = None;
+        let mut pos = 12;
+        while pos + 8 <= prefix.len() && (comm.is_none() || ssnd.is_none()) {
+            let id = &prefix[pos..pos + 4];
+            let size = u32::from_be_bytes(prefix[pos + 4..pos + 8].try_into().unwrap());
+            let body = pos + 8;
+            match id {
+                b"COMM" => {
+                    comm = Some(parse_comm(
+                        prefix.get(body..body + size as usize)?,
+                        compressed,
+                    )?)
+                }
+                b"SSND" => {
+                    let offset =
+                        u32::from_be_bytes(prefix.get(body..body + 4)?.try_into().unwrap());
+                    let skip = 8 + offset as u64;
+                    if (size as u64) <= skip {
+                        return None;
+                    }
+                    ssnd = Some((body as u64 + skip, size as u64 - skip));
+                }
+                _ => {}
+            }
+            pos = body + size as usize + (size as usize & 1);
+        }
+        let (format, big_endian, channels, sample_rate, frames) = comm?;
+        let (data_offset, data_len) = ssnd?;
+        let layout = PcmLayout {
+            format,
+            big_endian,
+            channels,
+            sample_rate,
+            data_offset,
+            data_len,
+        };
+        // The frame count is authoritative; `SSND` may carry padding.
+        Some(PcmLayout {
+            data_len: data_len.min(frames * layout.block_align()),
+            ..layout
+        })
+    }
+
+    fn block_align(&self) -> u64 {
+        (self.format.width() * self.channels as usize) as u64
+    }
+
+    pub(crate) fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub(crate) fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub(crate) fn bits(&self) -> u16 {
+        self.format.width() as u16 * 8
+    }
+
+    /// Whole frames present in a file of `file_len` bytes. A header claiming
+    /// more data than the file holds (an interrupted recording) counts only
+    /// what's there.
+    pub(crate) fn frames(&self, file_len: u64) -> u64 {
+        let available = file_len.saturating_sub(self.data_offset);
+        self.data_len.min(available) / self.block_align()
+    }
+}
+
+/// `(format, channels, sample_rate)` from a `fmt ` chunk body, if it's one
+/// the fast path converts.
+fn parse_fmt(body: &[u8]) -> Option<(SampleFormat, u16, u32)> {
+    let u16_at = |i: usize| Some(u16::from_le_bytes(body.get(i..i + 2)?.try_into().unwrap()));
+    let mut tag = u16_at(0)?;
+    let channels = u16_at(2)?;
+    let sample_rate = u32::from_le_bytes(body.get(4..8)?.try_into().unwrap());
+    let block_align = u16_at(12)?;
+    let bits = u16_at(14)?;
+
+    if tag == WAVE_FORMAT_EXTENSIBLE {
+        // Padded containers (e.g. 20 valid bits in 24) are left to symphonia.
+        if u16_at(18)? != bits || body.get(26..40)? != SUBTYPE_GUID_TAIL {
+            return None;
+        }
+        tag = u16_at(24)?;
+    }
+    let format = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 8) => SampleFormat::U8,
+        (WAVE_FORMAT_PCM, 16) => SampleFormat::I16,
+        (WAVE_FORMAT_PCM, 24) => SampleFormat::I24,
+        (WAVE_FORMAT_PCM, 32) => SampleFormat::I32,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => SampleFormat::F32,
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => SampleFormat::F64,
+        _ => return None,
+    };
+    if channels == 0
+        || sample_rate == 0
+        || block_align as usize != format.width() * channels as usize
+    {
+        return None;
+    }
+    Some((format, channels, sample_rate))
+}
+
+/// `(format, big_endian, channels, sample_rate, frames)` from an AIFF or
+/// AIFC `COMM` chunk body, if it's one the fast path converts.
+fn parse_comm(body: &[u8], compressed: bool) -> Option<(SampleFormat, bool, u16, u32, u64)> {
+    let channels = u16::from_be_bytes(body.get(0..2)?.try_into().unwrap());
+    let frames = u32::from_be_bytes(body.get(2..6)?.try_into().unwrap()) as u64;
+    let bits = u16::from_be_bytes(body.get(6..8)?.try_into().unwrap());
+    let sample_rate = extended_to_rate(body.get(8..18)?.try_into().unwrap())?;
+    let compression = if compressed {
+        body.get(18..22)?
+    } else {
+        b"NONE"
+    };
+
+    let int = |bits| match bits {
+        8 => Some(SampleFormat::I8),
+        16 => Some(SampleFormat::I16),
+        24 => Some(SampleFormat::I24),
+        32 => Some(SampleFormat::I32),
+        _ => None,
+    };
+    let (format, big_endian) = match compression {
+        b"NONE" | b"none" | b"twos" | b"TWOS" => (int(bits)?, true),
+        b"sowt" | b"SOWT" => (int(bits)?, false),
+        b"fl32" | b"FL32" => (SampleFormat::F32, true),
+        b"fl64" | b"FL64" => (SampleFormat::F64, true),
+        _ => return None,
+    };
+    if channels == 0 || frames == 0 {
+        return None;
+    }
+    Some((format, big_endian, channels, sample_rate, frames))
+}
+
+/// The sample rate in an AIFF `COMM` chunk, an 80-bit IEEE extended float;
+/// `None` unless it's a whole number of hertz a `u32` holds.
+fn extended_to_rate(bytes: [u8; 10]) -> Option<u32> {
+    let exponent = i32::from(u16::from_be_bytes([bytes[0], bytes[1]]));
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+    if exponent & 0x8000 != 0 || mantissa == 0 {
+        return None;
+    }
+    let rate = mantissa as f64 * 2f64.powi(exponent - 16_383 - 63);
+    (rate >= 1.0 && rate <= u32::MAX as f64).then(|| rate.round() as u32)
+}
+
+/// Decode `layout`'s samples from `path`, honouring `region` and the preview
+/// bound the same way `decode_from_stream` does, but sample-exact throughout.
+pub(crate) fn decode(
+    path: &Path,
+    layout: &PcmLayout,
+    metadata: AudioMetadata,
+    region: Option<Region>,
+    limits: DecodeLimits,
+) -> Result<DecodedAudio, PlaybackError> {
+    let err = |e| PlaybackError::io(path, e);
+    let file = File::open(punks_core::paths::extended(path)).map_err(err)?;
+    let file_len = file.metadata().map_err(err)?.len();
+
+    let block_align = layout.block_align();
+    let source_frames = layout.frames(file_len);
+
+    let rate = layout.sample_rate as f64;
+    let to_frames = |d: Duration| ((d.as_secs_f64() * rate) as u64).min(source_frames);
+    let start_frame = region.map_or(0, |r| to_frames(r.start));
+    let end_frame = region
+        .and_then(|r| r.end)
+        .map_or(source_frames, to_frames)
+        .max(start_frame);
+    let span_frames = end_frame - start_frame;
+    let budget = preview_budget_frames(span_frames, layout.sample_rate, limits);
+    let frames = budget.map_or(span_frames, |b| b.min(span_frames));
+    if frames == 0 {
+        return Err(PlaybackError::decode("no audio data decoded"));
+    }
+    limits.fits(frames * layout.channels as u64)?;
+
+    let offset = layout.data_offset + start_frame * block_align;
+    let len = (frames * block_align) as usize;
+    // A file cut short since its length was read fails here, as an error
+    // for the file, rather than taking the decode worker down with it.
+    let interleaved = convert(layout, &read_range(file, offset, len).map_err(err)?);
+
+    Ok(DecodedAudio {
+        interleaved,
+        channels: layout.channels,
+        sample_rate: layout.sample_rate,
+        metadata,
+        source_duration: Duration::from_secs_f64(source_frames as f64 / rate),
+        preview_duration: Duration::from_secs_f64(frames as f64 / rate),
+        truncated: budget.is_some(),
+    })
+}
+
+fn read_range(mut file: File, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0u8; len];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// `layout`'s samples to f32, scaled like symphonia's `FromSample`.
+fn convert(layout: &PcmLayout, bytes: &[u8]) -> Vec<f32> {
+    let samples = bytes.chunks_exact(layout.format.width());
+    // Each sample's bytes in little-endian order.
+    let le = |b: &[u8]| -> [u8; 8] {
+        let mut word = [0; 8];
+        word[..b.len()].copy_from_slice(b);
+        if layout.big_endian {
+            word[..b.len()].reverse();
+        }
+        word
+    };
+    match layout.format {
+        SampleFormat::U8 => samples.map(|b| (b[0] as f32 - 128.0) / 128.0).collect(),
+        SampleFormat::I8 => samples.map(|b| b[0] as i8 as f32 / 128.0).collect(),
+        SampleFormat::I16 => samples
+            .map(|b| {
+                let w = le(b);
+                i16::from_le_bytes([w[0], w[1]]) as f32 / 32_768.0
+            })
+            .collect(),
+        SampleFormat::I24 => samples
+            // Shift into the top of an i32 to sign-extend.
+            .map(|b| {
+                let w = le(b);
+                (i32::from_le_bytes([0, w[0], w[1], w[2]]) >> 8) as f32 / 8_388_608.0
+            })
+            .collect(),
+        SampleFormat::I32 => samples
+            .map(|b| {
+                let w = le(b);
+                (i32::from_le_bytes(w[..4].try_into().unwrap()) as f64 / 2_147_483_648.0) as f32
+            })
+            .collect(),
+        SampleFormat::F32 => samples
+            .map(|b| f32::from_le_bytes(le(b)[..4].try_into().unwrap()))
+            .collect(),
+        SampleFormat::F64 => samples.map(|b| f64::from_le_bytes(le(b)) as f32).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::probe::Hint;
+
+    /// A WAV with a `fmt ` chunk of `format_tag`/`bits` and `data` as given.
+    fn wav(format_tag: u16, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&format_tag.to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&8_000u32.to_le_bytes());
+        fmt.extend_from_slice(&(8_000 * block_align as u32).to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+
+        let mut v = Vec::new();
+        v.extend_from_slice(b"RIFF");
+        v.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+        v.extend_from_slice(b"WAVE");
+        v.extend_from_slice(b"fmt ");
+        v.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        v.extend_from_slice(&fmt);
+        v.extend_from_slice(b"data");
+        v.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        v.extend_from_slice(data);
+        v
+    }
+
+    /// An AIFF (or AIFC with `compression`) at 8 kHz, with `SSND` holding
+    /// `data` after `offset` bytes of padding.
+    fn aiff(
+        compression: Option<&[u8; 4]>,
+        channels: u16,
+        bits: u16,
+        offset: u32,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let frames = data.len() as u32 / (channels as u32 * bits as u32 / 8);
+        let mut comm = Vec::new();
+        comm.extend_from_slice(&channels.to_be_bytes());
+        comm.extend_from_slice(&frames.to_be_bytes());
+        comm.extend_from_slice(&bits.to_be_bytes());
+        comm.extend_from_slice(&[0x40, 0x0B, 0xFA, 0, 0, 0, 0, 0, 0, 0]);
+        if let Some(compression) = compression {
+            comm.extend_from_slice(compression);
+            comm.extend_from_slice(&[0, 0]); // empty pascal-string name
+        }
+        let ssnd_len = 8 + offset as usize + data.len();
+
+        let mut v = Vec::new();
+        v.extend_from_slice(b"FORM");
+        v.extend_from_slice(&((4 + 8 + comm.len() + 8 + ssnd_len) as u32).to_be_bytes());
+        v.extend_from_slice(if compression.is_some() {
+            b"AIFC"
+        } else {
+            b"AIFF"
+        });
+        v.extend_from_slice(b"COMM");
+        v.extend_from_slice(&(comm.len() as u32).to_be_bytes());
+        v.extend_from_slice(&comm);
+        v.extend_from_slice(b"SSND");
+        v.extend_from_slice(&(ssnd_len as u32).to_be_bytes());
+        v.extend_from_slice(&offset.to_be_bytes());
+        v.extend_from_slice(&0u32.to_be_bytes());
+        v.extend_from_slice(&vec![0; offset as usize]);
+        v.extend_from_slice(data);
+        v
+    }
+
+    fn via_symphonia(bytes: Vec<u8>, region: Option<Region>) -> Vec<f32> {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("wav");
+        crate::decode::decode_from_stream(
+            mss,
+            &hint,
+            AudioMetadata::default(),
+            None,
+            region,
+            DecodeLimits::FULL,
+        )
+        .unwrap()
+        .interleaved
+    }
+
+    fn via_fast_path(bytes: &[u8], region: Option<Region>) -> Vec<f32> {
+        let layout = PcmLayout::parse(bytes).expect("fast-path layout");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.wav");
+        std::fs::write(&path, bytes).unwrap();
+        decode(
+            &path,
+            &layout,
+            AudioMetadata::default(),
+            region,
+            DecodeLimits::FULL,
+        )
+        .unwrap()
+        .interleaved
+    }
+
+    #[test]
+    fn matches_symphonia_for_int_depths() {
+        let region = Some(Region {
+            start: Duration::from_millis(10),
+            end: Some(Duration::from_millis(30)),
+        });
+        for bits in [8u16, 16, 24, 32] {
+            // Stereo, 0.1 s of bytes cycling through every value.
+            let width = bits as usize / 8;
+            let data: Vec<u8> = (0..800 * 2 * width).map(|i| (i * 37) as u8).collect();
+            let bytes = wav(WAVE_FORMAT_PCM, 2, bits, &data);
+            for r in [None, region] {
+                assert_eq!(
+                    via_fast_path(&bytes, r),
+                    via_symphonia(bytes.clone(), r),
+                    "{bits}-bit, region {r:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn aiff_matches_symphonia_for_int_depths() {
+        for bits in [8u16, 16, 24, 32] {
+            let width = bits as usize / 8;
+            let data: Vec<u8> = (0..800 * 2 * width).map(|i| (i * 37) as u8).collect();
+            let bytes = aiff(None, 2, bits, 0, &data);
+            assert_eq!(
+                via_fast_path(&bytes, None),
+                via_symphonia(bytes.clone(), None),
+                "{bits}-bit"
+            );
+        }
+    }
+
+    #[test]
+    fn aifc_sowt_and_float() {
+        // 24-bit `sowt`, which symphonia turns down, reads as the same
+        // samples stored big-endian.
+        let data: Vec<u8> = (0..600).map(|i| (i * 37) as u8).collect();
+        let swapped: Vec<u8> = data.chunks(3).flat_map(|s| [s[2], s[1], s[0]]).collect();
+        assert_eq!(
+            via_fast_path(&aiff(Some(b"sowt"), 1, 24, 4, &swapped), None),
+            via_fast_path(&aiff(None, 1, 24, 0, &data), None)
+        );
+
+        let samples = [0.5f32, -0.25, 1.0, 0.0];
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        assert_eq!(
+            via_fast_path(&aiff(Some(b"fl32"), 1, 32, 0, &data), None),
+            samples
+        );
+
+        // Compressed AIFC stays with symphonia.
+        assert_eq!(
+            PcmLayout::parse(&aiff(Some(b"ulaw"), 1, 8, 0, &[0; 4])),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_extended_sample_rates() {
+        assert_eq!(
+            extended_to_rate([0x40, 0x0B, 0xFA, 0, 0, 0, 0, 0, 0, 0]),
+            Some(8_000)
+        );
+        assert_eq!(
+            extended_to_rate([0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]),
+            Some(44_100)
+        );
+        assert_eq!(extended_to_rate([0; 10]), None);
+    }
+
+    #[test]
+    fn float_and_truncated_data() {
+        let samples = [0.5f32, -0.25, 1.0, 0.0];
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut bytes = wav(WAVE_FORMAT_IEEE_FLOAT, 1, 32, &data);
+        assert_eq!(via_fast_path(&bytes, None), samples);
+
+        // Header promises four frames; the file holds two and a half.
+        bytes.truncate(bytes.len() - 6);
+        assert_eq!(via_fast_path(&bytes, None), samples[..2]);
+    }
+
+    #[test]
+    fn probe_reads_length_from_header() {
+        // 0.1 s of 16-bit stereo, with the header promising twice that.
+        let mut bytes = wav(WAVE_FORMAT_PCM, 2, 16, &[0; 3_200]);
+        // `data` size field, after RIFF header, `fmt ` chunk and `data` id.
+        bytes[40..44].copy_from_slice(&6_400u32.to_le_bytes());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hat.wav");
+        std::fs::write(&path, &bytes).unwrap();
+        let info = crate::decode::probe(&path).unwrap();
+        assert_eq!((info.sample_rate, info.channels), (8_000, 2));
+        assert_eq!(info.bit_depth, Some(16));
+        assert_eq!(info.duration, Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn leaves_unusual_files_to_symphonia() {
+        // 12-bit PCM and compressed tags aren't handled.
+        assert_eq!(
+            PcmLayout::parse(&wav(WAVE_FORMAT_PCM, 1, 12, &[0; 6])),
+            None
+        );
+        assert_eq!(PcmLayout::parse(&wav(0x0002, 1, 4, &[0; 4])), None);
+        // Unpatched data size.
+        assert_eq!(PcmLayout::parse(&wav(WAVE_FORMAT_PCM, 1, 16, &[])), None);
+        assert!(PcmLayout::parse(&wav(WAVE_FORMAT_PCM, 1, 16, &[0; 4])).is_some());
+    }
+}
@@ -1,3 +1,7 @@
+use std::path::Path;
+
+use crate::PlaybackError;
+
 pub const DEFAULT_NUM_BUCKETS: usize = 512;
 
 #[derive(Debug, Clone)]
@@ -17,13 +21,15 @@ pub fn compute_peaks(samples: &[f32], channels: usize, num_buckets: usize) -> Wa
         };
     }
 
-    let frames_per_bucket = (num_frames as f64 / num_buckets as f64).max(1.0);
+    // Fractional, so a clip shorter than `num_buckets` frames still spans
+    // the whole overview, each frame repeated across the buckets it covers.
+    let frames_per_bucket = num_frames as f64 / num_buckets as f64;
     let inv_channels = 1.0 / channels as f32;
     let mut peaks = Vec::with_capacity(num_buckets);
 
     for bucket in 0..num_buckets {
-        let start = (bucket as f64 * frames_per_bucket) as usize;
-        let end = (((bucket + 1) as f64 * frames_per_bucket) as usize).min(num_frames);
+        let start = ((bucket as f64 * frames_per_bucket) as usize).min(num_frames - 1);
+        let end = (((bucket + 1) as f64 * frames_per_bucket) as usize).clamp(start + 1, num_frames);
 
         let mut min = f32::MAX;
         let mut max = f32::MIN;
@@ -50,10 +56,234 @@ pub fn compute_peaks(samples: &[f32], channels: usize, num_buckets: usize) -> Wa
     WaveformPeaks { peaks, num_buckets }
 }
 
+/// Decode `path` (preview-bounded, like playback) and reduce it to an
+/// overview of `num_buckets` peaks, e.g. for a list thumbnail. With
+/// `reapeaks`, a current `.reapeaks` file is used instead of decoding, and
+/// one is written when there was none.
+#[tracing::instrument(level = "debug", skip(num_buckets), fields(path = %path.display()))]
+pub fn file_peaks(
+    path: &Path,
+    num_buckets: usize,
+    reapeaks: bool,
+) -> Result<WaveformPeaks, PlaybackError> {
+    if reapeaks {
+        if let Some(peaks) = crate::reapeaks::whole_file(path, num_buckets) {
+            return Ok(peaks);
+        }
+    }
+    let audio = crate::decode::decode_file(path)?;
+    if reapeaks {
+        return Ok(crate::reapeaks::peaks_for(path, &audio, None, num_buckets));
+    }
+    Ok(compute_peaks(
+        &audio.interleaved,
+        audio.channels as usize,
+        num_buckets,
+    ))
+}
+
+/// Frames per bucket at the finest level of [`PeakLevels`].
+const FINEST_FRAMES: usize = 32;
+
+/// A clip's min/max peaks at several resolutions, finest first and each
+/// level half as detailed as the one before, so a zoomed-in waveform draws
+/// real detail and a zoomed-out one doesn't walk every fine bucket.
+#[derive(Debug, Clone, Default)]
+pub struct PeakLevels {
+    levels: Vec<Vec<(f32, f32)>>,
+    total_frames: usize,
+}
+
+impl PeakLevels {
+    /// Peaks of interleaved `samples`, channels averaged like
+    /// [`compute_peaks`].
+    pub fn new(samples: &[f32], channels: usize) -> Self {
+        let channels = channels.max(1);
+        let total_frames = samples.len() / channels;
+        let inv_channels = 1.0 / channels as f32;
+        let finest: Vec<(f32, f32)> = samples[..total_frames * channels]
+            .chunks(FINEST_FRAMES * channels)
+            .map(|chunk| {
+                chunk
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() * inv_channels)
+                    .fold((f32::MAX, f32::MIN), |(lo, hi), s| (lo.min(s), hi.max(s)))
+            })
+            .map(|(lo, hi)| (lo.clamp(-1.0, 1.0), hi.clamp(-1.0, 1.0)))
+            .collect();
+
+        let mut levels = vec![finest];
+        while let Some(last) = levels.last().filter(|l| l.len() > 1) {
+            let coarser = last
+                .chunks(2)
+                .map(|pair| merge(pair.iter().copied()))
+                .collect();
+            levels.push(coarser);
+        }
+        PeakLevels {
+            levels,
+            total_frames,
+        }
+    }
+
+    pub fn total_frames(&self) -> usize {
+        self.total_frames
+    }
+
+    /// `buckets` peaks across the `start..end` part of the clip (fractions
+    /// of its length), from the coarsest level that still has a bucket for
+    /// each one. Zoomed in past the finest level, neighbours repeat it.
+    pub fn view(&self, start: f64, end: f64, buckets: usize) -> Vec<(f32, f32)> {
+        let start = start.clamp(0.0, 1.0);
+        let end = end.clamp(start, 1.0);
+        if self.total_frames == 0 || buckets == 0 {
+            return vec![(0.0, 0.0); buckets];
+        }
+        let first = start * self.total_frames as f64;
+        let frames_per_bucket = (end - start) * self.total_frames as f64 / buckets as f64;
+        let level = (frames_per_bucket / FINEST_FRAMES as f64)
+            .log2()
+            .floor()
+            .clamp(0.0, (self.levels.len() - 1) as f64) as usize;
+        let peaks = &self.levels[level];
+        let level_frames = (FINEST_FRAMES << level) as f64;
+
+        (0..buckets)
+            .map(|i| {
+                let from = first + i as f64 * frames_per_bucket;
+                let to = from + frames_per_bucket;
+                let a = ((from / level_frames) as usize).min(peaks.len() - 1);
+                let b = ((to / level_frames).ceil() as usize).clamp(a + 1, peaks.len());
+                merge(peaks[a..b].iter().copied())
+            })
+            .collect()
+    }
+}
+
+fn merge(peaks: impl Iterator<Item = (f32, f32)>) -> (f32, f32) {
+    let (lo, hi) = peaks.fold((f32::MAX, f32::MIN), |(lo, hi), (l, h)| {
+        (lo.min(l), hi.max(h))
+    });
+    if lo > hi {
+        (0.0, 0.0)
+    } else {
+        (lo, hi)
+    }
+}
+
+/// The part of a clip a waveform shows, as fractions of its length, with
+/// the zoom and pan maths. Positions across the widget are 0..1 too.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveformView {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Default for WaveformView {
+    fn default() -> Self {
+        WaveformView {
+            start: 0.0,
+            end: 1.0,
+        }
+    }
+}
+
+impl WaveformView {
+    /// Narrowest view, as a fraction of the clip.
+    pub const MIN_SPAN: f64 = 1e-4;
+
+    pub fn span(&self) -> f64 {
+        self.end - self.start
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        self.span() < 1.0
+    }
+
+    /// The clip fraction under `x` across the widget.
+    pub fn fraction_at(&self, x: f64) -> f64 {
+        self.start + x.clamp(0.0, 1.0) * self.span()
+    }
+
+    /// Where clip fraction `fraction` is across the widget; outside 0..1
+    /// when it's out of view.
+    pub fn x_of(&self, fraction: f64) -> f64 {
+        (fraction - self.start) / self.span()
+    }
+
+    /// Zoom by `factor` (below 1 zooms in), keeping the point under `x`
+    /// where it is.
+    pub fn zoom(&mut self, factor: f64, x: f64) {
+        let anchor = self.fraction_at(x);
+        let span = (self.span() * factor).clamp(Self::MIN_SPAN, 1.0);
+        self.start = anchor - x.clamp(0.0, 1.0) * span;
+        self.end = self.start + span;
+        self.clamp();
+    }
+
+    /// Move the view by `dx` widget widths; positive shows later audio.
+    pub fn pan(&mut self, dx: f64) {
+        let shift = dx * self.span();
+        self.start += shift;
+        self.end += shift;
+        self.clamp();
+    }
+
+    fn clamp(&mut self) {
+        let span = self.span();
+        self.start = self.start.clamp(0.0, 1.0 - span);
+        self.end = self.start + span;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn levels_match_the_view_resolution() {
+        // A single spike in 64k frames of silence.
+        let mut samples = vec![0.0f32; 65_536];
+        samples[40_000] = 0.8;
+        let levels = PeakLevels::new(&samples, 1);
+        assert_eq!(levels.total_frames(), 65_536);
+        assert_eq!(levels.levels[0].len(), 2048);
+        assert_eq!(levels.levels.last().unwrap().len(), 1);
+
+        let whole = levels.view(0.0, 1.0, 64);
+        assert_eq!(whole.len(), 64);
+        assert_eq!(whole[39], (0.0, 0.8)); // 40_000 / 1024 frames per bucket
+        assert_eq!(whole.iter().filter(|p| p.1 > 0.0).count(), 1);
+
+        // Zoomed to 2048 frames from 39_936, one finest bucket per pixel:
+        // the spike is in the third.
+        let start = 39_936.0 / 65_536.0;
+        let zoomed = levels.view(start, start + 1.0 / 32.0, 64);
+        let hot: Vec<usize> = (0..64).filter(|&i| zoomed[i].1 > 0.0).collect();
+        assert_eq!(hot, [2]);
+    }
+
+    #[test]
+    fn view_zooms_around_the_pointer_and_pans_within_the_clip() {
+        let mut view = WaveformView::default();
+        view.zoom(0.5, 0.5);
+        assert_eq!((view.start, view.end), (0.25, 0.75));
+        assert!(view.is_zoomed());
+        // The point under the pointer stays put.
+        let under = view.fraction_at(0.2);
+        view.zoom(0.5, 0.2);
+        assert!((view.fraction_at(0.2) - under).abs() < 1e-12);
+
+        view.pan(10.0);
+        assert_eq!(view.end, 1.0);
+        view.pan(-10.0);
+        assert_eq!(view.start, 0.0);
+        assert!((view.x_of(view.fraction_at(0.3)) - 0.3).abs() < 1e-12);
+
+        view.zoom(100.0, 0.5);
+        assert_eq!(view, WaveformView::default());
+    }
+
     #[test]
     fn empty_samples_returns_zeroed_peaks() {
         let peaks = compute_peaks(&[], 1, 8);
@@ -85,6 +315,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn short_clips_fill_every_bucket() {
+        let peaks = compute_peaks(&[0.5, -0.5], 1, 4);
+        assert_eq!(
+            peaks.peaks,
+            [(0.5, 0.5), (0.5, 0.5), (-0.5, -0.5), (-0.5, -0.5)]
+        );
+    }
+
     #[test]
     fn hot_samples_are_clamped() {
         let samples = vec![2.0f32, -3.0, 0.5, 0.5];
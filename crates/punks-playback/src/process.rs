@@ -0,0 +1,385 @@
+//! Offline batch processing: trim leading/trailing silence and peak- or
+//! loudness-normalize files, writing the result as a WAV at the source rate
+//! and channel count. WAVs rewritten in place keep their sample format and
+//! bit depth (dithered if integer); everything else is written as 32-bit
+//! float.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::analysis;
+use crate::decode::{self, DecodeLimits, Region};
+use crate::{wav, PlaybackError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalize {
+    Off,
+    /// Scale so the sample peak lands on `target_dbfs`.
+    Peak {
+        target_dbfs: f32,
+    },
+    /// Scale so integrated loudness lands on `target_lufs`. Float output means
+    /// peaks pushed above 0 dBFS are kept; integer output clips them.
+    Loudness {
+        target_lufs: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputMode {
+    /// Non-destructive: write `<stem>.wav` into this folder, numbered
+    /// `<stem> (2).wav` and so on rather than replacing a file already there.
+    Folder(PathBuf),
+    /// Destructive: replace WAV sources in place, in their own sample format
+    /// and bit depth. Other formats can't be rewritten as themselves, so they
+    /// get a `<stem>.wav` sibling instead, numbered like [`Folder`]'s if
+    /// that's taken.
+    ///
+    /// [`Folder`]: OutputMode::Folder
+    InPlace,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessOptions {
+    pub trim_silence: bool,
+    /// Anything quieter than this (dBFS) at the head or tail counts as silence.
+    pub silence_threshold_db: f32,
+    pub normalize: Normalize,
+    pub output: OutputMode,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        ProcessOptions {
+            trim_silence: true,
+            silence_threshold_db: -60.0,
+            normalize: Normalize::Peak { target_dbfs: -1.0 },
+            output: OutputMode::InPlace,
+        }
+    }
+}
+
+/// What processing did to one file.
+#[derive(Debug, Clone)]
+pub struct ProcessedFile {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    /// Silence removed from head + tail.
+    pub trimmed: Duration,
+    /// Gain applied by normalization (0.0 when off or the file was silent).
+    pub gain_db: f32,
+    /// Samples past full scale, clipped to fit an integer WAV rewritten in
+    /// place.
+    pub clipped: usize,
+}
+
+/// Levels of a whole file, for reporting rather than processing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Levels {
+    pub duration: Duration,
+    /// Sample peak, dBFS.
+    pub peak_db: f32,
+    /// Inter-sample peak, dBTP.
+    pub true_peak_db: f32,
+    /// Integrated loudness; `None` when the file is silent or too short.
+    pub loudness_lufs: Option<f32>,
+    /// Samples flat-topped at full scale.
+    pub clipped: usize,
+}
+
+/// Decode all of `source` and measure it.
+#[tracing::instrument(level = "debug", skip_all, fields(path = %source.display()))]
+pub fn measure_file(source: &Path) -> Result<Levels, PlaybackError> {
+    let decoded = decode::decode_file_full(source)?;
+    let channels = decoded.channels.max(1) as usize;
+    let samples = &decoded.interleaved;
+    let frames = samples.len() / channels;
+    Ok(Levels {
+        duration: Duration::from_secs_f64(frames as f64 / decoded.sample_rate as f64),
+        peak_db: analysis::gain_to_db(analysis::sample_peak(samples)),
+        true_peak_db: analysis::gain_to_db(analysis::true_peak(samples, channels)),
+        loudness_lufs: analysis::integrated_loudness(samples, channels, decoded.sample_rate),
+        clipped: analysis::clipped_samples(samples, channels),
+    })
+}
+
+/// Where `source` is written under `output`.
+fn output_path(source: &Path, output: &OutputMode) -> PathBuf {
+    let stem = source.file_stem().unwrap_or(source.as_os_str());
+    let mut name = stem.to_os_string();
+    name.push(".wav");
+    match output {
+        OutputMode::Folder(dir) => dir.join(name),
+        OutputMode::InPlace => source.with_file_name(name),
+    }
+}
+
+/// Trim and normalize one file according to `opts`.
+#[tracing::instrument(level = "debug", skip_all, fields(path = %source.display()))]
+pub fn process_file(source: &Path, opts: &ProcessOptions) -> Result<ProcessedFile, PlaybackError> {
+    let output = match output_path(source, &opts.output) {
+        same if same == source => same,
+        // Several sources called `kick` can meet in one folder, and a
+        // `kick.mp3` can sit beside a `kick.wav`: never write over either.
+        other => punks_core::unused_path(&other),
+    };
+    // Overwriting the source: keep its format rather than silently turning
+    // a 16-bit original into a float one twice the size.
+    let kept_format = if output == source {
+        let reader = hound::WavReader::open(source).map_err(|e| PlaybackError::Encode {
+            path: source.to_path_buf(),
+            message: format!("can't tell which format to keep it in ({e}); process into a folder"),
+        })?;
+        Some(reader.spec())
+    } else {
+        None
+    };
+    let decoded = decode::decode_file_full(source)?;
+    let channels = decoded.channels.max(1) as usize;
+    let total_frames = decoded.interleaved.len() / channels;
+
+    let range = if opts.trim_silence {
+        // An all-silent file trims to nothing; keep it whole rather than write
+        // an empty WAV.
+        analysis::audible_frames(&decoded.interleaved, channels, opts.silence_threshold_db)
+            .unwrap_or(0..total_frames)
+    } else {
+        0..total_frames
+    };
+    let mut samples = decoded.interleaved[range.start * channels..range.end * channels].to_vec();
+
+    let gain = match opts.normalize {
+        Normalize::Off => None,
+        Normalize::Peak { target_dbfs } => {
+            let peak = analysis::sample_peak(&samples);
+            (peak > 0.0).then(|| analysis::db_to_gain(target_dbfs) / peak)
+        }
+        Normalize::Loudness { target_lufs } => {
+            analysis::integrated_loudness(&samples, channels, decoded.sample_rate)
+                .map(|lufs| analysis::db_to_gain(target_lufs - lufs))
+        }
+    };
+    if let Some(g) = gain {
+        samples.iter_mut().for_each(|s| *s *= g);
+    }
+
+    let float = wav::float_spec(decoded.channels, decoded.sample_rate);
+    let spec = kept_format.map_or(float, |kept| hound::WavSpec {
+        bits_per_sample: kept.bits_per_sample,
+        sample_format: kept.sample_format,
+        ..float
+    });
+    let clipped = wav::write_wav(&output, &samples, spec, true)?;
+
+    let trimmed_frames = total_frames - range.len();
+    Ok(ProcessedFile {
+        source: source.to_path_buf(),
+        output,
+        trimmed: Duration::from_secs_f64(trimmed_frames as f64 / decoded.sample_rate as f64),
+        gain_db: gain.map(analysis::gain_to_db).unwrap_or(0.0),
+        clipped,
+    })
+}
+
+/// Process `sources` in order, handing each result to `on_file` as soon as it
+/// is ready so callers can report progress. One failing file doesn't stop the
+/// batch.
+pub fn process_batch(
+    sources: &[PathBuf],
+    opts: &ProcessOptions,
+    mut on_file: impl FnMut(&Path, Result<ProcessedFile, PlaybackError>),
+) {
+    for source in sources {
+        on_file(source, process_file(source, opts));
+    }
+}
+
+/// Write `source`, or just `region` of it, to `output` as it is, at its own
+/// rate and channel count. Tracker modules and the samples inside them decode
+/// like any file, so this gives clips that aren't files of their own on disk
+/// one that other programs can open.
+pub fn export_clip(
+    source: &Path,
+    region: Option<Region>,
+    output: &Path,
+) -> Result<(), PlaybackError> {
+    let decoded = decode::decode(source, region, DecodeLimits::FULL)?;
+    let spec = wav::float_spec(decoded.channels, decoded.sample_rate);
+    wav::write_wav(output, &decoded.interleaved, spec, false).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pcm16(path: &Path, rate: u32, samples: &[i16]) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut w = hound::WavWriter::create(path, spec).unwrap();
+        for &s in samples {
+            w.write_sample(s).unwrap();
+        }
+        w.finalize().unwrap();
+    }
+
+    #[test]
+    fn output_path_per_mode() {
+        let src = Path::new("/lib/kick.mp3");
+        assert_eq!(
+            output_path(src, &OutputMode::Folder("/out".into())),
+            Path::new("/out/kick.wav")
+        );
+        assert_eq!(
+            output_path(src, &OutputMode::InPlace),
+            Path::new("/lib/kick.wav")
+        );
+    }
+
+    #[test]
+    fn trims_and_peak_normalizes_into_folder() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let src = dir.join("hit.wav");
+        // 100 silent frames, 50 frames at half scale, 100 silent frames.
+        let mut pcm = vec![0i16; 100];
+        pcm.extend(std::iter::repeat_n(i16::MAX / 2, 50));
+        pcm.extend(std::iter::repeat_n(0, 100));
+        write_pcm16(&src, 8_000, &pcm);
+
+        let opts = ProcessOptions {
+            normalize: Normalize::Peak { target_dbfs: 0.0 },
+            output: OutputMode::Folder(dir.join("out")),
+            ..Default::default()
+        };
+        let done = process_file(&src, &opts).expect("process");
+
+        let out: Vec<f32> = hound::WavReader::open(&done.output)
+            .unwrap()
+            .into_samples::<f32>()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(out.len(), 50);
+        assert!(out.iter().all(|s| (s - 1.0).abs() < 1e-3));
+        assert!(
+            (done.gain_db - 6.02).abs() < 0.05,
+            "gain = {}",
+            done.gain_db
+        );
+        assert_eq!(done.trimmed, Duration::from_millis(25));
+    }
+
+    #[test]
+    fn outputs_of_the_same_name_dont_replace_each_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = ["a/kick.wav", "b/kick.wav", "b/kick.mp3"].map(|p| dir.path().join(p));
+        for (i, src) in sources.iter().take(2).enumerate() {
+            std::fs::create_dir_all(src.parent().unwrap()).unwrap();
+            write_pcm16(src, 8_000, &[1_000 * (i as i16 + 1); 10]);
+        }
+        let opts = ProcessOptions {
+            trim_silence: false,
+            normalize: Normalize::Off,
+            output: OutputMode::Folder(dir.path().join("out")),
+            ..Default::default()
+        };
+        let mut outputs = Vec::new();
+        process_batch(&sources[..2], &opts, |_, done| {
+            outputs.push(done.expect("process").output)
+        });
+        assert_eq!(
+            outputs,
+            [
+                dir.path().join("out/kick.wav"),
+                dir.path().join("out/kick (2).wav")
+            ]
+        );
+
+        // An MP3 beside a WAV of the same name doesn't replace it in place.
+        std::fs::copy(&sources[1], &sources[2]).unwrap();
+        let in_place = ProcessOptions {
+            output: OutputMode::InPlace,
+            ..opts
+        };
+        let done = process_file(&sources[2], &in_place).expect("process");
+        assert_eq!(done.output, dir.path().join("b/kick (2).wav"));
+    }
+
+    #[test]
+    fn in_place_keeps_the_sources_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("hit.wav");
+        let mut pcm = vec![0i16; 100];
+        pcm.extend(std::iter::repeat_n(i16::MAX / 4, 50));
+        write_pcm16(&src, 8_000, &pcm);
+
+        let opts = ProcessOptions {
+            normalize: Normalize::Peak { target_dbfs: -6.0 },
+            output: OutputMode::InPlace,
+            ..Default::default()
+        };
+        let done = process_file(&src, &opts).expect("process");
+        assert_eq!((done.output.as_path(), done.clipped), (src.as_path(), 0));
+
+        let mut reader = hound::WavReader::open(&src).unwrap();
+        assert_eq!(reader.spec().bits_per_sample, 16);
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Int);
+        let out: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+        assert_eq!(out.len(), 50);
+        assert!(out.iter().all(|&s| (s - 16_423).abs() <= 1), "{out:?}");
+    }
+
+    #[test]
+    fn measures_a_whole_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let src = dir.join("square.wav");
+        let pcm: Vec<i16> = (0..8_000)
+            .map(|i| {
+                if i % 40 < 20 {
+                    i16::MAX / 2
+                } else {
+                    -i16::MAX / 2
+                }
+            })
+            .collect();
+        write_pcm16(&src, 8_000, &pcm);
+
+        let levels = measure_file(&src).expect("measure");
+
+        assert_eq!(levels.duration, Duration::from_secs(1));
+        assert!((levels.peak_db + 6.02).abs() < 0.05, "{levels:?}");
+        assert!(levels.true_peak_db >= levels.peak_db);
+        assert!(levels.loudness_lufs.is_some());
+        assert_eq!(levels.clipped, 0);
+    }
+
+    #[test]
+    fn exports_just_the_region() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let src = dir.join("mix.wav");
+        // A second of silence, then a second at half scale.
+        let mut pcm = vec![0i16; 8_000];
+        pcm.extend(std::iter::repeat_n(i16::MAX / 2, 8_000));
+        write_pcm16(&src, 8_000, &pcm);
+
+        let region = Region {
+            start: Duration::from_secs(1),
+            end: None,
+        };
+        let out_path = dir.join("out/track.wav");
+        export_clip(&src, Some(region), &out_path).expect("export");
+
+        let reader = hound::WavReader::open(&out_path).unwrap();
+        assert_eq!(reader.spec().sample_rate, 8_000);
+        let out: Vec<f32> = reader.into_samples::<f32>().map(Result::unwrap).collect();
+
+        assert_eq!(out.len(), 8_000);
+        assert!(out.iter().all(|s| (s - 0.5).abs() < 1e-3));
+    }
+}
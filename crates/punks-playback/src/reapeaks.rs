@@ -0,0 +1,309 @@
+//! REAPER's `.reapeaks` peak files, kept next to the source as
+//! `kick.wav.reapeaks`. Reading them lets folders REAPER has already scanned
+//! show overviews without decoding anything; writing them hands our peak work
+//! back to the DAW.
+//!
+//! Layout (little-endian): `RPKM`, channel count (u8), mipmap count (u8),
+//! sample rate, source mtime (Unix seconds) and source size (low 32 bits) as
+//! i32; then per mipmap its division (frames per peak) and peak count as
+//! i32; then each mipmap's peaks, every peak being one i16 maximum per
+//! channel followed by one i16 minimum per channel. `RPKN` files share that
+//! layout and are read too; we always write `RPKM`.
+
+use std::ffi::OsString;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::decode::{DecodedAudio, Region};
+use crate::peaks::{self, WaveformPeaks};
+
+const MAGIC_WRITE: &[u8; 4] = b"RPKM";
+const MAGIC_READ: [&[u8; 4]; 2] = [b"RPKM", b"RPKN"];
+const HEADER_LEN: usize = 4 + 1 + 1 + 4 * 3;
+
+/// Peaks per second of each mipmap we write, finest first.
+const MIPMAP_RATES: [u32; 3] = [400, 40, 4];
+
+/// Where REAPER keeps `source`'s peaks by default.
+pub fn peaks_path(source: &Path) -> PathBuf {
+    let mut name = OsString::from(source.as_os_str());
+    name.push(".reapeaks");
+    PathBuf::from(name)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Mipmap {
+    /// Source frames per peak.
+    division: u32,
+    /// Per peak: `channels` maxima, then `channels` minima.
+    peaks: Vec<i16>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakFile {
+    channels: u8,
+    sample_rate: u32,
+    /// Finest first.
+    mipmaps: Vec<Mipmap>,
+}
+
+impl PeakFile {
+    /// Peaks for interleaved `samples` at the standard mipmap rates.
+    pub fn from_samples(samples: &[f32], channels: u16, sample_rate: u32) -> PeakFile {
+        let channels = channels.clamp(1, u8::MAX as u16) as usize;
+        let mipmaps = MIPMAP_RATES
+            .iter()
+            .map(|&rate| {
+                let division = (sample_rate / rate).max(1);
+                let mut peaks = Vec::new();
+                for block in samples.chunks(division as usize * channels) {
+                    let mut max = vec![f32::MIN; channels];
+                    let mut min = vec![f32::MAX; channels];
+                    for frame in block.chunks_exact(channels) {
+                        for (ch, &s) in frame.iter().enumerate() {
+                            max[ch] = max[ch].max(s);
+                            min[ch] = min[ch].min(s);
+                        }
+                    }
+                    peaks.extend(max.iter().chain(&min).map(|&s| to_i16(s)));
+                }
+                Mipmap { division, peaks }
+            })
+            .collect();
+        PeakFile {
+            channels: channels as u8,
+            sample_rate,
+            mipmaps,
+        }
+    }
+
+    /// `source`'s peak file, if there is one and it was made from the file as
+    /// it is now (same modification time and size).
+    pub fn read(source: &Path) -> Option<PeakFile> {
+        let stamp = stamp(source)?;
+        let bytes = std::fs::read(peaks_path(source)).ok()?;
+        let (file, file_stamp) = parse(&bytes)?;
+        (file_stamp == stamp).then_some(file)
+    }
+
+    /// Write the peak file for `source`, stamped with its current mtime and
+    /// size.
+    pub fn write(&self, source: &Path) -> std::io::Result<()> {
+        let stamp = stamp(source).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "source has no mtime")
+        })?;
+        std::fs::write(peaks_path(source), self.encode(stamp))
+    }
+
+    /// Overview of source frames `frames` in `num_buckets` peaks, combining
+    /// channels by taking their overall minimum and maximum.
+    pub fn to_waveform(&self, frames: Range<u64>, num_buckets: usize) -> WaveformPeaks {
+        let channels = self.channels as usize;
+        let stride = channels * 2;
+        let span = |m: &Mipmap| {
+            let count = (m.peaks.len() / stride) as u64;
+            let div = m.division as u64;
+            let start = (frames.start / div).min(count);
+            let end = frames.end.div_ceil(div).clamp(start, count);
+            start as usize..end as usize
+        };
+        // The coarsest mipmap that still has a peak per bucket.
+        let Some(mipmap) = self
+            .mipmaps
+            .iter()
+            .rev()
+            .find(|m| span(m).len() >= num_buckets)
+            .or_else(|| self.mipmaps.first())
+        else {
+            return peaks::compute_peaks(&[], 1, num_buckets);
+        };
+
+        let range = span(mipmap);
+        let per_bucket = range.len() as f64 / num_buckets as f64;
+        let peaks = (0..num_buckets)
+            .map(|bucket| {
+                let start = range.start + (bucket as f64 * per_bucket) as usize;
+                let end = (range.start + ((bucket + 1) as f64 * per_bucket) as usize)
+                    .max(start + 1)
+                    .min(range.end);
+                if start >= end {
+                    return (0.0, 0.0);
+                }
+                let (mut lo, mut hi) = (i16::MAX, i16::MIN);
+                for peak in mipmap.peaks[start * stride..end * stride].chunks_exact(stride) {
+                    let (max, min) = peak.split_at(channels);
+                    hi = hi.max(*max.iter().max().unwrap());
+                    lo = lo.min(*min.iter().min().unwrap());
+                }
+                (from_i16(lo), from_i16(hi))
+            })
+            .collect();
+        WaveformPeaks { peaks, num_buckets }
+    }
+
+    fn encode(&self, (mtime, size): (i32, i32)) -> Vec<u8> {
+        let data: usize = self.mipmaps.iter().map(|m| m.peaks.len() * 2).sum();
+        let mut out = Vec::with_capacity(HEADER_LEN + self.mipmaps.len() * 8 + data);
+        out.extend_from_slice(MAGIC_WRITE);
+        out.push(self.channels);
+        out.push(self.mipmaps.len() as u8);
+        out.extend_from_slice(&(self.sample_rate as i32).to_le_bytes());
+        out.extend_from_slice(&mtime.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        let stride = self.channels as usize * 2;
+        for m in &self.mipmaps {
+            out.extend_from_slice(&(m.division as i32).to_le_bytes());
+            out.extend_from_slice(&((m.peaks.len() / stride) as i32).to_le_bytes());
+        }
+        for m in &self.mipmaps {
+            out.extend(m.peaks.iter().flat_map(|p| p.to_le_bytes()));
+        }
+        out
+    }
+}
+
+/// Overview for `decoded`, which holds `region` of `path` (or all of it, or
+/// its preview). Served from `path`'s peak file when that's current;
+/// otherwise computed, and written out when the decode covered the whole file.
+pub(crate) fn peaks_for(
+    path: &Path,
+    decoded: &DecodedAudio,
+    region: Option<Region>,
+    num_buckets: usize,
+) -> WaveformPeaks {
+    let channels = decoded.channels.max(1) as usize;
+    if let Some(file) = PeakFile::read(path)
+        .filter(|f| f.sample_rate == decoded.sample_rate && f.channels as usize == channels)
+    {
+        let rate = decoded.sample_rate as f64;
+        let start = region.map_or(0, |r| (r.start.as_secs_f64() * rate) as u64);
+        let len = (decoded.interleaved.len() / channels) as u64;
+        return file.to_waveform(start..start + len, num_buckets);
+    }
+
+    if region.is_none() && !decoded.truncated {
+        let file =
+            PeakFile::from_samples(&decoded.interleaved, decoded.channels, decoded.sample_rate);
+        if let Err(e) = file.write(path) {
+            // Read-only shares and the like; the overview is still computed.
+            tracing::debug!("writing {}: {e}", peaks_path(path).display());
+        }
+    }
+    peaks::compute_peaks(&decoded.interleaved, channels, num_buckets)
+}
+
+/// Overview of all of `path` straight from its peak file, without decoding.
+pub(crate) fn whole_file(path: &Path, num_buckets: usize) -> Option<WaveformPeaks> {
+    PeakFile::read(path).map(|f| f.to_waveform(0..u64::MAX, num_buckets))
+}
+
+/// `(mtime, size)` as a peak file records them.
+fn stamp(source: &Path) -> Option<(i32, i32)> {
+    let meta = std::fs::metadata(source).ok()?;
+    let secs = meta
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((secs as u32 as i32, meta.len() as u32 as i32))
+}
+
+fn parse(bytes: &[u8]) -> Option<(PeakFile, (i32, i32))> {
+    let i32_at = |i: usize| Some(i32::from_le_bytes(bytes.get(i..i + 4)?.try_into().ok()?));
+    if !MAGIC_READ.iter().any(|m| bytes.get(0..4) == Some(&m[..])) {
+        return None;
+    }
+    let channels = *bytes.get(4)?;
+    let num_mipmaps = *bytes.get(5)? as usize;
+    if channels == 0 || num_mipmaps == 0 {
+        return None;
+    }
+    let sample_rate = u32::try_from(i32_at(6)?).ok().filter(|&r| r > 0)?;
+    let stamp = (i32_at(10)?, i32_at(14)?);
+
+    let stride = channels as usize * 2;
+    let mut pos = HEADER_LEN + num_mipmaps * 8;
+    let mut mipmaps = Vec::with_capacity(num_mipmaps);
+    for i in 0..num_mipmaps {
+        let division = u32::try_from(i32_at(HEADER_LEN + i * 8)?)
+            .ok()
+            .filter(|&d| d > 0)?;
+        let count = usize::try_from(i32_at(HEADER_LEN + i * 8 + 4)?).ok()?;
+        let len = count.checked_mul(stride * 2)?;
+        let peaks = bytes
+            .get(pos..pos.checked_add(len)?)?
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        pos += len;
+        mipmaps.push(Mipmap { division, peaks });
+    }
+    Some((
+        PeakFile {
+            channels,
+            sample_rate,
+            mipmaps,
+        },
+        stamp,
+    ))
+}
+
+fn to_i16(s: f32) -> i16 {
+    (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn from_i16(s: i16) -> f32 {
+    (s as f32 / i16::MAX as f32).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let samples: Vec<f32> = (0..2 * 1000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let file = PeakFile::from_samples(&samples, 2, 8_000);
+        assert_eq!(file.mipmaps[0].division, 20);
+        assert_eq!(file.mipmaps[0].peaks.len(), 50 * 4);
+
+        let bytes = file.encode((1_700_000_000, 1234));
+        assert_eq!(&bytes[..4], b"RPKM");
+        assert_eq!(parse(&bytes), Some((file, (1_700_000_000, 1234))));
+        assert_eq!(parse(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn waveform_covers_requested_span() {
+        // Mono: silence, then a full-scale burst in the second half.
+        let mut samples = vec![0.0f32; 8_000];
+        samples[4_000..].iter_mut().enumerate().for_each(|(i, s)| {
+            *s = if i % 2 == 0 { 1.0 } else { -1.0 };
+        });
+        let file = PeakFile::from_samples(&samples, 1, 8_000);
+
+        let whole = file.to_waveform(0..u64::MAX, 4);
+        assert_eq!(
+            whole.peaks,
+            [(0.0, 0.0), (0.0, 0.0), (-1.0, 1.0), (-1.0, 1.0)]
+        );
+        let quiet = file.to_waveform(0..4_000, 8);
+        assert!(quiet.peaks.iter().all(|&p| p == (0.0, 0.0)));
+    }
+
+    #[test]
+    fn stale_peak_files_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("kick.wav");
+        std::fs::write(&source, b"pretend audio").unwrap();
+        let file = PeakFile::from_samples(&[0.5, -0.5], 1, 44_100);
+        file.write(&source).unwrap();
+        assert!(peaks_path(&source).ends_with("kick.wav.reapeaks"));
+        assert_eq!(PeakFile::read(&source), Some(file));
+
+        std::fs::write(&source, b"re-rendered audio").unwrap();
+        assert_eq!(PeakFile::read(&source), None);
+    }
+}
@@ -0,0 +1,258 @@
+//! Session recording: everything the audio callback sends to the device,
+//! written to a WAV file as it plays, so a happy accident while skipping
+//! through samples can be kept.
+//!
+//! The callback never blocks on the file, nor allocates or frees. It copies
+//! each buffer into one or more of a fixed set of spare ones and hands them
+//! to a writer thread; if the writer falls behind and none are spare, the
+//! audio is dropped and counted.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::PlaybackError;
+
+/// Spare buffers between the callback and the writer; a few seconds' worth
+/// at common buffer sizes.
+const SPARE_BUFFERS: usize = 64;
+
+/// Samples each spare buffer holds without growing.
+const BUFFER_SAMPLES: usize = 8192;
+
+enum Chunk {
+    Samples(Vec<f32>),
+    End,
+}
+
+/// The callback's end of a recording.
+pub(crate) struct Tap {
+    tx: SyncSender<Chunk>,
+    spare: Mutex<Vec<Vec<f32>>>,
+    /// Samples copied into each buffer: whole frames, within its capacity,
+    /// so a dropped piece doesn't shift the channels after it.
+    piece: usize,
+    dropped: AtomicUsize,
+}
+
+impl Tap {
+    fn new(tx: SyncSender<Chunk>, channels: u16) -> Self {
+        let channels = usize::from(channels.max(1));
+        Tap {
+            tx,
+            spare: Mutex::new(
+                (0..SPARE_BUFFERS)
+                    .map(|_| Vec::with_capacity(BUFFER_SAMPLES))
+                    .collect(),
+            ),
+            piece: (BUFFER_SAMPLES - BUFFER_SAMPLES % channels).max(channels),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Queue a copy of `data` for the file. Called on the audio thread.
+    pub(crate) fn push(&self, data: &[f32]) {
+        for piece in data.chunks(self.piece) {
+            // `try_lock`: the writer only holds it to hand a buffer back.
+            let buffer = self.spare.try_lock().ok().and_then(|mut s| s.pop());
+            let Some(mut buffer) = buffer else {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            };
+            buffer.clear();
+            buffer.extend_from_slice(piece);
+            let (TrySendError::Full(chunk) | TrySendError::Disconnected(chunk)) =
+                match self.tx.try_send(Chunk::Samples(buffer)) {
+                    Ok(()) => continue,
+                    Err(e) => e,
+                };
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            // Back to the spares rather than freed here. Only if the writer
+            // is handing one back this very moment is it freed after all.
+            if let (Chunk::Samples(buffer), Ok(mut spare)) = (chunk, self.spare.try_lock()) {
+                spare.push(buffer);
+            }
+        }
+    }
+}
+
+/// A recording in progress.
+pub(crate) struct Recording {
+    path: PathBuf,
+    dir: PathBuf,
+    tap: Arc<Tap>,
+    writer: JoinHandle<Result<(), hound::Error>>,
+}
+
+impl Recording {
+    /// Start a file in `dir` named for the current time (UTC),
+    /// `punks-20261016-142530.wav`, for audio in this format.
+    pub(crate) fn start(
+        dir: &Path,
+        channels: u16,
+        sample_rate: u32,
+    ) -> Result<Self, PlaybackError> {
+        let path = dir.join(format!("punks-{}.wav", timestamp(SystemTime::now())));
+        let err = |e: &dyn std::fmt::Display| PlaybackError::Encode {
+            path: path.clone(),
+            message: e.to_string(),
+        };
+        std::fs::create_dir_all(dir).map_err(|e| err(&e))?;
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let writer = hound::WavWriter::create(&path, spec).map_err(|e| err(&e))?;
+
+        let (tx, rx) = mpsc::sync_channel(SPARE_BUFFERS);
+        let tap = Arc::new(Tap::new(tx, channels));
+        let writer_tap = Arc::clone(&tap);
+        let writer = std::thread::Builder::new()
+            .name("punks-record".into())
+            .spawn(move || write_chunks(writer, &rx, &writer_tap))
+            .map_err(|e| err(&e))?;
+        Ok(Recording {
+            path,
+            dir: dir.to_path_buf(),
+            tap,
+            writer,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub(crate) fn tap(&self) -> Arc<Tap> {
+        Arc::clone(&self.tap)
+    }
+
+    /// Write out what's queued and close the file. The tap must already be
+    /// unhooked from the callback.
+    pub(crate) fn finish(self) -> Result<PathBuf, PlaybackError> {
+        let _ = self.tap.tx.send(Chunk::End);
+        let written = self
+            .writer
+            .join()
+            .unwrap_or(Err(hound::Error::FormatError("recording thread panicked")));
+        let dropped = self.tap.dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            tracing::warn!(
+                "{}: {dropped} buffers dropped while writing",
+                self.path.display()
+            );
+        }
+        match written {
+            Ok(()) => Ok(self.path),
+            Err(e) => Err(PlaybackError::Encode {
+                path: self.path,
+                message: e.to_string(),
+            }),
+        }
+    }
+}
+
+fn write_chunks(
+    mut writer: hound::WavWriter<std::io::BufWriter<std::fs::File>>,
+    rx: &Receiver<Chunk>,
+    tap: &Tap,
+) -> Result<(), hound::Error> {
+    while let Ok(Chunk::Samples(buffer)) = rx.recv() {
+        for &s in &buffer {
+            writer.write_sample(s)?;
+        }
+        tap.spare
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(buffer);
+    }
+    writer.finalize()
+}
+
+/// `YYYYMMDD-HHMMSS` in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn names_files_by_utc_time() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_792_160_730);
+        assert_eq!(timestamp(t), "20261016-142530");
+        assert_eq!(timestamp(UNIX_EPOCH), "19700101-000000");
+    }
+
+    #[test]
+    fn writes_what_the_callback_sends() {
+        let dir = tempfile::tempdir().unwrap();
+        let recording = Recording::start(dir.path(), 2, 48_000).unwrap();
+        let tap = recording.tap();
+        tap.push(&[0.5, -0.5, 0.25, -0.25]);
+        tap.push(&[1.0, 0.0]);
+        let path = recording.finish().unwrap();
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 48_000);
+        let samples: Vec<f32> = reader.into_samples().map(Result::unwrap).collect();
+        assert_eq!(samples, [0.5, -0.5, 0.25, -0.25, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn long_buffers_go_in_whole_frame_pieces() {
+        let dir = tempfile::tempdir().unwrap();
+        let recording = Recording::start(dir.path(), 3, 48_000).unwrap();
+        let tap = recording.tap();
+        assert_eq!(tap.piece % 3, 0);
+        let long: Vec<f32> = (0..3 * BUFFER_SAMPLES).map(|i| i as f32).collect();
+        tap.push(&long);
+        let path = recording.finish().unwrap();
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        let samples: Vec<f32> = reader.into_samples().map(Result::unwrap).collect();
+        assert_eq!(samples, long);
+    }
+
+    #[test]
+    fn buffers_the_writer_cant_take_go_back_to_the_spares() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let tap = Tap::new(tx, 2);
+        tap.push(&[0.5; 4]);
+        // Full, then gone, as when the writer fails on a full disk.
+        tap.push(&[0.5; 4]);
+        drop(rx);
+        tap.push(&[0.5; 4]);
+        assert_eq!(tap.dropped.load(Ordering::Relaxed), 2);
+        assert_eq!(tap.spare.lock().unwrap().len(), SPARE_BUFFERS - 1);
+    }
+}
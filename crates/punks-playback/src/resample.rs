@@ -1,6 +1,19 @@
 use crate::PlaybackError;
 use rubato::{FftFixedIn, Resampler};
 
+/// Resample interleaved audio. The file is fed through one chunk at a time:
+/// each chunk is de-interleaved into reused scratch buffers and the output
+/// interleaved straight into the result, so only the input and output are
+/// ever fully in memory.
+///
+/// The FFT resampler delays its output; that lead-in is dropped and the tail
+/// flushed out, so the result lines up with the input sample for sample and
+/// is exactly as long.
+#[tracing::instrument(
+    level = "debug",
+    skip(interleaved),
+    fields(frames = interleaved.len() / channels.max(1))
+)]
 pub fn resample(
     interleaved: &[f32],
     channels: usize,
@@ -25,54 +38,125 @@ pub fn resample(
         2,
         channels,
     )
-    .map_err(|e| PlaybackError::DecodeError(format!("resampler init: {e}")))?;
+    .map_err(|e| PlaybackError::decode(format!("resampler init: {e}")))?;
 
-    let mut channel_data: Vec<Vec<f32>> = vec![Vec::with_capacity(num_frames); channels];
-    for frame in 0..num_frames {
-        for ch in 0..channels {
-            channel_data[ch].push(interleaved[frame * channels + ch]);
+    let mut input = resampler.input_buffer_allocate(false);
+    let mut output = resampler.output_buffer_allocate(true);
+    let expected_frames =
+        (num_frames as u64 * target_rate as u64).div_ceil(source_rate as u64) as usize;
+    let mut result =
+        Vec::with_capacity((expected_frames + resampler.output_frames_max()) * channels);
+    let mut delay = resampler.output_delay();
+    let mut emit = |output: &[Vec<f32>], written: usize, result: &mut Vec<f32>| {
+        let skip = delay.min(written);
+        delay -= skip;
+        for frame in skip..written {
+            result.extend(output.iter().map(|ch| ch[frame]));
         }
-    }
+    };
 
-    let mut output_channels: Vec<Vec<f32>> = vec![Vec::new(); channels];
-    let mut pos = 0;
+    for block in interleaved[..num_frames * channels].chunks(chunk_size * channels) {
+        for (ch, buf) in input.iter_mut().enumerate() {
+            buf.clear();
+            buf.extend(block[ch..].iter().step_by(channels));
+        }
 
-    while pos + chunk_size <= num_frames {
-        let input: Vec<&[f32]> = channel_data
-            .iter()
-            .map(|ch| &ch[pos..pos + chunk_size])
-            .collect();
+        let (_, written) = if block.len() == chunk_size * channels {
+            resampler
+                .process_into_buffer(&input, &mut output, None)
+                .map_err(|e| PlaybackError::decode(format!("resample: {e}")))?
+        } else {
+            resampler
+                .process_partial_into_buffer(Some(&input), &mut output, None)
+                .map_err(|e| PlaybackError::decode(format!("resample partial: {e}")))?
+        };
 
-        let out = resampler
-            .process(&input, None)
-            .map_err(|e| PlaybackError::DecodeError(format!("resample: {e}")))?;
+        emit(&output, written, &mut result);
+    }
 
-        for (ch, data) in out.iter().enumerate() {
-            output_channels[ch].extend_from_slice(data);
+    // Flush what's still held back by the delay.
+    while result.len() < expected_frames * channels {
+        let (_, written) = resampler
+            .process_partial_into_buffer(None::<&[Vec<f32>]>, &mut output, None)
+            .map_err(|e| PlaybackError::decode(format!("resample flush: {e}")))?;
+        if written == 0 {
+            break;
         }
-
-        pos += chunk_size;
+        emit(&output, written, &mut result);
     }
+    result.truncate(expected_frames * channels);
 
-    if pos < num_frames {
-        let input: Vec<&[f32]> = channel_data.iter().map(|ch| &ch[pos..]).collect();
+    Ok(result)
+}
 
-        let out = resampler
-            .process_partial(Some(&input), None)
-            .map_err(|e| PlaybackError::DecodeError(format!("resample partial: {e}")))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for (ch, data) in out.iter().enumerate() {
-            output_channels[ch].extend_from_slice(data);
+    /// The allocation-heavy original, as the reference for the output.
+    fn reference(interleaved: &[f32], channels: usize, from: u32, to: u32) -> Vec<f32> {
+        let num_frames = interleaved.len() / channels;
+        let chunk_size = 1024.min(num_frames);
+        let mut resampler =
+            FftFixedIn::<f32>::new(from as usize, to as usize, chunk_size, 2, channels).unwrap();
+        let channel_data: Vec<Vec<f32>> = (0..channels)
+            .map(|ch| {
+                (0..num_frames)
+                    .map(|f| interleaved[f * channels + ch])
+                    .collect()
+            })
+            .collect();
+        let mut out_channels: Vec<Vec<f32>> = vec![Vec::new(); channels];
+        let mut pos = 0;
+        while pos + chunk_size <= num_frames {
+            let input: Vec<&[f32]> = channel_data
+                .iter()
+                .map(|ch| &ch[pos..pos + chunk_size])
+                .collect();
+            for (ch, data) in resampler.process(&input, None).unwrap().iter().enumerate() {
+                out_channels[ch].extend_from_slice(data);
+            }
+            pos += chunk_size;
+        }
+        if pos < num_frames {
+            let input: Vec<&[f32]> = channel_data.iter().map(|ch| &ch[pos..]).collect();
+            let out = resampler.process_partial(Some(&input), None).unwrap();
+            for (ch, data) in out.iter().enumerate() {
+                out_channels[ch].extend_from_slice(data);
+            }
         }
+        (0..out_channels[0].len())
+            .flat_map(|f| out_channels.iter().map(move |ch| ch[f]))
+            .collect()
     }
 
-    let out_frames = output_channels[0].len();
-    let mut result = Vec::with_capacity(out_frames * channels);
-    for frame in 0..out_frames {
-        for ch in &output_channels {
-            result.push(ch[frame]);
+    #[test]
+    fn matches_reference_without_the_delay() {
+        // Stereo, a partial last chunk and a dangling half frame.
+        let input: Vec<f32> = (0..2 * 5_000 + 1)
+            .map(|i| (i as f32 * 0.013).sin())
+            .collect();
+        for (from, to) in [(44_100, 48_000), (48_000, 44_100), (96_000, 48_000)] {
+            let delay = FftFixedIn::<f32>::new(from as usize, to as usize, 1024, 2, 2)
+                .unwrap()
+                .output_delay();
+            let out = resample(&input, 2, from, to).unwrap();
+            let reference = reference(&input, 2, from, to);
+            let expected = (5_000u64 * to as u64).div_ceil(from as u64) as usize;
+            assert_eq!(out.len(), expected * 2, "{from} -> {to}");
+            let shared = reference.len() - delay * 2;
+            assert_eq!(out[..shared], reference[delay * 2..], "{from} -> {to}");
         }
     }
 
-    Ok(result)
+    #[test]
+    fn impulse_stays_in_place() {
+        let mut input = vec![0.0; 4_000];
+        input[1_000] = 1.0;
+        let out = resample(&input, 1, 48_000, 96_000).unwrap();
+        let peak = (0..out.len())
+            .max_by(|&a, &b| out[a].total_cmp(&out[b]))
+            .unwrap();
+        assert!((1_999..=2_001).contains(&peak), "peak at {peak}");
+    }
 }
@@ -0,0 +1,377 @@
+//! Tracker modules (`.mod`, `.s3m`, `.xm`, `.it`): rendered to stereo PCM
+//! for previewing like any other file, and opened up so the samples inside
+//! can be auditioned and pulled out. Each embedded sample has a path under
+//! its module, `song.xm/03 Bass.wav`, that [`decode`](crate::decode) plays
+//! and [`list_samples`] lists as a folder.
+
+mod it;
+mod player;
+mod protracker;
+mod s3m;
+mod song;
+mod xm;
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use punks_core::{DirListing, FileEntry, MODULE_EXTENSIONS};
+
+use crate::decode::{AudioMetadata, DecodeLimits, DecodedAudio, FileInfo, Region};
+use crate::PlaybackError;
+use player::Player;
+use song::Song;
+
+/// Rate modules are rendered at.
+const RATE: u32 = 48_000;
+
+/// Songs that haven't ended or come round again by now are cut off here.
+const LONGEST: Duration = Duration::from_secs(30 * 60);
+
+/// Frames rendered at a time when only counting or skipping.
+const CHUNK: usize = 4096;
+
+/// A sample stored inside a module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedSample {
+    /// 1-based, as the tracker numbers it.
+    pub number: usize,
+    pub name: String,
+    /// Where it can be played from: a path under the module.
+    pub path: PathBuf,
+    pub frames: usize,
+    pub sample_rate: u32,
+}
+
+/// Whether `path` is named like a module.
+pub fn is_module(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| MODULE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// The module and sample number an embedded sample's path points to.
+pub fn embedded(path: &Path) -> Option<(&Path, usize)> {
+    let module = path.parent()?;
+    let name = path.file_name()?.to_str()?;
+    let digits = name.split(' ').next()?;
+    let number = digits.parse().ok().filter(|&n| n > 0)?;
+    (is_module(module) && module.is_file()).then_some((module, number))
+}
+
+fn load(path: &Path) -> Result<Song, PlaybackError> {
+    let data = std::fs::read(path).map_err(|e| PlaybackError::io(path, e))?;
+    if xm::is_xm(&data) {
+        xm::load(&data)
+    } else if it::is_it(&data) {
+        it::load(&data)
+    } else if s3m::is_s3m(&data) {
+        s3m::load(&data)
+    } else if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("mod"))
+    {
+        protracker::load(&data)
+    } else {
+        Err(PlaybackError::unsupported("not a tracker module"))
+    }
+}
+
+/// The samples in `module` that hold any audio, in the tracker's order.
+pub fn samples(module: &Path) -> Result<Vec<EmbeddedSample>, PlaybackError> {
+    let song = load(module).map_err(|e| e.at(module))?;
+    let digits = song.samples.len().to_string().len().max(2);
+    Ok(song
+        .samples
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !s.data.is_empty())
+        .map(|(i, s)| {
+            let name: String = s
+                .name
+                .chars()
+                .map(|c| {
+                    if c.is_alphanumeric() || " -_().,'&+#".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let name = if name.trim().is_empty() {
+                "sample".to_string()
+            } else {
+                name.trim().to_string()
+            };
+            EmbeddedSample {
+                number: i + 1,
+                path: module.join(format!("{:0digits$} {name}.wav", i + 1)),
+                name,
+                frames: s.data.len(),
+                sample_rate: s.c5_speed.round() as u32,
+            }
+        })
+        .collect())
+}
+
+/// `module`'s samples as a folder listing, sized as they'd be extracted.
+pub fn list_samples(module: &Path) -> Result<DirListing, PlaybackError> {
+    let entries = samples(module)?
+        .into_iter()
+        .map(|s| FileEntry {
+            name: s
+                .path
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+            file_name: s.path.file_name().unwrap_or_default().to_os_string(),
+            extension: "wav".to_string(),
+            size_bytes: 44 + s.frames as u64 * 4,
+            path: s.path,
+            is_directory: false,
+            region: None,
+            audio_count: None,
+            modified: None,
+        })
+        .collect();
+    Ok(DirListing {
+        root: module.to_path_buf(),
+        entries,
+        skipped: Vec::new(),
+    })
+}
+
+/// Write each of `module`'s samples into `dir` as a WAV named like its
+/// entry in [`list_samples`], replacing files of the same name. Returns the
+/// files written.
+pub fn extract_samples(module: &Path, dir: &Path) -> Result<Vec<PathBuf>, PlaybackError> {
+    let song = load(module).map_err(|e| e.at(module))?;
+    let mut written = Vec::new();
+    for sample in samples(module)? {
+        let data = &song.samples[sample.number - 1].data;
+        let file = dir.join(sample.path.file_name().unwrap_or_default());
+        let spec = crate::wav::float_spec(1, sample.sample_rate.max(1));
+        crate::wav::write_wav(&file, data, spec, false)?;
+        written.push(file);
+    }
+    Ok(written)
+}
+
+/// Render a module, or decode an embedded sample; `None` if `path` is
+/// neither.
+pub(crate) fn decode(
+    path: &Path,
+    region: Option<Region>,
+    limits: DecodeLimits,
+) -> Option<Result<DecodedAudio, PlaybackError>> {
+    if let Some((module, number)) = embedded(path) {
+        return Some(decode_sample(module, number, region, limits));
+    }
+    is_module(path).then(|| render(path, region, limits))
+}
+
+/// Rate, channels and length of a module or embedded sample; `None` if
+/// `path` is neither.
+pub(crate) fn probe(path: &Path) -> Option<Result<FileInfo, PlaybackError>> {
+    if let Some((module, number)) = embedded(path) {
+        let info = load(module).and_then(|song| {
+            let sample = sample_of(&song, number)?;
+            let rate = sample.c5_speed.round().max(1.0);
+            Ok(FileInfo {
+                sample_rate: rate as u32,
+                channels: 1,
+                bit_depth: None,
+                duration: Some(Duration::from_secs_f64(sample.data.len() as f64 / rate)),
+                metadata: AudioMetadata::default(),
+            })
+        });
+        return Some(info);
+    }
+    is_module(path).then(|| {
+        let song = load(path)?;
+        let frames = Player::new(&song, RATE).render(None, frames_in(LONGEST));
+        Ok(FileInfo {
+            sample_rate: RATE,
+            channels: 2,
+            bit_depth: None,
+            duration: Some(duration_of(frames, RATE)),
+            metadata: metadata(&song),
+        })
+    })
+}
+
+fn sample_of(song: &Song, number: usize) -> Result<&song::Sample, PlaybackError> {
+    number
+        .checked_sub(1)
+        .and_then(|i| song.samples.get(i))
+        .filter(|s| !s.data.is_empty())
+        .ok_or_else(|| PlaybackError::decode(format!("the module has no sample {number}")))
+}
+
+fn render(
+    path: &Path,
+    region: Option<Region>,
+    limits: DecodeLimits,
+) -> Result<DecodedAudio, PlaybackError> {
+    let song = load(path)?;
+    let total = Player::new(&song, RATE).render(None, frames_in(LONGEST));
+    let (start, end) = span(total, RATE, region);
+    let source = duration_of(end - start, RATE);
+    let kept = kept_frames(end - start, RATE, source, limits);
+    limits.fits(kept as u64 * 2)?;
+
+    let mut player = Player::new(&song, RATE);
+    let mut scratch = vec![0.0; CHUNK * 2];
+    let mut skipped = 0;
+    while skipped < start {
+        scratch.fill(0.0);
+        match player.render(Some(&mut scratch), CHUNK.min(start - skipped)) {
+            0 => break,
+            n => skipped += n,
+        }
+    }
+    let mut interleaved = vec![0.0; kept * 2];
+    player.render(Some(&mut interleaved), kept);
+    Ok(DecodedAudio {
+        interleaved,
+        channels: 2,
+        sample_rate: RATE,
+        metadata: metadata(&song),
+        source_duration: source,
+        preview_duration: duration_of(kept, RATE),
+        truncated: kept < end - start,
+    })
+}
+
+fn decode_sample(
+    module: &Path,
+    number: usize,
+    region: Option<Region>,
+    limits: DecodeLimits,
+) -> Result<DecodedAudio, PlaybackError> {
+    let song = load(module)?;
+    let sample = sample_of(&song, number)?;
+    let rate = sample.c5_speed.round().max(1.0) as u32;
+    let (start, end) = span(sample.data.len(), rate, region);
+    let source = duration_of(end - start, rate);
+    let kept = kept_frames(end - start, rate, source, limits);
+    limits.fits(kept as u64)?;
+    Ok(DecodedAudio {
+        interleaved: sample.data[start..start + kept].to_vec(),
+        channels: 1,
+        sample_rate: rate,
+        metadata: AudioMetadata {
+            description: Some(sample.name.clone()).filter(|n| !n.is_empty()),
+            ..AudioMetadata::default()
+        },
+        source_duration: source,
+        preview_duration: duration_of(kept, rate),
+        truncated: kept < end - start,
+    })
+}
+
+fn metadata(song: &Song) -> AudioMetadata {
+    AudioMetadata {
+        description: Some(song.title.clone()).filter(|t| !t.is_empty()),
+        ..AudioMetadata::default()
+    }
+}
+
+/// The frames `region` covers of `total` at `rate`.
+fn span(total: usize, rate: u32, region: Option<Region>) -> (usize, usize) {
+    let Some(region) = region else {
+        return (0, total);
+    };
+    let start = frames_in_at(region.start, rate).min(total);
+    let end = region
+        .end
+        .map_or(total, |end| frames_in_at(end, rate).clamp(start, total));
+    (start, end)
+}
+
+/// How many of `frames` (lasting `source`) a decode within `limits` keeps.
+fn kept_frames(frames: usize, rate: u32, source: Duration, limits: DecodeLimits) -> usize {
+    let kept = limits.kept(source);
+    if kept < source {
+        frames_in_at(kept, rate).min(frames)
+    } else {
+        frames
+    }
+}
+
+fn frames_in(duration: Duration) -> usize {
+    frames_in_at(duration, RATE)
+}
+
+fn frames_in_at(duration: Duration, rate: u32) -> usize {
+    (duration.as_secs_f64() * f64::from(rate)).min(usize::MAX as f64) as usize
+}
+
+fn duration_of(frames: usize, rate: u32) -> Duration {
+    Duration::from_secs_f64(frames as f64 / f64::from(rate.max(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A four-channel ProTracker module: one 64-row pattern with a note on
+    /// its first row and a break on its second, and one 32-frame sample.
+    fn write_mod(path: &Path) {
+        let mut data = vec![0u8; 1084];
+        data[..4].copy_from_slice(b"test");
+        let sample = 20;
+        data[sample..sample + 5].copy_from_slice(b"Kick!");
+        data[sample + 22..sample + 24].copy_from_slice(&16u16.to_be_bytes());
+        data[sample + 25] = 64;
+        data[sample + 28..sample + 30].copy_from_slice(&1u16.to_be_bytes());
+        data[950] = 1;
+        data[1080..1084].copy_from_slice(b"M.K.");
+        let mut pattern = vec![0u8; 64 * 4 * 4];
+        // C-2 (period 428), sample 1; then D00 on the next row.
+        pattern[..4].copy_from_slice(&[0x01, 0xac, 0x10, 0x00]);
+        pattern[16..20].copy_from_slice(&[0x00, 0x00, 0x0d, 0x00]);
+        data.extend(pattern);
+        data.extend((0..32).map(|i| if i < 16 { 100u8 } else { 156 }));
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn renders_modules_and_their_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let module = dir.path().join("song.mod");
+        write_mod(&module);
+
+        // Two rows at speed 6, tempo 125: 0.24 s.
+        let rendered = decode(&module, None, DecodeLimits::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(rendered.channels, 2);
+        assert_eq!(rendered.interleaved.len(), 11_520 * 2);
+        assert_eq!(rendered.metadata.description.as_deref(), Some("test"));
+        assert!(rendered.interleaved.iter().any(|&s| s != 0.0));
+        let info = probe(&module).unwrap().unwrap();
+        let secs = info.duration.unwrap().as_secs_f64();
+        assert!((secs - 0.24).abs() < 1e-6, "{secs}");
+
+        let listing = list_samples(&module).unwrap();
+        let names: Vec<&str> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["01 Kick_.wav"]);
+        let sample = &listing.entries[0].path;
+        assert_eq!(embedded(sample), Some((module.as_path(), 1)));
+        let decoded = decode(sample, None, DecodeLimits::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.sample_rate, 8363);
+        assert_eq!(decoded.interleaved.len(), 32);
+        assert_eq!(decoded.interleaved[0], 100.0 / 128.0);
+        assert_eq!(decoded.interleaved[16], -100.0 / 128.0);
+
+        let out = dir.path().join("out");
+        let written = extract_samples(&module, &out).unwrap();
+        assert_eq!(written, [out.join("01 Kick_.wav")]);
+        let wav = hound::WavReader::open(&written[0]).unwrap();
+        assert_eq!(wav.len(), 32);
+
+        assert!(decode(&dir.path().join("kick.wav"), None, DecodeLimits::default()).is_none());
+    }
+}
@@ -0,0 +1,413 @@
+//! Impulse Tracker `.it`: up to 64 channels, instruments with keyboard
+//! tables and volume envelopes (or bare samples), and the IT 2.14/2.15
+//! compressed sample format.
+
+use super::s3m;
+use super::song::{
+    Bytes, Cell, Effect, Envelope, Instrument, Loop, NoteEvent, Pattern, Sample, Slides, Song,
+    NOTES,
+};
+use crate::PlaybackError;
+
+const CHANNELS: usize = 64;
+
+pub(crate) fn is_it(data: &[u8]) -> bool {
+    data.starts_with(b"IMPM")
+}
+
+pub(crate) fn load(data: &[u8]) -> Result<Song, PlaybackError> {
+    let b = Bytes(data);
+    let orders = usize::from(b.u16(32)?);
+    let instrument_count = usize::from(b.u16(34)?);
+    let sample_count = usize::from(b.u16(36)?);
+    let pattern_count = usize::from(b.u16(38)?);
+    let compatible = b.u16(42)?;
+    let flags = b.u16(44)?;
+    let stereo = flags & 1 != 0;
+    let use_instruments = flags & 4 != 0;
+    let linear = flags & 8 != 0;
+
+    let order_list = b.slice(192, orders)?.to_vec();
+    let offsets_at = 192 + orders;
+    let offset = |i: usize| b.u32(offsets_at + i * 4).map(|o| o as usize);
+
+    let mut samples = Vec::with_capacity(sample_count);
+    for i in 0..sample_count {
+        samples.push(sample(&b, offset(instrument_count + i)?)?);
+    }
+    let instruments = if use_instruments {
+        (0..instrument_count)
+            .map(|i| instrument(&b, offset(i)?, compatible >= 0x200, sample_count))
+            .collect::<Result<_, PlaybackError>>()?
+    } else {
+        (0..samples.len()).map(Instrument::of_sample).collect()
+    };
+
+    let mut patterns = Vec::with_capacity(pattern_count);
+    let mut used = 0;
+    for i in 0..pattern_count {
+        let at = offset(instrument_count + sample_count + i)?;
+        let pattern = if at == 0 {
+            Pattern::empty(64, CHANNELS)
+        } else {
+            pattern(&b, at, &mut used)?
+        };
+        patterns.push(pattern);
+    }
+    // Drop the channels no pattern touches.
+    let channels = used.max(1);
+    for pattern in &mut patterns {
+        pattern.cells = pattern
+            .cells
+            .chunks_exact(CHANNELS)
+            .flat_map(|row| row[..channels].iter().copied())
+            .collect();
+    }
+
+    let panning = b
+        .slice(64, channels)?
+        .iter()
+        .map(|&p| match p & 0x7f {
+            p if !stereo || p > 64 => 0.0,
+            p => f32::from(p) / 32.0 - 1.0,
+        })
+        .collect();
+
+    Ok(Song {
+        title: b.text(4, 26)?,
+        channels,
+        orders: order_list,
+        patterns,
+        instruments,
+        samples,
+        speed: b.u8(50)?.max(1),
+        tempo: b.u8(51)?.max(32),
+        global_volume: f32::from(b.u8(48)?.min(128)) / 128.0,
+        global_volume_max: 128.0,
+        panning,
+        slides: if linear {
+            Slides::Linear
+        } else {
+            Slides::Amiga
+        },
+        decimal_breaks: false,
+    })
+}
+
+fn sample(b: &Bytes, at: usize) -> Result<Sample, PlaybackError> {
+    let name = b.text(at + 20, 26)?;
+    let name = if name.is_empty() {
+        b.text(at + 4, 12)?
+    } else {
+        name
+    };
+    let flags = b.u8(at + 18)?;
+    let convert = b.u8(at + 46)?;
+    let len = b.u32(at + 48)? as usize;
+    let data_at = b.u32(at + 72)? as usize;
+    let sixteen_bit = flags & 2 != 0;
+    let stereo = flags & 4 != 0;
+    let data = if flags & 1 == 0 {
+        Vec::new()
+    } else if flags & 8 != 0 {
+        decompress(
+            b.0.get(data_at..).unwrap_or_default(),
+            len,
+            sixteen_bit,
+            convert & 4 != 0,
+        )
+    } else {
+        b.pcm(data_at, len, sixteen_bit, convert & 1 != 0, stereo)
+    };
+    let default_pan = b.u8(at + 47)?;
+    Ok(Sample {
+        name,
+        repeat: (flags & 0x10 != 0)
+            .then(|| {
+                let (start, end) = (b.u32(at + 52).ok()?, b.u32(at + 56).ok()?);
+                Loop::new(start as usize, end as usize, data.len(), flags & 0x40 != 0)
+            })
+            .flatten(),
+        data,
+        volume: b.u8(at + 19)?.min(64),
+        global_volume: f32::from(b.u8(at + 17)?.min(64)) / 64.0,
+        pan: (default_pan & 0x80 != 0)
+            .then(|| f32::from((default_pan & 0x7f).min(64)) / 32.0 - 1.0),
+        c5_speed: f64::from(b.u32(at + 60)?.max(1)),
+    })
+}
+
+/// The instrument at `at`; `new_format` for those saved by Impulse Tracker
+/// 2 and later, whose envelopes are read (older ones only map notes).
+fn instrument(
+    b: &Bytes,
+    at: usize,
+    new_format: bool,
+    sample_count: usize,
+) -> Result<Instrument, PlaybackError> {
+    let keys = b.slice(at + 64, NOTES * 2)?;
+    let keymap = keys
+        .chunks_exact(2)
+        .map(|k| {
+            let sample = usize::from(k[1])
+                .checked_sub(1)
+                .filter(|&s| s < sample_count);
+            (k[0].min(NOTES as u8 - 1), sample)
+        })
+        .collect();
+    if !new_format {
+        return Ok(Instrument {
+            keymap,
+            envelope: None,
+            fadeout: 0.0,
+        });
+    }
+
+    let env = at + 304;
+    let flags = b.u8(env)?;
+    let envelope = (flags & 1 != 0)
+        .then(|| -> Result<Envelope, PlaybackError> {
+            let points = usize::from(b.u8(env + 1)?.clamp(1, 25));
+            let point = |i: usize| -> Result<usize, PlaybackError> {
+                Ok(usize::from(b.u8(env + i)?).min(points - 1))
+            };
+            Ok(Envelope {
+                points: (0..points)
+                    .map(|p| {
+                        let level = f32::from(b.u8(env + 6 + p * 3)?.min(64)) / 64.0;
+                        Ok((b.u16(env + 7 + p * 3)?, level))
+                    })
+                    .collect::<Result<_, PlaybackError>>()?,
+                repeat: (flags & 2 != 0).then_some((point(2)?, point(3)?)),
+                sustain: (flags & 4 != 0).then_some((point(4)?, point(5)?)),
+            })
+        })
+        .transpose()?;
+    Ok(Instrument {
+        keymap,
+        envelope,
+        fadeout: f32::from(b.u16(at + 20)?) / 1024.0,
+    })
+}
+
+/// The packed pattern at `at`, laid out [`CHANNELS`] wide; `used` grows to
+/// the highest channel with anything in it.
+fn pattern(b: &Bytes, at: usize, used: &mut usize) -> Result<Pattern, PlaybackError> {
+    let rows = usize::from(b.u16(at + 2)?).max(1);
+    let mut pattern = Pattern::empty(rows, CHANNELS);
+    let mut at = at + 8;
+    let mut masks = [0u8; CHANNELS];
+    let mut last = [Cell::default(); CHANNELS];
+    let mut row = 0;
+    while row < rows {
+        let what = b.u8(at)?;
+        at += 1;
+        if what == 0 {
+            row += 1;
+            continue;
+        }
+        let channel = usize::from((what - 1) & 0x3f);
+        if what & 0x80 != 0 {
+            masks[channel] = b.u8(at)?;
+            at += 1;
+        }
+        let mask = masks[channel];
+        let last = &mut last[channel];
+        if mask & 1 != 0 {
+            last.note = match b.u8(at)? {
+                n @ 0..=119 => Some(NoteEvent::Note(n)),
+                255 => Some(NoteEvent::Off),
+                254 => Some(NoteEvent::Cut),
+                // Note fade: released, like an off.
+                _ => Some(NoteEvent::Off),
+            };
+            at += 1;
+        }
+        if mask & 2 != 0 {
+            last.instrument = u16::from(b.u8(at)?);
+            at += 1;
+        }
+        if mask & 4 != 0 {
+            (last.volume, last.volume_effect) = volume_column(b.u8(at)?);
+            at += 1;
+        }
+        if mask & 8 != 0 {
+            let (command, param) = (b.u8(at)?, b.u8(at + 1)?);
+            last.effect = match command {
+                24 => Effect::Pan(f32::from(param) / 127.5 - 1.0),
+                20 if param < 0x20 => Effect::None,
+                command => s3m::effect(command, param),
+            };
+            at += 2;
+        }
+        let cell = &mut pattern.cells[row * CHANNELS + channel];
+        if mask & 0x11 != 0 {
+            cell.note = last.note;
+        }
+        if mask & 0x22 != 0 {
+            cell.instrument = last.instrument;
+        }
+        if mask & 0x44 != 0 {
+            (cell.volume, cell.volume_effect) = (last.volume, last.volume_effect);
+        }
+        if mask & 0x88 != 0 {
+            cell.effect = last.effect;
+        }
+        *used = (*used).max(channel + 1);
+    }
+    Ok(pattern)
+}
+
+fn volume_column(v: u8) -> (Option<u8>, Effect) {
+    match v {
+        0..=64 => (Some(v), Effect::None),
+        65..=74 => (None, Effect::FineVolume((v - 65) as i8)),
+        75..=84 => (None, Effect::FineVolume(-((v - 75) as i8))),
+        85..=94 => (None, Effect::VolumeSlide((v - 85) << 4)),
+        95..=104 => (None, Effect::VolumeSlide(v - 95)),
+        105..=114 => (None, Effect::PortaDown((v - 105) * 4)),
+        115..=124 => (None, Effect::PortaUp((v - 115) * 4)),
+        128..=192 => (None, Effect::Pan(f32::from(v - 128) / 32.0 - 1.0)),
+        193..=202 => {
+            const SPEEDS: [u8; 10] = [0, 1, 4, 8, 16, 32, 64, 96, 128, 255];
+            (None, Effect::TonePorta(SPEEDS[usize::from(v - 193)]))
+        }
+        203..=212 => (None, Effect::Vibrato(v - 203)),
+        _ => (None, Effect::None),
+    }
+}
+
+/// Reads `width`-bit little-endian values, lowest bit first.
+struct Bits<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl Bits<'_> {
+    fn read(&mut self, width: u32) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..width {
+            let byte = *self.data.get(self.bit / 8)?;
+            value |= u32::from((byte >> (self.bit % 8)) & 1) << i;
+            self.bit += 1;
+        }
+        Some(value)
+    }
+}
+
+/// Unpack `frames` of IT 2.14 (or, `it215`, 2.15) compressed sample data:
+/// blocks of variable-width deltas, each block behind its byte length.
+/// A damaged block ends the sample there.
+fn decompress(data: &[u8], frames: usize, sixteen_bit: bool, it215: bool) -> Vec<f32> {
+    let (block_frames, top, scale) = if sixteen_bit {
+        (0x4000, 17u32, 32768.0)
+    } else {
+        (0x8000, 9u32, 128.0)
+    };
+    let mut out = Vec::with_capacity(frames);
+    let mut at = 0;
+    while out.len() < frames {
+        let Some(len) = data
+            .get(at..at + 2)
+            .map(|l| usize::from(u16::from_le_bytes([l[0], l[1]])))
+        else {
+            break;
+        };
+        let Some(block) = data.get(at + 2..at + 2 + len) else {
+            break;
+        };
+        at += 2 + len;
+        let mut bits = Bits {
+            data: block,
+            bit: 0,
+        };
+        let mut width = top;
+        let (mut d1, mut d2) = (0i32, 0i32);
+        let end = (out.len() + block_frames).min(frames);
+        while out.len() < end {
+            let Some(value) = bits.read(width) else {
+                return out;
+            };
+            if width < 7 {
+                if value == 1 << (width - 1) {
+                    let Some(new) = bits.read(if sixteen_bit { 4 } else { 3 }) else {
+                        return out;
+                    };
+                    let new = new + 1;
+                    width = if new < width { new } else { new + 1 };
+                    continue;
+                }
+            } else if width < top {
+                let border = ((1u32 << (top - 1)) - 1) >> (top - width);
+                let border = border - if sixteen_bit { 8 } else { 4 };
+                let span = if sixteen_bit { 16 } else { 8 };
+                if value > border && value <= border + span {
+                    let new = value - border;
+                    width = if new < width { new } else { new + 1 };
+                    continue;
+                }
+            } else if width == top {
+                if value & (1 << (top - 1)) != 0 {
+                    width = (value + 1) & 0xff;
+                    continue;
+                }
+            } else {
+                return out;
+            }
+            let value = if width < top - 1 {
+                let shift = 32 - width;
+                ((value << shift) as i32) >> shift
+            } else if sixteen_bit {
+                value as u16 as i16 as i32
+            } else {
+                value as u8 as i8 as i32
+            };
+            if sixteen_bit {
+                d1 = i32::from((d1 + value) as i16);
+                d2 = i32::from((d2 + d1) as i16);
+            } else {
+                d1 = i32::from((d1 + value) as i8);
+                d2 = i32::from((d2 + d1) as i8);
+            }
+            out.push(if it215 { d2 } else { d1 } as f32 / scale);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack `values` at `width` bits each, lowest bit first.
+    fn pack(values: &[(u32, u32)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut bit = 0;
+        for &(value, width) in values {
+            for i in 0..width {
+                if bit / 8 == bytes.len() {
+                    bytes.push(0);
+                }
+                bytes[bit / 8] |= (((value >> i) & 1) as u8) << (bit % 8);
+                bit += 1;
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn unpacks_compressed_samples() {
+        // 9-bit deltas 10, -3; a 9-bit value with the top bit set switches
+        // to width 4; then deltas 2, -1.
+        let block = pack(&[(10, 9), (0xfd, 9), (0x103, 9), (2, 4), (0xf, 4)]);
+        let mut data = (block.len() as u16).to_le_bytes().to_vec();
+        data.extend(&block);
+        let out = decompress(&data, 4, false, false);
+        let expect: Vec<f32> = [10.0, 7.0, 9.0, 8.0].iter().map(|v| v / 128.0).collect();
+        assert_eq!(out, expect);
+        // IT 2.15 integrates twice.
+        let out = decompress(&data, 4, false, true);
+        let expect: Vec<f32> = [10.0, 17.0, 26.0, 34.0].iter().map(|v| v / 128.0).collect();
+        assert_eq!(out, expect);
+    }
+}
@@ -0,0 +1,689 @@
+//! Playing a [`Song`] into stereo float: rows and ticks, the common effects,
+//! volume envelopes and fadeout, and linearly interpolated sample playback
+//! with forward and ping-pong loops. Anything rarer (tremolo, retrigger,
+//! pattern loops, filters, new-note actions) is ignored, which leaves a
+//! preview recognisable if not exact.
+
+use std::collections::HashSet;
+
+use super::song::{Cell, Effect, NoteEvent, Slides, Song, MIDDLE_NOTE, ORDER_END, ORDER_SKIP};
+
+/// Amiga period of [`MIDDLE_NOTE`], and the linear-slide period scale
+/// (64 per semitone).
+const MIDDLE_PERIOD: f64 = 428.0;
+const LINEAR_BASE: f64 = 7680.0;
+
+/// Headroom for channels playing at full volume together.
+const MIX_GAIN: f32 = 0.5;
+
+#[derive(Default)]
+struct Channel {
+    sample: Option<usize>,
+    instrument: Option<usize>,
+    playing: bool,
+    position: f64,
+    backwards: bool,
+    c5_speed: f64,
+    period: f64,
+    target: f64,
+    /// 0..=64.
+    volume: i32,
+    pan: f32,
+    held: bool,
+    fade: f32,
+    envelope_tick: u32,
+
+    effect: Effect,
+    volume_effect: Effect,
+    /// Semitones the arpeggio adds this tick.
+    arpeggio: i32,
+    vibrato_offset: f64,
+    vibrato_position: u32,
+    cut_at: Option<u32>,
+    off_at: Option<u32>,
+    delayed: Option<(u32, Cell)>,
+
+    last_arpeggio: u8,
+    last_porta: u8,
+    last_tone_speed: u8,
+    last_vibrato: (u8, u8),
+    last_slide: u8,
+    last_offset: u8,
+}
+
+pub(crate) struct Player<'a> {
+    song: &'a Song,
+    rate: u32,
+    channels: Vec<Channel>,
+    order: usize,
+    row: usize,
+    tick: u32,
+    speed: u32,
+    tempo: u32,
+    pattern_delay: u32,
+    global_volume: f32,
+    jump: Option<usize>,
+    break_row: Option<usize>,
+    visited: HashSet<(usize, usize)>,
+    finished: bool,
+    /// Output frames until the next tick.
+    tick_frames: usize,
+}
+
+impl<'a> Player<'a> {
+    pub(crate) fn new(song: &'a Song, rate: u32) -> Self {
+        let channels = song
+            .panning
+            .iter()
+            .map(|&pan| Channel {
+                pan,
+                ..Channel::default()
+            })
+            .collect();
+        let mut player = Player {
+            song,
+            rate,
+            channels,
+            order: 0,
+            row: 0,
+            tick: 0,
+            speed: u32::from(song.speed.max(1)),
+            tempo: u32::from(song.tempo.max(32)),
+            pattern_delay: 0,
+            global_volume: song.global_volume,
+            jump: None,
+            break_row: None,
+            visited: HashSet::new(),
+            finished: false,
+            tick_frames: 0,
+        };
+        player.finished = !player.enter_order(0);
+        player
+    }
+
+    /// Add up to `frames` of the song, stereo interleaved, into `out` (or,
+    /// with `None`, just count them). Returns how many frames there were; fewer
+    /// than asked means the song ended or started repeating itself.
+    pub(crate) fn render(&mut self, mut out: Option<&mut [f32]>, frames: usize) -> usize {
+        let mut done = 0;
+        while done < frames {
+            if self.tick_frames == 0 && !self.next_tick() {
+                break;
+            }
+            let n = self.tick_frames.min(frames - done);
+            if let Some(out) = out.as_deref_mut() {
+                let span = &mut out[done * 2..(done + n) * 2];
+                for c in 0..self.channels.len() {
+                    self.mix(c, span);
+                }
+                for s in span {
+                    *s = s.clamp(-1.0, 1.0);
+                }
+            }
+            self.tick_frames -= n;
+            done += n;
+        }
+        done
+    }
+
+    /// Move to the first pattern at or after `order`; false past the end.
+    fn enter_order(&mut self, mut order: usize) -> bool {
+        loop {
+            match self.song.orders.get(order) {
+                None | Some(&ORDER_END) => return false,
+                Some(&ORDER_SKIP) => order += 1,
+                Some(&p) if usize::from(p) >= self.song.patterns.len() => order += 1,
+                Some(_) => {
+                    self.order = order;
+                    return true;
+                }
+            }
+        }
+    }
+
+    fn pattern_rows(&self) -> usize {
+        self.song.patterns[usize::from(self.song.orders[self.order])].rows
+    }
+
+    /// Run one tick's worth of events; false once the song is over.
+    fn next_tick(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        if self.tick == 0 {
+            if !self.visited.insert((self.order, self.row)) {
+                self.finished = true;
+                return false;
+            }
+            self.start_row();
+        } else {
+            for c in 0..self.channels.len() {
+                self.tick_effects(c);
+            }
+        }
+        for c in 0..self.channels.len() {
+            self.advance_envelope(c);
+        }
+        // Tempo is in units of 2.5 ticks a second.
+        self.tick_frames = (self.rate as usize * 5 / (self.tempo as usize * 2)).max(1);
+
+        self.tick += 1;
+        if self.tick >= self.speed * (1 + self.pattern_delay) {
+            self.tick = 0;
+            self.pattern_delay = 0;
+            self.next_row();
+        }
+        true
+    }
+
+    fn next_row(&mut self) {
+        let (jump, break_row) = (self.jump.take(), self.break_row.take());
+        let entered = match (jump, break_row) {
+            (Some(order), row) => {
+                self.row = row.unwrap_or(0);
+                self.enter_order(order)
+            }
+            (None, Some(row)) => {
+                self.row = row;
+                self.enter_order(self.order + 1)
+            }
+            (None, None) => {
+                self.row += 1;
+                if self.row < self.pattern_rows() {
+                    true
+                } else {
+                    self.row = 0;
+                    self.enter_order(self.order + 1)
+                }
+            }
+        };
+        if !entered {
+            self.finished = true;
+        } else if self.row >= self.pattern_rows() {
+            self.row = 0;
+        }
+    }
+
+    fn start_row(&mut self) {
+        let song = self.song;
+        let pattern = &song.patterns[usize::from(song.orders[self.order])];
+        let width = song.channels;
+        for c in 0..self.channels.len() {
+            let cell = pattern.cells[self.row * width + c];
+            let ch = &mut self.channels[c];
+            ch.arpeggio = 0;
+            ch.vibrato_offset = 0.0;
+            ch.cut_at = None;
+            ch.off_at = None;
+            ch.delayed = None;
+            match cell.effect {
+                Effect::NoteDelay(delay) if delay > 0 => {
+                    ch.effect = Effect::None;
+                    ch.volume_effect = Effect::None;
+                    ch.delayed = Some((u32::from(delay), cell));
+                }
+                _ => self.play_cell(c, cell),
+            }
+        }
+    }
+
+    /// A cell's note, instrument, volume and first-tick effects.
+    fn play_cell(&mut self, c: usize, cell: Cell) {
+        let song = self.song;
+        let ch = &mut self.channels[c];
+        ch.effect = cell.effect;
+        ch.volume_effect = cell.volume_effect;
+        if cell.instrument != 0 && usize::from(cell.instrument) <= song.instruments.len() {
+            ch.instrument = Some(usize::from(cell.instrument) - 1);
+        }
+        let sliding = [cell.effect, cell.volume_effect]
+            .iter()
+            .any(|e| matches!(e, Effect::TonePorta(_) | Effect::TonePortaVolumeSlide(_)));
+
+        let mut triggered = false;
+        match cell.note {
+            Some(NoteEvent::Note(note)) => {
+                let mapped = ch
+                    .instrument
+                    .and_then(|i| song.instruments[i].keymap.get(usize::from(note)).copied());
+                if let Some((note, Some(sample))) = mapped {
+                    let period = period_of(song.slides, note);
+                    if sliding && ch.playing {
+                        ch.target = period;
+                    } else if let Some(s) = song.samples.get(sample) {
+                        ch.sample = Some(sample);
+                        ch.c5_speed = s.c5_speed;
+                        ch.period = period;
+                        ch.target = period;
+                        ch.position = 0.0;
+                        ch.backwards = false;
+                        ch.playing = !s.data.is_empty();
+                        ch.held = true;
+                        ch.fade = 1.0;
+                        ch.envelope_tick = 0;
+                        ch.vibrato_position = 0;
+                        if let Some(pan) = s.pan {
+                            ch.pan = pan;
+                        }
+                        triggered = true;
+                    }
+                }
+            }
+            Some(NoteEvent::Off) => release(song, ch),
+            Some(NoteEvent::Cut) => ch.playing = false,
+            None => {}
+        }
+        if cell.instrument != 0 {
+            if let Some(s) = ch.sample.and_then(|s| song.samples.get(s)) {
+                ch.volume = i32::from(s.volume);
+            }
+        }
+        if let Some(volume) = cell.volume {
+            ch.volume = i32::from(volume);
+        }
+        for effect in [cell.volume_effect, cell.effect] {
+            self.first_tick_effect(c, effect, triggered);
+        }
+    }
+
+    fn first_tick_effect(&mut self, c: usize, effect: Effect, triggered: bool) {
+        let unit = slide_unit(self.song.slides);
+        let ch = &mut self.channels[c];
+        match effect {
+            Effect::Arpeggio(p) => {
+                remember(&mut ch.last_arpeggio, p);
+            }
+            Effect::PortaUp(p) | Effect::PortaDown(p) => {
+                let p = remember(&mut ch.last_porta, p);
+                let fine = match p >> 4 {
+                    0xf => f64::from(p & 0x0f) * unit,
+                    0xe => f64::from(p & 0x0f) * unit / 4.0,
+                    _ => 0.0,
+                };
+                if matches!(effect, Effect::PortaUp(_)) {
+                    ch.period -= fine;
+                } else {
+                    ch.period += fine;
+                }
+            }
+            Effect::TonePorta(p) => {
+                remember(&mut ch.last_tone_speed, p);
+            }
+            Effect::Vibrato(p) => set_vibrato(ch, p),
+            Effect::VolumeSlide(p) => fine_slide(ch, p),
+            Effect::FineVolume(delta) => ch.volume = (ch.volume + i32::from(delta)).clamp(0, 64),
+            Effect::TonePortaVolumeSlide(p) | Effect::VibratoVolumeSlide(p) => fine_slide(ch, p),
+            Effect::Pan(pan) => ch.pan = pan.clamp(-1.0, 1.0),
+            Effect::SampleOffset(p) => {
+                let p = remember(&mut ch.last_offset, p);
+                if triggered {
+                    ch.position = f64::from(p) * 256.0;
+                    let len = ch
+                        .sample
+                        .and_then(|s| self.song.samples.get(s))
+                        .map_or(0, |s| s.data.len());
+                    ch.playing &= (ch.position as usize) < len;
+                }
+            }
+            Effect::Jump(order) => self.jump = Some(usize::from(order)),
+            Effect::Break(row) => {
+                let row = if self.song.decimal_breaks {
+                    (row >> 4) * 10 + (row & 0x0f)
+                } else {
+                    row
+                };
+                self.break_row = Some(usize::from(row));
+            }
+            Effect::Speed(speed) => self.speed = u32::from(speed.max(1)),
+            Effect::Tempo(tempo) => self.tempo = u32::from(tempo.max(32)),
+            Effect::GlobalVolume(v) => {
+                self.global_volume = (f32::from(v) / self.song.global_volume_max).min(1.0);
+            }
+            Effect::NoteCut(0) => ch.volume = 0,
+            Effect::NoteCut(tick) => ch.cut_at = Some(u32::from(tick)),
+            Effect::KeyOff(0) => release(self.song, ch),
+            Effect::KeyOff(tick) => ch.off_at = Some(u32::from(tick)),
+            Effect::PatternDelay(rows) => {
+                if self.pattern_delay == 0 {
+                    self.pattern_delay = u32::from(rows);
+                }
+            }
+            Effect::NoteDelay(_) | Effect::None => {}
+        }
+    }
+
+    /// The per-tick part of a channel's effects, on every tick but the
+    /// first of a row.
+    fn tick_effects(&mut self, c: usize) {
+        let song = self.song;
+        let tick = self.tick % self.speed;
+        if let Some((at, cell)) = self.channels[c].delayed {
+            if tick == at {
+                self.channels[c].delayed = None;
+                self.play_cell(c, cell);
+                return;
+            }
+        }
+        let unit = slide_unit(song.slides);
+        let ch = &mut self.channels[c];
+        if ch.cut_at == Some(tick) {
+            ch.volume = 0;
+        }
+        if ch.off_at == Some(tick) {
+            release(song, ch);
+        }
+        for effect in [ch.volume_effect, ch.effect] {
+            match effect {
+                Effect::Arpeggio(_) => {
+                    let p = ch.last_arpeggio;
+                    ch.arpeggio = i32::from(match tick % 3 {
+                        0 => 0,
+                        1 => p >> 4,
+                        _ => p & 0x0f,
+                    });
+                }
+                Effect::PortaUp(_) | Effect::PortaDown(_) if ch.last_porta < 0xe0 => {
+                    let amount = f64::from(ch.last_porta) * unit;
+                    if matches!(effect, Effect::PortaUp(_)) {
+                        ch.period -= amount;
+                    } else {
+                        ch.period += amount;
+                    }
+                }
+                Effect::TonePorta(_) | Effect::TonePortaVolumeSlide(_) => {
+                    let speed = f64::from(ch.last_tone_speed) * unit;
+                    ch.period = if ch.period < ch.target {
+                        (ch.period + speed).min(ch.target)
+                    } else {
+                        (ch.period - speed).max(ch.target)
+                    };
+                    if let Effect::TonePortaVolumeSlide(p) = effect {
+                        slide(ch, p);
+                    }
+                }
+                Effect::Vibrato(_) | Effect::VibratoVolumeSlide(_) => {
+                    let (speed, depth) = ch.last_vibrato;
+                    ch.vibrato_position = ch.vibrato_position.wrapping_add(u32::from(speed));
+                    let phase = f64::from(ch.vibrato_position % 64) / 64.0;
+                    ch.vibrato_offset =
+                        (phase * std::f64::consts::TAU).sin() * f64::from(depth) * 2.0 * unit;
+                    if let Effect::VibratoVolumeSlide(p) = effect {
+                        slide(ch, p);
+                    }
+                }
+                Effect::VolumeSlide(p) => slide(ch, p),
+                _ => {}
+            }
+        }
+        ch.period = ch.period.clamp(1.0, 1e5);
+    }
+
+    /// Step the channel's envelope and fadeout on by a tick.
+    fn advance_envelope(&mut self, c: usize) {
+        let ch = &mut self.channels[c];
+        let Some(instrument) = ch.instrument.map(|i| &self.song.instruments[i]) else {
+            return;
+        };
+        if let Some(envelope) = &instrument.envelope {
+            ch.envelope_tick = envelope.advance(ch.envelope_tick, ch.held);
+            if !ch.held {
+                ch.fade -= instrument.fadeout;
+                if ch.fade <= 0.0 {
+                    ch.playing = false;
+                }
+            }
+        }
+    }
+
+    /// Add channel `c`'s sample into `out` (stereo) for its length.
+    fn mix(&mut self, c: usize, out: &mut [f32]) {
+        let song = self.song;
+        let ch = &mut self.channels[c];
+        let Some(sample) = ch.sample.and_then(|s| song.samples.get(s)) else {
+            return;
+        };
+        if !ch.playing || ch.volume == 0 {
+            return;
+        }
+        let envelope = ch
+            .instrument
+            .and_then(|i| song.instruments[i].envelope.as_ref())
+            .map_or(1.0, |e| e.level(ch.envelope_tick));
+        let volume = ch.volume as f32 / 64.0
+            * envelope
+            * ch.fade.max(0.0)
+            * self.global_volume
+            * sample.global_volume
+            * MIX_GAIN;
+        let left = (1.0 - ch.pan).min(1.0) * volume;
+        let right = (1.0 + ch.pan).min(1.0) * volume;
+        let pitch = pitch_of(song.slides, ch.period + ch.vibrato_offset)
+            * 2f64.powf(f64::from(ch.arpeggio) / 12.0);
+        let step = ch.c5_speed * pitch / f64::from(self.rate);
+        let data = &sample.data;
+
+        for frame in out.chunks_exact_mut(2) {
+            let i = ch.position as usize;
+            let Some(&s0) = data.get(i) else {
+                ch.playing = false;
+                return;
+            };
+            let next = match sample.repeat {
+                Some(l) if i + 1 >= l.end => data[l.start],
+                _ => data.get(i + 1).copied().unwrap_or(0.0),
+            };
+            let frac = (ch.position - i as f64) as f32;
+            let s = s0 + (next - s0) * frac;
+            frame[0] += s * left;
+            frame[1] += s * right;
+
+            if ch.backwards {
+                ch.position -= step;
+            } else {
+                ch.position += step;
+            }
+            match sample.repeat {
+                Some(l) => {
+                    let (start, end) = (l.start as f64, l.end as f64);
+                    if l.ping_pong {
+                        if !ch.backwards && ch.position >= end {
+                            ch.position = (2.0 * end - ch.position - 1.0).max(start);
+                            ch.backwards = true;
+                        } else if ch.backwards && ch.position < start {
+                            ch.position = (2.0 * start - ch.position).min(end - 1.0);
+                            ch.backwards = false;
+                        }
+                    } else if ch.position >= end {
+                        ch.position = start + (ch.position - start) % (end - start);
+                    }
+                }
+                None if ch.position >= data.len() as f64 => {
+                    ch.playing = false;
+                    return;
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// `p`, or the last one when it's 0; remembered either way.
+fn remember(last: &mut u8, p: u8) -> u8 {
+    if p != 0 {
+        *last = p;
+    }
+    *last
+}
+
+fn set_vibrato(ch: &mut Channel, p: u8) {
+    if p >> 4 != 0 {
+        ch.last_vibrato.0 = p >> 4;
+    }
+    if p & 0x0f != 0 {
+        ch.last_vibrato.1 = p & 0x0f;
+    }
+}
+
+/// The first-tick half of a volume slide: the fine slides.
+fn fine_slide(ch: &mut Channel, p: u8) {
+    let p = remember(&mut ch.last_slide, p);
+    let (up, down) = (p >> 4, p & 0x0f);
+    if down == 0x0f && up != 0 {
+        ch.volume = (ch.volume + i32::from(up)).min(64);
+    } else if up == 0x0f && down != 0 {
+        ch.volume = (ch.volume - i32::from(down)).max(0);
+    }
+}
+
+/// The per-tick half of a volume slide.
+fn slide(ch: &mut Channel, p: u8) {
+    let p = if p == 0 { ch.last_slide } else { p };
+    let (up, down) = (p >> 4, p & 0x0f);
+    if (down == 0x0f && up != 0) || (up == 0x0f && down != 0) {
+        return;
+    }
+    ch.volume = if up != 0 {
+        (ch.volume + i32::from(up)).min(64)
+    } else {
+        (ch.volume - i32::from(down)).max(0)
+    };
+}
+
+/// Let go of the note: envelopes leave their sustain and fade out, and a
+/// note without one stops.
+fn release(song: &Song, ch: &mut Channel) {
+    ch.held = false;
+    let enveloped = ch
+        .instrument
+        .is_some_and(|i| song.instruments[i].envelope.is_some());
+    if !enveloped {
+        ch.playing = false;
+    }
+}
+
+/// Period units a slide parameter of 1 moves.
+fn slide_unit(slides: Slides) -> f64 {
+    match slides {
+        Slides::Amiga => 1.0,
+        Slides::Linear => 4.0,
+    }
+}
+
+fn period_of(slides: Slides, note: u8) -> f64 {
+    let semitones = f64::from(note) - f64::from(MIDDLE_NOTE);
+    match slides {
+        Slides::Amiga => MIDDLE_PERIOD * 2f64.powf(-semitones / 12.0),
+        Slides::Linear => LINEAR_BASE - 64.0 * f64::from(note),
+    }
+}
+
+/// Playback rate relative to [`MIDDLE_NOTE`]'s at `period`.
+fn pitch_of(slides: Slides, period: f64) -> f64 {
+    match slides {
+        Slides::Amiga => MIDDLE_PERIOD / period.max(1.0),
+        Slides::Linear => 2f64.powf((LINEAR_BASE - period) / 768.0 - 5.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::song::{Instrument, Pattern, Sample};
+    use super::*;
+
+    /// A one-channel song of `rows` rows at speed 1, tempo 125 (20 ms a row
+    /// at 1 kHz output), playing a square wave.
+    fn song(cells: &[(usize, Cell)], rows: usize, orders: Vec<u8>) -> Song {
+        let mut pattern = Pattern::empty(rows, 1);
+        for &(row, cell) in cells {
+            pattern.cells[row] = cell;
+        }
+        Song {
+            title: "test".into(),
+            channels: 1,
+            orders,
+            patterns: vec![pattern],
+            instruments: vec![Instrument::of_sample(0)],
+            samples: vec![Sample {
+                name: "square".into(),
+                data: (0..100)
+                    .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+                    .collect(),
+                repeat: None,
+                volume: 64,
+                global_volume: 1.0,
+                pan: None,
+                c5_speed: 1000.0,
+            }],
+            speed: 1,
+            tempo: 125,
+            global_volume: 1.0,
+            global_volume_max: 64.0,
+            panning: vec![0.0],
+            slides: Slides::Linear,
+            decimal_breaks: false,
+        }
+    }
+
+    fn note() -> Cell {
+        Cell {
+            note: Some(NoteEvent::Note(MIDDLE_NOTE)),
+            instrument: 1,
+            ..Cell::default()
+        }
+    }
+
+    #[test]
+    fn plays_rows_until_the_song_repeats() {
+        let song = song(&[(0, note())], 4, vec![0, ORDER_SKIP, 0]);
+        let mut player = Player::new(&song, 1000);
+        let mut out = vec![0.0; 1000];
+        // Two passes through four 20-frame rows, then the order list ends.
+        assert_eq!(player.render(Some(&mut out), 500), 160);
+        assert_eq!(&out[..4], &[0.5, 0.5, -0.5, -0.5]);
+        // Struck again on the second pass, and cut short where the song ends.
+        assert_eq!(&out[160..164], &[0.5, 0.5, -0.5, -0.5]);
+        assert_eq!(out[319], -0.5);
+        assert_eq!(out[320], 0.0);
+
+        // A jump back to the start is where a looping song ends.
+        let jump = Cell {
+            effect: Effect::Jump(0),
+            ..Cell::default()
+        };
+        let song = song_with(&[(0, note()), (1, jump)]);
+        assert_eq!(Player::new(&song, 1000).render(None, 500), 40);
+    }
+
+    fn song_with(cells: &[(usize, Cell)]) -> Song {
+        song(cells, 4, vec![0])
+    }
+
+    #[test]
+    fn applies_volume_and_pitch_effects() {
+        // Half volume, then a cut on the next row.
+        let quiet = Cell {
+            volume: Some(32),
+            ..note()
+        };
+        let cut = Cell {
+            note: Some(NoteEvent::Cut),
+            ..Cell::default()
+        };
+        let song = song_with(&[(0, quiet), (1, cut)]);
+        let mut out = vec![0.0; 160];
+        Player::new(&song, 1000).render(Some(&mut out), 80);
+        assert_eq!(out[0], 0.25);
+        assert!(out[40..].iter().all(|&s| s == 0.0));
+
+        // An octave up plays the 100-frame sample out in 50.
+        let high = Cell {
+            note: Some(NoteEvent::Note(MIDDLE_NOTE + 12)),
+            ..note()
+        };
+        let song = song_with(&[(0, high)]);
+        let mut out = vec![0.0; 160];
+        Player::new(&song, 1000).render(Some(&mut out), 80);
+        assert!(out[..100].iter().all(|&s| s != 0.0));
+        assert!(out[100..].iter().all(|&s| s == 0.0));
+    }
+}
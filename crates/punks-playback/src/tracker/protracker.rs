@@ -0,0 +1,158 @@
+//! ProTracker `.mod` and its relatives: 31 samples and four to 32 channels
+//! named by the tag at 1080 (`M.K.`, `6CHN`, `16CH`, ...), or the original
+//! Soundtracker's 15 samples and no tag.
+
+use super::song::{
+    tuned, Bytes, Cell, Effect, Instrument, Loop, NoteEvent, Pattern, Sample, Slides, Song,
+    MIDDLE_NOTE,
+};
+use crate::PlaybackError;
+
+const ROWS: usize = 64;
+/// The period of [`MIDDLE_NOTE`]: ProTracker's C-2.
+const MIDDLE_PERIOD: f64 = 428.0;
+
+pub(crate) fn load(data: &[u8]) -> Result<Song, PlaybackError> {
+    let b = Bytes(data);
+    let (samples, channels) = match b.slice(1080, 4).ok().and_then(channels_of) {
+        Some(channels) => (31, channels),
+        None => (15, 4),
+    };
+    let orders_at = 20 + samples * 30;
+    let length = usize::from(b.u8(orders_at)?).clamp(1, 128);
+    let all_orders = b.slice(orders_at + 2, 128)?;
+    let pattern_count = usize::from(all_orders.iter().copied().max().unwrap_or(0)) + 1;
+    let patterns_at = orders_at + 130 + if samples == 31 { 4 } else { 0 };
+
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for p in 0..pattern_count {
+        let raw = b.slice(patterns_at + p * ROWS * channels * 4, ROWS * channels * 4)?;
+        let cells = raw.chunks_exact(4).map(cell).collect();
+        patterns.push(Pattern { rows: ROWS, cells });
+    }
+
+    let mut sample_at = patterns_at + pattern_count * ROWS * channels * 4;
+    let mut song_samples = Vec::with_capacity(samples);
+    for s in 0..samples {
+        let header = 20 + s * 30;
+        let len = usize::from(b.u16_be(header + 22)?) * 2;
+        let finetune = (b.u8(header + 24)? & 0x0f) as i8;
+        let finetune = if finetune > 7 {
+            finetune - 16
+        } else {
+            finetune
+        };
+        let repeat_start = usize::from(b.u16_be(header + 26)?) * 2;
+        let repeat_len = usize::from(b.u16_be(header + 28)?) * 2;
+        let data = b.pcm(sample_at, len, false, true, false);
+        sample_at += len;
+        song_samples.push(Sample {
+            name: b.text(header, 22)?,
+            repeat: (repeat_len > 2)
+                .then(|| Loop::new(repeat_start, repeat_start + repeat_len, data.len(), false))
+                .flatten(),
+            data,
+            volume: b.u8(header + 25)?.min(64),
+            global_volume: 1.0,
+            pan: None,
+            c5_speed: tuned(f64::from(finetune) / 8.0),
+        });
+    }
+
+    Ok(Song {
+        title: b.text(0, 20)?,
+        channels,
+        orders: all_orders[..length].to_vec(),
+        patterns,
+        instruments: (0..song_samples.len()).map(Instrument::of_sample).collect(),
+        samples: song_samples,
+        speed: 6,
+        tempo: 125,
+        global_volume: 1.0,
+        global_volume_max: 64.0,
+        // The Amiga's hard left-right-right-left, narrowed for headphones.
+        panning: (0..channels)
+            .map(|c| if matches!(c % 4, 0 | 3) { -0.5 } else { 0.5 })
+            .collect(),
+        slides: Slides::Amiga,
+        decimal_breaks: true,
+    })
+}
+
+/// Channels named by a 31-sample module's tag.
+fn channels_of(tag: &[u8]) -> Option<usize> {
+    let digits = |s: &[u8]| std::str::from_utf8(s).ok()?.parse::<usize>().ok();
+    match tag {
+        b"M.K." | b"M!K!" | b"M&K!" | b"FLT4" | b"4CHN" | b"N.T." => Some(4),
+        b"FLT8" | b"CD81" | b"OKTA" | b"OCTA" => Some(8),
+        [n, b'C', b'H', b'N'] => digits(&[*n]),
+        [a, b, b'C', b'H'] | [a, b, b'C', b'N'] => digits(&[*a, *b]),
+        _ => None,
+    }
+    .filter(|n| (1..=32).contains(n))
+}
+
+fn cell(raw: &[u8]) -> Cell {
+    let instrument = u16::from((raw[0] & 0xf0) | (raw[2] >> 4));
+    let period = (u16::from(raw[0] & 0x0f) << 8) | u16::from(raw[1]);
+    let note = (period > 0).then(|| {
+        let n = f64::from(MIDDLE_NOTE) + 12.0 * (MIDDLE_PERIOD / f64::from(period)).log2();
+        NoteEvent::Note(n.round().clamp(0.0, 119.0) as u8)
+    });
+    let (effect, volume) = effect(raw[2] & 0x0f, raw[3]);
+    Cell {
+        note,
+        instrument,
+        volume,
+        effect,
+        volume_effect: Effect::None,
+    }
+}
+
+/// A ProTracker effect as the player's, with `Cxx` as a volume. Also serves
+/// Fast Tracker's, which share the numbering.
+pub(crate) fn effect(command: u8, param: u8) -> (Effect, Option<u8>) {
+    let (x, y) = (param >> 4, param & 0x0f);
+    let effect = match command {
+        0x0 if param != 0 => Effect::Arpeggio(param),
+        0x1 if param != 0 => Effect::PortaUp(param.min(0xdf)),
+        0x2 if param != 0 => Effect::PortaDown(param.min(0xdf)),
+        0x3 => Effect::TonePorta(param),
+        0x4 => Effect::Vibrato(param),
+        0x5 if param != 0 => Effect::TonePortaVolumeSlide(slide(x, y)),
+        0x5 => Effect::TonePorta(0),
+        0x6 if param != 0 => Effect::VibratoVolumeSlide(slide(x, y)),
+        0x6 => Effect::Vibrato(0),
+        0x8 => Effect::Pan(f32::from(param) / 127.5 - 1.0),
+        0x9 => Effect::SampleOffset(param),
+        0xa if param != 0 => Effect::VolumeSlide(slide(x, y)),
+        0xb => Effect::Jump(param),
+        0xc => return (Effect::None, Some(param.min(64))),
+        0xd => Effect::Break(param),
+        0xe => match x {
+            0x1 if y != 0 => Effect::PortaUp(0xf0 | y),
+            0x2 if y != 0 => Effect::PortaDown(0xf0 | y),
+            0x8 => Effect::Pan(f32::from(y) / 7.5 - 1.0),
+            0xa => Effect::FineVolume(y as i8),
+            0xb => Effect::FineVolume(-(y as i8)),
+            0xc => Effect::NoteCut(y),
+            0xd => Effect::NoteDelay(y),
+            0xe => Effect::PatternDelay(y),
+            _ => Effect::None,
+        },
+        0xf if param == 0 => Effect::None,
+        0xf if param < 0x20 => Effect::Speed(param),
+        0xf => Effect::Tempo(param),
+        _ => Effect::None,
+    };
+    (effect, None)
+}
+
+/// A ProTracker volume slide, which slides up when both halves are set.
+fn slide(up: u8, down: u8) -> u8 {
+    if up != 0 {
+        up << 4
+    } else {
+        down
+    }
+}
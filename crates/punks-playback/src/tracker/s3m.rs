@@ -0,0 +1,195 @@
+//! Scream Tracker 3 `.s3m`: up to 32 channels, samples addressed by
+//! paragraph pointers, packed patterns of 64 rows.
+
+use super::song::{
+    Bytes, Cell, Effect, Instrument, Loop, NoteEvent, Pattern, Sample, Slides, Song, MIDDLE_NOTE,
+};
+use crate::PlaybackError;
+
+const ROWS: usize = 64;
+
+pub(crate) fn is_s3m(data: &[u8]) -> bool {
+    data.get(44..48) == Some(b"SCRM")
+}
+
+pub(crate) fn load(data: &[u8]) -> Result<Song, PlaybackError> {
+    let b = Bytes(data);
+    let orders = usize::from(b.u16(32)?);
+    let sample_count = usize::from(b.u16(34)?);
+    let pattern_count = usize::from(b.u16(36)?);
+    let signed = b.u16(42)? == 1;
+    let stereo = b.u8(51)? & 0x80 != 0;
+    let settings = b.slice(64, 32)?;
+
+    // Enabled channels in order; left ones are 0-7, right ones 8-15.
+    let mut map = [None; 32];
+    let mut panning = Vec::new();
+    for (c, &setting) in settings.iter().enumerate() {
+        if setting < 16 {
+            map[c] = Some(panning.len());
+            panning.push(match (stereo, setting < 8) {
+                (false, _) => 0.0,
+                (true, true) => -0.6,
+                (true, false) => 0.6,
+            });
+        }
+    }
+    let channels = panning.len().max(1);
+    panning.resize(channels, 0.0);
+
+    let order_list = b.slice(96, orders)?.to_vec();
+    let pointers_at = 96 + orders;
+    let pointer = |i: usize| b.u16(pointers_at + i * 2).map(|p| usize::from(p) * 16);
+
+    let pan_table_at = pointers_at + (sample_count + pattern_count) * 2;
+    if b.u8(53)? == 252 {
+        for (c, &setting) in b.slice(pan_table_at, 32)?.iter().enumerate() {
+            if let Some(channel) = map[c] {
+                if setting & 0x20 != 0 {
+                    panning[channel] = f32::from(setting & 0x0f) / 7.5 - 1.0;
+                }
+            }
+        }
+    }
+
+    let mut samples = Vec::with_capacity(sample_count);
+    for i in 0..sample_count {
+        samples.push(sample(&b, pointer(i)?, signed)?);
+    }
+
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for i in 0..pattern_count {
+        let at = pointer(sample_count + i)?;
+        patterns.push(if at == 0 {
+            Pattern::empty(ROWS, channels)
+        } else {
+            pattern(&b, at + 2, channels, &map)?
+        });
+    }
+
+    Ok(Song {
+        title: b.text(0, 28)?,
+        channels,
+        orders: order_list,
+        patterns,
+        instruments: (0..samples.len()).map(Instrument::of_sample).collect(),
+        samples,
+        speed: b.u8(49)?.max(1),
+        tempo: b.u8(50)?.max(32),
+        global_volume: f32::from(b.u8(48)?.min(64)) / 64.0,
+        global_volume_max: 64.0,
+        panning,
+        slides: Slides::Amiga,
+        decimal_breaks: true,
+    })
+}
+
+fn sample(b: &Bytes, at: usize, signed: bool) -> Result<Sample, PlaybackError> {
+    let name = b.text(at + 48, 28)?;
+    let name = if name.is_empty() {
+        b.text(at + 1, 12)?
+    } else {
+        name
+    };
+    if at == 0 || b.u8(at)? != 1 {
+        return Ok(Sample {
+            name,
+            data: Vec::new(),
+            repeat: None,
+            volume: 0,
+            global_volume: 1.0,
+            pan: None,
+            c5_speed: 8363.0,
+        });
+    }
+    let data_at = ((usize::from(b.u8(at + 13)?) << 16) | usize::from(b.u16(at + 14)?)) * 16;
+    let len = b.u32(at + 16)? as usize;
+    let flags = b.u8(at + 31)?;
+    let data = b.pcm(data_at, len, flags & 4 != 0, signed, flags & 2 != 0);
+    Ok(Sample {
+        name,
+        repeat: (flags & 1 != 0)
+            .then(|| {
+                let (start, end) = (b.u32(at + 20).ok()?, b.u32(at + 24).ok()?);
+                Loop::new(start as usize, end as usize, data.len(), false)
+            })
+            .flatten(),
+        data,
+        volume: b.u8(at + 28)?.min(64),
+        global_volume: 1.0,
+        pan: None,
+        c5_speed: f64::from(b.u32(at + 32)?.max(1)),
+    })
+}
+
+fn pattern(
+    b: &Bytes,
+    mut at: usize,
+    channels: usize,
+    map: &[Option<usize>; 32],
+) -> Result<Pattern, PlaybackError> {
+    let mut pattern = Pattern::empty(ROWS, channels);
+    let mut row = 0;
+    while row < ROWS {
+        let what = b.u8(at)?;
+        at += 1;
+        if what == 0 {
+            row += 1;
+            continue;
+        }
+        let mut cell = Cell::default();
+        if what & 0x20 != 0 {
+            cell.note = match b.u8(at)? {
+                255 => None,
+                254 => Some(NoteEvent::Cut),
+                n => Some(NoteEvent::Note(
+                    ((n >> 4) * 12 + (n & 0x0f) + MIDDLE_NOTE - 48).min(119),
+                )),
+            };
+            cell.instrument = u16::from(b.u8(at + 1)?);
+            at += 2;
+        }
+        if what & 0x40 != 0 {
+            cell.volume = Some(b.u8(at)?.min(64));
+            at += 1;
+        }
+        if what & 0x80 != 0 {
+            cell.effect = effect(b.u8(at)?, b.u8(at + 1)?);
+            at += 2;
+        }
+        if let Some(channel) = map[usize::from(what & 0x1f)] {
+            pattern.cells[row * channels + channel] = cell;
+        }
+    }
+    Ok(pattern)
+}
+
+/// A Scream Tracker or Impulse Tracker command (`A` is 1) as the player's.
+pub(crate) fn effect(command: u8, param: u8) -> Effect {
+    let (x, y) = (param >> 4, param & 0x0f);
+    match command {
+        1 if param != 0 => Effect::Speed(param),
+        2 => Effect::Jump(param),
+        3 => Effect::Break(param),
+        4 => Effect::VolumeSlide(param),
+        5 => Effect::PortaDown(param),
+        6 => Effect::PortaUp(param),
+        7 => Effect::TonePorta(param),
+        8 => Effect::Vibrato(param),
+        10 => Effect::Arpeggio(param),
+        11 => Effect::VibratoVolumeSlide(param),
+        12 => Effect::TonePortaVolumeSlide(param),
+        15 => Effect::SampleOffset(param),
+        19 => match x {
+            0x8 => Effect::Pan(f32::from(y) / 7.5 - 1.0),
+            0xc => Effect::NoteCut(y),
+            0xd => Effect::NoteDelay(y),
+            0xe => Effect::PatternDelay(y),
+            _ => Effect::None,
+        },
+        20 if param >= 0x20 => Effect::Tempo(param),
+        22 => Effect::GlobalVolume(param),
+        24 => Effect::Pan(f32::from(param.min(0x80)) / 64.0 - 1.0),
+        _ => Effect::None,
+    }
+}
@@ -0,0 +1,308 @@
+//! The format-neutral song every loader produces and the player reads:
+//! an order list of patterns of cells, instruments mapping notes onto
+//! samples, and the samples as mono float.
+
+use crate::PlaybackError;
+
+/// Notes are numbered in semitones with 60 playing a sample at its
+/// [`Sample::c5_speed`] (ProTracker's C-2, Scream Tracker's and Fast
+/// Tracker's C-4, Impulse Tracker's C-5).
+pub(crate) const MIDDLE_NOTE: u8 = 60;
+pub(crate) const NOTES: usize = 120;
+
+/// Order list entries that aren't patterns.
+pub(crate) const ORDER_SKIP: u8 = 254;
+pub(crate) const ORDER_END: u8 = 255;
+
+/// How pitch slides move: in Amiga periods, or in fractions of a semitone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slides {
+    Amiga,
+    Linear,
+}
+
+pub(crate) struct Song {
+    pub(crate) title: String,
+    pub(crate) channels: usize,
+    pub(crate) orders: Vec<u8>,
+    pub(crate) patterns: Vec<Pattern>,
+    pub(crate) instruments: Vec<Instrument>,
+    pub(crate) samples: Vec<Sample>,
+    pub(crate) speed: u8,
+    pub(crate) tempo: u8,
+    /// 0..1.
+    pub(crate) global_volume: f32,
+    /// What a global volume command's 1.0 is (64 or 128).
+    pub(crate) global_volume_max: f32,
+    /// Per channel, -1 (left) to 1 (right).
+    pub(crate) panning: Vec<f32>,
+    pub(crate) slides: Slides,
+    /// Pattern breaks are written in decimal (`D10` is row ten).
+    pub(crate) decimal_breaks: bool,
+}
+
+pub(crate) struct Pattern {
+    pub(crate) rows: usize,
+    /// `rows` × the song's channels, row by row.
+    pub(crate) cells: Vec<Cell>,
+}
+
+impl Pattern {
+    pub(crate) fn empty(rows: usize, channels: usize) -> Self {
+        Pattern {
+            rows,
+            cells: vec![Cell::default(); rows * channels],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Cell {
+    pub(crate) note: Option<NoteEvent>,
+    /// 1-based; 0 for none.
+    pub(crate) instrument: u16,
+    /// 0..=64.
+    pub(crate) volume: Option<u8>,
+    pub(crate) effect: Effect,
+    /// Effects from a volume column.
+    pub(crate) volume_effect: Effect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NoteEvent {
+    Note(u8),
+    /// Release: envelopes leave their sustain and the note fades.
+    Off,
+    Cut,
+}
+
+/// The effects the player understands, with parameters in Scream Tracker's
+/// encoding where formats differ; a zero parameter repeats the channel's
+/// last one for the effects that remember.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum Effect {
+    #[default]
+    None,
+    Arpeggio(u8),
+    /// `xx` per tick; `Fx` fine and `Ex` extra fine, once per row.
+    PortaUp(u8),
+    PortaDown(u8),
+    TonePorta(u8),
+    Vibrato(u8),
+    /// `x0` up, `0y` down per tick; `xF`/`Fy` fine, once per row.
+    VolumeSlide(u8),
+    /// Once per row, without memory.
+    FineVolume(i8),
+    TonePortaVolumeSlide(u8),
+    VibratoVolumeSlide(u8),
+    /// -1..1.
+    Pan(f32),
+    SampleOffset(u8),
+    Jump(u8),
+    Break(u8),
+    Speed(u8),
+    Tempo(u8),
+    GlobalVolume(u8),
+    NoteCut(u8),
+    NoteDelay(u8),
+    PatternDelay(u8),
+    KeyOff(u8),
+}
+
+pub(crate) struct Instrument {
+    /// For each note: the note to play and the sample (index into
+    /// [`Song::samples`]) it plays.
+    pub(crate) keymap: Vec<(u8, Option<usize>)>,
+    pub(crate) envelope: Option<Envelope>,
+    /// Fraction of full volume lost per tick once released.
+    pub(crate) fadeout: f32,
+}
+
+impl Instrument {
+    /// An instrument that's just `sample`, as in formats without them.
+    pub(crate) fn of_sample(sample: usize) -> Self {
+        Instrument {
+            keymap: (0..NOTES as u8).map(|n| (n, Some(sample))).collect(),
+            envelope: None,
+            fadeout: 0.0,
+        }
+    }
+}
+
+/// A volume envelope.
+pub(crate) struct Envelope {
+    /// Tick and level (0..1), ticks ascending.
+    pub(crate) points: Vec<(u16, f32)>,
+    /// Points held between while the note is down.
+    pub(crate) sustain: Option<(usize, usize)>,
+    pub(crate) repeat: Option<(usize, usize)>,
+}
+
+impl Envelope {
+    /// Level at `tick`.
+    pub(crate) fn level(&self, tick: u32) -> f32 {
+        let Some(&(first_tick, first)) = self.points.first() else {
+            return 1.0;
+        };
+        if tick <= u32::from(first_tick) {
+            return first;
+        }
+        for pair in self.points.windows(2) {
+            let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+            if tick < u32::from(t1) {
+                let span = f32::from(t1.saturating_sub(t0)).max(1.0);
+                return v0 + (v1 - v0) * (tick - u32::from(t0)) as f32 / span;
+            }
+        }
+        self.points.last().map_or(1.0, |p| p.1)
+    }
+
+    /// Where the envelope goes after `tick`: back to a loop's start when it
+    /// reaches the end of the sustain loop (while held) or of the loop.
+    pub(crate) fn advance(&self, tick: u32, held: bool) -> u32 {
+        let next = tick + 1;
+        let span = if held { self.sustain } else { None }.or(self.repeat);
+        if let Some((start, end)) = span {
+            if let (Some(&(start, _)), Some(&(end, _))) =
+                (self.points.get(start), self.points.get(end))
+            {
+                if next > u32::from(end) {
+                    return u32::from(start);
+                }
+            }
+        }
+        next
+    }
+}
+
+pub(crate) struct Sample {
+    pub(crate) name: String,
+    /// Mono, -1..1.
+    pub(crate) data: Vec<f32>,
+    pub(crate) repeat: Option<Loop>,
+    /// 0..=64.
+    pub(crate) volume: u8,
+    /// Impulse Tracker's per-sample volume, 0..1.
+    pub(crate) global_volume: f32,
+    /// -1..1, set on the channel when the sample starts.
+    pub(crate) pan: Option<f32>,
+    /// Playback rate of [`MIDDLE_NOTE`], in Hz.
+    pub(crate) c5_speed: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Loop {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) ping_pong: bool,
+}
+
+impl Loop {
+    /// A loop over `[start, end)` of `len` frames, if it's long enough to be
+    /// one.
+    pub(crate) fn new(start: usize, end: usize, len: usize, ping_pong: bool) -> Option<Self> {
+        let end = end.min(len);
+        (end > start + 1).then_some(Loop {
+            start,
+            end,
+            ping_pong,
+        })
+    }
+}
+
+/// Little-endian reads within a module, failing rather than panicking on
+/// truncated files.
+pub(crate) struct Bytes<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Bytes<'a> {
+    pub(crate) fn slice(&self, at: usize, len: usize) -> Result<&'a [u8], PlaybackError> {
+        at.checked_add(len)
+            .and_then(|end| self.0.get(at..end))
+            .ok_or_else(|| PlaybackError::decode("module is truncated"))
+    }
+
+    pub(crate) fn u8(&self, at: usize) -> Result<u8, PlaybackError> {
+        Ok(self.slice(at, 1)?[0])
+    }
+
+    pub(crate) fn u16(&self, at: usize) -> Result<u16, PlaybackError> {
+        let b = self.slice(at, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u16_be(&self, at: usize) -> Result<u16, PlaybackError> {
+        let b = self.slice(at, 2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&self, at: usize) -> Result<u32, PlaybackError> {
+        let b = self.slice(at, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// A fixed-width, NUL-padded name.
+    pub(crate) fn text(&self, at: usize, len: usize) -> Result<String, PlaybackError> {
+        let raw = self.slice(at, len)?;
+        let raw = &raw[..raw.iter().position(|&b| b == 0).unwrap_or(raw.len())];
+        Ok(raw
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    ' '
+                }
+            })
+            .collect::<String>()
+            .trim()
+            .to_string())
+    }
+
+    /// PCM sample data, as much of it as the file holds: `frames` of 8- or
+    /// 16-bit, signed or not, with stereo samples stored as a left block then
+    /// a right one and mixed down.
+    pub(crate) fn pcm(
+        &self,
+        at: usize,
+        frames: usize,
+        sixteen_bit: bool,
+        signed: bool,
+        stereo: bool,
+    ) -> Vec<f32> {
+        let width = if sixteen_bit { 2 } else { 1 };
+        let channels = if stereo { 2 } else { 1 };
+        let available = self.0.len().saturating_sub(at) / width / channels;
+        let frames = frames.min(available);
+        let read = |i: usize| -> f32 {
+            let i = at + i * width;
+            if sixteen_bit {
+                let v = u16::from_le_bytes([self.0[i], self.0[i + 1]]);
+                let v = if signed {
+                    v as i16
+                } else {
+                    (v ^ 0x8000) as i16
+                };
+                f32::from(v) / 32768.0
+            } else {
+                let v = self.0[i];
+                let v = if signed { v as i8 } else { (v ^ 0x80) as i8 };
+                f32::from(v) / 128.0
+            }
+        };
+        (0..frames)
+            .map(|i| {
+                if stereo {
+                    (read(i) + read(frames + i)) * 0.5
+                } else {
+                    read(i)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Middle C's rate for a sample tuned `semitones` (fractional for finetune)
+/// off 8363 Hz.
+pub(crate) fn tuned(semitones: f64) -> f64 {
+    8363.0 * 2f64.powf(semitones / 12.0)
+}
@@ -0,0 +1,258 @@
+//! Fast Tracker II `.xm`: instruments of several samples split across the
+//! keyboard, volume envelopes, delta-coded sample data and a volume column
+//! with effects of its own.
+
+use super::protracker;
+use super::song::{
+    tuned, Bytes, Effect, Envelope, Instrument, Loop, NoteEvent, Pattern, Sample, Slides, Song,
+    MIDDLE_NOTE, NOTES,
+};
+use crate::PlaybackError;
+
+const MAGIC: &[u8] = b"Extended Module: ";
+
+pub(crate) fn is_xm(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub(crate) fn load(data: &[u8]) -> Result<Song, PlaybackError> {
+    let b = Bytes(data);
+    let length = usize::from(b.u16(64)?).min(256);
+    let channels = usize::from(b.u16(68)?).clamp(1, 64);
+    let pattern_count = usize::from(b.u16(70)?);
+    let instrument_count = usize::from(b.u16(72)?);
+    let linear = b.u16(74)? & 1 != 0;
+    let orders = b.slice(80, length)?.to_vec();
+
+    let mut at = 60 + b.u32(60)? as usize;
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        let header = b.u32(at)? as usize;
+        let rows = usize::from(b.u16(at + 5)?).max(1);
+        let size = usize::from(b.u16(at + 7)?);
+        at += header;
+        patterns.push(pattern(&b, at, size, rows, channels)?);
+        at += size;
+    }
+
+    let mut instruments = Vec::with_capacity(instrument_count);
+    let mut samples = Vec::new();
+    for _ in 0..instrument_count {
+        let (instrument, next) = instrument(&b, at, &mut samples)?;
+        instruments.push(instrument);
+        at = next;
+    }
+
+    Ok(Song {
+        title: b.text(17, 20)?,
+        channels,
+        orders,
+        patterns,
+        instruments,
+        samples,
+        speed: b.u16(76)?.clamp(1, 31) as u8,
+        tempo: b.u16(78)?.clamp(32, 255) as u8,
+        global_volume: 1.0,
+        global_volume_max: 64.0,
+        panning: vec![0.0; channels],
+        slides: if linear {
+            Slides::Linear
+        } else {
+            Slides::Amiga
+        },
+        decimal_breaks: true,
+    })
+}
+
+fn pattern(
+    b: &Bytes,
+    at: usize,
+    size: usize,
+    rows: usize,
+    channels: usize,
+) -> Result<Pattern, PlaybackError> {
+    let mut pattern = Pattern::empty(rows, channels);
+    if size == 0 {
+        return Ok(pattern);
+    }
+    let raw = b.slice(at, size)?;
+    let mut i = 0;
+    let mut next = || {
+        let byte = raw.get(i).copied();
+        i += 1;
+        byte.unwrap_or(0)
+    };
+    for cell in &mut pattern.cells {
+        let first = next();
+        let flags = if first & 0x80 != 0 { first } else { 0x1f };
+        let mut take = |bit: u8, first_is_it: bool| {
+            if flags & bit == 0 {
+                0
+            } else if first_is_it && first & 0x80 == 0 {
+                first
+            } else {
+                next()
+            }
+        };
+        let note = take(1, true);
+        let instrument = take(2, false);
+        let volume = take(4, false);
+        let command = take(8, false);
+        let param = take(16, false);
+
+        cell.note = match note {
+            0 => None,
+            97 => Some(NoteEvent::Off),
+            n => Some(NoteEvent::Note((n - 1 + MIDDLE_NOTE - 48).min(119))),
+        };
+        cell.instrument = u16::from(instrument);
+        (cell.effect, cell.volume) = match command {
+            // Fast Tracker's own, past ProTracker's.
+            0x10 => (Effect::GlobalVolume(param), None),
+            0x14 => (Effect::KeyOff(param), None),
+            0x21 => match param >> 4 {
+                1 => (Effect::PortaUp(0xe0 | (param & 0x0f)), None),
+                2 => (Effect::PortaDown(0xe0 | (param & 0x0f)), None),
+                _ => (Effect::None, None),
+            },
+            0x0a => (Effect::VolumeSlide(xm_slide(param)), None),
+            command => protracker::effect(command, param),
+        };
+        let (x, y) = (volume >> 4, volume & 0x0f);
+        match x {
+            0x1..=0x5 => cell.volume = Some((volume - 0x10).min(64)),
+            0x6 => cell.volume_effect = Effect::VolumeSlide(y),
+            0x7 => cell.volume_effect = Effect::VolumeSlide(y << 4),
+            0x8 => cell.volume_effect = Effect::FineVolume(-(y as i8)),
+            0x9 => cell.volume_effect = Effect::FineVolume(y as i8),
+            0xb => cell.volume_effect = Effect::Vibrato(y),
+            0xc => cell.volume_effect = Effect::Pan(f32::from(y) / 7.5 - 1.0),
+            0xf => cell.volume_effect = Effect::TonePorta(y << 4),
+            _ => {}
+        }
+    }
+    Ok(pattern)
+}
+
+/// Fast Tracker's `Axy`, which remembers its parameter, unlike ProTracker's.
+fn xm_slide(param: u8) -> u8 {
+    if param >> 4 != 0 {
+        param & 0xf0
+    } else {
+        param
+    }
+}
+
+/// The instrument at `at`, its samples appended to `samples`, and where the
+/// next instrument starts.
+fn instrument(
+    b: &Bytes,
+    at: usize,
+    samples: &mut Vec<Sample>,
+) -> Result<(Instrument, usize), PlaybackError> {
+    let header = b.u32(at)? as usize;
+    let count = usize::from(b.u16(at + 27)?);
+    let first = samples.len();
+    if count == 0 {
+        let instrument = Instrument {
+            keymap: (0..NOTES as u8).map(|n| (n, None)).collect(),
+            envelope: None,
+            fadeout: 0.0,
+        };
+        return Ok((instrument, at + header));
+    }
+
+    let sample_header = b.u32(at + 29)? as usize;
+    let keys = b.slice(at + 33, 96)?;
+    let keymap = (0..NOTES)
+        .map(|n| {
+            let key = (n + 48)
+                .checked_sub(usize::from(MIDDLE_NOTE))
+                .and_then(|k| keys.get(k));
+            let sample = key.map(|&s| usize::from(s)).filter(|&s| s < count);
+            (n as u8, sample.map(|s| first + s))
+        })
+        .collect();
+    let envelope_type = b.u8(at + 233)?;
+    let envelope = (envelope_type & 1 != 0)
+        .then(|| -> Result<Envelope, PlaybackError> {
+            let points = usize::from(b.u8(at + 225)?.clamp(1, 12));
+            let point = |i: usize| -> Result<usize, PlaybackError> {
+                Ok(usize::from(b.u8(at + i)?).min(points - 1))
+            };
+            let sustain = point(227)?;
+            Ok(Envelope {
+                points: (0..points)
+                    .map(|p| {
+                        let tick = b.u16(at + 129 + p * 4)?;
+                        let level = f32::from(b.u16(at + 131 + p * 4)?.min(64)) / 64.0;
+                        Ok((tick, level))
+                    })
+                    .collect::<Result<_, PlaybackError>>()?,
+                sustain: (envelope_type & 2 != 0).then_some((sustain, sustain)),
+                repeat: (envelope_type & 4 != 0).then_some((point(228)?, point(229)?)),
+            })
+        })
+        .transpose()?;
+    let fadeout = f32::from(b.u16(at + 239)?) / 65536.0;
+
+    let mut header_at = at + header;
+    let mut headers = Vec::with_capacity(count);
+    for _ in 0..count {
+        headers.push(header_at);
+        header_at += sample_header;
+    }
+    let mut data_at = header_at;
+    for h in headers {
+        let len = b.u32(h)? as usize;
+        let flags = b.u8(h + 14)?;
+        let sixteen_bit = flags & 0x10 != 0;
+        let width = if sixteen_bit { 2 } else { 1 };
+        let mut data = b.pcm(data_at, len / width, sixteen_bit, true, false);
+        undelta(&mut data, sixteen_bit);
+        data_at += len;
+        let (start, repeat_len) = (
+            b.u32(h + 4)? as usize / width,
+            b.u32(h + 8)? as usize / width,
+        );
+        let finetune = f64::from(b.u8(h + 13)? as i8) / 128.0;
+        let relative = f64::from(b.u8(h + 16)? as i8);
+        samples.push(Sample {
+            name: b.text(h + 18, 22)?,
+            repeat: match flags & 3 {
+                0 => None,
+                kind => Loop::new(start, start + repeat_len, data.len(), kind == 2),
+            },
+            data,
+            volume: b.u8(h + 12)?.min(64),
+            global_volume: 1.0,
+            pan: Some(f32::from(b.u8(h + 15)?) / 127.5 - 1.0),
+            c5_speed: tuned(relative + finetune),
+        });
+    }
+
+    let instrument = Instrument {
+        keymap,
+        envelope,
+        fadeout,
+    };
+    Ok((instrument, data_at))
+}
+
+/// Undo the delta coding of Fast Tracker's sample data, which
+/// [`Bytes::pcm`] read as plain signed values.
+fn undelta(data: &mut [f32], sixteen_bit: bool) {
+    if sixteen_bit {
+        let mut acc = 0i16;
+        for s in data {
+            acc = acc.wrapping_add((*s * 32768.0) as i16);
+            *s = f32::from(acc) / 32768.0;
+        }
+    } else {
+        let mut acc = 0i8;
+        for s in data {
+            acc = acc.wrapping_add((*s * 128.0) as i8);
+            *s = f32::from(acc) / 128.0;
+        }
+    }
+}
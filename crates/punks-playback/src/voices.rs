@@ -0,0 +1,377 @@
+//! Layered previews: a stack of samples started on the same frame and mixed
+//! into the output, each through its own gain, for hearing a kick with a top
+//! or a chord built from single notes before committing to them. The layers
+//! share one playhead, so they can never drift apart, and the stack is
+//! swapped in whole, so the callback never sees half of one.
+//!
+//! Polyphonic preview works the other way round: each clip started over
+//! another leaves the one before ringing out in a [`Pool`] of fixed slots,
+//! so a kick can be played and a snare auditioned on top of it.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use crate::MAX_GAIN;
+
+/// Most samples a stack holds.
+pub const MAX_LAYERS: usize = 8;
+
+/// Most clips left ringing out at once under polyphonic preview; starting
+/// another cuts the oldest.
+pub const MAX_VOICES: usize = 8;
+
+/// One layer: a buffer prepared for the output, and its gain.
+pub(crate) struct Voice {
+    samples: Arc<Vec<f32>>,
+    gain: AtomicU32,
+}
+
+/// The layers playing together.
+pub(crate) struct Stack {
+    voices: Vec<Voice>,
+    /// Length of the longest layer, in samples.
+    len: usize,
+}
+
+impl Stack {
+    /// Layers from `(buffer, linear gain)` pairs, all in the output's layout;
+    /// anything past [`MAX_LAYERS`] is left out.
+    pub(crate) fn new(layers: impl IntoIterator<Item = (Arc<Vec<f32>>, f32)>) -> Self {
+        let voices: Vec<Voice> = layers
+            .into_iter()
+            .take(MAX_LAYERS)
+            .map(|(samples, gain)| Voice {
+                samples,
+                gain: AtomicU32::new(gain.clamp(0.0, MAX_GAIN).to_bits()),
+            })
+            .collect();
+        let len = voices.iter().map(|v| v.samples.len()).max().unwrap_or(0);
+        Stack { voices, len }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Change `layer`'s gain while it plays; out-of-range layers are ignored.
+    pub(crate) fn set_gain(&self, layer: usize, gain: f32) {
+        if let Some(voice) = self.voices.get(layer) {
+            voice
+                .gain
+                .store(gain.clamp(0.0, MAX_GAIN).to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Add the layers from `cursor` on into `data`, scaled by `volume`, and
+    /// move the cursor along. Returns whether any layer has more to play.
+    pub(crate) fn mix(&self, data: &mut [f32], cursor: &AtomicUsize, volume: f32) -> bool {
+        let start = cursor.load(Ordering::Relaxed);
+        for voice in &self.voices {
+            let gain = f32::from_bits(voice.gain.load(Ordering::Relaxed)) * volume;
+            let remaining = voice.samples.get(start..).unwrap_or_default();
+            for (dst, &src) in data.iter_mut().zip(remaining) {
+                *dst += src * gain;
+            }
+        }
+        let end = start.saturating_add(data.len());
+        cursor.store(end, Ordering::Relaxed);
+        end < self.len
+    }
+}
+
+/// Names a voice of polyphonic preview, to stop it by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoiceId(pub(crate) u64);
+
+/// A voice still sounding, as [`PlaybackEngine::voices`](crate::PlaybackEngine::voices)
+/// lists them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceInfo {
+    pub id: VoiceId,
+    pub path: PathBuf,
+}
+
+/// A clip carrying on from where it was when another started over it, at
+/// the gain and speed it had, once through to its end.
+pub(crate) struct Released {
+    id: VoiceId,
+    path: PathBuf,
+    samples: Arc<Vec<f32>>,
+    /// Frames it plays up to.
+    end: usize,
+    gain: f32,
+    rate: f32,
+    frame: AtomicUsize,
+    /// How far between `frame` and the next one, as f32 bits.
+    phase: AtomicU32,
+    /// Frames of fade-out left once stopped, over `fade_len`; `usize::MAX`
+    /// while it plays on.
+    fading: AtomicUsize,
+    fade_len: AtomicUsize,
+    done: AtomicBool,
+}
+
+impl Released {
+    /// `samples` in the output's layout, from `(frame, phase)` up to frame
+    /// `end`.
+    pub(crate) fn new(
+        id: VoiceId,
+        path: PathBuf,
+        samples: Arc<Vec<f32>>,
+        (frame, phase): (usize, f32),
+        end: usize,
+        gain: f32,
+        rate: f32,
+    ) -> Self {
+        Released {
+            id,
+            path,
+            samples,
+            end,
+            gain,
+            rate,
+            frame: AtomicUsize::new(frame),
+            phase: AtomicU32::new(phase.to_bits()),
+            fading: AtomicUsize::new(usize::MAX),
+            fade_len: AtomicUsize::new(0),
+            done: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn info(&self) -> VoiceInfo {
+        VoiceInfo {
+            id: self.id,
+            path: self.path.clone(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// Fade out over `frames`, or cut now with 0.
+    fn stop(&self, frames: usize) {
+        if frames == 0 {
+            self.done.store(true, Ordering::Relaxed);
+        } else if self.fading.load(Ordering::Relaxed) == usize::MAX {
+            self.fade_len.store(frames, Ordering::Relaxed);
+            self.fading.store(frames, Ordering::Relaxed);
+        }
+    }
+
+    /// Add the next `data.len()` samples into `data`.
+    fn mix(&self, data: &mut [f32], channels: usize) {
+        if self.is_done() {
+            return;
+        }
+        let channels = channels.max(1);
+        let frames = (self.samples.len() / channels).min(self.end);
+        let mut frame = self.frame.load(Ordering::Relaxed);
+        let mut phase = f32::from_bits(self.phase.load(Ordering::Relaxed));
+        let mut fading = self.fading.load(Ordering::Relaxed);
+        let fade_len = self.fade_len.load(Ordering::Relaxed).max(1);
+        for out in data.chunks_exact_mut(channels) {
+            if frame >= frames || fading == 0 {
+                self.done.store(true, Ordering::Relaxed);
+                break;
+            }
+            let mut gain = self.gain;
+            if fading != usize::MAX {
+                gain *= fading as f32 / fade_len as f32;
+                fading -= 1;
+            }
+            let here = &self.samples[frame * channels..][..channels];
+            let next = &self.samples[(frame + 1).min(frames - 1) * channels..][..channels];
+            for ((dst, &a), &b) in out.iter_mut().zip(here).zip(next) {
+                *dst += (a + (b - a) * phase) * gain;
+            }
+            phase += self.rate;
+            let whole = phase.floor();
+            frame += whole as usize;
+            phase -= whole;
+        }
+        self.frame.store(frame, Ordering::Relaxed);
+        self.phase.store(phase.to_bits(), Ordering::Relaxed);
+        self.fading.store(fading, Ordering::Relaxed);
+    }
+}
+
+/// The slots released clips ring out from: fixed, so the callback never
+/// allocates, and each swapped whole, so it never sees half a voice. Only
+/// the engine fills and empties them.
+pub(crate) struct Pool {
+    slots: [ArcSwapOption<Released>; MAX_VOICES],
+    paused: AtomicBool,
+}
+
+impl Pool {
+    pub(crate) fn new() -> Self {
+        Pool {
+            slots: std::array::from_fn(|_| ArcSwapOption::empty()),
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Start `voice` in a free slot, or in place of the oldest; returns
+    /// what it replaced, for the caller to drop off the audio thread.
+    pub(crate) fn add(&self, voice: Arc<Released>) -> Option<Arc<Released>> {
+        let slot = self
+            .slots
+            .iter()
+            .find(|slot| slot.load().as_ref().is_none_or(|v| v.is_done()))
+            .or_else(|| {
+                self.slots
+                    .iter()
+                    .min_by_key(|slot| slot.load().as_ref().map(|v| v.id))
+            })?;
+        slot.swap(Some(voice))
+    }
+
+    /// Empty the slots of voices that have finished; returns them, for the
+    /// caller to drop off the audio thread.
+    pub(crate) fn sweep(&self) -> Vec<Arc<Released>> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.load().as_ref().is_some_and(|v| v.is_done()))
+            .filter_map(|slot| slot.swap(None))
+            .collect()
+    }
+
+    /// The voices still sounding, oldest first.
+    pub(crate) fn voices(&self) -> Vec<VoiceInfo> {
+        let mut voices: Vec<VoiceInfo> = self
+            .slots
+            .iter()
+            .filter_map(|slot| {
+                let voice = slot.load();
+                voice.as_ref().filter(|v| !v.is_done()).map(|v| v.info())
+            })
+            .collect();
+        voices.sort_by_key(|v| v.id);
+        voices
+    }
+
+    /// Fade `id` out over `frames`; false if it isn't sounding.
+    pub(crate) fn stop(&self, id: VoiceId, frames: usize) -> bool {
+        self.slots.iter().any(|slot| match &*slot.load() {
+            Some(voice) if voice.id == id && !voice.is_done() => {
+                voice.stop(frames);
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// Fade every voice out over `frames`.
+    pub(crate) fn stop_all(&self, frames: usize) {
+        self.paused.store(false, Ordering::Relaxed);
+        for slot in &self.slots {
+            if let Some(voice) = &*slot.load() {
+                voice.stop(frames);
+            }
+        }
+    }
+
+    /// Hold the voices where they are; false if none were sounding.
+    pub(crate) fn pause(&self) -> bool {
+        let sounding = self
+            .slots
+            .iter()
+            .any(|slot| slot.load().as_ref().is_some_and(|v| !v.is_done()));
+        self.paused.fetch_or(sounding, Ordering::Relaxed);
+        sounding
+    }
+
+    pub(crate) fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Add every sounding voice into `data`, unless paused.
+    pub(crate) fn mix(&self, data: &mut [f32], channels: usize) {
+        if self.is_paused() {
+            return;
+        }
+        for slot in &self.slots {
+            if let Some(voice) = &*slot.load() {
+                voice.mix(data, channels);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_start_together_at_their_own_gain() {
+        let stack = Stack::new([(Arc::new(vec![1.0; 6]), 1.0), (Arc::new(vec![1.0; 2]), 0.5)]);
+        let cursor = AtomicUsize::new(0);
+        let mut out = [0.0; 4];
+        assert!(stack.mix(&mut out, &cursor, 1.0));
+        assert_eq!(out, [1.5, 1.5, 1.0, 1.0]);
+
+        // Gains change live; the shorter layer is done.
+        stack.set_gain(0, 0.25);
+        stack.set_gain(5, 1.0);
+        let mut out = [0.0; 4];
+        assert!(!stack.mix(&mut out, &cursor, 0.5));
+        assert_eq!(out, [0.125, 0.125, 0.0, 0.0]);
+    }
+
+    fn voice(id: u64, samples: Vec<f32>, rate: f32) -> Arc<Released> {
+        let end = samples.len();
+        Arc::new(Released::new(
+            VoiceId(id),
+            PathBuf::from(format!("{id}.wav")),
+            Arc::new(samples),
+            (0, 0.0),
+            end,
+            1.0,
+            rate,
+        ))
+    }
+
+    #[test]
+    fn voices_ring_out_together_and_stop_on_their_own() {
+        let pool = Pool::new();
+        assert!(pool.add(voice(1, vec![1.0; 8], 1.0)).is_none());
+        assert!(pool.add(voice(2, vec![0.0, 1.0, 2.0, 3.0], 2.0)).is_none());
+        let mut out = [0.0; 3];
+        pool.mix(&mut out, 1);
+        // The second plays at double speed and is done after two frames.
+        assert_eq!(out, [1.0, 3.0, 1.0]);
+        assert_eq!(pool.sweep().len(), 1);
+        assert_eq!(pool.voices()[0].id, VoiceId(1));
+
+        // Paused voices hold their place; stopped ones fade out.
+        assert!(pool.pause());
+        pool.mix(&mut out, 1);
+        pool.resume();
+        assert!(pool.stop(VoiceId(1), 2));
+        assert!(!pool.stop(VoiceId(2), 2));
+        let mut out = [0.0; 3];
+        pool.mix(&mut out, 1);
+        assert_eq!(out, [1.0, 0.5, 0.0]);
+        assert!(pool.voices().is_empty());
+    }
+
+    #[test]
+    fn a_full_pool_cuts_the_oldest_voice() {
+        let pool = Pool::new();
+        for id in 0..MAX_VOICES as u64 {
+            assert!(pool.add(voice(id, vec![1.0; 4], 1.0)).is_none());
+        }
+        let cut = pool.add(voice(99, vec![1.0; 4], 1.0)).unwrap();
+        assert_eq!(cut.id, VoiceId(0));
+        assert_eq!(pool.voices().len(), MAX_VOICES);
+        assert_eq!(pool.voices().last().unwrap().id, VoiceId(99));
+    }
+}
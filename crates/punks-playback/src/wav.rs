@@ -0,0 +1,153 @@
+//! Writing WAVs, for exports, batch processing and extracted tracker
+//! samples alike: floats are written as they are, and integer formats are
+//! rounded, optionally with dither, and clipped at full scale.
+
+use std::path::{Path, PathBuf};
+
+use crate::PlaybackError;
+
+/// 32-bit float, which keeps anything above full scale rather than
+/// clipping it.
+pub(crate) fn float_spec(channels: u16, sample_rate: u32) -> hound::WavSpec {
+    hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    }
+}
+
+/// Write `samples` as a WAV in `spec`'s format, returning how many had to be
+/// clipped to fit an integer one; `dither` applies to integers only. Goes
+/// through a temp sibling + rename so an in-place overwrite never leaves a
+/// half-written file behind.
+pub(crate) fn write_wav(
+    path: &Path,
+    samples: &[f32],
+    spec: hound::WavSpec,
+    dither: bool,
+) -> Result<usize, PlaybackError> {
+    let err = |e: &dyn std::fmt::Display| PlaybackError::Encode {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| err(&e))?;
+    }
+    let mut tmp_name = path.as_os_str().to_os_string();
+    tmp_name.push(".part");
+    let tmp = PathBuf::from(tmp_name);
+
+    let mut clipped = 0;
+    let mut write = || -> Result<(), hound::Error> {
+        let mut writer = hound::WavWriter::create(&tmp, spec)?;
+        if spec.sample_format == hound::SampleFormat::Float {
+            for &s in samples {
+                writer.write_sample(s)?;
+            }
+        } else {
+            let mut quantizer = Quantizer::new(spec.bits_per_sample, dither);
+            for &s in samples {
+                let (value, clip) = quantizer.quantize(s);
+                clipped += usize::from(clip);
+                writer.write_sample(value)?;
+            }
+        }
+        writer.finalize()
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(err(&e));
+    }
+    std::fs::rename(&tmp, path).map_err(|e| err(&e))?;
+    Ok(clipped)
+}
+
+/// Rounds float samples to `bits`-bit integers, with TPDF dither if asked.
+struct Quantizer {
+    /// Value of full scale.
+    scale: f32,
+    max: i32,
+    dither: bool,
+    /// xorshift state: dither only has to sound like noise, and a fixed
+    /// seed makes exports repeatable.
+    noise: u32,
+}
+
+impl Quantizer {
+    fn new(bits: u16, dither: bool) -> Self {
+        let max = (1i32 << (bits - 1)) - 1;
+        Quantizer {
+            scale: max as f32 + 1.0,
+            max,
+            dither,
+            noise: 0x9e37_79b9,
+        }
+    }
+
+    /// Uniform in `0.0..1.0`.
+    fn uniform(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        (self.noise >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// `sample` as an integer, and whether it had to be clipped.
+    fn quantize(&mut self, sample: f32) -> (i32, bool) {
+        let mut value = sample * self.scale;
+        if self.dither {
+            value += self.uniform() - self.uniform();
+        }
+        let value = value.round();
+        let min = -self.max - 1;
+        let clamped = value.clamp(min as f32, self.max as f32);
+        (clamped as i32, clamped != value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizing_rounds_dithers_and_clips() {
+        let mut plain = Quantizer::new(16, false);
+        assert_eq!(plain.quantize(0.5), (16_384, false));
+        assert_eq!(plain.quantize(-1.0), (-32_768, false));
+        assert_eq!(plain.quantize(1.5), (32_767, true));
+
+        // Dither moves a value by at most a step, and averages out.
+        let mut dithered = Quantizer::new(16, true);
+        let values: Vec<i32> = (0..10_000).map(|_| dithered.quantize(0.25).0).collect();
+        assert!(values.iter().all(|v| (v - 8_192).abs() <= 1));
+        assert!(values.iter().any(|&v| v != 8_192));
+        let mean = values.iter().map(|&v| v as f64).sum::<f64>() / values.len() as f64;
+        assert!((mean - 8_192.0).abs() < 0.05, "mean = {mean}");
+    }
+
+    #[test]
+    fn writes_the_format_asked_for() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22_050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let clipped = write_wav(&path, &[0.5, -0.5, 1.5, 0.0], spec, false).unwrap();
+        assert_eq!(clipped, 1);
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec(), spec);
+        let samples: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+        assert_eq!(samples, [16_384, -16_384, 32_767, 0]);
+
+        write_wav(&path, &[1.5], float_spec(1, 8_000), false).unwrap();
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec(), float_spec(1, 8_000));
+        assert_eq!(reader.samples::<f32>().next().unwrap().unwrap(), 1.5);
+        assert!(!dir.path().join("out.wav.part").exists());
+    }
+}
@@ -0,0 +1,283 @@
+//! Python bindings, for library-curation scripts that want the same listing,
+//! search, measurement and batch processing the app uses:
+//!
+//! ```python
+//! import punks
+//!
+//! for entry in punks.search("/samples/drums", "kick"):
+//!     levels = punks.measure(entry.path)
+//!     if levels.clipped:
+//!         print(entry.name, levels.true_peak_db)
+//! ```
+//!
+//! Built with maturin (`maturin develop -m crates/punks-py/Cargo.toml`). Slow
+//! calls release the GIL, so they can run on Python threads.
+
+use std::path::PathBuf;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{
+    PyException, PyFileNotFoundError, PyNotADirectoryError, PyOSError, PyPermissionError,
+    PyValueError,
+};
+use pyo3::prelude::*;
+
+use punks_core::{ErrorKind, ExtensionRegistry, FileEntry, ListOptions, ScanError};
+use punks_playback::process::{measure_file, process_batch, Normalize, OutputMode, ProcessOptions};
+use punks_playback::PlaybackError;
+
+create_exception!(
+    punks,
+    PunksError,
+    PyException,
+    "A file couldn't be decoded, played or written."
+);
+
+/// The built-in exception for filesystem kinds, so scripts can catch
+/// `FileNotFoundError` and the like; `PunksError` for the rest.
+fn py_error(kind: ErrorKind, message: String) -> PyErr {
+    match kind {
+        ErrorKind::NotFound => PyFileNotFoundError::new_err(message),
+        ErrorKind::PermissionDenied => PyPermissionError::new_err(message),
+        ErrorKind::NotADirectory => PyNotADirectoryError::new_err(message),
+        ErrorKind::Io | ErrorKind::Unreachable => PyOSError::new_err(message),
+        _ => PunksError::new_err(message),
+    }
+}
+
+fn scan_error(e: ScanError) -> PyErr {
+    py_error(e.kind(), e.to_string())
+}
+
+fn playback_error(e: PlaybackError) -> PyErr {
+    py_error(e.kind(), e.to_string())
+}
+
+/// A listed file or folder. Playlists and CUE sheets count as folders; CUE
+/// tracks carry the span of `path` they cover, in seconds.
+#[pyclass(module = "punks", frozen, get_all)]
+#[derive(Clone)]
+struct Entry {
+    path: PathBuf,
+    name: String,
+    extension: String,
+    size: u64,
+    is_directory: bool,
+    /// `(start, end)`; `end` is `None` when it runs to the end of the file.
+    region: Option<(f64, Option<f64>)>,
+    /// Audio files directly inside, for folders.
+    audio_count: Option<usize>,
+}
+
+#[pymethods]
+impl Entry {
+    fn __repr__(&self) -> String {
+        format!("Entry({:?})", self.path)
+    }
+}
+
+impl From<FileEntry> for Entry {
+    fn from(e: FileEntry) -> Self {
+        Entry {
+            region: e
+                .region
+                .map(|r| (r.start.as_secs_f64(), r.end.map(|e| e.as_secs_f64()))),
+            path: e.path,
+            name: e.name,
+            extension: e.extension,
+            size: e.size_bytes,
+            is_directory: e.is_directory,
+            audio_count: e.audio_count,
+        }
+    }
+}
+
+/// What a file is, from its headers.
+#[pyclass(module = "punks", frozen, get_all)]
+struct FileInfo {
+    sample_rate: u32,
+    channels: u16,
+    /// `None` for formats without a fixed depth, like MP3.
+    bit_depth: Option<u16>,
+    /// Seconds, or `None` when the file doesn't say.
+    duration: Option<f64>,
+    description: Option<String>,
+    originator: Option<String>,
+}
+
+/// Whole-file levels; see `measure`.
+#[pyclass(module = "punks", frozen, get_all)]
+struct Levels {
+    duration: f64,
+    peak_db: f32,
+    true_peak_db: f32,
+    loudness_lufs: Option<f32>,
+    clipped: usize,
+}
+
+#[pymethods]
+impl Levels {
+    fn __repr__(&self) -> String {
+        format!(
+            "Levels(peak_db={:.1}, true_peak_db={:.1}, loudness_lufs={:?}, clipped={})",
+            self.peak_db, self.true_peak_db, self.loudness_lufs, self.clipped
+        )
+    }
+}
+
+/// One file's outcome from `process`: `output` is set on success, `error`
+/// on failure.
+#[pyclass(module = "punks", frozen, get_all)]
+struct Processed {
+    source: PathBuf,
+    output: Option<PathBuf>,
+    trimmed: f64,
+    gain_db: f32,
+    error: Option<String>,
+}
+
+/// List a folder, playlist or CUE sheet: folders first, then audio files,
+/// each sorted by name.
+#[pyfunction]
+#[pyo3(signature = (path, show_hidden = false))]
+fn list_directory(path: PathBuf, show_hidden: bool) -> PyResult<Vec<Entry>> {
+    let opts = ListOptions {
+        count_audio: true,
+        show_hidden,
+        ..ListOptions::default()
+    };
+    let listing = punks_core::list_directory_with(&path, &opts).map_err(scan_error)?;
+    Ok(listing.entries.into_iter().map(Entry::from).collect())
+}
+
+/// Audio files under `root`, recursively, whose name contains `query`
+/// (case-insensitive).
+#[pyfunction]
+fn search(py: Python<'_>, root: PathBuf, query: &str) -> PyResult<Vec<Entry>> {
+    let extensions = ExtensionRegistry::default();
+    let found = py
+        .allow_threads(|| punks_core::search_directory(&root, query, &extensions))
+        .map_err(scan_error)?;
+    Ok(found.into_iter().map(Entry::from).collect())
+}
+
+/// `(bytes, audio_files, files)` under `root`, recursively.
+#[pyfunction]
+fn folder_size(py: Python<'_>, root: PathBuf) -> PyResult<(u64, usize, usize)> {
+    let size = py
+        .allow_threads(|| {
+            punks_core::size::folder_size(&root, &ExtensionRegistry::default(), |_| {})
+        })
+        .map_err(scan_error)?;
+    Ok((size.bytes, size.audio_files, size.files))
+}
+
+/// Read a file's format and Broadcast Wave metadata without decoding it.
+#[pyfunction]
+fn probe(path: PathBuf) -> PyResult<FileInfo> {
+    let info = punks_playback::probe(&path).map_err(playback_error)?;
+    Ok(FileInfo {
+        sample_rate: info.sample_rate,
+        channels: info.channels,
+        bit_depth: info.bit_depth,
+        duration: info.duration.map(|d| d.as_secs_f64()),
+        description: info.metadata.description,
+        originator: info.metadata.originator,
+    })
+}
+
+/// Decode a whole file and measure its peak, true peak, integrated loudness
+/// and clipping.
+#[pyfunction]
+fn measure(py: Python<'_>, path: PathBuf) -> PyResult<Levels> {
+    let levels = py
+        .allow_threads(|| measure_file(&path))
+        .map_err(playback_error)?;
+    Ok(Levels {
+        duration: levels.duration.as_secs_f64(),
+        peak_db: levels.peak_db,
+        true_peak_db: levels.true_peak_db,
+        loudness_lufs: levels.loudness_lufs,
+        clipped: levels.clipped,
+    })
+}
+
+/// Trim silence from and normalize `paths`, writing 32-bit float WAVs into
+/// `output_dir`. `output_dir=None` overwrites WAV sources in place, keeping
+/// their sample format and bit depth. Give at most one of `peak_dbfs` and
+/// `loudness_lufs` to normalize; neither leaves the level alone. A failing
+/// file doesn't stop the rest.
+#[pyfunction]
+#[pyo3(signature = (
+    paths,
+    output_dir,
+    trim_silence = true,
+    silence_threshold_db = -60.0,
+    peak_dbfs = None,
+    loudness_lufs = None,
+))]
+fn process(
+    py: Python<'_>,
+    paths: Vec<PathBuf>,
+    output_dir: Option<PathBuf>,
+    trim_silence: bool,
+    silence_threshold_db: f32,
+    peak_dbfs: Option<f32>,
+    loudness_lufs: Option<f32>,
+) -> PyResult<Vec<Processed>> {
+    let normalize = match (peak_dbfs, loudness_lufs) {
+        (None, None) => Normalize::Off,
+        (Some(target_dbfs), None) => Normalize::Peak { target_dbfs },
+        (None, Some(target_lufs)) => Normalize::Loudness { target_lufs },
+        (Some(_), Some(_)) => {
+            return Err(PyValueError::new_err(
+                "give peak_dbfs or loudness_lufs, not both",
+            ))
+        }
+    };
+    let opts = ProcessOptions {
+        trim_silence,
+        silence_threshold_db,
+        normalize,
+        output: output_dir.map_or(OutputMode::InPlace, OutputMode::Folder),
+    };
+
+    let mut results = Vec::with_capacity(paths.len());
+    py.allow_threads(|| {
+        process_batch(&paths, &opts, |source, result| {
+            results.push(match result {
+                Ok(done) => Processed {
+                    source: done.source,
+                    output: Some(done.output),
+                    trimmed: done.trimmed.as_secs_f64(),
+                    gain_db: done.gain_db,
+                    error: None,
+                },
+                Err(e) => Processed {
+                    source: source.to_path_buf(),
+                    output: None,
+                    trimmed: 0.0,
+                    gain_db: 0.0,
+                    error: Some(e.to_string()),
+                },
+            });
+        })
+    });
+    Ok(results)
+}
+
+#[pymodule]
+fn punks(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("PunksError", m.py().get_type::<PunksError>())?;
+    m.add_class::<Entry>()?;
+    m.add_class::<FileInfo>()?;
+    m.add_class::<Levels>()?;
+    m.add_class::<Processed>()?;
+    m.add_function(wrap_pyfunction!(list_directory, m)?)?;
+    m.add_function(wrap_pyfunction!(search, m)?)?;
+    m.add_function(wrap_pyfunction!(folder_size, m)?)?;
+    m.add_function(wrap_pyfunction!(probe, m)?)?;
+    m.add_function(wrap_pyfunction!(measure, m)?)?;
+    m.add_function(wrap_pyfunction!(process, m)?)?;
+    Ok(())
+}
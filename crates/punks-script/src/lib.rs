@@ -0,0 +1,601 @@
+//! Scripting for batch actions: a [rhai] engine with the library, analysis
+//! and file operations bound in, so repetitive curation can be written once
+//! and re-run.
+//!
+//! ```rhai
+//! // Tag every 808 under Kicks and normalize it to -1 dBFS, next to the
+//! // originals.
+//! for entry in scan("/samples/Kicks") {
+//!     if matches(entry.name, "*808*") {
+//!         tag(entry, "808");
+//!         let done = process(entry, #{ peak_dbfs: -1.0, output_dir: "/samples/Kicks/norm" });
+//!         print(`${entry.name}: ${done.gain_db} dB`);
+//!     }
+//! }
+//! ```
+//!
+//! Entries are maps with `path`, `name`, `extension`, `size` and
+//! `is_directory` (and `audio_count` for listed folders). Functions that fail
+//! throw the error's message, which `try`/`catch` can handle. The bindings:
+//!
+//! | function | does |
+//! |---|---|
+//! | `list(dir)`, `list(dir, show_hidden)` | a folder's entries, folders first |
+//! | `scan(root)` | every audio file under `root` |
+//! | `search(root, query)` | audio files under `root` whose name contains `query` |
+//! | `matches(name, pattern)` | case-insensitive `*`/`?` wildcard match |
+//! | `probe(path)` | `sample_rate`, `channels`, `duration`, `description`, `originator` |
+//! | `measure(path)` | `duration`, `peak_db`, `true_peak_db`, `loudness_lufs`, `clipped` |
+//! | `process(paths, options)` | trim/normalize; see below |
+//! | `folder_size(root)` | `bytes`, `audio_files`, `files` |
+//! | `exists(path)`, `join(dir, name)` | path helpers |
+//! | `mkdir(path)`, `copy_file(from, to)`, `rename(from, to)`, `trash(path)` | file operations |
+//!
+//! With [`register_index`], scripts also read and file samples in the sample
+//! index:
+//!
+//! | function | does |
+//! |---|---|
+//! | `tags(path)`, `rating(path)` | a sample's tags, and its stars or `()` |
+//! | `tag(path, tag)`, `untag(path, tag)` | add or take off a tag; whether anything changed |
+//! | `rate(path, stars)` | rate from 1 to 5; 0 clears it |
+//! | `tagged(tags)` | paths carrying every one of `tags`, a string or an array |
+//! | `rated(stars)` | paths rated `stars` or more |
+//!
+//! Wherever a path is taken, an entry map works too. `process` takes one
+//! path or an array of them, and an options map with `output_dir` (omit to
+//! overwrite WAVs in place), `trim_silence` (default `true`),
+//! `silence_threshold_db` (default -60) and at most one of `peak_dbfs` and
+//! `loudness_lufs`. It returns a map per file with `source`, `output`,
+//! `trimmed` and `gain_db`, or `source` and `error` when that file failed.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use punks_core::index::{SampleIndex, MAX_RATING};
+use punks_core::{ExtensionRegistry, FileEntry, ListOptions, ScanOptions};
+use punks_playback::process::{measure_file, process_batch, Normalize, OutputMode, ProcessOptions};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, FLOAT, INT};
+
+pub use rhai;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn fail(e: impl ToString) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+/// An engine with the punks bindings registered. `extensions` decides what
+/// counts as audio for `list`, `scan` and `search`; `on_change` is called
+/// with every file a script writes, moves or trashes, so a host can drop
+/// cached decodes and listings for it. Scripts' `print` goes to stdout
+/// unless the host sets its own with [`Engine::on_print`].
+pub fn engine(extensions: ExtensionRegistry, on_change: impl Fn(&Path) + 'static) -> Engine {
+    let mut engine = Engine::new();
+    let extensions = Rc::new(extensions);
+    let on_change: Rc<dyn Fn(&Path)> = Rc::new(on_change);
+
+    let ext = Rc::clone(&extensions);
+    engine.register_fn("list", move |dir: Dynamic| list(&ext, dir, false));
+    let ext = Rc::clone(&extensions);
+    engine.register_fn("list", move |dir: Dynamic, show_hidden: bool| {
+        list(&ext, dir, show_hidden)
+    });
+    let ext = Rc::clone(&extensions);
+    engine.register_fn("scan", move |root: Dynamic| -> ScriptResult<Array> {
+        let options = ScanOptions {
+            extensions: (*ext).clone(),
+            ..ScanOptions::default()
+        };
+        let found = punks_core::scan_directory_iter(&path_of(root)?, options).map_err(fail)?;
+        Ok(found.flatten().map(entry_map).collect())
+    });
+    let ext = Rc::clone(&extensions);
+    engine.register_fn(
+        "search",
+        move |root: Dynamic, query: &str| -> ScriptResult<Array> {
+            let found = punks_core::search_directory(&path_of(root)?, query, &ext).map_err(fail)?;
+            Ok(found.into_iter().map(entry_map).collect())
+        },
+    );
+    engine.register_fn("matches", |name: &str, pattern: &str| {
+        wildcard_match(name, pattern)
+    });
+
+    engine.register_fn("probe", probe);
+    engine.register_fn("measure", measure);
+    let changed = Rc::clone(&on_change);
+    engine.register_fn("process", move |paths: Dynamic, options: Map| {
+        process(paths, &options, &*changed)
+    });
+    let ext = Rc::clone(&extensions);
+    engine.register_fn("folder_size", move |root: Dynamic| -> ScriptResult<Map> {
+        let size = punks_core::size::folder_size(&path_of(root)?, &ext, |_| {}).map_err(fail)?;
+        let mut map = Map::new();
+        map.insert("bytes".into(), (size.bytes as INT).into());
+        map.insert("audio_files".into(), (size.audio_files as INT).into());
+        map.insert("files".into(), (size.files as INT).into());
+        Ok(map)
+    });
+
+    engine.register_fn("exists", |path: Dynamic| -> ScriptResult<bool> {
+        Ok(path_of(path)?.exists())
+    });
+    engine.register_fn("join", |dir: Dynamic, name: &str| -> ScriptResult<String> {
+        Ok(path_string(&path_of(dir)?.join(name)))
+    });
+    engine.register_fn("mkdir", |path: Dynamic| -> ScriptResult<()> {
+        let path = path_of(path)?;
+        std::fs::create_dir_all(&path).map_err(|e| fail(format!("{}: {e}", path.display())))
+    });
+    let changed = Rc::clone(&on_change);
+    engine.register_fn(
+        "copy_file",
+        move |from: Dynamic, to: Dynamic| -> ScriptResult<()> {
+            let (from, to) = (path_of(from)?, path_of(to)?);
+            std::fs::copy(&from, &to).map_err(|e| fail(format!("copy {}: {e}", from.display())))?;
+            changed(&to);
+            Ok(())
+        },
+    );
+    let changed = Rc::clone(&on_change);
+    engine.register_fn(
+        "rename",
+        move |from: Dynamic, to: Dynamic| -> ScriptResult<()> {
+            let (from, to) = (path_of(from)?, path_of(to)?);
+            std::fs::rename(&from, &to)
+                .map_err(|e| fail(format!("rename {}: {e}", from.display())))?;
+            changed(&from);
+            changed(&to);
+            Ok(())
+        },
+    );
+    let changed = Rc::clone(&on_change);
+    engine.register_fn("trash", move |path: Dynamic| -> ScriptResult<()> {
+        let path = path_of(path)?;
+        punks_core::trash::move_to_trash(&path).map_err(fail)?;
+        changed(&path);
+        Ok(())
+    });
+
+    engine
+}
+
+/// A change a script made to the sample index, for the host to make to its
+/// own copy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexEdit {
+    Tag(PathBuf, String),
+    Untag(PathBuf, String),
+    Rate(PathBuf, Option<u8>),
+}
+
+impl IndexEdit {
+    /// Make the change to `index`; whether it changed anything.
+    pub fn apply(&self, index: &mut SampleIndex) -> bool {
+        match self {
+            IndexEdit::Tag(path, tag) => index.add_tag(path, tag),
+            IndexEdit::Untag(path, tag) => index.remove_tag(path, tag),
+            IndexEdit::Rate(path, rating) => {
+                let before = index.rating(path);
+                index.set_rating(path, *rating);
+                index.rating(path) != before
+            }
+        }
+    }
+}
+
+/// Register the sample index bindings on `engine`, over `index`, a copy of
+/// the host's. Scripts see their own edits straight away; each one is also
+/// passed to `on_edit`, for the host to apply and save.
+pub fn register_index(
+    engine: &mut Engine,
+    index: SampleIndex,
+    on_edit: impl Fn(&IndexEdit) + 'static,
+) {
+    let index = Rc::new(RefCell::new(index));
+    let edit = {
+        let index = Rc::clone(&index);
+        Rc::new(move |edit: IndexEdit| {
+            let changed = edit.apply(&mut index.borrow_mut());
+            if changed {
+                on_edit(&edit);
+            }
+            changed
+        })
+    };
+
+    let idx = Rc::clone(&index);
+    engine.register_fn("tags", move |path: Dynamic| -> ScriptResult<Array> {
+        let tags = idx.borrow().tags(&path_of(path)?).to_vec();
+        Ok(tags.into_iter().map(Dynamic::from).collect())
+    });
+    let idx = Rc::clone(&index);
+    engine.register_fn("rating", move |path: Dynamic| -> ScriptResult<Dynamic> {
+        let rating = idx.borrow().rating(&path_of(path)?);
+        Ok(optional(rating.map(INT::from)))
+    });
+    let tag = Rc::clone(&edit);
+    engine.register_fn(
+        "tag",
+        move |path: Dynamic, name: &str| -> ScriptResult<bool> {
+            Ok(tag(IndexEdit::Tag(path_of(path)?, name.to_string())))
+        },
+    );
+    let untag = Rc::clone(&edit);
+    engine.register_fn(
+        "untag",
+        move |path: Dynamic, name: &str| -> ScriptResult<bool> {
+            Ok(untag(IndexEdit::Untag(path_of(path)?, name.to_string())))
+        },
+    );
+    let rate = Rc::clone(&edit);
+    engine.register_fn(
+        "rate",
+        move |path: Dynamic, stars: INT| -> ScriptResult<bool> {
+            let stars = u8::try_from(stars.clamp(0, INT::from(MAX_RATING))).unwrap_or(0);
+            Ok(rate(IndexEdit::Rate(
+                path_of(path)?,
+                (stars > 0).then_some(stars),
+            )))
+        },
+    );
+    let idx = Rc::clone(&index);
+    engine.register_fn("tagged", move |tags: Dynamic| -> ScriptResult<Array> {
+        let tags: Vec<String> = if tags.is_array() {
+            tags.cast::<Array>()
+                .into_iter()
+                .map(|t| {
+                    t.into_string()
+                        .map_err(|t| fail(format!("expected a tag, got {t}")))
+                })
+                .collect::<ScriptResult<_>>()?
+        } else {
+            let tags = tags
+                .into_string()
+                .map_err(|t| fail(format!("expected tags, got {t}")))?;
+            tags.split_whitespace().map(str::to_string).collect()
+        };
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        let index = idx.borrow();
+        Ok(index
+            .tagged(&tags)
+            .into_iter()
+            .map(|p| path_string(p).into())
+            .collect())
+    });
+    let idx = Rc::clone(&index);
+    engine.register_fn("rated", move |stars: INT| -> Array {
+        let index = idx.borrow();
+        index
+            .tagged(&[])
+            .into_iter()
+            .filter(|p| index.rating(p).is_some_and(|r| INT::from(r) >= stars))
+            .map(|p| path_string(p).into())
+            .collect()
+    });
+}
+
+/// A path argument: a string, or an entry map's `path`.
+fn path_of(value: Dynamic) -> ScriptResult<PathBuf> {
+    if value.is_map() {
+        let map = value.cast::<Map>();
+        return match map.get("path") {
+            Some(path) => path_of(path.clone()),
+            None => Err(fail(
+                "expected a path or an entry, got a map without `path`",
+            )),
+        };
+    }
+    match value.into_string() {
+        Ok(s) => Ok(PathBuf::from(s)),
+        Err(type_name) => Err(fail(format!("expected a path, got {type_name}"))),
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn entry_map(e: FileEntry) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("path".into(), path_string(&e.path).into());
+    map.insert("name".into(), e.name.into());
+    map.insert("extension".into(), e.extension.into());
+    map.insert("size".into(), (e.size_bytes as INT).into());
+    map.insert("is_directory".into(), e.is_directory.into());
+    if let Some(count) = e.audio_count {
+        map.insert("audio_count".into(), (count as INT).into());
+    }
+    map.into()
+}
+
+/// `None` as rhai's unit, so scripts can test `x == ()`.
+fn optional<T: Into<Dynamic>>(value: Option<T>) -> Dynamic {
+    value.map_or(Dynamic::UNIT, Into::into)
+}
+
+fn list(extensions: &ExtensionRegistry, dir: Dynamic, show_hidden: bool) -> ScriptResult<Array> {
+    let opts = ListOptions {
+        extensions: extensions.clone(),
+        count_audio: true,
+        show_hidden,
+    };
+    let listing = punks_core::list_directory_with(&path_of(dir)?, &opts).map_err(fail)?;
+    Ok(listing.entries.into_iter().map(entry_map).collect())
+}
+
+fn probe(path: Dynamic) -> ScriptResult<Map> {
+    let info = punks_playback::probe(&path_of(path)?).map_err(fail)?;
+    let mut map = Map::new();
+    map.insert("sample_rate".into(), INT::from(info.sample_rate).into());
+    map.insert("channels".into(), INT::from(info.channels).into());
+    map.insert(
+        "duration".into(),
+        optional(info.duration.map(|d| d.as_secs_f64() as FLOAT)),
+    );
+    map.insert("description".into(), optional(info.metadata.description));
+    map.insert("originator".into(), optional(info.metadata.originator));
+    Ok(map)
+}
+
+fn measure(path: Dynamic) -> ScriptResult<Map> {
+    let levels = measure_file(&path_of(path)?).map_err(fail)?;
+    let mut map = Map::new();
+    map.insert(
+        "duration".into(),
+        (levels.duration.as_secs_f64() as FLOAT).into(),
+    );
+    map.insert("peak_db".into(), FLOAT::from(levels.peak_db).into());
+    map.insert(
+        "true_peak_db".into(),
+        FLOAT::from(levels.true_peak_db).into(),
+    );
+    map.insert(
+        "loudness_lufs".into(),
+        optional(levels.loudness_lufs.map(FLOAT::from)),
+    );
+    map.insert("clipped".into(), (levels.clipped as INT).into());
+    Ok(map)
+}
+
+/// A number option, given as a float or an integer.
+fn number(options: &Map, key: &str) -> ScriptResult<Option<f32>> {
+    let Some(value) = options.get(key) else {
+        return Ok(None);
+    };
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|i| i as FLOAT))
+        .map(|n| Some(n as f32))
+        .map_err(|type_name| fail(format!("`{key}` should be a number, got {type_name}")))
+}
+
+fn process_options(options: &Map) -> ScriptResult<ProcessOptions> {
+    let normalize = match (
+        number(options, "peak_dbfs")?,
+        number(options, "loudness_lufs")?,
+    ) {
+        (None, None) => Normalize::Off,
+        (Some(target_dbfs), None) => Normalize::Peak { target_dbfs },
+        (None, Some(target_lufs)) => Normalize::Loudness { target_lufs },
+        (Some(_), Some(_)) => return Err(fail("give peak_dbfs or loudness_lufs, not both")),
+    };
+    let trim_silence = match options.get("trim_silence") {
+        Some(value) => value
+            .as_bool()
+            .map_err(|t| fail(format!("`trim_silence` should be a bool, got {t}")))?,
+        None => true,
+    };
+    let output = match options.get("output_dir") {
+        Some(dir) => OutputMode::Folder(path_of(dir.clone())?),
+        None => OutputMode::InPlace,
+    };
+    Ok(ProcessOptions {
+        trim_silence,
+        silence_threshold_db: number(options, "silence_threshold_db")?.unwrap_or(-60.0),
+        normalize,
+        output,
+    })
+}
+
+fn process(paths: Dynamic, options: &Map, on_change: &dyn Fn(&Path)) -> ScriptResult<Dynamic> {
+    let opts = process_options(options)?;
+    let single = !paths.is_array();
+    let paths: Vec<PathBuf> = if single {
+        vec![path_of(paths)?]
+    } else {
+        paths
+            .cast::<Array>()
+            .into_iter()
+            .map(path_of)
+            .collect::<ScriptResult<_>>()?
+    };
+
+    let mut results = Array::with_capacity(paths.len());
+    process_batch(&paths, &opts, |source, result| {
+        let mut map = Map::new();
+        map.insert("source".into(), path_string(source).into());
+        match result {
+            Ok(done) => {
+                on_change(&done.output);
+                map.insert("output".into(), path_string(&done.output).into());
+                map.insert(
+                    "trimmed".into(),
+                    (done.trimmed.as_secs_f64() as FLOAT).into(),
+                );
+                map.insert("gain_db".into(), FLOAT::from(done.gain_db).into());
+            }
+            Err(e) => {
+                map.insert("error".into(), e.to_string().into());
+            }
+        }
+        results.push(map.into());
+    });
+    // One path in, one result out; a single failure throws like the other
+    // single-file functions do.
+    if single {
+        let result = results.remove(0);
+        if let Some(error) = result
+            .read_lock::<Map>()
+            .and_then(|m| m.get("error").cloned())
+        {
+            return Err(fail(error));
+        }
+        return Ok(result);
+    }
+    Ok(results.into())
+}
+
+/// Case-insensitive glob match of a whole name: `*` matches any run of
+/// characters, `?` any one.
+fn wildcard_match(name: &str, pattern: &str) -> bool {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let (mut n, mut p) = (0, 0);
+    // Where the last `*` was and how much of the name it has taken so far.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                n += 1;
+                p += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::fs;
+
+    #[test]
+    fn wildcards_match_whole_names() {
+        assert!(wildcard_match("Kick 808 Long.wav", "*808*"));
+        assert!(wildcard_match("KICK.WAV", "kick.wav"));
+        assert!(wildcard_match("snare_01.wav", "snare_0?.*"));
+        assert!(!wildcard_match("snare_10.wav", "snare_0?.*"));
+        assert!(!wildcard_match("808 kick.wav", "*808"));
+        assert!(wildcard_match("", "*"));
+    }
+
+    #[test]
+    fn scripts_walk_the_library_and_report_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("Kicks")).unwrap();
+        fs::write(root.join("Kicks/808 long.wav"), b"").unwrap();
+        fs::write(root.join("Kicks/acoustic.wav"), b"").unwrap();
+        fs::write(root.join("readme.txt"), b"").unwrap();
+
+        let changed = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&changed);
+        let engine = engine(ExtensionRegistry::default(), move |p| {
+            seen.borrow_mut().push(p.to_path_buf())
+        });
+        let mut scope = rhai::Scope::new();
+        scope.push("root", path_string(root));
+        let moved: Array = engine
+            .eval_with_scope(
+                &mut scope,
+                r#"
+                    let moved = [];
+                    mkdir(join(root, "808s"));
+                    for entry in scan(root) {
+                        if matches(entry.name, "*808*") {
+                            let to = join(join(root, "808s"), entry.name);
+                            rename(entry, to);
+                            moved.push(to);
+                        }
+                    }
+                    moved
+                "#,
+            )
+            .unwrap();
+        let target = root.join("808s/808 long.wav");
+        assert_eq!(moved.len(), 1);
+        assert!(target.exists());
+        assert_eq!(changed.borrow().last(), Some(&target));
+
+        let err = engine
+            .eval_with_scope::<Dynamic>(&mut scope, r#"measure(join(root, "missing.wav"))"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("missing.wav"), "{err}");
+    }
+
+    #[test]
+    fn scripts_tag_and_find_samples_in_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("808 long.wav"), b"").unwrap();
+        fs::write(root.join("acoustic.wav"), b"").unwrap();
+        let acoustic = root.join("acoustic.wav");
+        let mut index = SampleIndex::default();
+        index.add_tag(&acoustic, "kick");
+
+        let edits = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&edits);
+        let mut engine = engine(ExtensionRegistry::default(), |_| {});
+        register_index(&mut engine, index, move |e| {
+            seen.borrow_mut().push(e.clone())
+        });
+        let mut scope = rhai::Scope::new();
+        scope.push("root", path_string(root));
+        let found: Array = engine
+            .eval_with_scope(
+                &mut scope,
+                r#"
+                    for entry in scan(root) {
+                        tag(entry, "Kick");
+                        if matches(entry.name, "*808*") {
+                            tag(entry, "808");
+                            rate(entry, 4);
+                        }
+                    }
+                    untag(join(root, "acoustic.wav"), "missing");
+                    [tagged("kick 808"), tagged(["kick"]), rated(3), tags(join(root, "808 long.wav"))]
+                "#,
+            )
+            .unwrap();
+        let strings = |v: &Dynamic| -> Vec<String> {
+            v.clone()
+                .cast::<Array>()
+                .into_iter()
+                .map(|s| s.into_string().unwrap())
+                .collect()
+        };
+        let long = path_string(&root.join("808 long.wav"));
+        let acoustic = path_string(&acoustic);
+        assert_eq!(strings(&found[0]), [long.as_str()]);
+        assert_eq!(strings(&found[1]), [long.as_str(), acoustic.as_str()]);
+        assert_eq!(strings(&found[2]), [long.as_str()]);
+        assert_eq!(strings(&found[3]), ["kick", "808"]);
+
+        // Only what changed reaches the host.
+        let long = root.join("808 long.wav");
+        assert_eq!(
+            *edits.borrow(),
+            [
+                IndexEdit::Tag(long.clone(), "Kick".into()),
+                IndexEdit::Tag(long.clone(), "808".into()),
+                IndexEdit::Rate(long, Some(4)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1430 @@
+//! The browse panel drawn with egui instead of imgui, for eframe apps and
+//! other egui hosts. It drives the same [`SampleBrowser`] as `punks-ui`:
+//! tabs, breadcrumbs, search, the file list with keyboard navigation, the
+//! zoomable, scrubbable waveform and the volume slider. Settings, batch
+//! processing, auto-tagging, watch folders, layered preview, the metronome,
+//! library verification and the audition history stay in `punks-ui` for
+//! now.
+//!
+//! ```ignore
+//! impl eframe::App for App {
+//!     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+//!         egui::CentralPanel::default().show(ctx, |ui| {
+//!             self.panel.show(ui, &mut self.browser, None);
+//!         });
+//!     }
+//! }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use egui::{
+    Align2, Button, Color32, FontId, Key, Pos2, Rect, RichText, ScrollArea, Sense, Slider, Stroke,
+    TextEdit, Vec2,
+};
+use punks_browser::keyboard::{self, NoteKeyboard};
+use punks_browser::{
+    FolderSize, PlaybackStatus, Region, SampleBrowser, SortMode, WaveformPeaks, WaveformView,
+};
+use punks_core::config::PunksConfig;
+use punks_core::index::MAX_RATING;
+
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// egui only redraws on input, so the panel asks for a frame this often to
+/// pick up playback progress and background results.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const ROW_HEIGHT: f32 = 18.0;
+const WAVEFORM_HEIGHT: f32 = 64.0;
+const PROGRESS_HEIGHT: f32 = 14.0;
+
+const DIR_TEXT_COLOR: Color32 = Color32::from_rgb(140, 217, 255);
+const ERROR_TEXT_COLOR: Color32 = Color32::from_rgb(255, 77, 77);
+const RECORDING_COLOR: Color32 = Color32::from_rgb(179, 38, 38);
+const WARNING_TEXT_COLOR: Color32 = Color32::from_rgb(255, 179, 77);
+
+const WAVEFORM_BG: Color32 = Color32::from_rgb(31, 31, 36);
+const WAVEFORM_BAR: Color32 = Color32::from_rgb(77, 191, 115);
+const WAVEFORM_PLAYHEAD: Color32 = Color32::from_rgba_premultiplied(230, 230, 230, 230);
+const WAVEFORM_TEXT: Color32 = Color32::from_rgba_premultiplied(217, 217, 217, 217);
+// Subtle hover/scrub crosshair — dimmer than the opaque playhead.
+const WAVEFORM_HOVER: Color32 = Color32::from_rgba_premultiplied(90, 90, 90, 90);
+const WAVEFORM_SELECTION: Color32 = Color32::from_rgba_premultiplied(40, 60, 90, 90);
+const WAVEFORM_LOOP: Color32 = Color32::from_rgba_premultiplied(90, 68, 27, 90);
+/// How much one notch of the mouse wheel (50 points) zooms the waveform.
+const WAVEFORM_ZOOM_STEP: f64 = 0.8;
+const PROGRESS_FILL: Color32 = Color32::from_rgb(46, 110, 70);
+
+/// A keybind from the config. Bindings are stored with imgui's key names
+/// (`UpArrow`); egui's own names (`ArrowUp`, `Up`) work too.
+fn parse_key(s: &str) -> Option<Key> {
+    match s {
+        "UpArrow" => Some(Key::ArrowUp),
+        "DownArrow" => Some(Key::ArrowDown),
+        "LeftArrow" => Some(Key::ArrowLeft),
+        "RightArrow" => Some(Key::ArrowRight),
+        _ => Key::from_name(s),
+    }
+}
+
+/// Number keys that rate the selected file: 0 clears, 1–5 give that many
+/// stars.
+const RATING_KEYS: [Key; 6] = [
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+];
+
+/// The rating a number key pressed this frame asks for; `Some(None)` clears.
+fn rating_key(ui: &egui::Ui) -> Option<Option<u8>> {
+    let stars = RATING_KEYS
+        .iter()
+        .position(|&key| ui.input(|input| input.key_pressed(key)))?;
+    Some((stars > 0).then_some(stars as u8))
+}
+
+/// Duration as `M:SS` (or `H:MM:SS` past an hour).
+fn format_hms(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    let (h, m, s) = (total / 3600, (total % 3600) / 60, total % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// A sample's length: tenths of a second for one-shots, `m:ss` past a minute.
+fn format_length(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", d.as_secs_f32())
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Directory-row size suffix, e.g. `1.4 GB · 312 samples`.
+fn format_folder_size(size: &FolderSize) -> String {
+    format!(
+        "{} \u{b7} {} samples",
+        punks_core::size::format_bytes(size.bytes),
+        size.audio_files
+    )
+}
+
+fn relative_parent(root: Option<&Path>, file_path: &Path) -> String {
+    let Some(parent) = file_path.parent() else {
+        return String::new();
+    };
+    if let Some(rel) = root.and_then(|root| parent.strip_prefix(root).ok()) {
+        let s = rel.to_string_lossy();
+        return if s.is_empty() {
+            ".".into()
+        } else {
+            s.into_owned()
+        };
+    }
+    parent
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// What a click in the list asked for, applied once the list is drawn so
+/// drawing only needs to borrow the browser immutably.
+enum ListAction {
+    Open(usize, bool),
+    SelectSearchResult(usize),
+    PlaySearchResult(usize),
+    Delete(usize),
+    /// Rate the selected file; `None` clears.
+    Rate(Option<u8>),
+    ComputeSize(PathBuf),
+    Drag(PathBuf),
+}
+
+pub struct BrowserPanel {
+    prefs: PunksConfig,
+    search_buf: String,
+    last_typed_query: String,
+    query_change_time: Instant,
+    last_searched_query: String,
+    volume: f32,
+    /// Tracks the active tab between frames so the search box can be reloaded
+    /// from the newly active tab's stored query when the user switches tabs.
+    last_active_tab: usize,
+    waveform: WaveformState,
+    /// Put the cursor in the search box next frame.
+    focus_search: bool,
+    /// Where a drag on the progress bar is, as a fraction of the clip; the
+    /// bar shows it rather than the playback position, which lags the seek.
+    progress_drag: Option<f32>,
+    /// The note keyboard, while it's on.
+    note_keys: Option<NoteKeyboard>,
+    /// Tag being typed for the loaded sample.
+    tag_buf: String,
+}
+
+/// Zoom, pan and selection on the waveform, kept between frames.
+#[derive(Default)]
+struct WaveformState {
+    view: WaveformView,
+    /// The clip `view` is of; another one starts zoomed out.
+    clip: Option<(PathBuf, Option<Region>)>,
+    /// Last pointer x we seeked to during a waveform drag, so a held-still
+    /// pointer lets audio play forward instead of re-seeking every frame.
+    scrub_last_x: Option<f32>,
+    /// A shift- or alt-drag selection in progress: where it started and
+    /// where the pointer is, as fractions of the clip.
+    dragging: Option<(f64, f64)>,
+    /// Whether `dragging` sets an A/B loop (alt) rather than a span to play
+    /// once (shift).
+    loop_drag: bool,
+    /// The span last selected, shown until the clip changes.
+    selection: Option<(f64, f64)>,
+}
+
+impl BrowserPanel {
+    pub fn new() -> Self {
+        let prefs = punks_core::config::load();
+        let volume = prefs.volume;
+        BrowserPanel {
+            prefs,
+            search_buf: String::new(),
+            last_typed_query: String::new(),
+            query_change_time: Instant::now(),
+            last_searched_query: String::new(),
+            volume,
+            last_active_tab: 0,
+            waveform: WaveformState::default(),
+            focus_search: false,
+            progress_drag: None,
+            note_keys: None,
+            tag_buf: String::new(),
+        }
+    }
+
+    /// The config loaded at construction, for callers that need to share it
+    /// with other components instead of loading it a second time.
+    pub fn prefs(&self) -> &PunksConfig {
+        &self.prefs
+    }
+
+    /// Draw the panel into `ui`. `on_drag_file` is called when a file is
+    /// dragged out of the list, for hosts that start an OS drag.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        browser: &mut SampleBrowser,
+        on_drag_file: Option<&mut dyn FnMut(&Path)>,
+    ) {
+        browser.poll();
+        ui.ctx().request_repaint_after(POLL_INTERVAL);
+
+        if self.last_active_tab != browser.active_tab() {
+            self.reset_search(browser.search_query().to_string());
+            self.last_active_tab = browser.active_tab();
+        }
+
+        // Persist the deepest directory the user has navigated into; see
+        // punks-ui for why this only ever moves forward.
+        if let Some(dir) = browser.current_directory() {
+            if self.prefs.last_directory.as_deref() != Some(dir) {
+                self.prefs.last_directory = Some(dir.to_path_buf());
+                punks_core::config::save(&self.prefs);
+            }
+        }
+
+        self.show_tabs(ui, browser);
+        self.show_toolbar(ui, browser);
+        show_breadcrumbs(ui, browser);
+        ui.separator();
+        self.show_search_box(ui, browser);
+        if !browser.is_in_search_mode() {
+            self.show_sort_header(ui, browser);
+        }
+
+        // Keys drive the list only while no text field has focus, and the
+        // letters only while the note keyboard is off.
+        let keys = !ui.ctx().wants_keyboard_input();
+        let list_keys = keys && self.note_keys.is_none();
+        // Reserve room below the list for: waveform + metadata line + tags +
+        // transport.
+        let list_height = (ui.available_height() - 176.0).max(100.0);
+        let action = ui
+            .allocate_ui(Vec2::new(ui.available_width(), list_height), |ui| {
+                if browser.is_in_search_mode() {
+                    self.show_search_results(ui, browser, list_keys)
+                } else {
+                    self.show_browse_list(ui, browser, list_keys)
+                }
+            })
+            .inner;
+
+        match action {
+            Some(ListAction::Open(i, is_dir)) => {
+                browser.select(i);
+                if is_dir {
+                    if let Err(e) = browser.navigate_into(i) {
+                        tracing::error!("navigate_into failed: {e}");
+                    }
+                } else {
+                    browser.play_selected();
+                }
+            }
+            Some(ListAction::SelectSearchResult(i)) => browser.select_search_result(i),
+            Some(ListAction::PlaySearchResult(i)) => {
+                browser.select_search_result(i);
+                if let Some(entry) = browser.search_results().and_then(|r| r.get(i)).cloned() {
+                    browser.play_entry(&entry);
+                }
+            }
+            Some(ListAction::Delete(i)) => {
+                if let Err(e) = browser.delete_entry(i) {
+                    tracing::error!("{e}");
+                }
+            }
+            Some(ListAction::Rate(rating)) => browser.rate_selected(rating),
+            Some(ListAction::ComputeSize(dir)) => browser.compute_folder_size(&dir),
+            Some(ListAction::Drag(path)) => {
+                if let Some(on_drag_file) = on_drag_file {
+                    on_drag_file(&path);
+                }
+                return;
+            }
+            None => {}
+        }
+
+        ui.separator();
+        if keys {
+            self.handle_panel_keys(ui, browser);
+        }
+        show_waveform(ui, browser, &mut self.waveform);
+        show_progress_bar(ui, browser, &mut self.progress_drag);
+        show_track_details(ui, browser);
+        self.show_tag_row(ui, browser);
+        self.show_transport(ui, browser);
+    }
+
+    fn reset_search(&mut self, query: String) {
+        self.last_typed_query = query.clone();
+        self.last_searched_query = query.clone();
+        self.search_buf = query;
+        self.query_change_time = Instant::now();
+    }
+
+    fn show_tabs(&mut self, ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+        let mut switch_to = None;
+        let mut close = None;
+        let mut open_new = false;
+        let count = browser.tab_count();
+        ui.horizontal_wrapped(|ui| {
+            for i in 0..count {
+                let title = browser.tab_title(i);
+                if ui
+                    .add(Button::selectable(i == browser.active_tab(), title))
+                    .clicked()
+                {
+                    switch_to = Some(i);
+                }
+                // Hidden on the only tab so one always remains.
+                if count > 1 && ui.small_button("\u{00d7}").clicked() {
+                    close = Some(i);
+                }
+            }
+            open_new = ui.button("+").clicked();
+        });
+        ui.separator();
+
+        // Closing shifts indices, so tab changes wait until after the loop.
+        if let Some(i) = close {
+            browser.close_tab(i);
+        }
+        if open_new {
+            let start = browser.current_directory().map(Path::to_path_buf);
+            browser.new_tab(start.as_deref());
+        }
+        if let Some(i) = switch_to {
+            browser.switch_tab(i);
+        }
+    }
+
+    fn show_toolbar(&mut self, ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+        ui.horizontal(|ui| {
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    match browser.open_directory(&path) {
+                        Ok(()) => self.reset_search(String::new()),
+                        Err(e) => tracing::error!("failed to open directory: {e}"),
+                    }
+                }
+            }
+            // Back and forward like a file manager: Alt+Left / Alt+Right, or
+            // the mouse's side buttons.
+            let (back_key, forward_key) = ui.input(|input| {
+                let alt = |key| input.modifiers.alt && input.key_pressed(key);
+                let side = |button| input.pointer.button_pressed(button);
+                (
+                    alt(Key::ArrowLeft) || side(egui::PointerButton::Extra1),
+                    alt(Key::ArrowRight) || side(egui::PointerButton::Extra2),
+                )
+            });
+            let back = ui
+                .add_enabled(browser.can_go_back(), Button::new("<"))
+                .on_hover_text("Back (Alt+Left)");
+            if back.clicked() || back_key {
+                if let Err(e) = browser.navigate_back() {
+                    tracing::error!("navigate_back failed: {e}");
+                }
+            }
+            let forward = ui
+                .add_enabled(browser.can_go_forward(), Button::new(">"))
+                .on_hover_text("Forward (Alt+Right)");
+            if forward.clicked() || forward_key {
+                if let Err(e) = browser.navigate_forward() {
+                    tracing::error!("navigate_forward failed: {e}");
+                }
+            }
+            if browser.can_navigate_up() && ui.button("^  Up").clicked() {
+                if let Err(e) = browser.navigate_up() {
+                    tracing::error!("navigate_up failed: {e}");
+                }
+            }
+            if ui
+                .checkbox(&mut self.prefs.auto_preview, "Auto-play")
+                .on_hover_text("Play files as they're selected")
+                .changed()
+            {
+                browser.set_auto_preview(self.prefs.auto_preview);
+                punks_core::config::save(&self.prefs);
+            }
+            let mut matched = self.prefs.loudness_target.is_some();
+            if ui
+                .checkbox(&mut matched, "Level-match")
+                .on_hover_text(format!(
+                    "Play every sample at {} LUFS, so louder and quieter packs audition alike",
+                    punks_playback::DEFAULT_LOUDNESS_TARGET
+                ))
+                .changed()
+            {
+                self.prefs.loudness_target =
+                    matched.then_some(punks_playback::DEFAULT_LOUDNESS_TARGET);
+                browser.set_loudness_target(self.prefs.loudness_target);
+                punks_core::config::save(&self.prefs);
+            }
+        });
+    }
+
+    fn show_search_box(&mut self, ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+        let response = ui.add(
+            TextEdit::singleline(&mut self.search_buf)
+                .hint_text("Search...")
+                .desired_width(f32::INFINITY),
+        );
+        if std::mem::take(&mut self.focus_search) {
+            response.request_focus();
+        }
+
+        if self.search_buf != self.last_typed_query {
+            self.last_typed_query = self.search_buf.clone();
+            self.query_change_time = Instant::now();
+        }
+        if self.last_typed_query != self.last_searched_query {
+            let waited = self.query_change_time.elapsed();
+            if waited >= SEARCH_DEBOUNCE {
+                self.last_searched_query = self.last_typed_query.clone();
+                if self.last_searched_query.is_empty() {
+                    browser.clear_search();
+                } else {
+                    browser.search(&self.last_searched_query);
+                }
+            } else {
+                ui.ctx().request_repaint_after(SEARCH_DEBOUNCE - waited);
+            }
+        }
+
+        if let Some(progress) = browser.search_progress() {
+            ui.weak(format!("Searching... {progress}"));
+        }
+    }
+
+    /// The orders the listing can be sorted in; clicking the current one
+    /// turns it around.
+    fn show_sort_header(&mut self, ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+        ui.horizontal(|ui| {
+            ui.weak("Sort:");
+            for mode in SortMode::ALL {
+                let current = browser.sort_mode() == mode;
+                let arrow = match (current, browser.sort_descending()) {
+                    (false, _) => "",
+                    (true, false) => " \u{25b2}",
+                    (true, true) => " \u{25bc}",
+                };
+                if ui
+                    .selectable_label(current, format!("{}{arrow}", mode.label()))
+                    .clicked()
+                {
+                    let descending = current && !browser.sort_descending();
+                    browser.set_sort_mode(mode, descending);
+                    self.prefs.sort_mode = mode;
+                    self.prefs.sort_descending = descending;
+                    punks_core::config::save(&self.prefs);
+                }
+            }
+        });
+    }
+
+    /// The loaded sample's rating and its tags as chips, each listing the
+    /// samples with that tag under the current folder when clicked, a box to
+    /// add one, and every tag in use to browse by.
+    fn show_tag_row(&mut self, ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+        let mut browse = None;
+        ui.horizontal(|ui| {
+            if let Some((path, _)) = browser.loaded_clip() {
+                let path = path.to_path_buf();
+                // Click a star to rate, the last lit one again to clear.
+                let rating = browser.rating(&path).unwrap_or(0);
+                let mut rate = None;
+                for star in 1..=MAX_RATING {
+                    let label = if star <= rating {
+                        "\u{2605}"
+                    } else {
+                        "\u{2606}"
+                    };
+                    if ui
+                        .small_button(label)
+                        .on_hover_text("Rate (keys 1–5 rate the selection, 0 clears)")
+                        .clicked()
+                    {
+                        rate = Some((star != rating).then_some(star));
+                    }
+                }
+                if let Some(rating) = rate {
+                    browser.set_rating(&path, rating);
+                }
+
+                let mut remove = None;
+                for tag in browser.sample_tags(&path) {
+                    if ui.small_button(tag).clicked() {
+                        browse = Some(tag.clone());
+                    }
+                    if ui
+                        .small_button("\u{00d7}")
+                        .on_hover_text("Remove tag")
+                        .clicked()
+                    {
+                        remove = Some(tag.clone());
+                    }
+                }
+                if let Some(tag) = remove {
+                    browser.remove_sample_tag(&path, &tag);
+                }
+                let response = ui.add(
+                    TextEdit::singleline(&mut self.tag_buf)
+                        .hint_text("Add tag...")
+                        .desired_width(120.0),
+                );
+                if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+                    browser.add_sample_tag(&path, &self.tag_buf);
+                    self.tag_buf.clear();
+                }
+            }
+
+            let tags = browser.all_tags();
+            egui::ComboBox::from_id_salt("browse_tags")
+                .selected_text(format!("Tags ({})", tags.len()))
+                .show_ui(ui, |ui| {
+                    for (tag, count) in &tags {
+                        if ui
+                            .selectable_label(false, format!("{tag}  ({count})"))
+                            .clicked()
+                        {
+                            browse = Some(tag.to_string());
+                        }
+                    }
+                });
+        });
+
+        // Through the search box, so the results show like any search's.
+        if let Some(tag) = browse {
+            self.search_buf = format!("#{tag}");
+        }
+    }
+
+    fn show_search_results(
+        &mut self,
+        ui: &mut egui::Ui,
+        browser: &SampleBrowser,
+        keys: bool,
+    ) -> Option<ListAction> {
+        let results = match browser.search_results() {
+            Some(r) if !r.is_empty() => r,
+            // The progress line above says it's still looking.
+            Some(_) if browser.is_searching() => return None,
+            Some(_) => {
+                ui.weak("No results.");
+                return None;
+            }
+            None => {
+                ui.weak("Searching...");
+                return None;
+            }
+        };
+
+        let selected = browser.search_selected();
+        let mut action = None;
+        if keys {
+            let (up, down) = (self.key(ui, NavKey::Up), self.key(ui, NavKey::Down));
+            if up {
+                let i = selected.unwrap_or(0).saturating_sub(1);
+                action = Some(ListAction::SelectSearchResult(i));
+            } else if down {
+                let i = selected.map_or(0, |i| (i + 1).min(results.len() - 1));
+                action = Some(ListAction::SelectSearchResult(i));
+            } else if self.key(ui, NavKey::Back) {
+                self.reset_search(String::new());
+                return None;
+            } else if let Some(rating) = rating_key(ui) {
+                action = Some(ListAction::Rate(rating));
+            }
+        }
+
+        let root = browser.current_directory();
+        ScrollArea::vertical()
+            .id_salt("search_results")
+            .auto_shrink(false)
+            .show_rows(ui, ROW_HEIGHT, results.len(), |ui, rows| {
+                for i in rows {
+                    let e = &results[i];
+                    let label = format!("{}  ({})", e.name, relative_parent(root, &e.path));
+                    let response = ui.add(
+                        Button::selectable(selected == Some(i), label)
+                            .sense(Sense::click_and_drag()),
+                    );
+                    if response.drag_started() {
+                        action = Some(ListAction::Drag(e.path.clone()));
+                    } else if response.clicked() {
+                        action = Some(ListAction::PlaySearchResult(i));
+                    }
+                }
+            });
+        action
+    }
+
+    fn show_browse_list(
+        &mut self,
+        ui: &mut egui::Ui,
+        browser: &mut SampleBrowser,
+        keys: bool,
+    ) -> Option<ListAction> {
+        let skipped = browser.skipped_entries();
+        if !skipped.is_empty() {
+            ui.colored_label(
+                WARNING_TEXT_COLOR,
+                format!("{} entries couldn't be read", skipped.len()),
+            )
+            .on_hover_ui(|ui| {
+                for (path, e) in skipped.iter().take(20) {
+                    let name = path.file_name().unwrap_or(path.as_os_str());
+                    ui.label(format!("{}: {e}", name.to_string_lossy()));
+                }
+                if skipped.len() > 20 {
+                    ui.weak(format!("...and {} more", skipped.len() - 20));
+                }
+            });
+        }
+
+        let entry_count = browser.entries().len();
+        if entry_count == 0 {
+            if browser.current_directory().is_some() {
+                ui.weak("Empty directory.");
+            } else {
+                ui.weak("No folder open. Click Browse to get started.");
+            }
+            return None;
+        }
+
+        let selected = browser.selected();
+        if keys {
+            if let Some(action) = self.browse_keys(ui, browser, selected, entry_count) {
+                return Some(action);
+            }
+        }
+
+        let mut action = None;
+        let mut visible = 0..0;
+        ScrollArea::vertical()
+            .id_salt("browse_list")
+            .auto_shrink(false)
+            .show_rows(ui, ROW_HEIGHT, entry_count, |ui, rows| {
+                visible = rows.clone();
+                for i in rows {
+                    if let Some(a) = show_entry(ui, browser, i, selected == Some(i)) {
+                        action = Some(a);
+                    }
+                }
+            });
+        browser.request_metadata(visible.clone());
+        browser.request_overviews(visible);
+        action
+    }
+
+    /// Up/down move (and auto-preview plays), back leaves the folder, confirm opens or plays,
+    /// Delete trashes, 1–5 rate and 0 clears the rating. Moves apply here; the rest come back as actions.
+    fn browse_keys(
+        &self,
+        ui: &egui::Ui,
+        browser: &mut SampleBrowser,
+        selected: Option<usize>,
+        entry_count: usize,
+    ) -> Option<ListAction> {
+        if self.key(ui, NavKey::Up) {
+            browser.select(selected.unwrap_or(0).saturating_sub(1));
+        } else if self.key(ui, NavKey::Down) {
+            browser.select(selected.map_or(0, |i| (i + 1).min(entry_count - 1)));
+        } else if self.key(ui, NavKey::Back) {
+            if let Err(e) = browser.navigate_up() {
+                tracing::error!("navigate_up failed: {e}");
+            }
+        } else if let Some(i) = selected {
+            if ui.input(|input| input.key_pressed(Key::Delete)) {
+                return Some(ListAction::Delete(i));
+            }
+            if let Some(rating) = rating_key(ui) {
+                return Some(ListAction::Rate(rating));
+            }
+            let confirm =
+                self.key(ui, NavKey::Confirm) || ui.input(|input| input.key_pressed(Key::Enter));
+            if confirm {
+                let is_dir = browser.entries().get(i)?.is_directory;
+                return Some(ListAction::Open(i, is_dir));
+            }
+        }
+        None
+    }
+
+    /// Play/pause pauses and resumes playback, with Shift it stops; the
+    /// tab keybinds switch, create and close tabs, and the rest stop and
+    /// go to the search box.
+    fn handle_panel_keys(&mut self, ui: &egui::Ui, browser: &mut SampleBrowser) {
+        if self.key(ui, NavKey::PlayPause) {
+            let shift = ui.input(|input| input.modifiers.shift);
+            match browser.playback_status() {
+                _ if shift => browser.stop(),
+                PlaybackStatus::Playing { .. } => browser.pause(),
+                PlaybackStatus::Paused { .. } => browser.resume(),
+                PlaybackStatus::Loading { .. } | PlaybackStatus::Reconnecting { .. } => {
+                    browser.stop()
+                }
+                PlaybackStatus::Idle | PlaybackStatus::Finished { .. } => {
+                    if browser.is_in_search_mode() {
+                        let entry = browser
+                            .search_selected()
+                            .and_then(|i| browser.search_results()?.get(i).cloned());
+                        if let Some(entry) = entry {
+                            browser.play_entry(&entry);
+                        }
+                    } else {
+                        browser.play_selected();
+                    }
+                }
+            }
+        }
+
+        if let Some(keys) = self.note_keys.as_mut() {
+            let letters = keyboard::NOTE_KEYS
+                .iter()
+                .map(|&(letter, _)| letter)
+                .chain([keyboard::OCTAVE_DOWN, keyboard::OCTAVE_UP]);
+            for letter in letters {
+                let key = parse_key(letter.encode_utf8(&mut [0; 4]));
+                if key.is_some_and(|key| ui.input(|input| input.key_pressed(key))) {
+                    if let Some(note) = keys.press(letter) {
+                        browser.play_note(note);
+                    }
+                }
+            }
+            // The other keybinds can be letters too.
+            return;
+        }
+
+        let count = browser.tab_count();
+        let active = browser.active_tab();
+        if self.key(ui, NavKey::NextTab) {
+            browser.switch_tab((active + 1) % count);
+        } else if self.key(ui, NavKey::PrevTab) {
+            browser.switch_tab((active + count - 1) % count);
+        } else if self.key(ui, NavKey::NewTab) {
+            let start = browser.current_directory().map(Path::to_path_buf);
+            browser.new_tab(start.as_deref());
+        } else if self.key(ui, NavKey::CloseTab) {
+            browser.close_tab(active);
+        } else if self.key(ui, NavKey::Stop) {
+            browser.stop();
+        } else if self.key(ui, NavKey::FocusSearch) {
+            self.focus_search = true;
+        }
+    }
+
+    /// Whether the key bound to `nav` was pressed this frame.
+    fn key(&self, ui: &egui::Ui, nav: NavKey) -> bool {
+        let binds = &self.prefs.keybinds;
+        let (bind, fallback) = match nav {
+            NavKey::Up => (&binds.navigate_up, Key::W),
+            NavKey::Down => (&binds.navigate_down, Key::S),
+            NavKey::Back => (&binds.navigate_back, Key::A),
+            NavKey::Confirm => (&binds.confirm, Key::D),
+            NavKey::NewTab => (&binds.new_tab, Key::T),
+            NavKey::CloseTab => (&binds.close_tab, Key::X),
+            NavKey::PrevTab => (&binds.prev_tab, Key::ArrowLeft),
+            NavKey::NextTab => (&binds.next_tab, Key::ArrowRight),
+            NavKey::PlayPause => (&binds.play_pause, Key::Space),
+            NavKey::Stop => (&binds.stop, Key::Q),
+            NavKey::FocusSearch => (&binds.focus_search, Key::Slash),
+        };
+        let key = parse_key(bind).unwrap_or(fallback);
+        ui.input(|input| input.key_pressed(key))
+    }
+
+    /// Start a session recording in the configured folder, or finish the
+    /// one running.
+    fn toggle_recording(&self, browser: &mut SampleBrowser) {
+        if browser.recording().is_some() {
+            match browser.stop_recording() {
+                Some(Ok(file)) => tracing::info!("recorded {}", file.display()),
+                Some(Err(e)) => tracing::error!("recording failed: {e}"),
+                None => {}
+            }
+            return;
+        }
+        let Some(dir) = self.prefs.recording_folder() else {
+            tracing::error!("no folder to record into; set recordings_dir in the config");
+            return;
+        };
+        if let Err(e) = browser.start_recording(&dir) {
+            tracing::error!("couldn't start recording: {e}");
+        }
+    }
+
+    fn show_transport(&mut self, ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let response = ui.add(Slider::new(&mut self.volume, 0.0..=1.0).show_value(false));
+            if response.changed() {
+                browser.set_volume(self.volume);
+            }
+            let committed = response.drag_stopped() || (response.changed() && !response.dragged());
+            response.on_hover_text(format!("Volume: {}%", (self.volume * 100.0).round() as i32));
+            if committed {
+                self.prefs.volume = self.volume;
+                punks_core::config::save(&self.prefs);
+            }
+
+            // Per-sample trim while something is auditioning; remembered for
+            // the file.
+            if let PlaybackStatus::Loading { file, .. }
+            | PlaybackStatus::Playing { file, .. }
+            | PlaybackStatus::Paused { file, .. } = browser.playback_status()
+            {
+                let mut trim = browser.sample_gain_db(&file);
+                let response = ui.add(
+                    Slider::new(&mut trim, -24.0..=12.0)
+                        .suffix(" dB")
+                        .fixed_decimals(1),
+                );
+                if response.changed() {
+                    browser.set_sample_gain_db(&file, trim);
+                }
+                let reset = response.secondary_clicked();
+                if reset {
+                    browser.set_sample_gain_db(&file, 0.0);
+                }
+                if reset || response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    browser.save_sample_index();
+                }
+                response.on_hover_text("Trim for this sample, remembered (right-click to reset)");
+            }
+
+            // Varispeed: slowed down or sped up, pitch and all.
+            let mut rate = browser.rate();
+            let response = ui.add(
+                Slider::new(
+                    &mut rate,
+                    punks_playback::MIN_RATE..=punks_playback::MAX_RATE,
+                )
+                .logarithmic(true)
+                .suffix("x")
+                .fixed_decimals(2),
+            );
+            if response.changed() {
+                browser.set_rate(rate);
+            }
+            if response.secondary_clicked() {
+                browser.set_rate(1.0);
+            }
+            response.on_hover_text("Playback speed; pitch follows (right-click to reset)");
+
+            // Session recording: everything auditioned goes to a WAV.
+            let recording = browser.recording().map(Path::to_path_buf);
+            let button = match &recording {
+                Some(_) => Button::new("Stop recording").fill(RECORDING_COLOR),
+                None => Button::new("Record"),
+            };
+            let response = ui.add(button);
+            if response.clicked() {
+                self.toggle_recording(browser);
+            }
+            match (&recording, self.prefs.recording_folder()) {
+                (Some(file), _) => {
+                    response.on_hover_text(format!("Recording to {}", file.display()));
+                }
+                (None, Some(dir)) => {
+                    response.on_hover_text(format!(
+                        "Record everything you audition to a WAV in {}",
+                        dir.display()
+                    ));
+                }
+                (None, None) => {}
+            }
+
+            // Note keyboard: play the selected sample chromatically.
+            let button = match &self.note_keys {
+                Some(keys) => Button::new(format!(
+                    "Keys (A = {})",
+                    keyboard::note_name(keys.octave() * 12)
+                ))
+                .selected(true),
+                None => Button::new("Keys"),
+            };
+            let response = ui.add(button).on_hover_text(
+                "Play the selected sample chromatically from the keyboard: A to K are \
+                 the white keys from C (the sample's own pitch), W E T Y U O P the \
+                 black keys, Z / X an octave down / up. Letter keys don't navigate \
+                 while this is on.",
+            );
+            if response.clicked() {
+                self.note_keys = match self.note_keys {
+                    Some(_) => {
+                        browser.set_pitch(0.0);
+                        None
+                    }
+                    None => Some(NoteKeyboard::default()),
+                };
+            }
+
+            // Polyphonic preview: each sample rings out under the next.
+            let voices = browser.voices();
+            let label = match voices.len() {
+                0 => "Poly".to_string(),
+                n => format!("Poly ({n})"),
+            };
+            let response = ui
+                .add(Button::new(label).selected(browser.polyphonic()))
+                .on_hover_text(
+                    "Let each sample ring out under the next instead of stopping it, \
+                     to hear them together. Right-click to stop one; Stop stops them all.",
+                );
+            if response.clicked() {
+                self.prefs.polyphonic = !browser.polyphonic();
+                browser.set_polyphonic(self.prefs.polyphonic);
+                punks_core::config::save(&self.prefs);
+            }
+            response.context_menu(|ui| {
+                for voice in &voices {
+                    let name = voice.path.file_name().unwrap_or_default().to_string_lossy();
+                    if ui.button(format!("Stop {name}")).clicked() {
+                        browser.stop_voice(voice.id);
+                        ui.close();
+                    }
+                }
+            });
+        });
+
+        if let Some(err) = browser.last_error() {
+            ui.colored_label(ERROR_TEXT_COLOR, err);
+        }
+    }
+}
+
+impl Default for BrowserPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum NavKey {
+    Up,
+    Down,
+    Back,
+    Confirm,
+    NewTab,
+    CloseTab,
+    PrevTab,
+    NextTab,
+    PlayPause,
+    Stop,
+    FocusSearch,
+}
+
+fn show_breadcrumbs(ui: &mut egui::Ui, browser: &mut SampleBrowser) {
+    let crumbs = browser.breadcrumbs();
+    if crumbs.is_empty() {
+        return;
+    }
+    ui.separator();
+    let mut clicked = None;
+    ui.horizontal_wrapped(|ui| {
+        for (i, crumb) in crumbs.iter().enumerate() {
+            if i > 0 {
+                ui.weak(">");
+            }
+            if i < crumbs.len() - 1 {
+                if ui.small_button(crumb).clicked() {
+                    clicked = Some(i);
+                }
+            } else {
+                ui.label(crumb);
+            }
+        }
+    });
+    if let Some(i) = clicked {
+        if let Err(e) = browser.navigate_to_breadcrumb(i) {
+            tracing::error!("breadcrumb nav failed: {e}");
+        }
+    }
+}
+
+/// One row of the browse list.
+fn show_entry(
+    ui: &mut egui::Ui,
+    browser: &SampleBrowser,
+    index: usize,
+    selected: bool,
+) -> Option<ListAction> {
+    let e = &browser.entries()[index];
+    let label = if e.is_directory {
+        let count = match e.audio_count {
+            Some(0) => "  (empty)".to_string(),
+            Some(n) => format!("  ({n})"),
+            None => String::new(),
+        };
+        match browser.folder_size(&e.path) {
+            Some((size, done)) => format!(
+                "> {}{}   {}{}",
+                e.name,
+                count,
+                format_folder_size(&size),
+                if done { "" } else { "..." }
+            ),
+            None => format!("> {}{}", e.name, count),
+        }
+    } else {
+        match browser.file_info(&e.path).and_then(|info| info.duration) {
+            Some(d) => format!("{}   {}", e.name, format_length(d)),
+            None => e.name.clone(),
+        }
+    };
+    let playable = browser.is_playable(e);
+    let text = if e.is_directory {
+        RichText::new(label).color(DIR_TEXT_COLOR)
+    } else if !playable {
+        // List-only types (see Settings) are shown but don't play.
+        RichText::new(label).weak()
+    } else {
+        RichText::new(label)
+    };
+
+    let mut response = ui.add(
+        Button::selectable(selected, text)
+            .sense(Sense::click_and_drag())
+            .min_size(Vec2::new(ui.available_width(), 0.0)),
+    );
+    if playable {
+        if let Some(peaks) = browser.overview(&e.path) {
+            response = response.on_hover_ui(|ui| show_overview(ui, peaks));
+        }
+    }
+
+    let mut action = None;
+    // Virtual folders (playlists, CUE sheets) keep their extension, and CUE
+    // tracks share one file, so neither can be trashed singly.
+    let is_folder = e.is_directory && e.extension.is_empty();
+    if e.region.is_none() {
+        response.context_menu(|ui| {
+            if is_folder && ui.button("Compute size").clicked() {
+                action = Some(ListAction::ComputeSize(e.path.clone()));
+                ui.close();
+            }
+            if ui.button("Move to Trash").clicked() {
+                action = Some(ListAction::Delete(index));
+                ui.close();
+            }
+        });
+    }
+    if !e.is_directory && response.drag_started() {
+        action = Some(ListAction::Drag(e.path.clone()));
+    } else if response.clicked() {
+        action = Some(ListAction::Open(index, e.is_directory));
+    }
+    action
+}
+
+/// Min/max bars for `peaks` filling `rect`.
+fn paint_peaks(painter: &egui::Painter, peaks: &WaveformPeaks, rect: Rect, color: Color32) {
+    paint_buckets(painter, &peaks.peaks, rect, color);
+}
+
+/// `(min, max)` buckets spread evenly across `rect`.
+fn paint_buckets(painter: &egui::Painter, peaks: &[(f32, f32)], rect: Rect, color: Color32) {
+    let bar_w = (rect.width() / peaks.len().max(1) as f32).max(1.0);
+    let mid_y = rect.center().y;
+    let half_h = rect.height() / 2.0;
+    for (i, &(lo, hi)) in peaks.iter().enumerate() {
+        let x = rect.left() + i as f32 * bar_w;
+        let y_top = mid_y - hi * half_h;
+        let y_bot = (mid_y - lo * half_h).max(y_top + 1.0);
+        let bar = Rect::from_min_max(Pos2::new(x, y_top), Pos2::new(x + bar_w - 0.5, y_bot));
+        painter.rect_filled(bar, 0.0, color);
+    }
+}
+
+/// A file's overview inside a hover tooltip.
+fn show_overview(ui: &mut egui::Ui, peaks: &WaveformPeaks) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(256.0, 48.0), Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, WAVEFORM_BG);
+    paint_peaks(painter, peaks, rect, WAVEFORM_BAR);
+}
+
+/// Elapsed and total time of what's playing, as a bar across the panel. A
+/// click seeks there and a drag follows the pointer; the bar always spans
+/// the whole clip, however far the waveform is zoomed.
+fn show_progress_bar(ui: &mut egui::Ui, browser: &SampleBrowser, drag: &mut Option<f32>) {
+    let size = Vec2::new(ui.available_width(), PROGRESS_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, WAVEFORM_BG);
+
+    let (position, duration) = match browser.playback_status() {
+        PlaybackStatus::Playing {
+            position, duration, ..
+        }
+        | PlaybackStatus::Paused {
+            position, duration, ..
+        } if !duration.is_zero() => (position, duration),
+        _ => {
+            *drag = None;
+            return;
+        }
+    };
+    let total = duration.as_secs_f64();
+    let at = |x: f32| ((x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+
+    // Re-seek only once the pointer has moved a pixel, so a held-still one
+    // lets playback run on.
+    match response.interact_pointer_pos().map(|p| at(p.x)) {
+        Some(f) => {
+            if drag.is_none_or(|d| ((f - d) * rect.width()).abs() >= 1.0) {
+                browser.seek_fraction(f);
+                *drag = Some(f);
+            }
+        }
+        None => *drag = None,
+    }
+
+    let elapsed = drag.map_or(position.as_secs_f64(), |f| total * f64::from(f));
+    let fill = (elapsed / total).clamp(0.0, 1.0) as f32;
+    let filled = Rect::from_min_max(
+        rect.left_top(),
+        Pos2::new(rect.left() + rect.width() * fill, rect.bottom()),
+    );
+    painter.rect_filled(filled, 0.0, PROGRESS_FILL);
+    painter.text(
+        rect.center(),
+        Align2::CENTER_CENTER,
+        format!("{} / {}", format_hms(elapsed), format_hms(total)),
+        FontId::proportional(11.0),
+        WAVEFORM_TEXT,
+    );
+
+    if let Some(pos) = response.hover_pos() {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        let label = format_hms(total * f64::from(at(pos.x)));
+        response.on_hover_text_at_pointer(label);
+    }
+}
+
+/// The waveform of the loaded clip. The wheel zooms around the pointer;
+/// shift+wheel, a sideways scroll or a middle-button drag pans; a click or
+/// drag scrubs, a shift-drag selects a span and plays it, and an alt-drag
+/// sets an A/B loop that repeats until a click lands outside it.
+fn show_waveform(ui: &mut egui::Ui, browser: &SampleBrowser, state: &mut WaveformState) {
+    let size = Vec2::new(ui.available_width(), WAVEFORM_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    let font = FontId::proportional(13.0);
+    let top_left = rect.left_top() + Vec2::new(4.0, 2.0);
+    let mid_left = rect.left_center() + Vec2::new(4.0, 0.0);
+    let across = |x: f32| f64::from((x - rect.left()) / rect.width());
+
+    let loaded = browser.loaded_clip();
+    if state.clip.as_ref().map(|(p, r)| (p.as_path(), *r)) != loaded {
+        *state = WaveformState {
+            clip: loaded.map(|(p, r)| (p.to_path_buf(), r)),
+            ..WaveformState::default()
+        };
+    }
+    if response.hovered() {
+        let (scroll, zoom, shift) =
+            ui.input(|i| (i.smooth_scroll_delta, i.zoom_delta(), i.modifiers.shift));
+        let pointer = response.hover_pos().map_or(0.5, |p| across(p.x));
+        let (wheel, sideways) = if shift {
+            (0.0, scroll.x + scroll.y)
+        } else {
+            (scroll.y, scroll.x)
+        };
+        let factor = WAVEFORM_ZOOM_STEP.powf(f64::from(wheel) / 50.0) / f64::from(zoom);
+        if factor != 1.0 {
+            state.view.zoom(factor, pointer);
+        }
+        if sideways != 0.0 {
+            state.view.pan(f64::from(-sideways / rect.width()));
+        }
+    }
+    if response.dragged_by(egui::PointerButton::Middle) {
+        state
+            .view
+            .pan(f64::from(-response.drag_delta().x / rect.width()));
+    }
+
+    let view = state.view;
+    let x_at = |fraction: f64| rect.left() + view.x_of(fraction) as f32 * rect.width();
+
+    painter.rect_filled(rect, 0.0, WAVEFORM_BG);
+    match browser.waveform_levels() {
+        Some(levels) if view.is_zoomed() => {
+            let peaks = levels.view(view.start, view.end, rect.width() as usize);
+            paint_buckets(&painter, &peaks, rect, WAVEFORM_BAR);
+        }
+        _ => {
+            if let Some(peaks) = browser.waveform_peaks() {
+                paint_peaks(&painter, peaks, rect, WAVEFORM_BAR);
+            }
+        }
+    }
+    let pointer_fraction = response
+        .interact_pointer_pos()
+        .map(|p| view.fraction_at(across(p.x)));
+    let (shift, alt) = ui.input(|i| (i.modifiers.shift, i.modifiers.alt));
+    if response.drag_started_by(egui::PointerButton::Primary) && (shift || alt) {
+        state.dragging = pointer_fraction.map(|f| (f, f));
+        state.loop_drag = alt;
+    }
+    if let (Some(drag), Some(f)) = (&mut state.dragging, pointer_fraction) {
+        drag.1 = f;
+    }
+    let dragged = state.dragging.map(|(a, b)| (a.min(b), a.max(b)));
+    let selection = dragged.filter(|_| !state.loop_drag).or(state.selection);
+    let ab_loop = match dragged.filter(|_| state.loop_drag) {
+        Some(span) => Some(span),
+        None => browser
+            .loop_region()
+            .zip(browser.loaded_duration())
+            .map(|((a, b), dur)| {
+                let secs = dur.as_secs_f64();
+                (a.as_secs_f64() / secs, b.as_secs_f64() / secs)
+            }),
+    };
+    for (span, color) in [(selection, WAVEFORM_SELECTION), (ab_loop, WAVEFORM_LOOP)] {
+        if let Some((from, to)) = span {
+            let span = Rect::from_x_y_ranges(x_at(from)..=x_at(to), rect.y_range());
+            painter.rect_filled(span.intersect(rect), 0.0, color);
+        }
+    }
+
+    let name = |file: &Path| {
+        file.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("?")
+            .to_string()
+    };
+    let status = browser.playback_status();
+    let paused = matches!(status, PlaybackStatus::Paused { .. });
+    match status {
+        PlaybackStatus::Playing {
+            file,
+            position,
+            duration,
+        }
+        | PlaybackStatus::Paused {
+            file,
+            position,
+            duration,
+        } => {
+            let dur_secs = duration.as_secs_f64();
+            if dur_secs > 0.0 {
+                let x = x_at(position.as_secs_f64() / dur_secs);
+                if rect.x_range().contains(x) {
+                    painter.vline(x, rect.y_range(), Stroke::new(1.0, WAVEFORM_PLAYHEAD));
+                }
+            }
+            let text = format!("{}{}", name(&file), if paused { "  (paused)" } else { "" });
+            painter.text(top_left, Align2::LEFT_TOP, text, font, WAVEFORM_TEXT);
+        }
+        PlaybackStatus::Loading { file, duration } => {
+            let text = match duration {
+                Some(d) => format!("Loading: {} ({})...", name(&file), format_length(d)),
+                None => format!("Loading: {}...", name(&file)),
+            };
+            painter.text(mid_left, Align2::LEFT_CENTER, text, font, WAVEFORM_TEXT);
+        }
+        PlaybackStatus::Reconnecting { error } => {
+            let text = format!("Audio device lost, reconnecting... ({error})");
+            painter.text(
+                mid_left,
+                Align2::LEFT_CENTER,
+                text,
+                font,
+                WARNING_TEXT_COLOR,
+            );
+        }
+        PlaybackStatus::Finished { file } => {
+            let text = format!("{}  (ended)", name(&file));
+            painter.text(top_left, Align2::LEFT_TOP, text, font, WAVEFORM_TEXT);
+        }
+        PlaybackStatus::Idle => {
+            if browser.waveform_peaks().is_none() {
+                painter.text(mid_left, Align2::LEFT_CENTER, "Idle", font, Color32::GRAY);
+            }
+        }
+    }
+
+    let Some(duration) = browser.loaded_duration() else {
+        state.scrub_last_x = None;
+        state.dragging = None;
+        return;
+    };
+
+    // Hover / scrub crosshair and the time under the pointer.
+    if let Some(pos) = response.hover_pos().or(response.interact_pointer_pos()) {
+        let x = pos.x.clamp(rect.left(), rect.right());
+        let stroke = Stroke::new(1.0, WAVEFORM_HOVER);
+        painter.vline(x, rect.y_range(), stroke);
+        let mid = rect.center().y;
+        painter.hline(x - 4.0..=x + 4.0, mid, stroke);
+        let fraction = view.fraction_at(across(x));
+        let label = format_hms(duration.as_secs_f64() * fraction);
+        let label_x = (x + 4.0).clamp(rect.left() + 2.0, rect.right() - 36.0);
+        painter.text(
+            Pos2::new(label_x, rect.top() + 2.0),
+            Align2::LEFT_TOP,
+            label,
+            FontId::proportional(13.0),
+            WAVEFORM_TEXT,
+        );
+        ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+    }
+
+    // Letting go of a shift-drag plays the selection; of an alt-drag,
+    // loops it.
+    if state.dragging.is_some() {
+        if !response.dragged() {
+            state.dragging = None;
+            if let Some((from, to)) = dragged.filter(|(from, to)| to > from) {
+                let secs = duration.as_secs_f64();
+                let (start, end) = (
+                    Duration::from_secs_f64(secs * from),
+                    Duration::from_secs_f64(secs * to),
+                );
+                if state.loop_drag {
+                    browser.set_loop_region(start, end);
+                } else {
+                    state.selection = Some((from, to));
+                    browser.play_span(Region {
+                        start,
+                        end: Some(end),
+                    });
+                }
+            }
+        }
+        state.scrub_last_x = None;
+        return;
+    }
+
+    // Click seeks once; drag follows the pointer, re-seeking only when it
+    // moved at least a pixel so a held-still pointer lets audio play on.
+    let pressed = response.clicked()
+        || (response.is_pointer_button_down_on() && ui.input(|i| i.pointer.primary_down()));
+    match response.interact_pointer_pos().filter(|_| pressed) {
+        Some(pos) => {
+            let x = pos.x.clamp(rect.left(), rect.right());
+            if state
+                .scrub_last_x
+                .is_none_or(|last| (x - last).abs() >= 1.0)
+            {
+                browser.seek_fraction(view.fraction_at(across(x)) as f32);
+                state.scrub_last_x = Some(x);
+            }
+        }
+        None => state.scrub_last_x = None,
+    }
+}
+
+/// Container metadata, loudness and level warnings for the loaded sample,
+/// one line. A blank line is reserved when there's nothing so the layout
+/// doesn't jump.
+fn show_track_details(ui: &mut egui::Ui, browser: &SampleBrowser) {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(info) = browser.current_track_info() {
+        if let Some(desc) = info.metadata.description.as_deref() {
+            if !desc.is_empty() {
+                parts.push(desc.to_string());
+            }
+        }
+        if info.truncated {
+            parts.push(format!(
+                "preview: first {} of {}",
+                format_hms(info.preview_duration.as_secs_f64()),
+                format_hms(info.source_duration.as_secs_f64()),
+            ));
+        }
+        if let Some(lufs) = info.loudness {
+            parts.push(format!("{lufs:.1} LUFS"));
+        }
+        // Shown always, not just over 0 dBTP: with the loudness it says how
+        // hard the sample was mastered.
+        if info.true_peak > 0.0 {
+            parts.push(format!(
+                "true peak {:+.1} dBTP",
+                punks_playback::analysis::gain_to_db(info.true_peak)
+            ));
+        }
+        if info.clipped > 0 {
+            parts.push(format!("{} clipped samples", info.clipped));
+        }
+        if info.sanitized > 0 {
+            parts.push(format!("{} bad samples repaired", info.sanitized));
+        }
+    }
+    ui.weak(parts.join("   \u{b7}   "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_imgui_and_egui_key_names() {
+        assert_eq!(parse_key("W"), Some(Key::W));
+        assert_eq!(parse_key("UpArrow"), Some(Key::ArrowUp));
+        assert_eq!(parse_key("ArrowUp"), Some(Key::ArrowUp));
+        assert_eq!(parse_key("Enter"), Some(Key::Enter));
+        assert_eq!(parse_key("Slash"), Some(Key::Slash));
+        assert_eq!(parse_key("nope"), None);
+    }
+}
@@ -1,9 +1,20 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use imgui::Key;
-use punks_browser::{PlaybackStatus, SampleBrowser};
-use punks_core::config::{Keybinds, PunksConfig};
+use punks_browser::keyboard::{self, NoteKeyboard};
+use punks_browser::midi::{self, MidiAction, PadSource};
+use punks_browser::{
+    Audition, BitDepth, Category, ExportSpec, ExportedFile, ExtensionRegistry, FileEntry, FileInfo,
+    IntegrityOutcome, LoopSync, Normalize, OutputMode, PlaybackStatus, ProcessOptions, Region,
+    SampleBrowser, SortMode, WaveformPeaks, WaveformView, MAX_BPM, MIN_BPM,
+};
+use punks_core::config::{CustomExtension, Keybinds, Profile, PunksConfig, WatchFolder};
+use punks_core::index::MAX_RATING;
+use punks_core::{dj, history};
+use punks_playback::analysis::tempo;
+use punks_playback::export;
 
 #[derive(Clone, Copy, PartialEq)]
 enum BrowserAction {
@@ -15,6 +26,9 @@ enum BrowserAction {
     CloseTab,
     PrevTab,
     NextTab,
+    PlayPause,
+    Stop,
+    FocusSearch,
 }
 
 const CAPTURABLE_KEYS: &[(Key, &str)] = &[
@@ -51,6 +65,8 @@ const CAPTURABLE_KEYS: &[(Key, &str)] = &[
     (Key::LeftArrow, "LeftArrow"),
     (Key::RightArrow, "RightArrow"),
     (Key::Tab, "Tab"),
+    (Key::Slash, "Slash"),
+    (Key::Backspace, "Backspace"),
 ];
 
 fn parse_key(s: &str) -> Option<Key> {
@@ -78,6 +94,9 @@ fn keybind_field_mut(keybinds: &mut Keybinds, action: BrowserAction) -> &mut Str
         BrowserAction::CloseTab => &mut keybinds.close_tab,
         BrowserAction::PrevTab => &mut keybinds.prev_tab,
         BrowserAction::NextTab => &mut keybinds.next_tab,
+        BrowserAction::PlayPause => &mut keybinds.play_pause,
+        BrowserAction::Stop => &mut keybinds.stop,
+        BrowserAction::FocusSearch => &mut keybinds.focus_search,
     }
 }
 
@@ -91,6 +110,9 @@ fn keybind_field(keybinds: &Keybinds, action: BrowserAction) -> &str {
         BrowserAction::CloseTab => &keybinds.close_tab,
         BrowserAction::PrevTab => &keybinds.prev_tab,
         BrowserAction::NextTab => &keybinds.next_tab,
+        BrowserAction::PlayPause => &keybinds.play_pause,
+        BrowserAction::Stop => &keybinds.stop,
+        BrowserAction::FocusSearch => &keybinds.focus_search,
     }
 }
 
@@ -103,8 +125,51 @@ const KEYBIND_ACTIONS: &[(BrowserAction, &str)] = &[
     (BrowserAction::CloseTab, "Close tab"),
     (BrowserAction::PrevTab, "Previous tab"),
     (BrowserAction::NextTab, "Next tab"),
+    (BrowserAction::PlayPause, "Play / Pause"),
+    (BrowserAction::Stop, "Stop"),
+    (BrowserAction::FocusSearch, "Search"),
 ];
 
+/// The key for each action, from the config's [`Keybinds`]; a binding that
+/// isn't one of [`CAPTURABLE_KEYS`] falls back to the default.
+struct KeyMap {
+    up: Key,
+    down: Key,
+    back: Key,
+    confirm: Key,
+    new_tab: Key,
+    close_tab: Key,
+    prev_tab: Key,
+    next_tab: Key,
+    play_pause: Key,
+    stop: Key,
+    focus_search: Key,
+}
+
+impl KeyMap {
+    fn new(binds: &Keybinds) -> Self {
+        let defaults = Keybinds::default();
+        let key = |action| {
+            parse_key(keybind_field(binds, action))
+                .or_else(|| parse_key(keybind_field(&defaults, action)))
+                .expect("default keybinds are capturable keys")
+        };
+        KeyMap {
+            up: key(BrowserAction::NavigateUp),
+            down: key(BrowserAction::NavigateDown),
+            back: key(BrowserAction::NavigateBack),
+            confirm: key(BrowserAction::Confirm),
+            new_tab: key(BrowserAction::NewTab),
+            close_tab: key(BrowserAction::CloseTab),
+            prev_tab: key(BrowserAction::PrevTab),
+            next_tab: key(BrowserAction::NextTab),
+            play_pause: key(BrowserAction::PlayPause),
+            stop: key(BrowserAction::Stop),
+            focus_search: key(BrowserAction::FocusSearch),
+        }
+    }
+}
+
 // Tab palette: active tab carries a muted blue accent, inactive tabs are grey.
 const TAB_ACTIVE_BG: [f32; 4] = [0.24, 0.36, 0.52, 1.0];
 const TAB_ACTIVE_HOVER: [f32; 4] = [0.28, 0.41, 0.59, 1.0];
@@ -114,11 +179,31 @@ const TAB_CLOSE_TEXT: [f32; 4] = [0.70, 0.72, 0.76, 1.0];
 
 const DIR_TEXT_COLOR: [f32; 4] = [0.55, 0.85, 1.0, 1.0];
 
-// File list lays out entries in width-adaptive columns; each column is at least
+const RECORDING_COLOR: [f32; 4] = [0.70, 0.15, 0.15, 1.0];
+
+/// The browse table's columns: heading, the order clicking it sorts by
+/// (`None` for one it can't), and starting width (or weight, for the name,
+/// which takes the room left).
+const LIST_COLUMNS: [(&str, Option<SortMode>, f32); 6] = [
+    ("Name", Some(SortMode::Name), 1.0),
+    ("Length", Some(SortMode::Duration), 56.0),
+    ("Size", Some(SortMode::Size), 72.0),
+    ("Type", Some(SortMode::Extension), 48.0),
+    ("BPM", None, 40.0),
+    ("Modified", Some(SortMode::Modified), 112.0),
+];
+
+// Search results lay out in width-adaptive columns; each column is at least
 // this wide, so wide windows show 2+ columns and narrow ones collapse to 1.
 const MIN_COLUMN_WIDTH: f32 = 300.0;
 const COLUMN_GUTTER: f32 = 8.0;
 
+/// Seconds decoded before a long file starts when quick audition is first
+/// switched on.
+const DEFAULT_QUICK_AUDITION_SECS: f32 = 5.0;
+/// Memory cap in MiB when the limit is switched back on.
+const DEFAULT_MEMORY_BUDGET_MB: u32 = 1024;
+
 fn column_count(avail_width: f32) -> usize {
     ((avail_width / MIN_COLUMN_WIDTH).floor() as usize).max(1)
 }
@@ -147,6 +232,57 @@ fn format_timecode(samples: u64, sample_rate: u32) -> String {
     format!("{h:02}:{m:02}:{s:02}.{ms:03}")
 }
 
+/// A sample's length: tenths of a second for one-shots, `m:ss` past a minute.
+fn format_length(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", d.as_secs_f32())
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// A file's format for its row's tooltip, e.g. `48 kHz 24-bit stereo`;
+/// whatever the headers didn't say is left out.
+fn describe_format(info: &FileInfo) -> String {
+    let mut text = format!("{} kHz", f64::from(info.sample_rate) / 1000.0);
+    if let Some(bits) = info.bit_depth {
+        text.push_str(&format!(" {bits}-bit"));
+    }
+    text.push_str(match info.channels {
+        1 => " mono",
+        2 => " stereo",
+        _ => "",
+    });
+    if info.channels > 2 {
+        text.push_str(&format!(" {}ch", info.channels));
+    }
+    text
+}
+
+/// A file's tempo for its row: from its length once that's been probed,
+/// until then only from its name, so drawing a row never reads the file.
+fn row_bpm(browser: &SampleBrowser, path: &Path) -> Option<f32> {
+    match browser.file_info(path).and_then(|info| info.duration) {
+        Some(length) => tempo::loop_tempo(path, length).map(|t| t.bpm),
+        None => tempo::bpm_from_name(&path.file_stem()?.to_string_lossy()),
+    }
+}
+
+/// The files in the current view: search results when searching, otherwise
+/// the open folder's.
+fn files_in_view(browser: &SampleBrowser) -> Vec<PathBuf> {
+    match browser.search_results() {
+        Some(results) => results.iter().map(|e| e.path.clone()).collect(),
+        None => browser
+            .entries()
+            .iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.path.clone())
+            .collect(),
+    }
+}
+
 const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
 
 fn relative_parent(root: Option<&Path>, file_path: &Path) -> String {
@@ -169,6 +305,188 @@ fn relative_parent(root: Option<&Path>, file_path: &Path) -> String {
         .unwrap_or_default()
 }
 
+/// Zoom, pan and selection on the waveform, kept between frames.
+#[derive(Default)]
+struct WaveformState {
+    view: WaveformView,
+    /// The clip `view` is of; another one starts zoomed out.
+    clip: Option<(PathBuf, Option<Region>)>,
+    /// Last mouse-x we seeked to during a waveform drag, so a held-still cursor
+    /// lets audio play forward instead of re-seeking every frame. `None` when
+    /// not scrubbing.
+    scrub_last_x: Option<f32>,
+    /// A shift- or alt-drag selection in progress: where it started and
+    /// where the mouse is, as fractions of the clip.
+    dragging: Option<(f64, f64)>,
+    /// Whether `dragging` sets an A/B loop (alt) rather than a span to play
+    /// once (shift).
+    loop_drag: bool,
+    /// The span last selected, shown until the clip changes.
+    selection: Option<(f64, f64)>,
+    /// How saving the loop or selection as a file last went.
+    saved: Option<Result<PathBuf, String>>,
+}
+
+/// Batch-process modal choices, kept between openings.
+struct BatchForm {
+    trim: bool,
+    threshold_db: f32,
+    /// Index into `NORMALIZE_MODES`.
+    normalize: usize,
+    peak_dbfs: f32,
+    lufs: f32,
+    /// `None` processes in place.
+    output_dir: Option<PathBuf>,
+}
+
+const NORMALIZE_MODES: &[&str] = &["Off", "Peak", "Loudness (LUFS)"];
+
+/// The `NORMALIZE_MODES` choice at `index`, to the targets given.
+fn normalize_mode(index: usize, peak_dbfs: f32, lufs: f32) -> Normalize {
+    match index {
+        1 => Normalize::Peak {
+            target_dbfs: peak_dbfs,
+        },
+        2 => Normalize::Loudness { target_lufs: lufs },
+        _ => Normalize::Off,
+    }
+}
+
+/// The normalize combo and, under it, the chosen mode's target.
+fn draw_normalize(ui: &imgui::Ui, index: &mut usize, peak_dbfs: &mut f32, lufs: &mut f32) {
+    ui.set_next_item_width(160.0);
+    ui.combo_simple_string("Normalize", index, NORMALIZE_MODES);
+    match *index {
+        1 => {
+            ui.set_next_item_width(160.0);
+            ui.slider_config("Peak target", -24.0_f32, 0.0_f32)
+                .display_format("%.1f dBFS")
+                .build(peak_dbfs);
+        }
+        2 => {
+            ui.set_next_item_width(160.0);
+            ui.slider_config("Loudness target", -36.0_f32, -6.0_f32)
+                .display_format("%.1f LUFS")
+                .build(lufs);
+        }
+        _ => {}
+    }
+}
+
+/// The loop sync choices, and what the transport row calls them.
+const LOOP_SYNC_MODES: [LoopSync; 3] = [LoopSync::Off, LoopSync::Align, LoopSync::Stretch];
+const LOOP_SYNC_LABELS: &[&str] = &["Loops: free", "Loops: in time", "Loops: stretched"];
+
+impl Default for BatchForm {
+    fn default() -> Self {
+        BatchForm {
+            trim: true,
+            threshold_db: -60.0,
+            normalize: 1,
+            peak_dbfs: -1.0,
+            lufs: -14.0,
+            output_dir: None,
+        }
+    }
+}
+
+impl BatchForm {
+    fn options(&self) -> ProcessOptions {
+        ProcessOptions {
+            trim_silence: self.trim,
+            silence_threshold_db: self.threshold_db,
+            normalize: normalize_mode(self.normalize, self.peak_dbfs, self.lufs),
+            output: match &self.output_dir {
+                Some(dir) => OutputMode::Folder(dir.clone()),
+                None => OutputMode::InPlace,
+            },
+        }
+    }
+}
+
+/// Auto-tag modal choices, kept between openings.
+#[derive(Default)]
+struct AutoTagForm {
+    /// Suggestions shown: 0 for all, else those of `Category::ALL[i - 1]`.
+    category: usize,
+    /// Samples ticked for confirming or rejecting.
+    picked: HashSet<PathBuf>,
+}
+
+/// Collections modal state, kept between openings.
+#[derive(Default)]
+struct CollectionForm {
+    /// The collection shown.
+    selected: Option<String>,
+    /// A name typed for a new collection, or for renaming `selected`.
+    name: String,
+}
+
+/// Export modal choices, kept between openings.
+struct ExportForm {
+    /// The sample to export, from the row it was asked for on.
+    entry: Option<FileEntry>,
+    /// Ask for the modal next frame, outside the row menu's ID scope.
+    open: bool,
+    /// 0 keeps the source's rate, else `export::SAMPLE_RATES[i - 1]`.
+    rate: usize,
+    /// Index into `BitDepth::ALL`.
+    depth: usize,
+    dither: bool,
+    /// Index into `NORMALIZE_MODES`.
+    normalize: usize,
+    peak_dbfs: f32,
+    lufs: f32,
+    /// Bake in the preview's trim, speed and loudness target, so the file
+    /// sounds as it was auditioned.
+    bake: bool,
+    /// How the last export went.
+    result: Option<Result<ExportedFile, String>>,
+}
+
+impl Default for ExportForm {
+    fn default() -> Self {
+        ExportForm {
+            entry: None,
+            open: false,
+            rate: 0,
+            depth: 0,
+            dither: true,
+            normalize: 0,
+            peak_dbfs: -1.0,
+            lufs: -14.0,
+            bake: false,
+            result: None,
+        }
+    }
+}
+
+impl ExportForm {
+    fn spec(&self) -> ExportSpec {
+        ExportSpec {
+            sample_rate: self
+                .rate
+                .checked_sub(1)
+                .and_then(|i| export::SAMPLE_RATES.get(i).copied()),
+            bit_depth: BitDepth::ALL[self.depth],
+            dither: self.dither,
+            normalize: normalize_mode(self.normalize, self.peak_dbfs, self.lufs),
+            ..ExportSpec::default()
+        }
+    }
+
+    /// [`spec`](Self::spec), with the preview of `path` (or `region` of it)
+    /// baked in if asked for.
+    fn spec_for(&self, browser: &SampleBrowser, path: &Path, region: Option<Region>) -> ExportSpec {
+        let spec = self.spec();
+        if self.bake {
+            browser.baked_spec(path, region, &spec)
+        } else {
+            spec
+        }
+    }
+}
+
 pub struct BrowserPanel {
     prefs: PunksConfig,
     rebinding: Option<BrowserAction>,
@@ -180,10 +498,35 @@ pub struct BrowserPanel {
     /// Tracks the active tab between frames so the search box can be reloaded
     /// from the newly active tab's stored query when the user switches tabs.
     last_active_tab: usize,
-    /// Last mouse-x we seeked to during a waveform drag, so a held-still cursor
-    /// lets audio play forward instead of re-seeking every frame. `None` when
-    /// not scrubbing.
-    scrub_last_x: Option<f32>,
+    waveform: WaveformState,
+    /// Put the cursor in the search box next frame.
+    focus_search: bool,
+    /// Where a drag on the progress bar is, as a fraction of the clip; the
+    /// bar shows it rather than the playback position, which lags the seek.
+    progress_drag: Option<f32>,
+    batch_form: BatchForm,
+    auto_tag_form: AutoTagForm,
+    export_form: ExportForm,
+    collection_form: CollectionForm,
+    /// Entry the row context menu was opened on: index, path, and whether
+    /// it's a real folder (as opposed to a file or virtual folder).
+    context_entry: Option<(usize, PathBuf, bool)>,
+    /// Extension being typed into Settings → Extra file types.
+    extension_buf: String,
+    /// Name filter in the History modal.
+    history_filter: String,
+    /// Tag being typed for the loaded sample.
+    tag_buf: String,
+    /// The note keyboard, while it's on.
+    note_keys: Option<NoteKeyboard>,
+    /// MIDI input ports as of the last rescan; listing them opens a MIDI
+    /// client, so it isn't done every frame.
+    midi_ports: Vec<String>,
+    /// Output devices as of the last listing; filled the first time the
+    /// picker opens and on rescan.
+    output_devices: Vec<String>,
+    #[cfg(feature = "freesound")]
+    freesound_query: String,
 }
 
 impl BrowserPanel {
@@ -199,7 +542,22 @@ impl BrowserPanel {
             last_searched_query: String::new(),
             volume,
             last_active_tab: 0,
-            scrub_last_x: None,
+            waveform: WaveformState::default(),
+            focus_search: false,
+            progress_drag: None,
+            batch_form: BatchForm::default(),
+            auto_tag_form: AutoTagForm::default(),
+            export_form: ExportForm::default(),
+            collection_form: CollectionForm::default(),
+            context_entry: None,
+            extension_buf: String::new(),
+            history_filter: String::new(),
+            tag_buf: String::new(),
+            note_keys: None,
+            midi_ports: midi::input_ports(),
+            output_devices: Vec::new(),
+            #[cfg(feature = "freesound")]
+            freesound_query: String::new(),
         }
     }
 
@@ -210,6 +568,18 @@ impl BrowserPanel {
         &self.prefs
     }
 
+    /// Remember the selected entry and the window's `size` (logical pixels)
+    /// for the next launch; the app shell calls this on quit. The folder,
+    /// volume and toggles are saved as they change.
+    pub fn save_session(&mut self, browser: &SampleBrowser, size: [f64; 2]) {
+        self.prefs.last_selected = browser
+            .selected()
+            .and_then(|i| browser.entries().get(i))
+            .map(|e| e.path.clone());
+        self.prefs.window_size = Some(size);
+        punks_core::config::save(&self.prefs);
+    }
+
     pub fn draw(
         &mut self,
         ui: &imgui::Ui,
@@ -327,7 +697,7 @@ impl BrowserPanel {
         if ui.button("Browse...") {
             if let Some(path) = rfd::FileDialog::new().pick_folder() {
                 if let Err(e) = browser.open_directory(&path) {
-                    log::error!("failed to open directory: {e}");
+                    tracing::error!("failed to open directory: {e}");
                 } else {
                     // last_directory is persisted centrally at the top of draw().
                     self.search_buf.clear();
@@ -337,11 +707,34 @@ impl BrowserPanel {
             }
         }
 
+        ui.same_line();
+        let back_disabled = ui.begin_disabled(!browser.can_go_back());
+        if ui.button("<##back") {
+            if let Err(e) = browser.navigate_back() {
+                tracing::error!("navigate_back failed: {e}");
+            }
+        }
+        back_disabled.end();
+        if ui.is_item_hovered_with_flags(imgui::ItemHoveredFlags::ALLOW_WHEN_DISABLED) {
+            ui.tooltip_text("Back (Alt+Left)");
+        }
+        ui.same_line();
+        let forward_disabled = ui.begin_disabled(!browser.can_go_forward());
+        if ui.button(">##forward") {
+            if let Err(e) = browser.navigate_forward() {
+                tracing::error!("navigate_forward failed: {e}");
+            }
+        }
+        forward_disabled.end();
+        if ui.is_item_hovered_with_flags(imgui::ItemHoveredFlags::ALLOW_WHEN_DISABLED) {
+            ui.tooltip_text("Forward (Alt+Right)");
+        }
+
         if browser.can_navigate_up() {
             ui.same_line();
             if ui.button("^  Up") {
                 if let Err(e) = browser.navigate_up() {
-                    log::error!("navigate_up failed: {e}");
+                    tracing::error!("navigate_up failed: {e}");
                 }
             }
         }
@@ -351,7 +744,88 @@ impl BrowserPanel {
             ui.open_popup("Settings##modal");
         }
 
+        ui.same_line();
+        if ui.button("Process...") {
+            ui.open_popup("Batch process##modal");
+        }
+
+        ui.same_line();
+        if ui.button("Auto-tag...") {
+            ui.open_popup("Auto-tag##modal");
+        }
+
+        ui.same_line();
+        if ui.button("Verify...") {
+            ui.open_popup("Verify library##modal");
+        }
+
+        ui.same_line();
+        if ui.button("Duplicates...") {
+            ui.open_popup("Duplicates##modal");
+        }
+
+        ui.same_line();
+        if ui.button("History") {
+            ui.open_popup("History##modal");
+        }
+
+        ui.same_line();
+        if ui.button("Favorites") {
+            ui.open_popup("Favorites##modal");
+        }
+
+        ui.same_line();
+        if ui.button("Collections") {
+            ui.open_popup("Collections##modal");
+        }
+
+        ui.same_line();
+        if ui.button("Recently added") {
+            ui.open_popup("Recently added##modal");
+        }
+
+        ui.same_line();
+        if ui.button(format!("Layers ({})##layers", browser.layers().len())) {
+            ui.open_popup("Layers##modal");
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Ctrl+click samples to stack them, then audition them together");
+        }
+
+        #[cfg(feature = "freesound")]
+        {
+            ui.same_line();
+            if ui.button("Freesound...") {
+                ui.open_popup("Freesound##modal");
+            }
+        }
+
+        #[cfg(feature = "scripting")]
+        {
+            ui.same_line();
+            if ui.button("Script...") {
+                ui.open_popup("Run script##modal");
+            }
+        }
+
+        if std::mem::take(&mut self.export_form.open) {
+            ui.open_popup("Export as WAV##modal");
+        }
         self.draw_settings_modal(ui, browser);
+        self.draw_batch_modal(ui, browser);
+        self.draw_export_modal(ui, browser);
+        self.draw_auto_tag_modal(ui, browser);
+        self.draw_integrity_modal(ui, browser);
+        self.draw_duplicates_modal(ui, browser);
+        self.draw_history_modal(ui, browser);
+        self.draw_favorites_modal(ui, browser);
+        self.draw_collections_modal(ui, browser);
+        self.draw_recently_added_modal(ui, browser);
+        self.draw_layers_modal(ui, browser);
+        #[cfg(feature = "freesound")]
+        self.draw_freesound_modal(ui, browser);
+        #[cfg(feature = "scripting")]
+        self.draw_script_modal(ui, browser);
 
         let crumbs = browser.breadcrumbs();
         if !crumbs.is_empty() {
@@ -365,7 +839,7 @@ impl BrowserPanel {
                 if i < crumbs.len() - 1 {
                     if ui.small_button(format!("{}##crumb{}", crumb, i)) {
                         if let Err(e) = browser.navigate_to_breadcrumb(i) {
-                            log::error!("breadcrumb nav failed: {e}");
+                            tracing::error!("breadcrumb nav failed: {e}");
                         }
                     }
                 } else {
@@ -376,13 +850,29 @@ impl BrowserPanel {
 
         ui.separator();
 
+        let keymap = KeyMap::new(&self.prefs.keybinds);
         let avail = ui.content_region_avail();
-        ui.set_next_item_width(avail[0]);
+        ui.set_next_item_width((avail[0] * 0.6).floor());
+        if std::mem::take(&mut self.focus_search) {
+            ui.set_keyboard_focus_here();
+        }
         ui.input_text("##search", &mut self.search_buf)
             .hint("Search...")
             .build();
+        let mut search_focused = ui.is_item_active();
 
-        let search_focused = ui.is_item_active();
+        // Narrows the folder straight away, without a search.
+        ui.same_line();
+        ui.set_next_item_width(ui.content_region_avail()[0]);
+        let mut filter = browser.filter().to_string();
+        if ui
+            .input_text("##filter", &mut filter)
+            .hint("Filter this folder...")
+            .build()
+        {
+            browser.set_filter(&filter);
+        }
+        search_focused |= ui.is_item_active();
 
         if self.search_buf != self.last_typed_query {
             self.last_typed_query = self.search_buf.clone();
@@ -399,72 +889,79 @@ impl BrowserPanel {
             }
         }
 
-        if browser.is_searching() && !browser.is_in_search_mode() {
-            ui.text_disabled("Searching...");
+        if let Some(progress) = browser.search_progress() {
+            ui.text_disabled(format!("Searching... {progress}"));
         }
 
         let avail = ui.content_region_avail();
-        // Reserve room below the list for: waveform + metadata line + transport.
-        let list_height = (avail[1] - 132.0).max(100.0);
-        let mut drag_requested: Option<PathBuf> = None;
-
-        let up_key = parse_key(&self.prefs.keybinds.navigate_up).unwrap_or(Key::W);
-        let down_key = parse_key(&self.prefs.keybinds.navigate_down).unwrap_or(Key::S);
-        let back_key = parse_key(&self.prefs.keybinds.navigate_back).unwrap_or(Key::A);
-        let conf_key = parse_key(&self.prefs.keybinds.confirm).unwrap_or(Key::D);
+        // Reserve room below the list for: waveform + metadata line + tags +
+        // transport.
+        let list_height = (avail[1] - 176.0).max(100.0);
+        let mut drag_requested: Option<FileEntry> = None;
 
         let in_search = browser.is_in_search_mode();
+        // The note keyboard takes the letter keys from navigation while on.
+        let keys_taken = search_focused || self.note_keys.is_some();
 
         ui.child_window("file_list")
             .size([avail[0], list_height])
             .build(|| {
                 if in_search {
-                    self.draw_search_results(
-                        ui,
-                        browser,
-                        &mut drag_requested,
-                        search_focused,
-                        up_key,
-                        down_key,
-                        back_key,
-                    );
+                    self.draw_search_results(ui, browser, &mut drag_requested, keys_taken, &keymap);
                 } else {
-                    self.draw_browse_list(
-                        ui,
-                        browser,
-                        &mut drag_requested,
-                        search_focused,
-                        up_key,
-                        down_key,
-                        back_key,
-                        conf_key,
-                    );
+                    self.draw_browse_list(ui, browser, &mut drag_requested, keys_taken, &keymap);
                 }
             });
 
-        if let Some(path) = drag_requested.as_deref() {
+        if let Some(entry) = drag_requested {
             if let Some(on_drag_file) = on_drag_file {
-                on_drag_file(path);
+                // CUE tracks and module samples are written out as WAVs first.
+                match browser.drag_file(&entry) {
+                    Ok(path) => on_drag_file(&path),
+                    Err(e) => tracing::error!("{e}"),
+                }
             }
             return;
         }
 
         ui.separator();
 
-        // Panel-level keys (same focus gating as nav): Space toggles playback;
-        // the tab keybinds switch / create / close tabs.
+        // Back and forward like a file manager: Alt+Left / Alt+Right, or the
+        // mouse's side buttons over the panel.
+        let nav_keys = ui.is_window_focused() && !search_focused && ui.io().key_alt;
+        let hovered = ui.is_window_hovered_with_flags(imgui::WindowHoveredFlags::CHILD_WINDOWS);
+        let back = (nav_keys && ui.is_key_pressed(Key::LeftArrow))
+            || (hovered && ui.is_mouse_clicked(imgui::MouseButton::Extra1));
+        let forward = (nav_keys && ui.is_key_pressed(Key::RightArrow))
+            || (hovered && ui.is_mouse_clicked(imgui::MouseButton::Extra2));
+        if back {
+            if let Err(e) = browser.navigate_back() {
+                tracing::error!("navigate_back failed: {e}");
+            }
+        } else if forward {
+            if let Err(e) = browser.navigate_forward() {
+                tracing::error!("navigate_forward failed: {e}");
+            }
+        }
+
+        // Panel-level keys (same focus gating as nav): play/pause pauses and
+        // resumes playback, with Shift it stops; the tab keybinds switch /
+        // create / close tabs.
         if ui.is_window_focused() && !search_focused {
-            if ui.is_key_pressed_no_repeat(Key::Space) {
+            if ui.is_key_pressed_no_repeat(keymap.play_pause) {
                 match browser.playback_status() {
-                    PlaybackStatus::Playing { .. } | PlaybackStatus::Loading { .. } => {
+                    _ if ui.io().key_shift => browser.stop(),
+                    PlaybackStatus::Playing { .. } => browser.pause(),
+                    PlaybackStatus::Paused { .. } => browser.resume(),
+                    PlaybackStatus::Loading { .. } | PlaybackStatus::Reconnecting { .. } => {
                         browser.stop();
                     }
-                    PlaybackStatus::Idle => {
+                    PlaybackStatus::Idle | PlaybackStatus::Finished { .. } => {
                         if in_search {
                             if let Some(idx) = browser.search_selected() {
                                 if let Some(e) = browser.search_results().and_then(|r| r.get(idx)) {
-                                    let path = e.path.clone();
-                                    browser.play_file(&path);
+                                    let entry = e.clone();
+                                    browser.play_entry(&entry);
                                 }
                             }
                         } else {
@@ -474,23 +971,45 @@ impl BrowserPanel {
                 }
             }
 
+            let letter_pressed =
+                |bind: &str| parse_key(bind).is_some_and(|k| ui.is_key_pressed_no_repeat(k));
+            if let Some(keys) = self.note_keys.as_mut() {
+                let letters = keyboard::NOTE_KEYS
+                    .iter()
+                    .map(|&(letter, _)| letter)
+                    .chain([keyboard::OCTAVE_DOWN, keyboard::OCTAVE_UP]);
+                for letter in letters {
+                    if letter_pressed(letter.encode_utf8(&mut [0; 4])) {
+                        if let Some(note) = keys.press(letter) {
+                            browser.play_note(note);
+                        }
+                    }
+                }
+            }
+
             let count = browser.tab_count();
             let active = browser.active_tab();
-            let pressed =
-                |bind: &str| parse_key(bind).is_some_and(|k| ui.is_key_pressed_no_repeat(k));
-            if pressed(&self.prefs.keybinds.next_tab) {
+            let pressed = |key| ui.is_key_pressed_no_repeat(key);
+            if self.note_keys.is_some() {
+                // Tab, stop and search keybinds can be letters too.
+            } else if pressed(keymap.next_tab) {
                 browser.switch_tab((active + 1) % count);
-            } else if pressed(&self.prefs.keybinds.prev_tab) {
+            } else if pressed(keymap.prev_tab) {
                 browser.switch_tab((active + count - 1) % count);
-            } else if pressed(&self.prefs.keybinds.new_tab) {
+            } else if pressed(keymap.new_tab) {
                 let start = browser.current_directory().map(|p| p.to_path_buf());
                 browser.new_tab(start.as_deref());
-            } else if pressed(&self.prefs.keybinds.close_tab) {
+            } else if pressed(keymap.close_tab) {
                 browser.close_tab(browser.active_tab());
+            } else if pressed(keymap.stop) {
+                browser.stop();
+            } else if pressed(keymap.focus_search) {
+                self.focus_search = true;
             }
         }
 
-        draw_waveform_widget(ui, browser, &mut self.scrub_last_x);
+        draw_waveform_widget(ui, browser, &mut self.waveform, &self.export_form);
+        draw_progress_bar(ui, browser, &mut self.progress_drag);
 
         // Container metadata (BWF bext) + long-file preview indicator, one line.
         // A blank line is reserved when absent so the layout doesn't jump.
@@ -515,6 +1034,23 @@ impl BrowserPanel {
                         format_hms(info.source_duration.as_secs_f64()),
                     ));
                 }
+                if let Some(lufs) = info.loudness {
+                    parts.push(format!("{lufs:.1} LUFS"));
+                }
+                // Shown always, not just over 0 dBTP: with the loudness it says how
+                // hard the sample was mastered.
+                if info.true_peak > 0.0 {
+                    parts.push(format!(
+                        "true peak {:+.1} dBTP",
+                        punks_playback::analysis::gain_to_db(info.true_peak)
+                    ));
+                }
+                if info.clipped > 0 {
+                    parts.push(format!("{} clipped samples", info.clipped));
+                }
+                if info.sanitized > 0 {
+                    parts.push(format!("{} bad samples repaired", info.sanitized));
+                }
             }
             if parts.is_empty() {
                 ui.new_line();
@@ -523,11 +1059,223 @@ impl BrowserPanel {
             }
         }
 
+        self.draw_tag_row(ui, browser);
+
         // Transport row: volume slider pinned to the right edge of the panel.
         let transport_x = ui.cursor_pos()[0];
         let transport_y = ui.cursor_pos()[1];
         let panel_width = ui.content_region_avail()[0];
         const VOLUME_SLIDER_WIDTH: f32 = 120.0;
+        const TRIM_SLIDER_WIDTH: f32 = 90.0;
+        const SPEED_SLIDER_WIDTH: f32 = 90.0;
+
+        // Session recording, at the left: everything auditioned goes to a WAV.
+        ui.set_cursor_pos([transport_x, transport_y]);
+        let recording = browser.recording().map(Path::to_path_buf);
+        let rec_color = recording
+            .is_some()
+            .then(|| ui.push_style_color(imgui::StyleColor::Button, RECORDING_COLOR));
+        if ui.button(if recording.is_some() {
+            "Stop recording"
+        } else {
+            "Record"
+        }) {
+            toggle_recording(browser, &self.prefs);
+        }
+        drop(rec_color);
+        if ui.is_item_hovered() {
+            match (&recording, self.prefs.recording_folder()) {
+                (Some(file), _) => ui.tooltip_text(format!("Recording to {}", file.display())),
+                (None, Some(dir)) => ui.tooltip_text(format!(
+                    "Record everything you audition to a WAV in {}",
+                    dir.display()
+                )),
+                (None, None) => {}
+            }
+        }
+
+        // Note keyboard: play the selected sample chromatically.
+        ui.same_line();
+        let keys_label = match &self.note_keys {
+            Some(keys) => format!(
+                "Keys (A = {})##keys",
+                keyboard::note_name(keys.octave() * 12)
+            ),
+            None => "Keys##keys".to_string(),
+        };
+        let keys_color = self.note_keys.is_some().then(|| {
+            let on = ui.style_color(imgui::StyleColor::ButtonActive);
+            ui.push_style_color(imgui::StyleColor::Button, on)
+        });
+        if ui.button(&keys_label) {
+            self.note_keys = match self.note_keys {
+                Some(_) => {
+                    browser.set_pitch(0.0);
+                    None
+                }
+                None => Some(NoteKeyboard::default()),
+            };
+        }
+        drop(keys_color);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Play the selected sample chromatically from the keyboard: A to K are \
+                 the white keys from C (the sample's own pitch), W E T Y U O P the \
+                 black keys, Z / X an octave down / up. Letter keys don't navigate \
+                 while this is on.",
+            );
+        }
+
+        // Polyphonic preview: each sample rings out under the next.
+        ui.same_line();
+        let poly_on = browser.polyphonic();
+        let voices = browser.voices();
+        let poly_label = match voices.len() {
+            0 => "Poly##poly".to_string(),
+            n => format!("Poly ({n})##poly"),
+        };
+        let poly_color = poly_on.then(|| {
+            let on = ui.style_color(imgui::StyleColor::ButtonActive);
+            ui.push_style_color(imgui::StyleColor::Button, on)
+        });
+        if ui.button(&poly_label) {
+            browser.set_polyphonic(!poly_on);
+            self.prefs.polyphonic = !poly_on;
+            punks_core::config::save(&self.prefs);
+        }
+        drop(poly_color);
+        if ui.is_item_clicked_with_button(imgui::MouseButton::Right) && !voices.is_empty() {
+            ui.open_popup("poly_voices");
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Let each sample ring out under the next instead of stopping it, \
+                 to hear them together. Right-click to stop one; Stop stops them all.",
+            );
+        }
+        if let Some(_popup) = ui.begin_popup("poly_voices") {
+            for voice in &voices {
+                let name = voice.path.file_name().unwrap_or_default().to_string_lossy();
+                if ui.selectable(format!("Stop {name}##{:?}", voice.id)) {
+                    browser.stop_voice(voice.id);
+                }
+            }
+        }
+
+        // Metronome, and whether loops play along with it.
+        ui.same_line();
+        let click_on = browser.metronome_on();
+        let click_color = click_on.then(|| {
+            let on = ui.style_color(imgui::StyleColor::ButtonActive);
+            ui.push_style_color(imgui::StyleColor::Button, on)
+        });
+        if ui.button("Click##metronome") {
+            browser.set_metronome_on(!click_on);
+            self.prefs.metronome.enabled = !click_on;
+            punks_core::config::save(&self.prefs);
+        }
+        drop(click_color);
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Metronome");
+        }
+        ui.same_line();
+        ui.set_next_item_width(70.0);
+        let mut bpm = browser.metronome_bpm();
+        if imgui::Drag::new("##bpm")
+            .range(MIN_BPM, MAX_BPM)
+            .speed(0.5)
+            .display_format("%.0f BPM")
+            .build(ui, &mut bpm)
+        {
+            browser.set_metronome_bpm(bpm);
+        }
+        if ui.is_item_deactivated_after_edit() {
+            self.prefs.metronome.bpm = browser.metronome_bpm();
+            punks_core::config::save(&self.prefs);
+        }
+        ui.same_line();
+        ui.set_next_item_width(130.0);
+        let mut sync = LOOP_SYNC_MODES
+            .iter()
+            .position(|&mode| mode == browser.loop_sync())
+            .unwrap_or(0);
+        if ui.combo_simple_string("##loopsync", &mut sync, LOOP_SYNC_LABELS) {
+            browser.set_loop_sync(LOOP_SYNC_MODES[sync]);
+            self.prefs.metronome.sync = LOOP_SYNC_MODES[sync];
+            punks_core::config::save(&self.prefs);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Loops (a tempo in the name, or named or filed as loops) repeat, \
+                 started in step with the metronome; stretched, they also play at its \
+                 tempo (and pitch shifts with it).",
+            );
+        }
+
+        // Per-sample trim, left of the volume, while something is auditioning.
+        // Remembered for the file, so it comes back at this level next time.
+        if let PlaybackStatus::Loading { file, .. }
+        | PlaybackStatus::Playing { file, .. }
+        | PlaybackStatus::Paused { file, .. } = browser.playback_status()
+        {
+            ui.set_cursor_pos([
+                transport_x
+                    + (panel_width - VOLUME_SLIDER_WIDTH - TRIM_SLIDER_WIDTH - 8.0).max(0.0),
+                transport_y,
+            ]);
+            ui.set_next_item_width(TRIM_SLIDER_WIDTH);
+            let mut trim = browser.sample_gain_db(&file);
+            if ui
+                .slider_config("##trim", -24.0_f32, 12.0_f32)
+                .display_format("%+.1f dB")
+                .build(&mut trim)
+            {
+                browser.set_sample_gain_db(&file, trim);
+            }
+            if ui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+                browser.set_sample_gain_db(&file, 0.0);
+                browser.save_sample_index();
+            }
+            if ui.is_item_deactivated_after_edit() {
+                browser.save_sample_index();
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Trim for this sample, remembered (right-click to reset)");
+            }
+        }
+
+        // Varispeed, left of the trim's place: slowed down or sped up,
+        // pitch and all.
+        ui.set_cursor_pos([
+            transport_x
+                + (panel_width
+                    - VOLUME_SLIDER_WIDTH
+                    - TRIM_SLIDER_WIDTH
+                    - SPEED_SLIDER_WIDTH
+                    - 16.0)
+                    .max(0.0),
+            transport_y,
+        ]);
+        ui.set_next_item_width(SPEED_SLIDER_WIDTH);
+        let mut rate = browser.rate();
+        if ui
+            .slider_config(
+                "##speed",
+                punks_playback::MIN_RATE,
+                punks_playback::MAX_RATE,
+            )
+            .display_format("%.2fx")
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .build(&mut rate)
+        {
+            browser.set_rate(rate);
+        }
+        if ui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+            browser.set_rate(1.0);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Playback speed; pitch follows (right-click to reset)");
+        }
 
         ui.set_cursor_pos([
             transport_x + (panel_width - VOLUME_SLIDER_WIDTH).max(0.0),
@@ -558,19 +1306,93 @@ impl BrowserPanel {
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
+    /// The loaded sample's rating and its tags as chips, each listing the
+    /// samples with that tag under the current folder when clicked, a box to
+    /// add one, and every tag in use to browse by.
+    fn draw_tag_row(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let mut browse = None;
+        if let Some((path, _)) = browser.loaded_clip() {
+            let path = path.to_path_buf();
+            // Click a star to rate, the last lit one again to clear. The
+            // default font has no star glyphs.
+            let rating = browser.rating(&path).unwrap_or(0);
+            let mut rate = None;
+            for star in 1..=MAX_RATING {
+                let lit = if star <= rating { "*" } else { "." };
+                if ui.small_button(format!("{lit}##star{star}")) {
+                    rate = Some((star != rating).then_some(star));
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Rate (keys 1-5 rate the selection, 0 clears)");
+                }
+                if star < MAX_RATING {
+                    ui.same_line_with_spacing(0.0, 1.0);
+                } else {
+                    ui.same_line();
+                }
+            }
+            if let Some(rating) = rate {
+                browser.set_rating(&path, rating);
+            }
+
+            let mut remove = None;
+            for (i, tag) in browser.sample_tags(&path).iter().enumerate() {
+                if ui.small_button(format!("{tag}##tagchip{i}")) {
+                    browse = Some(tag.clone());
+                }
+                ui.same_line_with_spacing(0.0, 1.0);
+                if ui.small_button(format!("\u{00d7}##tagremove{i}")) {
+                    remove = Some(tag.clone());
+                }
+                ui.same_line();
+            }
+            if let Some(tag) = remove {
+                browser.remove_sample_tag(&path, &tag);
+            }
+            ui.set_next_item_width(120.0);
+            if ui
+                .input_text("##newtag", &mut self.tag_buf)
+                .hint("Add tag...")
+                .enter_returns_true(true)
+                .build()
+            {
+                browser.add_sample_tag(&path, &self.tag_buf);
+                self.tag_buf.clear();
+            }
+            ui.same_line();
+        }
+
+        let tags = browser.all_tags();
+        ui.set_next_item_width(140.0);
+        if let Some(_combo) = ui.begin_combo("##browsetags", format!("Tags ({})", tags.len())) {
+            for (tag, count) in &tags {
+                if ui.selectable(format!("{tag}  ({count})")) {
+                    browse = Some(tag.to_string());
+                }
+            }
+        }
+        if ui.is_item_hovered() && tags.is_empty() {
+            ui.tooltip_text("No tags yet: play a sample and add one.");
+        }
+
+        // Through the search box, so the results show like any search's.
+        if let Some(tag) = browse {
+            self.search_buf = format!("#{tag}");
+        }
+    }
+
     fn draw_search_results(
         &mut self,
         ui: &imgui::Ui,
         browser: &mut SampleBrowser,
-        drag_requested: &mut Option<PathBuf>,
-        search_focused: bool,
-        up_key: Key,
-        down_key: Key,
-        back_key: Key,
+        drag_requested: &mut Option<FileEntry>,
+        keys_taken: bool,
+        keys: &KeyMap,
     ) {
         let count = match browser.search_results() {
             Some(r) if !r.is_empty() => r.len(),
+            // The progress line above says it's still looking.
+            Some(_) if browser.is_searching() => return,
             Some(_) => {
                 ui.text_disabled("No results.");
                 return;
@@ -586,24 +1408,19 @@ impl BrowserPanel {
         let root: Option<PathBuf> = browser.current_directory().map(|p| p.to_path_buf());
 
         // Keyboard navigation — mutable borrows happen here, before the clipper.
-        if ui.is_window_focused() && !search_focused {
-            if ui.is_key_pressed_no_repeat(up_key) {
+        if ui.is_window_focused() && !keys_taken {
+            if ui.is_key_pressed(keys.up) {
                 let idx = browser.search_selected().unwrap_or(0).saturating_sub(1);
                 browser.select_search_result(idx);
-                if let Some(e) = browser.search_results().and_then(|r| r.get(idx)) {
-                    let path = e.path.clone();
-                    browser.play_file(&path);
-                }
             }
-            if ui.is_key_pressed_no_repeat(down_key) {
+            if ui.is_key_pressed(keys.down) {
                 let idx = (browser.search_selected().unwrap_or(0) + 1).min(count.saturating_sub(1));
                 browser.select_search_result(idx);
-                if let Some(e) = browser.search_results().and_then(|r| r.get(idx)) {
-                    let path = e.path.clone();
-                    browser.play_file(&path);
-                }
             }
-            if ui.is_key_pressed_no_repeat(back_key) {
+            if let Some(rating) = rating_key(ui) {
+                browser.rate_selected(rating);
+            }
+            if ui.is_key_pressed_no_repeat(keys.back) {
                 self.search_buf.clear();
                 self.last_typed_query.clear();
                 self.last_searched_query.clear();
@@ -613,7 +1430,7 @@ impl BrowserPanel {
         }
 
         let selected = browser.search_selected();
-        let mut click_action: Option<(usize, PathBuf)> = None;
+        let mut click_action: Option<usize> = None;
 
         // Width-adaptive columns; the clipper iterates rows of `cols` items so
         // only visible rows allocate label strings.
@@ -634,12 +1451,11 @@ impl BrowserPanel {
                 }
                 // Extract owned data in a short block so the borrow on browser
                 // ends before we call any mutable method.
-                let (label, path) = {
+                let label = {
                     let results = browser.search_results().unwrap();
                     let e = &results[i];
                     let parent_hint = relative_parent(root.as_deref(), &e.path);
-                    let label = format!("{}  ({})##sresult{}", e.name, parent_hint, i);
-                    (label, e.path.clone())
+                    format!("{}  ({})##sresult{}", e.name, parent_hint, i)
                 };
 
                 let clicked = ui
@@ -650,35 +1466,52 @@ impl BrowserPanel {
                 if ui.is_item_hovered()
                     && ui.is_mouse_dragging_with_threshold(imgui::MouseButton::Left, -1.0)
                 {
-                    *drag_requested = Some(path);
+                    *drag_requested = browser.search_results().map(|r| r[i].clone());
                     break 'rows;
                 }
                 if clicked {
-                    click_action = Some((i, path));
+                    click_action = Some(i);
                 }
             }
         }
 
-        if let Some((i, path)) = click_action {
+        if let Some(i) = click_action {
             browser.select_search_result(i);
-            browser.play_file(&path);
+            if let Some(entry) = browser.search_results().and_then(|r| r.get(i)).cloned() {
+                browser.play_entry(&entry);
+            }
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
     fn draw_browse_list(
         &mut self,
         ui: &imgui::Ui,
         browser: &mut SampleBrowser,
-        drag_requested: &mut Option<PathBuf>,
-        search_focused: bool,
-        up_key: Key,
-        down_key: Key,
-        back_key: Key,
-        conf_key: Key,
+        drag_requested: &mut Option<FileEntry>,
+        keys_taken: bool,
+        keys: &KeyMap,
     ) {
         let entry_count = browser.entries().len();
 
+        let skipped = browser.skipped_entries();
+        if !skipped.is_empty() {
+            ui.text_colored(
+                [1.0, 0.7, 0.3, 1.0],
+                format!("{} entries couldn't be read", skipped.len()),
+            );
+            if ui.is_item_hovered() {
+                ui.tooltip(|| {
+                    for (path, e) in skipped.iter().take(20) {
+                        let name = path.file_name().unwrap_or(path.as_os_str());
+                        ui.text(format!("{}: {e}", name.to_string_lossy()));
+                    }
+                    if skipped.len() > 20 {
+                        ui.text_disabled(format!("...and {} more", skipped.len() - 20));
+                    }
+                });
+            }
+        }
+
         if entry_count == 0 {
             if browser.current_directory().is_some() {
                 ui.text_disabled("Empty directory.");
@@ -691,23 +1524,31 @@ impl BrowserPanel {
         // Keyboard navigation — mutable borrows happen here, before the
         // clipper loop takes short immutable borrows to read entry data.
         let selected = browser.selected();
-        if ui.is_window_focused() && !search_focused {
-            if ui.is_key_pressed_no_repeat(up_key) {
+        if ui.is_window_focused() && !keys_taken {
+            if ui.is_key_pressed(keys.up) {
                 let idx = selected.unwrap_or(0).saturating_sub(1);
                 browser.select(idx);
-                browser.play_selected();
             }
-            if ui.is_key_pressed_no_repeat(down_key) {
+            if ui.is_key_pressed(keys.down) {
                 let idx = (selected.unwrap_or(0) + 1).min(entry_count.saturating_sub(1));
                 browser.select(idx);
-                browser.play_selected();
             }
-            if ui.is_key_pressed_no_repeat(back_key) {
+            if ui.is_key_pressed_no_repeat(keys.back) {
                 if let Err(e) = browser.navigate_up() {
-                    log::error!("navigate_up failed: {e}");
+                    tracing::error!("navigate_up failed: {e}");
+                }
+            }
+            if let Some(rating) = rating_key(ui) {
+                browser.rate_selected(rating);
+            }
+            if let (true, Some(i)) = (ui.is_key_pressed_no_repeat(Key::Delete), selected) {
+                if let Err(e) = browser.delete_entry(i) {
+                    tracing::error!("{e}");
                 }
+                // The listing changed under `entry_count`; draw it next frame.
+                return;
             }
-            let confirm = ui.is_key_pressed_no_repeat(conf_key)
+            let confirm = ui.is_key_pressed_no_repeat(keys.confirm)
                 || ui.is_key_pressed_no_repeat(Key::Enter)
                 || ui.is_key_pressed_no_repeat(Key::KeypadEnter);
             if confirm {
@@ -715,7 +1556,7 @@ impl BrowserPanel {
                     let is_dir = browser.entries().get(i).map(|e| e.is_directory);
                     if is_dir == Some(true) {
                         if let Err(e) = browser.navigate_into(i) {
-                            log::error!("navigate_into failed: {e}");
+                            tracing::error!("navigate_into failed: {e}");
                         }
                     } else if is_dir == Some(false) {
                         browser.play_selected();
@@ -728,77 +1569,318 @@ impl BrowserPanel {
         let selected = browser.selected();
         let mut click_action: Option<(usize, bool, PathBuf)> = None;
 
-        // Lay out entries in width-adaptive columns to use horizontal space.
-        // The clipper iterates rows of `cols` items, so off-screen rows are
-        // skipped and label strings are allocated only for visible items.
-        let avail_w = ui.content_region_avail()[0];
-        let cols = column_count(avail_w);
-        let col_w = avail_w / cols as f32;
-        let num_rows = entry_count.div_ceil(cols);
-
-        let clip = imgui::ListClipper::new(num_rows as i32).begin(ui);
-        'rows: for row in clip.iter() {
-            for c in 0..cols {
-                let i = row as usize * cols + c;
-                if i >= entry_count {
-                    break;
+        // One row per entry, in columns that can be resized, dragged into
+        // another order, hidden from the header's context menu, and clicked
+        // to sort by. The clipper lays out only the rows on screen.
+        let mut visible: Option<std::ops::Range<usize>> = None;
+        let mut open_menu = false;
+        let mut sort: Option<(SortMode, bool)> = None;
+        let now = SystemTime::now();
+        let flags = imgui::TableFlags::RESIZABLE
+            | imgui::TableFlags::REORDERABLE
+            | imgui::TableFlags::HIDEABLE
+            | imgui::TableFlags::SORTABLE
+            | imgui::TableFlags::ROW_BG
+            | imgui::TableFlags::BORDERS_INNER_V
+            | imgui::TableFlags::SIZING_FIXED_FIT;
+        if let Some(_table) = ui.begin_table_with_flags("browse_table", LIST_COLUMNS.len(), flags) {
+            for (name, mode, width) in LIST_COLUMNS {
+                let mut column_flags = if mode == Some(SortMode::Name) {
+                    imgui::TableColumnFlags::WIDTH_STRETCH | imgui::TableColumnFlags::NO_HIDE
+                } else {
+                    imgui::TableColumnFlags::WIDTH_FIXED
+                };
+                match mode {
+                    None => column_flags |= imgui::TableColumnFlags::NO_SORT,
+                    Some(SortMode::Modified) => {
+                        column_flags |= imgui::TableColumnFlags::DEFAULT_HIDE;
+                    }
+                    Some(_) => {}
                 }
-                if c > 0 {
-                    ui.same_line_with_pos(c as f32 * col_w);
+                // Start out sorted the way the listing already is.
+                if mode == Some(browser.sort_mode()) {
+                    column_flags |= imgui::TableColumnFlags::DEFAULT_SORT;
+                    if browser.sort_descending() {
+                        column_flags |= imgui::TableColumnFlags::PREFER_SORT_DESCENDING;
+                    }
                 }
+                ui.table_setup_column_with(imgui::TableColumnSetup {
+                    flags: column_flags,
+                    init_width_or_weight: width,
+                    ..imgui::TableColumnSetup::new(name)
+                });
+            }
+            ui.table_headers_row();
+            if let Some(specs) = ui.table_sort_specs_mut() {
+                specs.conditional_sort(|specs| {
+                    sort = specs.iter().next().and_then(|spec| {
+                        let mode = LIST_COLUMNS.get(spec.column_idx())?.1?;
+                        let descending =
+                            spec.sort_direction() == Some(imgui::TableSortDirection::Descending);
+                        Some((mode, descending))
+                    });
+                });
+            }
+
+            let clip = imgui::ListClipper::new(entry_count as i32).begin(ui);
+            for row in clip.iter() {
+                let i = row as usize;
+                visible = Some(visible.map_or(i..i + 1, |v| v.start.min(i)..v.end.max(i + 1)));
+                ui.table_next_row();
+                ui.table_next_column();
 
                 // Extract owned data in a short block so the immutable borrow on
                 // browser ends before we call any mutable method.
-                let (label, is_dir, path) = {
+                let (label, cells, is_dir, is_folder, playable, deletable, path) = {
                     let e = &browser.entries()[i];
+                    let folder_size = browser.folder_size(&e.path);
                     let label = if e.is_directory {
-                        format!("> {}##entry{}", e.name, i)
+                        let count = match e.audio_count {
+                            Some(0) => "  (empty)".to_string(),
+                            Some(n) => format!("  ({n})"),
+                            None => String::new(),
+                        };
+                        format!("> {}{}##entry{}", e.name, count, i)
                     } else {
                         format!("{}##entry{}", e.name, i)
                     };
-                    (label, e.is_directory, e.path.clone())
+                    let info = browser.file_info(&e.path);
+                    let size = match folder_size {
+                        Some((size, done)) => format!(
+                            "{}{}",
+                            punks_core::size::format_bytes(size.bytes),
+                            if done { "" } else { "..." }
+                        ),
+                        None if e.is_directory => String::new(),
+                        None => punks_core::size::format_bytes(e.size_bytes),
+                    };
+                    let cells = [
+                        info.and_then(|info| info.duration)
+                            .map(format_length)
+                            .unwrap_or_default(),
+                        size,
+                        if e.is_directory && e.extension.is_empty() {
+                            "Folder".to_string()
+                        } else {
+                            e.extension.to_uppercase()
+                        },
+                        if e.is_directory {
+                            String::new()
+                        } else {
+                            row_bpm(browser, &e.path)
+                                .map(|bpm| format!("{bpm}"))
+                                .unwrap_or_default()
+                        },
+                        e.modified
+                            .map(|t| history::when(t, now))
+                            .unwrap_or_default(),
+                    ];
+                    // Virtual folders (playlists, CUE sheets) keep their extension.
+                    let is_folder = e.is_directory && e.extension.is_empty();
+                    let playable = browser.is_playable(e);
+                    // CUE tracks share one file, so they can't be deleted singly.
+                    let deletable = e.region.is_none();
+                    (
+                        label,
+                        cells,
+                        e.is_directory,
+                        is_folder,
+                        playable,
+                        deletable,
+                        e.path.clone(),
+                    )
                 };
 
-                let is_selected = selected == Some(i);
-                let size = [col_w - COLUMN_GUTTER, 0.0];
-                let clicked = if is_dir {
-                    let color = ui.push_style_color(imgui::StyleColor::Text, DIR_TEXT_COLOR);
-                    let clicked = ui
-                        .selectable_config(&label)
-                        .selected(is_selected)
-                        .size(size)
-                        .build();
-                    color.pop();
-                    clicked
+                // Folders in their colour, list-only types (see Settings)
+                // dimmed: they're shown but don't play.
+                let color = if is_dir {
+                    Some(ui.push_style_color(imgui::StyleColor::Text, DIR_TEXT_COLOR))
+                } else if !playable {
+                    let dim = ui.style_color(imgui::StyleColor::TextDisabled);
+                    Some(ui.push_style_color(imgui::StyleColor::Text, dim))
                 } else {
-                    ui.selectable_config(&label)
-                        .selected(is_selected)
-                        .size(size)
-                        .build()
+                    None
                 };
+                let clicked = ui
+                    .selectable_config(&label)
+                    .selected(selected == Some(i))
+                    .flags(imgui::SelectableFlags::SPAN_ALL_COLUMNS)
+                    .build();
+                if let Some(color) = color {
+                    color.pop();
+                }
 
                 if !is_dir
                     && ui.is_item_hovered()
                     && ui.is_mouse_dragging_with_threshold(imgui::MouseButton::Left, -1.0)
                 {
-                    *drag_requested = Some(path);
-                    break 'rows;
+                    *drag_requested = Some(browser.entries()[i].clone());
+                    break;
+                }
+                if playable && ui.is_item_hovered() {
+                    let info = browser.file_info(&path);
+                    let peaks = browser.overview(&path);
+                    if info.is_some() || peaks.is_some() {
+                        ui.tooltip(|| {
+                            if let Some(info) = info {
+                                ui.text(describe_format(info));
+                            }
+                            if let Some(peaks) = peaks {
+                                draw_overview_tooltip(ui, peaks);
+                            }
+                        });
+                    }
+                }
+                if deletable && ui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+                    self.context_entry = Some((i, path.clone(), is_folder));
+                    open_menu = true;
                 }
                 if clicked {
                     click_action = Some((i, is_dir, path));
                 }
+
+                for cell in &cells {
+                    ui.table_next_column();
+                    ui.text_disabled(cell);
+                }
+            }
+        }
+        // Outside the table, whose ID the popup would otherwise be under.
+        if open_menu {
+            ui.open_popup("entry_menu");
+        }
+        if let Some((mode, descending)) = sort {
+            if (mode, descending) != (browser.sort_mode(), browser.sort_descending()) {
+                browser.set_sort_mode(mode, descending);
+                self.prefs.sort_mode = mode;
+                self.prefs.sort_descending = descending;
+                punks_core::config::save(&self.prefs);
+            }
+        }
+
+        let visible = visible.unwrap_or_default();
+        browser.request_metadata(visible.clone());
+        browser.request_overviews(visible);
+
+        if let Some(_popup) = ui.begin_popup("entry_menu") {
+            if let Some((index, path, is_folder)) = &self.context_entry {
+                if *is_folder && ui.menu_item("Compute size") {
+                    browser.compute_folder_size(path);
+                }
+                let is_set = browser
+                    .entries()
+                    .get(*index)
+                    .is_some_and(|e| e.is_directory && &e.path == path);
+                if is_set && ui.menu_item("Export to rekordbox XML...") {
+                    export_rekordbox(path, browser.extensions());
+                }
+                if is_set && ui.menu_item("Export as Serato crate") {
+                    export_serato(path, browser.extensions());
+                }
+                let is_file = browser
+                    .entries()
+                    .get(*index)
+                    .is_some_and(|e| !e.is_directory && &e.path == path);
+                let layered = browser.layers().iter().any(|l| &l.path == path);
+                let label = if layered {
+                    "Remove from layers"
+                } else {
+                    "Add to layers"
+                };
+                if is_file && ui.menu_item(label) {
+                    browser.toggle_layer(path);
+                }
+                if is_file && ui.menu_item("Export as WAV...") {
+                    self.export_form.entry = browser.entries().get(*index).cloned();
+                    self.export_form.result = None;
+                    self.export_form.open = true;
+                }
+                let is_module = browser
+                    .entries()
+                    .get(*index)
+                    .is_some_and(|e| &e.path == path && browser.has_samples(e));
+                if is_module && ui.menu_item("Browse samples") {
+                    if let Err(e) = browser.navigate_into(*index) {
+                        tracing::error!("navigate_into failed: {e}");
+                    }
+                }
+                #[cfg(feature = "tracker")]
+                if is_module && ui.menu_item("Extract samples...") {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        match browser.extract_samples(path, &dir) {
+                            Ok(files) => tracing::info!(
+                                "extracted {} samples to {}",
+                                files.len(),
+                                dir.display()
+                            ),
+                            Err(e) => tracing::error!("{e}"),
+                        }
+                    }
+                }
+                if browser.is_favorite(path) {
+                    if ui.menu_item("Remove from favorites") {
+                        browser.remove_favorite(path);
+                    }
+                } else if ui.menu_item("Add to favorites") {
+                    browser.add_favorite(path);
+                }
+                if is_file {
+                    if let Some(_menu) = ui.begin_menu("Add to collection") {
+                        let mut chosen = None;
+                        for collection in browser.collections() {
+                            if ui
+                                .menu_item_config(collection.name())
+                                .enabled(!collection.contains(path))
+                                .build()
+                            {
+                                chosen = Some(collection.name().to_string());
+                            }
+                        }
+                        let form = &mut self.collection_form;
+                        ui.set_next_item_width(180.0);
+                        let entered = ui
+                            .input_text("##new_collection", &mut form.name)
+                            .hint("New collection")
+                            .enter_returns_true(true)
+                            .build();
+                        if entered && browser.create_collection(&form.name) {
+                            chosen = Some(form.name.trim().to_string());
+                            form.name.clear();
+                        }
+                        if let Some(name) = chosen {
+                            browser.add_to_collection(&name, path);
+                            form.selected = Some(name);
+                            ui.close_current_popup();
+                        }
+                    }
+                }
+                // Only if the listing hasn't shifted since the menu opened.
+                let still_there = browser.entries().get(*index).map(|e| &e.path) == Some(path);
+                if ui
+                    .menu_item_config("Move to Trash")
+                    .enabled(still_there)
+                    .build()
+                {
+                    if let Err(e) = browser.delete_entry(*index) {
+                        tracing::error!("{e}");
+                    }
+                }
             }
         }
 
         // Apply click after the loop — avoids holding an immutable borrow
         // on browser.entries() while calling mutable browser methods.
-        if let Some((i, is_dir, _)) = click_action {
-            browser.select(i);
-            if is_dir {
+        if let Some((i, is_dir, path)) = click_action {
+            if !is_dir && ui.io().key_ctrl {
+                // Not selected: auto-preview would play it over the layers.
+                browser.toggle_layer(&path);
+            } else if is_dir {
+                browser.select(i);
                 if let Err(e) = browser.navigate_into(i) {
-                    log::error!("navigate_into failed: {e}");
+                    tracing::error!("navigate_into failed: {e}");
                 }
             } else {
+                browser.select(i);
                 browser.play_selected();
             }
         }
@@ -824,7 +1906,7 @@ impl BrowserPanel {
                 if let Some(path) = rfd::FileDialog::new().pick_folder() {
                     // last_directory is persisted centrally at the top of draw().
                     if let Err(e) = browser.open_directory(&path) {
-                        log::error!("failed to open directory: {e}");
+                        tracing::error!("failed to open directory: {e}");
                     }
                 }
             }
@@ -866,138 +1948,2187 @@ impl BrowserPanel {
                 self.prefs.keybinds = Keybinds::default();
                 punks_core::config::save(&self.prefs);
             }
-            ui.same_line();
+
+            ui.separator();
+            self.draw_extension_settings(ui, browser);
+
+            ui.separator();
+            self.draw_watch_settings(ui, browser);
+
+            ui.separator();
+            self.draw_library_settings(ui, browser);
+
+            ui.separator();
+            if ui.checkbox(
+                "Play files as they're selected",
+                &mut self.prefs.auto_preview,
+            ) {
+                browser.set_auto_preview(self.prefs.auto_preview);
+                punks_core::config::save(&self.prefs);
+            }
+            let mut matched = self.prefs.loudness_target.is_some();
+            if ui.checkbox(
+                format!(
+                    "Level-match previews to {} LUFS",
+                    punks_playback::DEFAULT_LOUDNESS_TARGET
+                ),
+                &mut matched,
+            ) {
+                self.prefs.loudness_target =
+                    matched.then_some(punks_playback::DEFAULT_LOUDNESS_TARGET);
+                browser.set_loudness_target(self.prefs.loudness_target);
+                punks_core::config::save(&self.prefs);
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("So packs mastered louder or quieter than others audition alike.");
+            }
+            self.draw_fade_settings(ui, browser);
+            if ui.checkbox(
+                "Delete permanently where there's no trash",
+                &mut self.prefs.allow_permanent_delete,
+            ) {
+                browser.set_allow_permanent_delete(self.prefs.allow_permanent_delete);
+                punks_core::config::save(&self.prefs);
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("e.g. on network shares. Deleted files can't be recovered.");
+            }
+            if ui.checkbox(
+                "Share waveform peaks with REAPER (.reapeaks)",
+                &mut self.prefs.reapeaks,
+            ) {
+                browser.set_reapeaks(self.prefs.reapeaks);
+                punks_core::config::save(&self.prefs);
+            }
+            self.draw_quick_audition_settings(ui, browser);
+            self.draw_memory_budget_settings(ui, browser);
+            self.draw_output_settings(ui, browser);
+
+            ui.separator();
+            self.draw_midi_settings(ui, browser);
+
+            #[cfg(feature = "remote")]
+            {
+                ui.separator();
+                self.draw_remote_settings(ui, browser);
+            }
+
+            ui.separator();
+            self.draw_profile_settings(ui, browser);
+
+            ui.separator();
             if ui.button("Close") {
                 self.rebinding = None;
+                browser.midi_map_mut().cancel_learn();
                 ui.close_current_popup();
             }
         }
     }
 }
 
-impl Default for BrowserPanel {
-    fn default() -> Self {
-        Self::new()
+impl BrowserPanel {
+    /// Export the shareable settings to a profile file, or import one over
+    /// them; see [`Profile`].
+    fn draw_profile_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.text("Settings profile");
+        ui.same_line();
+        if ui.button("Export...##profile") {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("punks profile", &["json"])
+                .set_file_name("punks-profile.json")
+                .save_file()
+            {
+                let name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if let Err(e) = self.prefs.to_profile(&name).save(&path) {
+                    tracing::error!("failed to export profile to {}: {e}", path.display());
+                }
+            }
+        }
+        ui.same_line();
+        if ui.button("Import...##profile") {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("punks profile", &["json"])
+                .pick_file()
+            {
+                match Profile::load(&path) {
+                    Ok(profile) => {
+                        self.prefs.apply_profile(&profile);
+                        self.volume = self.prefs.volume;
+                        self.rebinding = None;
+                        browser.apply_settings(&self.prefs);
+                        punks_core::config::save(&self.prefs);
+                    }
+                    Err(e) => tracing::error!("failed to import {}: {e}", path.display()),
+                }
+            }
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Audio, keybinds, MIDI mappings and file types. \
+                 Folders, ports and accounts stay as they are.",
+            );
+        }
     }
-}
 
-const WAVEFORM_BG: [f32; 4] = [0.12, 0.12, 0.14, 1.0];
-const WAVEFORM_BAR: [f32; 4] = [0.30, 0.75, 0.45, 1.0];
-const WAVEFORM_PLAYHEAD: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
-const WAVEFORM_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 0.85];
-// Subtle hover/scrub crosshair — dimmer than the opaque playhead.
-const WAVEFORM_HOVER: [f32; 4] = [1.0, 1.0, 1.0, 0.35];
+    /// MIDI input port picker plus a Learn button per action. Bindings are
+    /// persisted as soon as a learn completes.
+    #[cfg(feature = "remote")]
+    fn draw_remote_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.text("Remote API");
+        ui.spacing();
 
-fn color_u32(c: [f32; 4]) -> u32 {
-    let r = (c[0] * 255.0) as u32;
-    let g = (c[1] * 255.0) as u32;
-    let b = (c[2] * 255.0) as u32;
-    let a = (c[3] * 255.0) as u32;
-    (a << 24) | (b << 16) | (g << 8) | r
-}
+        let root_label = self
+            .prefs
+            .remote
+            .root
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "(no root folder)".into());
+        ui.text_disabled(&root_label);
+        ui.same_line();
+        let mut changed = false;
+        if ui.button("Use current folder##remote") {
+            if let Some(dir) = browser.current_directory() {
+                self.prefs.remote.root = Some(dir.to_path_buf());
+                changed = true;
+            }
+        }
 
-fn draw_waveform_widget(ui: &imgui::Ui, browser: &SampleBrowser, scrub_last_x: &mut Option<f32>) {
-    let [cx, cy] = ui.cursor_screen_pos();
-    let w = ui.content_region_avail()[0];
-    const H: f32 = 64.0;
+        ui.set_next_item_width(220.0);
+        // Rebinding on every keystroke would churn the socket; apply on Enter.
+        changed |= ui
+            .input_text("Bind address##remote", &mut self.prefs.remote.bind)
+            .enter_returns_true(true)
+            .build();
+        changed |= ui.checkbox("Enabled##remote", &mut self.prefs.remote.enabled);
+        changed |= ui.checkbox(
+            "Allow other devices##remote",
+            &mut self.prefs.remote.allow_network,
+        );
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Needed to bind an address like 0.0.0.0; requires an access token");
+        }
 
-    // Interactive hit area (replaces the passive dummy) for hover + scrub.
-    let clicked = ui.invisible_button("##waveform", [w, H]);
-    let hovered = ui.is_item_hovered();
-    let active = ui.is_item_active();
-    let scrubbable = browser.loaded_duration().is_some();
-    let mouse_x = ui.io().mouse_pos[0];
+        ui.set_next_item_width(220.0);
+        changed |= ui
+            .input_text("Access token##remote", &mut self.prefs.remote.token)
+            .password(true)
+            .enter_returns_true(true)
+            .build();
+        ui.same_line();
+        if ui.button("New##remote_token") {
+            self.prefs.remote.token = punks_core::config::RemoteConfig::new_token();
+            changed = true;
+        }
+        if !self.prefs.remote.token.is_empty() {
+            ui.same_line();
+            if ui.button("Copy##remote_token") {
+                ui.set_clipboard_text(&self.prefs.remote.token);
+            }
+        }
 
-    let draw = ui.get_window_draw_list();
+        if changed {
+            browser.stop_remote();
+            if self.prefs.remote.enabled && self.prefs.remote.root.is_some() {
+                if let Err(e) = browser.start_remote(&self.prefs.remote) {
+                    tracing::error!("{e}");
+                }
+            }
+            punks_core::config::save(&self.prefs);
+        }
 
-    let bg = color_u32(WAVEFORM_BG);
-    let bar_color = color_u32(WAVEFORM_BAR);
-    let playhead_color = color_u32(WAVEFORM_PLAYHEAD);
-    let text_color = color_u32(WAVEFORM_TEXT);
+        match browser.remote_addr() {
+            Some(addr) => ui.text(format!("Listening on http://{addr}/api")),
+            None => ui.text_disabled("Not running"),
+        }
+    }
 
-    draw.add_rect([cx, cy], [cx + w, cy + H], bg)
-        .filled(true)
-        .build();
+    /// Quick audition on/off, and how many seconds to decode before playing.
+    fn draw_quick_audition_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let mut enabled = self.prefs.quick_audition_secs.is_some();
+        let mut changed = ui.checkbox("Quick audition of long files", &mut enabled);
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Start playing after a short decode and load the rest while it plays.");
+        }
+        if changed {
+            self.prefs.quick_audition_secs = enabled.then_some(DEFAULT_QUICK_AUDITION_SECS);
+        }
+        if let Some(secs) = self.prefs.quick_audition_secs.as_mut() {
+            ui.same_line();
+            ui.set_next_item_width(120.0);
+            changed |= ui
+                .slider_config("seconds##quick", 1.0, 30.0)
+                .display_format("%.0f s")
+                .build(secs);
+        }
+        if changed {
+            browser.set_quick_audition(self.prefs.quick_audition_secs);
+            punks_core::config::save(&self.prefs);
+        }
+    }
 
-    if let Some(peaks) = browser.waveform_peaks() {
-        let bar_w = (w / peaks.num_buckets as f32).max(1.0);
-        let mid_y = cy + H / 2.0;
-        let half_h = H / 2.0;
+    fn draw_fade_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let mut frames = self.prefs.fade_frames as u32;
+        ui.set_next_item_width(120.0);
+        if ui
+            .slider_config("Fade##declick", 0, punks_playback::MAX_FADE_FRAMES as u32)
+            .display_format("%d samples")
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .build(&mut frames)
+        {
+            self.prefs.fade_frames = frames as usize;
+            browser.set_fade_frames(self.prefs.fade_frames);
+            punks_core::config::save(&self.prefs);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Fade previews in and out when they start, stop or are retriggered, so they don't click. 0 cuts hard.",
+            );
+        }
+    }
 
-        for (i, &(lo, hi)) in peaks.peaks.iter().enumerate() {
-            let x = cx + i as f32 * bar_w;
-            let y_top = mid_y - hi * half_h;
-            let y_bot = (mid_y - lo * half_h).max(y_top + 1.0);
-            draw.add_rect([x, y_top], [x + bar_w - 0.5, y_bot], bar_color)
-                .filled(true)
-                .build();
+    /// Which device previews play through. Switching keeps what's loaded.
+    fn draw_output_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let current = browser.output_device().unwrap_or("(default)").to_string();
+        ui.set_next_item_width(220.0);
+        if let Some(_combo) = ui.begin_combo("Output##device", &current) {
+            if self.output_devices.is_empty() {
+                self.rescan_output_devices(browser);
+            }
+            let mut chosen = None;
+            if ui.selectable("(default)") {
+                chosen = Some(None);
+            }
+            for device in &self.output_devices {
+                if ui.selectable(device) {
+                    chosen = Some(Some(device.clone()));
+                }
+            }
+            if let Some(device) = chosen {
+                match browser.switch_output_device(device.as_deref()) {
+                    Ok(()) => {
+                        self.prefs.output_device = device;
+                        punks_core::config::save(&self.prefs);
+                    }
+                    Err(e) => tracing::error!("{e}"),
+                }
+            }
+        }
+        ui.same_line();
+        if ui.button("Rescan##device") {
+            self.rescan_output_devices(browser);
         }
     }
 
-    match browser.playback_status() {
-        PlaybackStatus::Playing {
-            file,
-            position,
-            duration,
-        } => {
-            let dur_secs = duration.as_secs_f32();
-            if dur_secs > 0.0 {
-                let t = position.as_secs_f32() / dur_secs;
-                let px = cx + t * w;
-                draw.add_line([px, cy], [px, cy + H], playhead_color)
-                    .build();
+    fn rescan_output_devices(&mut self, browser: &SampleBrowser) {
+        self.output_devices = browser.output_devices().unwrap_or_else(|e| {
+            tracing::warn!("{e}");
+            Vec::new()
+        });
+    }
+
+    /// Cap on how much memory one loaded sample's audio may take.
+    fn draw_memory_budget_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let mut enabled = self.prefs.memory_budget_mb.is_some();
+        let mut changed = ui.checkbox("Limit memory per sample", &mut enabled);
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Samples whose decoded audio would need more are refused.");
+        }
+        if changed {
+            self.prefs.memory_budget_mb = enabled.then_some(DEFAULT_MEMORY_BUDGET_MB);
+        }
+        if let Some(mb) = self.prefs.memory_budget_mb.as_mut() {
+            ui.same_line();
+            ui.set_next_item_width(120.0);
+            changed |= ui
+                .slider_config("MB##memory", 64, 8192)
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .build(mb);
+        }
+        if changed {
+            browser.set_memory_budget(self.prefs.memory_budget_mb);
+            punks_core::config::save(&self.prefs);
+        }
+    }
+
+    /// File types listed on top of the built-in formats, each optionally
+    /// playable. Changes apply to the open folder straight away.
+    fn draw_extension_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.text("Extra file types");
+        ui.spacing();
+
+        let mut changed = false;
+        let mut remove = None;
+        for (i, custom) in self.prefs.extensions.iter_mut().enumerate() {
+            ui.text(format!(".{}", custom.extension));
+            ui.same_line_with_pos(180.0);
+            changed |= ui.checkbox(format!("Playable##ext{i}"), &mut custom.playable);
+            ui.same_line();
+            if ui.small_button(format!("\u{00d7}##extremove{i}")) {
+                remove = Some(i);
             }
-            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            let pos_s = position.as_secs();
-            let dur_s = duration.as_secs();
-            draw.add_text(
-                [cx + 4.0, cy + 2.0],
-                text_color,
-                format!(
-                    "{}  {}:{:02} / {}:{:02}",
-                    name,
-                    pos_s / 60,
-                    pos_s % 60,
-                    dur_s / 60,
-                    dur_s % 60,
-                ),
-            );
         }
-        PlaybackStatus::Loading { file } => {
-            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            draw.add_text(
-                [cx + 4.0, cy + H / 2.0 - 7.0],
-                text_color,
-                format!("Loading: {name}..."),
-            );
+        if let Some(i) = remove {
+            self.prefs.extensions.remove(i);
+            changed = true;
         }
-        PlaybackStatus::Idle => {
-            if browser.waveform_peaks().is_none() {
-                draw.add_text(
-                    [cx + 4.0, cy + H / 2.0 - 7.0],
-                    color_u32([0.5, 0.5, 0.5, 0.7]),
-                    "Idle",
-                );
+
+        ui.set_next_item_width(120.0);
+        let entered = ui
+            .input_text("##newext", &mut self.extension_buf)
+            .hint("e.g. rex")
+            .enter_returns_true(true)
+            .build();
+        ui.same_line();
+        if ui.button("Add##ext") || entered {
+            let extension = self
+                .extension_buf
+                .trim()
+                .trim_start_matches('.')
+                .to_ascii_lowercase();
+            if !extension.is_empty() {
+                self.prefs.extensions.retain(|c| c.extension != extension);
+                self.prefs.extensions.push(CustomExtension {
+                    extension,
+                    playable: false,
+                });
+                changed = true;
             }
+            self.extension_buf.clear();
+        }
+
+        if changed {
+            browser.set_extensions(ExtensionRegistry::with_custom(&self.prefs.extensions));
+            punks_core::config::save(&self.prefs);
         }
     }
 
-    // Hover / scrub crosshair + time label at the mouse position.
-    if scrubbable && (hovered || active) {
-        let mx = mouse_x.clamp(cx, cx + w);
-        let hover = color_u32(WAVEFORM_HOVER);
-        draw.add_line([mx, cy], [mx, cy + H], hover).build();
-        let mid = cy + H / 2.0;
-        draw.add_line([mx - 4.0, mid], [mx + 4.0, mid], hover)
-            .build();
-        if let Some(dur) = browser.loaded_duration() {
-            let frac = ((mx - cx) / w).clamp(0.0, 1.0);
-            let t = (dur.as_secs_f64() * frac as f64) as u64;
-            let label = format!("{}:{:02}", t / 60, t % 60);
-            let lx = (mx + 4.0).clamp(cx + 2.0, cx + w - 36.0);
-            draw.add_text([lx, cy + 2.0], color_u32(WAVEFORM_TEXT), label);
+    /// Folders whose new arrivals are analyzed and listed under Recently
+    /// added, e.g. the Splice download folder.
+    fn draw_watch_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.text("Watch folders");
+        ui.spacing();
+
+        let mut changed = false;
+        let mut remove = None;
+        for (i, folder) in self.prefs.watch_folders.iter().enumerate() {
+            ui.text(folder.path.display().to_string());
+            ui.same_line();
+            if ui.small_button(format!("\u{00d7}##watchremove{i}")) {
+                remove = Some(i);
+            }
         }
-        ui.set_mouse_cursor(Some(imgui::MouseCursor::ResizeEW));
+        if let Some(i) = remove {
+            self.prefs.watch_folders.remove(i);
+            changed = true;
+        }
+
+        if ui.button("Add...##watch") {
+            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                self.prefs.watch_folders.retain(|f| f.path != path);
+                self.prefs.watch_folders.push(WatchFolder::new(path));
+                changed = true;
+            }
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Samples that land in it from now on are analyzed, suggested a tag \
+                 and listed under Recently added.",
+            );
+        }
+
+        if changed {
+            browser.set_watch_folders(self.prefs.watch_folders.clone());
+            punks_core::config::save(&self.prefs);
+        }
+    }
+
+    /// Folders kept in the library index, so searching them is instant.
+    fn draw_library_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.text("Library folders");
+        ui.spacing();
+
+        let mut changed = false;
+        let mut remove = None;
+        for (i, folder) in self.prefs.library_folders.iter().enumerate() {
+            ui.text(folder.display().to_string());
+            ui.same_line();
+            if ui.small_button(format!("\u{00d7}##libraryremove{i}")) {
+                remove = Some(i);
+            }
+        }
+        if let Some(i) = remove {
+            self.prefs.library_folders.remove(i);
+            changed = true;
+        }
+
+        if ui.button("Add...##library") {
+            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                self.prefs.library_folders.retain(|f| *f != path);
+                self.prefs.library_folders.push(path);
+                changed = true;
+            }
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Indexed in the background and kept between sessions; searches \
+                 inside it don't walk the disk.",
+            );
+        }
+        if !self.prefs.library_folders.is_empty() {
+            ui.same_line();
+            if browser.is_indexing() {
+                ui.text_disabled(format!("Indexing... ({} files)", browser.library().len()));
+            } else {
+                if ui.button("Reindex##library") {
+                    browser.reindex_library();
+                }
+                ui.same_line();
+                ui.text_disabled(format!("{} files", browser.library().len()));
+            }
+        }
+
+        if changed {
+            browser.set_library_folders(self.prefs.library_folders.clone());
+            punks_core::config::save(&self.prefs);
+        }
+    }
+
+    fn draw_midi_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.text("MIDI");
+        ui.spacing();
+
+        let current = browser.midi_port().unwrap_or("(none)").to_string();
+        ui.set_next_item_width(220.0);
+        if let Some(_combo) = ui.begin_combo("Input##midi", &current) {
+            if ui.selectable("(none)") {
+                browser.disconnect_midi();
+                self.prefs.midi.input_port = None;
+                punks_core::config::save(&self.prefs);
+            }
+            for port in &self.midi_ports {
+                if ui.selectable(port) {
+                    match browser.connect_midi(port) {
+                        Ok(()) => {
+                            self.prefs.midi.input_port = Some(port.clone());
+                            punks_core::config::save(&self.prefs);
+                        }
+                        Err(e) => tracing::error!("{e}"),
+                    }
+                }
+            }
+        }
+        ui.same_line();
+        if ui.button("Rescan##midi") {
+            self.midi_ports = midi::input_ports();
+        }
+
+        let learning = browser.midi_map().learning();
+        for &action in MidiAction::ALL {
+            let label = action.label();
+            let btn_label = if learning == Some(action) {
+                format!("Send a note or CC...##midi{label}")
+            } else {
+                match browser.midi_map().binding_for(action) {
+                    Some(t) => format!("[ {} ]##midi{label}", midi::trigger_label(&t)),
+                    None => format!("Learn##midi{label}"),
+                }
+            };
+
+            ui.text(label);
+            ui.same_line_with_pos(180.0);
+            if ui.button(&btn_label) {
+                browser.midi_map_mut().learn(action);
+            }
+            if browser.midi_map().binding_for(action).is_some() {
+                ui.same_line();
+                if ui.small_button(format!("\u{00d7}##midiclear{label}")) {
+                    browser.midi_map_mut().clear(action);
+                }
+            }
+        }
+
+        let bindings = browser.midi_map().to_bindings();
+        if bindings != self.prefs.midi.bindings {
+            self.prefs.midi.bindings = bindings;
+            punks_core::config::save(&self.prefs);
+        }
+
+        self.draw_pad_settings(ui, browser);
+    }
+
+    /// Which samples the pads play, and from which note.
+    fn draw_pad_settings(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        ui.spacing();
+        ui.text("Pads play");
+        ui.same_line_with_pos(180.0);
+        ui.set_next_item_width(160.0);
+        let labels = PadSource::ALL.map(PadSource::label);
+        let mut source = PadSource::ALL
+            .iter()
+            .position(|&s| s == browser.pad_source())
+            .unwrap_or(0);
+        if ui.combo_simple_string("##padsource", &mut source, &labels) {
+            browser.set_pad_source(PadSource::ALL[source]);
+            self.prefs.midi.pads = PadSource::ALL[source];
+            punks_core::config::save(&self.prefs);
+        }
+        if browser.pad_source() == PadSource::Off {
+            return;
+        }
+        ui.same_line();
+        ui.set_next_item_width(90.0);
+        let mut first = i32::from(browser.first_pad_note());
+        let last = 127 - midi::PAD_COUNT as i32 + 1;
+        if imgui::Drag::new("first note##pads")
+            .range(0, last)
+            .build(ui, &mut first)
+        {
+            browser.set_first_pad_note(first.clamp(0, last) as u8);
+        }
+        if ui.is_item_deactivated_after_edit() {
+            self.prefs.midi.first_pad_note = browser.first_pad_note();
+            punks_core::config::save(&self.prefs);
+        }
+        ui.same_line();
+        if ui.button("Refresh##pads") {
+            browser.refresh_pads();
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Take the samples again; they stay put while you play them.");
+        }
+
+        let first = browser.first_pad_note();
+        if browser.pads().is_empty() {
+            ui.text_disabled("No samples to put on the pads yet.");
+        }
+        for (i, path) in browser.pads().iter().enumerate() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            ui.text_disabled(format!("{:>3}", first as usize + i));
+            ui.same_line();
+            ui.text(name);
+        }
+    }
+
+    /// Write one sample out as a WAV at a chosen rate and bit depth.
+    fn draw_export_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Export as WAV##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        let form = &mut self.export_form;
+        let Some(entry) = form.entry.clone() else {
+            ui.close_current_popup();
+            return;
+        };
+        ui.text(&entry.name);
+
+        let rates: Vec<String> = std::iter::once("Source rate".to_string())
+            .chain(export::SAMPLE_RATES.iter().map(|r| format!("{r} Hz")))
+            .collect();
+        ui.set_next_item_width(160.0);
+        ui.combo_simple_string("Sample rate", &mut form.rate, &rates);
+        let depths = BitDepth::ALL.map(BitDepth::label);
+        ui.set_next_item_width(160.0);
+        ui.combo_simple_string("Bit depth", &mut form.depth, &depths);
+        if BitDepth::ALL[form.depth] != BitDepth::Float32 {
+            ui.checkbox("Dither", &mut form.dither);
+        }
+        draw_normalize(ui, &mut form.normalize, &mut form.peak_dbfs, &mut form.lufs);
+        ui.checkbox("Bake in preview trim and speed", &mut form.bake);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "The sample's trim, the speed or pitch, a loop's stretch and the \
+                 loudness target, so the file sounds as you auditioned it",
+            );
+        }
+
+        ui.separator();
+        if ui.button("Export...##export") {
+            let dialog = rfd::FileDialog::new()
+                .set_file_name(punks_browser::export_name(&entry))
+                .add_filter("WAV", &["wav"]);
+            let dialog = match entry.path.parent() {
+                Some(dir) => dialog.set_directory(dir),
+                None => dialog,
+            };
+            if let Some(dest) = dialog.save_file() {
+                let spec = form.spec_for(browser, &entry.path, entry.region);
+                form.result = Some(
+                    browser
+                        .export_entry(&entry, &dest, &spec)
+                        .map_err(|e| e.to_string()),
+                );
+            }
+        }
+        ui.same_line();
+        if ui.button("Close##export") {
+            form.entry = None;
+            ui.close_current_popup();
+        }
+
+        match &form.result {
+            Some(Ok(done)) => {
+                let name = done
+                    .output
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                ui.text(format!("Wrote {name} at {} Hz", done.sample_rate));
+                if done.clipped > 0 {
+                    ui.text_colored(
+                        [1.0, 0.7, 0.3, 1.0],
+                        format!("{} samples clipped", done.clipped),
+                    );
+                }
+            }
+            Some(Err(e)) => ui.text_colored([1.0, 0.3, 0.3, 1.0], e),
+            None => {}
+        }
+    }
+
+    /// Trim / normalize every audio file in the current view (search results
+    /// when searching, otherwise the open folder).
+    fn draw_batch_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Batch process##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        let form = &mut self.batch_form;
+        ui.checkbox("Trim leading/trailing silence", &mut form.trim);
+        if form.trim {
+            ui.set_next_item_width(160.0);
+            ui.slider_config("Silence threshold", -90.0_f32, -20.0_f32)
+                .display_format("%.0f dBFS")
+                .build(&mut form.threshold_db);
+        }
+
+        draw_normalize(ui, &mut form.normalize, &mut form.peak_dbfs, &mut form.lufs);
+
+        ui.separator();
+        ui.text("Output");
+        if ui.radio_button_bool("Overwrite in place", form.output_dir.is_none()) {
+            form.output_dir = None;
+        }
+        let folder_label = match &form.output_dir {
+            Some(dir) => format!("Folder: {}", dir.display()),
+            None => "Folder...".to_string(),
+        };
+        if ui.radio_button_bool(&folder_label, form.output_dir.is_some()) {
+            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                form.output_dir = Some(dir);
+            }
+        }
+        if form.output_dir.is_none() {
+            ui.text_disabled("WAV files are replaced; other formats get a .wav copy.");
+        }
+
+        ui.separator();
+        let sources = files_in_view(browser);
+
+        let running = browser.is_batch_running();
+        if running {
+            ui.text_disabled("Processing...");
+        } else if ui.button(format!("Run on {} files", sources.len())) && !sources.is_empty() {
+            browser.start_batch(sources, form.options());
+        }
+        ui.same_line();
+        if ui.button("Close##batch") {
+            if !running {
+                browser.clear_batch();
+            }
+            ui.close_current_popup();
+        }
+
+        if let Some((done, total)) = browser.batch_progress() {
+            imgui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                .overlay_text(format!("{done} / {total}"))
+                .size([360.0, 0.0])
+                .build(ui);
+
+            ui.child_window("batch_results")
+                .size([360.0, 160.0])
+                .build(|| {
+                    for r in browser.batch_results() {
+                        let name = r
+                            .source
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+                        match &r.result {
+                            Ok(done) => ui.text(format!(
+                                "{name}: -{:.2}s, {:+.1} dB",
+                                done.trimmed.as_secs_f32(),
+                                done.gain_db
+                            )),
+                            Err(e) => ui.text_colored([1.0, 0.3, 0.3, 1.0], format!("{name}: {e}")),
+                        }
+                    }
+                });
+        }
+    }
+
+    /// Guess what kind of sound each audio file in the current view is, and
+    /// confirm or reject the suggested tags in bulk.
+    fn draw_auto_tag_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Auto-tag##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        let sources = files_in_view(browser);
+        if browser.is_auto_tag_running() {
+            ui.text_disabled("Listening...");
+        } else if ui.button(format!("Analyze {} files", sources.len())) && !sources.is_empty() {
+            browser.start_auto_tag(sources);
+        }
+        ui.same_line();
+        if ui.button("Close##autotag") {
+            ui.close_current_popup();
+        }
+        ui.text_disabled("Files already tagged or with suggestions waiting are skipped.");
+        if let Some((done, total)) = browser.auto_tag_progress() {
+            imgui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                .overlay_text(format!("{done} / {total}"))
+                .size([560.0, 0.0])
+                .build(ui);
+        }
+
+        ui.separator();
+        let form = &mut self.auto_tag_form;
+        let mut filters = vec!["All"];
+        filters.extend(Category::ALL.map(Category::tag));
+        ui.set_next_item_width(160.0);
+        ui.combo_simple_string("Suggested", &mut form.category, &filters);
+        let wanted = form.category.checked_sub(1).map(|i| Category::ALL[i].tag());
+        let suggestions: Vec<(&Path, &[String])> = browser
+            .tag_suggestions()
+            .into_iter()
+            .filter(|(_, tags)| wanted.is_none_or(|w| tags.iter().any(|t| t == w)))
+            .collect();
+        if suggestions.is_empty() {
+            ui.text_disabled("No suggestions waiting.");
+            return;
+        }
+
+        let mut play: Option<PathBuf> = None;
+        ui.child_window("auto_tag_list")
+            .size([560.0, 320.0])
+            .build(|| {
+                let clip = imgui::ListClipper::new(suggestions.len() as i32).begin(ui);
+                for row in clip.iter() {
+                    let (path, tags) = suggestions[row as usize];
+                    let mut ticked = form.picked.contains(path);
+                    if ui.checkbox(format!("##pick{row}"), &mut ticked) {
+                        if ticked {
+                            form.picked.insert(path.to_path_buf());
+                        } else {
+                            form.picked.remove(path);
+                        }
+                    }
+                    ui.same_line();
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    if ui
+                        .selectable_config(format!("{name}##suggested{row}"))
+                        .size([380.0, 0.0])
+                        .build()
+                    {
+                        play = Some(path.to_path_buf());
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(path.display().to_string());
+                    }
+                    ui.same_line_with_pos(470.0);
+                    ui.text(tags.join(", "));
+                }
+            });
+        let shown: Vec<PathBuf> = suggestions.iter().map(|(p, _)| p.to_path_buf()).collect();
+
+        if ui.button("Select all") {
+            form.picked.extend(shown.iter().cloned());
+        }
+        ui.same_line();
+        if ui.button("Select none") {
+            form.picked.clear();
+        }
+        let picked: Vec<PathBuf> = shown
+            .into_iter()
+            .filter(|p| form.picked.contains(p))
+            .collect();
+        ui.same_line();
+        if ui.button(format!("Confirm {}", picked.len())) && !picked.is_empty() {
+            browser.confirm_tag_suggestions(&picked);
+            form.picked.clear();
+        }
+        ui.same_line();
+        if ui.button(format!("Reject {}", picked.len())) && !picked.is_empty() {
+            browser.reject_tag_suggestions(&picked);
+            form.picked.clear();
+        }
+        if let Some(path) = play {
+            browser.play_file(&path);
+        }
+    }
+
+    fn draw_integrity_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Verify library##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        ui.text_wrapped(
+            "Checks every sample under a folder against its checksum manifest to catch \
+             corrupted files. The first run records the manifest.",
+        );
+        ui.separator();
+
+        let root = browser.current_directory().map(Path::to_path_buf);
+        let running = browser.is_integrity_running();
+        match &root {
+            Some(dir) => ui.text(format!("Folder: {}", dir.display())),
+            None => ui.text_disabled("Open a folder first."),
+        }
+        if let (Some(dir), false) = (&root, running) {
+            if ui.button("Verify##integrity") {
+                browser.start_integrity_check(dir, false);
+            }
+            ui.same_line();
+            if ui.button("Rebuild manifest##integrity") {
+                browser.start_integrity_check(dir, true);
+            }
+            ui.same_line();
+        }
+        if ui.button("Close##integrity") {
+            ui.close_current_popup();
+        }
+
+        if let Some((dir, hashed)) = browser.integrity_progress() {
+            ui.separator();
+            if running {
+                ui.text_disabled(format!("Hashing {}... {hashed} files", dir.display()));
+            }
+        }
+        let red = [1.0, 0.3, 0.3, 1.0];
+        match browser.integrity_outcome() {
+            Some(IntegrityOutcome::ManifestWritten(n)) => {
+                ui.text(format!("Manifest written for {n} files."));
+            }
+            Some(IntegrityOutcome::Failed(e)) => ui.text_colored(red, e),
+            Some(IntegrityOutcome::Verified(report)) => {
+                if report.is_clean() {
+                    ui.text(format!("All {} files OK.", report.ok));
+                } else {
+                    ui.text_colored(
+                        red,
+                        format!(
+                            "{} corrupted, {} missing, {} unreadable ({} OK)",
+                            report.mismatched.len(),
+                            report.missing.len(),
+                            report.unreadable.len(),
+                            report.ok
+                        ),
+                    );
+                }
+                if !report.untracked.is_empty() {
+                    ui.text_disabled(format!(
+                        "{} new files not in the manifest; rebuild to include them.",
+                        report.untracked.len()
+                    ));
+                }
+                ui.child_window("integrity_report")
+                    .size([480.0, 160.0])
+                    .build(|| {
+                        for path in &report.mismatched {
+                            ui.text_colored(red, format!("corrupted: {}", path.display()));
+                        }
+                        for path in &report.missing {
+                            ui.text(format!("missing: {}", path.display()));
+                        }
+                        for (path, e) in &report.unreadable {
+                            ui.text(format!("unreadable: {} ({e})", path.display()));
+                        }
+                    });
+            }
+            None => {}
+        }
+    }
+
+    /// Samples with the same contents, grouped: click a copy to play it,
+    /// double-click to go to it, or trash the copies not wanted.
+    fn draw_duplicates_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Duplicates##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        ui.text_wrapped(if browser.library_folders().is_empty() {
+            "Finds byte-identical samples under the open folder. Add library folders \
+             in Settings to search the whole library."
+        } else {
+            "Finds byte-identical samples across the library folders."
+        });
+        ui.separator();
+
+        let running = browser.is_duplicate_scan_running();
+        if !running && ui.button("Find duplicates##dupes") {
+            browser.start_duplicate_scan();
+        }
+        if !running {
+            ui.same_line();
+        }
+        if ui.button("Close##dupes") {
+            ui.close_current_popup();
+        }
+        if let (true, Some(hashed)) = (running, browser.duplicate_scan_progress()) {
+            ui.text_disabled(format!("Comparing... {hashed} files hashed"));
+        }
+
+        enum Action {
+            Play(PathBuf),
+            Reveal(PathBuf),
+            Trash(PathBuf),
+        }
+        let mut action = None;
+        match browser.duplicates() {
+            Some(Err(e)) => ui.text_colored([1.0, 0.3, 0.3, 1.0], e),
+            Some(Ok([])) => ui.text("No duplicates found."),
+            Some(Ok(groups)) => {
+                let wasted: u64 = groups.iter().map(|g| g.wasted_bytes()).sum();
+                ui.text(format!(
+                    "{} groups, {} in extra copies",
+                    groups.len(),
+                    punks_core::size::format_bytes(wasted)
+                ));
+                ui.child_window("duplicate_groups")
+                    .size([520.0, 320.0])
+                    .build(|| {
+                        for (g, group) in groups.iter().enumerate() {
+                            ui.separator();
+                            ui.text_disabled(format!(
+                                "{} copies of {}",
+                                group.paths.len(),
+                                punks_core::size::format_bytes(group.size)
+                            ));
+                            for (i, path) in group.paths.iter().enumerate() {
+                                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                                if ui
+                                    .selectable_config(format!("{name}##dupe{g}_{i}"))
+                                    .size([420.0, 0.0])
+                                    .build()
+                                {
+                                    action = Some(Action::Play(path.clone()));
+                                }
+                                if ui.is_item_hovered() {
+                                    if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                                        action = Some(Action::Reveal(path.clone()));
+                                    }
+                                    ui.tooltip_text(path.display().to_string());
+                                }
+                                ui.same_line_with_pos(440.0);
+                                if ui.small_button(format!("Trash##dupe_trash{g}_{i}")) {
+                                    action = Some(Action::Trash(path.clone()));
+                                }
+                            }
+                        }
+                    });
+            }
+            None => {}
+        }
+
+        match action {
+            Some(Action::Play(path)) => browser.play_file(&path),
+            Some(Action::Reveal(path)) => {
+                if let Err(e) = browser.reveal(&path) {
+                    tracing::error!("failed to open {}: {e}", path.display());
+                }
+                ui.close_current_popup();
+            }
+            Some(Action::Trash(path)) => {
+                if let Err(e) = browser.trash_duplicate(&path) {
+                    tracing::error!("{e}");
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Samples auditioned, newest first: click one to play it again,
+    /// double-click to go to it.
+    /// The layer stack: each sample's gain, and playing them together.
+    fn draw_layers_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Layers##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        if browser.layers().is_empty() {
+            ui.text_disabled(
+                "Ctrl+click samples (or right-click > Add to layers) to stack them here.",
+            );
+        }
+
+        let mut remove = None;
+        let mut gains = Vec::new();
+        for (i, layer) in browser.layers().iter().enumerate() {
+            let name = layer.path.file_name().unwrap_or_default();
+            ui.text(name.to_string_lossy());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(layer.path.display().to_string());
+            }
+            ui.same_line_with_pos(260.0);
+            ui.set_next_item_width(120.0);
+            let mut db = layer.gain_db;
+            if ui
+                .slider_config(format!("##layergain{i}"), -24.0_f32, 12.0_f32)
+                .display_format("%+.1f dB")
+                .build(&mut db)
+            {
+                gains.push((i, db));
+            }
+            if ui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+                gains.push((i, 0.0));
+            }
+            ui.same_line();
+            if ui.small_button(format!("\u{00d7}##layerremove{i}")) {
+                remove = Some(i);
+            }
+        }
+        for (i, db) in gains {
+            browser.set_layer_gain_db(i, db);
+        }
+        if let Some(i) = remove {
+            browser.remove_layer(i);
+        }
+
+        ui.separator();
+        if !browser.layers().is_empty() {
+            if ui.button("Play together") {
+                browser.play_layers();
+            }
+            ui.same_line();
+            if ui.button("Clear##layers") {
+                browser.clear_layers();
+            }
+            ui.same_line();
+        }
+        if browser.layers_playing() {
+            if ui.button("Stop##layers") {
+                browser.stop();
+            }
+            ui.same_line();
+        }
+        if ui.button("Close##layers") {
+            ui.close_current_popup();
+        }
+    }
+
+    /// What the watch folders turned up, newest first.
+    fn draw_recently_added_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Recently added##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        if ui.button("Close##recent") {
+            ui.close_current_popup();
+        }
+        ui.separator();
+
+        let added = browser.recently_added();
+        if added.is_empty() {
+            ui.text_disabled(if browser.watch_folders().is_empty() {
+                "No watch folders; add one in Settings."
+            } else {
+                "Nothing new in the watch folders yet."
+            });
+            return;
+        }
+
+        let now = SystemTime::now();
+        // (path, reveal): applied after the list, which borrows the index.
+        let mut picked: Option<(PathBuf, bool)> = None;
+        ui.child_window("recent_list")
+            .size([560.0, 320.0])
+            .build(|| {
+                let clip = imgui::ListClipper::new(added.len() as i32).begin(ui);
+                for row in clip.iter() {
+                    let (path, at) = added[row as usize];
+                    let name = path.file_name().unwrap_or_default();
+                    ui.text_disabled(history::when(at, now));
+                    ui.same_line_with_pos(120.0);
+                    if ui
+                        .selectable_config(format!("{}##added{row}", name.to_string_lossy()))
+                        .size([340.0, 0.0])
+                        .build()
+                    {
+                        picked = Some((path.to_path_buf(), false));
+                    }
+                    if ui.is_item_hovered() {
+                        if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                            picked = Some((path.to_path_buf(), true));
+                        }
+                        ui.tooltip_text(path.display().to_string());
+                    }
+                    ui.same_line_with_pos(470.0);
+                    let tags = browser.sample_tags(path);
+                    match browser.sample_index().get(path) {
+                        _ if !tags.is_empty() => ui.text(tags.join(", ")),
+                        Some(r) if !r.suggested_tags.is_empty() => {
+                            ui.text_disabled(format!("{}?", r.suggested_tags.join(", ")))
+                        }
+                        _ => {}
+                    }
+                }
+            });
+
+        match picked {
+            Some((path, true)) => {
+                if let Err(e) = browser.reveal(&path) {
+                    tracing::error!("failed to open {}: {e}", path.display());
+                }
+                ui.close_current_popup();
+            }
+            Some((path, false)) => browser.play_file(&path),
+            None => {}
+        }
+    }
+
+    fn draw_history_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("History##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        ui.set_next_item_width(260.0);
+        ui.input_text("##history_filter", &mut self.history_filter)
+            .hint("Filter by name")
+            .build();
+        ui.same_line();
+        if ui.button("Close##history") {
+            ui.close_current_popup();
+        }
+        ui.separator();
+
+        let filter = self.history_filter.to_lowercase();
+        let matches = |path: &Path| {
+            path.file_name()
+                .is_some_and(|n| n.to_string_lossy().to_lowercase().contains(&filter))
+        };
+        let empty = if filter.is_empty() {
+            "Nothing auditioned yet."
+        } else {
+            "No matches."
+        };
+
+        let now = SystemTime::now();
+        // (path, reveal): applied after the list, which borrows the history.
+        let mut picked: Option<(PathBuf, bool)> = None;
+        let mut pick = |path: &Path, row: usize, label: &str| {
+            if ui
+                .selectable_config(format!("{label}##heard{row}"))
+                .size([340.0, 0.0])
+                .build()
+            {
+                picked = Some((path.to_path_buf(), false));
+            }
+            if ui.is_item_hovered() {
+                if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                    picked = Some((path.to_path_buf(), true));
+                }
+                ui.tooltip_text(path.display().to_string());
+            }
+        };
+        let Some(_tabs) = ui.tab_bar("history_tabs") else {
+            return;
+        };
+
+        // Each sample once, latest first.
+        if let Some(_tab) = ui.tab_item("Recent samples") {
+            let recent: Vec<&Path> = browser
+                .recent()
+                .into_iter()
+                .filter(|path| matches(path))
+                .collect();
+            if recent.is_empty() {
+                ui.text_disabled(empty);
+            }
+            ui.child_window("recent_list")
+                .size([560.0, 320.0])
+                .build(|| {
+                    for (row, path) in recent.iter().enumerate() {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        pick(path, row, &name);
+                        let folder = path.parent().and_then(Path::file_name);
+                        if let Some(folder) = folder {
+                            ui.same_line_with_pos(350.0);
+                            ui.text_disabled(folder.to_string_lossy());
+                        }
+                    }
+                });
+        }
+
+        // Every audition, with when and for how long.
+        if let Some(_tab) = ui.tab_item("Every audition") {
+            let heard: Vec<&Audition> = browser
+                .audition_history()
+                .recent()
+                .filter(|a| matches(&a.path))
+                .collect();
+            if heard.is_empty() {
+                ui.text_disabled(empty);
+            }
+            ui.child_window("history_list")
+                .size([560.0, 320.0])
+                .build(|| {
+                    let clip = imgui::ListClipper::new(heard.len() as i32).begin(ui);
+                    for row in clip.iter() {
+                        let audition = heard[row as usize];
+                        let name = audition.path.file_name().unwrap_or_default();
+                        ui.text_disabled(history::when(audition.started, now));
+                        ui.same_line_with_pos(120.0);
+                        pick(&audition.path, row as usize, &name.to_string_lossy());
+                        ui.same_line_with_pos(470.0);
+                        ui.text_disabled(format_length(audition.listened));
+                    }
+                });
+        }
+
+        match picked {
+            Some((path, true)) => {
+                if let Err(e) = browser.reveal(&path) {
+                    tracing::error!("failed to open {}: {e}", path.display());
+                }
+                ui.close_current_popup();
+            }
+            Some((path, false)) => browser.play_file(&path),
+            None => {}
+        }
+    }
+
+    /// Starred files and folders. Clicking a sample plays it; double-clicking
+    /// goes to it, as does clicking a folder.
+    fn draw_favorites_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Favorites##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        if ui.button("Close##favorites") {
+            ui.close_current_popup();
+        }
+        ui.separator();
+        if browser.favorites().is_empty() {
+            ui.text_disabled("Nothing starred yet. Right-click a sample or folder to add it.");
+            return;
+        }
+
+        // (path, go to it): applied after the list, which borrows the favorites.
+        let mut picked: Option<(PathBuf, bool)> = None;
+        let mut removed: Option<PathBuf> = None;
+        ui.child_window("favorites_list")
+            .size([560.0, 320.0])
+            .build(|| {
+                for (row, path) in browser.favorites().iter().enumerate() {
+                    let playable = path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| browser.extensions().is_playable(&e.to_ascii_lowercase()));
+                    let name = punks_core::paths::display_name(path);
+                    if ui
+                        .selectable_config(format!("{name}##fav{row}"))
+                        .size([500.0, 0.0])
+                        .build()
+                    {
+                        picked = Some((path.clone(), !playable));
+                    }
+                    if ui.is_item_hovered() {
+                        if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                            picked = Some((path.clone(), true));
+                        }
+                        ui.tooltip_text(path.display().to_string());
+                    }
+                    ui.same_line_with_pos(520.0);
+                    if ui.small_button(format!("\u{00d7}##unfav{row}")) {
+                        removed = Some(path.clone());
+                    }
+                }
+            });
+
+        if let Some(path) = removed {
+            browser.remove_favorite(&path);
+        }
+        match picked {
+            Some((path, true)) => {
+                if let Err(e) = browser.open_favorite(&path) {
+                    tracing::error!("failed to open {}: {e}", path.display());
+                }
+                ui.close_current_popup();
+            }
+            Some((path, false)) => browser.play_file(&path),
+            None => {}
+        }
+    }
+
+    /// Named lists of samples from any folders: make, rename and delete
+    /// them on the left, and play, reorder or take out their samples on the
+    /// right. Samples are added from a row's right-click menu.
+    fn draw_collections_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Collections##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        let form = &mut self.collection_form;
+        if form
+            .selected
+            .as_deref()
+            .is_some_and(|name| browser.collection(name).is_none())
+        {
+            form.selected = None;
+        }
+
+        ui.set_next_item_width(200.0);
+        ui.input_text("##collection_name", &mut form.name)
+            .hint("Name")
+            .build();
+        ui.same_line();
+        if ui.button("New##collection") && browser.create_collection(&form.name) {
+            form.selected = Some(form.name.trim().to_string());
+            form.name.clear();
+        }
+        if let Some(selected) = form.selected.clone() {
+            ui.same_line();
+            if ui.button("Rename##collection") && browser.rename_collection(&selected, &form.name) {
+                form.selected = Some(form.name.trim().to_string());
+                form.name.clear();
+            }
+            ui.same_line();
+            if ui.button("Delete##collection") {
+                browser.delete_collection(&selected);
+                form.selected = None;
+            }
+        }
+        ui.same_line();
+        if ui.button("Close##collections") {
+            ui.close_current_popup();
+        }
+        ui.separator();
+
+        ui.child_window("collection_names")
+            .size([180.0, 320.0])
+            .build(|| {
+                for collection in browser.collections() {
+                    let name = collection.name();
+                    let label = format!("{name} ({})##coll_{name}", collection.samples().len());
+                    let is_selected = form.selected.as_deref() == Some(name);
+                    if ui.selectable_config(label).selected(is_selected).build() {
+                        form.selected = Some(name.to_string());
+                    }
+                }
+            });
+        ui.same_line();
+
+        enum Action {
+            Play(usize),
+            Reveal(PathBuf),
+            Move(usize, usize),
+            Remove(usize),
+        }
+        let mut action = None;
+        ui.child_window("collection_samples")
+            .size([400.0, 320.0])
+            .build(|| {
+                let Some(collection) = form.selected.as_deref().and_then(|n| browser.collection(n))
+                else {
+                    ui.text_disabled(if browser.collections().is_empty() {
+                        "No collections yet. Name one above and click New."
+                    } else {
+                        "Pick a collection."
+                    });
+                    return;
+                };
+                let samples = collection.samples();
+                if samples.is_empty() {
+                    ui.text_disabled("Empty. Right-click a sample to add it.");
+                    return;
+                }
+                if ui.button("Play all##collection") {
+                    action = Some(Action::Play(0));
+                }
+                let playing = browser
+                    .collection_playing()
+                    .filter(|(name, _)| *name == collection.name())
+                    .map(|(_, at)| at);
+                for (row, path) in samples.iter().enumerate() {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    if ui
+                        .selectable_config(format!("{name}##coll_sample{row}"))
+                        .selected(playing == Some(row))
+                        .size([300.0, 0.0])
+                        .build()
+                    {
+                        action = Some(Action::Play(row));
+                    }
+                    if ui.is_item_hovered() {
+                        if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                            action = Some(Action::Reveal(path.clone()));
+                        }
+                        ui.tooltip_text(path.display().to_string());
+                    }
+                    ui.same_line_with_pos(320.0);
+                    if ui.small_button(format!("^##coll_up{row}")) && row > 0 {
+                        action = Some(Action::Move(row, row - 1));
+                    }
+                    ui.same_line();
+                    if ui.small_button(format!("v##coll_down{row}")) {
+                        action = Some(Action::Move(row, row + 1));
+                    }
+                    ui.same_line();
+                    if ui.small_button(format!("\u{00d7}##coll_remove{row}")) {
+                        action = Some(Action::Remove(row));
+                    }
+                }
+            });
+
+        let (Some(name), Some(action)) = (form.selected.clone(), action) else {
+            return;
+        };
+        match action {
+            Action::Play(index) => browser.play_collection(&name, index),
+            Action::Reveal(path) => {
+                if let Err(e) = browser.reveal(&path) {
+                    tracing::error!("failed to open {}: {e}", path.display());
+                }
+                ui.close_current_popup();
+            }
+            Action::Move(from, to) => {
+                browser.reorder_collection(&name, from, to);
+            }
+            Action::Remove(index) => {
+                browser.remove_from_collection(&name, index);
+            }
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    fn draw_script_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Run script##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        ui.text_wrapped(
+            "Runs a rhai script with the library, analysis and file functions. \
+             `folder` is the folder being browsed and `selected` the selected entry.",
+        );
+        ui.separator();
+
+        let running = browser.is_script_running();
+        if !running && ui.button("Choose script...##script") {
+            if let Some(file) = rfd::FileDialog::new()
+                .add_filter("rhai script", &["rhai"])
+                .pick_file()
+            {
+                browser.start_script(&file);
+            }
+        }
+        if running && ui.button("Cancel##script") {
+            browser.cancel_script();
+        }
+        ui.same_line();
+        if ui.button("Close##script") {
+            ui.close_current_popup();
+        }
+
+        if let Some((file, output)) = browser.script_output() {
+            ui.separator();
+            if running {
+                ui.text_disabled(format!("Running {}...", file.display()));
+            } else {
+                ui.text(file.display().to_string());
+            }
+            ui.child_window("script_output")
+                .size([480.0, 200.0])
+                .build(|| {
+                    for line in output {
+                        ui.text_wrapped(line);
+                    }
+                    // Follow new output unless the user has scrolled up.
+                    if running && ui.scroll_y() >= ui.scroll_max_y() {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                    }
+                });
+        }
+        match browser.script_outcome() {
+            Some(Ok(())) => ui.text("Done."),
+            Some(Err(e)) => ui.text_colored([1.0, 0.3, 0.3, 1.0], e),
+            None => {}
+        }
+    }
+
+    #[cfg(feature = "freesound")]
+    fn draw_freesound_modal(&mut self, ui: &imgui::Ui, browser: &mut SampleBrowser) {
+        let modal = ui
+            .modal_popup_config("Freesound##modal")
+            .save_settings(false)
+            .always_auto_resize(true);
+
+        let Some(_token) = modal.begin_popup() else {
+            return;
+        };
+
+        let cfg = &mut self.prefs.freesound;
+        let mut changed = false;
+        let mut key = cfg.api_key.clone().unwrap_or_default();
+        ui.set_next_item_width(260.0);
+        if ui.input_text("API key", &mut key).password(true).build() {
+            cfg.api_key = Some(key).filter(|k| !k.is_empty());
+            changed = true;
+        }
+        let mut token = cfg.oauth_token.clone().unwrap_or_default();
+        ui.set_next_item_width(260.0);
+        if ui
+            .input_text("OAuth2 token (optional)", &mut token)
+            .password(true)
+            .build()
+        {
+            cfg.oauth_token = Some(token).filter(|t| !t.is_empty());
+            changed = true;
+        }
+        if cfg.oauth_token.is_none() {
+            ui.text_disabled("Without an OAuth2 token, downloads save the HQ MP3 preview.");
+        }
+
+        let dir_label = match &cfg.download_dir {
+            Some(dir) => format!("Download to: {}", dir.display()),
+            None => "Download to: (current folder)".to_string(),
+        };
+        ui.text(&dir_label);
+        ui.same_line();
+        if ui.button("Browse##freesound") {
+            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                cfg.download_dir = Some(dir);
+                changed = true;
+            }
+        }
+        if changed {
+            punks_core::config::save(&self.prefs);
+        }
+
+        ui.separator();
+        let api_key = self.prefs.freesound.api_key.clone();
+        ui.set_next_item_width(260.0);
+        let submitted = ui
+            .input_text("##freesound_query", &mut self.freesound_query)
+            .hint("Search freesound.org")
+            .enter_returns_true(true)
+            .build();
+        ui.same_line();
+        let clicked = ui.button("Search##freesound");
+        match &api_key {
+            Some(key) if (submitted || clicked) && !self.freesound_query.trim().is_empty() => {
+                browser
+                    .freesound_mut()
+                    .search(key, self.freesound_query.trim());
+            }
+            None => ui.text_disabled("Enter an API key to search."),
+            _ => {}
+        }
+
+        let client = browser.freesound();
+        if client.is_searching() {
+            ui.text_disabled("Searching...");
+        } else if let Some(status) = client.status() {
+            ui.text_disabled(status);
+        }
+
+        let download_dir = self
+            .prefs
+            .freesound
+            .download_dir
+            .clone()
+            .or_else(|| browser.current_directory().map(Path::to_path_buf));
+        let oauth_token = self.prefs.freesound.oauth_token.clone();
+        let mut preview = None;
+        let mut download = None;
+        ui.child_window("freesound_results")
+            .size([520.0, 280.0])
+            .build(|| {
+                for (i, sound) in browser.freesound().results().iter().enumerate() {
+                    let _id = ui.push_id_usize(i);
+                    if ui.small_button("Play") {
+                        preview = Some(i);
+                    }
+                    ui.same_line();
+                    if ui.small_button("Download") {
+                        download = Some(i);
+                    }
+                    ui.same_line();
+                    ui.text(format!(
+                        "{} ({:.1}s) by {}",
+                        sound.name, sound.duration, sound.username
+                    ));
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(&sound.license);
+                    }
+                }
+            });
+        if let Some(i) = preview {
+            browser.freesound_mut().preview(i);
+        }
+        if let (Some(i), Some(dir)) = (download, &download_dir) {
+            browser
+                .freesound_mut()
+                .download(i, oauth_token.as_deref(), dir);
+        }
+
+        let in_flight = browser.freesound().downloads_in_flight();
+        if in_flight > 0 {
+            ui.text_disabled(format!("Downloading {in_flight}..."));
+        }
+        if ui.button("Close##freesound") {
+            ui.close_current_popup();
+        }
+    }
+}
+
+impl Default for BrowserPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const WAVEFORM_BG: [f32; 4] = [0.12, 0.12, 0.14, 1.0];
+const WAVEFORM_BAR: [f32; 4] = [0.30, 0.75, 0.45, 1.0];
+const WAVEFORM_PLAYHEAD: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const WAVEFORM_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 0.85];
+// Subtle hover/scrub crosshair — dimmer than the opaque playhead.
+const WAVEFORM_HOVER: [f32; 4] = [1.0, 1.0, 1.0, 0.35];
+const WAVEFORM_SELECTION: [f32; 4] = [0.45, 0.65, 1.0, 0.25];
+const WAVEFORM_LOOP: [f32; 4] = [1.0, 0.75, 0.3, 0.25];
+/// How much one notch of the mouse wheel zooms the waveform.
+/// Number keys that rate the selected file: 0 clears, 1–5 give that many
+/// stars.
+const RATING_KEYS: [Key; 6] = [
+    Key::Alpha0,
+    Key::Alpha1,
+    Key::Alpha2,
+    Key::Alpha3,
+    Key::Alpha4,
+    Key::Alpha5,
+];
+
+/// The rating a number key pressed this frame asks for; `Some(None)` clears.
+fn rating_key(ui: &imgui::Ui) -> Option<Option<u8>> {
+    let stars = RATING_KEYS
+        .iter()
+        .position(|&key| ui.is_key_pressed_no_repeat(key))?;
+    Some((stars > 0).then_some(stars as u8))
+}
+
+const WAVEFORM_ZOOM_STEP: f64 = 0.8;
+const PROGRESS_FILL: [f32; 4] = [0.18, 0.43, 0.27, 1.0];
+
+/// Start a session recording in the configured folder, or finish the one
+/// running.
+fn toggle_recording(browser: &mut SampleBrowser, prefs: &PunksConfig) {
+    if browser.recording().is_some() {
+        match browser.stop_recording() {
+            Some(Ok(file)) => tracing::info!("recorded {}", file.display()),
+            Some(Err(e)) => tracing::error!("recording failed: {e}"),
+            None => {}
+        }
+        return;
+    }
+    let Some(dir) = prefs.recording_folder() else {
+        tracing::error!("no folder to record into; set recordings_dir in the config");
+        return;
+    };
+    if let Err(e) = browser.start_recording(&dir) {
+        tracing::error!("couldn't start recording: {e}");
+    }
+}
+
+/// Ask where to save `source` as rekordbox XML, and write it there.
+fn export_rekordbox(source: &Path, extensions: &ExtensionRegistry) {
+    let name = dj::set_name(source);
+    let Some(file) = rfd::FileDialog::new()
+        .add_filter("rekordbox xml", &["xml"])
+        .set_file_name(format!("{name}.xml"))
+        .save_file()
+    else {
+        return;
+    };
+    let written = dj::collect_tracks(source, extensions)
+        .map_err(|e| e.to_string())
+        .and_then(|tracks| {
+            std::fs::File::create(&file)
+                .and_then(|f| dj::write_rekordbox_xml(&name, &tracks, std::io::BufWriter::new(f)))
+                .map_err(|e| format!("{}: {e}", file.display()))
+        });
+    if let Err(e) = written {
+        tracing::error!("rekordbox export failed: {e}");
+    }
+}
+
+/// Write `source` as a crate in the `_Serato_` folder for the drive its
+/// samples are on.
+fn export_serato(source: &Path, extensions: &ExtensionRegistry) {
+    let saved = dj::collect_tracks(source, extensions)
+        .map_err(|e| e.to_string())
+        .and_then(|tracks| {
+            dj::save_serato_crate(&dj::set_name(source), &tracks).map_err(|e| e.to_string())
+        });
+    match saved {
+        Ok(file) => tracing::info!("wrote {}", file.display()),
+        Err(e) => tracing::error!("Serato export of {} failed: {e}", source.display()),
+    }
+}
+
+fn color_u32(c: [f32; 4]) -> u32 {
+    let r = (c[0] * 255.0) as u32;
+    let g = (c[1] * 255.0) as u32;
+    let b = (c[2] * 255.0) as u32;
+    let a = (c[3] * 255.0) as u32;
+    (a << 24) | (b << 16) | (g << 8) | r
+}
+
+/// Min/max bars for `peaks` filling the `size` box at `origin`.
+fn draw_peaks(
+    draw: &imgui::DrawListMut<'_>,
+    peaks: &WaveformPeaks,
+    origin: [f32; 2],
+    size: [f32; 2],
+    color: u32,
+) {
+    draw_buckets(draw, &peaks.peaks, origin, size, color);
+}
+
+/// `(min, max)` buckets spread evenly across the `size` box at `origin`.
+fn draw_buckets(
+    draw: &imgui::DrawListMut<'_>,
+    peaks: &[(f32, f32)],
+    [cx, cy]: [f32; 2],
+    [w, h]: [f32; 2],
+    color: u32,
+) {
+    let bar_w = (w / peaks.len().max(1) as f32).max(1.0);
+    let mid_y = cy + h / 2.0;
+    let half_h = h / 2.0;
+
+    for (i, &(lo, hi)) in peaks.iter().enumerate() {
+        let x = cx + i as f32 * bar_w;
+        let y_top = mid_y - hi * half_h;
+        let y_bot = (mid_y - lo * half_h).max(y_top + 1.0);
+        draw.add_rect([x, y_top], [x + bar_w - 0.5, y_bot], color)
+            .filled(true)
+            .build();
+    }
+}
+
+/// A file's overview inside a hover tooltip.
+fn draw_overview_tooltip(ui: &imgui::Ui, peaks: &WaveformPeaks) {
+    const SIZE: [f32; 2] = [256.0, 48.0];
+    let origin = ui.cursor_screen_pos();
+    ui.dummy(SIZE);
+    let draw = ui.get_window_draw_list();
+    draw.add_rect(
+        origin,
+        [origin[0] + SIZE[0], origin[1] + SIZE[1]],
+        color_u32(WAVEFORM_BG),
+    )
+    .filled(true)
+    .build();
+    draw_peaks(&draw, peaks, origin, SIZE, color_u32(WAVEFORM_BAR));
+}
+
+/// Elapsed and total time of what's playing, as a bar across the panel. A
+/// click seeks there and a drag follows the mouse; the bar always spans the
+/// whole clip, however far the waveform is zoomed.
+fn draw_progress_bar(ui: &imgui::Ui, browser: &SampleBrowser, drag: &mut Option<f32>) {
+    let [cx, cy] = ui.cursor_screen_pos();
+    let w = ui.content_region_avail()[0];
+    const H: f32 = 14.0;
+
+    let pressed = ui.invisible_button("##progress", [w, H]) || ui.is_item_active();
+    let hovered = ui.is_item_hovered();
+    let draw = ui.get_window_draw_list();
+    draw.add_rect([cx, cy], [cx + w, cy + H], color_u32(WAVEFORM_BG))
+        .filled(true)
+        .build();
+
+    let (position, duration) = match browser.playback_status() {
+        PlaybackStatus::Playing {
+            position, duration, ..
+        }
+        | PlaybackStatus::Paused {
+            position, duration, ..
+        } if !duration.is_zero() => (position, duration),
+        _ => {
+            *drag = None;
+            return;
+        }
+    };
+    let total = duration.as_secs_f64();
+    let at = |x: f32| ((x - cx) / w).clamp(0.0, 1.0);
+    let mouse_x = ui.io().mouse_pos[0];
+
+    // Re-seek only once the mouse has moved a pixel, so a held-still one
+    // lets playback run on.
+    if pressed {
+        let f = at(mouse_x);
+        if drag.is_none_or(|d| ((f - d) * w).abs() >= 1.0) {
+            browser.seek_fraction(f);
+            *drag = Some(f);
+        }
+    } else {
+        *drag = None;
+    }
+
+    let elapsed = drag.map_or(position.as_secs_f64(), |f| total * f64::from(f));
+    let fill = (elapsed / total).clamp(0.0, 1.0) as f32;
+    draw.add_rect([cx, cy], [cx + w * fill, cy + H], color_u32(PROGRESS_FILL))
+        .filled(true)
+        .build();
+    let label = format!("{} / {}", format_hms(elapsed), format_hms(total));
+    let [tw, th] = ui.calc_text_size(&label);
+    draw.add_text(
+        [cx + (w - tw) / 2.0, cy + (H - th) / 2.0],
+        color_u32(WAVEFORM_TEXT),
+        label,
+    );
+
+    if hovered {
+        ui.set_mouse_cursor(Some(imgui::MouseCursor::Hand));
+        ui.tooltip_text(format_hms(total * f64::from(at(mouse_x))));
+    }
+}
+
+/// Write `start..end` of the loaded clip to `dest`, or next to its file.
+fn save_waveform_slice(
+    browser: &SampleBrowser,
+    start: Duration,
+    end: Duration,
+    dest: Option<PathBuf>,
+    export: &ExportForm,
+) -> Option<Result<PathBuf, String>> {
+    let dest = dest.or_else(|| browser.slice_file(start, end))?;
+    let (path, region) = browser.loaded_clip()?;
+    let spec = export.spec_for(browser, path, region);
+    Some(
+        browser
+            .save_slice(start, end, &dest, &spec)
+            .map(|done| done.output)
+            .map_err(|e| e.to_string()),
+    )
+}
+
+/// The waveform of the loaded clip. The wheel zooms around the mouse;
+/// shift+wheel, a sideways scroll or a middle-button drag pans; a click or
+/// drag scrubs, a shift-drag selects a span and plays it, and an alt-drag
+/// sets an A/B loop that repeats until a click lands outside it. Either can
+/// be saved as a WAV of its own from the right-click menu, in the format
+/// last picked in the export modal.
+fn draw_waveform_widget(
+    ui: &imgui::Ui,
+    browser: &SampleBrowser,
+    state: &mut WaveformState,
+    export: &ExportForm,
+) {
+    let [cx, cy] = ui.cursor_screen_pos();
+    let w = ui.content_region_avail()[0];
+    const H: f32 = 64.0;
+
+    // Interactive hit area (replaces the passive dummy) for hover + scrub.
+    let clicked = ui.invisible_button("##waveform", [w, H]);
+    let hovered = ui.is_item_hovered();
+    let active = ui.is_item_active();
+    let activated = ui.is_item_activated();
+    let right_clicked = ui.is_item_clicked_with_button(imgui::MouseButton::Right);
+    let scrubbable = browser.loaded_duration().is_some();
+    let io = ui.io();
+    let mouse_x = io.mouse_pos[0];
+    let across = |x: f32| f64::from((x - cx) / w);
+
+    let loaded = browser.loaded_clip();
+    if state.clip.as_ref().map(|(p, r)| (p.as_path(), *r)) != loaded {
+        *state = WaveformState {
+            clip: loaded.map(|(p, r)| (p.to_path_buf(), r)),
+            ..WaveformState::default()
+        };
+    }
+    if hovered {
+        let (wheel, sideways) = if io.key_shift {
+            (0.0, io.mouse_wheel + io.mouse_wheel_h)
+        } else {
+            (io.mouse_wheel, io.mouse_wheel_h)
+        };
+        if wheel != 0.0 {
+            let factor = WAVEFORM_ZOOM_STEP.powf(f64::from(wheel));
+            state.view.zoom(factor, across(mouse_x));
+        }
+        // A notch moves a tenth of the view.
+        if sideways != 0.0 {
+            state.view.pan(f64::from(-sideways) / 10.0);
+        }
+        if ui.is_mouse_dragging(imgui::MouseButton::Middle) {
+            state.view.pan(f64::from(-io.mouse_delta[0] / w));
+        }
+    }
+    let view = state.view;
+    let x_at = |fraction: f64| cx + view.x_of(fraction) as f32 * w;
+
+    let draw = ui.get_window_draw_list();
+
+    let bg = color_u32(WAVEFORM_BG);
+    let bar_color = color_u32(WAVEFORM_BAR);
+    let playhead_color = color_u32(WAVEFORM_PLAYHEAD);
+    let text_color = color_u32(WAVEFORM_TEXT);
+
+    draw.add_rect([cx, cy], [cx + w, cy + H], bg)
+        .filled(true)
+        .build();
+
+    match browser.waveform_levels() {
+        Some(levels) if view.is_zoomed() => {
+            let peaks = levels.view(view.start, view.end, w as usize);
+            draw_buckets(&draw, &peaks, [cx, cy], [w, H], bar_color);
+        }
+        _ => {
+            if let Some(peaks) = browser.waveform_peaks() {
+                draw_peaks(&draw, peaks, [cx, cy], [w, H], bar_color);
+            }
+        }
+    }
+
+    let mouse_fraction = view.fraction_at(across(mouse_x));
+    if scrubbable && activated && (io.key_shift || io.key_alt) {
+        state.dragging = Some((mouse_fraction, mouse_fraction));
+        state.loop_drag = io.key_alt;
+    }
+    if let Some(drag) = state.dragging.as_mut().filter(|_| active) {
+        drag.1 = mouse_fraction;
+    }
+    let dragged = state.dragging.map(|(a, b)| (a.min(b), a.max(b)));
+    let selection = dragged.filter(|_| !state.loop_drag).or(state.selection);
+    let ab_loop = match dragged.filter(|_| state.loop_drag) {
+        Some(span) => Some(span),
+        None => browser
+            .loop_region()
+            .zip(browser.loaded_duration())
+            .map(|((a, b), dur)| {
+                let secs = dur.as_secs_f64();
+                (a.as_secs_f64() / secs, b.as_secs_f64() / secs)
+            }),
+    };
+    for (span, color) in [(selection, WAVEFORM_SELECTION), (ab_loop, WAVEFORM_LOOP)] {
+        let Some((from, to)) = span else { continue };
+        let (x0, x1) = (x_at(from).max(cx), x_at(to).min(cx + w));
+        if x1 > x0 {
+            draw.add_rect([x0, cy], [x1, cy + H], color_u32(color))
+                .filled(true)
+                .build();
+        }
+    }
+
+    let status = browser.playback_status();
+    let paused = matches!(status, PlaybackStatus::Paused { .. });
+    match status {
+        PlaybackStatus::Playing {
+            file,
+            position,
+            duration,
+        }
+        | PlaybackStatus::Paused {
+            file,
+            position,
+            duration,
+        } => {
+            let dur_secs = duration.as_secs_f64();
+            if dur_secs > 0.0 {
+                let px = x_at(position.as_secs_f64() / dur_secs);
+                if (cx..=cx + w).contains(&px) {
+                    draw.add_line([px, cy], [px, cy + H], playhead_color)
+                        .build();
+                }
+            }
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+            draw.add_text(
+                [cx + 4.0, cy + 2.0],
+                text_color,
+                format!("{}{}", name, if paused { "  (paused)" } else { "" }),
+            );
+        }
+        PlaybackStatus::Loading { file, duration } => {
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+            let text = match duration {
+                Some(d) => format!("Loading: {name} ({})...", format_length(d)),
+                None => format!("Loading: {name}..."),
+            };
+            draw.add_text([cx + 4.0, cy + H / 2.0 - 7.0], text_color, text);
+        }
+        PlaybackStatus::Reconnecting { error } => {
+            draw.add_text(
+                [cx + 4.0, cy + H / 2.0 - 7.0],
+                color_u32([0.95, 0.6, 0.3, 1.0]),
+                format!("Audio device lost, reconnecting... ({error})"),
+            );
+        }
+        PlaybackStatus::Finished { file } => {
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+            draw.add_text([cx + 4.0, cy + 2.0], text_color, format!("{name}  (ended)"));
+        }
+        PlaybackStatus::Idle => {
+            if browser.waveform_peaks().is_none() {
+                draw.add_text(
+                    [cx + 4.0, cy + H / 2.0 - 7.0],
+                    color_u32([0.5, 0.5, 0.5, 0.7]),
+                    "Idle",
+                );
+            }
+        }
+    }
+
+    // Hover / scrub crosshair + time label at the mouse position.
+    if scrubbable && (hovered || active) {
+        let mx = mouse_x.clamp(cx, cx + w);
+        let hover = color_u32(WAVEFORM_HOVER);
+        draw.add_line([mx, cy], [mx, cy + H], hover).build();
+        let mid = cy + H / 2.0;
+        draw.add_line([mx - 4.0, mid], [mx + 4.0, mid], hover)
+            .build();
+        if let Some(dur) = browser.loaded_duration() {
+            let t = (dur.as_secs_f64() * view.fraction_at(across(mx))) as u64;
+            let label = format!("{}:{:02}", t / 60, t % 60);
+            let lx = (mx + 4.0).clamp(cx + 2.0, cx + w - 36.0);
+            draw.add_text([lx, cy + 2.0], color_u32(WAVEFORM_TEXT), label);
+        }
+        ui.set_mouse_cursor(Some(imgui::MouseCursor::ResizeEW));
+    }
+
+    if let Some(saved) = &state.saved {
+        let (color, text) = match saved {
+            Ok(path) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                (color_u32(WAVEFORM_TEXT), format!("Saved {name}"))
+            }
+            Err(e) => (color_u32([1.0, 0.3, 0.3, 1.0]), e.clone()),
+        };
+        draw.add_text([cx + 4.0, cy + H - 16.0], color, text);
+    }
+
+    // Right-click: save the A/B loop or the selection as a sample of its own.
+    if scrubbable && right_clicked {
+        ui.open_popup("waveform_menu");
+    }
+    if let Some(_popup) = ui.begin_popup("waveform_menu") {
+        let dur = browser.loaded_duration().unwrap_or_default().as_secs_f64();
+        let secs = |f: f64| Duration::from_secs_f64(dur * f);
+        let spans = [
+            ("loop", browser.loop_region()),
+            (
+                "selection",
+                state.selection.map(|(from, to)| (secs(from), secs(to))),
+            ),
+        ];
+        for (what, span) in spans {
+            let Some((start, end)) = span else { continue };
+            if ui.menu_item(format!("Save {what}")) {
+                state.saved = save_waveform_slice(browser, start, end, None, export);
+            }
+            if ui.menu_item(format!("Save {what} as...")) {
+                let mut dialog = rfd::FileDialog::new().add_filter("WAV", &["wav"]);
+                if let Some(file) = browser.slice_file(start, end) {
+                    if let (Some(dir), Some(name)) = (file.parent(), file.file_name()) {
+                        dialog = dialog
+                            .set_directory(dir)
+                            .set_file_name(name.to_string_lossy());
+                    }
+                }
+                if let Some(dest) = dialog.save_file() {
+                    state.saved = save_waveform_slice(browser, start, end, Some(dest), export);
+                }
+            }
+        }
+        if spans.iter().all(|(_, span)| span.is_none()) {
+            ui.text_disabled("Alt-drag a loop or shift-drag a selection to save it");
+        }
+    }
+
+    // Letting go of a shift-drag plays the selection; of an alt-drag,
+    // loops it.
+    if let Some((a, b)) = state.dragging {
+        if !active {
+            state.dragging = None;
+            let (from, to) = (a.min(b), a.max(b));
+            if let Some(dur) = browser.loaded_duration().filter(|_| to > from) {
+                let secs = dur.as_secs_f64();
+                let (start, end) = (
+                    Duration::from_secs_f64(secs * from),
+                    Duration::from_secs_f64(secs * to),
+                );
+                if state.loop_drag {
+                    browser.set_loop_region(start, end);
+                } else {
+                    state.selection = Some((from, to));
+                    browser.play_span(Region {
+                        start,
+                        end: Some(end),
+                    });
+                }
+            }
+        }
+        state.scrub_last_x = None;
+        return;
     }
 
     // Click seeks once; drag follows the cursor. Re-seek only when the cursor
@@ -1005,11 +4136,11 @@ fn draw_waveform_widget(ui: &imgui::Ui, browser: &SampleBrowser, scrub_last_x: &
     // forward instead of re-triggering the same grain every frame.
     if scrubbable && (active || clicked) {
         let mx = mouse_x.clamp(cx, cx + w);
-        if scrub_last_x.is_none_or(|lx| (mx - lx).abs() >= 1.0) {
-            browser.seek_fraction((mx - cx) / w);
-            *scrub_last_x = Some(mx);
+        if state.scrub_last_x.is_none_or(|lx| (mx - lx).abs() >= 1.0) {
+            browser.seek_fraction(view.fraction_at(across(mx)) as f32);
+            state.scrub_last_x = Some(mx);
         }
     } else {
-        *scrub_last_x = None;
+        state.scrub_last_x = None;
     }
 }
@@ -0,0 +1,12 @@
+//! Browser front-end support: list a shared library from its checksum
+//! manifest and preview samples through Web Audio, for a web page in front
+//! of a team's sample server. Build with
+//! `wasm-pack build crates/punks-web --target web`; the page fetches the
+//! library's `.punks-manifest.json`, opens a [`Library`] on it and plays
+//! entries' URLs with a [`WebPlayer`].
+
+mod library;
+mod player;
+
+pub use library::Library;
+pub use player::WebPlayer;
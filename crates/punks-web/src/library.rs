@@ -0,0 +1,111 @@
+//! A library served over HTTP, listed from its manifest.
+
+use std::path::Path;
+
+use js_sys::{Array, Object, Reflect};
+use punks_core::integrity::Manifest;
+use punks_core::vfs::MemoryTree;
+use punks_core::{FileEntry, ListOptions, ScanError};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Library {
+    tree: MemoryTree,
+    base_url: String,
+    options: ListOptions,
+}
+
+#[wasm_bindgen]
+impl Library {
+    /// `manifest_json` is the library's `.punks-manifest.json`; each file is
+    /// served at `base_url` plus its path in the manifest. Paths in listings
+    /// start at `/`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(manifest_json: &str, base_url: &str) -> Result<Library, JsError> {
+        let manifest: Manifest = serde_json::from_str(manifest_json)?;
+        Ok(Library {
+            tree: MemoryTree::from_manifest(Path::new("/"), &manifest),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            options: ListOptions {
+                count_audio: true,
+                ..ListOptions::default()
+            },
+        })
+    }
+
+    /// Files in the library.
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// `dir`'s folders, then its audio files, as objects with `path`, `name`,
+    /// `url`, `size`, `isDirectory` and (for folders) `audioCount`.
+    pub fn list(&self, dir: &str) -> Result<Array, JsValue> {
+        let entries = self
+            .entries(dir)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        let array = Array::new();
+        for entry in &entries {
+            let path = entry.path.to_string_lossy();
+            let object = Object::new();
+            let audio_count = entry
+                .audio_count
+                .map_or(JsValue::NULL, |n| JsValue::from(n as u32));
+            for (key, value) in [
+                ("path", JsValue::from_str(&path)),
+                ("name", JsValue::from_str(&entry.name)),
+                ("url", JsValue::from_str(&self.url(&path))),
+                ("size", JsValue::from(entry.size_bytes as f64)),
+                ("isDirectory", JsValue::from_bool(entry.is_directory)),
+                ("audioCount", audio_count),
+            ] {
+                Reflect::set(&object, &JsValue::from_str(key), &value)?;
+            }
+            array.push(&object);
+        }
+        Ok(array)
+    }
+
+    /// Where the file at `path` (as listed) is served.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, encode_path(path))
+    }
+}
+
+impl Library {
+    fn entries(&self, dir: &str) -> Result<Vec<FileEntry>, ScanError> {
+        Ok(self.tree.list(Path::new(dir), &self.options)?.entries)
+    }
+}
+
+/// Percent-encode everything in `path` but unreserved characters and `/`.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_escape_names() {
+        let manifest = r#"{"files": {"Kicks/808 kick #2.wav": {"size": 4, "blake3": ""}}}"#;
+        let library = Library::new(manifest, "https://samples.example/lib/").unwrap();
+        assert_eq!(library.size(), 1);
+        let entries = library.entries("/Kicks").unwrap();
+        assert_eq!(
+            library.url(&entries[0].path.to_string_lossy()),
+            "https://samples.example/lib/Kicks/808%20kick%20%232.wav"
+        );
+        assert_eq!(encode_path("/Café"), "/Caf%C3%A9");
+    }
+}
@@ -0,0 +1,210 @@
+//! Preview playback through Web Audio: the browser decodes (so every format
+//! it supports plays), and decoded buffers are kept for instant replay like
+//! the native engine's cache.
+
+use std::cell::RefCell;
+use std::num::NonZeroUsize;
+use std::rc::Rc;
+
+use js_sys::{ArrayBuffer, Promise};
+use lru::LruCache;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{
+    AudioBuffer, AudioBufferSourceNode, AudioContext, AudioScheduledSourceNode, GainNode, Response,
+};
+
+/// Decoded samples kept for replay.
+const CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(10).unwrap();
+
+struct State {
+    context: AudioContext,
+    gain: GainNode,
+    source: Option<AudioBufferSourceNode>,
+    cache: LruCache<String, AudioBuffer>,
+    /// Bumped by every `play`, so a fetch that lands after a newer `play`
+    /// is dropped: the latest request wins, as in the native engine.
+    generation: u64,
+    current: Option<String>,
+    buffer: Option<AudioBuffer>,
+    /// Context time at which the buffer's start played (or would have).
+    started_at: f64,
+}
+
+impl State {
+    fn halt(&mut self) {
+        if let Some(source) = self.source.take() {
+            let _ = AudioScheduledSourceNode::stop(&source);
+        }
+    }
+
+    /// Play `buffer` from `offset` seconds.
+    fn start(&mut self, buffer: AudioBuffer, offset: f64) -> Result<(), JsValue> {
+        self.halt();
+        let source = self.context.create_buffer_source()?;
+        source.set_buffer(Some(&buffer));
+        source.connect_with_audio_node(&self.gain)?;
+        source.start_with_when_and_grain_offset(0.0, offset)?;
+        self.started_at = self.context.current_time() - offset;
+        self.source = Some(source);
+        self.buffer = Some(buffer);
+        Ok(())
+    }
+
+    fn position(&self) -> f64 {
+        let duration = self.buffer.as_ref().map_or(0.0, AudioBuffer::duration);
+        (self.context.current_time() - self.started_at).clamp(0.0, duration)
+    }
+}
+
+#[wasm_bindgen]
+pub struct WebPlayer {
+    state: Rc<RefCell<State>>,
+}
+
+#[wasm_bindgen]
+impl WebPlayer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<WebPlayer, JsValue> {
+        let context = AudioContext::new()?;
+        let gain = context.create_gain()?;
+        gain.connect_with_audio_node(&context.destination())?;
+        Ok(WebPlayer {
+            state: Rc::new(RefCell::new(State {
+                context,
+                gain,
+                source: None,
+                cache: LruCache::new(CACHE_CAPACITY),
+                generation: 0,
+                current: None,
+                buffer: None,
+                started_at: 0.0,
+            })),
+        })
+    }
+
+    /// Fetch, decode and play `url`. The promise resolves once it starts (or
+    /// is superseded by a later `play`) and rejects if it can't be loaded.
+    pub fn play(&self, url: String) -> Promise {
+        let state = Rc::clone(&self.state);
+        future_to_promise(async move {
+            let (generation, context, cached) = {
+                let mut s = state.borrow_mut();
+                s.generation += 1;
+                s.halt();
+                s.current = Some(url.clone());
+                s.buffer = None;
+                let cached = s.cache.get(&url).cloned();
+                (s.generation, s.context.clone(), cached)
+            };
+            // Autoplay policies keep a context suspended until the page has
+            // had a user gesture; a click that starts playback counts.
+            JsFuture::from(context.resume()?).await?;
+
+            let loaded = match cached {
+                Some(buffer) => Ok(buffer),
+                None => fetch_and_decode(&context, &url).await,
+            };
+            let mut s = state.borrow_mut();
+            if s.generation != generation {
+                return Ok(JsValue::UNDEFINED);
+            }
+            match loaded {
+                Ok(buffer) => {
+                    s.cache.put(url, buffer.clone());
+                    s.start(buffer, 0.0)?;
+                    Ok(JsValue::UNDEFINED)
+                }
+                Err(e) => {
+                    s.current = None;
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    pub fn stop(&self) {
+        let mut s = self.state.borrow_mut();
+        s.generation += 1;
+        s.halt();
+        s.current = None;
+        s.buffer = None;
+    }
+
+    /// Jump to `fraction` (0 to 1) of the loaded sample and play from there.
+    pub fn seek(&self, fraction: f64) -> Result<(), JsValue> {
+        let mut s = self.state.borrow_mut();
+        let Some(buffer) = s.buffer.clone() else {
+            return Ok(());
+        };
+        let offset = fraction.clamp(0.0, 1.0) * buffer.duration();
+        s.start(buffer, offset)
+    }
+
+    /// `"idle"`, `"loading"`, `"playing"` or `"finished"`.
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> String {
+        let s = self.state.borrow();
+        match (&s.current, &s.buffer) {
+            (None, _) => "idle",
+            (Some(_), None) => "loading",
+            (Some(_), Some(buffer)) if s.position() < buffer.duration() => "playing",
+            (Some(_), Some(_)) => "finished",
+        }
+        .to_string()
+    }
+
+    /// The URL playing or loading.
+    #[wasm_bindgen(getter)]
+    pub fn file(&self) -> Option<String> {
+        self.state.borrow().current.clone()
+    }
+
+    /// Seconds into the sample.
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> f64 {
+        self.state.borrow().position()
+    }
+
+    /// Length of the loaded sample in seconds; 0 while loading.
+    #[wasm_bindgen(getter)]
+    pub fn duration(&self) -> f64 {
+        self.state
+            .borrow()
+            .buffer
+            .as_ref()
+            .map_or(0.0, AudioBuffer::duration)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn volume(&self) -> f32 {
+        self.state.borrow().gain.gain().value()
+    }
+
+    /// Preview volume, 0 to 1.
+    #[wasm_bindgen(setter)]
+    pub fn set_volume(&self, volume: f32) {
+        let volume = if volume.is_finite() { volume } else { 1.0 };
+        self.state
+            .borrow()
+            .gain
+            .gain()
+            .set_value(volume.clamp(0.0, 1.0));
+    }
+}
+
+async fn fetch_and_decode(context: &AudioContext, url: &str) -> Result<AudioBuffer, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsError::new("no window"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(url))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        let message = format!("{url}: HTTP {}", response.status());
+        return Err(JsError::new(&message).into());
+    }
+    let bytes: ArrayBuffer = JsFuture::from(response.array_buffer()?).await?.dyn_into()?;
+    JsFuture::from(context.decode_audio_data(&bytes)?)
+        .await?
+        .dyn_into()
+}
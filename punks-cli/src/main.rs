@@ -0,0 +1,295 @@
+//! Headless library maintenance, for scripts and cron jobs on the machine the
+//! drive is plugged into.
+//!
+//! ```text
+//! punks scan <root>       print every sample under <root> as it's found
+//! punks manifest <root>   record checksums for every sample under <root>
+//! punks verify <root>     re-hash and report corrupted / missing files
+//! punks export <root> [--csv] [--analyze]
+//!                         catalog every sample as JSON (or CSV) on stdout
+//! punks rekordbox <dir>   a folder or playlist as rekordbox XML on stdout
+//! punks serato <dir>      a folder or playlist as a Serato crate
+//! punks script <file>     run a rhai script (see the punks-script crate)
+//! punks export-profile <file>
+//!                         write the shareable settings to a profile file
+//! punks import-profile <file>
+//!                         apply a profile over this machine's settings
+//! ```
+//!
+//! `serato` writes the crate into the `_Serato_` folder of the drive the
+//! samples are on (or of the Music folder) and prints where it went.
+//! `export` lists each file's path, size, format, Broadcast Wave metadata,
+//! and the tags and rating given it in the app; `--analyze` decodes every
+//! file to add levels and any other analysis results, which takes much
+//! longer.
+//!
+//! `scan` streams paths one per line, so it can feed other tools on libraries
+//! of any size; folders it can't read are reported on stderr and skipped.
+//! It reads several folders at once, so the paths come in no set order.
+//! `verify` exits 1 when anything is corrupted, missing or unreadable, and
+//! `script` when the script fails; both exit 2 on usage or I/O errors. Tags
+//! and ratings a script changes are saved to the app's sample index. File
+//! types added in the browser's config count as samples here too.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+
+use punks_core::config::{Profile, PunksConfig};
+use punks_core::dj;
+use punks_core::index::SampleIndex;
+use punks_core::integrity::{self, Manifest};
+use punks_core::{ExtensionRegistry, ScanOptions};
+use punks_playback::analysis::analyzer::AnalyzerRegistry;
+use punks_playback::catalog::{self, ExportFormat};
+
+const USAGE: &str = "usage: punks <scan|manifest|verify> <library root>
+       punks <rekordbox|serato> <folder or playlist>
+       punks export <library root> [--csv] [--analyze]
+       punks script <file.rhai>
+       punks <export-profile|import-profile> <file>";
+
+fn main() -> ExitCode {
+    punks_core::logging::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, path, flags) = match args.as_slice() {
+        [command, path, flags @ ..] if command == "export" || flags.is_empty() => {
+            (command.as_str(), PathBuf::from(path), flags)
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let config = punks_core::config::load();
+    let extensions = ExtensionRegistry::with_custom(&config.extensions);
+    let result = match command {
+        "scan" => scan(&path, extensions),
+        "manifest" => manifest(&path, &extensions),
+        "verify" => verify(&path, &extensions),
+        "export" => export(&path, &extensions, flags),
+        "rekordbox" => rekordbox(&path, &extensions),
+        "serato" => serato(&path, &extensions),
+        "script" => Ok(script(&path, extensions)),
+        "export-profile" => Ok(export_profile(&path, &config)),
+        "import-profile" => Ok(import_profile(&path, config)),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("punks: {e}");
+        ExitCode::from(2)
+    })
+}
+
+fn scan(root: &Path, extensions: ExtensionRegistry) -> Result<ExitCode, punks_core::ScanError> {
+    let options = ScanOptions {
+        extensions,
+        threads: Some(punks_core::scan::default_threads()),
+        ..ScanOptions::default()
+    };
+    let mut out = std::io::stdout().lock();
+    for entry in punks_core::scan_directory_iter(root, options)? {
+        match entry {
+            Ok(entry) => {
+                // A closed pipe (`punks scan | head`) just ends the listing.
+                if writeln!(out, "{}", entry.path.display()).is_err() {
+                    break;
+                }
+            }
+            Err(e) => eprintln!("punks: {e}"),
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The app's tags and ratings; empty without a config directory.
+fn sample_index() -> Result<SampleIndex, ExitCode> {
+    let Some(file) = SampleIndex::default_path() else {
+        return Ok(SampleIndex::default());
+    };
+    SampleIndex::load(&file).map_err(|e| {
+        eprintln!("punks: {}: {e}", file.display());
+        ExitCode::from(2)
+    })
+}
+
+fn script(file: &Path, extensions: ExtensionRegistry) -> ExitCode {
+    let mut index = match sample_index() {
+        Ok(index) => index,
+        Err(code) => return code,
+    };
+    let mut engine = punks_script::engine(extensions, |_| {});
+    let edits = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&edits);
+    punks_script::register_index(&mut engine, index.clone(), move |edit| {
+        recorded.borrow_mut().push(edit.clone());
+    });
+    let outcome = engine.run_file(file.to_path_buf());
+
+    // What the script tagged before failing stays tagged, as in the app.
+    let edits = edits.borrow();
+    if let Some(index_file) = SampleIndex::default_path().filter(|_| !edits.is_empty()) {
+        for edit in edits.iter() {
+            edit.apply(&mut index);
+        }
+        if let Err(e) = index.save(&index_file) {
+            eprintln!("punks: {}: {e}", index_file.display());
+            return ExitCode::from(2);
+        }
+    }
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("punks: {}: {e}", file.display());
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn export(
+    root: &Path,
+    extensions: &ExtensionRegistry,
+    flags: &[String],
+) -> Result<ExitCode, punks_core::ScanError> {
+    let mut format = ExportFormat::Json;
+    let mut analyzers = AnalyzerRegistry::default();
+    for flag in flags {
+        match flag.as_str() {
+            "--csv" => format = ExportFormat::Csv,
+            "--analyze" => analyzers = AnalyzerRegistry::with_builtins(),
+            _ => {
+                eprintln!("{USAGE}");
+                return Ok(ExitCode::from(2));
+            }
+        }
+    }
+    let index = match sample_index() {
+        Ok(index) => index,
+        Err(code) => return Ok(code),
+    };
+    let entries = catalog::build_catalog(root, extensions, &index, &analyzers, |_| {})?;
+    if let Err(e) = catalog::export(&entries, format, std::io::stdout().lock()) {
+        eprintln!("punks: {e}");
+        return Ok(ExitCode::from(2));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The profile is named after the file.
+fn export_profile(file: &Path, config: &PunksConfig) -> ExitCode {
+    let name = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match config.to_profile(&name).save(file) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("punks: {}: {e}", file.display());
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn import_profile(file: &Path, mut config: PunksConfig) -> ExitCode {
+    match Profile::load(file) {
+        Ok(profile) => {
+            config.apply_profile(&profile);
+            punks_core::config::save(&config);
+            println!("applied profile {:?}", profile.name);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("punks: {}: {e}", file.display());
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn rekordbox(
+    source: &Path,
+    extensions: &ExtensionRegistry,
+) -> Result<ExitCode, punks_core::ScanError> {
+    let tracks = dj::collect_tracks(source, extensions)?;
+    let out = std::io::stdout().lock();
+    if let Err(e) = dj::write_rekordbox_xml(&dj::set_name(source), &tracks, out) {
+        eprintln!("punks: {e}");
+        return Ok(ExitCode::from(2));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn serato(
+    source: &Path,
+    extensions: &ExtensionRegistry,
+) -> Result<ExitCode, punks_core::ScanError> {
+    let tracks = dj::collect_tracks(source, extensions)?;
+    match dj::save_serato_crate(&dj::set_name(source), &tracks) {
+        Ok(file) => {
+            println!("wrote {} ({} files)", file.display(), tracks.len());
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            eprintln!("punks: {}: {e}", source.display());
+            Ok(ExitCode::from(2))
+        }
+    }
+}
+
+fn manifest(
+    root: &Path,
+    extensions: &ExtensionRegistry,
+) -> Result<ExitCode, punks_core::ScanError> {
+    let manifest = integrity::build_manifest(root, extensions, |_| {})?;
+    manifest.save(root)?;
+    println!(
+        "wrote {} ({} files)",
+        root.join(integrity::MANIFEST_NAME).display(),
+        manifest.files.len()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn verify(root: &Path, extensions: &ExtensionRegistry) -> Result<ExitCode, punks_core::ScanError> {
+    let Some(manifest) = Manifest::load(root)? else {
+        eprintln!(
+            "punks: no manifest in {}; run `punks manifest` first",
+            root.display()
+        );
+        return Ok(ExitCode::from(2));
+    };
+    let report = integrity::verify(root, &manifest, extensions, |_| {})?;
+
+    for path in &report.mismatched {
+        println!("CORRUPT    {}", path.display());
+    }
+    for path in &report.missing {
+        println!("MISSING    {}", path.display());
+    }
+    for (path, e) in &report.unreadable {
+        println!("UNREADABLE {} ({e})", path.display());
+    }
+    for path in &report.untracked {
+        println!("NEW        {}", path.display());
+    }
+    println!(
+        "{} ok, {} corrupt, {} missing, {} unreadable, {} new",
+        report.ok,
+        report.mismatched.len(),
+        report.missing.len(),
+        report.unreadable.len(),
+        report.untracked.len()
+    );
+
+    Ok(if report.is_clean() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
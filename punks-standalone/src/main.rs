@@ -31,7 +31,7 @@ fn start_file_drag(window: &Window, path: &std::path::Path) {
     let drag_path = match std::fs::canonicalize(path) {
         Ok(path) => path,
         Err(err) => {
-            log::error!("failed to canonicalize drag path {path:?}: {err}");
+            tracing::error!("failed to canonicalize drag path {path:?}: {err}");
             return;
         }
     };
@@ -43,14 +43,23 @@ fn start_file_drag(window: &Window, path: &std::path::Path) {
         item,
         preview,
         |result, _cursor_position| {
-            log::debug!("drag finished: {result:?}");
+            tracing::debug!("drag finished: {result:?}");
         },
         Default::default(),
     ) {
-        log::error!("failed to start drag operation: {err}");
+        tracing::error!("failed to start drag operation: {err}");
     }
 }
 
+/// Smallest saved window size restored; see [`AppWindow::init_gpu`].
+const MIN_WINDOW_SIZE: [f64; 2] = [320.0, 240.0];
+
+/// The window's inner size in logical pixels, as saved between launches.
+fn logical_size(window: &Window) -> [f64; 2] {
+    let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+    [size.width, size.height]
+}
+
 /// Slight dark-theme polish: rounded frames, a touch more breathing room, and
 /// muted greys so the only saturated colour is the active tab / selection.
 fn apply_style(style: &mut imgui::Style) {
@@ -104,11 +113,11 @@ struct AppWindow {
 
 impl AppWindow {
     fn new(event_loop: &ActiveEventLoop) -> Self {
-        let gpu = Self::init_gpu(event_loop);
-        let imgui = Self::init_imgui(&gpu);
         // BrowserPanel loads the config; SampleBrowser reuses that copy
         // instead of reading it from disk again (P3).
         let panel = BrowserPanel::new();
+        let gpu = Self::init_gpu(event_loop, panel.prefs().window_size);
+        let imgui = Self::init_imgui(&gpu);
         let browser = SampleBrowser::new(panel.prefs()).expect("failed to initialize audio engine");
 
         AppWindow {
@@ -119,13 +128,19 @@ impl AppWindow {
         }
     }
 
-    fn init_gpu(event_loop: &ActiveEventLoop) -> GpuState {
+    /// Open the window at `size` (logical pixels), as it was last closed,
+    /// or 800x600.
+    fn init_gpu(event_loop: &ActiveEventLoop, size: Option<[f64; 2]>) -> GpuState {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
             ..Default::default()
         });
 
-        let size = LogicalSize::new(800.0, 600.0);
+        // A window closed while minimized can report next to no size.
+        let [width, height] = size
+            .filter(|&[w, h]| w >= MIN_WINDOW_SIZE[0] && h >= MIN_WINDOW_SIZE[1])
+            .unwrap_or([800.0, 600.0]);
+        let size = LogicalSize::new(width, height);
         let attributes = Window::default_attributes()
             .with_inner_size(size)
             .with_title("punks2");
@@ -237,11 +252,17 @@ impl ApplicationHandler for App {
                     .configure(&app.gpu.device, &app.gpu.surface_config);
             }
 
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                app.panel
+                    .save_session(&app.browser, logical_size(&app.gpu.window));
+                event_loop.exit();
+            }
 
             WindowEvent::KeyboardInput { event, .. } => {
                 if let Key::Named(NamedKey::Escape) = event.logical_key {
                     if event.state.is_pressed() {
+                        app.panel
+                            .save_session(&app.browser, logical_size(&app.gpu.window));
                         event_loop.exit();
                     }
                 }
@@ -255,7 +276,7 @@ impl ApplicationHandler for App {
                 let frame = match app.gpu.surface.get_current_texture() {
                     Ok(f) => f,
                     Err(e) => {
-                        log::error!("dropped frame: {e:?}");
+                        tracing::error!("dropped frame: {e:?}");
                         return;
                     }
                 };
@@ -362,7 +383,7 @@ impl ApplicationHandler for App {
 }
 
 fn main() {
-    env_logger::init();
+    punks_core::logging::init();
 
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);